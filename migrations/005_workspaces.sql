@@ -0,0 +1,79 @@
+-- KTME Workspaces
+-- Version: 005
+-- Description: Add a workspace dimension to services and generation history so
+--              separate service catalogs can live in one database.
+--              (Version 004 ships the cloud sync schema, which SQLite does not run.)
+
+-- services.name was globally unique; rebuild the table so names are unique per
+-- workspace. Foreign keys are switched off so dropping the old table does not
+-- cascade into mappings, features and history.
+PRAGMA foreign_keys = OFF;
+
+-- Views reference services and would block the rename below
+DROP VIEW IF EXISTS feature_details;
+DROP VIEW IF EXISTS search_results_view;
+
+CREATE TABLE IF NOT EXISTS services_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    name TEXT NOT NULL,
+    path TEXT,
+    description TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(workspace, name)
+);
+
+INSERT INTO services_new (id, workspace, name, path, description, created_at, updated_at)
+SELECT id, 'default', name, path, description, created_at, updated_at FROM services;
+
+DROP TABLE services;
+ALTER TABLE services_new RENAME TO services;
+
+PRAGMA foreign_keys = ON;
+
+ALTER TABLE generation_history ADD COLUMN workspace TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX IF NOT EXISTS idx_services_name ON services(name);
+CREATE INDEX IF NOT EXISTS idx_services_workspace ON services(workspace);
+CREATE INDEX IF NOT EXISTS idx_generation_history_workspace ON generation_history(workspace);
+
+-- Recreate the views dropped above
+CREATE VIEW IF NOT EXISTS feature_details AS
+SELECT
+    f.id,
+    f.service_id,
+    s.name as service_name,
+    f.name,
+    f.description,
+    f.feature_type,
+    f.tags,
+    f.metadata,
+    f.relevance_score,
+    f.created_at,
+    f.updated_at,
+    COUNT(DISTINCT fr_child.id) as child_count,
+    COUNT(DISTINCT fr_parent.id) as parent_count
+FROM features f
+LEFT JOIN services s ON f.service_id = s.id
+LEFT JOIN feature_relations fr_child ON f.id = fr_child.parent_feature_id
+LEFT JOIN feature_relations fr_parent ON f.id = fr_parent.child_feature_id
+GROUP BY f.id;
+
+CREATE VIEW IF NOT EXISTS search_results_view AS
+SELECT
+    si.id,
+    si.feature_id,
+    f.service_id,
+    s.name as service_name,
+    f.name as feature_name,
+    f.feature_type,
+    si.content_type,
+    si.content,
+    f.relevance_score,
+    si.indexed_at
+FROM search_index si
+JOIN features f ON si.feature_id = f.id
+JOIN services s ON f.service_id = s.id;
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (5);
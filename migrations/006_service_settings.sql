@@ -0,0 +1,8 @@
+-- KTME Service Settings
+-- Version: 006
+-- Description: Per-service generation defaults (doc type, template, model,
+--              language, publish targets, include/exclude globs) stored as JSON.
+
+ALTER TABLE services ADD COLUMN settings TEXT;
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (6);
@@ -0,0 +1,24 @@
+-- KTME Review Queue
+-- Version: 007
+-- Description: Generated documentation held for review before it is published
+--              (`ktme generate --queue`, `ktme queue`).
+
+CREATE TABLE IF NOT EXISTS pending_docs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    service TEXT NOT NULL,
+    doc_type TEXT NOT NULL,
+    source_identifier TEXT,
+    -- Output file; NULL publishes to the service's mapped locations
+    target TEXT,
+    content TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'approved', 'rejected')),
+    reviewer TEXT,
+    note TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    reviewed_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_docs_status ON pending_docs(workspace, status);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (7);
@@ -0,0 +1,8 @@
+-- KTME Generation Model
+-- Version: 008
+-- Description: AI model that generated each documentation change, as chosen
+--              by the service settings, --model or [ai.routing] rules.
+
+ALTER TABLE generation_history ADD COLUMN model TEXT;
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (8);
@@ -0,0 +1,32 @@
+-- KTME Jobs
+-- Version: 009
+-- Description: Multi-step operations (`ktme backfill`, multi-service
+--              `ktme generate`) and the state of each step, so a failed run
+--              can be resumed with `ktme jobs resume`.
+
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    kind TEXT NOT NULL,
+    description TEXT NOT NULL,
+    -- Arguments of the operation as JSON, to run it again on resume
+    params TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'running' CHECK(status IN ('running', 'failed', 'completed')),
+    error TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS job_steps (
+    job_id INTEGER NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'done', 'failed')),
+    error TEXT,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (job_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(workspace, status);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (9);
@@ -0,0 +1,21 @@
+-- KTME MCP audit log
+-- Version: 010
+-- Description: Every MCP tool call (`ktme mcp audit`), so teams can review
+--              what agents did to their documentation.
+
+CREATE TABLE IF NOT EXISTS mcp_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    tool TEXT NOT NULL,
+    -- Arguments as JSON, long values cut
+    arguments TEXT NOT NULL,
+    transport TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    status TEXT NOT NULL CHECK(status IN ('success', 'error', 'denied')),
+    error TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mcp_audit_created ON mcp_audit(workspace, created_at);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (10);
@@ -0,0 +1,11 @@
+-- KTME Idempotency Keys
+-- Version: 011
+-- Description: Canonical key of each generation (source, template, model and
+--              options), so a publish with the same key is not repeated.
+
+ALTER TABLE generation_history ADD COLUMN idempotency_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_generation_history_idempotency
+    ON generation_history(document_url, idempotency_key);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (11);
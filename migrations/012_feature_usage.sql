@@ -0,0 +1,11 @@
+-- KTME Feature Usage
+-- Version: 012
+-- Description: How often each feature is retrieved by searches and MCP tools
+--              since its relevance was last ranked, so `ktme feature rank`
+--              can decay idle features and boost the ones agents rely on.
+
+ALTER TABLE features ADD COLUMN usage_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE features ADD COLUMN last_used_at DATETIME;
+ALTER TABLE features ADD COLUMN ranked_at DATETIME;
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (12);
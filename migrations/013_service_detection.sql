@@ -0,0 +1,19 @@
+-- KTME Service Detection
+-- Version: 013
+-- Description: Service detected for each directory, so repeated commands and
+--              MCP calls do not run detection (and its AI fallback) again, and
+--              services pinned to a repository with `ktme service pin`.
+
+CREATE TABLE IF NOT EXISTS service_detections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    -- Current directory of a detection, repository root of a pin
+    directory TEXT NOT NULL,
+    service_name TEXT NOT NULL,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(workspace, directory, pinned)
+);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (13);
@@ -0,0 +1,22 @@
+-- KTME Generation Locks
+-- Version: 014
+-- Description: Advisory lock of each service held from generation to publish,
+--              so the CLI and the MCP server do not race on the same files
+--              and Confluence page versions. Locks expire, so a crashed run
+--              does not block the service for good.
+
+CREATE TABLE IF NOT EXISTS generation_locks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    -- Locked resource, e.g. `service:payments`
+    resource TEXT NOT NULL,
+    -- Random token of the holder, so only it can release the lock
+    owner TEXT NOT NULL,
+    pid INTEGER NOT NULL,
+    purpose TEXT NOT NULL,
+    acquired_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    UNIQUE(workspace, resource)
+);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (14);
@@ -0,0 +1,26 @@
+-- KTME Translations
+-- Version: 015
+-- Description: Translated counterparts of mapped documents (e.g. a Japanese
+--              page of an English Confluence page), kept in sync after the
+--              primary is published. The translation of each section is
+--              stored by the hash of its source text, so only the sections
+--              that changed are translated again.
+
+CREATE TABLE IF NOT EXISTS translations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    service_name TEXT NOT NULL,
+    -- Mapped location of the primary document
+    source TEXT NOT NULL,
+    -- Provider and location of the translated document
+    target_type TEXT NOT NULL,
+    target TEXT NOT NULL,
+    language TEXT NOT NULL,
+    -- JSON object of the translated sections by the hash of their source
+    sections TEXT NOT NULL DEFAULT '{}',
+    synced_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(workspace, target_type, target)
+);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (15);
@@ -0,0 +1,47 @@
+-- KTME Workspace Provider Configs and Diff Cache
+-- Version: 016
+-- Description: Give provider configurations and cached diffs a workspace, like
+--              every other table, so one workspace cannot read or replace
+--              another's providers and diffs. Existing rows belong to the
+--              default workspace.
+
+-- The unique constraints gain the workspace, so both tables are rebuilt
+CREATE TABLE IF NOT EXISTS provider_configs_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    provider_type TEXT NOT NULL,
+    config_json TEXT NOT NULL,
+    is_default BOOLEAN DEFAULT FALSE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(workspace, provider_type)
+);
+
+INSERT INTO provider_configs_new (id, workspace, provider_type, config_json, is_default, created_at, updated_at)
+SELECT id, 'default', provider_type, config_json, is_default, created_at, updated_at FROM provider_configs;
+
+DROP TABLE provider_configs;
+ALTER TABLE provider_configs_new RENAME TO provider_configs;
+
+CREATE TABLE IF NOT EXISTS diff_cache_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    source_type TEXT NOT NULL,
+    source_identifier TEXT NOT NULL,
+    repository_path TEXT,
+    diff_json TEXT NOT NULL,
+    expires_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(workspace, source_type, source_identifier, repository_path)
+);
+
+INSERT INTO diff_cache_new (id, workspace, source_type, source_identifier, repository_path, diff_json, expires_at, created_at)
+SELECT id, 'default', source_type, source_identifier, repository_path, diff_json, expires_at, created_at FROM diff_cache;
+
+DROP TABLE diff_cache;
+ALTER TABLE diff_cache_new RENAME TO diff_cache;
+
+CREATE INDEX IF NOT EXISTS idx_diff_cache_lookup ON diff_cache(workspace, source_type, source_identifier);
+CREATE INDEX IF NOT EXISTS idx_diff_cache_expires ON diff_cache(expires_at);
+
+INSERT OR IGNORE INTO schema_versions (version) VALUES (16);
@@ -0,0 +1,10 @@
+-- Revert 001: initial schema
+-- schema_versions is kept; it records the migrations themselves.
+
+DROP TABLE IF EXISTS diff_cache;
+DROP TABLE IF EXISTS generation_history;
+DROP TABLE IF EXISTS document_templates;
+DROP TABLE IF EXISTS prompt_templates;
+DROP TABLE IF EXISTS provider_configs;
+DROP TABLE IF EXISTS document_mappings;
+DROP TABLE IF EXISTS services;
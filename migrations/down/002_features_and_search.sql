@@ -0,0 +1,13 @@
+-- Revert 002: features, relations and search
+
+DROP VIEW IF EXISTS search_results_view;
+DROP VIEW IF EXISTS feature_details;
+DROP TRIGGER IF EXISTS update_features_timestamp;
+
+DROP TABLE IF EXISTS knowledge_graph_cache;
+DROP TABLE IF EXISTS search_cache;
+DROP TABLE IF EXISTS search_index;
+DROP TABLE IF EXISTS feature_relations;
+DROP TABLE IF EXISTS features;
+
+ALTER TABLE document_mappings DROP COLUMN feature_id;
@@ -0,0 +1,39 @@
+-- Revert 003: unique (feature_id, content_type) in search_index
+
+DROP VIEW IF EXISTS search_results_view;
+
+CREATE TABLE search_index_old (
+    id TEXT PRIMARY KEY,
+    feature_id TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    content TEXT NOT NULL,
+    embedding BLOB,
+    indexed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (feature_id) REFERENCES features(id) ON DELETE CASCADE
+);
+
+INSERT INTO search_index_old (id, feature_id, content_type, content, embedding, indexed_at)
+SELECT id, feature_id, content_type, content, embedding, indexed_at FROM search_index;
+
+DROP TABLE search_index;
+ALTER TABLE search_index_old RENAME TO search_index;
+
+CREATE INDEX IF NOT EXISTS idx_search_index_feature ON search_index(feature_id);
+CREATE INDEX IF NOT EXISTS idx_search_index_type ON search_index(content_type);
+CREATE INDEX IF NOT EXISTS idx_search_index_content ON search_index(content);
+
+CREATE VIEW IF NOT EXISTS search_results_view AS
+SELECT
+    si.id,
+    si.feature_id,
+    f.service_id,
+    s.name as service_name,
+    f.name as feature_name,
+    f.feature_type,
+    si.content_type,
+    si.content,
+    f.relevance_score,
+    si.indexed_at
+FROM search_index si
+JOIN features f ON si.feature_id = f.id
+JOIN services s ON f.service_id = s.id;
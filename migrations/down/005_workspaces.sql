@@ -0,0 +1,63 @@
+-- Revert 005: workspaces
+-- Fails when two workspaces hold a service of the same name, as service
+-- names were globally unique before workspaces.
+
+DROP VIEW IF EXISTS feature_details;
+DROP VIEW IF EXISTS search_results_view;
+
+CREATE TABLE services_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    path TEXT,
+    description TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO services_old (id, name, path, description, created_at, updated_at)
+SELECT id, name, path, description, created_at, updated_at FROM services;
+
+DROP TABLE services;
+ALTER TABLE services_old RENAME TO services;
+
+DROP INDEX IF EXISTS idx_generation_history_workspace;
+ALTER TABLE generation_history DROP COLUMN workspace;
+
+CREATE INDEX IF NOT EXISTS idx_services_name ON services(name);
+
+CREATE VIEW IF NOT EXISTS feature_details AS
+SELECT
+    f.id,
+    f.service_id,
+    s.name as service_name,
+    f.name,
+    f.description,
+    f.feature_type,
+    f.tags,
+    f.metadata,
+    f.relevance_score,
+    f.created_at,
+    f.updated_at,
+    COUNT(DISTINCT fr_child.id) as child_count,
+    COUNT(DISTINCT fr_parent.id) as parent_count
+FROM features f
+LEFT JOIN services s ON f.service_id = s.id
+LEFT JOIN feature_relations fr_child ON f.id = fr_child.parent_feature_id
+LEFT JOIN feature_relations fr_parent ON f.id = fr_parent.child_feature_id
+GROUP BY f.id;
+
+CREATE VIEW IF NOT EXISTS search_results_view AS
+SELECT
+    si.id,
+    si.feature_id,
+    f.service_id,
+    s.name as service_name,
+    f.name as feature_name,
+    f.feature_type,
+    si.content_type,
+    si.content,
+    f.relevance_score,
+    si.indexed_at
+FROM search_index si
+JOIN features f ON si.feature_id = f.id
+JOIN services s ON f.service_id = s.id;
@@ -0,0 +1,3 @@
+-- Revert 006: per-service settings
+
+ALTER TABLE services DROP COLUMN settings;
@@ -0,0 +1,3 @@
+-- Revert 007: review queue
+
+DROP TABLE IF EXISTS pending_docs;
@@ -0,0 +1,3 @@
+-- Revert 008: model of each generation
+
+ALTER TABLE generation_history DROP COLUMN model;
@@ -0,0 +1,4 @@
+-- Revert 009: jobs and their steps
+
+DROP TABLE IF EXISTS job_steps;
+DROP TABLE IF EXISTS jobs;
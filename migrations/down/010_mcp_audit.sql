@@ -0,0 +1,3 @@
+-- Revert 010: MCP audit log
+
+DROP TABLE IF EXISTS mcp_audit;
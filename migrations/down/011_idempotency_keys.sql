@@ -0,0 +1,4 @@
+-- Revert 011: idempotency keys of generations
+
+DROP INDEX IF EXISTS idx_generation_history_idempotency;
+ALTER TABLE generation_history DROP COLUMN idempotency_key;
@@ -0,0 +1,5 @@
+-- Revert 012: feature usage counters
+
+ALTER TABLE features DROP COLUMN ranked_at;
+ALTER TABLE features DROP COLUMN last_used_at;
+ALTER TABLE features DROP COLUMN usage_count;
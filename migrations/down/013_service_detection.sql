@@ -0,0 +1,3 @@
+-- Revert 013: service detection cache and pins
+
+DROP TABLE IF EXISTS service_detections;
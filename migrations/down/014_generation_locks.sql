@@ -0,0 +1,3 @@
+-- Revert 014: generation locks
+
+DROP TABLE IF EXISTS generation_locks;
@@ -0,0 +1,3 @@
+-- Revert 015: translations
+
+DROP TABLE IF EXISTS translations;
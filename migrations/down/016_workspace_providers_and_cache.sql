@@ -0,0 +1,40 @@
+-- Revert 016: workspace of provider configs and cached diffs
+-- Fails when two workspaces hold a provider of the same type, as provider
+-- types were globally unique before. Cached diffs of other workspaces are
+-- dropped.
+
+CREATE TABLE provider_configs_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    provider_type TEXT NOT NULL UNIQUE,
+    config_json TEXT NOT NULL,
+    is_default BOOLEAN DEFAULT FALSE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO provider_configs_old (id, provider_type, config_json, is_default, created_at, updated_at)
+SELECT id, provider_type, config_json, is_default, created_at, updated_at FROM provider_configs;
+
+DROP TABLE provider_configs;
+ALTER TABLE provider_configs_old RENAME TO provider_configs;
+
+CREATE TABLE diff_cache_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    source_type TEXT NOT NULL,
+    source_identifier TEXT NOT NULL,
+    repository_path TEXT,
+    diff_json TEXT NOT NULL,
+    expires_at DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(source_type, source_identifier, repository_path)
+);
+
+INSERT INTO diff_cache_old (id, source_type, source_identifier, repository_path, diff_json, expires_at, created_at)
+SELECT id, source_type, source_identifier, repository_path, diff_json, expires_at, created_at
+FROM diff_cache WHERE workspace = 'default';
+
+DROP TABLE diff_cache;
+ALTER TABLE diff_cache_old RENAME TO diff_cache;
+
+CREATE INDEX IF NOT EXISTS idx_diff_cache_lookup ON diff_cache(source_type, source_identifier);
+CREATE INDEX IF NOT EXISTS idx_diff_cache_expires ON diff_cache(expires_at);
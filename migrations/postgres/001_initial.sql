@@ -0,0 +1,108 @@
+-- KTME Initial Schema (PostgreSQL)
+-- Version: 001
+-- Description: PostgreSQL equivalent of migrations/001_initial.sql
+
+-- Service registry
+CREATE TABLE IF NOT EXISTS services (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    path TEXT,
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Document locations for each service (supports multiple providers per service)
+CREATE TABLE IF NOT EXISTS document_mappings (
+    id BIGSERIAL PRIMARY KEY,
+    service_id BIGINT NOT NULL REFERENCES services(id) ON DELETE CASCADE,
+    provider TEXT NOT NULL,
+    location TEXT NOT NULL,
+    title TEXT,
+    section TEXT,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(service_id, provider, location)
+);
+
+-- Provider configurations (Confluence, Google Docs, etc.)
+CREATE TABLE IF NOT EXISTS provider_configs (
+    id BIGSERIAL PRIMARY KEY,
+    provider_type TEXT NOT NULL UNIQUE,
+    config_json TEXT NOT NULL,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Prompt templates for AI generation
+CREATE TABLE IF NOT EXISTS prompt_templates (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    template TEXT NOT NULL,
+    variables_json TEXT,
+    output_format TEXT DEFAULT 'markdown',
+    is_builtin BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Document templates (api-doc, changelog, etc.)
+CREATE TABLE IF NOT EXISTS document_templates (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    content TEXT NOT NULL,
+    template_type TEXT,
+    is_builtin BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Generation history (audit log)
+CREATE TABLE IF NOT EXISTS generation_history (
+    id BIGSERIAL PRIMARY KEY,
+    service_id BIGINT REFERENCES services(id) ON DELETE SET NULL,
+    provider TEXT NOT NULL,
+    document_id TEXT,
+    document_url TEXT,
+    action TEXT NOT NULL,
+    source_type TEXT,
+    source_identifier TEXT,
+    content_hash TEXT,
+    status TEXT NOT NULL,
+    error_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Diff cache (for performance)
+CREATE TABLE IF NOT EXISTS diff_cache (
+    id BIGSERIAL PRIMARY KEY,
+    source_type TEXT NOT NULL,
+    source_identifier TEXT NOT NULL,
+    repository_path TEXT,
+    diff_json TEXT NOT NULL,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(source_type, source_identifier, repository_path)
+);
+
+-- Schema version tracking
+CREATE TABLE IF NOT EXISTS schema_versions (
+    version BIGINT PRIMARY KEY,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Insert schema version
+INSERT INTO schema_versions (version) VALUES (1) ON CONFLICT DO NOTHING;
+
+-- Indexes for performance
+CREATE INDEX IF NOT EXISTS idx_services_name ON services(name);
+CREATE INDEX IF NOT EXISTS idx_document_mappings_service ON document_mappings(service_id);
+CREATE INDEX IF NOT EXISTS idx_document_mappings_provider ON document_mappings(provider);
+CREATE INDEX IF NOT EXISTS idx_generation_history_service ON generation_history(service_id);
+CREATE INDEX IF NOT EXISTS idx_generation_history_created ON generation_history(created_at);
+CREATE INDEX IF NOT EXISTS idx_diff_cache_lookup ON diff_cache(source_type, source_identifier);
+CREATE INDEX IF NOT EXISTS idx_diff_cache_expires ON diff_cache(expires_at);
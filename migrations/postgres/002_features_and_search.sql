@@ -0,0 +1,138 @@
+-- KTME Features and Search Enhancement (PostgreSQL)
+-- Version: 002
+-- Description: PostgreSQL equivalent of migrations/002_features_and_search.sql
+
+-- Features table - represents software features within services
+CREATE TABLE IF NOT EXISTS features (
+    id TEXT PRIMARY KEY,  -- UUID for distributed compatibility
+    service_id BIGINT NOT NULL REFERENCES services(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    feature_type TEXT NOT NULL,
+    tags TEXT,  -- JSON array of tags
+    metadata TEXT,  -- JSON object for additional metadata
+    relevance_score DOUBLE PRECISION NOT NULL DEFAULT 0.0,
+    embedding BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(service_id, name)
+);
+
+-- Feature relationships table
+CREATE TABLE IF NOT EXISTS feature_relations (
+    id TEXT PRIMARY KEY,
+    parent_feature_id TEXT NOT NULL REFERENCES features(id) ON DELETE CASCADE,
+    child_feature_id TEXT NOT NULL REFERENCES features(id) ON DELETE CASCADE,
+    relation_type TEXT NOT NULL,
+    strength DOUBLE PRECISION NOT NULL DEFAULT 1.0,
+    metadata TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (parent_feature_id != child_feature_id)
+);
+
+-- Search index for semantic and keyword search
+CREATE TABLE IF NOT EXISTS search_index (
+    id TEXT PRIMARY KEY,
+    feature_id TEXT NOT NULL REFERENCES features(id) ON DELETE CASCADE,
+    content_type TEXT NOT NULL,
+    content TEXT NOT NULL,
+    embedding BYTEA,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Document mappings enhancement - add feature relationship
+ALTER TABLE document_mappings ADD COLUMN IF NOT EXISTS feature_id TEXT;
+
+-- Search results cache for performance
+CREATE TABLE IF NOT EXISTS search_cache (
+    id TEXT PRIMARY KEY,
+    query_hash TEXT NOT NULL,
+    query_params TEXT,
+    results TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Knowledge graph nodes cache
+CREATE TABLE IF NOT EXISTS knowledge_graph_cache (
+    id TEXT PRIMARY KEY,
+    service_filter TEXT,
+    graph_data TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+-- Insert schema version
+INSERT INTO schema_versions (version) VALUES (2) ON CONFLICT DO NOTHING;
+
+-- Indexes for performance
+CREATE INDEX IF NOT EXISTS idx_features_service ON features(service_id);
+CREATE INDEX IF NOT EXISTS idx_features_type ON features(feature_type);
+CREATE INDEX IF NOT EXISTS idx_features_name ON features(name);
+CREATE INDEX IF NOT EXISTS idx_features_relevance ON features(relevance_score DESC);
+
+CREATE INDEX IF NOT EXISTS idx_feature_relations_parent ON feature_relations(parent_feature_id);
+CREATE INDEX IF NOT EXISTS idx_feature_relations_child ON feature_relations(child_feature_id);
+CREATE INDEX IF NOT EXISTS idx_feature_relations_type ON feature_relations(relation_type);
+CREATE INDEX IF NOT EXISTS idx_feature_relations_strength ON feature_relations(strength DESC);
+
+CREATE INDEX IF NOT EXISTS idx_search_index_feature ON search_index(feature_id);
+CREATE INDEX IF NOT EXISTS idx_search_index_type ON search_index(content_type);
+CREATE INDEX IF NOT EXISTS idx_search_index_content ON search_index(content);
+
+CREATE INDEX IF NOT EXISTS idx_search_cache_query ON search_cache(query_hash);
+CREATE INDEX IF NOT EXISTS idx_search_cache_expires ON search_cache(expires_at);
+
+CREATE INDEX IF NOT EXISTS idx_knowledge_graph_service ON knowledge_graph_cache(service_filter);
+CREATE INDEX IF NOT EXISTS idx_knowledge_graph_expires ON knowledge_graph_cache(expires_at);
+
+-- Keep updated_at current on feature updates
+CREATE OR REPLACE FUNCTION update_features_timestamp() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at = NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS update_features_timestamp ON features;
+CREATE TRIGGER update_features_timestamp
+    BEFORE UPDATE ON features
+    FOR EACH ROW EXECUTE FUNCTION update_features_timestamp();
+
+-- Helper views for common queries
+CREATE OR REPLACE VIEW feature_details AS
+SELECT
+    f.id,
+    f.service_id,
+    s.name as service_name,
+    f.name,
+    f.description,
+    f.feature_type,
+    f.tags,
+    f.metadata,
+    f.relevance_score,
+    f.created_at,
+    f.updated_at,
+    COUNT(DISTINCT fr_child.id) as child_count,
+    COUNT(DISTINCT fr_parent.id) as parent_count
+FROM features f
+LEFT JOIN services s ON f.service_id = s.id
+LEFT JOIN feature_relations fr_child ON f.id = fr_child.parent_feature_id
+LEFT JOIN feature_relations fr_parent ON f.id = fr_parent.child_feature_id
+GROUP BY f.id, s.name;
+
+CREATE OR REPLACE VIEW search_results_view AS
+SELECT
+    si.id,
+    si.feature_id,
+    f.service_id,
+    s.name as service_name,
+    f.name as feature_name,
+    f.feature_type,
+    si.content_type,
+    si.content,
+    f.relevance_score,
+    si.indexed_at
+FROM search_index si
+JOIN features f ON si.feature_id = f.id
+JOIN services s ON f.service_id = s.id;
@@ -0,0 +1,14 @@
+-- KTME Search Index Unique Constraint (PostgreSQL)
+-- Version: 003
+-- Description: Add unique constraint on (feature_id, content_type) in search_index
+--              to enable idempotent upserts via ON CONFLICT clause.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_search_index_feature_content_type
+    ON search_index(feature_id, content_type);
+
+-- Indexes kept in line with the SQLite migration, which rebuilds the table
+CREATE INDEX IF NOT EXISTS idx_search_index_feature ON search_index(feature_id);
+CREATE INDEX IF NOT EXISTS idx_search_index_type ON search_index(content_type);
+CREATE INDEX IF NOT EXISTS idx_search_index_content ON search_index(content);
+
+INSERT INTO schema_versions (version) VALUES (3) ON CONFLICT DO NOTHING;
@@ -0,0 +1,14 @@
+-- KTME Workspaces (PostgreSQL)
+-- Version: 005
+-- Description: PostgreSQL equivalent of migrations/005_workspaces.sql
+
+ALTER TABLE services ADD COLUMN IF NOT EXISTS workspace TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE services DROP CONSTRAINT IF EXISTS services_name_key;
+ALTER TABLE services ADD CONSTRAINT services_workspace_name_key UNIQUE (workspace, name);
+
+ALTER TABLE generation_history ADD COLUMN IF NOT EXISTS workspace TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX IF NOT EXISTS idx_services_workspace ON services(workspace);
+CREATE INDEX IF NOT EXISTS idx_generation_history_workspace ON generation_history(workspace);
+
+INSERT INTO schema_versions (version) VALUES (5) ON CONFLICT DO NOTHING;
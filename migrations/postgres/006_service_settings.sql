@@ -0,0 +1,7 @@
+-- KTME Service Settings (PostgreSQL)
+-- Version: 006
+-- Description: PostgreSQL equivalent of migrations/006_service_settings.sql
+
+ALTER TABLE services ADD COLUMN IF NOT EXISTS settings TEXT;
+
+INSERT INTO schema_versions (version) VALUES (6) ON CONFLICT DO NOTHING;
@@ -0,0 +1,22 @@
+-- KTME Review Queue (PostgreSQL)
+-- Version: 007
+-- Description: PostgreSQL equivalent of migrations/007_pending_docs.sql
+
+CREATE TABLE IF NOT EXISTS pending_docs (
+    id BIGSERIAL PRIMARY KEY,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    service TEXT NOT NULL,
+    doc_type TEXT NOT NULL,
+    source_identifier TEXT,
+    target TEXT,
+    content TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'approved', 'rejected')),
+    reviewer TEXT,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    reviewed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_docs_status ON pending_docs(workspace, status);
+
+INSERT INTO schema_versions (version) VALUES (7) ON CONFLICT DO NOTHING;
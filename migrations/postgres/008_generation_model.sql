@@ -0,0 +1,7 @@
+-- KTME Generation Model (PostgreSQL)
+-- Version: 008
+-- Description: PostgreSQL equivalent of migrations/008_generation_model.sql
+
+ALTER TABLE generation_history ADD COLUMN IF NOT EXISTS model TEXT;
+
+INSERT INTO schema_versions (version) VALUES (8) ON CONFLICT DO NOTHING;
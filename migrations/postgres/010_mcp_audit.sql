@@ -0,0 +1,19 @@
+-- KTME MCP audit log (PostgreSQL)
+-- Version: 010
+-- Description: PostgreSQL equivalent of migrations/010_mcp_audit.sql
+
+CREATE TABLE IF NOT EXISTS mcp_audit (
+    id BIGSERIAL PRIMARY KEY,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    tool TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    transport TEXT NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('success', 'error', 'denied')),
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_mcp_audit_created ON mcp_audit(workspace, created_at);
+
+INSERT INTO schema_versions (version) VALUES (10) ON CONFLICT DO NOTHING;
@@ -0,0 +1,10 @@
+-- KTME Idempotency Keys (PostgreSQL)
+-- Version: 011
+-- Description: PostgreSQL equivalent of migrations/011_idempotency_keys.sql
+
+ALTER TABLE generation_history ADD COLUMN IF NOT EXISTS idempotency_key TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_generation_history_idempotency
+    ON generation_history(document_url, idempotency_key);
+
+INSERT INTO schema_versions (version) VALUES (11) ON CONFLICT DO NOTHING;
@@ -0,0 +1,9 @@
+-- KTME Feature Usage (PostgreSQL)
+-- Version: 012
+-- Description: PostgreSQL equivalent of migrations/012_feature_usage.sql
+
+ALTER TABLE features ADD COLUMN IF NOT EXISTS usage_count BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE features ADD COLUMN IF NOT EXISTS last_used_at TIMESTAMPTZ;
+ALTER TABLE features ADD COLUMN IF NOT EXISTS ranked_at TIMESTAMPTZ;
+
+INSERT INTO schema_versions (version) VALUES (12) ON CONFLICT DO NOTHING;
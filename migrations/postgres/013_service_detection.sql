@@ -0,0 +1,16 @@
+-- KTME Service Detection (PostgreSQL)
+-- Version: 013
+-- Description: PostgreSQL equivalent of migrations/013_service_detection.sql
+
+CREATE TABLE IF NOT EXISTS service_detections (
+    id BIGSERIAL PRIMARY KEY,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    directory TEXT NOT NULL,
+    service_name TEXT NOT NULL,
+    pinned BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (workspace, directory, pinned)
+);
+
+INSERT INTO schema_versions (version) VALUES (13) ON CONFLICT DO NOTHING;
@@ -0,0 +1,17 @@
+-- KTME Generation Locks (PostgreSQL)
+-- Version: 014
+-- Description: PostgreSQL equivalent of migrations/014_generation_locks.sql
+
+CREATE TABLE IF NOT EXISTS generation_locks (
+    id BIGSERIAL PRIMARY KEY,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    resource TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    pid BIGINT NOT NULL,
+    purpose TEXT NOT NULL,
+    acquired_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (workspace, resource)
+);
+
+INSERT INTO schema_versions (version) VALUES (14) ON CONFLICT DO NOTHING;
@@ -0,0 +1,19 @@
+-- KTME Translations (PostgreSQL)
+-- Version: 015
+-- Description: PostgreSQL equivalent of migrations/015_translations.sql
+
+CREATE TABLE IF NOT EXISTS translations (
+    id BIGSERIAL PRIMARY KEY,
+    workspace TEXT NOT NULL DEFAULT 'default',
+    service_name TEXT NOT NULL,
+    source TEXT NOT NULL,
+    target_type TEXT NOT NULL,
+    target TEXT NOT NULL,
+    language TEXT NOT NULL,
+    sections TEXT NOT NULL DEFAULT '{}',
+    synced_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    UNIQUE (workspace, target_type, target)
+);
+
+INSERT INTO schema_versions (version) VALUES (15) ON CONFLICT DO NOTHING;
@@ -0,0 +1,16 @@
+-- KTME Workspace Provider Configs and Diff Cache (PostgreSQL)
+-- Version: 016
+-- Description: PostgreSQL equivalent of migrations/016_workspace_providers_and_cache.sql
+
+ALTER TABLE provider_configs ADD COLUMN IF NOT EXISTS workspace TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE provider_configs DROP CONSTRAINT IF EXISTS provider_configs_provider_type_key;
+ALTER TABLE provider_configs ADD CONSTRAINT provider_configs_workspace_provider_type_key UNIQUE (workspace, provider_type);
+
+ALTER TABLE diff_cache ADD COLUMN IF NOT EXISTS workspace TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE diff_cache DROP CONSTRAINT IF EXISTS diff_cache_source_type_source_identifier_repository_path_key;
+ALTER TABLE diff_cache ADD CONSTRAINT diff_cache_workspace_source_key UNIQUE (workspace, source_type, source_identifier, repository_path);
+
+DROP INDEX IF EXISTS idx_diff_cache_lookup;
+CREATE INDEX IF NOT EXISTS idx_diff_cache_lookup ON diff_cache(workspace, source_type, source_identifier);
+
+INSERT INTO schema_versions (version) VALUES (16) ON CONFLICT DO NOTHING;
@@ -1,53 +1,154 @@
-use crate::ai::providers::{AIProvider, AIProviderFactory, ClaudeConfig, OpenAIConfig};
-use crate::error::Result;
+use crate::ai::fixtures::{RecordingProvider, ReplayProvider, RECORD_ENV, REPLAY_ENV};
+use crate::ai::providers::{
+    AIProvider, AIProviderFactory, ClaudeConfig, FallbackProvider, OpenAIConfig,
+    SummarizingProvider,
+};
+use crate::config::{AiConfig, Config};
+use crate::error::{KtmeError, Result};
 use std::env;
+use std::str::FromStr;
+use std::time::Instant;
 
 pub struct AIClient {
     provider: Box<dyn AIProvider>,
 }
 
 impl AIClient {
+    /// Client for the `[ai]` provider in the ktme config file
     pub fn new() -> Result<Self> {
-        // Try to detect available AI provider from environment variables
-        if let Ok(api_key) = env::var("OPENAI_API_KEY") {
-            let config = OpenAIConfig {
-                api_key,
-                model: env::var("OPENAI_MODEL").unwrap_or_else(|_| "gpt-4".to_string()),
-                max_tokens: env::var("OPENAI_MAX_TOKENS")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
+        Self::from_config(&Config::load()?.ai)
+    }
+
+    /// Client for the configured provider. Without `provider` it is detected
+    /// from OPENAI_API_KEY or ANTHROPIC_API_KEY, falling back to the mock.
+    /// In offline mode hosted providers are replaced by the mock; an
+    /// OpenAI-compatible server on localhost is still used.
+    ///
+    /// The providers of `fallback` are tried in order when the provider
+    /// fails, and a `summary` provider condenses the changes first.
+    ///
+    /// `ktme --replay <dir>` answers from recorded responses instead, and
+    /// `ktme --record <dir>` records the responses of the provider.
+    pub fn from_config(config: &AiConfig) -> Result<Self> {
+        if let Some(dir) = env::var_os(REPLAY_ENV) {
+            return Ok(Self::with_provider(Box::new(ReplayProvider::new(dir))));
+        }
+
+        let mut provider = Self::create_chain(config)?;
+        if let Some(summary) = &config.summary {
+            provider = Box::new(SummarizingProvider::new(
+                Self::create_chain(summary)?,
+                provider,
+            ));
+        }
+        let provider: Box<dyn AIProvider> = match env::var_os(RECORD_ENV) {
+            Some(dir) => Box::new(RecordingProvider::new(provider, dir)),
+            None => provider,
+        };
+
+        Ok(Self { provider })
+    }
+
+    /// The provider of `config` followed by its `fallback` providers
+    fn create_chain(config: &AiConfig) -> Result<Box<dyn AIProvider>> {
+        let provider = Self::create_provider(config)?;
+        if config.fallback.is_empty() {
+            return Ok(provider);
+        }
+        let mut providers = vec![provider];
+        for fallback in &config.fallback {
+            providers.push(Self::create_provider(fallback)?);
+        }
+        Ok(Box::new(FallbackProvider::new(providers)))
+    }
+
+    fn create_provider(config: &AiConfig) -> Result<Box<dyn AIProvider>> {
+        let name = match config.provider.as_deref() {
+            Some(name) => name.to_string(),
+            None if env::var("OPENAI_API_KEY").is_ok() => "openai".to_string(),
+            None if env::var("ANTHROPIC_API_KEY").is_ok() => "anthropic".to_string(),
+            None => {
+                tracing::warn!("No AI provider configured. Using mock provider for testing.");
+                "mock".to_string()
+            }
+        };
+
+        let base_url = config
+            .base_url
+            .clone()
+            .or_else(|| env::var("OPENAI_BASE_URL").ok());
+        let remote = match name.to_lowercase().as_str() {
+            "openai" => !base_url.as_deref().is_some_and(crate::http::is_local_url),
+            "anthropic" | "claude" => true,
+            _ => false,
+        };
+
+        let provider = match name.to_lowercase().as_str() {
+            _ if remote && crate::http::offline() => {
+                tracing::warn!(
+                    "Offline mode: using the mock AI provider instead of {}",
+                    name
+                );
+                AIProviderFactory::create_mock()?
+            }
+            "openai" => AIProviderFactory::create_openai(OpenAIConfig {
+                // OpenAI-compatible gateways may not need a key
+                api_key: config
+                    .api_key
+                    .clone()
+                    .or_else(|| env::var("OPENAI_API_KEY").ok())
+                    .unwrap_or_default(),
+                model: config
+                    .model
+                    .clone()
+                    .or_else(|| env::var("OPENAI_MODEL").ok())
+                    .unwrap_or_else(|| "gpt-4".to_string()),
+                max_tokens: config
+                    .max_tokens
+                    .or_else(|| env_parse("OPENAI_MAX_TOKENS"))
                     .unwrap_or(4096),
-                temperature: env::var("OPENAI_TEMPERATURE")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
+                temperature: config
+                    .temperature
+                    .or_else(|| env_parse("OPENAI_TEMPERATURE"))
                     .unwrap_or(0.7),
-                base_url: env::var("OPENAI_BASE_URL").ok(),
-            };
-
-            let provider = AIProviderFactory::create_openai(config)?;
-            Ok(Self { provider })
-        } else if let Ok(api_key) = env::var("ANTHROPIC_API_KEY") {
-            let config = ClaudeConfig {
-                api_key,
-                model: env::var("CLAUDE_MODEL")
-                    .unwrap_or_else(|_| "claude-3-sonnet-20240229".to_string()),
-                max_tokens: env::var("CLAUDE_MAX_TOKENS")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
+                base_url,
+                headers: config.headers.clone(),
+            })?,
+            "anthropic" | "claude" => AIProviderFactory::create_claude(ClaudeConfig {
+                api_key: config
+                    .api_key
+                    .clone()
+                    .or_else(|| env::var("ANTHROPIC_API_KEY").ok())
+                    .ok_or_else(|| {
+                        KtmeError::Config(
+                            "AI provider 'anthropic' needs ai.api_key or ANTHROPIC_API_KEY"
+                                .to_string(),
+                        )
+                    })?,
+                model: config
+                    .model
+                    .clone()
+                    .or_else(|| env::var("CLAUDE_MODEL").ok())
+                    .unwrap_or_else(|| "claude-3-sonnet-20240229".to_string()),
+                max_tokens: config
+                    .max_tokens
+                    .or_else(|| env_parse("CLAUDE_MAX_TOKENS"))
                     .unwrap_or(4096),
-                temperature: env::var("CLAUDE_TEMPERATURE")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
+                temperature: config
+                    .temperature
+                    .or_else(|| env_parse("CLAUDE_TEMPERATURE"))
                     .unwrap_or(0.7),
-            };
-
-            let provider = AIProviderFactory::create_claude(config)?;
-            Ok(Self { provider })
-        } else {
-            tracing::warn!("No AI provider configured. Using mock provider for testing.");
-            let provider = AIProviderFactory::create_mock()?;
-            Ok(Self { provider })
-        }
+            })?,
+            "mock" => AIProviderFactory::create_mock()?,
+            _ => AIProviderFactory::create_external(&name, config)?,
+        };
+
+        Ok(provider)
+    }
+
+    /// Client over an explicitly chosen provider
+    pub fn with_provider(provider: Box<dyn AIProvider>) -> Self {
+        Self { provider }
     }
 
     pub fn new_with_fallback() -> Result<Self> {
@@ -67,16 +168,28 @@ impl AIClient {
             self.provider.provider_name()
         );
 
-        let response = self.provider.generate(prompt).await?;
+        let started = Instant::now();
+        let result = self.provider.generate(prompt).await;
+        crate::metrics::record_ai_request(
+            self.provider.provider_name(),
+            started.elapsed(),
+            result.is_ok(),
+        );
+        let response = result?;
 
         tracing::info!("Documentation generated successfully");
         Ok(response)
     }
 
-    pub fn provider_name(&self) -> &'static str {
+    pub fn provider_name(&self) -> &str {
         self.provider.provider_name()
     }
 
+    /// Model of the provider, when it has one
+    pub fn model(&self) -> Option<&str> {
+        self.provider.model()
+    }
+
     pub async fn test_connection(&self) -> Result<bool> {
         match self.provider.generate("Hello").await {
             Ok(_) => Ok(true),
@@ -84,3 +197,7 @@ impl AIClient {
         }
     }
 }
+
+fn env_parse<T: FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|s| s.parse().ok())
+}
@@ -0,0 +1,143 @@
+//! Recorded AI responses for reproducible runs
+//!
+//! With `--record <dir>` every response of the configured provider is saved
+//! in `<dir>/<prompt hash>.json`; with `--replay <dir>` the saved responses
+//! are returned instead of calling a provider, so no API key or network
+//! access is needed. A prompt without a recording fails the replay.
+
+use crate::ai::providers::AIProvider;
+use crate::error::{KtmeError, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set by `ktme --record <dir>`
+pub const RECORD_ENV: &str = "KTME_AI_RECORD";
+
+/// Set by `ktme --replay <dir>`
+pub const REPLAY_ENV: &str = "KTME_AI_REPLAY";
+
+/// One recorded response
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    provider: String,
+    prompt_hash: String,
+    /// Kept so that a changed prompt can be compared with its recording
+    prompt: String,
+    response: String,
+}
+
+fn prompt_hash(prompt: &str) -> String {
+    format!("{:x}", Sha256::digest(prompt.as_bytes()))
+}
+
+fn fixture_path(dir: &Path, prompt: &str) -> PathBuf {
+    dir.join(format!("{}.json", prompt_hash(prompt)))
+}
+
+/// Saves every response of the wrapped provider
+pub struct RecordingProvider {
+    inner: Box<dyn AIProvider>,
+    dir: PathBuf,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Box<dyn AIProvider>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for RecordingProvider {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let response = self.inner.generate(prompt).await?;
+
+        let fixture = Fixture {
+            provider: self.inner.provider_name().to_string(),
+            prompt_hash: prompt_hash(prompt),
+            prompt: prompt.to_string(),
+            response,
+        };
+        fs::create_dir_all(&self.dir)?;
+        let path = fixture_path(&self.dir, prompt);
+        fs::write(&path, serde_json::to_string_pretty(&fixture)?)?;
+        tracing::debug!("Recorded AI response in {}", path.display());
+
+        Ok(fixture.response)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.inner.model()
+    }
+}
+
+/// Answers prompts from recorded responses
+pub struct ReplayProvider {
+    dir: PathBuf,
+}
+
+impl ReplayProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl AIProvider for ReplayProvider {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let path = fixture_path(&self.dir, prompt);
+        let content = fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => KtmeError::NotFound(format!(
+                "No recorded AI response for this prompt ({}); record it with --record {}",
+                path.display(),
+                self.dir.display()
+            )),
+            _ => e.into(),
+        })?;
+        let fixture: Fixture = serde_json::from_str(&content)
+            .map_err(|e| KtmeError::DeserializationError(format!("{}: {}", path.display(), e)))?;
+        tracing::debug!(
+            "Replaying the {} response recorded in {}",
+            fixture.provider,
+            path.display()
+        );
+
+        Ok(fixture.response)
+    }
+
+    fn provider_name(&self) -> &str {
+        "Replay"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::providers::MockProvider;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = TempDir::new().unwrap();
+        let recorder = RecordingProvider::new(Box::new(MockProvider::new()), dir.path());
+        let recorded = recorder.generate("Document the API").await.unwrap();
+        assert_eq!(recorder.provider_name(), "Mock");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let replay = ReplayProvider::new(dir.path());
+        assert_eq!(replay.generate("Document the API").await.unwrap(), recorded);
+        assert!(matches!(
+            replay.generate("Document the changelog").await,
+            Err(KtmeError::NotFound(_))
+        ));
+    }
+}
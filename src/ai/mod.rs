@@ -1,6 +1,10 @@
 pub mod client;
+pub mod fixtures;
+pub mod prompt_pack;
 pub mod prompts;
 pub mod providers;
+pub mod routing;
+pub mod template;
 
 pub use client::AIClient;
 
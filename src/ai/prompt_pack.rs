@@ -0,0 +1,222 @@
+//! Editable built-in prompts
+//!
+//! Every built-in prompt of [`PromptTemplates`] can be replaced by a
+//! `<name>.md` file (see [`PromptTemplates::NAMES`]). ktme looks in the
+//! selected prompt pack first, then in `prompts/` of the config directory,
+//! and falls back to the built-in text:
+//!
+//! ```text
+//! ~/.config/ktme/prompts/changelog.md            override for everyone
+//! ~/.config/ktme/prompts/packs/sre/general.md    the "sre" pack
+//! ```
+//!
+//! A pack is selected with `[documentation] prompt_pack` or per service with
+//! `mapping add --set prompt_pack=sre`, and may also be the path of a
+//! directory, so a team can keep its pack in a repository. A pack may add
+//! prompts for its own document types, e.g. `runbook.md` for
+//! `--type runbook`.
+
+use crate::ai::prompts::PromptTemplates;
+use crate::config::Config;
+use crate::doc::sections::DocKind;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prompt files searched before the built-in prompts; the default has
+/// none and gives the built-in prompts
+#[derive(Debug, Clone, Default)]
+pub struct PromptPack {
+    name: Option<String>,
+    dirs: Vec<PathBuf>,
+}
+
+/// Directory of the prompt overrides, `prompts/` in the config directory
+pub fn overrides_dir() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("prompts"))
+}
+
+/// Directory holding the named prompt packs
+pub fn packs_dir() -> Result<PathBuf> {
+    Ok(overrides_dir()?.join("packs"))
+}
+
+/// Names of the packs in [`packs_dir`]
+pub fn available_packs() -> Result<Vec<String>> {
+    let dir = packs_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut packs: Vec<String> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    packs.sort();
+    Ok(packs)
+}
+
+/// Write the built-in prompts to `dir`; existing files are kept unless
+/// `force`. Returns each file and whether it was written.
+pub fn export(dir: &Path, force: bool) -> Result<Vec<(PathBuf, bool)>> {
+    fs::create_dir_all(dir)?;
+    let mut files = Vec::new();
+    for name in PromptTemplates::NAMES {
+        let path = dir.join(format!("{}.md", name));
+        let write = force || !path.exists();
+        if write {
+            let prompt = PromptTemplates::builtin(name).unwrap_or_default();
+            fs::write(&path, format!("{}\n", prompt))?;
+        }
+        files.push((path, write));
+    }
+    Ok(files)
+}
+
+impl PromptPack {
+    /// Prompts found in `dirs`, in order
+    pub fn new(name: Option<String>, dirs: Vec<PathBuf>) -> Self {
+        Self { name, dirs }
+    }
+
+    /// The overrides of the config directory, behind `pack` when given: the
+    /// name of a pack in [`packs_dir`] or a directory path
+    pub fn load(pack: Option<&str>) -> Result<Self> {
+        let mut dirs = Vec::new();
+        if let Some(pack) = pack {
+            let dir = if pack.contains(['/', '\\']) || pack.starts_with('.') {
+                PathBuf::from(pack)
+            } else {
+                packs_dir()?.join(pack)
+            };
+            if !dir.is_dir() {
+                return Err(KtmeError::NotFound(format!(
+                    "Prompt pack '{}' not found at {}",
+                    pack,
+                    dir.display()
+                )));
+            }
+            dirs.push(dir);
+        }
+        dirs.push(overrides_dir()?);
+        Ok(Self::new(pack.map(str::to_string), dirs))
+    }
+
+    /// Name of the selected pack
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The file replacing prompt `name`
+    pub fn file(&self, name: &str) -> Option<PathBuf> {
+        self.dirs
+            .iter()
+            .map(|dir| dir.join(format!("{}.md", name)))
+            .find(|path| path.is_file())
+    }
+
+    /// Prompt `name`: its file, else the built-in prompt
+    pub fn prompt(&self, name: &str) -> Result<Option<String>> {
+        match self.file(name) {
+            Some(path) => Ok(Some(fs::read_to_string(path)?.trim_end().to_string())),
+            None => Ok(PromptTemplates::builtin(name)),
+        }
+    }
+
+    /// The `generate` prompt of `doc_type` with the additional `context`;
+    /// unknown types use `general`
+    pub fn documentation_prompt(
+        &self,
+        diff: &ExtractedDiff,
+        doc_type: &str,
+        context: Option<&str>,
+    ) -> Result<String> {
+        let base_prompt = match self.prompt(doc_type)? {
+            Some(prompt) if !doc_type.starts_with("update-") => prompt,
+            _ => self.prompt("general")?.unwrap_or_default(),
+        };
+        Ok(PromptTemplates::documentation_prompt(
+            &base_prompt,
+            diff,
+            context,
+        ))
+    }
+
+    /// The `update` prompt for `section` of a document of `kind`
+    pub fn update_prompt(
+        &self,
+        diff: &ExtractedDiff,
+        section: Option<&str>,
+        kind: DocKind,
+    ) -> Result<String> {
+        let base_prompt = self
+            .prompt(PromptTemplates::update_prompt_name(section, kind))?
+            .unwrap_or_default()
+            .replace("{{SECTION}}", section.unwrap_or_default());
+        Ok(PromptTemplates::update_prompt(&base_prompt, diff))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn diff() -> ExtractedDiff {
+        serde_json::from_str(
+            r#"{"source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+                "author": "dev", "message": "Add exports", "files": [],
+                "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pack_overrides_and_fallbacks() {
+        let overrides = TempDir::new().unwrap();
+        let pack = TempDir::new().unwrap();
+        fs::write(overrides.path().join("changelog.md"), "Team changelog\n").unwrap();
+        fs::write(overrides.path().join("general.md"), "Team general").unwrap();
+        fs::write(pack.path().join("changelog.md"), "SRE changelog").unwrap();
+        fs::write(pack.path().join("runbook.md"), "SRE runbook").unwrap();
+        fs::write(pack.path().join("update-section.md"), "Update {{SECTION}}").unwrap();
+
+        let prompts = PromptPack::new(
+            Some("sre".to_string()),
+            vec![pack.path().to_path_buf(), overrides.path().to_path_buf()],
+        );
+        let diff = diff();
+        let prompt = |doc_type| prompts.documentation_prompt(&diff, doc_type, None).unwrap();
+        assert!(prompt("changelog").starts_with("SRE changelog\n"));
+        assert!(prompt("runbook").starts_with("SRE runbook\n"));
+        assert!(prompt("unknown").starts_with("Team general\n"));
+        assert!(prompt("readme").starts_with(&PromptTemplates::builtin("readme").unwrap()));
+        assert!(prompts
+            .update_prompt(&diff, Some("Usage"), DocKind::General)
+            .unwrap()
+            .starts_with("Update Usage\n"));
+        assert!(prompts
+            .update_prompt(&diff, None, DocKind::Changelog)
+            .unwrap()
+            .starts_with(&PromptTemplates::builtin("update-changelog").unwrap()));
+    }
+
+    #[test]
+    fn test_export_keeps_edited_files() {
+        let dir = TempDir::new().unwrap();
+        let files = export(dir.path(), false).unwrap();
+        assert_eq!(files.len(), PromptTemplates::NAMES.len());
+        assert!(files.iter().all(|(_, written)| *written));
+
+        let changelog = dir.path().join("changelog.md");
+        fs::write(&changelog, "Edited").unwrap();
+        export(dir.path(), false).unwrap();
+        assert_eq!(fs::read_to_string(&changelog).unwrap(), "Edited");
+        export(dir.path(), true).unwrap();
+        assert_eq!(
+            fs::read_to_string(&changelog).unwrap().trim_end(),
+            PromptTemplates::builtin("changelog").unwrap()
+        );
+    }
+}
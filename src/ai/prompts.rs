@@ -1,34 +1,82 @@
-use crate::error::Result;
-use crate::git::diff::ExtractedDiff;
+use crate::doc::annotations;
+use crate::doc::sections::DocKind;
+use crate::git::diff::{CommitSummary, ExtractedDiff};
+
+/// Separates the instructions of a prompt from the diff content
+pub const CHANGES_HEADING: &str = "\n\nChanges:\n";
 
 pub struct PromptTemplates;
 
 impl PromptTemplates {
-    pub fn generate_documentation_prompt(
-        diff: &ExtractedDiff,
-        doc_type: &str,
-        context: Option<&str>,
-    ) -> Result<String> {
-        let base_prompt = match doc_type {
+    /// Names of the built-in prompts: one per document type of `generate`,
+    /// the prompts of `update` and the prompt of `ask`. `update-section` gets
+    /// the section name in `{{SECTION}}`.
+    pub const NAMES: &'static [&'static str] = &[
+        "changelog",
+        "api-doc",
+        "readme",
+        "commit-message",
+        "general",
+        "onboarding",
+        "postmortem",
+        "release-notes",
+        "security-review",
+        "test-plan",
+        "update-changelog",
+        "update-api-doc",
+        "update-general",
+        "update-section",
+        "answer",
+    ];
+
+    /// The built-in prompt `name`
+    pub fn builtin(name: &str) -> Option<String> {
+        let prompt = match name {
             "changelog" => Self::changelog_prompt(),
             "api-doc" => Self::api_doc_prompt(),
             "readme" => Self::readme_prompt(),
             "commit-message" => Self::commit_message_prompt(),
-            _ => Self::general_prompt(),
+            "general" => Self::general_prompt(),
+            "onboarding" => Self::onboarding_prompt(),
+            "postmortem" => Self::postmortem_prompt(),
+            "release-notes" => Self::release_notes_prompt(),
+            "security-review" => Self::security_review_prompt(),
+            "test-plan" => Self::test_plan_prompt(),
+            "update-changelog" => "You are updating a changelog. Based on the provided Git diff, write only the new entries as bullet points grouped under `### Added`, `### Changed` or `### Fixed` headings. Do not add a version heading; the entries are filed under [Unreleased].".to_string(),
+            "update-api-doc" => "You are updating API documentation. Based on the provided Git diff, write one `###` section per changed endpoint, titled with the method and path only (e.g. `### GET /users`), so each section replaces the matching endpoint section of the existing documentation.".to_string(),
+            "update-general" => "You are updating existing documentation. Based on the provided Git diff, generate appropriate update content that should be added to the documentation.".to_string(),
+            "update-section" => "You are updating the '{{SECTION}}' section of existing documentation. Based on the provided Git diff, generate appropriate update content for this section.".to_string(),
+            "answer" => "You answer questions about a codebase using only the numbered documentation excerpts below. Cite the excerpts each statement relies on as [1], [2], etc. If the excerpts do not contain the answer, say so instead of guessing. Answer concisely in Markdown.".to_string(),
+            _ => return None,
         };
+        Some(prompt)
+    }
 
-        let diff_summary = Self::format_diff_summary(diff);
+    /// `base_prompt` followed by the commit, pull request, developer
+    /// annotations and diff
+    pub fn documentation_prompt(
+        base_prompt: &str,
+        diff: &ExtractedDiff,
+        context: Option<&str>,
+    ) -> String {
+        let diff_summary = format!(
+            "{}{}{}",
+            Self::format_diff_summary(diff),
+            Self::format_pull_request_context(diff),
+            annotations::prompt_context(&annotations::collect(diff))
+        );
         let context_section = context
             .map(|c| format!("\nAdditional Context:\n{}\n", c))
             .unwrap_or_default();
 
-        Ok(format!(
-            "{}\n\n{}{}\n\nChanges:\n{}",
+        format!(
+            "{}\n\n{}{}{}{}",
             base_prompt,
             context_section,
             diff_summary,
+            CHANGES_HEADING,
             Self::format_diff_content(diff)
-        ))
+        )
     }
 
     fn changelog_prompt() -> String {
@@ -180,6 +228,163 @@ Requirements:
 Generate documentation in Markdown format."#.to_string()
     }
 
+    fn onboarding_prompt() -> String {
+        r#"You are a senior engineer writing an onboarding guide for a new team member joining this service. Use the additional context (setup commands, key modules, maintainers and existing documentation) and the latest changes to write a guide following this format:
+
+# Onboarding Guide
+
+## What this service does
+[Purpose and main responsibilities]
+
+## Getting set up
+1. [Step-by-step setup, using the setup commands found in the repository]
+
+## Key modules
+- `path/to/module` - [What it is responsible for]
+
+## Who to ask
+- [Maintainers and what they know best]
+
+## Further reading
+- [Links to the existing documentation]
+
+## Recent changes
+[What the latest changes mean for someone new to the code]
+
+Guidelines:
+- Write for an engineer who has never seen the code
+- Only use commands that appear in the context; do not invent setup steps
+- Prefer the order a newcomer would follow: set up, build, test, run
+- Keep each module description to one sentence"#.to_string()
+    }
+
+    fn postmortem_prompt() -> String {
+        r#"You are a site reliability engineer drafting an incident postmortem. The changes span from the start of the incident to the commit that fixed it; the additional context holds the commit timeline and the fix. Write a blameless draft following this format:
+
+# Postmortem: [Short incident title]
+
+**Status**: Draft
+**Incident window**: [From the timeline]
+
+## Summary
+[Two or three sentences on what happened and how it was fixed]
+
+## Impact
+_To be completed: affected users, services and duration._
+[What the changes suggest was affected]
+
+## Timeline
+| Time (UTC) | Event |
+|------------|-------|
+| [time] | [commit or event] |
+
+## Root Cause
+[The most likely cause, based on the changes]
+
+## Remediation
+[What the fix commit changed and why it resolves the issue]
+
+## Follow-ups
+- [ ] [Action item] — _Owner: to be assigned_
+
+## Detection and Response
+_To be completed: how the incident was detected and escalated._
+
+Guidelines:
+- Keep the tone blameless; describe systems and changes, not people
+- Build the timeline from the commits, oldest first, and mark the fix
+- Say when a conclusion is uncertain instead of guessing
+- Leave the "To be completed" notes for the on-call team to fill in"#.to_string()
+    }
+
+    fn release_notes_prompt() -> String {
+        r#"You are writing release notes. The additional context names the audience and lists the changes grouped by conventional-commit type; rewrite them for that audience following this format:
+
+## [Version] - [Date]
+
+### Highlights
+[One or two sentences on the most important changes]
+
+### Breaking changes
+- [What changed and what readers must do]
+
+### New features
+- [Feature, described by what it lets readers do]
+
+### Fixes
+- [Fixed problem, described by its symptom]
+
+Guidelines:
+- Only include changes listed in the context; omit empty sections
+- Rephrase commit messages into complete sentences for the audience
+- For customers, avoid internal jargon, file names and commit ids
+- For the team, keep scopes and commit ids and add a "Migration steps" section when the context lists any"#.to_string()
+    }
+
+    fn security_review_prompt() -> String {
+        r#"You are an application security engineer preparing a change for security sign-off. The additional context lists the changed lines touching authentication and authorization, new endpoints, input handling, secrets and cryptography, dependencies, and dependency audit results when available. Write a checklist-style review following this format:
+
+# Security Review: [Short title of the change]
+
+**Risk level**: [Low/Medium/High, with one sentence of justification]
+
+## Summary
+[What the change does, from a security point of view]
+
+## Authentication and Authorization
+- [ ] [Check, e.g. "New /admin route requires the admin role"] — [finding]
+
+## New Endpoints
+- [ ] [Endpoint and the access control, rate limiting and input checks it needs]
+
+## Input Validation
+- [ ] [Input, where it is validated, and injection risks]
+
+## Secrets and Cryptography
+- [ ] [Secret or crypto use and whether it is handled safely]
+
+## Dependencies
+- [ ] [Added or upgraded dependency and known vulnerabilities]
+
+## Sign-off
+- [ ] Reviewed by: _to be completed_
+
+Guidelines:
+- Make every item a checkbox a reviewer can tick; mark items that need attention with **Action required**
+- Base findings on the diff; the context lists keyword matches that may be false positives
+- Write "No changes" under sections the change does not touch
+- List every vulnerability reported by an audit under Dependencies"#.to_string()
+    }
+
+    fn test_plan_prompt() -> String {
+        r#"You are a QA engineer writing the test plan for a change. The additional context pairs the changed source files with their test changes and lists the test cases the change adds, modifies or removes. Write the plan following this format:
+
+# Test Plan: [Short title of the change]
+
+## Test Summary
+[Source files changed, how many come with test changes, and the test cases added, modified and removed]
+
+## Test Cases
+| Test | Change | File |
+|------|--------|------|
+| [name] | [added/modified/removed] | [file] |
+
+## Coverage Gaps
+- [Changed source file without test changes and the behavior left untested]
+
+## Manual Verification
+1. [Step to verify behavior not covered by automated tests]
+
+## Risks
+- [What could break and how it would show]
+
+Guidelines:
+- Only list test cases named in the context
+- When the context warns that code changed without test changes, open the Test Summary with that warning
+- Suggest concrete tests for each coverage gap
+- Omit the Manual Verification section when the automated tests cover every change"#.to_string()
+    }
+
     fn format_diff_summary(diff: &ExtractedDiff) -> String {
         format!(
             "Commit: {}\nAuthor: {}\nTimestamp: {}\nMessage: {}\nFiles changed: {} (+{}/-{})",
@@ -193,36 +398,176 @@ Generate documentation in Markdown format."#.to_string()
         )
     }
 
+    /// Pull request description, labels, linked issues and review discussion;
+    /// empty when the diff did not come from a PR
+    pub fn format_pull_request_context(diff: &ExtractedDiff) -> String {
+        let Some(pr) = &diff.pull_request else {
+            return String::new();
+        };
+
+        let mut context = format!("\n\nPull Request #{}: {}", pr.number, pr.title);
+        if !pr.description.trim().is_empty() {
+            context.push_str(&format!("\nDescription:\n{}", pr.description.trim()));
+        }
+        if !pr.labels.is_empty() {
+            context.push_str(&format!("\nLabels: {}", pr.labels.join(", ")));
+        }
+        if !pr.linked_issues.is_empty() {
+            context.push_str(&format!("\nLinked issues: {}", pr.linked_issues.join(", ")));
+        }
+        if !pr.review_comments.is_empty() {
+            context.push_str("\nReview comments:");
+            context.push_str(&Self::format_review_comments(diff));
+        }
+
+        context
+    }
+
+    /// Review comments as a bullet list, one per line
+    pub fn format_review_comments(diff: &ExtractedDiff) -> String {
+        diff.pull_request
+            .iter()
+            .flat_map(|pr| &pr.review_comments)
+            .map(|c| match &c.path {
+                Some(path) => format!("\n- {} on {}: {}", c.author, path, c.body.trim()),
+                None => format!("\n- {}: {}", c.author, c.body.trim()),
+            })
+            .collect()
+    }
+
+    /// The changed files with their diffs; the files of a multi-repository
+    /// diff are grouped in a section per repository
     pub fn format_diff_content(diff: &ExtractedDiff) -> String {
-        let mut content = String::new();
+        let format_file = |file: &crate::git::diff::FileChange| {
+            format!(
+                "\n## File: {} ({})\n```\n{}\n```\n",
+                file.display_path(),
+                file.status,
+                file.diff
+            )
+        };
+        if diff.repositories.is_empty() {
+            return diff.files.iter().map(format_file).collect();
+        }
 
-        for file in &diff.files {
+        let mut content = format!(
+            "\nThe changes span {} repositories; document each in its own section.\n",
+            diff.repositories.len()
+        );
+        for repository in &diff.repositories {
             content.push_str(&format!(
-                "\n## File: {} ({})\n```\n{}\n```\n",
-                file.path, file.status, file.diff
+                "\n# Repository: {} ({})\n{}\n",
+                repository.name,
+                repository.identifier,
+                repository.message.trim()
             ));
+            for file in diff.files.iter().filter(|f| repository.contains(&f.path)) {
+                content.push_str(&format_file(file));
+            }
         }
-
         content
     }
 
-    pub fn update_documentation_prompt(
-        diff: &ExtractedDiff,
-        section: Option<&str>,
-    ) -> Result<String> {
-        let base_prompt = if let Some(sec) = section {
-            format!("You are updating the '{}' section of existing documentation. Based on the provided Git diff, generate appropriate update content for this section.", sec)
-        } else {
-            "You are updating existing documentation. Based on the provided Git diff, generate appropriate update content that should be added to the documentation.".to_string()
-        };
+    /// Prompt for a short narrative of the commits of a combined range,
+    /// grouped by the area of the codebase they touch; the diffs are left out
+    pub fn commit_history_prompt(diff: &ExtractedDiff) -> String {
+        let mut prompt = format!(
+            "Summarize the {} commits of {} as a short narrative for standup notes or a pull request description. \
+            Write one `###` heading per area of the codebase below, with a few sentences on what changed there and why. \
+            Do not write full documentation, code or diffs.\n",
+            diff.commits.len(),
+            diff.identifier
+        );
+        for (area, commits) in Self::commit_areas(diff) {
+            prompt.push_str(&format!("\nArea: {}\n", area));
+            for commit in commits {
+                prompt.push_str(&format!(
+                    "- {} {} ({}, {})\n",
+                    commit.short_id(),
+                    commit.subject(),
+                    commit.author,
+                    commit.timestamp
+                ));
+            }
+        }
+        prompt
+    }
 
-        let diff_summary = Self::format_diff_summary(diff);
+    /// Commits of a combined range by area, the directory of their changed
+    /// files at most two levels deep; a commit touching several areas is
+    /// listed under each
+    pub fn commit_areas(diff: &ExtractedDiff) -> Vec<(String, Vec<&CommitSummary>)> {
+        let mut areas: Vec<(String, Vec<&CommitSummary>)> = Vec::new();
+        for commit in &diff.commits {
+            let mut names: Vec<String> = commit.files.iter().map(|file| area(file)).collect();
+            if names.is_empty() {
+                names.push("(no files)".to_string());
+            }
+            for name in names {
+                match areas.iter_mut().find(|(area, _)| *area == name) {
+                    Some((_, commits)) if commits.last() != Some(&commit) => commits.push(commit),
+                    Some(_) => {}
+                    None => areas.push((name, vec![commit])),
+                }
+            }
+        }
+        areas
+    }
+
+    /// `prompt` asking for the documentation to be written in `language`
+    pub fn in_language(prompt: String, language: Option<&str>) -> String {
+        match language {
+            Some(language) => format!("{}\n\nWrite the documentation in {}.", prompt, language),
+            None => prompt,
+        }
+    }
 
-        Ok(format!(
-            "{}\n\n{}\n\nChanges:\n{}",
+    /// Prompt asking for the Markdown `text` to be translated into `language`
+    pub fn translation_prompt(text: &str, language: &str) -> String {
+        format!(
+            "Translate the following Markdown documentation into {}. Keep its \
+             Markdown structure, links, code blocks, identifiers and product names \
+             unchanged. Reply with the translation only.\n\n{}",
+            language, text
+        )
+    }
+
+    /// Name of the `update` prompt for `section` of a document of `kind`
+    pub fn update_prompt_name(section: Option<&str>, kind: DocKind) -> &'static str {
+        match (section, kind) {
+            (Some(_), _) => "update-section",
+            (None, DocKind::Changelog) => "update-changelog",
+            (None, DocKind::ApiDoc) => "update-api-doc",
+            (None, DocKind::General) => "update-general",
+        }
+    }
+
+    /// `base_prompt` of an update followed by the commit, pull request,
+    /// developer annotations and diff
+    pub fn update_prompt(base_prompt: &str, diff: &ExtractedDiff) -> String {
+        let diff_summary = format!(
+            "{}{}{}",
+            Self::format_diff_summary(diff),
+            Self::format_pull_request_context(diff),
+            annotations::prompt_context(&annotations::collect(diff))
+        );
+
+        format!(
+            "{}\n\n{}{}{}",
             base_prompt,
             diff_summary,
+            CHANGES_HEADING,
             Self::format_diff_content(diff)
-        ))
+        )
+    }
+}
+
+/// Area of the codebase of `path`: its directory, at most two levels deep
+fn area(path: &str) -> String {
+    let dirs: Vec<&str> = path.split('/').collect();
+    match dirs.len() {
+        1 => "(root)".to_string(),
+        2 => dirs[0].to_string(),
+        _ => dirs[..2].join("/"),
     }
 }
@@ -1,6 +1,12 @@
+use crate::config::AiConfig;
 use crate::error::Result;
+use crate::plugins::Plugin;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// File name prefix of AI provider plugins
+pub const PLUGIN_PREFIX: &str = "ktme-ai-";
 
 /// Configuration for OpenAI API
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +19,9 @@ pub struct OpenAIConfig {
     #[serde(default = "default_temperature")]
     pub temperature: f32,
     pub base_url: Option<String>,
+    /// Extra headers for OpenAI-compatible gateways
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
 }
 
 /// Configuration for Anthropic Claude API
@@ -31,7 +40,12 @@ pub struct ClaudeConfig {
 #[async_trait]
 pub trait AIProvider: Send + Sync {
     async fn generate(&self, prompt: &str) -> Result<String>;
-    fn provider_name(&self) -> &'static str;
+    fn provider_name(&self) -> &str;
+
+    /// Model answering the prompts, for providers that have one
+    fn model(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// Factory for creating AI providers
@@ -49,6 +63,20 @@ impl AIProviderFactory {
     pub fn create_mock() -> Result<Box<dyn AIProvider>> {
         Ok(Box::new(MockProvider::new()))
     }
+
+    /// Provider served by the `ktme-ai-<name>` plugin on PATH
+    pub fn create_external(name: &str, config: &AiConfig) -> Result<Box<dyn AIProvider>> {
+        let plugin = crate::plugins::find(PLUGIN_PREFIX, name).ok_or_else(|| {
+            crate::error::KtmeError::UnsupportedProvider(format!(
+                "AI provider '{}' is not supported and no {}{} plugin was found on PATH",
+                name, PLUGIN_PREFIX, name
+            ))
+        })?;
+        Ok(Box::new(ExternalAIProvider::new(
+            plugin,
+            serde_json::to_value(config)?,
+        )))
+    }
 }
 
 // OpenAI Provider Implementation
@@ -59,7 +87,7 @@ pub struct OpenAIProvider {
 
 impl OpenAIProvider {
     pub fn new(config: OpenAIConfig) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("ai")
             .user_agent("ktme/1.0")
             .build()
             .expect("Failed to create HTTP client");
@@ -89,28 +117,48 @@ impl AIProvider for OpenAIProvider {
             "temperature": self.config.temperature
         });
 
-        let response = self
+        let mut request = self
             .client
             .post(&format!("{}/chat/completions", base_url))
-            .header("Authorization", format!("Bearer {}", self.config.api_key))
-            .header("Content-Type", "application/json")
+            .header("Content-Type", "application/json");
+        // Gateways may authenticate through custom headers instead of a key
+        if !self.config.api_key.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", self.config.api_key));
+        }
+        for (name, value) in &self.config.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        let response = request
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| crate::error::KtmeError::NetworkError(e.to_string()))?;
+            .map_err(|e| crate::http::request_error("OpenAI request failed", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(crate::error::KtmeError::ApiError(format!(
-                "OpenAI API error: {} - {}",
-                status, error_text
-            )));
+            return Err(crate::error::KtmeError::from_status(
+                "OpenAI",
+                status,
+                crate::error::KtmeError::ApiError(format!(
+                    "OpenAI API error: {} - {}",
+                    status, error_text
+                )),
+            ));
         }
 
         #[derive(Deserialize)]
         struct OpenAIResponse {
             choices: Vec<OpenAIChoice>,
+            #[serde(default)]
+            usage: Option<OpenAIUsage>,
+        }
+
+        #[derive(Deserialize)]
+        struct OpenAIUsage {
+            prompt_tokens: u64,
+            completion_tokens: u64,
         }
 
         #[derive(Deserialize)]
@@ -128,6 +176,14 @@ impl AIProvider for OpenAIProvider {
             .await
             .map_err(|e| crate::error::KtmeError::DeserializationError(e.to_string()))?;
 
+        if let Some(usage) = &openai_response.usage {
+            crate::metrics::record_ai_tokens(
+                self.provider_name(),
+                usage.prompt_tokens,
+                usage.completion_tokens,
+            );
+        }
+
         openai_response
             .choices
             .into_iter()
@@ -136,9 +192,13 @@ impl AIProvider for OpenAIProvider {
             .map(|choice| choice.message.content)
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn provider_name(&self) -> &str {
         "OpenAI"
     }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.config.model)
+    }
 }
 
 // Claude Provider Implementation
@@ -149,7 +209,7 @@ pub struct ClaudeProvider {
 
 impl ClaudeProvider {
     pub fn new(config: ClaudeConfig) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("ai")
             .user_agent("ktme/1.0")
             .build()
             .expect("Failed to create HTTP client");
@@ -182,20 +242,32 @@ impl AIProvider for ClaudeProvider {
             .json(&request_body)
             .send()
             .await
-            .map_err(|e| crate::error::KtmeError::NetworkError(e.to_string()))?;
+            .map_err(|e| crate::http::request_error("Claude request failed", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(crate::error::KtmeError::ApiError(format!(
-                "Claude API error: {} - {}",
-                status, error_text
-            )));
+            return Err(crate::error::KtmeError::from_status(
+                "Claude",
+                status,
+                crate::error::KtmeError::ApiError(format!(
+                    "Claude API error: {} - {}",
+                    status, error_text
+                )),
+            ));
         }
 
         #[derive(Deserialize)]
         struct ClaudeResponse {
             content: Vec<ClaudeContent>,
+            #[serde(default)]
+            usage: Option<ClaudeUsage>,
+        }
+
+        #[derive(Deserialize)]
+        struct ClaudeUsage {
+            input_tokens: u64,
+            output_tokens: u64,
         }
 
         #[derive(Deserialize)]
@@ -210,6 +282,14 @@ impl AIProvider for ClaudeProvider {
             .await
             .map_err(|e| crate::error::KtmeError::DeserializationError(e.to_string()))?;
 
+        if let Some(usage) = &claude_response.usage {
+            crate::metrics::record_ai_tokens(
+                self.provider_name(),
+                usage.input_tokens,
+                usage.output_tokens,
+            );
+        }
+
         claude_response
             .content
             .into_iter()
@@ -220,9 +300,149 @@ impl AIProvider for ClaudeProvider {
             })
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn provider_name(&self) -> &str {
         "Claude"
     }
+
+    fn model(&self) -> Option<&str> {
+        Some(&self.config.model)
+    }
+}
+
+// Provider implemented by a `ktme-ai-<name>` plugin: the `generate`
+// operation receives `{"prompt": ...}` and replies with the generated text
+pub struct ExternalAIProvider {
+    plugin: Plugin,
+    config: serde_json::Value,
+}
+
+impl ExternalAIProvider {
+    pub fn new(plugin: Plugin, config: serde_json::Value) -> Self {
+        Self { plugin, config }
+    }
+}
+
+#[async_trait]
+impl AIProvider for ExternalAIProvider {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        self.plugin
+            .invoke(
+                "generate",
+                &self.config,
+                serde_json::json!({ "prompt": prompt }),
+            )
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.plugin.name
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.config.get("model").and_then(|model| model.as_str())
+    }
+}
+
+/// Providers tried in order: a failed or timed-out request is sent to the
+/// next one, and the error of the last one is returned
+pub struct FallbackProvider {
+    providers: Vec<Box<dyn AIProvider>>,
+}
+
+impl FallbackProvider {
+    pub fn new(providers: Vec<Box<dyn AIProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+#[async_trait]
+impl AIProvider for FallbackProvider {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let mut last_error = None;
+        for provider in &self.providers {
+            match provider.generate(prompt).await {
+                Ok(response) => return Ok(response),
+                Err(e) => {
+                    tracing::warn!(
+                        "AI provider {} failed, trying the next one: {}",
+                        provider.provider_name(),
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            crate::error::KtmeError::Config("No AI provider in the fallback chain".to_string())
+        }))
+    }
+
+    fn provider_name(&self) -> &str {
+        self.providers
+            .first()
+            .map(|provider| provider.provider_name())
+            .unwrap_or("Fallback")
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.providers.first().and_then(|provider| provider.model())
+    }
+}
+
+/// Two-stage generation: `summarizer`, usually a cheap model, condenses the
+/// changes of the prompt into notes, and `writer` writes the documentation
+/// from the instructions and those notes. Prompts without a
+/// [`CHANGES_HEADING`] (custom templates) go to `writer` unchanged.
+///
+/// [`CHANGES_HEADING`]: crate::ai::prompts::CHANGES_HEADING
+pub struct SummarizingProvider {
+    summarizer: Box<dyn AIProvider>,
+    writer: Box<dyn AIProvider>,
+}
+
+impl SummarizingProvider {
+    pub fn new(summarizer: Box<dyn AIProvider>, writer: Box<dyn AIProvider>) -> Self {
+        Self { summarizer, writer }
+    }
+}
+
+#[async_trait]
+impl AIProvider for SummarizingProvider {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        let Some((instructions, changes)) = prompt.split_once(crate::ai::prompts::CHANGES_HEADING)
+        else {
+            return self.writer.generate(prompt).await;
+        };
+
+        tracing::info!(
+            "Summarizing the changes with {}",
+            self.summarizer.provider_name()
+        );
+        let notes = self
+            .summarizer
+            .generate(&format!(
+                "Summarize the following code changes as concise, factual notes for a \
+                 technical writer: what changed, where, and why it matters. Keep names of \
+                 files, functions, endpoints and settings.\n\nChanges:\n{}",
+                changes
+            ))
+            .await?;
+
+        self.writer
+            .generate(&format!(
+                "{}\n\nSummary of the changes:\n{}",
+                instructions, notes
+            ))
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.writer.provider_name()
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.writer.model()
+    }
 }
 
 // Mock Provider for testing without API keys
@@ -364,7 +584,7 @@ Generated from git diff analysis using ktme's automated documentation system.
         Ok(documentation)
     }
 
-    fn provider_name(&self) -> &'static str {
+    fn provider_name(&self) -> &str {
         "Mock"
     }
 }
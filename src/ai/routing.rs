@@ -0,0 +1,146 @@
+//! Model selection by document type and prompt size
+//!
+//! `[[ai.routing.rules]]` send small diffs to a cheap, fast model and large
+//! or architectural documents to a stronger one:
+//!
+//! ```toml
+//! [[ai.routing.rules]]
+//! max_tokens = 2000
+//! model = "gpt-4o-mini"
+//!
+//! [[ai.routing.rules]]
+//! doc_types = ["api-doc", "readme"]
+//! model = "gpt-4o"
+//! ```
+//!
+//! Rules are checked in order and the first match wins. A model other than
+//! `[ai] model` set for the service or with `--model` is used as is.
+
+use crate::ai::AIClient;
+use crate::config::{AiConfig, RoutingRule};
+use crate::error::Result;
+
+/// Rough size of `text` in tokens, about four bytes per token
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+impl RoutingRule {
+    /// Whether the rule applies to a `doc_type` prompt of `tokens`
+    pub fn matches(&self, doc_type: &str, tokens: usize) -> bool {
+        (self.doc_types.is_empty() || self.doc_types.iter().any(|t| t == doc_type))
+            && self.min_tokens.is_none_or(|min| tokens >= min)
+            && self.max_tokens.is_none_or(|max| tokens <= max)
+    }
+}
+
+/// The first rule of `config` matching `prompt` for `doc_type`
+pub fn route<'a>(config: &'a AiConfig, doc_type: &str, prompt: &str) -> Option<&'a RoutingRule> {
+    let tokens = estimate_tokens(prompt);
+    let rule = config
+        .routing
+        .rules
+        .iter()
+        .find(|rule| rule.matches(doc_type, tokens))?;
+    tracing::info!(
+        "Routing {} prompt of ~{} tokens to model {}",
+        doc_type,
+        tokens,
+        rule.model
+    );
+    Some(rule)
+}
+
+/// Client for the model and provider of `rule`
+pub fn client_for(config: &AiConfig, rule: &RoutingRule) -> Result<AIClient> {
+    AIClient::from_config(&AiConfig {
+        provider: rule.provider.clone().or_else(|| config.provider.clone()),
+        model: Some(rule.model.clone()),
+        ..config.clone()
+    })
+}
+
+/// Client for the routed model of `prompt`, unless the service or
+/// `--model` chose one (`explicit`)
+pub fn routed_client(
+    config: &AiConfig,
+    explicit: bool,
+    doc_type: &str,
+    prompt: &str,
+) -> Result<Option<AIClient>> {
+    if explicit {
+        return Ok(None);
+    }
+    route(config, doc_type, prompt)
+        .map(|rule| client_for(config, rule))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        doc_types: &[&str],
+        min: Option<usize>,
+        max: Option<usize>,
+        model: &str,
+    ) -> RoutingRule {
+        RoutingRule {
+            doc_types: doc_types.iter().map(|t| t.to_string()).collect(),
+            min_tokens: min,
+            max_tokens: max,
+            provider: None,
+            model: model.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let config = AiConfig {
+            routing: crate::config::RoutingConfig {
+                rules: vec![
+                    rule(&[], None, Some(100), "small"),
+                    rule(&["api-doc", "readme"], None, None, "strong"),
+                    rule(&[], Some(10_000), None, "large"),
+                ],
+            },
+            ..Default::default()
+        };
+        let model = |doc_type, prompt: &str| route(&config, doc_type, prompt).map(|r| &r.model);
+
+        assert_eq!(model("changelog", "short"), Some(&"small".to_string()));
+        assert_eq!(
+            model("api-doc", &"x".repeat(2_000)),
+            Some(&"strong".to_string())
+        );
+        assert_eq!(model("general", &"x".repeat(2_000)), None);
+        assert_eq!(
+            model("general", &"x".repeat(40_000)),
+            Some(&"large".to_string())
+        );
+        assert!(route(&AiConfig::default(), "general", "short").is_none());
+    }
+
+    #[test]
+    fn test_routed_client_uses_rule_model() {
+        let config = AiConfig {
+            provider: Some("mock".to_string()),
+            routing: crate::config::RoutingConfig {
+                rules: vec![RoutingRule {
+                    provider: Some("openai".to_string()),
+                    ..rule(&[], None, None, "gpt-4o-mini")
+                }],
+            },
+            ..Default::default()
+        };
+
+        let client = routed_client(&config, false, "general", "prompt")
+            .unwrap()
+            .unwrap();
+        assert_eq!(client.model(), Some("gpt-4o-mini"));
+        assert!(routed_client(&config, true, "general", "prompt")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,230 @@
+//! Prompt template files
+//!
+//! A template is a prompt with `{{NAME}}` placeholders. It may start with a
+//! YAML front matter block declaring its variables:
+//!
+//! ```text
+//! ---
+//! description: Release notes for customers
+//! variables:
+//!   - name: AUDIENCE
+//!     description: Who reads the notes
+//!     required: true
+//!   - name: TONE
+//!     default: friendly
+//! ---
+//! Write release notes for {{AUDIENCE}} in a {{TONE}} tone. ...
+//! ```
+//!
+//! Rendering fills in the values from the diff (see [`diff_variables`]) and
+//! from `--var NAME=VALUE`, applies the declared defaults and fails when a
+//! required variable has no value.
+
+use crate::ai::prompts::PromptTemplates;
+use crate::doc::annotations;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use crate::git::ownership::Ownership;
+use crate::storage::models::{PromptTemplate, PromptVariable};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Front matter of a template file
+#[derive(Debug, Default, Deserialize)]
+struct FrontMatter {
+    description: Option<String>,
+    output_format: Option<String>,
+    #[serde(default)]
+    variables: Vec<PromptVariable>,
+}
+
+impl PromptTemplate {
+    /// Template read from `path`, named after the file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::parse(&name, &content)
+    }
+
+    /// Template `name` from its text, with optional front matter
+    pub fn parse(name: &str, content: &str) -> Result<Self> {
+        let (front_matter, template) = match split_front_matter(content) {
+            Some((yaml, body)) => {
+                let front_matter: FrontMatter = serde_yaml::from_str(yaml).map_err(|e| {
+                    KtmeError::InvalidInput(format!(
+                        "Invalid front matter in prompt template '{}': {}",
+                        name, e
+                    ))
+                })?;
+                (front_matter, body)
+            }
+            None => (FrontMatter::default(), content),
+        };
+
+        let now = chrono::Utc::now();
+        Ok(Self {
+            id: 0,
+            name: name.to_string(),
+            description: front_matter.description,
+            template: template.to_string(),
+            variables: front_matter.variables,
+            output_format: front_matter
+                .output_format
+                .unwrap_or_else(|| "markdown".to_string()),
+            is_builtin: false,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Substitute `values` and the declared defaults. Every required variable
+    /// without a value is reported in one error; placeholders the template
+    /// does not declare and no value is given for are left untouched.
+    pub fn render(&self, values: &HashMap<String, String>) -> Result<String> {
+        let missing: Vec<&str> = self
+            .variables
+            .iter()
+            .filter(|v| v.required && v.default.is_none() && !values.contains_key(&v.name))
+            .map(|v| v.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(KtmeError::InvalidInput(format!(
+                "Prompt template '{}' is missing required variables: {} (pass them with --var NAME=VALUE)",
+                self.name,
+                missing.join(", ")
+            )));
+        }
+
+        let mut rendered = self.template.clone();
+        for (name, value) in values {
+            rendered = rendered.replace(&format!("{{{{{}}}}}", name), value);
+        }
+        for variable in &self.variables {
+            if !values.contains_key(&variable.name) {
+                rendered = rendered.replace(
+                    &format!("{{{{{}}}}}", variable.name),
+                    variable.default.as_deref().unwrap_or_default(),
+                );
+            }
+        }
+
+        Ok(rendered)
+    }
+}
+
+/// `---` delimited YAML at the start of `content`, and the rest
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let end = rest.find("\n---")?;
+    let body = &rest[end + 4..];
+    let body = body
+        .strip_prefix("\r\n")
+        .or_else(|| body.strip_prefix('\n'))
+        .unwrap_or(body);
+    Some((&rest[..end], body))
+}
+
+/// Values every template can use, taken from the diff
+pub fn diff_variables(diff: &ExtractedDiff, ownership: &Ownership) -> HashMap<String, String> {
+    // Pull request placeholders render empty for non-PR sources
+    let pr = diff.pull_request.clone().unwrap_or_default();
+
+    [
+        ("SERVICE", diff.source.clone()),
+        ("AUTHOR", diff.author.clone()),
+        ("MESSAGE", diff.message.clone()),
+        ("TIMESTAMP", diff.timestamp.clone()),
+        ("FILES_CHANGED", diff.summary.total_files.to_string()),
+        ("ADDITIONS", diff.summary.total_additions.to_string()),
+        ("DELETIONS", diff.summary.total_deletions.to_string()),
+        ("PR_TITLE", pr.title),
+        ("PR_DESCRIPTION", pr.description),
+        ("PR_URL", pr.url),
+        ("PR_LABELS", pr.labels.join(", ")),
+        ("LINKED_ISSUES", pr.linked_issues.join(", ")),
+        (
+            "REVIEW_COMMENTS",
+            PromptTemplates::format_review_comments(diff)
+                .trim_start()
+                .to_string(),
+        ),
+        (
+            "DEVELOPER_NOTES",
+            annotations::render_list(&annotations::collect(diff))
+                .trim_end()
+                .to_string(),
+        ),
+        (
+            "PRIMARY_CONTACTS",
+            ownership.render_list().trim_end().to_string(),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// Parse `--var NAME=VALUE` assignments
+pub fn parse_variables(assignments: &[String]) -> Result<HashMap<String, String>> {
+    assignments
+        .iter()
+        .map(|assignment| {
+            let (name, value) = assignment.split_once('=').ok_or_else(|| {
+                KtmeError::InvalidInput(format!("Expected NAME=VALUE, got '{}'", assignment))
+            })?;
+            Ok((name.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "---\ndescription: Release notes\nvariables:\n  - name: AUDIENCE\n    description: Who reads the notes\n    required: true\n  - name: TONE\n    default: friendly\n  - name: EXTRA\n---\nWrite for {{AUDIENCE}} in a {{TONE}} tone by {{AUTHOR}}.{{EXTRA}} {{UNKNOWN}}\n";
+
+    #[test]
+    fn test_render_validates_and_applies_defaults() {
+        let template = PromptTemplate::parse("release-notes", TEMPLATE).unwrap();
+        assert_eq!(template.description.as_deref(), Some("Release notes"));
+        assert_eq!(template.variables.len(), 3);
+        assert!(template.template.starts_with("Write for"));
+
+        let values = HashMap::from([("AUTHOR".to_string(), "dev".to_string())]);
+        match template.render(&values) {
+            Err(KtmeError::InvalidInput(msg)) => {
+                assert!(msg.contains("missing required variables: AUDIENCE"))
+            }
+            other => panic!("Expected a missing variable error, got {:?}", other),
+        }
+
+        let mut values = values;
+        values.insert("AUDIENCE".to_string(), "customers".to_string());
+        assert_eq!(
+            template.render(&values).unwrap(),
+            "Write for customers in a friendly tone by dev. {{UNKNOWN}}\n"
+        );
+    }
+
+    #[test]
+    fn test_templates_without_front_matter() {
+        let template = PromptTemplate::parse("plain", "Summarize {{MESSAGE}}").unwrap();
+        assert!(template.variables.is_empty());
+        let values = HashMap::from([("MESSAGE".to_string(), "Fix login".to_string())]);
+        assert_eq!(template.render(&values).unwrap(), "Summarize Fix login");
+        assert!(PromptTemplate::parse("broken", "---\nvariables: [\n---\nx").is_err());
+    }
+
+    #[test]
+    fn test_parse_variables() {
+        let values = parse_variables(&["AUDIENCE=ops team".to_string()]).unwrap();
+        assert_eq!(values["AUDIENCE"], "ops team");
+        assert!(parse_variables(&["AUDIENCE".to_string()]).is_err());
+    }
+}
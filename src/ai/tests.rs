@@ -1,8 +1,16 @@
 #[cfg(test)]
 mod tests {
     use crate::ai::client::AIClient;
-    use crate::ai::providers::{AIProvider, ClaudeConfig, OpenAIConfig, OpenAIProvider};
+    use crate::ai::prompt_pack::PromptPack;
+    use crate::ai::prompts::PromptTemplates;
+    use crate::ai::providers::{
+        AIProvider, ClaudeConfig, FallbackProvider, MockProvider, OpenAIConfig, OpenAIProvider,
+        SummarizingProvider,
+    };
+    use crate::config::AiConfig;
     use crate::error::KtmeError;
+    use crate::git::diff::{ExtractedDiff, FileChange, PullRequestMetadata, ReviewComment};
+    use std::collections::HashMap;
 
     #[test]
     fn test_ai_client_fails_without_key() {
@@ -26,6 +34,7 @@ mod tests {
             max_tokens: 2048,
             temperature: 0.5,
             base_url: None,
+            headers: HashMap::new(),
         };
 
         assert_eq!(config.api_key, "test-key");
@@ -58,6 +67,7 @@ mod tests {
             max_tokens: 2048,
             temperature: 0.5,
             base_url: None,
+            headers: HashMap::new(),
         };
 
         let provider = OpenAIProvider::new(config);
@@ -72,6 +82,7 @@ mod tests {
             max_tokens: 100,
             temperature: 0.5,
             base_url: Some("http://localhost:9999".to_string()), // Invalid URL
+            headers: HashMap::new(),
         };
 
         let provider = OpenAIProvider::new(config);
@@ -80,4 +91,303 @@ mod tests {
         // Should fail with network error
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_prompt_includes_pull_request_context() {
+        let mut diff = ExtractedDiff {
+            identifier: "#7".to_string(),
+            message: "Add retries".to_string(),
+            ..Default::default()
+        };
+        assert!(PromptTemplates::format_pull_request_context(&diff).is_empty());
+
+        diff.pull_request = Some(PullRequestMetadata {
+            number: 7,
+            title: "Add retries".to_string(),
+            description: "Retry uploads so flaky networks do not lose docs.".to_string(),
+            labels: vec!["enhancement".to_string()],
+            linked_issues: vec!["#12".to_string()],
+            review_comments: vec![ReviewComment {
+                author: "reviewer".to_string(),
+                path: Some("src/upload.rs".to_string()),
+                body: "Cap the backoff".to_string(),
+            }],
+            ..Default::default()
+        });
+
+        let prompt = PromptPack::default()
+            .documentation_prompt(&diff, "changelog", None)
+            .unwrap();
+        assert!(prompt.contains("Pull Request #7: Add retries"));
+        assert!(prompt.contains("Retry uploads so flaky networks"));
+        assert!(prompt.contains("Labels: enhancement"));
+        assert!(prompt.contains("Linked issues: #12"));
+        assert!(prompt.contains("- reviewer on src/upload.rs: Cap the backoff"));
+    }
+
+    #[test]
+    fn test_prompt_sections_per_repository() {
+        let diff = |message: &str, path: &str| ExtractedDiff {
+            source: "commit".to_string(),
+            identifier: "HEAD".to_string(),
+            message: message.to_string(),
+            files: vec![FileChange {
+                path: path.to_string(),
+                old_path: None,
+                status: "modified".to_string(),
+                additions: 1,
+                deletions: 0,
+                diff: "+change".to_string(),
+                truncated: None,
+            }],
+            ..Default::default()
+        };
+        let merged = ExtractedDiff::merge_repositories(vec![
+            (
+                "/nonexistent/api".to_string(),
+                diff("Add endpoint", "src/api.rs"),
+            ),
+            (
+                "/nonexistent/infra".to_string(),
+                diff("Add route", "main.tf"),
+            ),
+        ]);
+        assert_eq!(merged.identifier, "api@HEAD, infra@HEAD");
+        assert_eq!(merged.files[1].path, "infra/main.tf");
+        assert_eq!(merged.summary.total_files, 2);
+
+        let content = PromptTemplates::format_diff_content(&merged);
+        let api = content
+            .find("# Repository: api (HEAD)\nAdd endpoint")
+            .unwrap();
+        let infra = content
+            .find("# Repository: infra (HEAD)\nAdd route")
+            .unwrap();
+        let api_file = content.find("## File: api/src/api.rs").unwrap();
+        let infra_file = content.find("## File: infra/main.tf").unwrap();
+        assert!(api < api_file && api_file < infra && infra < infra_file);
+    }
+
+    #[test]
+    fn test_commit_history_prompt_groups_by_area() {
+        let commit = |id: &str, message: &str, files: &[&str]| ExtractedDiff {
+            identifier: id.to_string(),
+            timestamp: "2026-01-05T10:00:00+00:00".to_string(),
+            author: "Dev".to_string(),
+            message: message.to_string(),
+            files: files
+                .iter()
+                .map(|path| FileChange {
+                    path: path.to_string(),
+                    old_path: None,
+                    status: "modified".to_string(),
+                    additions: 1,
+                    deletions: 0,
+                    diff: "+change".to_string(),
+                    truncated: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let history = ExtractedDiff::combine(
+            "v1.0..HEAD",
+            vec![
+                commit(
+                    "aaaaaaa1",
+                    "Add search tool",
+                    &["src/mcp/tools.rs", "README.md"],
+                ),
+                commit("bbbbbbb2", "Fix prompt", &["src/mcp/protocol.rs"]),
+            ],
+        );
+
+        let areas: Vec<(String, usize)> = PromptTemplates::commit_areas(&history)
+            .into_iter()
+            .map(|(area, commits)| (area, commits.len()))
+            .collect();
+        assert_eq!(
+            areas,
+            vec![("src/mcp".to_string(), 2), ("(root)".to_string(), 1)]
+        );
+
+        let prompt = PromptTemplates::commit_history_prompt(&history);
+        assert!(prompt.contains("the 2 commits of v1.0..HEAD"));
+        assert!(prompt.contains("Area: src/mcp\n- aaaaaaa Add search tool (Dev"));
+        assert!(!prompt.contains("+change"));
+    }
+
+    #[test]
+    fn test_configured_provider_selection() {
+        let config = AiConfig {
+            provider: Some("Mock".to_string()),
+            ..Default::default()
+        };
+        let client = AIClient::from_config(&config).unwrap();
+        assert_eq!(client.provider_name(), "Mock");
+
+        let config = AiConfig {
+            provider: Some("no-such-gateway".to_string()),
+            ..Default::default()
+        };
+        match AIClient::from_config(&config) {
+            Err(KtmeError::UnsupportedProvider(msg)) => {
+                assert!(msg.contains("ktme-ai-no-such-gateway"))
+            }
+            _ => panic!("Expected UnsupportedProvider error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openai_gateway_headers() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !String::from_utf8_lossy(&request).contains("\"messages\"") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = r#"{"choices": [{"message": {"content": "via gateway"}}]}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8_lossy(&request).to_lowercase()
+        });
+
+        let provider = OpenAIProvider::new(OpenAIConfig {
+            api_key: String::new(),
+            model: "internal-llm".to_string(),
+            max_tokens: 100,
+            temperature: 0.5,
+            base_url: Some(base_url),
+            headers: HashMap::from([("X-Gateway-Team".to_string(), "docs".to_string())]),
+        });
+
+        assert_eq!(provider.generate("test").await.unwrap(), "via gateway");
+        let request = server.join().unwrap();
+        assert!(request.contains("x-gateway-team: docs"));
+        assert!(!request.contains("authorization"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_external_ai_provider() {
+        use crate::ai::providers::{ExternalAIProvider, PLUGIN_PREFIX};
+        use crate::plugins::tests::write_plugin;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let plugin = write_plugin(
+            dir.path(),
+            PLUGIN_PREFIX,
+            "gateway",
+            r##"read request
+case "$request" in
+  *'"model":"internal-llm"'*'"prompt":"Summarize"'*) echo '{"result": "# Summary"}' ;;
+  *) echo '{"error": "unexpected request"}' ;;
+esac
+"##,
+        );
+        let config = AiConfig {
+            provider: Some("gateway".to_string()),
+            model: Some("internal-llm".to_string()),
+            ..Default::default()
+        };
+        let provider = ExternalAIProvider::new(plugin, serde_json::to_value(&config).unwrap());
+
+        assert_eq!(provider.provider_name(), "gateway");
+        assert_eq!(provider.generate("Summarize").await.unwrap(), "# Summary");
+    }
+
+    /// Answers with its name and the prompt, or fails
+    struct EchoProvider {
+        name: &'static str,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl AIProvider for EchoProvider {
+        async fn generate(&self, prompt: &str) -> crate::error::Result<String> {
+            if self.fail {
+                return Err(KtmeError::Timeout(format!("{} timed out", self.name)));
+            }
+            Ok(format!("{}: {}", self.name, prompt))
+        }
+
+        fn provider_name(&self) -> &str {
+            self.name
+        }
+    }
+
+    fn echo(name: &'static str, fail: bool) -> Box<dyn AIProvider> {
+        Box::new(EchoProvider { name, fail })
+    }
+
+    #[tokio::test]
+    async fn test_fallback_chain_fails_over() {
+        let chain = FallbackProvider::new(vec![
+            echo("claude", true),
+            echo("openai", false),
+            echo("mock", false),
+        ]);
+        assert_eq!(chain.provider_name(), "claude");
+        assert_eq!(chain.generate("Hi").await.unwrap(), "openai: Hi");
+
+        let chain = FallbackProvider::new(vec![echo("claude", true), echo("openai", true)]);
+        match chain.generate("Hi").await {
+            Err(KtmeError::Timeout(msg)) => assert_eq!(msg, "openai timed out"),
+            other => panic!("Expected the last timeout, got {:?}", other),
+        }
+
+        // Configured chain: an unreachable OpenAI-compatible server, then the mock
+        let config = AiConfig {
+            provider: Some("openai".to_string()),
+            base_url: Some("http://127.0.0.1:9".to_string()),
+            fallback: vec![AiConfig {
+                provider: Some("mock".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let client = AIClient::from_config(&config).unwrap();
+        let documentation = client.generate_documentation("Document it").await.unwrap();
+        assert!(documentation.contains("Generated by ktme mock provider"));
+    }
+
+    #[tokio::test]
+    async fn test_summary_provider_condenses_changes() {
+        let provider = SummarizingProvider::new(echo("cheap", false), echo("strong", false));
+        let diff = ExtractedDiff {
+            message: "Add retries".to_string(),
+            ..Default::default()
+        };
+        let prompt = PromptTemplates::documentation_prompt("Write a changelog.", &diff, None);
+
+        let documentation = provider.generate(&prompt).await.unwrap();
+        assert!(documentation.starts_with("strong: Write a changelog."));
+        let (instructions, notes) = documentation
+            .split_once("\n\nSummary of the changes:\n")
+            .unwrap();
+        assert!(!instructions.contains("Changes:"));
+        assert!(notes.starts_with("cheap: Summarize"));
+
+        // Custom templates without the changes heading skip the summary
+        assert_eq!(
+            provider.generate("Custom template").await.unwrap(),
+            "strong: Custom template"
+        );
+
+        let provider = SummarizingProvider::new(echo("cheap", true), Box::new(MockProvider::new()));
+        assert!(provider.generate(&prompt).await.is_err());
+    }
 }
@@ -0,0 +1,305 @@
+//! Doc comments of the public API touched by a diff
+//!
+//! The changed Rust files are parsed with `syn`; every public item (including
+//! `pub` methods of impl blocks and items of inline modules) whose lines were
+//! added by the diff contributes its rustdoc comment verbatim, so generated
+//! API documentation uses what the authors wrote instead of guessing.
+
+use crate::error::Result;
+use crate::git::diff::ExtractedDiff;
+use git2::{Oid, Repository};
+use serde::Serialize;
+use std::path::Path;
+use syn::spanned::Spanned;
+use syn::{Attribute, ImplItem, Item, Visibility};
+
+/// Files parsed per diff, to keep large refactors fast
+const MAX_PARSED_FILES: usize = 50;
+
+/// Doc comment of one public item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiDoc {
+    pub path: String,
+    /// Declaration line of the item, e.g. `pub fn deliver(event: &Event) -> Result<()>`
+    pub signature: String,
+    /// The doc comment, without the comment markers
+    pub docs: String,
+}
+
+/// Doc comments of the public items touched by `diff`, read from the commit
+/// of the diff in the repository at `repo_path` (or the current one), or
+/// from the working tree for staged changes
+pub fn for_diff(repo_path: Option<&str>, diff: &ExtractedDiff) -> Result<Vec<ApiDoc>> {
+    let repo = match repo_path {
+        Some(path) => Repository::open(path)?,
+        None => Repository::open_from_env()?,
+    };
+    let tree = Oid::from_str(&diff.identifier)
+        .ok()
+        .and_then(|oid| repo.find_commit(oid).ok())
+        .and_then(|commit| commit.tree().ok());
+
+    let mut docs = Vec::new();
+    for file in diff
+        .files
+        .iter()
+        .filter(|f| f.status != "deleted" && f.path.ends_with(".rs"))
+        .take(MAX_PARSED_FILES)
+    {
+        let source = match &tree {
+            Some(tree) => tree
+                .get_path(Path::new(&file.path))
+                .and_then(|entry| repo.find_blob(entry.id()))
+                .ok()
+                .and_then(|blob| String::from_utf8(blob.content().to_vec()).ok()),
+            None => repo
+                .workdir()
+                .and_then(|dir| std::fs::read_to_string(dir.join(&file.path)).ok()),
+        };
+        let Some(source) = source else {
+            continue;
+        };
+        match extract_rust(&file.path, &source, &added_lines(&file.diff)) {
+            Ok(items) => docs.extend(items),
+            Err(e) => tracing::debug!("Skipping doc comments of {}: {}", file.path, e),
+        }
+    }
+    Ok(docs)
+}
+
+/// Doc comments of the public items of the Rust `source` of `path` that
+/// contain one of the `changed` lines (1-based)
+pub fn extract_rust(path: &str, source: &str, changed: &[usize]) -> syn::Result<Vec<ApiDoc>> {
+    let file = syn::parse_file(source)?;
+    let lines: Vec<&str> = source.lines().collect();
+    let mut docs = Vec::new();
+    let mut collector = Collector {
+        path,
+        lines: &lines,
+        changed,
+        docs: &mut docs,
+    };
+    collector.items(&file.items);
+    Ok(docs)
+}
+
+/// Line numbers of the new version of a file added by its unified `diff`
+pub fn added_lines(diff: &str) -> Vec<usize> {
+    let mut added = Vec::new();
+    let mut line = 0;
+    for text in diff.lines() {
+        if let Some(hunk) = text.strip_prefix("@@") {
+            // @@ -a,b +c,d @@: the new side starts at line c
+            line = hunk
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+        } else if text.starts_with("+++") || text.starts_with("---") {
+            continue;
+        } else if text.starts_with('+') {
+            added.push(line);
+            line += 1;
+        } else if !text.starts_with('-') && !text.starts_with('\\') {
+            line += 1;
+        }
+    }
+    added
+}
+
+/// Doc comments as prompt context; empty when there are none
+pub fn prompt_context(docs: &[ApiDoc]) -> String {
+    if docs.is_empty() {
+        return String::new();
+    }
+    let mut context = String::from(
+        "Author-written documentation of the changed public API. Use it verbatim for these items \
+        and do not contradict or speculate beyond it:\n",
+    );
+    for doc in docs {
+        context.push_str(&format!(
+            "\n`{}` ({}):\n{}\n",
+            doc.signature, doc.path, doc.docs
+        ));
+    }
+    context
+}
+
+/// "Documented API" section appended to generated API documentation
+pub fn render_section(docs: &[ApiDoc]) -> String {
+    if docs.is_empty() {
+        return String::new();
+    }
+    let mut section = String::from("\n\n## Documented API\n");
+    for doc in docs {
+        section.push_str(&format!(
+            "\n```rust\n{}\n```\n\n{}\n\n_Source: `{}`_\n",
+            doc.signature, doc.docs, doc.path
+        ));
+    }
+    section.trim_end().to_string()
+}
+
+/// Walks the items of a file collecting the documented public ones
+struct Collector<'a> {
+    path: &'a str,
+    lines: &'a [&'a str],
+    changed: &'a [usize],
+    docs: &'a mut Vec<ApiDoc>,
+}
+
+impl Collector<'_> {
+    fn items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Fn(f) => self.item(&f.vis, &f.attrs, f.sig.ident.span(), item.span()),
+                Item::Struct(s) => self.item(&s.vis, &s.attrs, s.ident.span(), item.span()),
+                Item::Enum(e) => self.item(&e.vis, &e.attrs, e.ident.span(), item.span()),
+                Item::Trait(t) => self.item(&t.vis, &t.attrs, t.ident.span(), item.span()),
+                Item::Type(t) => self.item(&t.vis, &t.attrs, t.ident.span(), item.span()),
+                Item::Const(c) => self.item(&c.vis, &c.attrs, c.ident.span(), item.span()),
+                Item::Static(s) => self.item(&s.vis, &s.attrs, s.ident.span(), item.span()),
+                Item::Mod(m) if is_public(&m.vis) => {
+                    if let Some((_, items)) = &m.content {
+                        self.items(items);
+                    }
+                }
+                // Methods of a trait implementation are documented by the
+                // trait
+                Item::Impl(i) if i.trait_.is_none() => {
+                    for member in &i.items {
+                        if let ImplItem::Fn(f) = member {
+                            self.item(&f.vis, &f.attrs, f.sig.ident.span(), member.span());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn item(
+        &mut self,
+        vis: &Visibility,
+        attrs: &[Attribute],
+        name: proc_macro2::Span,
+        span: proc_macro2::Span,
+    ) {
+        if !is_public(vis) {
+            return;
+        }
+        let (start, end) = (span.start().line, span.end().line);
+        if !self.changed.iter().any(|line| (start..=end).contains(line)) {
+            return;
+        }
+        let docs = doc_comment(attrs);
+        if docs.is_empty() {
+            return;
+        }
+        let signature = self
+            .lines
+            .get(name.start().line.saturating_sub(1))
+            .map(|line| line.split(" {").next().unwrap_or(line).trim().to_string())
+            .unwrap_or_default();
+        self.docs.push(ApiDoc {
+            path: self.path.to_string(),
+            signature,
+            docs,
+        });
+    }
+}
+
+/// `pub`, but not `pub(crate)` or `pub(super)`
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// The `///` and `#[doc = "..."]` lines of `attrs`
+fn doc_comment(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(text),
+                        ..
+                    }),
+                ..
+            }) => Some(text.value()),
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').unwrap_or(&line).to_string())
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"use crate::Event;
+
+/// Deliver one event to its subscribers.
+///
+/// Failed deliveries are retried three times.
+pub fn deliver(event: &Event) -> Result<()> {
+    Ok(())
+}
+
+/// Not public, so not part of the API
+fn helper() {}
+
+pub struct Queue;
+
+impl Queue {
+    /// Enqueue an event for delivery
+    pub fn push(&mut self, event: Event) {
+        let _ = event;
+    }
+
+    /// Internal
+    pub(crate) fn drain(&mut self) {}
+}
+
+pub mod admin {
+    /// Replay every failed delivery
+    pub fn replay() {}
+}
+"#;
+
+    #[test]
+    fn test_extract_rust_doc_comments_of_changed_public_items() {
+        let all: Vec<usize> = (1..=SOURCE.lines().count()).collect();
+        let docs = extract_rust("src/webhooks.rs", SOURCE, &all).unwrap();
+        let signatures: Vec<&str> = docs.iter().map(|d| d.signature.as_str()).collect();
+        assert_eq!(
+            signatures,
+            vec![
+                "pub fn deliver(event: &Event) -> Result<()>",
+                "pub fn push(&mut self, event: Event)",
+                "pub fn replay()",
+            ]
+        );
+        assert_eq!(
+            docs[0].docs,
+            "Deliver one event to its subscribers.\n\nFailed deliveries are retried three times."
+        );
+
+        // Only the items containing a changed line
+        let docs = extract_rust("src/webhooks.rs", SOURCE, &[19]).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].docs, "Enqueue an event for delivery");
+        assert!(render_section(&docs).contains(
+            "```rust\npub fn push(&mut self, event: Event)\n```\n\nEnqueue an event for delivery"
+        ));
+    }
+
+    #[test]
+    fn test_added_lines() {
+        let diff = "@@ -1,3 +1,4 @@\n use a;\n-fn old() {}\n+fn new() {}\n+fn other() {}\n fn kept() {}\n@@ -10,2 +11,2 @@\n-x\n+y\n";
+        assert_eq!(added_lines(diff), vec![2, 3, 11]);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod coverage;
+pub mod doc_comments;
 pub mod doc_parser;
@@ -70,6 +70,8 @@ fn main() -> Result<()> {
         feature_types: None,
         content_types: None,
         limit: Some(10),
+        offset: None,
+        min_score: None,
         similarity_threshold: None,
         include_related: false,
         depth: None,
@@ -96,6 +98,8 @@ fn main() -> Result<()> {
         feature_types: Some(vec![FeatureType::Api]),
         content_types: None,
         limit: Some(5),
+        offset: None,
+        min_score: None,
         similarity_threshold: None,
         include_related: false,
         depth: None,
@@ -0,0 +1,336 @@
+use super::import::markdown_documents;
+use crate::ai::prompt_pack::PromptPack;
+use crate::ai::AIClient;
+use crate::cli::output;
+use crate::cli::progress::Progress;
+use crate::config::Config;
+use crate::doc::sections::{SectionFormat, SectionedDocument};
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::mapping::StorageManager;
+use crate::storage::models::SearchQuery;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Characters of a passage quoted in the prompt
+const MAX_PASSAGE_CHARS: usize = 1500;
+
+/// Indexed features read when looking for passages
+const MAX_INDEXED_FEATURES: u32 = 1000;
+
+/// Words of a question that say nothing about its subject
+const STOP_WORDS: &[&str] = &[
+    "about", "and", "are", "can", "does", "for", "from", "has", "have", "how", "into", "the",
+    "their", "there", "this", "what", "when", "where", "which", "who", "why", "with", "work",
+    "works", "you",
+];
+
+/// Documentation excerpt the answer may cite
+#[derive(Debug, Clone, Serialize)]
+struct Passage {
+    title: String,
+    service: String,
+    location: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<String>,
+    #[serde(skip)]
+    text: String,
+    score: usize,
+}
+
+/// Answer `question` from the documentation of `service` (or of every
+/// service): the `limit` most relevant sections of the mapped Markdown
+/// documents and indexed features are quoted in a prompt that asks for
+/// citations, and the answer is printed with its numbered sources
+pub async fn execute(question: String, service: Option<String>, limit: usize) -> Result<()> {
+    let terms = terms(&question);
+    if terms.is_empty() {
+        return Err(KtmeError::InvalidInput(
+            "The question has no words to search the documentation for".to_string(),
+        ));
+    }
+
+    let config = Config::load()?;
+    let manager = StorageManager::from_config(&config)?;
+    let services = match service {
+        Some(service) => vec![service],
+        None => manager.list_services()?,
+    };
+    let storage = open_storage(&config.storage)?;
+
+    let mut passages = Vec::new();
+    for name in &services {
+        match manager.get_mapping(name) {
+            Ok(mapping) => {
+                for doc in mapping.docs.iter().filter(|doc| doc.r#type == "markdown") {
+                    match markdown_documents(Path::new(&doc.location)) {
+                        Ok(documents) => {
+                            for document in documents {
+                                passages.extend(split(
+                                    name,
+                                    &document.title,
+                                    &document.location,
+                                    &document.content,
+                                ));
+                            }
+                        }
+                        Err(e) => tracing::warn!("Could not read {}: {}", doc.location, e),
+                    }
+                }
+            }
+            Err(KtmeError::MappingNotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    passages.extend(indexed_passages(storage.as_ref(), &services, &passages)?);
+    let sources = rank(passages, &terms, limit.max(1));
+
+    if sources.is_empty() {
+        if output::is_json() {
+            return output::print_json(&serde_json::json!({
+                "question": question,
+                "answer": null,
+                "sources": [],
+            }));
+        }
+        println!("No documentation found about: {}", question);
+        return Ok(());
+    }
+
+    let base_prompt = PromptPack::load(None)?
+        .prompt("answer")?
+        .unwrap_or_default();
+    let ai_client = AIClient::from_config(&config.ai)?;
+    let progress = Progress::spinner(format!("Asking {}", ai_client.provider_name()));
+    let answer = ai_client
+        .generate_documentation(&prompt(&base_prompt, &question, &sources))
+        .await;
+    progress.finish();
+    let answer = answer?;
+
+    if output::is_json() {
+        let sources: Vec<serde_json::Value> = sources
+            .iter()
+            .enumerate()
+            .map(|(number, source)| {
+                let mut value = serde_json::to_value(source).unwrap_or_default();
+                value["number"] = (number + 1).into();
+                value
+            })
+            .collect();
+        return output::print_json(&serde_json::json!({
+            "question": question,
+            "answer": answer.trim(),
+            "sources": sources,
+        }));
+    }
+
+    println!("{}\n", answer.trim());
+    println!("Sources:");
+    for (number, source) in sources.iter().enumerate() {
+        println!("  [{}] {} — {}", number + 1, source.title, cite(source));
+    }
+    Ok(())
+}
+
+/// Passages of a Markdown document: one per section, or the whole document
+/// when it has no headings
+fn split(service: &str, title: &str, location: &str, content: &str) -> Vec<Passage> {
+    let passage = |section: Option<&str>, text: &str| Passage {
+        title: section.unwrap_or(title).to_string(),
+        service: service.to_string(),
+        location: location.to_string(),
+        section: section.map(str::to_string),
+        text: text.trim().to_string(),
+        score: 0,
+    };
+    let document = SectionedDocument::parse(content, SectionFormat::Markdown);
+    let sections = document.sections();
+    if sections.is_empty() {
+        return vec![passage(None, content)];
+    }
+    sections
+        .into_iter()
+        .filter(|(_, body)| !body.trim().is_empty())
+        .map(|(section, body)| passage(Some(section), body))
+        .collect()
+}
+
+/// Indexed content and descriptions of the features of `services`, except
+/// the ones of documents already read from disk
+fn indexed_passages(
+    storage: &dyn Storage,
+    services: &[String],
+    read: &[Passage],
+) -> Result<Vec<Passage>> {
+    let mut service_ids = Vec::new();
+    let mut origins = HashMap::new();
+    for name in services {
+        if let Some(service) = storage.get_service_by_name(name)? {
+            service_ids.push(service.id);
+            for feature in storage.features_for_service(service.id)? {
+                if let Some(location) = feature.metadata["location"].as_str() {
+                    origins.insert(feature.id, location.to_string());
+                }
+            }
+        }
+    }
+    if service_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let read: HashSet<&str> = read.iter().map(|p| p.location.as_str()).collect();
+    let results = storage.search_features(&SearchQuery {
+        query: String::new(),
+        service_ids: Some(service_ids),
+        feature_types: None,
+        content_types: None,
+        limit: Some(MAX_INDEXED_FEATURES),
+        offset: None,
+        min_score: None,
+        similarity_threshold: None,
+        include_related: false,
+        depth: None,
+    })?;
+    Ok(results
+        .into_iter()
+        .filter_map(|result| {
+            let location = origins
+                .get(&result.feature_id)
+                .cloned()
+                .or(result.path)
+                .unwrap_or_else(|| format!("feature:{}", result.feature_id));
+            if read.contains(location.as_str()) {
+                return None;
+            }
+            let text = match result.description {
+                Some(description) if description != result.content => {
+                    format!("{}\n\n{}", description, result.content)
+                }
+                _ => result.content,
+            };
+            Some(Passage {
+                title: result.feature_name,
+                service: result.service_name,
+                location,
+                section: None,
+                text,
+                score: 0,
+            })
+        })
+        .collect())
+}
+
+/// Words of `question` to look for
+fn terms(question: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in question
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| word.chars().count() > 2 && !STOP_WORDS.contains(&word.as_str()))
+    {
+        if !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// The `limit` passages matching most terms, then with the most matches;
+/// matches in a title count three times
+fn rank(passages: Vec<Passage>, terms: &[String], limit: usize) -> Vec<Passage> {
+    let mut ranked: Vec<(usize, Passage)> = passages
+        .into_iter()
+        .filter_map(|mut passage| {
+            let title = passage.title.to_lowercase();
+            let text = passage.text.to_lowercase();
+            let mut covered = 0;
+            let mut matches = 0;
+            for term in terms {
+                let count =
+                    title.matches(term.as_str()).count() * 3 + text.matches(term.as_str()).count();
+                if count > 0 {
+                    covered += 1;
+                    matches += count;
+                }
+            }
+            passage.score = matches;
+            (covered > 0).then_some((covered, passage))
+        })
+        .collect();
+    ranked.sort_by(|(a_covered, a), (b_covered, b)| {
+        b_covered
+            .cmp(a_covered)
+            .then_with(|| b.score.cmp(&a.score))
+            .then_with(|| a.location.cmp(&b.location))
+    });
+    ranked
+        .into_iter()
+        .map(|(_, passage)| passage)
+        .take(limit)
+        .collect()
+}
+
+/// `base_prompt` followed by the numbered sources and the question
+fn prompt(base_prompt: &str, question: &str, sources: &[Passage]) -> String {
+    let mut prompt = format!("{}\n\nDocumentation excerpts:\n", base_prompt);
+    for (number, source) in sources.iter().enumerate() {
+        let text: String = source.text.chars().take(MAX_PASSAGE_CHARS).collect();
+        prompt.push_str(&format!(
+            "\n[{}] {} ({}, service {})\n{}\n",
+            number + 1,
+            source.title,
+            cite(source),
+            source.service,
+            text
+        ));
+    }
+    prompt.push_str(&format!("\nQuestion: {}\n", question));
+    prompt
+}
+
+/// Location of a passage, with its section
+fn cite(passage: &Passage) -> String {
+    match &passage.section {
+        Some(section) => format!("{} § {}", passage.location, section),
+        None => passage.location.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_sections_and_build_grounded_prompt() {
+        let mut passages = split(
+            "payments",
+            "Payments",
+            "docs/payments.md",
+            "# Payments\n\n## Retries\n\nFailed payments are retried three times, one hour apart.\n\n## Refunds\n\nRefunds go back to the original card.\n",
+        );
+        passages.extend(split(
+            "payments",
+            "Runbook",
+            "docs/runbook.md",
+            "Page the on-call engineer when payment retries are exhausted.",
+        ));
+        assert_eq!(passages.len(), 3);
+
+        let terms = terms("How does payment retries work?");
+        assert_eq!(terms, vec!["payment", "retries"]);
+
+        let sources = rank(passages, &terms, 2);
+        let cited: Vec<String> = sources.iter().map(cite).collect();
+        assert_eq!(cited, vec!["docs/payments.md § Retries", "docs/runbook.md"]);
+
+        let prompt = prompt("Answer with citations.", "How do retries work?", &sources);
+        assert!(prompt.starts_with("Answer with citations.\n\nDocumentation excerpts:\n"));
+        assert!(prompt.contains(
+            "[1] Retries (docs/payments.md § Retries, service payments)\nFailed payments are retried three times, one hour apart.\n"
+        ));
+        assert!(prompt.contains("[2] Runbook (docs/runbook.md, service payments)"));
+        assert!(prompt.ends_with("\nQuestion: How do retries work?\n"));
+    }
+}
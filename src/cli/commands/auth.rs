@@ -0,0 +1,127 @@
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::providers::confluence_oauth::ConfluenceOAuth;
+use crate::error::{KtmeError, Result};
+use serde_json::json;
+
+/// Sign in to `provider` with the OAuth authorization-code flow
+pub async fn login(provider: String, no_browser: bool) -> Result<()> {
+    tracing::info!("Signing in to {}", provider);
+
+    let oauth = oauth_client(&provider)?;
+    let tokens = oauth
+        .login(|url| {
+            eprintln!("Sign in to Confluence at:\n\n  {}\n", url);
+            if !no_browser && !open_browser(url) {
+                eprintln!("Could not open a browser; open the URL above manually.");
+            }
+            eprintln!("Waiting for the sign-in to complete...");
+        })
+        .await?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "provider": provider,
+            "site": oauth.site_url(),
+            "cloud_id": tokens.cloud_id,
+            "expires_at": tokens.expires_at,
+            "store": oauth.store().describe(),
+        }));
+    }
+
+    println!("✓ Signed in to {}", oauth.site_url());
+    println!("  Tokens stored in {}", oauth.store().describe());
+    Ok(())
+}
+
+/// Forget the stored tokens of `provider`
+pub async fn logout(provider: String) -> Result<()> {
+    tracing::info!("Signing out of {}", provider);
+
+    let oauth = oauth_client(&provider)?;
+    let removed = oauth.logout()?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "provider": provider,
+            "site": oauth.site_url(),
+            "signed_out": removed,
+        }));
+    }
+
+    if removed {
+        println!("✓ Signed out of {}", oauth.site_url());
+    } else {
+        println!("Not signed in to {}", oauth.site_url());
+    }
+    Ok(())
+}
+
+/// Show whether ktme holds tokens for `provider`
+pub async fn status(provider: String) -> Result<()> {
+    let oauth = oauth_client(&provider)?;
+    let tokens = oauth.tokens()?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "provider": provider,
+            "site": oauth.site_url(),
+            "signed_in": tokens.is_some(),
+            "expires_at": tokens.as_ref().map(|t| t.expires_at),
+            "refreshable": tokens.as_ref().map(|t| t.refresh_token.is_some()),
+            "store": oauth.store().describe(),
+        }));
+    }
+
+    match tokens {
+        Some(tokens) => {
+            println!("Signed in to {}", oauth.site_url());
+            println!(
+                "  Access token expires: {}{}",
+                tokens.expires_at.to_rfc3339(),
+                if tokens.refresh_token.is_some() {
+                    " (refreshed automatically)"
+                } else {
+                    ""
+                }
+            );
+            println!("  Tokens stored in {}", oauth.store().describe());
+        }
+        None => println!(
+            "Not signed in to {}. Run 'ktme auth login {}'",
+            oauth.site_url(),
+            provider
+        ),
+    }
+    Ok(())
+}
+
+fn oauth_client(provider: &str) -> Result<ConfluenceOAuth> {
+    match provider {
+        "confluence" => {
+            let config = Config::load()?;
+            ConfluenceOAuth::from_config(&config.confluence)
+        }
+        other => Err(KtmeError::UnsupportedProvider(other.to_string())),
+    }
+}
+
+/// Open `url` in the default browser; false when no opener could be started
+fn open_browser(url: &str) -> bool {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+
+    command
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .is_ok()
+}
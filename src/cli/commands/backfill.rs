@@ -0,0 +1,327 @@
+use super::generate::{generate_each, SequenceItem};
+use crate::cli::output;
+use crate::cli::progress::Progress;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::git::cache::CommitDiffCache;
+use crate::git::diff::{DiffExtractor, ExtractedDiff};
+use crate::git::reader::DiffLimits;
+use crate::http;
+use crate::storage::backend::open_storage;
+use crate::storage::models::Job;
+use chrono::{DateTime, Datelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Placeholder in `--output` replaced by the bucket label
+const BUCKET_PLACEHOLDER: &str = "{bucket}";
+
+/// How the commits of a backfilled range are grouped into documents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BackfillPeriod {
+    /// One document per commit
+    #[default]
+    Commit,
+    /// One document per ISO week of commits
+    Week,
+}
+
+/// Label of the bucket `diff` falls into, used in `{bucket}` output paths
+fn bucket_label(diff: &ExtractedDiff, per: BackfillPeriod) -> String {
+    match per {
+        BackfillPeriod::Commit => diff.identifier.chars().take(7).collect(),
+        BackfillPeriod::Week => DateTime::parse_from_rfc3339(&diff.timestamp)
+            .map(|time| {
+                let week = time.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            })
+            .unwrap_or_else(|_| "undated".to_string()),
+    }
+}
+
+/// The commits grouped into buckets, oldest first
+pub fn buckets(mut diffs: Vec<ExtractedDiff>, per: BackfillPeriod) -> Vec<(String, ExtractedDiff)> {
+    diffs.sort_by_key(|diff| DateTime::parse_from_rfc3339(&diff.timestamp).ok());
+    match per {
+        BackfillPeriod::Commit => diffs
+            .into_iter()
+            .map(|diff| (bucket_label(&diff, per), diff))
+            .collect(),
+        BackfillPeriod::Week => {
+            let mut weeks: Vec<(String, Vec<ExtractedDiff>)> = Vec::new();
+            for diff in diffs {
+                let label = bucket_label(&diff, per);
+                match weeks.last_mut() {
+                    Some((last, commits)) if *last == label => commits.push(diff),
+                    _ => weeks.push((label, vec![diff])),
+                }
+            }
+            weeks
+                .into_iter()
+                .map(|(label, commits)| {
+                    let diff = ExtractedDiff::combine(&label, commits);
+                    (label, diff)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Arguments of `ktme backfill`, stored with its job so it can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct BackfillJob {
+    service: String,
+    range: String,
+    per: BackfillPeriod,
+    doc_type: Option<String>,
+    template: Option<String>,
+    output: Option<String>,
+    publish: Option<String>,
+    queue: bool,
+    force: bool,
+}
+
+/// Generate documentation of `service` for a commit range, one document per
+/// commit or per week, oldest first
+#[allow(clippy::too_many_arguments)]
+pub async fn execute(
+    service: String,
+    range: String,
+    per: BackfillPeriod,
+    doc_type: Option<String>,
+    template: Option<String>,
+    output: Option<String>,
+    publish: Option<String>,
+    queue: bool,
+    force: bool,
+) -> Result<()> {
+    let args = BackfillJob {
+        service,
+        range,
+        per,
+        doc_type,
+        template,
+        output,
+        publish,
+        queue,
+        force,
+    };
+    run(args, None).await
+}
+
+/// `range` with both ends resolved to commit ids, so that resuming the job
+/// documents the same commits after branches move
+fn pin_range(range: &str) -> String {
+    let Ok(repo) = git2::Repository::discover(".") else {
+        return range.to_string();
+    };
+    let resolve = |rev: &str| {
+        repo.revparse_single(rev)
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id().to_string())
+    };
+    match range.split_once("..") {
+        Some((start, end)) => match (resolve(start), resolve(end)) {
+            (Ok(start), Ok(end)) => format!("{}..{}", start, end),
+            _ => range.to_string(),
+        },
+        None => range.to_string(),
+    }
+}
+
+/// Generate the documents of a stopped backfill `job` that were not done
+pub(crate) async fn resume(job: &Job) -> Result<()> {
+    let args: BackfillJob = serde_json::from_str(&job.params)?;
+    run(args, Some(job)).await
+}
+
+async fn run(args: BackfillJob, job: Option<&Job>) -> Result<()> {
+    let BackfillJob {
+        service,
+        range,
+        per,
+        ..
+    } = &args;
+    tracing::info!("Backfilling documentation for {} over {}", service, range);
+
+    let config = Config::load()?;
+    http::configure(&config.timeouts);
+    let storage = open_storage(&config.storage)?;
+
+    // A resumed job documents the commits the stored range was pinned to
+    let commits = match job {
+        Some(_) => range.clone(),
+        None => pin_range(range),
+    };
+
+    let progress = Progress::spinner(format!("Reading commits in {}", range));
+    let extractor = DiffExtractor::new("range".to_string(), commits.clone(), None)?
+        .with_limits(DiffLimits::from_config(&config.git))
+        .with_cache(CommitDiffCache::from_config(&config));
+    let diffs = extractor.extract_range(&commits)?;
+    if diffs.is_empty() {
+        return Err(KtmeError::InvalidInput(format!(
+            "No commits in range {}",
+            range
+        )));
+    }
+
+    let buckets = buckets(diffs, *per);
+    if *per == BackfillPeriod::Week {
+        if let Some((label, diff)) = buckets
+            .iter()
+            .find(|(_, diff)| diff.commits.len() > config.git.max_commit_range as usize)
+        {
+            return Err(KtmeError::InvalidInput(format!(
+                "Week {} has {} commits, more than git.max_commit_range ({}); use --per commit",
+                label,
+                diff.commits.len(),
+                config.git.max_commit_range
+            )));
+        }
+    }
+    match &args.output {
+        Some(path) if buckets.len() > 1 && !path.contains(BUCKET_PLACEHOLDER) => {
+            return Err(KtmeError::InvalidInput(format!(
+                "--output must contain {} when the range has several {}s",
+                BUCKET_PLACEHOLDER,
+                match per {
+                    BackfillPeriod::Commit => "commit",
+                    BackfillPeriod::Week => "week",
+                }
+            )));
+        }
+        _ => {}
+    }
+    progress.finish();
+
+    // Each bucket is a step of the job; a resumed job skips the done ones
+    let labels: Vec<String> = buckets.iter().map(|(label, _)| label.clone()).collect();
+    let (id, steps): (i64, HashMap<String, (i64, bool)>) = match job {
+        Some(job) => (
+            job.id,
+            job.steps
+                .iter()
+                .map(|step| (step.name.clone(), (step.position, step.status == "done")))
+                .collect(),
+        ),
+        None => {
+            let id = storage.create_job(
+                "backfill",
+                &format!("Backfill {} over {}", service, range),
+                &serde_json::to_string(&BackfillJob {
+                    range: commits.clone(),
+                    ..args.clone()
+                })?,
+                &labels,
+            )?;
+            let steps = labels
+                .iter()
+                .enumerate()
+                .map(|(position, label)| (label.clone(), (position as i64, false)))
+                .collect();
+            (id, steps)
+        }
+    };
+    let mut items = Vec::new();
+    for (label, diff) in buckets {
+        let Some((step, done)) = steps.get(&label) else {
+            return Err(KtmeError::InvalidInput(format!(
+                "Job {} has no step for {}; the range changed since it started",
+                id, label
+            )));
+        };
+        if *done {
+            continue;
+        }
+        let output = args
+            .output
+            .as_deref()
+            .map(|path| path.replace(BUCKET_PLACEHOLDER, &label));
+        items.push(SequenceItem {
+            step: *step,
+            diff,
+            output,
+        });
+    }
+
+    let total = items.len();
+    let progress = Progress::bar(total as u64, format!("Backfilling {}", service));
+    let result = generate_each(
+        service,
+        args.doc_type.clone(),
+        args.template.clone(),
+        items,
+        args.publish.as_deref(),
+        args.queue,
+        args.force,
+        id,
+        &progress,
+    )
+    .await;
+    progress.finish();
+    let summaries = match result {
+        Ok(summaries) => summaries,
+        Err(e) => {
+            output::message(format!(
+                "Backfill stopped; continue it with 'ktme jobs resume {}'",
+                id
+            ));
+            return Err(e);
+        }
+    };
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "service": service,
+            "range": range,
+            "job": id,
+            "documents": summaries,
+        }));
+    }
+    output::message(format!(
+        "✓ Backfilled {} document(s) for {} from {}",
+        summaries.len(),
+        service,
+        range
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, timestamp: &str) -> ExtractedDiff {
+        ExtractedDiff {
+            source: "commit".to_string(),
+            identifier: id.to_string(),
+            timestamp: timestamp.to_string(),
+            message: format!("Commit {}", id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_buckets_per_commit_and_week() {
+        let diffs = vec![
+            commit("ccccccc333", "2026-03-10T09:00:00+00:00"),
+            commit("aaaaaaa111", "2026-03-02T09:00:00+00:00"),
+            commit("bbbbbbb222", "2026-03-05T09:00:00+00:00"),
+        ];
+
+        let labels: Vec<String> = buckets(diffs.clone(), BackfillPeriod::Commit)
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(labels, ["aaaaaaa", "bbbbbbb", "ccccccc"]);
+
+        let weeks = buckets(diffs, BackfillPeriod::Week);
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[0].0, "2026-W10");
+        assert_eq!(weeks[0].1.commits.len(), 2);
+        assert_eq!(weeks[0].1.identifier, "2026-W10");
+        assert_eq!(weeks[1].0, "2026-W11");
+    }
+}
@@ -0,0 +1,65 @@
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::git::cache::{CommitDiffCache, DIFF_CACHE};
+use crate::git::diff::DiffExtractor;
+use crate::git::reader::DiffLimits;
+use crate::metrics;
+
+/// Extract the commits of `range` into the diff cache, so that generation
+/// jobs started afterwards (e.g. in parallel CI jobs) read them from the
+/// cache instead of from git
+pub async fn warm(range: String, repos: Vec<String>) -> Result<()> {
+    let config = Config::load()?;
+    let cache = CommitDiffCache::from_config(&config).ok_or_else(|| {
+        KtmeError::UnsupportedOperation(
+            "The diff cache needs a non-zero [git] diff_cache_ttl and a reachable storage backend"
+                .to_string(),
+        )
+    })?;
+    let pruned = cache.prune()?;
+    let limits = DiffLimits::from_config(&config.git);
+
+    let paths: Vec<Option<&str>> = if repos.is_empty() {
+        vec![None]
+    } else {
+        repos.iter().map(|path| Some(path.as_str())).collect()
+    };
+    let before = metrics::cache_lookups(DIFF_CACHE);
+    let mut commits = 0;
+    for path in paths {
+        let extractor = DiffExtractor::new("range".to_string(), range.clone(), path)?
+            .with_limits(limits.clone())
+            .with_cache(Some(cache.clone()));
+        commits += extractor.extract_range(&range)?.len();
+    }
+    let after = metrics::cache_lookups(DIFF_CACHE);
+    let lookups = metrics::CacheLookups {
+        hits: after.hits - before.hits,
+        misses: after.misses - before.misses,
+    };
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "range": range,
+            "commits": commits,
+            "extracted": lookups.misses,
+            "already_cached": lookups.hits,
+            "hit_rate": lookups.hit_rate(),
+            "expired_removed": pruned,
+            "ttl_seconds": cache.ttl().num_seconds(),
+        }));
+    }
+    output::message(format!(
+        "Cached {} commit(s) of {}: {} extracted, {} already cached ({:.0}% hit rate)",
+        commits,
+        range,
+        lookups.misses,
+        lookups.hits,
+        lookups.hit_rate() * 100.0
+    ));
+    if pruned > 0 {
+        output::message(format!("Removed {} expired diff(s)", pruned));
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::error::Result;
+use crate::storage::mapping::StorageManager;
+use clap::Command;
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use clap_complete::Shell;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+
+/// Environment variable the dynamic completion scripts call back with
+pub const COMPLETE_ENV: &str = "COMPLETE";
+
+/// Print a completion script for `shell`.
+///
+/// By default the script calls back into `ktme` so values such as service
+/// names are completed from storage; `static_script` prints a self-contained
+/// script that only knows about subcommands and flags.
+pub fn completions(shell: Shell, mut cmd: Command, static_script: bool) -> Result<()> {
+    let bin = cmd.get_name().to_string();
+    let mut stdout = std::io::stdout().lock();
+
+    if static_script {
+        clap_complete::generate(shell, &mut cmd, bin, &mut stdout);
+        return Ok(());
+    }
+
+    let shells = Shells::builtins();
+    let completer = shells.completer(&shell.to_string()).ok_or_else(|| {
+        crate::error::KtmeError::UnsupportedOperation(format!(
+            "Dynamic completions are not available for {}; use --static",
+            shell
+        ))
+    })?;
+    completer.write_registration(COMPLETE_ENV, &bin, &bin, &bin, &mut stdout)?;
+
+    Ok(())
+}
+
+/// Render the man page to `output`, or stdout when not given
+pub fn man(cmd: Command, output: Option<String>) -> Result<()> {
+    let mut buffer = Vec::new();
+    clap_mangen::Man::new(cmd).render(&mut buffer)?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, buffer)?;
+            tracing::info!("Man page written to {}", path);
+        }
+        None => std::io::stdout().lock().write_all(&buffer)?,
+    }
+
+    Ok(())
+}
+
+/// Completion candidates for `--service`: mapped service names matching the
+/// typed prefix. Storage errors yield no candidates rather than noise.
+pub fn complete_service(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(prefix) = current.to_str() else {
+        return Vec::new();
+    };
+
+    let names = StorageManager::new()
+        .and_then(|storage| storage.list_services())
+        .unwrap_or_default();
+
+    matching_candidates(names, prefix)
+}
+
+fn matching_candidates(
+    names: impl IntoIterator<Item = String>,
+    prefix: &str,
+) -> Vec<CompletionCandidate> {
+    let mut names: Vec<String> = names
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort();
+    names.dedup();
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
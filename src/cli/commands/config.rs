@@ -1,5 +1,12 @@
+use crate::ai::AIClient;
+use crate::cli::output;
+use crate::config::interpolate::env_lookup;
+use crate::config::validate::{self, Diagnostic, Report, Severity};
 use crate::config::Config;
-use crate::error::Result;
+use crate::doc::providers::confluence::ConfluenceProvider;
+use crate::doc::providers::DocumentProvider;
+use crate::error::{KtmeError, Result};
+use crate::issues::jira::JiraClient;
 
 pub async fn init() -> Result<()> {
     tracing::info!("Initializing configuration");
@@ -8,6 +15,9 @@ pub async fn init() -> Result<()> {
     config.save()?;
 
     let config_path = Config::config_file_path()?;
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({ "path": config_path }));
+    }
     println!("Configuration initialized at: {}", config_path.display());
     println!("\nEdit the configuration file to customize settings.");
 
@@ -17,7 +27,12 @@ pub async fn init() -> Result<()> {
 pub async fn show() -> Result<()> {
     tracing::info!("Showing current configuration");
 
-    let config = Config::load()?;
+    // Values from `${VAR}` references are resolved by now, so the secrets
+    // are masked before printing
+    let config = Config::load()?.redacted();
+    if output::is_json() {
+        return output::print_json(&config);
+    }
     let config_str = toml::to_string_pretty(&config)?;
 
     println!("Current configuration:\n");
@@ -36,15 +51,113 @@ pub async fn set(key: String, value: String) -> Result<()> {
     Ok(())
 }
 
-pub async fn validate() -> Result<()> {
+/// Check the config file and report every problem found; with `online`,
+/// also connect to the configured providers
+pub async fn validate(online: bool) -> Result<()> {
     tracing::info!("Validating configuration");
 
-    let config = Config::load()?;
-    println!("Configuration is valid");
-    println!("\nLoaded settings:");
-    println!("  Log level: {}", config.general.log_level);
-    println!("  Default branch: {}", config.git.default_branch);
-    println!("  MCP model: {}", config.mcp.model);
+    let config_path = Config::config_file_path()?;
+    let mut report = if config_path.exists() {
+        validate::check(&std::fs::read_to_string(&config_path)?, &env_lookup)
+    } else {
+        let config = Config::default();
+        Report {
+            diagnostics: validate::check_config(&config, &env_lookup),
+            config: Some(config),
+        }
+    };
+    if online {
+        crate::http::ensure_online("Checking provider connectivity")?;
+        if let Some(config) = &report.config {
+            let diagnostics = check_connectivity(config).await;
+            report.diagnostics.extend(diagnostics);
+        }
+    }
+
+    let errors = report.count(Severity::Error);
+    let warnings = report.count(Severity::Warning);
+    if output::is_json() {
+        output::print_json(&serde_json::json!({
+            "path": config_path,
+            "valid": errors == 0,
+            "errors": errors,
+            "warnings": warnings,
+            "diagnostics": report.diagnostics,
+        }))?;
+    } else if report.diagnostics.is_empty() {
+        println!("Configuration at {} is valid", config_path.display());
+    } else {
+        println!(
+            "{}: {} error(s), {} warning(s)\n",
+            config_path.display(),
+            errors,
+            warnings
+        );
+        for diagnostic in &report.diagnostics {
+            if diagnostic.key.is_empty() {
+                println!("  {:<8} {}", diagnostic.severity, diagnostic.message);
+            } else {
+                println!(
+                    "  {:<8} {}: {}",
+                    diagnostic.severity, diagnostic.key, diagnostic.message
+                );
+            }
+        }
+    }
 
+    if errors > 0 {
+        return Err(KtmeError::Config(format!(
+            "{} error(s) in {}",
+            errors,
+            config_path.display()
+        )));
+    }
     Ok(())
 }
+
+/// Connect to Confluence, Jira and the AI provider when they are configured
+async fn check_connectivity(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if config.confluence.base_url.is_some() {
+        let result = match ConfluenceProvider::from_settings(&config.confluence) {
+            Ok(provider) => match provider.health_check().await {
+                Ok(true) => Ok(()),
+                Ok(false) => Err("Confluence did not answer its health check".to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(message) = result {
+            diagnostics.push(Diagnostic::error("confluence", message));
+        }
+    }
+
+    let issues = &config.issues;
+    let jira_token = issues
+        .jira_api_token
+        .clone()
+        .or_else(|| env_lookup("JIRA_API_TOKEN"));
+    if let (Some(url), Some(username), Some(token)) =
+        (&issues.jira_base_url, &issues.jira_username, jira_token)
+    {
+        if let Err(e) = JiraClient::new(url, username, &token).check().await {
+            diagnostics.push(Diagnostic::error("issues", e.to_string()));
+        }
+    }
+
+    // Sends a one-word prompt to the provider and its fallbacks
+    let result = match AIClient::from_config(&config.ai) {
+        Ok(client) => match client.test_connection().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("the AI provider did not answer a test prompt".to_string()),
+            Err(e) => Err(e.to_string()),
+        },
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(message) = result {
+        diagnostics.push(Diagnostic::error("ai", message));
+    }
+
+    diagnostics
+}
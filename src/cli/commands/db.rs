@@ -0,0 +1,124 @@
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::storage::database::Database;
+use crate::storage::migrations::{self, MigrationState};
+
+/// Print the schema of the sqlite database, as a script of `CREATE`
+/// statements or as JSON
+pub async fn schema(table: Option<String>) -> Result<()> {
+    let db = open_sqlite("schema")?;
+    let objects = db.schema(table.as_deref())?;
+    if objects.is_empty() {
+        if let Some(table) = table {
+            return Err(KtmeError::NotFound(format!("Table '{}' not found", table)));
+        }
+    }
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "database": db.path(),
+            "version": migrations::latest_version(),
+            "objects": objects,
+        }));
+    }
+    println!(
+        "-- Schema of {} (version {})",
+        db.path().display(),
+        migrations::latest_version()
+    );
+    for object in &objects {
+        println!("\n{};", object.sql);
+    }
+    Ok(())
+}
+
+/// Open the database of the sqlite backend for `ktme db <command>`
+fn open_sqlite(command: &str) -> Result<Database> {
+    let config = Config::load()?;
+    if !config.storage.backend.eq_ignore_ascii_case("sqlite") {
+        return Err(KtmeError::UnsupportedOperation(format!(
+            "`ktme db {}` works on the sqlite backend; the {} backend manages its own schema when opened",
+            command, config.storage.backend
+        )));
+    }
+    // Opening the database applies the pending migrations
+    Database::open(&config.storage)
+}
+
+/// Apply pending migrations, show their state with `status`, or revert the
+/// migrations above `down_to` (development only)
+pub async fn migrate(status: bool, down_to: Option<i64>) -> Result<()> {
+    let db = open_sqlite("migrate")?;
+
+    if let Some(target) = down_to {
+        let reverted = db.rollback(target)?;
+        if output::is_json() {
+            return output::print_json(&serde_json::json!({
+                "reverted": reverted,
+                "version": target,
+            }));
+        }
+        if reverted.is_empty() {
+            output::message(format!("Nothing to revert above version {}", target));
+        } else {
+            output::message(format!(
+                "Reverted migration(s) {} of {}; they are applied again the next time ktme opens the database",
+                reverted
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                db.path().display()
+            ));
+        }
+        return Ok(());
+    }
+
+    let statuses = db.migration_status()?;
+    let drifted = statuses
+        .iter()
+        .filter(|s| matches!(s.state, MigrationState::Modified | MigrationState::Unknown))
+        .count();
+
+    if !status {
+        if output::is_json() {
+            return output::print_json(&serde_json::json!({
+                "database": db.path(),
+                "version": migrations::latest_version(),
+            }));
+        }
+        output::message(format!(
+            "Database {} is at schema version {}",
+            db.path().display(),
+            migrations::latest_version()
+        ));
+    } else if output::is_json() {
+        output::print_json(&serde_json::json!({
+            "database": db.path(),
+            "migrations": statuses,
+        }))?;
+    } else {
+        println!("Migrations of {}:\n", db.path().display());
+        for status in &statuses {
+            println!(
+                "  {:>3}  {:<28} {:<9} {}",
+                status.version,
+                status.name.as_deref().unwrap_or("-"),
+                status.state,
+                status
+                    .applied_at
+                    .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default()
+            );
+        }
+    }
+
+    if drifted > 0 {
+        return Err(KtmeError::Storage(format!(
+            "{} applied migration(s) differ from this version of ktme; see `ktme db migrate --status`",
+            drifted
+        )));
+    }
+    Ok(())
+}
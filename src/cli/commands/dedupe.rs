@@ -0,0 +1,368 @@
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::sections::{SectionFormat, SectionedDocument};
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::mapping::StorageManager;
+use crate::storage::models::{SearchContentType, SearchQuery};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Documents and sections shorter than this many words are not compared;
+/// short boilerplate such as "See below." matches too easily
+const MIN_WORDS: usize = 12;
+
+/// Indexed documents read per service
+const MAX_INDEXED_DOCUMENTS: u32 = 1000;
+
+/// A document of the service: a mapped Markdown file or content indexed by
+/// `ktme import docs`
+#[derive(Debug, Clone)]
+struct Doc {
+    location: String,
+    provider: String,
+    content: String,
+    /// Mapped to the service, so `ktme update` keeps it current
+    mapped: bool,
+}
+
+/// A document or one of its sections
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Part {
+    location: String,
+    provider: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<String>,
+}
+
+/// Two parts with highly similar content
+#[derive(Debug, Serialize)]
+struct Duplicate {
+    /// `document` or `section`
+    kind: &'static str,
+    /// Cosine similarity of the word frequencies, 0 to 1
+    score: f64,
+    first: Part,
+    second: Part,
+    /// Where to consolidate the content: the mapped, then the longer part
+    target: Part,
+}
+
+/// A part with its word frequencies
+struct Unit {
+    doc: usize,
+    part: Part,
+    words: usize,
+    frequencies: HashMap<String, f64>,
+}
+
+/// Report the documents of `service`, and the sections of different
+/// documents, whose content is at least `threshold` similar, with the part
+/// to consolidate each pair into
+pub async fn execute(service: String, threshold: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(KtmeError::InvalidInput(format!(
+            "--threshold must be between 0 and 1, got {}",
+            threshold
+        )));
+    }
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let docs = service_documents(&config, storage.as_ref(), &service)?;
+    let duplicates = find_duplicates(&docs, threshold);
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "service": service,
+            "documents": docs.len(),
+            "threshold": threshold,
+            "duplicates": duplicates,
+        }));
+    }
+
+    if duplicates.is_empty() {
+        println!(
+            "No duplicates found among {} document(s) of '{}' (similarity >= {:.2}).",
+            docs.len(),
+            service,
+            threshold
+        );
+        return Ok(());
+    }
+    println!(
+        "Possible duplicates in '{}' (similarity >= {:.2}):\n",
+        service, threshold
+    );
+    for (number, duplicate) in duplicates.iter().enumerate() {
+        println!(
+            "{}. {:.0}% similar {}s",
+            number + 1,
+            duplicate.score * 100.0,
+            duplicate.kind
+        );
+        println!("   {}", describe(&duplicate.first));
+        println!("   {}", describe(&duplicate.second));
+        let other = if duplicate.target == duplicate.first {
+            &duplicate.second
+        } else {
+            &duplicate.first
+        };
+        println!(
+            "   → Consolidate into {} and link to it from {}",
+            describe(&duplicate.target),
+            describe(other)
+        );
+    }
+    Ok(())
+}
+
+/// Mapped Markdown files of `service` and the documents indexed for it
+fn service_documents(config: &Config, storage: &dyn Storage, service: &str) -> Result<Vec<Doc>> {
+    let mapped = match StorageManager::from_config(config)?.get_mapping(service) {
+        Ok(mapping) => mapping.docs,
+        Err(KtmeError::MappingNotFound(_)) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    let mut docs: Vec<Doc> = mapped
+        .iter()
+        .filter(|doc| doc.r#type == "markdown")
+        .filter_map(|doc| {
+            let content = std::fs::read_to_string(&doc.location).ok()?;
+            Some(Doc {
+                location: doc.location.clone(),
+                provider: doc.r#type.clone(),
+                content,
+                mapped: true,
+            })
+        })
+        .collect();
+
+    let Some(service_entry) = storage.get_service_by_name(service)? else {
+        return Ok(docs);
+    };
+    // Imported features remember where their document came from
+    let origins: HashMap<String, (String, String)> = storage
+        .features_for_service(service_entry.id)?
+        .into_iter()
+        .filter_map(|feature| {
+            let location = feature.metadata["location"].as_str()?.to_string();
+            let provider = feature.metadata["provider"]
+                .as_str()
+                .unwrap_or("indexed")
+                .to_string();
+            Some((feature.id, (location, provider)))
+        })
+        .collect();
+    let indexed = storage.search_features(&SearchQuery {
+        query: String::new(),
+        service_ids: Some(vec![service_entry.id]),
+        feature_types: None,
+        content_types: None,
+        limit: Some(MAX_INDEXED_DOCUMENTS),
+        offset: None,
+        min_score: None,
+        similarity_threshold: None,
+        include_related: false,
+        depth: None,
+    })?;
+    for result in indexed
+        .into_iter()
+        .filter(|result| result.content_type == SearchContentType::Documentation)
+    {
+        let (location, provider) = origins
+            .get(&result.feature_id)
+            .cloned()
+            .unwrap_or_else(|| (result.feature_name.clone(), "indexed".to_string()));
+        if docs.iter().any(|doc| doc.location == location) {
+            continue;
+        }
+        docs.push(Doc {
+            mapped: mapped.iter().any(|doc| doc.location == location),
+            location,
+            provider,
+            content: result.content,
+        });
+    }
+    Ok(docs)
+}
+
+/// Similar document pairs and similar section pairs of different documents,
+/// most similar first. Sections of a pair of duplicate documents are not
+/// reported again.
+fn find_duplicates(docs: &[Doc], threshold: f64) -> Vec<Duplicate> {
+    let documents: Vec<Unit> = docs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, doc)| unit(index, doc, None, &doc.content))
+        .collect();
+    let sections: Vec<Unit> = docs
+        .iter()
+        .enumerate()
+        .flat_map(|(index, doc)| {
+            SectionedDocument::parse(&doc.content, SectionFormat::Markdown)
+                .sections()
+                .into_iter()
+                .filter_map(|(title, body)| unit(index, doc, Some(title), body))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut duplicates = Vec::new();
+    let mut duplicate_docs = Vec::new();
+    for (kind, units) in [("document", &documents), ("section", &sections)] {
+        for (i, first) in units.iter().enumerate() {
+            for second in &units[i + 1..] {
+                let pair = (first.doc.min(second.doc), first.doc.max(second.doc));
+                if first.doc == second.doc || (kind == "section" && duplicate_docs.contains(&pair))
+                {
+                    continue;
+                }
+                let score = cosine(&first.frequencies, &second.frequencies);
+                if score < threshold {
+                    continue;
+                }
+                if kind == "document" {
+                    duplicate_docs.push(pair);
+                }
+                duplicates.push(Duplicate {
+                    kind,
+                    score,
+                    first: first.part.clone(),
+                    second: second.part.clone(),
+                    target: target(docs, first, second).part.clone(),
+                });
+            }
+        }
+    }
+    duplicates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    duplicates
+}
+
+fn unit(doc: usize, source: &Doc, section: Option<&str>, text: &str) -> Option<Unit> {
+    let words = words(text);
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    let mut frequencies = HashMap::new();
+    for word in &words {
+        *frequencies.entry(word.clone()).or_insert(0.0) += 1.0;
+    }
+    Some(Unit {
+        doc,
+        part: Part {
+            location: source.location.clone(),
+            provider: source.provider.clone(),
+            section: section.map(str::to_string),
+        },
+        words: words.len(),
+        frequencies,
+    })
+}
+
+/// The part to keep: a mapped document over an unmapped one, then the
+/// longer content, then the first
+fn target<'a>(docs: &[Doc], first: &'a Unit, second: &'a Unit) -> &'a Unit {
+    let rank = |unit: &Unit| (docs[unit.doc].mapped, unit.words);
+    if rank(second) > rank(first) {
+        second
+    } else {
+        first
+    }
+}
+
+/// Lowercase words of `text`, without Markdown punctuation
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn cosine(a: &HashMap<String, f64>, b: &HashMap<String, f64>) -> f64 {
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(word, count)| b.get(word).map(|other| count * other))
+        .sum();
+    let norm = |v: &HashMap<String, f64>| v.values().map(|c| c * c).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+fn describe(part: &Part) -> String {
+    match &part.section {
+        Some(section) => format!("{} § {}", part.location, section),
+        None => part.location.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RETRIES: &str =
+        "Failed webhook deliveries are retried three times with exponential backoff, \
+        after which the event is moved to the dead letter queue for manual replay.";
+
+    fn doc(location: &str, content: &str, mapped: bool) -> Doc {
+        Doc {
+            location: location.to_string(),
+            provider: "markdown".to_string(),
+            content: content.to_string(),
+            mapped,
+        }
+    }
+
+    #[test]
+    fn test_find_duplicate_documents_and_sections() {
+        let webhooks = format!(
+            "# Webhooks\n\n## Retries\n\n{}\n\n## Signing\n\nEvery request carries an HMAC signature of its body in a header that receivers must verify first.\n",
+            RETRIES
+        );
+        let docs = vec![
+            doc("docs/webhooks.md", &webhooks, false),
+            doc(
+                "docs/operations.md",
+                &format!("# Operations\n\n## Delivery retries\n\n{} Alerts fire when it grows.\n\n## Backups\n\nThe database is backed up every night to object storage and kept for thirty days by default.\n", RETRIES),
+                true,
+            ),
+            doc("wiki/Webhooks", &webhooks, false),
+        ];
+
+        let duplicates = find_duplicates(&docs, 0.8);
+        let pairs: Vec<(&str, &str, &str)> = duplicates
+            .iter()
+            .map(|d| {
+                (
+                    d.kind,
+                    d.first.location.as_str(),
+                    d.second.location.as_str(),
+                )
+            })
+            .collect();
+        // The sections of the two copies of the webhooks page are not
+        // reported on top of the documents
+        assert_eq!(
+            pairs,
+            vec![
+                ("document", "docs/webhooks.md", "wiki/Webhooks"),
+                ("section", "docs/webhooks.md", "docs/operations.md"),
+                ("section", "docs/operations.md", "wiki/Webhooks"),
+            ]
+        );
+        assert!(duplicates[0].score > 0.99);
+        assert!(duplicates.iter().all(|d| d.score >= 0.8));
+
+        // The section of the mapped document is the one to keep
+        let sections = &duplicates[1];
+        assert_eq!(sections.first.section.as_deref(), Some("Retries"));
+        assert_eq!(sections.target.location, "docs/operations.md");
+        assert_eq!(sections.target.section.as_deref(), Some("Delivery retries"));
+
+        assert_eq!(find_duplicates(&docs, 0.99).len(), 1);
+    }
+}
@@ -0,0 +1,392 @@
+use super::index::{self, IndexEntry};
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::Result;
+use crate::storage::bundle;
+use crate::storage::mapping::DocumentLocation;
+use chrono::{DateTime, Utc};
+use html_escape::{encode_double_quoted_attribute as attr, encode_text as text};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Characters of a document kept in the search index
+const MAX_INDEXED_CHARS: usize = 20_000;
+
+/// One searchable page or document of the site
+#[derive(Debug, Serialize)]
+struct SearchEntry {
+    title: String,
+    service: String,
+    url: String,
+    text: String,
+}
+
+/// Render every service with its features, mappings and the Markdown
+/// documents it maps into a static HTML site in `output`, searchable through
+/// a pre-built index. The site has no server-side parts, so it can be hosted
+/// on GitHub Pages or opened from disk.
+pub async fn site(output_dir: String) -> Result<()> {
+    tracing::info!("Exporting static site to {}", output_dir);
+
+    let config = Config::load()?;
+    let entries = index::collect(&config)?;
+    let root = Path::new(&output_dir);
+    fs::create_dir_all(root.join("services"))?;
+    fs::create_dir_all(root.join("assets"))?;
+
+    let now = Utc::now();
+    let mut search = Vec::new();
+    for entry in &entries {
+        let page = format!("services/{}.html", slug(&entry.name));
+        fs::write(
+            root.join(&page),
+            service_page(entry, &page, now, &mut search),
+        )?;
+    }
+    fs::write(root.join("index.html"), index_page(&entries, now))?;
+    fs::write(root.join("assets/style.css"), STYLE)?;
+    fs::write(root.join("assets/search.js"), SEARCH_SCRIPT)?;
+    fs::write(
+        root.join("search-index.js"),
+        format!(
+            "window.KTME_SEARCH_INDEX = {};\n",
+            serde_json::to_string(&search)?
+        ),
+    )?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "output": output_dir,
+            "services": entries.len(),
+            "indexed": search.len(),
+        }));
+    }
+    output::message(format!(
+        "✓ Exported {} service(s) to {} ({} searchable pages and documents)",
+        entries.len(),
+        root.join("index.html").display(),
+        search.len()
+    ));
+    Ok(())
+}
+
+/// Write the config without secrets, the database, mappings, templates and
+/// prompts to the state bundle `file`, to set ktme up on another machine
+/// with `ktme import state`
+pub async fn state(file: String) -> Result<()> {
+    tracing::info!("Exporting state to {}", file);
+
+    let config = Config::load()?;
+    let manifest = bundle::export(&config, Path::new(&file))?;
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "output": file,
+            "manifest": manifest,
+        }));
+    }
+    output::message(format!(
+        "✓ Exported {} file(s) of workspace {} to {} (schema {})",
+        manifest.files.len(),
+        manifest.workspace,
+        file,
+        manifest.schema_version
+    ));
+    if !manifest.redacted.is_empty() {
+        output::message(format!(
+            "Left out secrets: {}",
+            manifest.redacted.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+fn index_page(entries: &[IndexEntry], now: DateTime<Utc>) -> String {
+    let mut body = String::from(
+        "<input id=\"search\" type=\"search\" placeholder=\"Search services, features and documentation\" autofocus>\n\
+         <ul id=\"results\"></ul>\n",
+    );
+    if entries.is_empty() {
+        body.push_str(
+            "<p>No services are mapped yet. Add one with <code>ktme mapping add</code>.</p>\n",
+        );
+    }
+    body.push_str("<ul class=\"services\">\n");
+    for entry in entries {
+        body.push_str(&format!(
+            "<li><a href=\"services/{}.html\">{}</a>",
+            attr(&slug(&entry.name)),
+            text(&entry.name)
+        ));
+        if let Some(description) = &entry.description {
+            body.push_str(&format!(" <span>{}</span>", text(description)));
+        }
+        body.push_str(&format!(
+            " <small>{} feature(s), {} document(s)</small></li>\n",
+            entry.features.len(),
+            entry.docs.len()
+        ));
+    }
+    body.push_str("</ul>\n");
+    body.push_str(
+        "<script src=\"search-index.js\"></script>\n<script src=\"assets/search.js\"></script>\n",
+    );
+    layout("Documentation", "", &body, entries.len(), now)
+}
+
+/// Page of one service; its features and documents are added to `search`
+fn service_page(
+    entry: &IndexEntry,
+    page: &str,
+    now: DateTime<Utc>,
+    search: &mut Vec<SearchEntry>,
+) -> String {
+    let mut body = String::from("<p><a href=\"../index.html\">← All services</a></p>\n");
+    body.push_str(&format!("<h1>{}</h1>\n", text(&entry.name)));
+    if let Some(description) = &entry.description {
+        body.push_str(&format!("<p>{}</p>\n", text(description)));
+    }
+    body.push_str("<dl>\n");
+    if let Some(path) = &entry.path {
+        body.push_str(&format!(
+            "<dt>Path</dt><dd><code>{}</code></dd>\n",
+            text(path)
+        ));
+    }
+    let last_updated = entry
+        .last_updated
+        .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| "never".to_string());
+    body.push_str(&format!(
+        "<dt>Last updated</dt><dd>{}</dd>\n</dl>\n",
+        last_updated
+    ));
+    search.push(SearchEntry {
+        title: entry.name.clone(),
+        service: entry.name.clone(),
+        url: page.to_string(),
+        text: entry.description.clone().unwrap_or_default(),
+    });
+
+    body.push_str("<h2>Documentation</h2>\n");
+    if entry.docs.is_empty() {
+        body.push_str("<p>None mapped.</p>\n");
+    } else {
+        body.push_str("<ul>\n");
+        for (number, doc) in entry.docs.iter().enumerate() {
+            let target = match doc_content(doc) {
+                Some(_) => format!("#doc-{}", number + 1),
+                None => doc.location.clone(),
+            };
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a> ({}{})</li>\n",
+                attr(&target),
+                text(&doc.location),
+                text(&doc.r#type),
+                doc.section
+                    .as_deref()
+                    .map(|section| format!(", section {}", text(section)))
+                    .unwrap_or_default()
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+
+    if !entry.features.is_empty() {
+        body.push_str("<h2>Features</h2>\n<table>\n<tr><th>Feature</th><th>Type</th><th>Description</th></tr>\n");
+        for feature in &entry.features {
+            let description = feature.description.as_deref().unwrap_or_default();
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                text(&feature.name),
+                text(&feature.feature_type),
+                text(description)
+            ));
+            search.push(SearchEntry {
+                title: format!("{} ({})", feature.name, feature.feature_type),
+                service: entry.name.clone(),
+                url: page.to_string(),
+                text: description.to_string(),
+            });
+        }
+        body.push_str("</table>\n");
+    }
+
+    for (number, doc) in entry.docs.iter().enumerate() {
+        let Some(content) = doc_content(doc) else {
+            continue;
+        };
+        let id = format!("doc-{}", number + 1);
+        body.push_str(&format!(
+            "<section id=\"{}\" class=\"doc\">\n<p class=\"source\">{}</p>\n{}</section>\n",
+            id,
+            text(&doc.location),
+            markdown_to_html(&content)
+        ));
+        search.push(SearchEntry {
+            title: doc.location.clone(),
+            service: entry.name.clone(),
+            url: format!("{}#{}", page, id),
+            text: content.chars().take(MAX_INDEXED_CHARS).collect(),
+        });
+    }
+
+    layout(&entry.name, "../", &body, 1, now)
+}
+
+/// Content of a Markdown document readable from disk; documents of other
+/// providers are linked instead
+fn doc_content(doc: &DocumentLocation) -> Option<String> {
+    if doc.r#type != "markdown" {
+        return None;
+    }
+    fs::read_to_string(&doc.location).ok()
+}
+
+/// HTML of `markdown`; raw HTML in the document is shown as text
+fn markdown_to_html(markdown: &str) -> String {
+    use pulldown_cmark::{Event, Options, Parser};
+
+    let parser = Parser::new_ext(markdown, Options::all()).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        event => event,
+    });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    html
+}
+
+/// File name of a service page
+fn slug(name: &str) -> String {
+    let slug = index::anchor(name);
+    if slug.is_empty() {
+        "service".to_string()
+    } else {
+        slug
+    }
+}
+
+/// `body` in the page layout; `base` leads from the page to the site root
+fn layout(title: &str, base: &str, body: &str, services: usize, now: DateTime<Utc>) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n<link rel=\"stylesheet\" href=\"{base}assets/style.css\">\n</head>\n\
+         <body>\n<main>\n{body}</main>\n<footer>{services} service(s), exported by ktme on {now}</footer>\n</body>\n</html>\n",
+        title = text(title),
+        base = base,
+        body = body,
+        services = services,
+        now = now.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+const STYLE: &str = r#"body { font-family: system-ui, sans-serif; margin: 0; color: #1f2328; }
+main { max-width: 960px; margin: 0 auto; padding: 2rem 1rem; }
+a { color: #0969da; }
+#search { width: 100%; padding: 0.6rem; font-size: 1rem; box-sizing: border-box; }
+#results li { margin: 0.4rem 0; }
+#results small, .services small, .services span { color: #59636e; margin-left: 0.5rem; }
+.services li { margin: 0.5rem 0; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #d1d9e0; padding: 0.4rem; text-align: left; }
+.doc { border-top: 1px solid #d1d9e0; margin-top: 2rem; }
+.source { color: #59636e; font-family: monospace; }
+pre { background: #f6f8fa; padding: 0.8rem; overflow-x: auto; }
+footer { text-align: center; color: #59636e; padding: 1rem; font-size: 0.85rem; }
+"#;
+
+/// Client-side search over `window.KTME_SEARCH_INDEX`: every word of the
+/// query must appear in the title, service or text of an entry
+const SEARCH_SCRIPT: &str = r#"(function () {
+  var input = document.getElementById("search");
+  var results = document.getElementById("results");
+  var entries = window.KTME_SEARCH_INDEX || [];
+  input.addEventListener("input", function () {
+    var words = input.value.toLowerCase().split(/\s+/).filter(Boolean);
+    results.innerHTML = "";
+    if (!words.length) return;
+    entries
+      .map(function (entry) {
+        var haystack = (entry.title + " " + entry.service + " " + entry.text).toLowerCase();
+        var title = entry.title.toLowerCase();
+        var score = 0;
+        for (var i = 0; i < words.length; i++) {
+          if (haystack.indexOf(words[i]) < 0) return null;
+          score += title.indexOf(words[i]) >= 0 ? 2 : 1;
+        }
+        return { entry: entry, score: score };
+      })
+      .filter(Boolean)
+      .sort(function (a, b) { return b.score - a.score; })
+      .slice(0, 20)
+      .forEach(function (match) {
+        var item = document.createElement("li");
+        var link = document.createElement("a");
+        link.href = match.entry.url;
+        link.textContent = match.entry.title;
+        var service = document.createElement("small");
+        service.textContent = match.entry.service;
+        item.appendChild(link);
+        item.appendChild(service);
+        results.appendChild(item);
+      });
+  });
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::commands::index::IndexFeature;
+
+    #[test]
+    fn test_service_page_is_escaped_and_indexed() {
+        let dir = tempfile::tempdir().unwrap();
+        let doc = dir.path().join("billing.md");
+        fs::write(&doc, "# Billing\n\nInvoices are sent <monthly>.\n").unwrap();
+        let entry = IndexEntry {
+            name: "Billing <API>".to_string(),
+            description: None,
+            path: None,
+            docs: vec![
+                DocumentLocation {
+                    r#type: "markdown".to_string(),
+                    location: doc.to_string_lossy().into_owned(),
+                    section: None,
+                },
+                DocumentLocation {
+                    r#type: "notion".to_string(),
+                    location: "https://www.notion.so/Billing-0123".to_string(),
+                    section: None,
+                },
+            ],
+            last_updated: None,
+            features: vec![IndexFeature {
+                name: "Invoices".to_string(),
+                feature_type: "api".to_string(),
+                description: Some("Create invoices".to_string()),
+            }],
+        };
+
+        let mut search = Vec::new();
+        let page = service_page(&entry, "services/billing-api.html", Utc::now(), &mut search);
+        assert!(page.contains("<h1>Billing &lt;API&gt;</h1>"));
+        assert!(page.contains("<h1>Billing</h1>"));
+        assert!(page.contains("Invoices are sent &lt;monthly&gt;."));
+        assert!(page.contains("<a href=\"https://www.notion.so/Billing-0123\">"));
+        assert!(page.contains("<section id=\"doc-1\" class=\"doc\">"));
+        assert!(!page.contains("id=\"doc-2\""));
+
+        let urls: Vec<&str> = search.iter().map(|entry| entry.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "services/billing-api.html",
+                "services/billing-api.html",
+                "services/billing-api.html#doc-1"
+            ]
+        );
+        assert_eq!(slug("Billing <API>"), "billing-api");
+    }
+}
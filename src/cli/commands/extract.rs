@@ -1,192 +1,198 @@
 use crate::config::Config;
 use crate::error::Result;
+use crate::git::cache::CommitDiffCache;
 use crate::git::diff::{DiffExtractor, ExtractedDiff};
-use crate::git::providers::github::GitHubProvider;
+use crate::git::providers::PullRequestSource;
+use crate::git::reader::DiffLimits;
+use crate::git::remote::{token_for, RemoteCheckout};
 use std::fs;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Output format for `ktme extract`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExtractFormat {
+    /// The full `ExtractedDiff` as JSON (readable by `generate --input`)
+    #[default]
+    Json,
+    /// The full `ExtractedDiff` as YAML
+    Yaml,
+    /// Unified-diff style patch of the changed files
+    Patch,
+    /// Human-readable summary
+    Summary,
+}
 
+#[allow(clippy::too_many_arguments)]
 pub async fn execute(
     commit: Option<String>,
     staged: bool,
     pr: Option<u32>,
     provider: Option<String>,
+    repo: Option<String>,
+    repos: Vec<String>,
     output: Option<String>,
+    format: ExtractFormat,
+    pretty: bool,
+    max_file_diff: Option<usize>,
+    spill: Option<String>,
 ) -> Result<()> {
     tracing::info!("Extracting code changes...");
 
-    let extracted_diff = if let Some(commit_ref) = commit {
+    let config = Config::load().unwrap_or_default();
+    let mut limits = DiffLimits::from_config(&config.git);
+    if let Some(max) = max_file_diff {
+        limits.max_file_bytes = (max > 0).then_some(max);
+    }
+    limits.spill_path = spill.map(PathBuf::from);
+
+    let extracted_diff = if let (Some(url), Some(commit_ref)) = (repo, &commit) {
+        tracing::info!("Extracting commit {} from {}", commit_ref, url);
+        crate::http::ensure_online(&format!("Fetching {}", url))?;
+        let token = token_for(&config.git, &url);
+        RemoteCheckout::fetch(&url, commit_ref, token.as_deref(), limits)?.read_commit()?
+    } else if !repos.is_empty() {
+        let (source, identifier) = match (&commit, staged) {
+            (Some(commit_ref), _) => ("commit", commit_ref.as_str()),
+            (None, true) => ("staged", "staged"),
+            (None, false) => {
+                return Err(crate::error::KtmeError::InvalidInput(
+                    "--repos needs --commit or --staged".to_string(),
+                ))
+            }
+        };
+        tracing::info!("Extracting {} from {}", identifier, repos.join(", "));
+        let cache = CommitDiffCache::from_config(&config);
+        DiffExtractor::extract_repositories(source, identifier, &repos, limits, cache.as_ref())?
+    } else if let Some(commit_ref) = commit {
         tracing::info!("Extracting from commit: {}", commit_ref);
-        let extractor = DiffExtractor::new("commit".to_string(), commit_ref.clone(), None)?;
+        let extractor = DiffExtractor::new("commit".to_string(), commit_ref.clone(), None)?
+            .with_limits(limits)
+            .with_cache(CommitDiffCache::from_config(&config));
         extractor.extract()?
     } else if staged {
         tracing::info!("Extracting staged changes");
-        let extractor = DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?;
+        let extractor = DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?
+            .with_limits(limits);
         extractor.extract()?
     } else if let Some(pr_number) = pr {
-        let provider_name = provider.unwrap_or_else(|| "github".to_string());
-        tracing::info!("Extracting from PR #{} ({})", pr_number, provider_name);
-
-        match provider_name.to_lowercase().as_str() {
-            "github" => {
-                // Load config to get token
-                let config = Config::load().unwrap_or_default();
-                let github = GitHubProvider::from_config(config.git.github_token);
-
-                // Get repository from current directory or error
-                let repo = detect_github_repo()?;
-
-                github.fetch_pull_request(&repo, pr_number).await?
-            }
-            "gitlab" => {
-                // Load config to get token
-                let config = Config::load().unwrap_or_default();
-                let gitlab = crate::git::providers::gitlab::GitLabProvider::from_config(
-                    config.git.gitlab_token,
-                );
-
-                // Get project from current directory or error
-                let project = detect_gitlab_project()?;
-
-                gitlab.fetch_merge_request(&project, pr_number).await?
-            }
-            _ => {
-                return Err(crate::error::KtmeError::UnsupportedProvider(format!(
-                    "Unknown provider: {}",
-                    provider_name
-                )));
-            }
-        }
+        crate::http::ensure_online(&format!("Extracting PR #{}", pr_number))?;
+
+        // The hosting service and repository come from the origin remote
+        let source = PullRequestSource::detect(&config.git, provider.as_deref())?;
+        tracing::info!(
+            "Extracting from PR #{} of {} ({:?})",
+            pr_number,
+            source.slug,
+            source.hosting
+        );
+        source.fetch(&config.git, pr_number).await?
     } else {
         return Err(crate::error::KtmeError::InvalidInput(
             "No source specified. Use --commit, --staged, or --pr".to_string(),
         ));
     };
 
-    // Print summary to console
-    print_diff_summary(&extracted_diff);
+    // Files are always pretty-printed; stdout is compact unless --pretty
+    let rendered = render(&extracted_diff, format, pretty || output.is_some())?;
 
-    // Save to file if requested
-    if let Some(output_path) = output {
-        save_to_file(&extracted_diff, &output_path)?;
-        tracing::info!("Output saved to: {}", output_path);
+    match output {
+        Some(output_path) => {
+            save_to_file(&rendered, &output_path)?;
+            tracing::info!("Output saved to: {}", output_path);
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(rendered.as_bytes())?;
+            if !rendered.ends_with('\n') {
+                stdout.write_all(b"\n")?;
+            }
+        }
     }
 
     Ok(())
 }
 
-/// Detect GitHub repository from Git remote
-fn detect_github_repo() -> Result<String> {
-    use git2::Repository;
-
-    let repo = Repository::open(".").map_err(|e| crate::error::KtmeError::Git(e))?;
-
-    let remote = repo
-        .find_remote("origin")
-        .map_err(|e| crate::error::KtmeError::Git(e))?;
-
-    let url = remote
-        .url()
-        .ok_or_else(|| crate::error::KtmeError::InvalidInput("No remote URL found".to_string()))?;
-
-    // Parse GitHub URL to owner/repo format
-    // Handles both HTTPS and SSH formats:
-    // - https://github.com/owner/repo.git
-    // - git@github.com:owner/repo.git
-    let repo_path = if url.starts_with("https://github.com/") {
-        url.trim_start_matches("https://github.com/")
-            .trim_end_matches(".git")
-    } else if url.starts_with("git@github.com:") {
-        url.trim_start_matches("git@github.com:")
-            .trim_end_matches(".git")
-    } else {
-        return Err(crate::error::KtmeError::InvalidInput(format!(
-            "Not a GitHub repository: {}",
-            url
-        )));
-    };
-
-    Ok(repo_path.to_string())
+/// Render an extracted diff in the requested format
+pub fn render(diff: &ExtractedDiff, format: ExtractFormat, pretty: bool) -> Result<String> {
+    match format {
+        ExtractFormat::Json if pretty => Ok(serde_json::to_string_pretty(diff)?),
+        ExtractFormat::Json => Ok(serde_json::to_string(diff)?),
+        ExtractFormat::Yaml => serde_yaml::to_string(diff).map_err(|e| {
+            crate::error::KtmeError::SerializationError(format!("Failed to render YAML: {}", e))
+        }),
+        ExtractFormat::Patch => Ok(format_patch(diff)),
+        ExtractFormat::Summary => Ok(format_summary(diff)),
+    }
 }
 
-/// Detect GitLab project from Git remote
-fn detect_gitlab_project() -> Result<String> {
-    use git2::Repository;
-
-    let repo = Repository::open(".").map_err(|e| crate::error::KtmeError::Git(e))?;
-
-    let remote = repo
-        .find_remote("origin")
-        .map_err(|e| crate::error::KtmeError::Git(e))?;
-
-    let url = remote
-        .url()
-        .ok_or_else(|| crate::error::KtmeError::InvalidInput("No remote URL found".to_string()))?;
-
-    // Parse GitLab URL to namespace/project format
-    // Handles both HTTPS and SSH formats:
-    // - https://gitlab.com/namespace/project.git
-    // - git@gitlab.com:namespace/project.git
-    // Also supports self-hosted GitLab instances
-
-    let project_path = if url.contains("gitlab.com") {
-        if url.starts_with("https://gitlab.com/") {
-            url.trim_start_matches("https://gitlab.com/")
-                .trim_end_matches(".git")
-        } else if url.starts_with("git@gitlab.com:") {
-            url.trim_start_matches("git@gitlab.com:")
-                .trim_end_matches(".git")
-        } else {
-            return Err(crate::error::KtmeError::InvalidInput(format!(
-                "Unsupported GitLab URL format: {}",
-                url
-            )));
+fn format_patch(diff: &ExtractedDiff) -> String {
+    let mut patch = String::new();
+
+    for file in &diff.files {
+        let old_path = file.old_path.as_deref().unwrap_or(&file.path);
+        let (old, new) = match file.status.as_str() {
+            "added" => ("/dev/null".to_string(), format!("b/{}", file.path)),
+            "deleted" => (format!("a/{}", file.path), "/dev/null".to_string()),
+            _ => (format!("a/{}", old_path), format!("b/{}", file.path)),
+        };
+
+        patch.push_str(&format!("diff --git a/{} b/{}\n", old_path, file.path));
+        if old_path != file.path {
+            let kind = if file.status == "copied" {
+                "copy"
+            } else {
+                "rename"
+            };
+            patch.push_str(&format!(
+                "{0} from {1}\n{0} to {2}\n",
+                kind, old_path, file.path
+            ));
         }
-    } else if url.contains("://") {
-        // HTTPS format for self-hosted GitLab
-        url.split("://")
-            .nth(1)
-            .and_then(|s| s.split_once('/'))
-            .map(|(_, path)| path.trim_end_matches(".git"))
-            .ok_or_else(|| {
-                crate::error::KtmeError::InvalidInput(format!(
-                    "Could not parse GitLab project path from: {}",
-                    url
-                ))
-            })?
-    } else if url.starts_with("git@") {
-        // SSH format for self-hosted GitLab
-        url.split(':')
-            .nth(1)
-            .map(|path| path.trim_end_matches(".git"))
-            .ok_or_else(|| {
-                crate::error::KtmeError::InvalidInput(format!(
-                    "Could not parse GitLab project path from: {}",
-                    url
-                ))
-            })?
-    } else {
-        return Err(crate::error::KtmeError::InvalidInput(format!(
-            "Not a GitLab repository: {}",
-            url
-        )));
-    };
+        patch.push_str(&format!("--- {}\n+++ {}\n", old, new));
+        patch.push_str(&file.diff);
+        if !file.diff.is_empty() && !file.diff.ends_with('\n') {
+            patch.push('\n');
+        }
+    }
 
-    Ok(project_path.to_string())
+    patch
 }
 
-fn print_diff_summary(diff: &ExtractedDiff) {
-    println!("\n📊 Diff Summary");
-    println!("Source: {}", diff.source);
-    println!("Identifier: {}", diff.identifier);
-    println!("Author: {}", diff.author);
-    println!("Timestamp: {}", diff.timestamp);
-    println!("Message: {}", diff.message);
-    println!("Files changed: {}", diff.summary.total_files);
-    println!("Additions: +{}", diff.summary.total_additions);
-    println!("Deletions: -{}", diff.summary.total_deletions);
+fn format_summary(diff: &ExtractedDiff) -> String {
+    let mut out = String::new();
+    out.push_str("📊 Diff Summary\n");
+    out.push_str(&format!("Source: {}\n", diff.source));
+    out.push_str(&format!("Identifier: {}\n", diff.identifier));
+    out.push_str(&format!("Author: {}\n", diff.author));
+    out.push_str(&format!("Timestamp: {}\n", diff.timestamp));
+    out.push_str(&format!("Message: {}\n", diff.message));
+    out.push_str(&format!("Files changed: {}\n", diff.summary.total_files));
+    out.push_str(&format!("Additions: +{}\n", diff.summary.total_additions));
+    out.push_str(&format!("Deletions: -{}\n", diff.summary.total_deletions));
+
+    for repository in &diff.repositories {
+        out.push_str(&format!(
+            "\n📦 {} ({}, {})\n",
+            repository.name, repository.path, repository.identifier
+        ));
+        let subject = repository.message.lines().next().unwrap_or_default();
+        out.push_str(&format!("  {}\n", subject.trim()));
+        out.push_str(&format!("  Files changed: {}\n", repository.files.len()));
+    }
+
+    if let Some(pr) = &diff.pull_request {
+        if !pr.labels.is_empty() {
+            out.push_str(&format!("Labels: {}\n", pr.labels.join(", ")));
+        }
+        if !pr.linked_issues.is_empty() {
+            out.push_str(&format!("Linked issues: {}\n", pr.linked_issues.join(", ")));
+        }
+        out.push_str(&format!("Review comments: {}\n", pr.review_comments.len()));
+    }
 
     if !diff.files.is_empty() {
-        println!("\n📁 Files:");
+        out.push_str("\n📁 Files:\n");
         for file in &diff.files {
             let icon = match file.status.as_str() {
                 "added" => "🆕",
@@ -195,24 +201,26 @@ fn print_diff_summary(diff: &ExtractedDiff) {
                 "renamed" => "🔄",
                 _ => "📄",
             };
-            println!(
-                "  {} {} (+{}/-{})",
-                icon, file.path, file.additions, file.deletions
-            );
+            out.push_str(&format!(
+                "  {} {} (+{}/-{})\n",
+                icon,
+                file.display_path(),
+                file.additions,
+                file.deletions
+            ));
         }
     }
-}
 
-fn save_to_file(diff: &ExtractedDiff, path: &str) -> Result<()> {
-    let json_output = serde_json::to_string_pretty(diff)
-        .map_err(|e| crate::error::KtmeError::Serialization(e))?;
+    out
+}
 
+fn save_to_file(content: &str, path: &str) -> Result<()> {
     // Create parent directories if they don't exist
     if let Some(parent) = Path::new(path).parent() {
         fs::create_dir_all(parent).map_err(|e| crate::error::KtmeError::Io(e))?;
     }
 
-    fs::write(path, json_output).map_err(|e| crate::error::KtmeError::Io(e))?;
+    fs::write(path, content).map_err(crate::error::KtmeError::Io)?;
 
     Ok(())
 }
@@ -0,0 +1,414 @@
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::knowledge::ranking::{self, RankingPolicy};
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::models::{Feature, FeatureRelation, RelationType, SearchQuery, SearchResult};
+use chrono::Utc;
+
+/// Link feature `from` to feature `to`, e.g. `checkout depends-on payments`
+pub async fn link(
+    from: String,
+    to: String,
+    relation: String,
+    strength: f64,
+    service: Option<String>,
+) -> Result<()> {
+    let relation_type: RelationType = relation.parse()?;
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let (relation, created) = link_features(
+        storage.as_ref(),
+        &from,
+        &to,
+        relation_type,
+        strength,
+        service.as_deref(),
+    )?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "relation": relation,
+            "created": created,
+        }));
+    }
+    let verb = if created { "Linked" } else { "Already linked" };
+    output::message(format!(
+        "{}: {} {} {}",
+        verb, from, relation.relation_type, to
+    ));
+    Ok(())
+}
+
+/// Remove the relations from feature `from` to feature `to`
+pub async fn unlink(from: String, to: String, service: Option<String>) -> Result<()> {
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let removed = unlink_features(storage.as_ref(), &from, &to, service.as_deref())?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "from": from,
+            "to": to,
+            "removed": removed,
+        }));
+    }
+    if removed {
+        output::message(format!("Unlinked {} from {}", from, to));
+    } else {
+        output::message(format!("{} is not linked to {}", from, to));
+    }
+    Ok(())
+}
+
+/// Search the features of the knowledge graph, optionally with the features
+/// related to the matches
+pub async fn search(
+    query: String,
+    service: Option<String>,
+    related: bool,
+    depth: Option<u32>,
+    limit: u32,
+) -> Result<()> {
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let results = search_features(
+        storage.as_ref(),
+        &query,
+        service.as_deref(),
+        related || depth.is_some(),
+        depth,
+        limit,
+    )?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "query": query,
+            "results": results,
+        }));
+    }
+    if results.is_empty() {
+        println!("No features found matching: {}", query);
+        return Ok(());
+    }
+    println!("Features matching '{}':\n", query);
+    for (number, result) in results.iter().enumerate() {
+        println!(
+            "{}. {} ({}, {}) — relevance {:.2}",
+            number + 1,
+            result.feature_name,
+            result.service_name,
+            result.feature_type,
+            result.relevance_score
+        );
+        if let Some(description) = &result.description {
+            println!("   {}", description);
+        }
+        if let Some(related_via) = &result.related_via {
+            println!("   ↳ related: {}", related_via);
+        }
+    }
+    Ok(())
+}
+
+/// Find a feature by id, or by case-insensitive name within `service` (or
+/// every service). A name shared by features of several services is an
+/// error rather than a guess.
+pub(crate) fn resolve_feature(
+    storage: &dyn Storage,
+    feature: &str,
+    service: Option<&str>,
+) -> Result<Feature> {
+    if let Some(found) = storage.get_feature(feature)? {
+        return Ok(found);
+    }
+
+    let services = match service {
+        Some(name) => vec![storage
+            .get_service_by_name(name)?
+            .ok_or_else(|| KtmeError::NotFound(format!("Service '{}' not found", name)))?],
+        None => storage.list_services()?,
+    };
+    let mut matches = Vec::new();
+    for candidate in &services {
+        for found in storage.features_for_service(candidate.id)? {
+            if found.name.eq_ignore_ascii_case(feature) {
+                matches.push((candidate.name.clone(), found));
+            }
+        }
+    }
+
+    match matches.len() {
+        0 => Err(KtmeError::NotFound(match service {
+            Some(service) => format!("Feature '{}' not found in service '{}'", feature, service),
+            None => format!("Feature '{}' not found", feature),
+        })),
+        1 => Ok(matches.remove(0).1),
+        _ => Err(KtmeError::InvalidInput(format!(
+            "Feature '{}' exists in several services ({}); name the service or use the feature id",
+            feature,
+            matches
+                .iter()
+                .map(|(service, _)| service.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Relate `from` to `to`. Linking two features again with the same relation
+/// type returns the existing relation, reported as not created.
+pub(crate) fn link_features(
+    storage: &dyn Storage,
+    from: &str,
+    to: &str,
+    relation_type: RelationType,
+    strength: f64,
+    service: Option<&str>,
+) -> Result<(FeatureRelation, bool)> {
+    if !(0.0..=1.0).contains(&strength) {
+        return Err(KtmeError::InvalidInput(format!(
+            "Relation strength must be between 0 and 1, got {}",
+            strength
+        )));
+    }
+    let parent = resolve_feature(storage, from, service)?;
+    let child = resolve_feature(storage, to, service)?;
+    if parent.id == child.id {
+        return Err(KtmeError::InvalidInput(format!(
+            "Cannot link feature '{}' to itself",
+            parent.name
+        )));
+    }
+
+    if let Some(existing) = storage.relations_of(&parent.id)?.into_iter().find(|r| {
+        r.parent_feature_id == parent.id
+            && r.child_feature_id == child.id
+            && r.relation_type == relation_type
+    }) {
+        return Ok((existing, false));
+    }
+    let relation = storage.create_relation(
+        &uuid::Uuid::new_v4().to_string(),
+        &parent.id,
+        &child.id,
+        relation_type,
+        strength,
+        serde_json::json!({}),
+    )?;
+    Ok((relation, true))
+}
+
+/// Remove the relations from `from` to `to`; false when there were none
+pub(crate) fn unlink_features(
+    storage: &dyn Storage,
+    from: &str,
+    to: &str,
+    service: Option<&str>,
+) -> Result<bool> {
+    let parent = resolve_feature(storage, from, service)?;
+    let child = resolve_feature(storage, to, service)?;
+    storage.delete_relation(&parent.id, &child.id)
+}
+
+/// Features matching `query` in `service` (or every service), followed by
+/// the features related to them when `related` is set
+pub(crate) fn search_features(
+    storage: &dyn Storage,
+    query: &str,
+    service: Option<&str>,
+    related: bool,
+    depth: Option<u32>,
+    limit: u32,
+) -> Result<Vec<SearchResult>> {
+    let service_ids = match service {
+        Some(name) => Some(vec![
+            storage
+                .get_service_by_name(name)?
+                .ok_or_else(|| KtmeError::NotFound(format!("Service '{}' not found", name)))?
+                .id,
+        ]),
+        None => None,
+    };
+    let results = storage.search_features(&SearchQuery {
+        query: query.to_string(),
+        service_ids,
+        feature_types: None,
+        content_types: None,
+        limit: Some(limit),
+        offset: None,
+        min_score: None,
+        similarity_threshold: None,
+        include_related: related,
+        depth,
+    })?;
+    record_usage(
+        storage,
+        results.iter().map(|result| result.feature_id.as_str()),
+    );
+    Ok(results)
+}
+
+/// Count the retrieval of features towards their ranking; a failure to
+/// count does not fail the retrieval
+pub(crate) fn record_usage<'a>(storage: &dyn Storage, feature_ids: impl Iterator<Item = &'a str>) {
+    let mut ids: Vec<&str> = feature_ids.collect();
+    ids.sort_unstable();
+    ids.dedup();
+    if let Err(e) = storage.record_feature_usage(&ids) {
+        tracing::warn!("Could not record feature usage: {}", e);
+    }
+}
+
+/// Decay the relevance of unused features and boost the retrieved ones
+pub async fn rank(half_life_days: f64, usage_boost: f64, dry_run: bool) -> Result<()> {
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let policy = RankingPolicy {
+        half_life_days,
+        usage_boost,
+    };
+    let changes = ranking::rank(storage.as_ref(), &policy, Utc::now(), dry_run)?;
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "dry_run": dry_run,
+            "features": changes,
+        }));
+    }
+    if changes.is_empty() {
+        println!("No features to rank.");
+        return Ok(());
+    }
+
+    let used: Vec<_> = changes.iter().filter(|change| change.uses > 0).collect();
+    let verb = if dry_run { "Would rank" } else { "Ranked" };
+    println!(
+        "{} {} feature(s); {} retrieved since the last ranking",
+        verb,
+        changes.len(),
+        used.len()
+    );
+    for change in used {
+        let name = storage
+            .get_feature(&change.feature_id)?
+            .map(|feature| feature.name)
+            .unwrap_or_else(|| change.feature_id.clone());
+        println!(
+            "  {} — {:.2} → {:.2} ({} retrieval(s))",
+            name, change.previous, change.score, change.uses
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
+    use crate::storage::models::FeatureType;
+
+    fn feature(storage: &dyn Storage, service_id: i64, name: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        storage
+            .create_feature(
+                &id,
+                service_id,
+                name,
+                Some(&format!("{} feature", name)),
+                FeatureType::BusinessLogic,
+                Vec::new(),
+                serde_json::json!({}),
+            )
+            .unwrap();
+        id
+    }
+
+    #[test]
+    fn test_link_resolve_and_search_related() {
+        let storage = SqliteStorage::new(Database::in_memory().unwrap());
+        let shop = storage.create_service("shop", None, None).unwrap();
+        let billing = storage.create_service("billing", None, None).unwrap();
+        let checkout = feature(&storage, shop.id, "Checkout");
+        storage.update_feature_relevance(&checkout, 0.8).unwrap();
+        feature(&storage, shop.id, "Invoices");
+        feature(&storage, billing.id, "Payments");
+        feature(&storage, billing.id, "Invoices");
+
+        let (relation, created) = link_features(
+            &storage,
+            "checkout",
+            "Payments",
+            "depends-on".parse().unwrap(),
+            1.0,
+            None,
+        )
+        .unwrap();
+        assert!(created);
+        assert_eq!(relation.relation_type, RelationType::DependsOn);
+        let (again, created) = link_features(
+            &storage,
+            "Checkout",
+            "Payments",
+            RelationType::DependsOn,
+            1.0,
+            None,
+        )
+        .unwrap();
+        assert!(!created);
+        assert_eq!(again.id, relation.id);
+
+        // "Invoices" exists in both services
+        assert!(matches!(
+            link_features(
+                &storage,
+                "Invoices",
+                "Payments",
+                RelationType::Uses,
+                1.0,
+                None
+            ),
+            Err(KtmeError::InvalidInput(_))
+        ));
+        link_features(
+            &storage,
+            "Payments",
+            "Invoices",
+            RelationType::Uses,
+            0.5,
+            Some("billing"),
+        )
+        .unwrap();
+
+        let direct = search_features(&storage, "Checkout", Some("shop"), false, None, 10).unwrap();
+        assert_eq!(direct.len(), 1);
+
+        let related = search_features(&storage, "Checkout", Some("shop"), true, None, 10).unwrap();
+        let names: Vec<(&str, Option<&str>)> = related
+            .iter()
+            .map(|r| (r.feature_name.as_str(), r.related_via.as_deref()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Checkout", None),
+                ("Payments", Some("Checkout depends_on Payments")),
+            ]
+        );
+        assert_eq!(related[1].service_name, "billing");
+
+        let deeper = search_features(&storage, "Checkout", None, true, Some(2), 10).unwrap();
+        assert_eq!(deeper.len(), 3);
+        assert_eq!(
+            deeper[2].related_via.as_deref(),
+            Some("Payments uses Invoices")
+        );
+        // Weighted by strength and halved at each hop: 0.8 * 1.0 / 2, 0.4 * 0.5 / 2
+        assert!((deeper[1].relevance_score - 0.4).abs() < 1e-9);
+        assert!((deeper[2].relevance_score - 0.1).abs() < 1e-9);
+
+        assert!(unlink_features(&storage, "Checkout", "Payments", None).unwrap());
+        assert!(!unlink_features(&storage, "Checkout", "Payments", None).unwrap());
+    }
+}
@@ -1,127 +1,1509 @@
+use super::update::publish_to_location;
+use crate::ai::prompt_pack::PromptPack;
 use crate::ai::{prompts::PromptTemplates, AIClient};
+use crate::ai::{routing, template};
+use crate::analysis::doc_comments;
+use crate::cli::detect;
+use crate::cli::output;
+use crate::cli::parallel::run_limited;
+use crate::cli::progress::Progress;
+use crate::config::{AiConfig, Config};
+use crate::doc::annotations::{self, Annotation};
+use crate::doc::idempotency::GenerationKey;
+use crate::doc::notices::{NoticeContext, Notices};
+use crate::doc::personas::{self, release_notes::Audience, PersonaOptions};
+use crate::doc::providers::markdown::write_atomic;
+use crate::doc::providers::PublishStatus;
+use crate::doc::redact;
+use crate::doc::sections::{DocKind, SectionFormat, SectionUpdate, UpdateMode};
 use crate::error::{KtmeError, Result};
+use crate::git::cache::CommitDiffCache;
+use crate::git::commit::CommitDocs;
 use crate::git::diff::{DiffExtractor, ExtractedDiff};
-use crate::storage::database::Database;
-use crate::storage::models::FeatureType;
-use crate::storage::repository::{FeatureRepository, ServiceRepository};
+use crate::git::ownership::{self, Ownership, AUTO_SERVICE};
+use crate::git::providers::PullRequestSource;
+use crate::git::reader::DiffLimits;
+use crate::issues::{render_related_tickets, IssueLinker, RelatedTicket};
+use crate::plugins::extensions::Extensions;
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::lock::ServiceLock;
+use crate::storage::mapping::{self, DocumentLocation, StorageManager};
+use crate::storage::models::{FeatureType, Job, PromptTemplate, ServiceSettings};
+use crate::{git, http, shutdown, translation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 // Constants for feature tracking
 const MIN_CHANGES_FOR_FEATURE: u32 = 5;
 const FEATURE_DESCRIPTION_MAX_LENGTH: usize = 200;
 
+/// Generate documentation, failing when the run takes longer than
+/// `timeout` (default: `[timeouts] operation`). Without `services` the
+/// service is detected from the current directory, confirmed unless `yes`.
 #[allow(clippy::too_many_arguments)]
 pub async fn execute(
     commit: Option<String>,
     input: Option<String>,
     pr: Option<u32>,
+    provider: Option<String>,
     staged: bool,
-    service: String,
+    range: Option<String>,
+    at: Option<String>,
+    repos: Vec<String>,
+    services: Vec<String>,
+    yes: bool,
     doc_type: Option<String>,
     format: Option<String>,
     output: Option<String>,
     template: Option<String>,
+    jobs: Option<usize>,
+    force: bool,
+    queue: bool,
+    variables: Vec<String>,
+    audience: Audience,
+    audit: bool,
+    publish: Option<String>,
+    timeout: Option<String>,
+    wait: bool,
+    commit_docs: Option<CommitDocs>,
+    redact_code: bool,
 ) -> Result<()> {
-    tracing::info!("Generating documentation for service: {}", service);
+    let services = if services.is_empty() {
+        vec![detect::service(yes).await?]
+    } else {
+        services
+    };
+    let args = GenerateJob {
+        commit,
+        input,
+        pr,
+        provider,
+        staged,
+        range,
+        at,
+        repos,
+        services,
+        doc_type,
+        format,
+        output,
+        template,
+        jobs,
+        force,
+        queue,
+        variables,
+        audience,
+        audit,
+        publish,
+        wait,
+        commit_docs,
+        redact_code,
+    };
+    with_deadline(run(args, None), timeout.as_deref()).await
+}
 
-    // Auto-initialize if not already done
-    check_and_initialize(&service).await?;
+/// Generate the services of a failed multi-service run `job` that did not
+/// finish
+pub(crate) async fn resume(job: &Job) -> Result<()> {
+    let mut args: GenerateJob = serde_json::from_str(&job.params)?;
+    args.services = job
+        .remaining_steps()
+        .map(|step| step.name.clone())
+        .collect();
+    with_deadline(run(args, Some(job.id)), None).await
+}
+
+async fn with_deadline(run: impl Future<Output = Result<()>>, timeout: Option<&str>) -> Result<()> {
+    let timeouts = Config::load()?.timeouts;
+    let deadline = timeouts.operation_timeout(timeout)?;
+    http::configure(&timeouts);
+    http::with_deadline("Generating documentation", deadline, run).await
+}
+
+fn no_source() -> KtmeError {
+    KtmeError::InvalidInput(
+        "No source specified. Use --commit, --range, --at, --input, --staged, or --pr".to_string(),
+    )
+}
+
+/// Arguments of `ktme generate`, stored with the job of a multi-service run
+/// so it can be resumed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct GenerateJob {
+    commit: Option<String>,
+    input: Option<String>,
+    pr: Option<u32>,
+    #[serde(default)]
+    provider: Option<String>,
+    staged: bool,
+    range: Option<String>,
+    #[serde(default)]
+    at: Option<String>,
+    #[serde(default)]
+    repos: Vec<String>,
+    services: Vec<String>,
+    doc_type: Option<String>,
+    format: Option<String>,
+    output: Option<String>,
+    template: Option<String>,
+    jobs: Option<usize>,
+    force: bool,
+    queue: bool,
+    variables: Vec<String>,
+    audience: Audience,
+    audit: bool,
+    publish: Option<String>,
+    #[serde(default)]
+    wait: bool,
+    #[serde(default)]
+    commit_docs: Option<CommitDocs>,
+    #[serde(default)]
+    redact_code: bool,
+}
+
+/// Generate documentation; `job` is the job of a resumed multi-service run
+async fn run(args: GenerateJob, job: Option<i64>) -> Result<()> {
+    let params = serde_json::to_string(&args)?;
+    let GenerateJob {
+        commit,
+        input,
+        pr,
+        provider,
+        staged,
+        range,
+        at,
+        repos,
+        services,
+        doc_type,
+        format,
+        output,
+        template,
+        jobs,
+        force,
+        queue,
+        variables,
+        audience,
+        audit,
+        publish,
+        wait,
+        commit_docs,
+        redact_code,
+    } = args;
+    tracing::info!(
+        "Generating documentation for service(s): {}",
+        services.join(", ")
+    );
+
+    if services.is_empty() {
+        return Err(KtmeError::InvalidInput(
+            "At least one --service is required".to_string(),
+        ));
+    }
+    let auto = services.iter().any(|s| s == AUTO_SERVICE);
+    if auto && services.len() > 1 {
+        return Err(KtmeError::InvalidInput(format!(
+            "--service {} cannot be combined with other services",
+            AUTO_SERVICE
+        )));
+    }
+    let concurrent = services.len() > 1;
+    if !auto {
+        check_output_placeholder(output.as_deref(), concurrent)?;
+    }
+
+    // With --json and no output file the JSON document is the command output
+    let format = if output::is_json() && output.is_none() {
+        Some("json".to_string())
+    } else {
+        format
+    };
+
+    let variables = template::parse_variables(&variables)?;
+    let config = Config::load()?;
+
+    // Open the storage backend once for the whole command
+    let storage = open_storage(&config.storage)?;
+
+    // A service spanning several repositories documents the changes of all
+    // of them unless --repos names the ones to read
+    let repos = match services.as_slice() {
+        [service] if repos.is_empty() && !auto => {
+            mapping::service_settings(&config, storage.as_ref(), service)?.repositories
+        }
+        _ => repos,
+    };
+
+    let progress = Progress::spinner("Extracting changes");
 
     // Get the diff data
+    let limits = DiffLimits::from_config(&config.git);
+    let diff_cache = CommitDiffCache::from_config(&config);
     let diff = if let Some(input_file) = input {
         tracing::info!("Using input file: {}", input_file);
         load_diff_from_file(&input_file)?
+    } else if !repos.is_empty() {
+        let (source, identifier) = if let Some(commit_ref) = commit {
+            ("commit", commit_ref)
+        } else if staged {
+            ("staged", "staged".to_string())
+        } else if let Some(range) = range {
+            ("range", range)
+        } else if let Some(at) = at {
+            ("snapshot", at)
+        } else if pr.is_some() {
+            return Err(KtmeError::InvalidInput(
+                "--pr cannot be combined with the repositories of a service".to_string(),
+            ));
+        } else if doc_type.as_deref() == Some("onboarding") {
+            ("commit", "HEAD".to_string())
+        } else {
+            return Err(no_source());
+        };
+        tracing::info!("Using {} {} of {}", source, identifier, repos.join(", "));
+        let diff = DiffExtractor::extract_repositories(
+            source,
+            &identifier,
+            &repos,
+            limits,
+            diff_cache.as_ref(),
+        )?;
+        if diff.commits.len() > config.git.max_commit_range as usize {
+            return Err(KtmeError::InvalidInput(format!(
+                "Range {} has {} commits, more than git.max_commit_range ({})",
+                identifier,
+                diff.commits.len(),
+                config.git.max_commit_range
+            )));
+        }
+        diff
     } else if let Some(commit_ref) = commit {
         tracing::info!("Using commit: {}", commit_ref);
-        let extractor = DiffExtractor::new("commit".to_string(), commit_ref, None)?;
+        let extractor = DiffExtractor::new("commit".to_string(), commit_ref, None)?
+            .with_limits(limits)
+            .with_cache(diff_cache);
         extractor.extract()?
     } else if staged {
         tracing::info!("Using staged changes");
-        let extractor = DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?;
+        let extractor = DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?
+            .with_limits(limits);
+        extractor.extract()?
+    } else if let Some(range) = range {
+        tracing::info!("Using commit range: {}", range);
+        let extractor = DiffExtractor::new("range".to_string(), range.clone(), None)?
+            .with_limits(limits)
+            .with_cache(diff_cache);
+        let diffs = extractor.extract_range(&range)?;
+        if diffs.is_empty() {
+            return Err(KtmeError::InvalidInput(format!(
+                "No commits in range {}",
+                range
+            )));
+        }
+        if diffs.len() > config.git.max_commit_range as usize {
+            return Err(KtmeError::InvalidInput(format!(
+                "Range {} has {} commits, more than git.max_commit_range ({})",
+                range,
+                diffs.len(),
+                config.git.max_commit_range
+            )));
+        }
+        ExtractedDiff::combine(&range, diffs)
+    } else if let Some(at) = at {
+        tracing::info!("Using repository snapshot at {}", at);
+        let extractor = DiffExtractor::new("snapshot".to_string(), at, None)?.with_limits(limits);
         extractor.extract()?
     } else if let Some(pr_number) = pr {
         tracing::info!("Using PR: #{}", pr_number);
-        return Err(crate::error::KtmeError::UnsupportedProvider(
-            "PR-based documentation generation is not yet implemented".to_string(),
-        ));
+        PullRequestSource::detect(&config.git, provider.as_deref())?
+            .fetch(&config.git, pr_number)
+            .await?
+    } else if doc_type.as_deref() == Some("onboarding") {
+        // An onboarding guide describes the service as it is now
+        tracing::info!("Using the latest commit");
+        let extractor = DiffExtractor::new("commit".to_string(), "HEAD".to_string(), None)?
+            .with_limits(limits)
+            .with_cache(diff_cache);
+        extractor.extract()?
     } else {
-        return Err(crate::error::KtmeError::InvalidInput(
-            "No source specified. Use --commit, --input, --staged, or --pr".to_string(),
-        ));
+        return Err(no_source());
+    };
+
+    // Resolve the services owning the changed files
+    let (services, resolved) = if auto {
+        progress.stage("Resolving services");
+        let resolution = ownership::resolve_services_for_diff(&diff)?;
+        progress.message(format!("Resolved services: {}", resolution.describe()));
+        let services: Vec<String> = resolution.services.keys().cloned().collect();
+        (services, Some(resolution.scoped_diffs(&diff)))
+    } else {
+        (services, None)
+    };
+    let concurrent = services.len() > 1;
+    if auto {
+        check_output_placeholder(output.as_deref(), concurrent)?;
+    }
+
+    // Auto-initialize if not already done
+    for service in &services {
+        check_and_initialize(storage.as_ref(), service).await?;
+    }
+
+    // Record a multi-service run as a job, so that the services that fail
+    // can be generated again with `ktme jobs resume`
+    let job = match job {
+        Some(id) => Some(id),
+        None if concurrent => Some(storage.create_job(
+            "generate",
+            &format!("Generate documentation for {}", services.join(", ")),
+            &params,
+            &services,
+        )?),
+        None => None,
+    };
+    let job_steps: HashMap<String, i64> = match job {
+        Some(id) => storage
+            .get_job(id)?
+            .map(|job| {
+                job.steps
+                    .into_iter()
+                    .map(|step| (step.name, step.position))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => HashMap::new(),
     };
 
     // Initialize AI client
-    let ai_client = AIClient::new()?;
+    let ai_client = AIClient::from_config(&config.ai)?;
+    let extensions = Extensions::load(&config)?;
     tracing::info!("Using AI provider: {}", ai_client.provider_name());
 
-    // Determine documentation type
-    let doc_type = doc_type.as_deref().unwrap_or("general");
+    // Link tickets referenced by the commit message or PR
+    let linker = config
+        .issues
+        .enabled
+        .then(|| IssueLinker::new(config.issues.clone(), config.git.github_token.clone()));
+
+    let context = GenerateContext {
+        config: &config,
+        storage: storage.as_ref(),
+        ai_client: &ai_client,
+        ai_config: &config.ai,
+        linker: linker.as_ref(),
+        format: format.as_deref(),
+        progress: (!concurrent).then_some(&progress),
+        force,
+        queue,
+        contacts: config.documentation.include_contacts,
+        extensions: &extensions,
+        variables: &variables,
+        publish: publish.as_deref(),
+        wait,
+        redact_code: redact_code || config.documentation.redact_code,
+    };
+
+    // In a monorepo each service only documents the changes under its path
+    let scoped_diffs = match resolved {
+        Some(scoped_diffs) => scoped_diffs,
+        None => {
+            let mut scoped_diffs = Vec::new();
+            for service in &services {
+                let scoped = match (concurrent || job.is_some())
+                    .then(|| ownership::service_dir_in_repo(service))
+                    .flatten()
+                {
+                    Some(dir) => {
+                        let scoped = diff.scoped_to(&dir);
+                        if scoped.files.is_empty() {
+                            progress.message(format!(
+                                "- {}: no changes under {}, skipped",
+                                service, dir
+                            ));
+                            continue;
+                        }
+                        scoped
+                    }
+                    None => diff.clone(),
+                };
+                scoped_diffs.push((service.clone(), scoped));
+            }
+            scoped_diffs
+        }
+    };
+
+    let persona_options = PersonaOptions { audience, audit };
+
+    // Command-line options override the service settings, which override
+    // the global config
+    let global = ServiceSettings::from_config(&config);
+    let cli = ServiceSettings {
+        doc_type,
+        template,
+        ..Default::default()
+    };
+    let mut runs = Vec::new();
+    for (service, scoped) in scoped_diffs {
+        let stored = mapping::service_settings(&config, storage.as_ref(), &service)?;
+        let settings = global.clone().overlay(&stored).overlay(&cli);
+        let filtered = scoped.filtered(|f| settings.covers(&f.path));
+        if filtered.files.is_empty() && !scoped.files.is_empty() {
+            progress.message(format!(
+                "- {}: no changes match its include/exclude settings, skipped",
+                service
+            ));
+            continue;
+        }
+        let scoped = filtered;
+        let output = output
+            .as_deref()
+            .map(|path| path.replace(SERVICE_PLACEHOLDER, &service));
+        let context = personas::context(
+            &config,
+            settings.doc_type.as_deref().unwrap_or("general"),
+            Some(&service),
+            &scoped,
+            &persona_options,
+        );
+        let run = ServiceRun {
+            ai_client: ai_client_for(&config.ai, &settings)?,
+            context,
+            settings,
+        };
+        runs.push((service, scoped, output, run));
+    }
+
+    let parallelism = jobs.unwrap_or(config.general.parallelism);
+    if concurrent {
+        progress.stage(format!(
+            "Generating documentation for {} services ({} at a time)",
+            runs.len(),
+            parallelism
+        ));
+    }
+
+    let results = run_limited(runs, parallelism, |(service, diff, output, run)| {
+        let context = &context;
+        async move {
+            let result =
+                generate_for_service(context, &run, &service, &diff, output.as_deref()).await;
+            (service, result)
+        }
+    })
+    .await;
+    progress.finish();
+
+    // Aggregate: print what succeeded, then report failures
+    let total = results.len();
+    let mut texts = Vec::new();
+    let mut documents = Vec::new();
+    let mut summaries = Vec::new();
+    let mut failures = Vec::new();
+    for (service, result) in results {
+        if let (Some(id), Some(position)) = (job, job_steps.get(&service)) {
+            let (status, error) = match &result {
+                Ok(_) => ("done", None),
+                Err(e) => ("failed", Some(e.to_string())),
+            };
+            if let Err(e) = storage.update_job_step(id, *position, status, error.as_deref()) {
+                tracing::warn!("Failed to record job {} progress: {}", id, e);
+            }
+        }
+        match result {
+            Ok(generated) => {
+                match generated.stdout {
+                    Some(Rendered::Text(text)) => texts.push(text),
+                    Some(Rendered::Json(document)) => documents.push(document),
+                    None => {}
+                }
+                summaries.push(generated.summary);
+            }
+            Err(e) => failures.push((service, e)),
+        }
+    }
+
+    for text in texts {
+        println!("{}", text);
+    }
+    match documents.len() {
+        0 => {}
+        1 => println!("{}", serde_json::to_string_pretty(&documents[0])?),
+        _ => println!("{}", serde_json::to_string_pretty(&documents)?),
+    }
+    if output::is_json() && (output.is_some() || queue) {
+        match summaries.len() {
+            0 => {}
+            1 => output::print_json(&summaries[0])?,
+            _ => output::print_json(&summaries)?,
+        }
+    }
+
+    if let Some(options) = &commit_docs {
+        let services: Vec<String> = summaries
+            .iter()
+            .filter_map(|summary| summary["service"].as_str().map(str::to_string))
+            .collect();
+        let source = summaries
+            .iter()
+            .find_map(|summary| summary["source"].as_str())
+            .unwrap_or("HEAD");
+        commit_written_docs(
+            &config,
+            options,
+            &written_files(&summaries),
+            &services,
+            source,
+        )
+        .await?;
+    }
+
+    if let Some(id) = job {
+        let error = (!failures.is_empty()).then(|| {
+            failures
+                .iter()
+                .map(|(service, e)| format!("{}: {}", service, e))
+                .collect::<Vec<_>>()
+                .join("; ")
+        });
+        let status = if error.is_some() {
+            "failed"
+        } else {
+            "completed"
+        };
+        if let Err(e) = storage.update_job_status(id, status, error.as_deref()) {
+            tracing::warn!("Failed to record job {} status: {}", id, e);
+        }
+        if error.is_some() {
+            output::message(format!(
+                "Generate the failed services again with 'ktme jobs resume {}'",
+                id
+            ));
+        }
+    }
+
+    if !concurrent {
+        if let Some((_, e)) = failures.pop() {
+            return Err(e);
+        }
+        return Ok(());
+    }
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+    for (service, e) in &failures {
+        tracing::error!("Documentation generation failed for {}: {}", service, e);
+    }
+    Err(KtmeError::Documentation(format!(
+        "Documentation generation failed for {} of {} services: {}",
+        failures.len(),
+        total,
+        failures
+            .iter()
+            .map(|(service, _)| service.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )))
+}
+
+/// One document of a sequence generated by [`generate_each`]
+pub(crate) struct SequenceItem {
+    /// Position of the document among the steps of the job
+    pub step: i64,
+    pub diff: ExtractedDiff,
+    pub output: Option<String>,
+}
+
+/// Generate documentation of `service` for each item in order, writing it to
+/// the item's output, and publishing it to the `publish` target. Each item is
+/// recorded as a step of `job`. Stops at the first failure; returns the
+/// summary of each generated document.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn generate_each(
+    service: &str,
+    doc_type: Option<String>,
+    template: Option<String>,
+    items: Vec<SequenceItem>,
+    publish: Option<&str>,
+    queue: bool,
+    force: bool,
+    job: i64,
+    progress: &Progress,
+) -> Result<Vec<serde_json::Value>> {
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    check_and_initialize(storage.as_ref(), service).await?;
+
+    let ai_client = AIClient::from_config(&config.ai)?;
+    let extensions = Extensions::load(&config)?;
+    let linker = config
+        .issues
+        .enabled
+        .then(|| IssueLinker::new(config.issues.clone(), config.git.github_token.clone()));
+    let variables = HashMap::new();
+    let context = GenerateContext {
+        config: &config,
+        storage: storage.as_ref(),
+        ai_client: &ai_client,
+        ai_config: &config.ai,
+        linker: linker.as_ref(),
+        format: None,
+        progress: Some(progress),
+        force,
+        queue,
+        contacts: config.documentation.include_contacts,
+        extensions: &extensions,
+        variables: &variables,
+        publish,
+        wait: false,
+        redact_code: config.documentation.redact_code,
+    };
+
+    let stored = mapping::service_settings(&config, storage.as_ref(), service)?;
+    let settings = ServiceSettings::from_config(&config)
+        .overlay(&stored)
+        .overlay(&ServiceSettings {
+            doc_type,
+            template,
+            ..Default::default()
+        });
+    let doc_type = settings.doc_type.clone().unwrap_or("general".to_string());
+    let ai_client = ai_client_for(&config.ai, &settings)?;
+    let mut run = ServiceRun {
+        ai_client,
+        context: None,
+        settings,
+    };
+
+    let mut summaries = Vec::new();
+    for item in items {
+        let diff = item.diff.filtered(|f| run.settings.covers(&f.path));
+        if diff.files.is_empty() {
+            progress.message(format!(
+                "- {}: no changes to document, skipped",
+                diff.identifier
+            ));
+        } else {
+            run.context = personas::context(
+                &config,
+                &doc_type,
+                Some(service),
+                &diff,
+                &PersonaOptions::default(),
+            );
+            match generate_for_service(&context, &run, service, &diff, item.output.as_deref()).await
+            {
+                Ok(generated) => {
+                    if let Some(Rendered::Text(text)) = generated.stdout {
+                        progress.suspend(|| println!("{}", text));
+                    }
+                    summaries.push(generated.summary);
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    storage.update_job_step(job, item.step, "failed", Some(&error))?;
+                    storage.update_job_status(job, "failed", Some(&error))?;
+                    return Err(e);
+                }
+            }
+        }
+        storage.update_job_step(job, item.step, "done", None)?;
+        progress.inc();
+    }
+    storage.update_job_status(job, "completed", None)?;
+    Ok(summaries)
+}
+
+/// Placeholder in `--output` replaced by the service name
+const SERVICE_PLACEHOLDER: &str = "{service}";
+
+/// Several services cannot share one output file
+fn check_output_placeholder(output: Option<&str>, concurrent: bool) -> Result<()> {
+    match output {
+        Some(path) if concurrent && !path.contains(SERVICE_PLACEHOLDER) => {
+            Err(KtmeError::InvalidInput(format!(
+                "--output must contain {} when generating for several services",
+                SERVICE_PLACEHOLDER
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Client for the model in `settings` when it differs from the configured
+/// one
+pub(crate) fn ai_client_for(
+    config: &AiConfig,
+    settings: &ServiceSettings,
+) -> Result<Option<AIClient>> {
+    if settings.model.is_none() || settings.model == config.model {
+        return Ok(None);
+    }
+    let config = AiConfig {
+        model: settings.model.clone(),
+        ..config.clone()
+    };
+    AIClient::from_config(&config).map(Some)
+}
+
+/// Resolved settings of one service's generation run
+struct ServiceRun {
+    settings: ServiceSettings,
+    /// Client for a per-service model; the shared client otherwise
+    ai_client: Option<AIClient>,
+    /// Repository context of document types such as `onboarding`
+    context: Option<String>,
+}
+
+/// Everything shared by the per-service generation runs
+struct GenerateContext<'a> {
+    config: &'a Config,
+    storage: &'a dyn Storage,
+    ai_client: &'a AIClient,
+    /// `[ai]` config, for the `[ai.routing]` rules
+    ai_config: &'a AiConfig,
+    linker: Option<&'a IssueLinker>,
+    format: Option<&'a str>,
+    /// Stage reporting for single-service runs
+    progress: Option<&'a Progress>,
+    /// Regenerate even when nothing changed
+    force: bool,
+    /// Hold the documentation in the review queue instead of publishing it
+    queue: bool,
+    /// Add primary contacts to the documentation
+    contacts: bool,
+    /// WASM analyzers and formatters from the plugins directory
+    extensions: &'a Extensions,
+    /// `--var` values for the prompt template
+    variables: &'a HashMap<String, String>,
+    /// `--publish` target among the service's mapped locations
+    publish: Option<&'a str>,
+    /// Wait for another run generating the same service instead of failing
+    wait: bool,
+    /// Leave the code out of the documentation, see [`redact`]
+    redact_code: bool,
+}
+
+impl GenerateContext<'_> {
+    fn stage(&self, message: impl Into<String>) {
+        if let Some(progress) = self.progress {
+            progress.stage(message);
+        }
+    }
+
+    fn message(&self, text: impl std::fmt::Display) {
+        match self.progress {
+            Some(progress) => progress.message(text),
+            None => output::message(text),
+        }
+    }
+
+    /// What was generated last time for `service` and `output`
+    fn previous_generation(
+        &self,
+        service: &str,
+        output: Option<&str>,
+    ) -> Option<PreviousGeneration> {
+        if self.force {
+            return None;
+        }
+        let cached = self
+            .storage
+            .get_cached_diff(
+                GENERATION_CACHE_SOURCE,
+                output.unwrap_or("stdout"),
+                Some(service),
+            )
+            .map_err(|e| tracing::debug!("Generation cache unavailable: {}", e))
+            .ok()??;
+        serde_json::from_str(&cached.diff_json).ok()
+    }
+
+    fn remember_generation(
+        &self,
+        service: &str,
+        output: Option<&str>,
+        input_hash: &str,
+        documentation: &str,
+    ) {
+        let previous = PreviousGeneration {
+            input_hash: input_hash.to_string(),
+            content_hash: content_hash(documentation),
+            documentation: documentation.to_string(),
+        };
+        let result = serde_json::to_string(&previous)
+            .map_err(KtmeError::from)
+            .and_then(|json| {
+                self.storage.cache_diff(
+                    GENERATION_CACHE_SOURCE,
+                    output.unwrap_or("stdout"),
+                    Some(service),
+                    &json,
+                    None,
+                )
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to cache generated documentation: {}", e);
+        }
+    }
+}
+
+/// `source_type` of generation entries in the diff cache
+const GENERATION_CACHE_SOURCE: &str = "generate";
+
+/// Inputs and result of the last generation for a service and output
+#[derive(Serialize, Deserialize)]
+struct PreviousGeneration {
+    input_hash: String,
+    content_hash: String,
+    documentation: String,
+}
+
+/// Hash of everything that determines the generated documentation: the
+/// prompt (diff, template and language), document type, format, provider
+/// and model
+fn input_hash(
+    prompt: &str,
+    diff: &ExtractedDiff,
+    doc_type: &str,
+    format: Option<&str>,
+    ai_client: &AIClient,
+) -> String {
+    let mut hasher = Sha256::new();
+    // Staged changes are stamped with the extraction time
+    hasher.update(prompt.replace(&diff.timestamp, "").as_bytes());
+    for part in [
+        doc_type,
+        format.unwrap_or("markdown"),
+        ai_client.provider_name(),
+    ]
+    .into_iter()
+    .chain(ai_client.model())
+    {
+        hasher.update([0]);
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+pub(crate) fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Whether `path` still holds the documentation last generated for `service`
+fn is_up_to_date(storage: &dyn Storage, service: &str, path: &str, content_hash: &str) -> bool {
+    if !Path::new(path).exists() {
+        return false;
+    }
+    let Ok(Some(entry)) = storage.get_service_by_name(service) else {
+        return false;
+    };
+
+    storage
+        .recent_generations(HISTORY_LOOKBACK)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|record| {
+            record.service_id == Some(entry.id)
+                && record.status == "success"
+                && record.document_url.as_deref() == Some(path)
+        })
+        .is_some_and(|record| record.content_hash.as_deref() == Some(content_hash))
+}
+
+/// Generation history entries searched for the last write to an output
+const HISTORY_LOOKBACK: usize = 200;
+
+/// What a run prints on stdout once all runs are done
+enum Rendered {
+    Text(String),
+    Json(serde_json::Value),
+}
+
+struct Generated {
+    stdout: Option<Rendered>,
+    /// Printed with `--json` when documentation was written to a file
+    summary: serde_json::Value,
+}
+
+async fn generate_for_service(
+    context: &GenerateContext<'_>,
+    run: &ServiceRun,
+    service: &str,
+    diff: &ExtractedDiff,
+    output: Option<&str>,
+) -> Result<Generated> {
+    // Held until the documentation is written and published, so another
+    // ktme process does not write or publish the same service meanwhile
+    let _lock = if output.is_some() || context.publish.is_some() {
+        Some(ServiceLock::acquire(context.config, service, "generate", context.wait).await?)
+    } else {
+        None
+    };
+
+    let settings = &run.settings;
+    let doc_type = settings.doc_type.as_deref().unwrap_or("general");
+
+    // Authors of the changed code and CODEOWNERS entries
+    let ownership = if context.contacts {
+        ownership::analyze(None, diff).unwrap_or_else(|e| {
+            tracing::debug!("Skipping primary contacts: {}", e);
+            Ownership::default()
+        })
+    } else {
+        Ownership::default()
+    };
+
+    // Doc comments of the changed public API, preferred over the model's
+    // own descriptions in API documentation
+    let api_docs = if doc_type == "api-doc" {
+        doc_comments::for_diff(None, diff).unwrap_or_else(|e| {
+            tracing::debug!("Skipping doc comments: {}", e);
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let prompt_context = [
+        run.context.clone().unwrap_or_default(),
+        doc_comments::prompt_context(&api_docs),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n\n");
 
     // Generate prompt
-    let prompt = if let Some(template_file) = template {
-        load_custom_template(&template_file, &diff)?
+    let prompt = build_prompt(
+        settings,
+        doc_type,
+        diff,
+        &ownership,
+        context.variables,
+        context.extensions,
+        (!prompt_context.is_empty()).then_some(prompt_context.as_str()),
+    )?;
+    let prompt = if context.redact_code {
+        format!("{}\n\n{}", prompt, redact::PROMPT_INSTRUCTION)
     } else {
-        PromptTemplates::generate_documentation_prompt(&diff, doc_type, None)?
+        prompt
     };
 
-    tracing::info!("Generating documentation using {}...", doc_type);
+    // A model chosen for the service or with --model wins over the routing
+    // rules
+    let routed = routing::routed_client(
+        context.ai_config,
+        run.ai_client.is_some(),
+        doc_type,
+        &prompt,
+    )?;
+    let ai_client = routed
+        .as_ref()
+        .or(run.ai_client.as_ref())
+        .unwrap_or(context.ai_client);
+
+    // Identity of this generation, stored with its history so the same
+    // documentation is not published twice
+    let key = context
+        .variables
+        .iter()
+        .fold(
+            GenerationKey::new(
+                diff,
+                settings.template.as_deref().unwrap_or(doc_type),
+                ai_client.model(),
+            )
+            .option("doc_type", Some(doc_type))
+            .option("format", context.format)
+            .option("language", settings.language.as_deref())
+            .option("prompt_pack", settings.prompt_pack.as_deref())
+            .option("redact_code", context.redact_code.then_some("true")),
+            |key, (name, value)| key.option(&format!("var.{}", name), Some(value)),
+        )
+        .hash();
 
-    // Generate documentation
-    let documentation = ai_client.generate_documentation(&prompt).await?;
+    tracing::info!("Generating {} documentation for {}...", doc_type, service);
+
+    context.stage(format!(
+        "Generating {} documentation with {}",
+        doc_type,
+        ai_client.provider_name()
+    ));
+
+    let output_provider = if output.is_some() {
+        "markdown"
+    } else {
+        "stdout"
+    };
+
+    // Skip the AI call when the diff and template are unchanged since the
+    // last generation to the same output
+    let input_hash = input_hash(&prompt, diff, doc_type, context.format, ai_client);
+    let previous = context
+        .previous_generation(service, output)
+        .filter(|previous| previous.input_hash == input_hash);
+    if let (Some(previous), Some(path)) = (&previous, output) {
+        if is_up_to_date(context.storage, service, path, &previous.content_hash) {
+            context.message(format!(
+                "✓ No changes for {} since the last generation; {} is up to date (use --force to regenerate)",
+                service, path
+            ));
+            record_history(
+                context.storage,
+                service,
+                "generate",
+                output_provider,
+                output,
+                diff,
+                Some(&previous.documentation),
+                None,
+                ai_client.model(),
+                Some(&key),
+            );
+            return Ok(Generated {
+                stdout: None,
+                summary: serde_json::json!({
+                    "service": service,
+                    "doc_type": doc_type,
+                    "source": diff.identifier,
+                    "output": output,
+                    "status": PublishStatus::NoChanges,
+                    "provider": ai_client.provider_name(),
+                    "model": ai_client.model()
+                }),
+            });
+        }
+    }
+
+    // Generate documentation, recording failures in the generation history
+    let reused = previous.is_some();
+    let documentation = match previous {
+        Some(previous) => {
+            tracing::info!(
+                "Reusing documentation for {}: the diff and template are unchanged",
+                service
+            );
+            previous.documentation
+        }
+        None => match shutdown::or_cancelled(
+            "Generating documentation",
+            ai_client.generate_documentation(&prompt),
+        )
+        .await
+        {
+            Ok(documentation) => {
+                let documentation = format!(
+                    "{}{}",
+                    context.extensions.format(documentation),
+                    doc_comments::render_section(&api_docs)
+                );
+                let documentation = if context.redact_code {
+                    redact::redact_code(&documentation)
+                } else {
+                    documentation
+                };
+                context.remember_generation(service, output, &input_hash, &documentation);
+                documentation
+            }
+            Err(e) => {
+                record_history(
+                    context.storage,
+                    service,
+                    "generate",
+                    output_provider,
+                    output,
+                    diff,
+                    None,
+                    Some(&e.to_string()),
+                    ai_client.model(),
+                    Some(&key),
+                );
+                return Err(e);
+            }
+        },
+    };
+
+    // Link tickets referenced by the commit message or PR
+    let tickets = match context.linker {
+        Some(linker) => {
+            context.stage("Resolving related tickets");
+            let default_repo = diff.source.strip_prefix("github-pr-");
+            linker.resolve(&linker.detect(diff), default_repo).await
+        }
+        None => Vec::new(),
+    };
 
     // Output the documentation
-    match format.as_deref() {
-        Some("markdown") | Some("md") => {
-            let content = format_documentation(&documentation, doc_type, &service);
-            write_output(&content, output.as_deref())?;
+    context.stage("Writing documentation");
+    let notes = annotations::collect(diff);
+    let rendered = match context.format {
+        Some("json") => Rendered::Json(serde_json::json!({
+            "service": service,
+            "doc_type": doc_type,
+            "source": diff.identifier,
+            "documentation": documentation,
+            "related_tickets": tickets,
+            "developer_notes": notes,
+            "primary_contacts": ownership,
+            "generated_at": chrono::Utc::now().to_rfc3339(),
+            "provider": ai_client.provider_name()
+        })),
+        // Markdown is the default
+        _ => Rendered::Text(format_documentation(
+            &documentation,
+            doc_type,
+            service,
+            &tickets,
+            &notes,
+            &ownership,
+        )),
+    };
+    if context.queue {
+        return queue_for_review(
+            context,
+            service,
+            doc_type,
+            diff,
+            output,
+            documentation,
+            rendered,
+            ai_client.model(),
+            &key,
+        );
+    }
+
+    let status = match output {
+        _ if reused => PublishStatus::NoChanges,
+        Some(path) if Path::new(path).exists() => PublishStatus::Updated,
+        _ => PublishStatus::Created,
+    };
+    let stdout = match (output, rendered) {
+        (Some(path), Rendered::Text(content)) => {
+            let notice = NoticeContext {
+                service: Some(service),
+                doc_type: Some(doc_type),
+                source: Some(&diff.identifier),
+            };
+            write_output(&with_notices(context.config, path, &content, &notice), path)?;
+            None
         }
-        Some("json") => {
-            let json_output = serde_json::json!({
-                "service": service,
-                "doc_type": doc_type,
-                "source": diff.identifier,
-                "documentation": documentation,
-                "generated_at": chrono::Utc::now().to_rfc3339(),
-                "provider": ai_client.provider_name()
-            });
-            write_json_output(&json_output, output.as_deref())?;
+        (Some(path), Rendered::Json(document)) => {
+            write_json_output(&document, path)?;
+            None
         }
-        _ => {
-            // Default to plain text/markdown
-            let content = format_documentation(&documentation, doc_type, &service);
-            write_output(&content, output.as_deref())?;
+        (None, rendered) => Some(rendered),
+    };
+
+    let published = match context.publish {
+        Some(target) => {
+            context.stage(format!("Publishing to {}", target));
+            let publish_key = (!context.force).then_some(key.as_str());
+            match publish_to_mapped(
+                context.config,
+                context.storage,
+                service,
+                target,
+                &documentation,
+                publish_key,
+                &NoticeContext {
+                    service: Some(service),
+                    doc_type: Some(doc_type),
+                    source: Some(&diff.identifier),
+                },
+            )
+            .await
+            {
+                Ok((published, skipped)) => {
+                    for location in skipped {
+                        context.message(format!(
+                            "- Skipping {}: it already has this documentation (use --force to publish again)",
+                            location.location
+                        ));
+                    }
+                    published
+                }
+                Err(e) => {
+                    record_history(
+                        context.storage,
+                        service,
+                        "publish",
+                        target,
+                        None,
+                        diff,
+                        Some(&documentation),
+                        Some(&e.to_string()),
+                        ai_client.model(),
+                        Some(&key),
+                    );
+                    return Err(e);
+                }
+            }
+        }
+        None => Vec::new(),
+    };
+    for location in &published {
+        record_history(
+            context.storage,
+            service,
+            "publish",
+            &location.r#type,
+            Some(&location.location),
+            diff,
+            Some(&documentation),
+            None,
+            ai_client.model(),
+            Some(&key),
+        );
+        context.message(format!(
+            "✓ Published {} documentation to {}",
+            doc_type, location.location
+        ));
+        for synced in translation::after_publish(context.config, service, location).await {
+            let marker = if synced.error.is_some() { "✗" } else { "✓" };
+            context.message(format!("{} Translation {}", marker, synced.describe()));
         }
     }
 
-    tracing::info!("Documentation generated successfully!");
+    // Tickets were already commented on when the documentation was generated
+    if let (Some(linker), Some(path), false) = (context.linker, output, reused) {
+        let location = fs::canonicalize(path)
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| path.to_string());
+        context.stage("Commenting on Jira tickets");
+        linker.comment_on_jira(&tickets, &location).await;
+    }
+
+    tracing::info!("Documentation generated successfully for {}!", service);
+
+    record_history(
+        context.storage,
+        service,
+        "generate",
+        output_provider,
+        output,
+        diff,
+        Some(&documentation),
+        None,
+        ai_client.model(),
+        Some(&key),
+    );
 
     // Update knowledge graph with generated documentation
-    update_knowledge_graph(&service, &diff, &documentation, doc_type).await?;
+    if !reused {
+        context.stage("Updating knowledge graph");
+        update_knowledge_graph(context.storage, service, diff, &documentation, doc_type).await?;
+    }
 
+    Ok(Generated {
+        stdout,
+        summary: serde_json::json!({
+            "service": service,
+            "doc_type": doc_type,
+            "source": diff.identifier,
+            "output": output,
+            "status": status,
+            "published": published,
+            "related_tickets": tickets,
+            "generated_at": chrono::Utc::now().to_rfc3339(),
+            "provider": ai_client.provider_name(),
+            "model": ai_client.model()
+        }),
+    })
+}
+
+/// Files written by the runs behind `summaries`: their `--output` and the
+/// published locations that are files
+fn written_files(summaries: &[serde_json::Value]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for summary in summaries.iter().filter(|s| s["status"] != "queued") {
+        let published = summary["published"].as_array().into_iter().flatten();
+        for path in std::iter::once(&summary["output"])
+            .chain(published)
+            .filter_map(|value| value.as_str())
+        {
+            let path = PathBuf::from(path);
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// Commit the documentation `files` of `services` generated from `source`
+/// as `options` ask (`--commit-docs`) and report the commit
+pub(crate) async fn commit_written_docs(
+    config: &Config,
+    options: &CommitDocs,
+    files: &[PathBuf],
+    services: &[String],
+    source: &str,
+) -> Result<()> {
+    let Some(commit) =
+        git::commit::commit_docs(files, services, source, options, &config.git).await?
+    else {
+        output::message("No documentation changes to commit");
+        return Ok(());
+    };
+    output::message(format!(
+        "✓ Committed {} documentation file(s) as {} on {}",
+        commit.files.len(),
+        &commit.id[..7],
+        commit.branch
+    ));
+    if let Some(url) = &commit.pull_request {
+        output::message(format!("✓ Opened {}", url));
+    }
     Ok(())
 }
 
-fn load_diff_from_file(file_path: &str) -> Result<ExtractedDiff> {
+/// Publish `documentation` above the existing content of the mapped
+/// locations of `service` matching `target`: a provider type, a location, or
+/// part of one such as the `Releases` page. Locations that already hold the
+/// documentation of `key` are skipped. Returns the published and the skipped
+/// locations. `notice` fills in the notices of the locations.
+async fn publish_to_mapped(
+    config: &Config,
+    storage: &dyn Storage,
+    service: &str,
+    target: &str,
+    documentation: &str,
+    key: Option<&str>,
+    notice: &NoticeContext<'_>,
+) -> Result<(Vec<DocumentLocation>, Vec<DocumentLocation>)> {
+    let needle = target.to_lowercase();
+    let locations: Vec<DocumentLocation> = StorageManager::from_config(config)?
+        .get_mapping(service)?
+        .docs
+        .into_iter()
+        .filter(|doc| {
+            doc.r#type == target
+                || doc.location == target
+                || doc.location.to_lowercase().contains(&needle)
+        })
+        .collect();
+    if locations.is_empty() {
+        return Err(KtmeError::NotFound(format!(
+            "No documentation location of {} matches '{}'",
+            service, target
+        )));
+    }
+
+    let update = SectionUpdate {
+        kind: DocKind::General,
+        section: None,
+        mode: Some(UpdateMode::Prepend),
+    };
+    let mut published = Vec::new();
+    let mut skipped = Vec::new();
+    for location in locations {
+        if key.is_some_and(|key| is_published(storage, &location.location, key)) {
+            skipped.push(location);
+            continue;
+        }
+        shutdown::check("Publishing")?;
+        publish_to_location(config, &location, documentation, &update, notice)
+            .await
+            .unwrap_or_else(|| Err(KtmeError::UnsupportedProvider(location.r#type.clone())))?;
+        published.push(location);
+    }
+    Ok((published, skipped))
+}
+
+/// Whether documentation with the idempotency `key` was already published to
+/// `location`
+pub(crate) fn is_published(storage: &dyn Storage, location: &str, key: &str) -> bool {
+    storage
+        .find_by_key(location, key)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to read the history of {}: {}", location, e);
+            None
+        })
+        .is_some()
+}
+
+/// Store generated documentation in the review queue. Documentation for an
+/// output file is queued as it would be written; otherwise the AI content is
+/// queued for the service's mapped locations, as `ktme update` publishes it.
+#[allow(clippy::too_many_arguments)]
+fn queue_for_review(
+    context: &GenerateContext<'_>,
+    service: &str,
+    doc_type: &str,
+    diff: &ExtractedDiff,
+    output: Option<&str>,
+    documentation: String,
+    rendered: Rendered,
+    model: Option<&str>,
+    key: &str,
+) -> Result<Generated> {
+    let content = match (output, rendered) {
+        (Some(_), Rendered::Text(text)) => text,
+        (Some(_), Rendered::Json(document)) => serde_json::to_string_pretty(&document)?,
+        (None, _) => documentation.clone(),
+    };
+    let id =
+        context
+            .storage
+            .queue_doc(service, doc_type, Some(&diff.identifier), output, &content)?;
+    context.message(format!(
+        "✓ Queued documentation for {} for review (id {}); publish it with 'ktme queue approve {}'",
+        service, id, id
+    ));
+
+    record_history(
+        context.storage,
+        service,
+        "queue",
+        "queue",
+        output,
+        diff,
+        Some(&documentation),
+        None,
+        model,
+        Some(key),
+    );
+
+    Ok(Generated {
+        stdout: None,
+        summary: serde_json::json!({
+            "service": service,
+            "doc_type": doc_type,
+            "source": diff.identifier,
+            "output": output,
+            "status": "queued",
+            "queue_id": id,
+            "generated_at": chrono::Utc::now().to_rfc3339(),
+        }),
+    })
+}
+
+pub(crate) fn load_diff_from_file(file_path: &str) -> Result<ExtractedDiff> {
     let content = fs::read_to_string(file_path).map_err(|e| crate::error::KtmeError::Io(e))?;
 
     serde_json::from_str(&content).map_err(|e| crate::error::KtmeError::Serialization(e))
 }
 
-fn load_custom_template(template_file: &str, diff: &ExtractedDiff) -> Result<String> {
-    let template_content =
-        fs::read_to_string(template_file).map_err(|e| crate::error::KtmeError::Io(e))?;
+/// Render the prompt template file with the diff's values and `variables`,
+/// followed by the diff
+pub(crate) fn load_custom_template(
+    template_file: &str,
+    diff: &ExtractedDiff,
+    ownership: &Ownership,
+    variables: &HashMap<String, String>,
+    context: Option<&str>,
+) -> Result<String> {
+    let template = PromptTemplate::from_file(Path::new(template_file))?;
+    let mut values = template::diff_variables(diff, ownership);
+    values.extend(variables.clone());
+    let mut prompt = template.render(&values)?;
 
-    // Simple template substitution
-    let mut prompt = template_content;
-    prompt = prompt.replace("{{SERVICE}}", &diff.source);
-    prompt = prompt.replace("{{AUTHOR}}", &diff.author);
-    prompt = prompt.replace("{{MESSAGE}}", &diff.message);
-    prompt = prompt.replace("{{TIMESTAMP}}", &diff.timestamp);
-    prompt = prompt.replace("{{FILES_CHANGED}}", &diff.summary.total_files.to_string());
-    prompt = prompt.replace("{{ADDITIONS}}", &diff.summary.total_additions.to_string());
-    prompt = prompt.replace("{{DELETIONS}}", &diff.summary.total_deletions.to_string());
+    if let Some(context) = context {
+        prompt.push_str(&format!("\n\nAdditional Context:\n{}", context));
+    }
 
     // Add diff content at the end
     prompt.push_str(&format!(
@@ -132,75 +1514,148 @@ fn load_custom_template(template_file: &str, diff: &ExtractedDiff) -> Result<Str
     Ok(prompt)
 }
 
-fn format_documentation(content: &str, doc_type: &str, service: &str) -> String {
+/// The prompt sent to the AI: the service's template file or the prompt of
+/// `doc_type` from its prompt pack with the document type's `context`, in the
+/// configured language and annotated by the WASM analyzers
+pub(crate) fn build_prompt(
+    settings: &ServiceSettings,
+    doc_type: &str,
+    diff: &ExtractedDiff,
+    ownership: &Ownership,
+    variables: &HashMap<String, String>,
+    extensions: &Extensions,
+    context: Option<&str>,
+) -> Result<String> {
+    let prompt = if let Some(template_file) = &settings.template {
+        load_custom_template(template_file, diff, ownership, variables, context)?
+    } else {
+        PromptPack::load(settings.prompt_pack.as_deref())?
+            .documentation_prompt(diff, doc_type, context)?
+    };
+    let prompt = PromptTemplates::in_language(prompt, settings.language.as_deref());
+    Ok(extensions.annotate(prompt, diff))
+}
+
+pub(crate) fn format_documentation(
+    content: &str,
+    doc_type: &str,
+    service: &str,
+    tickets: &[RelatedTicket],
+    notes: &[Annotation],
+    ownership: &Ownership,
+) -> String {
     let timestamp = chrono::Utc::now().format("%Y-%m-%d %H:%M UTC");
 
     format!(
-        "# Documentation for {}\n\n**Type**: {}\n**Generated**: {}\n\n---\n\n{}",
-        service, doc_type, timestamp, content
+        "# Documentation for {}\n\n**Type**: {}\n**Generated**: {}\n\n---\n\n{}{}{}{}",
+        service,
+        doc_type,
+        timestamp,
+        content,
+        render_related_tickets(tickets),
+        annotations::render_section(notes),
+        ownership.render_section()
     )
 }
 
-fn write_output(content: &str, output: Option<&str>) -> Result<()> {
-    match output {
-        Some(path) => {
-            // Create parent directories if they don't exist
-            if let Some(parent) = Path::new(path).parent() {
-                fs::create_dir_all(parent).map_err(|e| crate::error::KtmeError::Io(e))?;
-            }
+/// `content` of the output file `path` with the notices `[notices]` gives it
+pub(crate) fn with_notices(
+    config: &Config,
+    path: &str,
+    content: &str,
+    notice: &NoticeContext<'_>,
+) -> String {
+    let location = DocumentLocation {
+        r#type: "markdown".to_string(),
+        location: path.to_string(),
+        section: None,
+    };
+    Notices::for_location(&config.notices, &location, notice)
+        .apply(content, SectionFormat::Markdown)
+}
 
-            fs::write(path, content).map_err(|e| crate::error::KtmeError::Io(e))?;
+pub(crate) fn write_output(content: &str, path: &str) -> Result<()> {
+    write_atomic(Path::new(path), content)?;
 
-            println!("Documentation saved to: {}", path);
-        }
-        None => {
-            println!("{}", content);
-        }
-    }
+    output::message(format!("Documentation saved to: {}", path));
     Ok(())
 }
 
-fn write_json_output(json: &serde_json::Value, output: Option<&str>) -> Result<()> {
-    let json_content = serde_json::to_string_pretty(json)
-        .map_err(|e| crate::error::KtmeError::Serialization(e))?;
+fn write_json_output(json: &serde_json::Value, path: &str) -> Result<()> {
+    let json_content = serde_json::to_string_pretty(json).map_err(KtmeError::Serialization)?;
 
-    match output {
-        Some(path) => {
-            if let Some(parent) = Path::new(path).parent() {
-                fs::create_dir_all(parent).map_err(|e| crate::error::KtmeError::Io(e))?;
-            }
+    write_atomic(Path::new(path), &json_content)?;
 
-            fs::write(path, json_content).map_err(|e| crate::error::KtmeError::Io(e))?;
+    output::message(format!("JSON documentation saved to: {}", path));
+    Ok(())
+}
 
-            println!("JSON documentation saved to: {}", path);
-        }
-        None => {
-            println!("{}", json_content);
+/// Record a generation attempt for `service` in the generation history.
+/// History is informational, so storage errors are only logged.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn record_history(
+    storage: &dyn Storage,
+    service: &str,
+    action: &str,
+    provider: &str,
+    location: Option<&str>,
+    diff: &ExtractedDiff,
+    content: Option<&str>,
+    error: Option<&str>,
+    model: Option<&str>,
+    key: Option<&str>,
+) {
+    let service_id = match storage.get_service_by_name(service) {
+        Ok(entry) => entry.map(|s| s.id),
+        Err(e) => {
+            tracing::debug!("Skipping history for '{}': {}", service, e);
+            return;
         }
+    };
+    let content_hash = content.map(content_hash);
+
+    if let Err(e) = storage.record_generation(
+        service_id,
+        provider,
+        None,
+        location,
+        action,
+        Some(&diff.source),
+        Some(&diff.identifier),
+        content_hash.as_deref(),
+        match error {
+            None => "success",
+            Some(_) if shutdown::requested() => "cancelled",
+            Some(_) => "failed",
+        },
+        error,
+        model,
+        key,
+    ) {
+        tracing::warn!("Failed to record generation history: {}", e);
     }
-    Ok(())
 }
 
 /// Check if service is initialized and auto-initialize if needed
-async fn check_and_initialize(service: &str) -> Result<()> {
-    let db = Database::new(None)?;
-    let service_repo = ServiceRepository::new(db);
-
+async fn check_and_initialize(storage: &dyn Storage, service: &str) -> Result<()> {
     // Check if service exists
-    if service_repo.get_by_name(service)?.is_none() {
+    if storage.get_service_by_name(service)?.is_none() {
         tracing::info!(
             "Service '{}' not found in knowledge graph, auto-initializing...",
             service
         );
 
         // Create service entry
-        service_repo.create(
+        storage.create_service(
             service,
             None, // No path specified
             Some(&format!("Auto-initialized for documentation generation")),
         )?;
 
-        println!("ℹ️  Initialized knowledge graph for service '{}'", service);
+        output::message(format!(
+            "ℹ️  Initialized knowledge graph for service '{}'",
+            service
+        ));
     }
 
     Ok(())
@@ -208,18 +1663,15 @@ async fn check_and_initialize(service: &str) -> Result<()> {
 
 /// Update knowledge graph with generated documentation
 async fn update_knowledge_graph(
+    storage: &dyn Storage,
     service: &str,
     diff: &ExtractedDiff,
     documentation: &str,
     doc_type: &str,
 ) -> Result<()> {
-    let db = Database::new(None)?;
-    let service_repo = ServiceRepository::new(db.clone());
-    let feature_repo = FeatureRepository::new(db);
-
     // Get service ID
-    let service_entry = service_repo
-        .get_by_name(service)?
+    let service_entry = storage
+        .get_service_by_name(service)?
         .ok_or_else(|| KtmeError::Storage(format!("Service '{}' not found", service)))?;
 
     // Extract features from the diff and create feature entries
@@ -253,7 +1705,7 @@ async fn update_knowledge_graph(
                     .collect::<String>()
             );
 
-            match feature_repo.create(
+            match storage.create_feature(
                 &feature_id,
                 service_entry.id,
                 &feature_name,
@@ -276,7 +1728,7 @@ async fn update_knowledge_graph(
 }
 
 /// Determine feature type from file path
-fn determine_feature_type(path: &str) -> FeatureType {
+pub(crate) fn determine_feature_type(path: &str) -> FeatureType {
     let path_lower = path.to_lowercase();
 
     if path_lower.contains("api") || path_lower.contains("endpoint") || path_lower.contains("route")
@@ -0,0 +1,383 @@
+use super::generate::determine_feature_type;
+use crate::cli::output;
+use crate::cli::progress::Progress;
+use crate::config::Config;
+use crate::doc::providers::confluence::ConfluenceProvider;
+use crate::doc::providers::DocumentProvider;
+use crate::error::{KtmeError, Result};
+use crate::http;
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::bundle;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::SearchContentType;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+use walkdir::WalkDir;
+
+/// Extensions of the files imported from a directory
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// Directories never imported from
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// Characters of a document kept as the description of its feature
+const DESCRIPTION_MAX_LENGTH: usize = 200;
+
+/// A document written before ktme was set up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExistingDoc {
+    pub(crate) provider: &'static str,
+    pub(crate) location: String,
+    pub(crate) title: String,
+    /// Markdown, Confluence pages converted from their storage format
+    pub(crate) content: String,
+}
+
+/// Outcome of importing one document
+#[derive(Debug, Serialize)]
+struct ImportedDoc {
+    title: String,
+    provider: &'static str,
+    location: String,
+    feature_id: String,
+    /// The document was already imported and only its content was re-indexed
+    reindexed: bool,
+    mapped: bool,
+}
+
+/// Restore the state bundle `file` written by `ktme export state`: config,
+/// database, mappings, templates and prompts. Existing files are only
+/// replaced with `force`; the secrets and storage paths of the current
+/// config are kept.
+pub async fn state(file: String, force: bool) -> Result<()> {
+    tracing::info!("Importing state from {}", file);
+
+    let report = bundle::import(Path::new(&file), force)?;
+    if output::is_json() {
+        return output::print_json(&report);
+    }
+    let manifest = &report.manifest;
+    output::message(format!(
+        "✓ Imported the state exported by ktme {} on {} (schema {}, workspace {})",
+        manifest.ktme_version,
+        manifest.created_at.format("%Y-%m-%d %H:%M"),
+        manifest.schema_version,
+        manifest.workspace
+    ));
+    for path in &report.written {
+        output::message(format!("  {}", path.display()));
+    }
+    if !report.backups.is_empty() {
+        output::message(format!(
+            "Replaced files were kept with a .bak suffix ({})",
+            report.backups.len()
+        ));
+    }
+    if !report.missing_secrets.is_empty() {
+        output::message("Secrets are not part of bundles; set these again:");
+        for secret in &report.missing_secrets {
+            output::message(format!("  - {}", secret));
+        }
+    }
+    Ok(())
+}
+
+/// Index the existing Markdown files under `path` or the pages of the
+/// Confluence `space` as features of `service`, with their content in the
+/// search index, and map them to the service unless `no_mapping`.
+/// Importing again re-indexes the documents instead of duplicating them.
+pub async fn docs(
+    service: String,
+    path: Option<String>,
+    space: Option<String>,
+    no_mapping: bool,
+) -> Result<()> {
+    let config = Config::load()?;
+    let documents = match (path, space) {
+        (Some(path), _) => markdown_documents(Path::new(&path))?,
+        (None, Some(space)) => confluence_documents(&config, &space).await?,
+        (None, None) => {
+            return Err(KtmeError::InvalidInput(
+                "Either --path or --space must be provided".to_string(),
+            ))
+        }
+    };
+
+    let storage = open_storage(&config.storage)?;
+    let mut imported = index_documents(storage.as_ref(), &service, &documents)?;
+    if !no_mapping {
+        map_documents(&config, &service, &mut imported)?;
+    }
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "service": service,
+            "documents": imported,
+        }));
+    }
+
+    if imported.is_empty() {
+        println!("No documents found to import.");
+        return Ok(());
+    }
+    for doc in &imported {
+        let mut notes = Vec::new();
+        if doc.reindexed {
+            notes.push("re-indexed");
+        }
+        if doc.mapped {
+            notes.push("mapped");
+        }
+        let notes = if notes.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", notes.join(", "))
+        };
+        println!("  - {} [{}]{}", doc.title, doc.location, notes);
+    }
+    output::message(format!(
+        "✓ Imported {} document(s) into '{}'",
+        imported.len(),
+        service
+    ));
+    Ok(())
+}
+
+/// Markdown files under `path` (or `path` itself), sorted by location
+pub(crate) fn markdown_documents(path: &Path) -> Result<Vec<ExistingDoc>> {
+    if !path.exists() {
+        return Err(KtmeError::NotFound(format!(
+            "Path does not exist: {}",
+            path.display()
+        )));
+    }
+
+    let mut documents = Vec::new();
+    let walker = WalkDir::new(path).into_iter().filter_entry(|entry| {
+        entry.depth() == 0
+            || !SKIPPED_DIRS
+                .iter()
+                .any(|dir| entry.file_name() == std::ffi::OsStr::new(dir))
+    });
+    for entry in walker {
+        let entry = entry.map_err(|e| KtmeError::Io(e.into()))?;
+        let is_markdown = entry.file_type().is_file()
+            && entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| MARKDOWN_EXTENSIONS.contains(&ext));
+        if !is_markdown {
+            continue;
+        }
+        let content = std::fs::read_to_string(entry.path())?;
+        let location = entry.path().to_string_lossy().replace('\\', "/");
+        let title = markdown_title(&content).unwrap_or_else(|| {
+            entry
+                .path()
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| location.clone())
+        });
+        documents.push(ExistingDoc {
+            provider: "markdown",
+            location,
+            title,
+            content,
+        });
+    }
+    documents.sort_by(|a, b| a.location.cmp(&b.location));
+    Ok(documents)
+}
+
+/// Every page of the Confluence `space`
+async fn confluence_documents(config: &Config, space: &str) -> Result<Vec<ExistingDoc>> {
+    let provider = ConfluenceProvider::from_settings(&config.confluence)?;
+    http::ensure_online("Importing Confluence pages")?;
+
+    let progress = Progress::spinner(format!("Reading the pages of space {}", space));
+    let mut documents = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = provider
+            .list_documents(space, cursor.as_deref(), None)
+            .await?;
+        for doc in page.documents {
+            documents.push(ExistingDoc {
+                provider: "confluence",
+                location: doc.url.unwrap_or(doc.id),
+                title: doc.title,
+                content: doc.content,
+            });
+        }
+        progress.message(format!("{} page(s) read", documents.len()));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+    progress.finish();
+    Ok(documents)
+}
+
+/// Create a feature per document, or find the one of an earlier import, and
+/// index the document's content for it. ktme has no embedding provider, so
+/// the entries are found by text search only.
+fn index_documents(
+    storage: &dyn Storage,
+    service: &str,
+    documents: &[ExistingDoc],
+) -> Result<Vec<ImportedDoc>> {
+    let service_entry = match storage.get_service_by_name(service)? {
+        Some(service_entry) => service_entry,
+        None => storage.create_service(
+            service,
+            None,
+            Some(&format!("Auto-created service for {}", service)),
+        )?,
+    };
+
+    // Features of earlier imports, by the location they were imported from
+    let previous: HashMap<String, String> = storage
+        .features_for_service(service_entry.id)?
+        .into_iter()
+        .filter(|feature| feature.metadata["source"] == "import")
+        .filter_map(|feature| {
+            let location = feature.metadata["location"].as_str()?.to_string();
+            Some((location, feature.id))
+        })
+        .collect();
+
+    let mut imported = Vec::new();
+    for doc in documents {
+        let (feature_id, reindexed) = match previous.get(&doc.location) {
+            Some(id) => (id.clone(), true),
+            None => {
+                let id = Uuid::new_v4().to_string();
+                storage.create_feature(
+                    &id,
+                    service_entry.id,
+                    &doc.title,
+                    Some(&summary(&doc.content)),
+                    determine_feature_type(&doc.location),
+                    vec!["imported".to_string(), doc.provider.to_string()],
+                    serde_json::json!({
+                        "source": "import",
+                        "provider": doc.provider,
+                        "location": doc.location,
+                    }),
+                )?;
+                (id, false)
+            }
+        };
+        storage.index_feature_content(
+            &feature_id,
+            SearchContentType::Documentation,
+            &doc.content,
+        )?;
+        imported.push(ImportedDoc {
+            title: doc.title.clone(),
+            provider: doc.provider,
+            location: doc.location.clone(),
+            feature_id,
+            reindexed,
+            mapped: false,
+        });
+    }
+    Ok(imported)
+}
+
+/// Map the imported documents the service is not mapped to yet
+fn map_documents(config: &Config, service: &str, imported: &mut [ImportedDoc]) -> Result<()> {
+    let manager = StorageManager::from_config(config)?;
+    let mut mapped: Vec<String> = match manager.get_mapping(service) {
+        Ok(mapping) => mapping.docs.into_iter().map(|doc| doc.location).collect(),
+        Err(KtmeError::MappingNotFound(_)) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    for doc in imported.iter_mut() {
+        if mapped.contains(&doc.location) {
+            continue;
+        }
+        manager.add_mapping(
+            service.to_string(),
+            DocumentLocation {
+                r#type: doc.provider.to_string(),
+                location: doc.location.clone(),
+                section: None,
+            },
+        )?;
+        mapped.push(doc.location.clone());
+        doc.mapped = true;
+    }
+    Ok(())
+}
+
+/// Text of the first heading of a Markdown document
+fn markdown_title(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+/// First paragraph of a document, without headings, shortened to a
+/// description
+fn summary(content: &str) -> String {
+    let paragraph = content
+        .split("\n\n")
+        .map(str::trim)
+        .find(|block| !block.is_empty() && !block.starts_with('#'))
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    paragraph.chars().take(DESCRIPTION_MAX_LENGTH).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
+
+    #[test]
+    fn test_import_markdown_documents_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("docs");
+        std::fs::create_dir_all(docs.join("api")).unwrap();
+        std::fs::create_dir_all(docs.join("node_modules")).unwrap();
+        std::fs::write(
+            docs.join("api/endpoints.md"),
+            "# Endpoints\n\nThe invoices endpoint lists the invoices\nof a customer.\n\n## GET /invoices\n",
+        )
+        .unwrap();
+        std::fs::write(docs.join("runbook.markdown"), "Restart the worker.\n").unwrap();
+        std::fs::write(docs.join("node_modules/readme.md"), "# Vendored\n").unwrap();
+        std::fs::write(docs.join("notes.txt"), "not markdown").unwrap();
+
+        let documents = markdown_documents(&docs).unwrap();
+        let titles: Vec<&str> = documents.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(titles, vec!["Endpoints", "runbook"]);
+
+        let storage = SqliteStorage::new(Database::in_memory().unwrap());
+        let first = index_documents(&storage, "billing", &documents).unwrap();
+        assert!(first.iter().all(|doc| !doc.reindexed));
+        let again = index_documents(&storage, "billing", &documents).unwrap();
+        assert!(again.iter().all(|doc| doc.reindexed));
+        assert_eq!(first[0].feature_id, again[0].feature_id);
+
+        let service = storage.get_service_by_name("billing").unwrap().unwrap();
+        let features = storage.features_for_service(service.id).unwrap();
+        assert_eq!(features.len(), 2);
+        let endpoints = features.iter().find(|f| f.name == "Endpoints").unwrap();
+        assert_eq!(
+            endpoints.description.as_deref(),
+            Some("The invoices endpoint lists the invoices of a customer.")
+        );
+        assert_eq!(endpoints.tags, vec!["imported", "markdown"]);
+    }
+}
@@ -0,0 +1,300 @@
+use super::generate::write_output;
+use super::update::publish_to_location;
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::notices::NoticeContext;
+use crate::doc::sections::{DocKind, SectionUpdate, UpdateMode};
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::open_storage;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::{Feature, GenerationRecord};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Generation history entries searched for the last update of each service
+const HISTORY_LIMIT: usize = 1000;
+
+/// Features listed per service; the rest are counted
+const MAX_FEATURES: usize = 10;
+
+/// One service of the documentation index
+#[derive(Debug, Serialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub description: Option<String>,
+    pub path: Option<String>,
+    pub docs: Vec<DocumentLocation>,
+    /// Last successful generation or update
+    pub last_updated: Option<DateTime<Utc>>,
+    pub features: Vec<IndexFeature>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexFeature {
+    pub name: String,
+    pub feature_type: String,
+    pub description: Option<String>,
+}
+
+impl From<&Feature> for IndexFeature {
+    fn from(feature: &Feature) -> Self {
+        Self {
+            name: feature.name.clone(),
+            feature_type: feature.feature_type.to_string(),
+            description: feature.description.clone(),
+        }
+    }
+}
+
+/// Write an index of all services, their documentation locations, last
+/// update times and features to `output` (stdout when `None`) and, with
+/// `confluence`, replace that Confluence page with it
+pub async fn execute(output_path: Option<String>, confluence: Option<String>) -> Result<()> {
+    tracing::info!("Generating documentation index");
+
+    let config = Config::load()?;
+    let entries = collect(&config)?;
+
+    if output::is_json() && output_path.is_none() && confluence.is_none() {
+        return output::print_json(&serde_json::json!({ "services": entries }));
+    }
+
+    let link_base = output_path
+        .as_deref()
+        .and_then(|path| Path::new(path).parent())
+        .filter(|dir| !dir.as_os_str().is_empty());
+    let index = render(&entries, link_base, Utc::now());
+
+    if let Some(path) = &output_path {
+        write_output(&index, path)?;
+    }
+
+    if let Some(page) = &confluence {
+        // The landing page shows the index only
+        let location = DocumentLocation {
+            r#type: "confluence".to_string(),
+            location: page.clone(),
+            section: None,
+        };
+        let update = SectionUpdate {
+            kind: DocKind::General,
+            section: None,
+            mode: Some(UpdateMode::Replace),
+        };
+        publish_to_location(
+            &config,
+            &location,
+            &render(&entries, None, Utc::now()),
+            &update,
+            &NoticeContext::default(),
+        )
+        .await
+        .unwrap_or_else(|| Err(KtmeError::UnsupportedProvider("confluence".to_string())))?;
+        output::message(format!("✓ Published the index to Confluence page {}", page));
+    }
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "services": entries.len(),
+            "output": output_path,
+            "confluence": confluence,
+        }));
+    }
+    if output_path.is_none() && confluence.is_none() {
+        print!("{}", index);
+    }
+    Ok(())
+}
+
+/// Index entries of every mapped service, by name
+pub fn collect(config: &Config) -> Result<Vec<IndexEntry>> {
+    let manager = StorageManager::from_config(config)?;
+    let storage = open_storage(&config.storage)?;
+
+    let services: HashMap<String, _> = storage
+        .list_services()?
+        .into_iter()
+        .map(|service| (service.name.clone(), service))
+        .collect();
+    let history = storage.recent_generations(HISTORY_LIMIT)?;
+
+    let mut names = manager.list_services()?;
+    names.sort();
+    names.dedup();
+
+    let mut entries = Vec::new();
+    for name in names {
+        let (path, docs) = match manager.get_mapping(&name) {
+            Ok(mapping) => (mapping.path, mapping.docs),
+            Err(_) => (None, Vec::new()),
+        };
+        let service = services.get(&name);
+        let features = match service {
+            Some(service) => storage.features_for_service(service.id)?,
+            None => Vec::new(),
+        };
+
+        entries.push(IndexEntry {
+            description: service.and_then(|s| s.description.clone()),
+            path: path.or_else(|| service.and_then(|s| s.path.clone())),
+            last_updated: service.and_then(|s| last_success(&history, s.id)),
+            features: features.iter().map(IndexFeature::from).collect(),
+            name,
+            docs,
+        });
+    }
+    Ok(entries)
+}
+
+fn last_success(history: &[GenerationRecord], service_id: i64) -> Option<DateTime<Utc>> {
+    history
+        .iter()
+        .filter(|record| record.service_id == Some(service_id) && record.status == "success")
+        .map(|record| record.created_at)
+        .max()
+}
+
+/// Markdown index of `entries`. Links to local files are made relative to
+/// `link_base`, the directory the index is written to.
+pub fn render(entries: &[IndexEntry], link_base: Option<&Path>, now: DateTime<Utc>) -> String {
+    let mut index = String::from("# Documentation Index\n\n");
+    index.push_str(&format!(
+        "_{} service(s), generated by ktme on {}_\n\n",
+        entries.len(),
+        now.format("%Y-%m-%d %H:%M UTC")
+    ));
+
+    if entries.is_empty() {
+        index.push_str("No services are mapped yet. Add one with `ktme mapping add`.\n");
+        return index;
+    }
+
+    for entry in entries {
+        index.push_str(&format!("- [{}](#{})\n", entry.name, anchor(&entry.name)));
+    }
+
+    for entry in entries {
+        index.push_str(&format!("\n## {}\n\n", entry.name));
+        if let Some(description) = &entry.description {
+            index.push_str(&format!("{}\n\n", description));
+        }
+        if let Some(path) = &entry.path {
+            index.push_str(&format!("- **Path:** `{}`\n", path));
+        }
+        let last_updated = entry
+            .last_updated
+            .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_else(|| "never".to_string());
+        index.push_str(&format!("- **Last updated:** {}\n", last_updated));
+
+        if entry.docs.is_empty() {
+            index.push_str("- **Documentation:** none mapped\n");
+        } else {
+            index.push_str("- **Documentation:**\n");
+            for doc in &entry.docs {
+                index.push_str(&format!(
+                    "  - [{}]({}) ({})\n",
+                    doc.location,
+                    link(doc, link_base),
+                    doc.r#type
+                ));
+            }
+        }
+
+        if !entry.features.is_empty() {
+            index.push_str("- **Features:**\n");
+            for feature in entry.features.iter().take(MAX_FEATURES) {
+                match &feature.description {
+                    Some(description) => index.push_str(&format!(
+                        "  - **{}** ({}): {}\n",
+                        feature.name, feature.feature_type, description
+                    )),
+                    None => index.push_str(&format!(
+                        "  - **{}** ({})\n",
+                        feature.name, feature.feature_type
+                    )),
+                }
+            }
+            if entry.features.len() > MAX_FEATURES {
+                index.push_str(&format!(
+                    "  - … and {} more\n",
+                    entry.features.len() - MAX_FEATURES
+                ));
+            }
+        }
+    }
+    index
+}
+
+/// Link target of a documentation location
+fn link(doc: &DocumentLocation, link_base: Option<&Path>) -> String {
+    if doc.r#type != "markdown" {
+        return doc.location.clone();
+    }
+    link_base
+        .and_then(|base| Path::new(&doc.location).strip_prefix(base).ok())
+        .map(|relative| relative.display().to_string())
+        .unwrap_or_else(|| doc.location.clone())
+}
+
+/// GitHub-style heading anchor
+pub(crate) fn anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_render_index() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 1, 12, 0, 0).unwrap();
+        let entries = vec![IndexEntry {
+            name: "Billing API".to_string(),
+            description: Some("Invoices and payments".to_string()),
+            path: Some("services/billing".to_string()),
+            docs: vec![
+                DocumentLocation {
+                    r#type: "markdown".to_string(),
+                    location: "docs/billing.md".to_string(),
+                    section: None,
+                },
+                DocumentLocation {
+                    r#type: "confluence".to_string(),
+                    location: "https://wiki.example.com/pages/viewpage.action?pageId=42"
+                        .to_string(),
+                    section: None,
+                },
+            ],
+            last_updated: Some(now),
+            features: vec![IndexFeature {
+                name: "Invoices".to_string(),
+                feature_type: "api".to_string(),
+                description: Some("Create and list invoices".to_string()),
+            }],
+        }];
+
+        let index = render(&entries, Some(Path::new("docs")), now);
+        assert!(index.contains("_1 service(s), generated by ktme on 2026-10-01 12:00 UTC_"));
+        assert!(index.contains("- [Billing API](#billing-api)\n"));
+        assert!(index.contains("- **Last updated:** 2026-10-01 12:00 UTC\n"));
+        assert!(index.contains("  - [docs/billing.md](billing.md) (markdown)\n"));
+        assert!(index
+            .contains("(https://wiki.example.com/pages/viewpage.action?pageId=42) (confluence)\n"));
+        assert!(index.contains("  - **Invoices** (api): Create and list invoices\n"));
+
+        let empty = render(&[], None, now);
+        assert!(empty.contains("No services are mapped yet"));
+    }
+}
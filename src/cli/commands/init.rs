@@ -1,21 +1,38 @@
+use crate::config::Config;
+use crate::doc::lint;
+use crate::doc::notices::{NoticeContext, Notices};
+use crate::doc::sections::SectionFormat;
 use crate::error::{KtmeError, Result};
 use crate::service_detector::ServiceDetector;
-use crate::storage::database::Database;
-use crate::storage::repository::ServiceRepository;
-use crate::InitMode;
+use crate::storage::backend::open_storage;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::{InitMode, InitTemplate};
+use serde_json::Value as JsonValue;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// What `ktme init` wires up besides the `docs/` folder
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wiring {
+    /// Map the service to the generated changelog and API documents
+    pub mapping: bool,
+    /// Install a post-commit hook that updates the mapped documents
+    pub hooks: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn execute(
     path: Option<String>,
     service_name: Option<String>,
     force: bool,
     mode: InitMode,
+    template: InitTemplate,
+    wiring: Wiring,
     dry_run: bool,
     output: Option<String>,
 ) -> Result<()> {
     match mode {
-        InitMode::Fresh => execute_fresh(path, service_name, force).await,
+        InitMode::Fresh => execute_fresh(path, service_name, force, template, wiring).await,
         InitMode::Scan => execute_scan(path, service_name, output).await,
         InitMode::Validate => execute_validate(path, service_name, output).await,
         InitMode::Enhance => execute_enhance(path, service_name, dry_run, output).await,
@@ -28,6 +45,8 @@ pub async fn execute_fresh(
     path: Option<String>,
     service_name: Option<String>,
     force: bool,
+    template: InitTemplate,
+    wiring: Wiring,
 ) -> Result<()> {
     let project_path = path.as_deref().unwrap_or(".");
     let project_dir = PathBuf::from(project_path);
@@ -37,14 +56,18 @@ pub async fn execute_fresh(
         project_path
     );
 
+    // An existing docs/ folder is kept, but can still be wired up
     let docs_dir = project_dir.join("docs");
-    if docs_dir.exists() && !force {
+    let scaffold = force || !docs_dir.exists();
+    if !scaffold {
         println!(
             "Documentation directory already exists at: {}",
             docs_dir.display()
         );
-        println!("   Use --force to re-initialize");
-        return Ok(());
+        if !wiring.mapping && !wiring.hooks {
+            println!("   Use --force to re-initialize");
+            return Ok(());
+        }
     }
 
     let service = if let Some(name) = service_name {
@@ -57,34 +80,59 @@ pub async fn execute_fresh(
 
     println!("Service: {}", service);
 
-    println!("Creating documentation structure...");
-    create_docs_structure(&project_dir, force)?;
+    if scaffold {
+        let project = ProjectProfile::detect(&project_dir);
+        if !project.languages.is_empty() {
+            println!("Detected: {}", project.languages.join(", "));
+        }
 
-    println!("Initializing knowledge graph database...");
-    initialize_knowledge_graph(&service, &project_dir)?;
+        println!("Creating documentation structure...");
+        create_docs_structure(&project_dir, force, template)?;
 
-    println!("Creating initial documentation files...");
-    create_initial_docs(&docs_dir, &service)?;
+        println!("Initializing knowledge graph database...");
+        initialize_knowledge_graph(&service, &project_dir)?;
+
+        println!("Creating initial documentation files...");
+        create_initial_docs(&docs_dir, &service, template, &project)?;
+    } else {
+        println!("Initializing knowledge graph database...");
+        initialize_knowledge_graph(&service, &project_dir)?;
+    }
+
+    if wiring.mapping {
+        println!("Mapping documentation...");
+        map_docs(&service, project_path, &project_dir, template)?;
+    }
+    if wiring.hooks {
+        println!("Installing git hooks...");
+        install_hooks(&service, &project_dir)?;
+    }
 
     println!("\nInitialization complete!");
-    println!(
-        "\nDocumentation structure created at: {}",
-        docs_dir.display()
-    );
-    println!("   - README.md: Project overview");
-    println!("   - architecture.md: Architecture documentation");
-    println!("   - api.md: API documentation");
-    println!("   - changelog.md: Change log");
-    println!("\nNext steps:");
-    println!(
-        "   1. Run 'ktme generate --service {} --staged' to document your changes",
-        service
-    );
-    println!(
-        "   2. Run 'ktme mapping add {}' to link documentation",
+    if scaffold {
+        println!(
+            "\nDocumentation structure created at: {}",
+            docs_dir.display()
+        );
+        for doc in scaffold_docs(template) {
+            println!("   - {}: {}", doc.file, doc.description);
+        }
+    }
+    let mut steps = vec![format!(
+        "Run 'ktme generate --service {} --staged' to document your changes",
         service
-    );
-    println!("   3. Run 'ktme mcp start' to enable AI agent integration");
+    )];
+    if !wiring.mapping {
+        steps.push(format!(
+            "Run 'ktme mapping add {}' to link documentation (or 'ktme init --with-mapping')",
+            service
+        ));
+    }
+    steps.push("Run 'ktme mcp start' to enable AI agent integration".to_string());
+    println!("\nNext steps:");
+    for (number, step) in steps.iter().enumerate() {
+        println!("   {}. {}", number + 1, step);
+    }
 
     Ok(())
 }
@@ -182,26 +230,13 @@ pub async fn execute_validate(
         if path.is_file() && path.extension().map(|e| e == "md").unwrap_or(false) {
             let content = fs::read_to_string(&path)?;
 
-            if !content.starts_with("# ") {
-                validation_warnings.push(format!(
-                    "{}: Missing title header",
-                    path.file_name().unwrap().to_string_lossy()
-                ));
-            }
-
-            if !content.contains("## ") {
-                validation_warnings.push(format!(
-                    "{}: No sections found",
-                    path.file_name().unwrap().to_string_lossy()
-                ));
-            }
-
-            let broken_links = content.matches("[").count() != content.matches("]").count();
-            if broken_links {
-                validation_errors.push(format!(
-                    "{}: Potentially broken links detected",
-                    path.file_name().unwrap().to_string_lossy()
-                ));
+            let filename = path.file_name().unwrap().to_string_lossy();
+            for finding in lint::check(&content) {
+                let message = format!("{}: {}", filename, finding.message);
+                match finding.severity {
+                    lint::Severity::Error => validation_errors.push(message),
+                    lint::Severity::Warning => validation_warnings.push(message),
+                }
             }
         }
     }
@@ -406,7 +441,7 @@ pub async fn execute_research(
     Ok(())
 }
 
-fn create_docs_structure(project_dir: &Path, force: bool) -> Result<()> {
+fn create_docs_structure(project_dir: &Path, force: bool, template: InitTemplate) -> Result<()> {
     let docs_dir = project_dir.join("docs");
 
     if docs_dir.exists() {
@@ -419,7 +454,12 @@ fn create_docs_structure(project_dir: &Path, force: bool) -> Result<()> {
         fs::create_dir_all(&docs_dir).map_err(|e| KtmeError::Io(e))?;
     }
 
-    let subdirs = ["api", "guides", "examples"];
+    let subdirs: &[&str] = match template {
+        InitTemplate::Minimal => &[],
+        InitTemplate::Service => &["api", "guides", "examples"],
+        InitTemplate::Library => &["guides", "examples"],
+        InitTemplate::Platform => &["api", "guides", "runbooks"],
+    };
     for subdir in subdirs {
         let subdir_path = docs_dir.join(subdir);
         if !subdir_path.exists() {
@@ -431,10 +471,9 @@ fn create_docs_structure(project_dir: &Path, force: bool) -> Result<()> {
 }
 
 fn initialize_knowledge_graph(service: &str, project_dir: &Path) -> Result<()> {
-    let db = Database::new(None)?;
-    let service_repo = ServiceRepository::new(db);
+    let storage = open_storage(&Config::load()?.storage)?;
 
-    if let Some(existing) = service_repo.get_by_name(service)? {
+    if let Some(existing) = storage.get_service_by_name(service)? {
         tracing::info!(
             "Service '{}' already exists with id: {}",
             service,
@@ -451,19 +490,222 @@ fn initialize_knowledge_graph(service: &str, project_dir: &Path) -> Result<()> {
 
     let description = format!("Documentation and knowledge graph for {}", service);
 
-    service_repo.create(service, project_path.as_deref(), Some(&description))?;
+    storage.create_service(service, project_path.as_deref(), Some(&description))?;
 
     println!("   Service '{}' registered in knowledge graph", service);
     Ok(())
 }
 
-fn create_initial_docs(docs_dir: &Path, service: &str) -> Result<()> {
-    let readme_path = docs_dir.join("README.md");
-    if !readme_path.exists() {
-        let readme_content = format!(
-            r#"# {} Documentation
+/// Scaffold files mapped by `--with-mapping`: the ones `ktme update` can
+/// merge changes into
+const MAPPED_DOCS: &[&str] = &["changelog.md", "api.md"];
+
+/// Map `service` to the changelog and API documents of its `docs/` folder and
+/// record the project directory as the service path
+fn map_docs(
+    service: &str,
+    project_path: &str,
+    project_dir: &Path,
+    template: InitTemplate,
+) -> Result<()> {
+    let manager = StorageManager::new()?;
+    let mapped: Vec<String> = manager
+        .get_mapping(service)
+        .map(|mapping| mapping.docs.into_iter().map(|doc| doc.location).collect())
+        .unwrap_or_default();
+
+    let mut added = 0;
+    for doc in scaffold_docs(template)
+        .iter()
+        .filter(|doc| MAPPED_DOCS.contains(&doc.file))
+        .filter(|doc| project_dir.join("docs").join(doc.file).exists())
+    {
+        let location = Path::new(project_path).join("docs").join(doc.file);
+        let location = location.to_string_lossy();
+        let location = location.strip_prefix("./").unwrap_or(&location).to_string();
+        if mapped.contains(&location) {
+            println!("   {} is already mapped", location);
+            continue;
+        }
+        manager.add_mapping(
+            service.to_string(),
+            DocumentLocation {
+                r#type: "markdown".to_string(),
+                location: location.clone(),
+                section: None,
+            },
+        )?;
+        println!("   Mapped {} -> {}", service, location);
+        added += 1;
+    }
+    if added == 0 && mapped.is_empty() {
+        println!("   No changelog or API document to map in docs/");
+        return Ok(());
+    }
 
-Welcome to the {} documentation!
+    if let Some(dir) = project_dir
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.to_str().map(String::from))
+    {
+        manager.set_path(service, &dir)?;
+    }
+    Ok(())
+}
+
+/// Install a post-commit hook updating the mapped documentation of `service`
+/// in the repository of `project_dir`. An existing hook keeps its commands;
+/// the update is appended to it.
+fn install_hooks(service: &str, project_dir: &Path) -> Result<()> {
+    let repo = git2::Repository::discover(project_dir)?;
+    let hooks_dir = match repo
+        .config()
+        .ok()
+        .and_then(|config| config.get_path("core.hooksPath").ok())
+    {
+        Some(dir) if dir.is_absolute() => dir,
+        Some(dir) => repo.workdir().unwrap_or(repo.path()).join(dir),
+        None => repo.path().join("hooks"),
+    };
+    fs::create_dir_all(&hooks_dir)?;
+
+    let hook = hooks_dir.join("post-commit");
+    let existing = fs::read_to_string(&hook).unwrap_or_default();
+    let marker = format!("# ktme: update the documentation of {}", service);
+    if existing.contains(&marker) {
+        println!("   post-commit hook already updates {}", service);
+        return Ok(());
+    }
+
+    let mut script = if existing.is_empty() {
+        "#!/bin/sh\n".to_string()
+    } else {
+        format!("{}\n", existing.trim_end())
+    };
+    script.push_str(&format!(
+        "\n{}\nktme --quiet update --service '{}' --commit HEAD || echo \"ktme: updating the documentation of {} failed\" >&2\n",
+        marker, service, service
+    ));
+    fs::write(&hook, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+    }
+
+    println!("   Installed post-commit hook: {}", hook.display());
+    Ok(())
+}
+
+/// A file of a scaffold, with its description in the README contents
+struct ScaffoldDoc {
+    file: &'static str,
+    title: &'static str,
+    description: &'static str,
+}
+
+/// Files of the `template` scaffold, the README first
+fn scaffold_docs(template: InitTemplate) -> Vec<ScaffoldDoc> {
+    let doc = |file, title, description| ScaffoldDoc {
+        file,
+        title,
+        description,
+    };
+    let mut docs = vec![doc("README.md", "Overview", "Project overview")];
+    match template {
+        InitTemplate::Minimal => {}
+        InitTemplate::Service => docs.extend([
+            doc(
+                "architecture.md",
+                "Architecture",
+                "System architecture and design",
+            ),
+            doc("api.md", "API Documentation", "API endpoints and usage"),
+        ]),
+        InitTemplate::Library => docs.extend([
+            doc(
+                "architecture.md",
+                "Architecture",
+                "Modules and design decisions",
+            ),
+            doc("api.md", "Public API", "Public types, functions and usage"),
+        ]),
+        InitTemplate::Platform => docs.extend([
+            doc(
+                "architecture.md",
+                "Architecture",
+                "Services and how they interact",
+            ),
+            doc(
+                "api.md",
+                "API Documentation",
+                "APIs between and into services",
+            ),
+            doc(
+                "operations.md",
+                "Operations",
+                "Deployment, monitoring and runbooks",
+            ),
+        ]),
+    }
+    docs.push(doc(
+        "changelog.md",
+        "Change Log",
+        "Version history and changes",
+    ));
+    docs
+}
+
+/// Write the scaffold documents missing from `docs_dir`, with the notices
+/// `[notices]` gives them
+fn create_initial_docs(
+    docs_dir: &Path,
+    service: &str,
+    template: InitTemplate,
+    project: &ProjectProfile,
+) -> Result<()> {
+    let config = Config::load()?;
+    for doc in scaffold_docs(template) {
+        let path = docs_dir.join(doc.file);
+        if path.exists() {
+            continue;
+        }
+        let content = match doc.file {
+            "README.md" => readme(service, template),
+            "architecture.md" => architecture(service, template, project),
+            "api.md" => api(service, template),
+            "operations.md" => operations(service),
+            _ => changelog(service),
+        };
+        let location = DocumentLocation {
+            r#type: "markdown".to_string(),
+            location: path.to_string_lossy().into_owned(),
+            section: None,
+        };
+        let notice = NoticeContext {
+            service: Some(service),
+            ..NoticeContext::default()
+        };
+        let notices = Notices::for_location(&config.notices, &location, &notice);
+        fs::write(&path, notices.apply(&content, SectionFormat::Markdown))?;
+    }
+    Ok(())
+}
+
+fn readme(service: &str, template: InitTemplate) -> String {
+    let contents: String = scaffold_docs(template)
+        .iter()
+        .skip(1)
+        .map(|doc| format!("- [{}](./{}) - {}\n", doc.title, doc.file, doc.description))
+        .collect();
+    let getting_started = match template {
+        InitTemplate::Library => "TODO: Show how to add the library and a first example",
+        _ => "TODO: Add getting started guide",
+    };
+    format!(
+        r#"# {service} Documentation
+
+Welcome to the {service} documentation!
 
 ## Overview
 
@@ -471,61 +713,84 @@ This documentation is automatically maintained by ktme (Knowledge Transfer Me).
 
 ## Contents
 
-- [Architecture](./architecture.md) - System architecture and design
-- [API Documentation](./api.md) - API endpoints and usage
-- [Change Log](./changelog.md) - Version history and changes
-
+{contents}
 ## Getting Started
 
-TODO: Add getting started guide
+{getting_started}
 
 ## Contributing
 
 TODO: Add contribution guidelines
+"#
+    )
+}
 
----
-
-*This documentation is maintained using [ktme](https://github.com/FreePeak/ktme)*
-"#,
-            service, service
-        );
-        fs::write(&readme_path, readme_content).map_err(|e| KtmeError::Io(e))?;
-    }
-
-    let arch_path = docs_dir.join("architecture.md");
-    if !arch_path.exists() {
-        let arch_content = format!(
-            r#"# {} Architecture
+/// Architecture document with the detected components and technologies
+/// filled in
+fn architecture(service: &str, template: InitTemplate, project: &ProjectProfile) -> String {
+    let components = if project.components.is_empty() {
+        "TODO: Document main components".to_string()
+    } else {
+        project.render_components()
+    };
+    let technologies = if project.languages.is_empty() {
+        "TODO: List key technologies".to_string()
+    } else {
+        project
+            .languages
+            .iter()
+            .map(|language| format!("- {}", language))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let (components_heading, flow_heading) = match template {
+        InitTemplate::Library => ("Modules", "Design Decisions"),
+        InitTemplate::Platform => ("Services and Components", "Service Interactions"),
+        _ => ("Components", "Data Flow"),
+    };
+    format!(
+        r#"# {service} Architecture
 
 ## Overview
 
 TODO: Add system overview
 
-## Components
+## {components_heading}
 
-TODO: Document main components
+{components}
 
-## Data Flow
+## {flow_heading}
 
-TODO: Describe data flow
+TODO: Describe {flow}
 
 ## Technologies
 
-TODO: List key technologies
+{technologies}
+"#,
+        flow = flow_heading.to_lowercase()
+    )
+}
 
----
+fn api(service: &str, template: InitTemplate) -> String {
+    match template {
+        InitTemplate::Library => format!(
+            r#"# {service} Public API
 
-*Generated by ktme*
-"#,
-            service
-        );
-        fs::write(&arch_path, arch_content).map_err(|e| KtmeError::Io(e))?;
-    }
+## Modules
+
+TODO: Describe the public modules
+
+## Types and Functions
+
+TODO: Document the main types and functions
 
-    let api_path = docs_dir.join("api.md");
-    if !api_path.exists() {
-        let api_content = format!(
-            r#"# {} API Documentation
+## Examples
+
+TODO: Add usage examples
+"#
+        ),
+        _ => format!(
+            r#"# {service} API Documentation
 
 ## Endpoints
 
@@ -538,35 +803,228 @@ TODO: Describe authentication mechanism
 ## Examples
 
 TODO: Add usage examples
+"#
+        ),
+    }
+}
 
----
+fn operations(service: &str) -> String {
+    format!(
+        r#"# {service} Operations
 
-*Generated by ktme*
-"#,
-            service
-        );
-        fs::write(&api_path, api_content).map_err(|e| KtmeError::Io(e))?;
-    }
+## Deployment
 
-    let changelog_path = docs_dir.join("changelog.md");
-    if !changelog_path.exists() {
-        let changelog_content = format!(
-            r#"# {} Change Log
+TODO: Describe how each service is built and deployed
+
+## Monitoring
+
+TODO: List dashboards, alerts and SLOs
+
+## Runbooks
+
+TODO: Link the runbooks in ./runbooks
+"#
+    )
+}
+
+fn changelog(service: &str) -> String {
+    format!(
+        r#"# {service} Change Log
 
 ## [Unreleased]
 
 ### Added
 - Initial project setup
 - Documentation structure
+"#
+    )
+}
 
----
+/// Kind of a component detected from a project manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ComponentKind {
+    WorkspaceMember,
+    PackageScript,
+    DockerService,
+}
 
-*Generated by ktme*
-"#,
-            service
-        );
-        fs::write(&changelog_path, changelog_content).map_err(|e| KtmeError::Io(e))?;
+impl ComponentKind {
+    fn heading(self) -> &'static str {
+        match self {
+            ComponentKind::WorkspaceMember => "Workspace members",
+            ComponentKind::PackageScript => "Package scripts",
+            ComponentKind::DockerService => "Docker services",
+        }
     }
+}
 
-    Ok(())
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Component {
+    kind: ComponentKind,
+    name: String,
+    detail: Option<String>,
+}
+
+/// Languages and components detected from the manifests at the root of a
+/// project: Cargo and npm workspaces, package scripts and Docker Compose
+/// services
+#[derive(Debug, Default)]
+struct ProjectProfile {
+    languages: Vec<&'static str>,
+    components: Vec<Component>,
+}
+
+impl ProjectProfile {
+    fn detect(project_dir: &Path) -> Self {
+        let mut profile = Self::default();
+        let exists = |name: &str| project_dir.join(name).exists();
+
+        if let Some(cargo) = read_manifest(project_dir, "Cargo.toml")
+            .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+        {
+            profile.languages.push("Rust");
+            let members = cargo
+                .get("workspace")
+                .and_then(|w| w.get("members"))
+                .and_then(|m| m.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|m| m.as_str());
+            for member in members {
+                profile.add_members(project_dir, member, "Cargo.toml");
+            }
+        }
+
+        if let Some(package) = read_manifest(project_dir, "package.json")
+            .and_then(|content| serde_json::from_str::<JsonValue>(&content).ok())
+        {
+            profile.languages.push(if exists("tsconfig.json") {
+                "TypeScript"
+            } else {
+                "JavaScript"
+            });
+            let workspaces = match &package["workspaces"] {
+                JsonValue::Array(patterns) => patterns.clone(),
+                workspaces => workspaces["packages"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default(),
+            };
+            for pattern in workspaces.iter().filter_map(|p| p.as_str()) {
+                profile.add_members(project_dir, pattern, "package.json");
+            }
+            if let Some(scripts) = package["scripts"].as_object() {
+                for (name, command) in scripts {
+                    profile.components.push(Component {
+                        kind: ComponentKind::PackageScript,
+                        name: name.clone(),
+                        detail: command.as_str().map(str::to_string),
+                    });
+                }
+            }
+        }
+
+        if exists("go.mod") {
+            profile.languages.push("Go");
+        }
+        if exists("pyproject.toml") || exists("requirements.txt") || exists("setup.py") {
+            profile.languages.push("Python");
+        }
+        if exists("pom.xml") || exists("build.gradle") || exists("build.gradle.kts") {
+            profile.languages.push("Java");
+        }
+
+        let compose = [
+            "compose.yaml",
+            "compose.yml",
+            "docker-compose.yaml",
+            "docker-compose.yml",
+        ]
+        .iter()
+        .find_map(|name| read_manifest(project_dir, name))
+        .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok());
+        if let Some(services) = compose
+            .as_ref()
+            .and_then(|c| c.get("services"))
+            .and_then(|s| s.as_mapping())
+        {
+            for (name, service) in services {
+                let Some(name) = name.as_str() else {
+                    continue;
+                };
+                let detail = match (service.get("image"), service.get("build")) {
+                    (Some(image), _) => image.as_str().map(str::to_string),
+                    (None, Some(_)) => Some("built from source".to_string()),
+                    (None, None) => None,
+                };
+                profile.components.push(Component {
+                    kind: ComponentKind::DockerService,
+                    name: name.to_string(),
+                    detail,
+                });
+            }
+        }
+
+        profile
+    }
+
+    /// Add the workspace members matching `pattern`, a directory or a glob
+    /// of directories holding a `manifest`
+    fn add_members(&mut self, project_dir: &Path, pattern: &str, manifest: &str) {
+        let mut members: Vec<String> = if pattern.contains('*') {
+            glob::glob(&project_dir.join(pattern).to_string_lossy())
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|dir| dir.join(manifest).exists())
+                .filter_map(|dir| {
+                    dir.strip_prefix(project_dir)
+                        .ok()
+                        .map(|p| p.to_string_lossy().into_owned())
+                })
+                .collect()
+        } else {
+            vec![pattern.trim_end_matches('/').to_string()]
+        };
+        members.sort();
+        for member in members {
+            let component = Component {
+                kind: ComponentKind::WorkspaceMember,
+                name: member,
+                detail: None,
+            };
+            if !self.components.contains(&component) {
+                self.components.push(component);
+            }
+        }
+    }
+
+    /// The components grouped under a `###` heading per kind
+    fn render_components(&self) -> String {
+        let kinds = [
+            ComponentKind::WorkspaceMember,
+            ComponentKind::PackageScript,
+            ComponentKind::DockerService,
+        ];
+        let mut sections = Vec::new();
+        for kind in kinds {
+            let items: Vec<String> = self
+                .components
+                .iter()
+                .filter(|c| c.kind == kind)
+                .map(|c| match &c.detail {
+                    Some(detail) => format!("- `{}`: {}", c.name, detail),
+                    None => format!("- `{}`", c.name),
+                })
+                .collect();
+            if !items.is_empty() {
+                sections.push(format!("### {}\n\n{}", kind.heading(), items.join("\n")));
+            }
+        }
+        sections.join("\n\n")
+    }
+}
+
+fn read_manifest(project_dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(project_dir.join(name)).ok()
 }
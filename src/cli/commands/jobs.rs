@@ -0,0 +1,137 @@
+use super::{backfill, generate};
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::models::Job;
+use serde_json::json;
+
+/// List jobs with `status` ("all" for every status), newest first
+pub async fn list(status: String) -> Result<()> {
+    tracing::info!("Listing jobs ({})", status);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let filter = (status != "all").then_some(status.as_str());
+    let jobs = storage.list_jobs(filter)?;
+
+    if output::is_json() {
+        let jobs: Vec<_> = jobs
+            .iter()
+            .map(|job| {
+                json!({
+                    "id": job.id,
+                    "kind": job.kind,
+                    "description": job.description,
+                    "status": job.status,
+                    "steps_done": done_steps(job),
+                    "steps": job.steps.len(),
+                    "error": job.error,
+                    "updated_at": job.updated_at,
+                })
+            })
+            .collect();
+        return output::print_json(&json!({ "jobs": jobs }));
+    }
+
+    if jobs.is_empty() {
+        match filter {
+            Some(status) => println!("No {} jobs.", status),
+            None => println!("No jobs."),
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{:<6} {:<10} {:<10} {:<9} {:<17} DESCRIPTION",
+        "ID", "KIND", "STATUS", "STEPS", "UPDATED"
+    );
+    for job in &jobs {
+        println!(
+            "{:<6} {:<10} {:<10} {:<9} {:<17} {}",
+            job.id,
+            job.kind,
+            job.status,
+            format!("{}/{}", done_steps(job), job.steps.len()),
+            job.updated_at.format("%Y-%m-%d %H:%M"),
+            job.description
+        );
+    }
+
+    Ok(())
+}
+
+/// Print a job and the state of each step
+pub async fn show(id: i64) -> Result<()> {
+    tracing::info!("Showing job {}", id);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let job = get_job(storage.as_ref(), id)?;
+
+    if output::is_json() {
+        return output::print_json(&job);
+    }
+
+    println!("Job {}: {}", job.id, job.description);
+    println!("  Kind: {}", job.kind);
+    println!("  Status: {}", job.status);
+    println!("  Started: {}", job.created_at.to_rfc3339());
+    println!("  Updated: {}", job.updated_at.to_rfc3339());
+    if let Some(error) = &job.error {
+        println!("  Error: {}", error);
+    }
+    println!("\nSteps ({}/{} done):", done_steps(&job), job.steps.len());
+    for step in &job.steps {
+        match &step.error {
+            Some(error) => println!("  {:<8} {} ({})", step.status, step.name, error),
+            None => println!("  {:<8} {}", step.status, step.name),
+        }
+    }
+
+    Ok(())
+}
+
+/// Run the steps of a job that are not done
+pub async fn resume(id: i64) -> Result<()> {
+    tracing::info!("Resuming job {}", id);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let job = get_job(storage.as_ref(), id)?;
+    if job.status == "completed" {
+        return Err(KtmeError::InvalidInput(format!(
+            "Job {} is already completed",
+            id
+        )));
+    }
+
+    storage.update_job_status(id, "running", None)?;
+    output::message(format!(
+        "Resuming job {} at step {} of {}",
+        id,
+        done_steps(&job) + 1,
+        job.steps.len()
+    ));
+    match job.kind.as_str() {
+        "backfill" => backfill::resume(&job).await,
+        "generate" => generate::resume(&job).await,
+        kind => Err(KtmeError::InvalidInput(format!(
+            "Jobs of kind '{}' cannot be resumed",
+            kind
+        ))),
+    }
+}
+
+fn get_job(storage: &dyn Storage, id: i64) -> Result<Job> {
+    storage
+        .get_job(id)?
+        .ok_or_else(|| KtmeError::NotFound(format!("Job {} not found", id)))
+}
+
+fn done_steps(job: &Job) -> usize {
+    job.steps
+        .iter()
+        .filter(|step| step.status == "done")
+        .count()
+}
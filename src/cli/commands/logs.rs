@@ -0,0 +1,159 @@
+use crate::cli::output;
+use crate::error::{KtmeError, Result};
+use crate::logging::{self, LogEntry};
+use crate::shutdown;
+use chrono::{DateTime, Utc};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How often `--follow` checks the log file for new lines
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+struct Filter {
+    since: Option<DateTime<Utc>>,
+    level: Option<tracing::Level>,
+}
+
+impl Filter {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.level.is_none_or(|level| entry.at_least(level))
+    }
+}
+
+pub async fn execute(
+    follow: bool,
+    since: Option<String>,
+    level: Option<String>,
+    lines: Option<usize>,
+) -> Result<()> {
+    let filter = Filter {
+        since: since
+            .as_deref()
+            .map(logging::parse_since)
+            .transpose()?
+            .map(|d| Utc::now() - d),
+        level: level
+            .as_deref()
+            .map(|l| {
+                l.parse::<tracing::Level>().map_err(|_| {
+                    KtmeError::InvalidInput(format!(
+                        "Invalid level '{}': expected error, warn, info, debug or trace",
+                        l
+                    ))
+                })
+            })
+            .transpose()?,
+    };
+
+    let dir = logging::log_dir()?;
+    let files = logging::log_files(&dir)?;
+
+    let mut entries = Vec::new();
+    for file in &files {
+        let content = fs::read_to_string(file)?;
+        entries.extend(
+            content
+                .lines()
+                .filter_map(LogEntry::parse)
+                .filter(|e| filter.matches(e)),
+        );
+    }
+    if let Some(lines) = lines {
+        let skip = entries.len().saturating_sub(lines);
+        entries.drain(..skip);
+    }
+
+    if output::is_json() && !follow {
+        return output::print_json(&entries);
+    }
+
+    if entries.is_empty() && !follow {
+        output::message(format!("No log entries found in {}", dir.display()));
+        return Ok(());
+    }
+
+    for entry in &entries {
+        print_entry(entry)?;
+    }
+
+    if follow {
+        let current = files.last().cloned();
+        let position = match &current {
+            Some(path) => fs::metadata(path)?.len(),
+            None => 0,
+        };
+        follow_logs(&dir, current, position, &filter).await?;
+    }
+
+    Ok(())
+}
+
+/// Print new lines as they are appended, moving on to the next file when
+/// the log rotates
+async fn follow_logs(
+    dir: &std::path::Path,
+    mut current: Option<PathBuf>,
+    mut position: u64,
+    filter: &Filter,
+) -> Result<()> {
+    let mut pending = String::new();
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_INTERVAL) => {}
+            _ = shutdown::cancelled() => return Ok(()),
+        }
+
+        let newest = logging::log_files(dir)?.pop();
+        if newest != current {
+            current = newest;
+            position = 0;
+            pending.clear();
+        }
+        let Some(path) = &current else {
+            continue;
+        };
+
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        if len < position {
+            // Truncated; start over
+            position = 0;
+        }
+        if len == position {
+            continue;
+        }
+
+        file.seek(SeekFrom::Start(position))?;
+        let mut chunk = String::new();
+        file.read_to_string(&mut chunk)?;
+        position = len;
+        pending.push_str(&chunk);
+
+        // Keep a trailing partial line for the next read
+        let complete = match pending.rfind('\n') {
+            Some(end) => pending.drain(..=end).collect::<String>(),
+            None => continue,
+        };
+        for entry in complete
+            .lines()
+            .filter_map(LogEntry::parse)
+            .filter(|e| filter.matches(e))
+        {
+            print_entry(&entry)?;
+        }
+    }
+}
+
+fn print_entry(entry: &LogEntry) -> Result<()> {
+    if output::is_json() {
+        // One JSON document per line so the stream can be consumed live
+        println!("{}", serde_json::to_string(entry)?);
+    } else {
+        println!("{}", entry.render());
+    }
+    Ok(())
+}
@@ -1,53 +1,188 @@
-use crate::error::Result;
-use crate::storage::mapping::StorageManager;
+use super::update::{extract_confluence_page_id, provider_config};
+use crate::cli::output;
+use crate::cli::progress::Progress;
+use crate::config::Config;
+use crate::doc::providers::config::MarkdownConfig;
+use crate::doc::providers::confluence::ConfluenceProvider;
+use crate::doc::providers::external;
+use crate::doc::providers::markdown::MarkdownProvider;
+use crate::doc::providers::{Document, DocumentMetadata, DocumentProvider, ProviderFactory};
+use crate::error::{KtmeError, Result};
+use crate::http;
+use crate::storage::backend::open_storage;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::ServiceSettings;
+use crate::translation;
+use serde_json::json;
 use std::path::Path;
 
-pub async fn add(service: Option<String>, url: Option<String>, file: Option<String>) -> Result<()> {
+pub async fn add(
+    service: Option<String>,
+    url: Option<String>,
+    file: Option<String>,
+    section: Option<String>,
+    settings: Vec<String>,
+    translation: Option<(String, String)>,
+) -> Result<()> {
     // Auto-detect service name if not provided
     let service_name = if let Some(s) = service {
         s
     } else {
         tracing::info!("Auto-detecting service name...");
+        let cache = crate::service_detector::detection_cache(&Config::load()?);
         let detector = crate::service_detector::ServiceDetector::new()?;
-        detector.detect_with_ai_fallback().await?
+        detector.detect_cached(cache.as_deref()).await?.name
     };
 
     tracing::info!("Adding mapping for service: {}", service_name);
 
     let storage = StorageManager::new()?;
 
-    if let Some(location) = url {
+    let (provider, location) = if let Some(location) = url {
         // Add URL mapping (typically Confluence)
-        let location_clone = location.clone();
-        storage.add_mapping(service_name.clone(), "confluence".to_string(), location)?;
-        println!("✓ Added mapping: {} -> {}", service_name, location_clone);
+        ("confluence", location)
     } else if let Some(location) = file {
         // Add file mapping (local markdown)
+        // A translation is written by its first sync
         let path = Path::new(&location);
-        if !path.exists() {
+        if !path.exists() && translation.is_none() {
             return Err(crate::error::KtmeError::Config(format!(
                 "File does not exist: {}",
                 location
             )));
         }
-        let location_clone = location.clone();
-        storage.add_mapping(service_name.clone(), "markdown".to_string(), location)?;
-        println!("✓ Added mapping: {} -> {}", service_name, location_clone);
+        ("markdown", location)
+    } else if !settings.is_empty() {
+        // Only update the settings of an existing mapping
+        let settings = storage.set_settings(&service_name, &settings)?;
+        return print_settings_updated(&service_name, &settings);
     } else {
         return Err(crate::error::KtmeError::Config(
-            "Either --url or --file must be provided".to_string(),
+            "Either --url, --file or --set must be provided".to_string(),
         ));
+    };
+    if let Some((source, language)) = translation {
+        return add_translation(
+            &storage,
+            &service_name,
+            &source,
+            provider,
+            &location,
+            &language,
+        );
+    }
+    storage.add_mapping(
+        service_name.clone(),
+        DocumentLocation {
+            r#type: provider.to_string(),
+            location: location.clone(),
+            section: section.clone(),
+        },
+    )?;
+
+    let settings = if settings.is_empty() {
+        None
+    } else {
+        Some(storage.set_settings(&service_name, &settings)?)
+    };
+
+    if output::is_json() {
+        output::print_json(&json!({
+            "service": service_name,
+            "provider": provider,
+            "location": location,
+            "section": section,
+            "settings": settings,
+        }))?;
+    } else {
+        println!("✓ Added mapping: {} -> {}", service_name, location);
+        if let Some(section) = &section {
+            println!("  Section: {}", section);
+        }
+        if let Some(settings) = &settings {
+            print_settings("  ", settings);
+        }
+    }
+
+    Ok(())
+}
+
+/// Pair the document at `location` with the mapped `source` document of
+/// `service` it translates into `language`
+fn add_translation(
+    storage: &StorageManager,
+    service: &str,
+    source: &str,
+    provider: &str,
+    location: &str,
+    language: &str,
+) -> Result<()> {
+    let mapping = storage.get_mapping(service)?;
+    if !mapping.docs.iter().any(|doc| doc.location == source) {
+        return Err(KtmeError::DocumentNotFound(format!(
+            "{} is not a documentation location of service {}",
+            source, service
+        )));
     }
+    let config = Config::load()?;
+    open_storage(&config.storage)?
+        .add_translation(service, source, provider, location, language)?;
+
+    if output::is_json() {
+        output::print_json(&json!({
+            "service": service,
+            "provider": provider,
+            "location": location,
+            "translation_of": source,
+            "language": language,
+        }))
+    } else {
+        println!(
+            "✓ Added translation: {} -> {} ({})",
+            source, location, language
+        );
+        println!(
+            "  Run `ktme translate --service {}` to write it now",
+            service
+        );
+        Ok(())
+    }
+}
 
+fn print_settings_updated(service: &str, settings: &ServiceSettings) -> Result<()> {
+    if output::is_json() {
+        return output::print_json(&json!({ "service": service, "settings": settings }));
+    }
+    println!("✓ Updated settings for service: {}", service);
+    print_settings("  ", settings);
     Ok(())
 }
 
+fn print_settings(indent: &str, settings: &ServiceSettings) {
+    if settings.is_empty() {
+        return;
+    }
+    println!("{}Settings:", indent);
+    for line in settings.describe() {
+        println!("{}  {}", indent, line);
+    }
+}
+
 pub async fn list(service: Option<String>) -> Result<()> {
     tracing::info!("Listing service mappings");
 
     let storage = StorageManager::new()?;
     let mappings = storage.load_mappings()?;
 
+    if output::is_json() {
+        let services: Vec<_> = mappings
+            .services
+            .iter()
+            .filter(|s| service.as_ref().is_none_or(|name| &s.name == name))
+            .collect();
+        return output::print_json(&json!({ "services": services }));
+    }
+
     if mappings.services.is_empty() {
         println!("No service mappings found.");
         println!(
@@ -67,6 +202,7 @@ pub async fn list(service: Option<String>) -> Result<()> {
             for doc in &service_mapping.docs {
                 println!("    - {} ({})", doc.location, doc.r#type);
             }
+            print_settings("  ", &service_mapping.settings);
         } else {
             println!("No mapping found for service: {}", filter);
         }
@@ -83,20 +219,54 @@ pub async fn list(service: Option<String>) -> Result<()> {
             for doc in &service_mapping.docs {
                 println!("    - {} ({})", doc.location, doc.r#type);
             }
+            print_settings("  ", &service_mapping.settings);
         }
     }
 
     Ok(())
 }
 
-pub async fn get(service: String) -> Result<()> {
+pub async fn get(service: String, details: bool) -> Result<()> {
     tracing::info!("Getting mapping for service: {}", service);
 
     let storage = StorageManager::new()?;
     let mapping = storage.get_mapping(&service)?;
+    let config = Config::load()?;
+    let translations = translation::translations(&config, &service, None).unwrap_or_default();
+
+    let mut metadata = Vec::new();
+    if details {
+        http::configure(&config.timeouts);
+        for doc in &mapping.docs {
+            metadata.push(location_metadata(&config, doc).await);
+        }
+    }
+
+    if output::is_json() {
+        if !details && translations.is_empty() {
+            return output::print_json(&mapping);
+        }
+        let mut value = serde_json::to_value(&mapping)?;
+        if !translations.is_empty() {
+            value["translations"] = serde_json::to_value(&translations)?;
+        }
+        if !details {
+            return output::print_json(&value);
+        }
+        value["details"] = mapping
+            .docs
+            .iter()
+            .zip(&metadata)
+            .map(|(doc, metadata)| match metadata {
+                Ok(metadata) => json!({ "location": doc.location, "metadata": metadata }),
+                Err(e) => json!({ "location": doc.location, "error": e.to_string() }),
+            })
+            .collect();
+        return output::print_json(&value);
+    }
 
     println!("Service: {}", mapping.name);
-    if let Some(path) = mapping.path {
+    if let Some(path) = &mapping.path {
         println!("Path: {}", path);
     }
 
@@ -104,36 +274,423 @@ pub async fn get(service: String) -> Result<()> {
         println!("No documentation locations mapped for this service.");
     } else {
         println!("Documentation locations:");
-        for doc in mapping.docs {
+        for (index, doc) in mapping.docs.iter().enumerate() {
             println!("  - {} ({})", doc.location, doc.r#type);
+            if let Some(section) = &doc.section {
+                println!("      Section: {}", section);
+            }
+            match metadata.get(index) {
+                Some(Ok(Some(metadata))) => print_metadata(metadata),
+                Some(Ok(None)) => println!("      Not found"),
+                Some(Err(e)) => println!("      Details unavailable: {}", e),
+                None => {}
+            }
         }
     }
+    if !translations.is_empty() {
+        println!("Translations:");
+        for translation in &translations {
+            println!(
+                "  - {} ({}) of {}",
+                translation.target, translation.language, translation.source
+            );
+        }
+    }
+    print_settings("", &mapping.settings);
 
     Ok(())
 }
 
+/// Metadata of a documentation location read from its provider; `None` when
+/// the document does not exist or the location type has no provider
+async fn location_metadata(
+    config: &Config,
+    doc: &DocumentLocation,
+) -> Result<Option<DocumentMetadata>> {
+    Ok(read_location(config, doc)
+        .await?
+        .map(|document| document.metadata))
+}
+
+/// Document at a documentation location, its content as Markdown; `None`
+/// when the document does not exist or the location type has no provider
+pub(crate) async fn read_location(
+    config: &Config,
+    doc: &DocumentLocation,
+) -> Result<Option<Document>> {
+    Ok(match doc.r#type.as_str() {
+        "markdown" => {
+            let extension = Path::new(&doc.location)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("md");
+            MarkdownProvider::new(MarkdownConfig {
+                base_path: String::new(),
+                extension: extension.to_string(),
+                auto_create_dirs: false,
+                backup: false,
+            })
+            .get_document(&doc.location)
+            .await?
+        }
+        "confluence" => {
+            http::ensure_online("Reading Confluence pages")?;
+            let page_id = extract_confluence_page_id(&doc.location)?;
+            ConfluenceProvider::from_settings(&config.confluence)?
+                .get_document(&page_id)
+                .await?
+        }
+        provider_type => match provider_config(config, provider_type)? {
+            Some(provider_config) => {
+                http::ensure_online(&format!("Reading {} documents", provider_type))?;
+                ProviderFactory::create(provider_type, provider_config)?
+                    .get_document(&doc.location)
+                    .await?
+            }
+            None => None,
+        },
+    })
+}
+
+fn print_metadata(metadata: &DocumentMetadata) {
+    let fields = [
+        ("Author", metadata.author.clone()),
+        ("Created", metadata.created_at.clone()),
+        ("Updated", metadata.updated_at.clone()),
+        ("Version", metadata.version.map(|v| v.to_string())),
+        (
+            "Labels",
+            (!metadata.labels.is_empty()).then(|| metadata.labels.join(", ")),
+        ),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            println!("      {}: {}", name, value);
+        }
+    }
+}
+
 pub async fn remove(service: String) -> Result<()> {
     tracing::info!("Removing mapping for service: {}", service);
 
     let storage = StorageManager::new()?;
 
-    // Check if mapping exists
-    let mappings = storage.load_mappings()?;
-    if !mappings.services.iter().any(|s| s.name == service) {
-        return Err(crate::error::KtmeError::MappingNotFound(service));
+    // Fails with MappingNotFound when the service has no mapping
+    storage.remove_mapping(&service)?;
+    if let Ok(backend) = Config::load().and_then(|config| open_storage(&config.storage)) {
+        backend.remove_translations(&service)?;
     }
 
-    storage.remove_mapping(&service)?;
-    println!("✓ Removed mapping for service: {}", service);
+    if output::is_json() {
+        output::print_json(&json!({ "service": service, "removed": true }))?;
+    } else {
+        println!("✓ Removed mapping for service: {}", service);
+    }
 
     Ok(())
 }
 
+/// Rename a service, keeping its mappings, features, history and queued
+/// documentation. With `update_titles` the service name is also replaced in
+/// the titles of its Markdown files (first heading) and Confluence pages.
+pub async fn rename(old: String, new: String, update_titles: bool) -> Result<()> {
+    tracing::info!("Renaming service {} to {}", old, new);
+
+    let config = Config::load()?;
+    let storage = StorageManager::from_config(&config)?;
+    let docs = storage.get_mapping(&old)?.docs;
+    storage.rename_service(&old, &new)?;
+    if !storage.use_database {
+        // Features and history live in the database even when mappings are
+        // kept in mappings.toml
+        if let Err(e) = open_storage(&config.storage).and_then(|db| db.rename_service(&old, &new)) {
+            tracing::warn!("Failed to rename {} in the knowledge base: {}", old, e);
+        }
+    }
+
+    let mut titles = Vec::new();
+    if update_titles {
+        http::configure(&config.timeouts);
+        for doc in &docs {
+            let renamed = retitle(&config, doc, &old, &new).await;
+            titles.push((doc, renamed));
+        }
+    }
+
+    if output::is_json() {
+        let titles: Vec<_> = titles
+            .iter()
+            .map(|(doc, renamed)| match renamed {
+                Ok(renamed) => json!({ "location": doc.location, "renamed": renamed }),
+                Err(e) => json!({ "location": doc.location, "error": e.to_string() }),
+            })
+            .collect();
+        return output::print_json(&json!({
+            "service": new,
+            "previous": old,
+            "titles": titles,
+        }));
+    }
+
+    println!("✓ Renamed service {} to {}", old, new);
+    for (doc, renamed) in &titles {
+        match renamed {
+            Ok(true) => println!("  ✓ Updated the title of {}", doc.location),
+            Ok(false) => println!("  - {}: title does not mention {}", doc.location, old),
+            Err(e) => println!("  ✗ {}: {}", doc.location, e),
+        }
+    }
+    Ok(())
+}
+
+/// Replace `old` by `new` in the title of a mapped document; false when the
+/// title does not contain it
+async fn retitle(config: &Config, doc: &DocumentLocation, old: &str, new: &str) -> Result<bool> {
+    match doc.r#type.as_str() {
+        "markdown" => {
+            let content = std::fs::read_to_string(&doc.location)?;
+            let Some(heading) = content
+                .lines()
+                .find(|line| line.starts_with('#') && line.contains(old))
+            else {
+                return Ok(false);
+            };
+            let updated = content.replacen(heading, &heading.replace(old, new), 1);
+            std::fs::write(&doc.location, updated)?;
+            Ok(true)
+        }
+        "confluence" => {
+            http::ensure_online("Renaming Confluence pages")?;
+            let page_id = extract_confluence_page_id(&doc.location)?;
+            let previous = ConfluenceProvider::from_settings(&config.confluence)?
+                .retitle_page(&page_id, |title| title.replace(old, new))
+                .await?
+                .ok_or_else(|| KtmeError::DocumentNotFound(doc.location.clone()))?;
+            Ok(previous.contains(old))
+        }
+        other => Err(KtmeError::UnsupportedOperation(format!(
+            "Renaming {} documents is not supported",
+            other
+        ))),
+    }
+}
+
+/// Result of checking one mapped documentation location
+#[derive(Debug, PartialEq)]
+enum Check {
+    Ok,
+    /// The document no longer exists; `--fix` removes the entry
+    Missing(String),
+    /// Something to fix by hand, such as a missing provider configuration
+    Problem(String),
+}
+
+/// Check the mappings of `service` (all services without one): Markdown
+/// files exist, Confluence pages resolve, the location's provider is
+/// configured and the service path exists. `fix` removes the locations of
+/// documents that no longer exist.
+pub async fn validate(service: Option<String>, fix: bool) -> Result<()> {
+    tracing::info!("Validating mappings");
+
+    let config = Config::load()?;
+    http::configure(&config.timeouts);
+    let storage = StorageManager::from_config(&config)?;
+    let services = match service {
+        Some(service) => vec![service],
+        None => storage.list_services()?,
+    };
+    let saved_providers: Vec<String> = open_storage(&config.storage)
+        .and_then(|storage| storage.list_provider_configs())
+        .map(|providers| providers.into_iter().map(|p| p.provider_type).collect())
+        .unwrap_or_default();
+
+    let progress = Progress::spinner(format!("Validating {} mapping(s)", services.len()));
+    let mut reports = Vec::new();
+    for name in &services {
+        let mapping = storage.get_mapping(name)?;
+        let path = mapping
+            .path
+            .as_ref()
+            .filter(|path| !Path::new(path).exists())
+            .map(|path| format!("Service path {} does not exist", path));
+        let mut checks = Vec::new();
+        for doc in mapping.docs {
+            let check = check_location(&config, &saved_providers, &doc).await;
+            checks.push((doc, check));
+        }
+        reports.push((mapping.name.clone(), path, checks));
+    }
+    progress.finish();
+
+    let mut removed = 0;
+    if fix {
+        for (service, _, checks) in &reports {
+            for (doc, check) in checks {
+                if matches!(check, Check::Missing(_))
+                    && storage.remove_location(service, &doc.location)?
+                {
+                    removed += 1;
+                }
+            }
+        }
+    }
+
+    let locations: usize = reports.iter().map(|(_, _, checks)| checks.len()).sum();
+    let missing = reports
+        .iter()
+        .flat_map(|(_, _, checks)| checks)
+        .filter(|(_, check)| matches!(check, Check::Missing(_)))
+        .count();
+    let problems = reports
+        .iter()
+        .map(|(_, path, checks)| {
+            path.iter().count()
+                + checks
+                    .iter()
+                    .filter(|(_, check)| matches!(check, Check::Problem(_)))
+                    .count()
+        })
+        .sum::<usize>();
+
+    if output::is_json() {
+        let services: Vec<_> = reports
+            .iter()
+            .map(|(service, path, checks)| {
+                let locations: Vec<_> = checks
+                    .iter()
+                    .map(|(doc, check)| {
+                        let (status, message) = match check {
+                            Check::Ok => ("ok", None),
+                            Check::Missing(message) => ("missing", Some(message)),
+                            Check::Problem(message) => ("problem", Some(message)),
+                        };
+                        json!({
+                            "type": doc.r#type,
+                            "location": doc.location,
+                            "status": status,
+                            "message": message,
+                            "removed": fix && matches!(check, Check::Missing(_)),
+                        })
+                    })
+                    .collect();
+                json!({ "service": service, "path_error": path, "locations": locations })
+            })
+            .collect();
+        output::print_json(&json!({
+            "services": services,
+            "locations": locations,
+            "missing": missing,
+            "problems": problems,
+            "removed": removed,
+        }))?;
+    } else {
+        for (service, path, checks) in &reports {
+            println!("{}", service);
+            if let Some(path) = path {
+                println!("  ✗ {}", path);
+            }
+            for (doc, check) in checks {
+                match check {
+                    Check::Ok => println!("  ✓ {} ({})", doc.location, doc.r#type),
+                    Check::Missing(message) if fix => {
+                        println!("  - {}: {}, removed", doc.location, message)
+                    }
+                    Check::Missing(message) | Check::Problem(message) => {
+                        println!("  ✗ {}: {}", doc.location, message)
+                    }
+                }
+            }
+        }
+        println!(
+            "\nChecked {} location(s) of {} service(s): {} missing, {} other problem(s)",
+            locations,
+            reports.len(),
+            missing,
+            problems
+        );
+        if removed > 0 {
+            println!("Removed {} dead location(s)", removed);
+        } else if missing > 0 {
+            println!("Run 'ktme mapping validate --fix' to remove the missing locations");
+        }
+    }
+
+    let remaining = problems + missing - removed;
+    if remaining > 0 {
+        return Err(KtmeError::Config(format!(
+            "{} mapping problem(s) found",
+            remaining
+        )));
+    }
+    Ok(())
+}
+
+/// Check one documentation location against its provider
+async fn check_location(
+    config: &Config,
+    saved_providers: &[String],
+    doc: &DocumentLocation,
+) -> Check {
+    match doc.r#type.as_str() {
+        "markdown" => {
+            if Path::new(&doc.location).is_file() {
+                Check::Ok
+            } else {
+                Check::Missing("file does not exist".to_string())
+            }
+        }
+        "confluence" => check_confluence_page(config, &doc.location).await,
+        provider if saved_providers.iter().any(|saved| saved == provider) => Check::Ok,
+        provider if external::find(provider).is_some() => Check::Ok,
+        provider => Check::Problem(format!(
+            "no {} provider configured; add one with 'ktme provider add {}'",
+            provider, provider
+        )),
+    }
+}
+
+async fn check_confluence_page(config: &Config, location: &str) -> Check {
+    let provider = match ConfluenceProvider::from_settings(&config.confluence) {
+        Ok(provider) => provider,
+        Err(e) => return Check::Problem(format!("Confluence is not configured: {}", e)),
+    };
+    if let Err(e) = http::ensure_online("Checking Confluence pages") {
+        return Check::Problem(e.to_string());
+    }
+    let found = match extract_confluence_page_id(location) {
+        Ok(page_id) => provider.get_document(&page_id).await,
+        Err(e) => match confluence_title(location) {
+            Some(title) => provider.find_document(&title).await,
+            None => return Check::Problem(e.to_string()),
+        },
+    };
+    match found {
+        Ok(Some(_)) => Check::Ok,
+        Ok(None) => Check::Missing("page does not exist".to_string()),
+        Err(e) => Check::Problem(format!("could not check the page: {}", e)),
+    }
+}
+
+/// Page title of a `/display/SPACE/Page+Title` Confluence URL
+fn confluence_title(location: &str) -> Option<String> {
+    let (_, rest) = location.split_once("/display/")?;
+    let (_, title) = rest.split_once('/')?;
+    let title = title.split(['?', '#']).next()?.replace('+', " ");
+    let title = urlencoding::decode(&title).ok()?.trim().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
 pub async fn discover(directory: String) -> Result<()> {
     tracing::info!("Discovering services in directory: {}", directory);
 
     let storage = StorageManager::new()?;
+    let progress = Progress::spinner(format!("Scanning {} for services", directory));
     let discovered = storage.discover_services(&directory)?;
+    progress.finish();
+
+    if output::is_json() {
+        return output::print_json(&json!({ "services": discovered }));
+    }
 
     if discovered.is_empty() {
         println!("No services discovered in directory: {}", directory);
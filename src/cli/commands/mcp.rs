@@ -1,13 +1,33 @@
-use crate::error::Result;
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::logging;
+use crate::mcp::limits::ToolLimits;
+use crate::mcp::protocol::ToolFilter;
 use crate::mcp::server::{McpServer, ServerConfig};
 use crate::mcp::stdio_server::StdioServer;
+use crate::mcp::trace::{Direction, TraceEntry, Tracer};
+use crate::storage::backend::open_storage;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 
-pub async fn start(config: Option<String>, daemon: bool, stdio: bool) -> Result<()> {
+pub async fn start(
+    config: Option<String>,
+    daemon: bool,
+    stdio: bool,
+    tools: Vec<String>,
+    trace: Option<String>,
+) -> Result<()> {
     // Only enable tracing if not in STDIO mode
     if !stdio {
         tracing::info!("Starting MCP server");
     }
 
+    let mcp = Config::load().unwrap_or_default().mcp;
+    let tools = ToolFilter::from_config(&mcp, tools);
+    let limits = ToolLimits::from_config(&mcp)?;
+    let trace = Tracer::from_config(&mcp, trace.as_deref())?;
     let server_config = ServerConfig {
         server_name: "ktme-mcp-server".to_string(),
         transport: if stdio {
@@ -18,6 +38,9 @@ pub async fn start(config: Option<String>, daemon: bool, stdio: bool) -> Result<
             "stdio".to_string()
         },
         port: if daemon || !stdio { Some(3000) } else { None },
+        tools: tools.clone(),
+        limits: limits.clone(),
+        trace: trace.clone(),
     };
 
     let server = McpServer::new(server_config)?;
@@ -38,7 +61,10 @@ pub async fn start(config: Option<String>, daemon: bool, stdio: bool) -> Result<
         server.start().await
     } else if stdio {
         // Use clean STDIO server with no logging or output
-        let stdio_server = StdioServer::new();
+        let stdio_server = StdioServer::new()
+            .with_tool_filter(tools)
+            .with_limits(limits)
+            .with_trace(trace);
         stdio_server.run().await
     } else {
         tracing::info!("Running in STDIO mode (default)");
@@ -110,3 +136,134 @@ pub async fn stop() -> Result<()> {
 
     Ok(())
 }
+
+/// Print the tool calls recorded in the last `since` (e.g. 24h), newest
+/// first, optionally only those of `tool`
+pub async fn audit(since: String, tool: Option<String>) -> Result<()> {
+    tracing::info!("Listing MCP tool calls of the last {}", since);
+
+    let from = Utc::now() - logging::parse_since(&since)?;
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let tool = tool.map(|tool| tool.trim_start_matches("ktme_").to_string());
+    let calls: Vec<_> = storage
+        .tool_calls_since(from)?
+        .into_iter()
+        .filter(|call| {
+            tool.as_deref()
+                .is_none_or(|tool| call.tool.trim_start_matches("ktme_") == tool)
+        })
+        .collect();
+
+    if output::is_json() {
+        return output::print_json(&json!({ "since": from, "calls": calls }));
+    }
+
+    if calls.is_empty() {
+        println!("No MCP tool calls in the last {}.", since);
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:<28} {:<10} {:<8} {:>9}  ARGUMENTS",
+        "TIME", "TOOL", "TRANSPORT", "STATUS", "DURATION"
+    );
+    for call in &calls {
+        println!(
+            "{:<20} {:<28} {:<10} {:<8} {:>7}ms  {}",
+            call.created_at.format("%Y-%m-%d %H:%M:%S"),
+            call.tool,
+            call.transport,
+            call.status,
+            call.duration_ms,
+            call.arguments
+        );
+        if let Some(error) = &call.error {
+            println!("{:<20} {}", "", error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a session captured with `mcp start --trace`: one line per
+/// message, followed by the message itself unless `summary`
+pub async fn trace(file: String, summary: bool) -> Result<()> {
+    let content = std::fs::read_to_string(&file)
+        .map_err(|e| KtmeError::NotFound(format!("Trace file {}: {}", file, e)))?;
+    let entries = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str::<TraceEntry>(line).map_err(|e| {
+                KtmeError::InvalidInput(format!("{} line {}: {}", file, number + 1, e))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if output::is_json() {
+        return output::print_json(&json!({ "file": file, "messages": entries }));
+    }
+
+    if entries.is_empty() {
+        println!("No messages in {}.", file);
+        return Ok(());
+    }
+
+    // Requests by id, to show how long their responses took
+    let mut requests: HashMap<String, DateTime<Utc>> = HashMap::new();
+    for entry in &entries {
+        let message = &entry.message;
+        let id = message
+            .get("id")
+            .filter(|id| !id.is_null())
+            .map(|id| id.to_string());
+        let mut line = format!("{} ", entry.at.format("%H:%M:%S%.3f"));
+        match entry.direction {
+            Direction::In => {
+                let method = message["method"].as_str().unwrap_or("(no method)");
+                line.push_str(&format!("→ {}", method));
+                if let Some(tool) = message["params"]["name"].as_str() {
+                    line.push_str(&format!(" {}", tool));
+                }
+                match &id {
+                    Some(id) => {
+                        line.push_str(&format!(" #{}", id));
+                        requests.insert(id.clone(), entry.at);
+                    }
+                    None if message.is_string() => line.push_str(" (not JSON)"),
+                    None => line.push_str(" (notification)"),
+                }
+            }
+            Direction::Out => {
+                line.push_str("← response");
+                if let Some(id) = &id {
+                    line.push_str(&format!(" #{}", id));
+                    if let Some(at) = requests.remove(id) {
+                        line.push_str(&format!(" ({} ms)", (entry.at - at).num_milliseconds()));
+                    }
+                }
+                if let Some(error) = message.get("error") {
+                    line.push_str(&format!(
+                        " error {}: {}",
+                        error["code"],
+                        error["message"].as_str().unwrap_or("")
+                    ));
+                }
+            }
+        }
+        println!("{}", line);
+        if !summary {
+            let pretty = match message {
+                Value::String(raw) => raw.clone(),
+                _ => serde_json::to_string_pretty(message)?,
+            };
+            for text in pretty.lines() {
+                println!("    {}", text);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,9 +1,32 @@
+pub mod ask;
+pub mod auth;
+pub mod backfill;
+pub mod cache;
+pub mod completions;
 pub mod config;
+pub mod db;
+pub mod dedupe;
+pub mod export;
 pub mod extract;
+pub mod feature;
 pub mod generate;
+pub mod import;
+pub mod index;
 pub mod init;
+pub mod jobs;
+pub mod logs;
 pub mod mapping;
 pub mod mcp;
+pub mod plugins;
+pub mod prompts;
+pub mod providers;
+pub mod queue;
+pub mod republish;
+pub mod run;
 pub mod search;
+pub mod self_update;
+pub mod service;
+pub mod template;
+pub mod translate;
 pub mod tree;
 pub mod update;
@@ -0,0 +1,80 @@
+use crate::ai::providers::PLUGIN_PREFIX as AI_PLUGIN_PREFIX;
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::providers::external::PLUGIN_PREFIX as PROVIDER_PLUGIN_PREFIX;
+use crate::error::Result;
+use crate::plugins::{self, extensions};
+use serde_json::json;
+
+/// List provider and AI plugins on PATH and WASM extensions in the plugins
+/// directory
+pub async fn list() -> Result<()> {
+    tracing::info!("Listing plugins");
+
+    let config = Config::load()?;
+    let providers = plugins::discover(PROVIDER_PLUGIN_PREFIX);
+    let ai = plugins::discover(AI_PLUGIN_PREFIX);
+    let directory = extensions::directory(&config)?;
+    let wasm: Vec<_> = extensions::files(&directory)
+        .into_iter()
+        .map(|file| {
+            let kinds = extensions::describe(&file);
+            (file, kinds)
+        })
+        .collect();
+
+    if output::is_json() {
+        let executables = |found: &[plugins::Plugin]| -> Vec<serde_json::Value> {
+            found
+                .iter()
+                .map(|p| json!({ "name": p.name, "path": p.path }))
+                .collect()
+        };
+        let wasm: Vec<_> = wasm
+            .iter()
+            .map(|(file, kinds)| match kinds {
+                Ok(kinds) => json!({ "name": file.name, "path": file.path, "kinds": kinds }),
+                Err(e) => json!({ "name": file.name, "path": file.path, "error": e.to_string() }),
+            })
+            .collect();
+        return output::print_json(&json!({
+            "providers": executables(&providers),
+            "ai": executables(&ai),
+            "wasm": { "directory": directory, "extensions": wasm },
+        }));
+    }
+
+    println!(
+        "Document provider plugins ({}<name> on PATH):",
+        PROVIDER_PLUGIN_PREFIX
+    );
+    print_executables(&providers);
+
+    println!(
+        "\nAI provider plugins ({}<name> on PATH):",
+        AI_PLUGIN_PREFIX
+    );
+    print_executables(&ai);
+
+    println!("\nWASM extensions ({}):", directory.display());
+    if wasm.is_empty() {
+        println!("  (none)");
+    }
+    for (file, kinds) in &wasm {
+        match kinds {
+            Ok(kinds) => println!("  {:<20} {}", file.name, kinds.join(", ")),
+            Err(e) => println!("  {:<20} not loaded: {}", file.name, e),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_executables(found: &[plugins::Plugin]) {
+    if found.is_empty() {
+        println!("  (none)");
+    }
+    for plugin in found {
+        println!("  {:<20} {}", plugin.name, plugin.path.display());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::ai::prompt_pack::{self, PromptPack};
+use crate::ai::prompts::PromptTemplates;
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::Result;
+use serde_json::json;
+use std::path::PathBuf;
+
+/// Write the built-in prompts as editable files: to `dir`, the directory of
+/// prompt pack `pack`, or the overrides in the config directory
+pub async fn export(dir: Option<String>, pack: Option<String>, force: bool) -> Result<()> {
+    let dir = match (dir, &pack) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(pack)) => prompt_pack::packs_dir()?.join(pack),
+        (None, None) => prompt_pack::overrides_dir()?,
+    };
+    tracing::info!("Exporting prompts to {}", dir.display());
+
+    let files = prompt_pack::export(&dir, force)?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "directory": dir,
+            "files": files.iter().map(|(path, written)| json!({
+                "path": path,
+                "written": written,
+            })).collect::<Vec<_>>(),
+        }));
+    }
+
+    for (path, written) in &files {
+        if *written {
+            println!("✓ {}", path.display());
+        } else {
+            println!("- {} (exists, kept; --force overwrites)", path.display());
+        }
+    }
+    println!("Edit the files to change the prompts ktme sends to the AI provider.");
+    Ok(())
+}
+
+/// Show where each prompt comes from with prompt pack `pack`, by default
+/// the configured one
+pub async fn list(pack: Option<String>) -> Result<()> {
+    let pack = match pack {
+        Some(pack) => Some(pack),
+        None => Config::load()?.documentation.prompt_pack,
+    };
+    let prompts = PromptPack::load(pack.as_deref())?;
+    let sources: Vec<(&str, Option<PathBuf>)> = PromptTemplates::NAMES
+        .iter()
+        .map(|name| (*name, prompts.file(name)))
+        .collect();
+    let packs = prompt_pack::available_packs()?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "pack": prompts.name(),
+            "prompts": sources.iter().map(|(name, file)| json!({
+                "name": name,
+                "file": file,
+            })).collect::<Vec<_>>(),
+            "packs": packs,
+        }));
+    }
+
+    if let Some(pack) = prompts.name() {
+        println!("Prompt pack: {}", pack);
+    }
+    for (name, file) in &sources {
+        match file {
+            Some(file) => println!("  {:<16} {}", name, file.display()),
+            None => println!("  {:<16} built-in", name),
+        }
+    }
+    if !packs.is_empty() {
+        println!("Available packs: {}", packs.join(", "));
+    }
+    Ok(())
+}
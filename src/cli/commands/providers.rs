@@ -0,0 +1,379 @@
+use crate::cli::output;
+use crate::cli::parallel::run_limited;
+use crate::config::Config;
+use crate::doc::providers::ProviderFactory;
+use crate::error::{ErrorClass, KtmeError, Result};
+use crate::http;
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::models::ProviderConfig;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+/// Outcome of the health check of one saved provider
+struct Health {
+    provider: ProviderConfig,
+    latency: Duration,
+    result: Result<()>,
+}
+
+impl Health {
+    fn status(&self) -> &'static str {
+        match &self.result {
+            Ok(()) => "ok",
+            Err(KtmeError::Timeout(_)) => "timeout",
+            Err(e) if e.class() == ErrorClass::Auth => "auth failed",
+            Err(_) => "down",
+        }
+    }
+
+    /// Whether the provider accepted the credentials; unknown when it could
+    /// not be reached
+    fn auth(&self) -> Option<bool> {
+        match &self.result {
+            Ok(()) => Some(true),
+            Err(e) if e.class() == ErrorClass::Auth => Some(false),
+            Err(_) => None,
+        }
+    }
+}
+
+/// Keys of provider configuration values not printed by `provider list`
+const SECRET_KEYS: [&str; 4] = ["api_token", "api_key", "client_secret", "password"];
+
+fn repository(config: &Config) -> Result<Box<dyn Storage>> {
+    open_storage(&config.storage)
+}
+
+/// Save the configuration of a `provider_type` provider, given as JSON
+/// (`--config`) or a JSON file (`--file`), after checking it against the
+/// provider's configuration. The first provider saved becomes the default.
+pub async fn add(
+    provider_type: String,
+    json: Option<String>,
+    file: Option<String>,
+    default: bool,
+) -> Result<()> {
+    tracing::info!("Adding provider {}", provider_type);
+
+    let text = match (json, file) {
+        (Some(json), _) => json,
+        (None, Some(file)) => std::fs::read_to_string(&file).map_err(|e| {
+            KtmeError::InvalidInput(format!("Failed to read provider config {}: {}", file, e))
+        })?,
+        (None, None) => {
+            return Err(KtmeError::InvalidInput(
+                "Pass the provider configuration with --config or --file".to_string(),
+            ))
+        }
+    };
+    let provider_config: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| KtmeError::InvalidInput(format!("Invalid provider config JSON: {}", e)))?;
+    ProviderFactory::validate(&provider_type, &provider_config).map_err(|e| match e {
+        KtmeError::Config(message) => KtmeError::Config(format!(
+            "Invalid {} configuration: {}",
+            provider_type, message
+        )),
+        e => e,
+    })?;
+
+    let config = Config::load()?;
+    let repository = repository(&config)?;
+    let existing = repository.list_provider_configs()?;
+    let default = default
+        || existing.iter().all(|p| p.provider_type == provider_type)
+        || existing
+            .iter()
+            .any(|p| p.provider_type == provider_type && p.is_default);
+    repository.save_provider_config(&provider_type, &provider_config, default)?;
+    if default {
+        repository.set_default_provider(&provider_type)?;
+    }
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "provider": provider_type,
+            "default": default,
+            "saved": true,
+        }));
+    }
+    output::message(format!(
+        "✓ Saved {} provider{}",
+        provider_type,
+        if default { " (default)" } else { "" }
+    ));
+    Ok(())
+}
+
+/// List the saved providers, default first, with secrets hidden
+pub async fn list() -> Result<()> {
+    tracing::info!("Listing providers");
+
+    let config = Config::load()?;
+    let providers = repository(&config)?.list_provider_configs()?;
+
+    if output::is_json() {
+        let providers: Vec<_> = providers
+            .iter()
+            .map(|provider| {
+                json!({
+                    "provider": provider.provider_type,
+                    "default": provider.is_default,
+                    "config": redacted(&provider.config),
+                    "updated_at": provider.updated_at,
+                })
+            })
+            .collect();
+        return output::print_json(&json!({ "providers": providers }));
+    }
+
+    if providers.is_empty() {
+        println!("No document providers configured.");
+        return Ok(());
+    }
+    println!(
+        "{:<16} {:<8} {:<17} CONFIG",
+        "PROVIDER", "DEFAULT", "UPDATED"
+    );
+    for provider in &providers {
+        println!(
+            "{:<16} {:<8} {:<17} {}",
+            provider.provider_type,
+            if provider.is_default { "yes" } else { "" },
+            provider.updated_at.format("%Y-%m-%d %H:%M"),
+            redacted(&provider.config)
+        );
+    }
+    Ok(())
+}
+
+/// Make the saved `provider_type` provider the default
+pub async fn set_default(provider_type: String) -> Result<()> {
+    tracing::info!("Setting default provider {}", provider_type);
+
+    let config = Config::load()?;
+    let repository = repository(&config)?;
+    if repository.get_provider_config(&provider_type)?.is_none() {
+        return Err(not_configured(&provider_type));
+    }
+    repository.set_default_provider(&provider_type)?;
+
+    if output::is_json() {
+        return output::print_json(&json!({ "provider": provider_type, "default": true }));
+    }
+    output::message(format!("✓ {} is the default provider", provider_type));
+    Ok(())
+}
+
+/// Forget the saved `provider_type` provider
+pub async fn remove(provider_type: String) -> Result<()> {
+    tracing::info!("Removing provider {}", provider_type);
+
+    let config = Config::load()?;
+    if !repository(&config)?.delete_provider_config(&provider_type)? {
+        return Err(not_configured(&provider_type));
+    }
+
+    if output::is_json() {
+        return output::print_json(&json!({ "provider": provider_type, "removed": true }));
+    }
+    output::message(format!("✓ Removed {} provider", provider_type));
+    Ok(())
+}
+
+fn not_configured(provider_type: &str) -> KtmeError {
+    KtmeError::NotFound(format!(
+        "No {} provider configured; add one with 'ktme provider add {}'",
+        provider_type, provider_type
+    ))
+}
+
+/// `config` with the values of secret keys replaced
+fn redacted(config: &serde_json::Value) -> serde_json::Value {
+    match config {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                let value = if SECRET_KEYS.contains(&key.as_str()) {
+                    json!("***")
+                } else {
+                    redacted(value)
+                };
+                (key.clone(), value)
+            })
+            .collect(),
+        other => other.clone(),
+    }
+}
+
+/// Run the health check of every saved provider concurrently, each limited
+/// to `timeout` (default: the provider's `[timeouts]` request timeout), and
+/// fail when the default provider is not healthy
+pub async fn status(timeout: Option<String>) -> Result<()> {
+    tracing::info!("Checking document providers");
+
+    let config = Config::load()?;
+    let timeout = timeout.as_deref().map(http::parse_timeout).transpose()?;
+    http::configure(&config.timeouts);
+    let providers = repository(&config)?.list_provider_configs()?;
+
+    let checks = run_limited(providers, config.general.parallelism, |provider| {
+        let timeout =
+            timeout.unwrap_or_else(|| config.timeouts.request_timeout(&provider.provider_type));
+        async move {
+            let started = Instant::now();
+            let result = check(&provider, timeout).await;
+            Health {
+                provider,
+                latency: started.elapsed(),
+                result,
+            }
+        }
+    })
+    .await;
+
+    if output::is_json() {
+        let providers: Vec<_> = checks
+            .iter()
+            .map(|health| {
+                json!({
+                    "provider": health.provider.provider_type,
+                    "default": health.provider.is_default,
+                    "status": health.status(),
+                    "auth": health.auth(),
+                    "latency_ms": health.latency.as_millis() as u64,
+                    "error": health.result.as_ref().err().map(|e| e.to_string()),
+                })
+            })
+            .collect();
+        output::print_json(&json!({ "providers": providers }))?;
+    } else if checks.is_empty() {
+        println!("No document providers configured.");
+    } else {
+        println!(
+            "{:<16} {:<12} {:<8} {:>10}",
+            "PROVIDER", "STATUS", "AUTH", "LATENCY"
+        );
+        for health in &checks {
+            let name = if health.provider.is_default {
+                format!("{} (default)", health.provider.provider_type)
+            } else {
+                health.provider.provider_type.clone()
+            };
+            let auth = match health.auth() {
+                Some(true) => "valid",
+                Some(false) => "rejected",
+                None => "unknown",
+            };
+            println!(
+                "{:<16} {:<12} {:<8} {:>7} ms",
+                name,
+                health.status(),
+                auth,
+                health.latency.as_millis()
+            );
+            if let Err(e) = &health.result {
+                println!("  {}", e);
+            }
+        }
+    }
+
+    match checks.into_iter().find(|health| health.provider.is_default) {
+        Some(Health {
+            result: Err(e),
+            provider,
+            ..
+        }) => {
+            tracing::error!("Default provider {} is down", provider.provider_type);
+            Err(e)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Health check of `provider`, turning an unhealthy answer and running out
+/// of time into errors
+async fn check(provider: &ProviderConfig, timeout: Duration) -> Result<()> {
+    let instance = ProviderFactory::create(&provider.provider_type, provider.clone().into())?;
+    match tokio::time::timeout(timeout, instance.health_check()).await {
+        Ok(Ok(true)) => Ok(()),
+        Ok(Ok(false)) => Err(KtmeError::NetworkError(format!(
+            "{} failed its health check",
+            provider.provider_type
+        ))),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(KtmeError::Timeout(format!(
+            "{} did not answer its health check within {}s",
+            provider.provider_type,
+            timeout.as_secs()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(provider_type: &str, config: serde_json::Value) -> ProviderConfig {
+        ProviderConfig {
+            id: 1,
+            provider_type: provider_type.to_string(),
+            config,
+            is_default: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_reports_health() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let markdown = provider(
+            "markdown",
+            json!({ "base_path": dir.path().join("docs").to_str().unwrap() }),
+        );
+        assert!(check(&markdown, Duration::from_secs(5)).await.is_ok());
+
+        let unsupported = provider("ktme-test-missing", json!({}));
+        let health = Health {
+            result: check(&unsupported, Duration::from_secs(5)).await,
+            provider: unsupported,
+            latency: Duration::ZERO,
+        };
+        assert_eq!(health.status(), "down");
+        assert_eq!(health.auth(), None);
+    }
+
+    #[test]
+    fn test_redacted_hides_secrets() {
+        let config = json!({
+            "base_url": "https://example.atlassian.net",
+            "api_token": "secret",
+            "oauth": { "client_id": "id", "client_secret": "secret" },
+        });
+        let shown = redacted(&config);
+        assert_eq!(shown["base_url"], "https://example.atlassian.net");
+        assert_eq!(shown["api_token"], "***");
+        assert_eq!(shown["oauth"]["client_id"], "id");
+        assert_eq!(shown["oauth"]["client_secret"], "***");
+    }
+
+    #[test]
+    fn test_health_status() {
+        let health = |result| Health {
+            provider: provider("confluence", json!({})),
+            latency: Duration::ZERO,
+            result,
+        };
+        let rejected = health(Err(KtmeError::Unauthorized {
+            service: "Confluence",
+            status: 401,
+        }));
+        assert_eq!(rejected.status(), "auth failed");
+        assert_eq!(rejected.auth(), Some(false));
+        assert_eq!(
+            health(Err(KtmeError::Timeout("slow".to_string()))).status(),
+            "timeout"
+        );
+        assert_eq!(health(Ok(())).auth(), Some(true));
+    }
+}
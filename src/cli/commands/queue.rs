@@ -0,0 +1,282 @@
+use super::generate::{with_notices, write_output};
+use super::update::publish_to_location;
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::notices::NoticeContext;
+use crate::doc::sections::{DocKind, SectionUpdate};
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::{PendingDoc, ServiceSettings};
+use crate::translation;
+use serde_json::json;
+
+/// List queued documentation with `status` ("all" for every status)
+pub async fn list(status: String) -> Result<()> {
+    tracing::info!("Listing review queue ({})", status);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let filter = (status != "all").then_some(status.as_str());
+    let docs = storage.list_pending_docs(filter)?;
+
+    if output::is_json() {
+        let docs: Vec<_> = docs
+            .iter()
+            .map(|doc| {
+                json!({
+                    "id": doc.id,
+                    "service": doc.service,
+                    "doc_type": doc.doc_type,
+                    "source": doc.source_identifier,
+                    "target": target_of(doc),
+                    "status": doc.status,
+                    "reviewer": doc.reviewer,
+                    "created_at": doc.created_at,
+                })
+            })
+            .collect();
+        return output::print_json(&json!({ "documents": docs }));
+    }
+
+    if docs.is_empty() {
+        match filter {
+            Some(status) => println!("No {} documentation in the review queue.", status),
+            None => println!("The review queue is empty."),
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{:<6} {:<20} {:<15} {:<10} {:<17} TARGET",
+        "ID", "SERVICE", "TYPE", "STATUS", "QUEUED"
+    );
+    for doc in &docs {
+        println!(
+            "{:<6} {:<20} {:<15} {:<10} {:<17} {}",
+            doc.id,
+            doc.service,
+            doc.doc_type,
+            doc.status,
+            doc.created_at.format("%Y-%m-%d %H:%M"),
+            target_of(doc)
+        );
+    }
+
+    Ok(())
+}
+
+/// Print a queued document and its review state
+pub async fn show(id: i64) -> Result<()> {
+    tracing::info!("Showing queued documentation {}", id);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let doc = pending_doc(storage.as_ref(), id)?;
+
+    if output::is_json() {
+        return output::print_json(&doc);
+    }
+
+    println!("Queued documentation {}", doc.id);
+    println!("  Service: {}", doc.service);
+    println!("  Type: {}", doc.doc_type);
+    if let Some(source) = &doc.source_identifier {
+        println!("  Source: {}", source);
+    }
+    println!("  Target: {}", target_of(&doc));
+    println!("  Status: {}", doc.status);
+    println!("  Queued: {}", doc.created_at.to_rfc3339());
+    if let (Some(reviewer), Some(reviewed_at)) = (&doc.reviewer, doc.reviewed_at) {
+        println!("  Reviewed by {} at {}", reviewer, reviewed_at.to_rfc3339());
+    }
+    if let Some(note) = &doc.note {
+        println!("  Note: {}", note);
+    }
+    println!("\n{}", doc.content);
+
+    Ok(())
+}
+
+/// Publish a pending document and mark it approved
+pub async fn approve(id: i64) -> Result<()> {
+    tracing::info!("Approving queued documentation {}", id);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let doc = pending_doc(storage.as_ref(), id)?;
+    ensure_pending(&doc)?;
+
+    let locations = match &doc.target {
+        Some(path) => {
+            let notice = NoticeContext {
+                service: Some(&doc.service),
+                doc_type: Some(&doc.doc_type),
+                source: doc.source_identifier.as_deref(),
+            };
+            write_output(&with_notices(&config, path, &doc.content, &notice), path)?;
+            vec![DocumentLocation {
+                r#type: "markdown".to_string(),
+                location: path.clone(),
+                section: None,
+            }]
+        }
+        None => publish_to_mapping(&config, &doc).await?,
+    };
+
+    let service_id = storage.get_service_by_name(&doc.service)?.map(|s| s.id);
+    for location in &locations {
+        if let Err(e) = storage.record_generation(
+            service_id,
+            &location.r#type,
+            None,
+            Some(&location.location),
+            "publish",
+            Some("queue"),
+            Some(&doc.id.to_string()),
+            None,
+            "success",
+            None,
+            None,
+            None,
+        ) {
+            tracing::warn!("Failed to record generation history: {}", e);
+        }
+    }
+
+    if !storage.review_pending_doc(id, "approved", reviewer().as_deref(), None)? {
+        return Err(KtmeError::InvalidInput(format!(
+            "Queued documentation {} was reviewed by someone else while it was being published",
+            id
+        )));
+    }
+
+    // Translated counterparts follow the approved documentation
+    let mut translations = Vec::new();
+    for location in &locations {
+        translations.extend(translation::after_publish(&config, &doc.service, location).await);
+    }
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "id": id,
+            "service": doc.service,
+            "status": "approved",
+            "locations": locations,
+            "translations": translations,
+        }));
+    }
+
+    for location in &locations {
+        println!("✓ Published to {} ({})", location.location, location.r#type);
+    }
+    super::translate::report(&translations);
+    println!("✓ Approved queued documentation {}", id);
+    Ok(())
+}
+
+/// Mark a pending document rejected without publishing it
+pub async fn reject(id: i64, reason: Option<String>) -> Result<()> {
+    tracing::info!("Rejecting queued documentation {}", id);
+
+    let config = Config::load()?;
+    let storage = open_storage(&config.storage)?;
+    let doc = pending_doc(storage.as_ref(), id)?;
+    ensure_pending(&doc)?;
+
+    if !storage.review_pending_doc(id, "rejected", reviewer().as_deref(), reason.as_deref())? {
+        return Err(KtmeError::InvalidInput(format!(
+            "Queued documentation {} was already reviewed",
+            id
+        )));
+    }
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "id": id,
+            "service": doc.service,
+            "status": "rejected",
+            "reason": reason,
+        }));
+    }
+
+    println!("✓ Rejected queued documentation {}", id);
+    Ok(())
+}
+
+fn pending_doc(storage: &dyn Storage, id: i64) -> Result<PendingDoc> {
+    storage.get_pending_doc(id)?.ok_or_else(|| {
+        KtmeError::DocumentNotFound(format!("No queued documentation with id {}", id))
+    })
+}
+
+fn ensure_pending(doc: &PendingDoc) -> Result<()> {
+    if doc.status != "pending" {
+        return Err(KtmeError::InvalidInput(format!(
+            "Queued documentation {} was already {}",
+            doc.id, doc.status
+        )));
+    }
+    Ok(())
+}
+
+/// Publish into the service's mapped locations like `ktme update`; every
+/// location must succeed for the document to be approved
+async fn publish_to_mapping(config: &Config, doc: &PendingDoc) -> Result<Vec<DocumentLocation>> {
+    let mapping = StorageManager::from_config(config)?.get_mapping(&doc.service)?;
+    let settings = ServiceSettings::from_config(config).overlay(&mapping.settings);
+    let locations: Vec<_> = mapping
+        .docs
+        .into_iter()
+        .filter(|doc| settings.publishes_to(&doc.r#type, &doc.location))
+        .collect();
+    if locations.is_empty() {
+        return Err(KtmeError::DocumentNotFound(format!(
+            "No documentation locations mapped for service: {}",
+            doc.service
+        )));
+    }
+
+    let mut failures = Vec::new();
+    for location in &locations {
+        let update = SectionUpdate {
+            kind: DocKind::infer(&location.location),
+            section: None,
+            mode: None,
+        };
+        let notice = NoticeContext {
+            service: Some(&doc.service),
+            doc_type: Some(&doc.doc_type),
+            source: doc.source_identifier.as_deref(),
+        };
+        match publish_to_location(config, location, &doc.content, &update, &notice).await {
+            Some(Ok(())) => {}
+            Some(Err(e)) => failures.push(format!("{} ({})", location.location, e)),
+            None => failures.push(format!(
+                "{} (unknown documentation type: {})",
+                location.location, location.r#type
+            )),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(KtmeError::Documentation(format!(
+            "Queued documentation {} stays pending; failed to publish to {}",
+            doc.id,
+            failures.join(", ")
+        )));
+    }
+
+    Ok(locations)
+}
+
+/// Where a queued document is published
+fn target_of(doc: &PendingDoc) -> &str {
+    doc.target.as_deref().unwrap_or("mapped locations")
+}
+
+/// Name recorded as the reviewer
+fn reviewer() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
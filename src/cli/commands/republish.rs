@@ -0,0 +1,270 @@
+use super::mapping::read_location;
+use super::update::publish_to_location;
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::notices::NoticeContext;
+use crate::doc::sections::{DocKind, SectionUpdate, UpdateMode};
+use crate::error::{KtmeError, Result};
+use crate::http;
+use crate::shutdown;
+use crate::storage::backend::open_storage;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::GenerationRecord;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Generation history searched for the source of each document
+const HISTORY_LIMIT: usize = 1000;
+
+/// Largest number of documents written per period, e.g. `10/min`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    count: u32,
+    period: Duration,
+}
+
+impl Rate {
+    /// Parse `count/period`, the period being `s`, `min` or `h` (or a
+    /// duration such as `5m`)
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = || {
+            KtmeError::InvalidInput(format!(
+                "Invalid rate '{}'; expected e.g. 10/min, 1/s or 100/h",
+                value
+            ))
+        };
+        let (count, period) = value.split_once('/').ok_or_else(invalid)?;
+        let count: u32 = count.trim().parse().map_err(|_| invalid())?;
+        let period = match period.trim() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(3600),
+            other => http::parse_timeout(other).map_err(|_| invalid())?,
+        };
+        if count == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { count, period })
+    }
+
+    /// Time between two writes
+    fn interval(&self) -> Duration {
+        self.period / self.count
+    }
+}
+
+/// Outcome of republishing one document
+#[derive(Debug, Serialize)]
+struct Republished {
+    service: String,
+    r#type: String,
+    location: String,
+    /// "republished", "missing" or "failed"
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Write the documents mapped to `service`, or to every service with `all`,
+/// back through their providers so they pick up changed notices, templates
+/// or the Markdown to Confluence conversion. Only `provider` documents when
+/// given; writes to remote providers are spaced out to `rate`.
+pub async fn execute(
+    all: bool,
+    service: Option<String>,
+    provider: Option<String>,
+    rate: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let rate = Rate::parse(rate)?;
+    let config = Config::load()?;
+    http::configure(&config.timeouts);
+    let manager = StorageManager::from_config(&config)?;
+    let services = match service {
+        Some(service) => vec![service],
+        None if all => {
+            let mut services = manager.list_services()?;
+            services.sort();
+            services.dedup();
+            services
+        }
+        None => {
+            return Err(KtmeError::InvalidInput(
+                "Pass --service or --all".to_string(),
+            ))
+        }
+    };
+
+    // A page mapped to several services is written once
+    let mut seen = HashSet::new();
+    let mut documents = Vec::new();
+    for service in services {
+        for doc in manager.get_mapping(&service)?.docs {
+            let wanted = provider.as_deref().is_none_or(|p| p == doc.r#type);
+            if wanted && seen.insert((doc.r#type.clone(), doc.location.clone())) {
+                documents.push((service.clone(), doc));
+            }
+        }
+    }
+    if documents.is_empty() {
+        return Err(KtmeError::NotFound(
+            "No mapped documents to republish".to_string(),
+        ));
+    }
+
+    if dry_run {
+        if output::is_json() {
+            return output::print_json(&serde_json::json!({
+                "dry_run": true,
+                "documents": documents.iter().map(|(service, doc)| serde_json::json!({
+                    "service": service,
+                    "type": doc.r#type,
+                    "location": doc.location,
+                })).collect::<Vec<_>>(),
+            }));
+        }
+        println!(
+            "Dry run mode - would republish {} document(s):",
+            documents.len()
+        );
+        for (service, doc) in &documents {
+            println!("  - {} ({}) of {}", doc.location, doc.r#type, service);
+        }
+        return Ok(());
+    }
+
+    let history = match open_storage(&config.storage)
+        .and_then(|storage| storage.recent_generations(HISTORY_LIMIT))
+    {
+        Ok(history) => history,
+        Err(e) => {
+            tracing::debug!("Generation history unavailable: {}", e);
+            Vec::new()
+        }
+    };
+    let mut next_write: Option<Instant> = None;
+    let mut results = Vec::new();
+    for (service, doc) in &documents {
+        shutdown::check("Republishing")?;
+        let remote = doc.r#type != "markdown";
+        if remote {
+            if let Some(at) = next_write {
+                shutdown::or_cancelled("Republishing", async {
+                    tokio::time::sleep_until(at).await;
+                    Ok(())
+                })
+                .await?;
+            }
+        }
+        let result = republish(&config, service, doc, &history).await;
+        if remote {
+            next_write = Some(Instant::now() + rate.interval());
+        }
+
+        let (status, error) = match result {
+            Ok(status) => (status, None),
+            Err(e) => ("failed", Some(e.to_string())),
+        };
+        if !output::is_json() {
+            let line = match (status, &error) {
+                ("republished", _) => format!("✓ Republished {}", doc.location),
+                ("missing", _) => format!("! Skipping {}: document not found", doc.location),
+                (_, error) => format!(
+                    "✗ Failed to republish {}: {}",
+                    doc.location,
+                    error.as_deref().unwrap_or_default()
+                ),
+            };
+            output::message(line);
+        }
+        results.push(Republished {
+            service: service.clone(),
+            r#type: doc.r#type.clone(),
+            location: doc.location.clone(),
+            status,
+            error,
+        });
+    }
+
+    let failed = results.iter().filter(|r| r.status == "failed").count();
+    if output::is_json() {
+        output::print_json(&results)?;
+    } else {
+        let republished = results.iter().filter(|r| r.status == "republished").count();
+        println!(
+            "Republished {} of {} document(s)",
+            republished,
+            results.len()
+        );
+    }
+    if failed > 0 {
+        return Err(KtmeError::Documentation(format!(
+            "Failed to republish {} of {} document(s)",
+            failed,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Write the current content of `doc` back as a whole
+async fn republish(
+    config: &Config,
+    service: &str,
+    doc: &DocumentLocation,
+    history: &[GenerationRecord],
+) -> Result<&'static str> {
+    let Some(document) = read_location(config, doc).await? else {
+        return Ok("missing");
+    };
+    let update = SectionUpdate {
+        kind: DocKind::General,
+        section: None,
+        mode: Some(UpdateMode::Replace),
+    };
+    // The notices refer to the commit last published to the document
+    let notice = NoticeContext {
+        service: Some(service),
+        doc_type: None,
+        source: last_source(history, &doc.location),
+    };
+    publish_to_location(config, doc, &document.content, &update, &notice)
+        .await
+        .unwrap_or_else(|| Err(KtmeError::UnsupportedProvider(doc.r#type.clone())))?;
+    Ok("republished")
+}
+
+/// Source of the latest successful write to `location`
+fn last_source<'a>(history: &'a [GenerationRecord], location: &str) -> Option<&'a str> {
+    history
+        .iter()
+        .filter(|record| {
+            record.status == "success" && record.document_url.as_deref() == Some(location)
+        })
+        .max_by_key(|record| record.created_at)
+        .and_then(|record| record.source_identifier.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_parses_count_per_period() {
+        let rate = Rate::parse("10/min").unwrap();
+        assert_eq!(rate.interval(), Duration::from_secs(6));
+        assert_eq!(
+            Rate::parse("2/s").unwrap().interval(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            Rate::parse("4/2m").unwrap().interval(),
+            Duration::from_secs(30)
+        );
+        for invalid in ["10", "0/min", "ten/min", "10/fortnight"] {
+            assert!(Rate::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
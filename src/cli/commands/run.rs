@@ -0,0 +1,100 @@
+use super::generate::load_diff_from_file;
+use crate::cli::detect;
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::lint::Severity;
+use crate::engine::{DiffSource, KtmeEngine};
+use crate::error::{KtmeError, Result};
+use crate::git::ownership::{self, AUTO_SERVICE};
+use crate::http;
+use crate::workflow::{self, RunReport};
+use std::path::Path;
+
+/// Run the policy workflow of `service` on the changes of `source`,
+/// failing when the run takes longer than `timeout` (default:
+/// `[timeouts] operation`). Without `service` the service is detected from
+/// the current directory, confirmed unless `yes`.
+pub async fn execute(
+    service: Option<String>,
+    yes: bool,
+    source: String,
+    timeout: Option<String>,
+    wait: bool,
+) -> Result<()> {
+    let service = match service {
+        Some(service) => service,
+        None => detect::service(yes).await?,
+    };
+    tracing::info!("Running the workflow of {} on {}", service, source);
+
+    let config = Config::load()?;
+    let deadline = config.timeouts.operation_timeout(timeout.as_deref())?;
+    http::with_deadline("Running the workflow", deadline, async {
+        let engine = KtmeEngine::builder().config(config).build()?;
+        let diff = if source == "staged" {
+            engine.extract(DiffSource::Staged)?
+        } else if Path::new(&source).is_file() {
+            load_diff_from_file(&source)?
+        } else {
+            engine.extract(DiffSource::Commit(source))?
+        };
+
+        let scoped = if service == AUTO_SERVICE {
+            let resolution = ownership::resolve_services_for_diff(&diff)?;
+            output::message(format!("Resolved services: {}", resolution.describe()));
+            resolution.scoped_diffs(&diff)
+        } else {
+            vec![(service, diff)]
+        };
+
+        let mut reports = Vec::new();
+        for (service, diff) in scoped {
+            reports.push(workflow::run(&engine, &service, &diff, wait).await?);
+        }
+        report(&reports)
+    })
+    .await
+}
+
+fn report(reports: &[RunReport]) -> Result<()> {
+    if output::is_json() {
+        output::print_json(&reports)?;
+    } else {
+        for report in reports {
+            if report.documents.is_empty() {
+                println!("- {}: no changes to document", report.service);
+            }
+            for (document, line) in report.documents.iter().zip(report.describe()) {
+                let marker = if document.failed() { "✗" } else { "✓" };
+                println!("{} {} {}", marker, report.service, line);
+                for finding in &document.lint {
+                    let marker = match finding.severity {
+                        Severity::Error => "✗",
+                        Severity::Warning => "!",
+                    };
+                    println!("    {} {}", marker, finding.message);
+                }
+            }
+            super::translate::report(&report.translations);
+            for notification in &report.notifications {
+                match &notification.error {
+                    Some(error) => println!("✗ Notifying {}: {}", notification.url, error),
+                    None => println!("✓ Notified {}", notification.url),
+                }
+            }
+        }
+    }
+
+    let failed: Vec<_> = reports
+        .iter()
+        .filter(|report| report.failed())
+        .map(|report| report.service.as_str())
+        .collect();
+    if !failed.is_empty() {
+        return Err(KtmeError::Documentation(format!(
+            "The workflow did not publish all documentation of {}",
+            failed.join(", ")
+        )));
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::cli::output;
 use crate::error::Result;
 use crate::storage::mapping::StorageManager;
 
@@ -12,6 +13,21 @@ pub async fn execute(query: String, feature: bool, keyword: bool) -> Result<()>
         storage.search_services(&query)?
     };
 
+    if output::is_json() {
+        let search_type = if feature {
+            "feature"
+        } else if keyword {
+            "keyword"
+        } else {
+            "service"
+        };
+        return output::print_json(&serde_json::json!({
+            "query": query,
+            "type": search_type,
+            "results": results,
+        }));
+    }
+
     if results.is_empty() {
         let search_type = if feature {
             "feature"
@@ -0,0 +1,138 @@
+use crate::cli::output;
+use crate::error::{KtmeError, Result};
+use crate::release::{self, InstallMethod, CHECKSUMS_ASSET};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+/// Replace the running binary with the latest release after checking its
+/// checksum; with `check_only` only report whether there is one. Binaries
+/// installed by Homebrew, npm or cargo are left to them unless `force`.
+pub async fn execute(check_only: bool, force: bool) -> Result<()> {
+    let current = release::current_version();
+    let latest_release = release::latest().await?;
+    let latest = latest_release.version()?;
+    let update_available = latest > current;
+    let exe = std::env::current_exe()?;
+    let exe = exe.canonicalize().unwrap_or(exe);
+    let method = InstallMethod::detect(&exe);
+
+    if check_only || !update_available {
+        if output::is_json() {
+            return output::print_json(&json!({
+                "current": current.to_string(),
+                "latest": latest.to_string(),
+                "update_available": update_available,
+                "installed_by": method.as_str(),
+                "url": latest_release.html_url,
+            }));
+        }
+        if update_available {
+            println!("ktme {} is available (installed: {})", latest, current);
+            println!("   {}", latest_release.html_url);
+            println!("   Update with: {}", method.upgrade_command());
+        } else {
+            println!("ktme {} is up to date", current);
+        }
+        return Ok(());
+    }
+
+    if method != InstallMethod::Standalone && !force {
+        return Err(KtmeError::InvalidInput(format!(
+            "ktme was installed with {}; update it with `{}` (or pass --force to replace {} anyway)",
+            method.as_str(),
+            method.upgrade_command(),
+            exe.display()
+        )));
+    }
+
+    let name = release::platform_asset().ok_or_else(|| {
+        KtmeError::NotFound(format!(
+            "Release binary for {}-{}; build ktme from source instead",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        ))
+    })?;
+    let tag = &latest_release.tag_name;
+    let asset = latest_release
+        .asset(name)
+        .ok_or_else(|| KtmeError::NotFound(format!("Asset {} of release {}", name, tag)))?;
+    // An unverifiable binary is never installed
+    let sums = latest_release.asset(CHECKSUMS_ASSET).ok_or_else(|| {
+        KtmeError::NotFound(format!(
+            "{} of release {}, needed to verify the download",
+            CHECKSUMS_ASSET, tag
+        ))
+    })?;
+
+    output::message(format!("Downloading {} {}...", name, tag));
+    let binary = release::download(asset).await?;
+    let sums = String::from_utf8_lossy(&release::download(sums).await?).into_owned();
+    release::verify_checksum(&binary, name, &sums)?;
+    replace_binary(&exe, &binary)?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "previous": current.to_string(),
+            "installed": latest.to_string(),
+            "path": exe,
+        }));
+    }
+    println!("✅ Updated ktme {} → {}", current, latest);
+    println!("   {}", exe.display());
+    Ok(())
+}
+
+/// Write `binary` next to `exe` and move it over it, so that `exe` is
+/// either the old or the new binary, never a partial one
+fn replace_binary(exe: &Path, binary: &[u8]) -> Result<()> {
+    let dir = exe
+        .parent()
+        .ok_or_else(|| KtmeError::InvalidInput(format!("No directory for {}", exe.display())))?;
+    let staged = dir.join(format!(".ktme-update-{}", std::process::id()));
+    let result = stage(&staged, binary).and_then(|()| swap(exe, &staged));
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result.map_err(|e| match e {
+        KtmeError::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            KtmeError::Io(std::io::Error::new(
+                e.kind(),
+                format!(
+                    "Cannot write {}; run the update with permission to replace it: {}",
+                    exe.display(),
+                    e
+                ),
+            ))
+        }
+        e => e,
+    })
+}
+
+fn stage(path: &Path, binary: &[u8]) -> Result<()> {
+    fs::write(path, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Move `staged` to `exe`. A running binary cannot be overwritten on
+/// Windows, but it can be renamed out of the way first.
+fn swap(exe: &Path, staged: &Path) -> Result<()> {
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("exe.old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+        if let Err(e) = fs::rename(staged, exe) {
+            let _ = fs::rename(&old, exe);
+            return Err(e.into());
+        }
+    }
+    #[cfg(not(windows))]
+    fs::rename(staged, exe)?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::service_detector::{self, ServiceDetector};
+use crate::storage::backend::{open_storage, Storage};
+use serde_json::json;
+
+fn repository(config: &Config) -> Result<Box<dyn Storage>> {
+    open_storage(&config.storage)
+}
+
+/// Use `name` for the repository of the current directory, whatever is
+/// detected
+pub async fn pin(name: String) -> Result<()> {
+    tracing::info!("Pinning service {}", name);
+
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(KtmeError::InvalidInput("Service name is empty".to_string()));
+    }
+    let config = Config::load()?;
+    let directory = ServiceDetector::new()?.pin_directory();
+    repository(&config)?.save_service_detection(&directory, &name, true)?;
+
+    if output::is_json() {
+        return output::print_json(&json!({ "service": name, "directory": directory }));
+    }
+    output::message(format!("✓ Pinned service '{}' to {}", name, directory));
+    Ok(())
+}
+
+/// Remove the service pinned to the repository of the current directory
+pub async fn unpin() -> Result<()> {
+    tracing::info!("Unpinning service");
+
+    let config = Config::load()?;
+    let directory = ServiceDetector::new()?.pin_directory();
+    if !repository(&config)?.remove_service_detection(&directory, true)? {
+        return Err(KtmeError::NotFound(format!(
+            "No service is pinned to {}",
+            directory
+        )));
+    }
+
+    if output::is_json() {
+        return output::print_json(&json!({ "directory": directory, "removed": true }));
+    }
+    output::message(format!("✓ Removed the service pinned to {}", directory));
+    Ok(())
+}
+
+/// Show the service of the current directory and the other candidates;
+/// with `refresh`, detect it again instead of reading the cache
+pub async fn detect(refresh: bool) -> Result<()> {
+    tracing::info!("Detecting service");
+
+    let config = Config::load()?;
+    let cache = service_detector::detection_cache(&config);
+    let detector = ServiceDetector::new()?;
+    if refresh {
+        if let Some(cache) = &cache {
+            cache.clear_service_detections(&detector.directory())?;
+        }
+    }
+    let detection = detector.detect_cached(cache.as_deref()).await?;
+    let candidates = detector.candidates();
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "service": detection.name,
+            "origin": detection.origin,
+            "directory": detector.directory(),
+            "candidates": candidates,
+        }));
+    }
+    println!("Service: {} ({})", detection.name, detection.origin);
+    if !candidates.is_empty() {
+        println!("\nCandidates:");
+        for candidate in &candidates {
+            println!(
+                "  {:<28} {:.2}  {}",
+                candidate.name, candidate.confidence, candidate.source
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use crate::ai::prompt_pack::PromptPack;
+use crate::ai::template;
+use crate::cli::commands::generate::{build_prompt, load_diff_from_file};
+use crate::cli::output;
+use crate::config::Config;
+use crate::doc::personas;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::DiffExtractor;
+use crate::git::ownership::{self, Ownership};
+use crate::git::reader::DiffLimits;
+use crate::plugins::extensions::Extensions;
+use crate::storage::backend::open_storage;
+use crate::storage::mapping;
+use crate::storage::models::ServiceSettings;
+use serde_json::json;
+use std::path::Path;
+
+/// Document types with a built-in prompt
+const DOC_TYPES: &[&str] = &[
+    "changelog",
+    "api-doc",
+    "readme",
+    "commit-message",
+    "general",
+    "onboarding",
+    "postmortem",
+    "release-notes",
+    "security-review",
+    "test-plan",
+];
+
+/// Print the prompt `generate` would send for template `name` without
+/// calling the AI provider. `name` is a template file or a document type
+/// with a built-in prompt or one in the prompt pack.
+pub async fn preview(
+    name: String,
+    commit: Option<String>,
+    staged: bool,
+    input: Option<String>,
+    service: Option<String>,
+    variables: Vec<String>,
+) -> Result<()> {
+    let cli = if Path::new(&name).is_file() {
+        ServiceSettings {
+            template: Some(name.clone()),
+            ..Default::default()
+        }
+    } else {
+        ServiceSettings {
+            doc_type: Some(name.clone()),
+            ..Default::default()
+        }
+    };
+
+    let variables = template::parse_variables(&variables)?;
+    let config = Config::load()?;
+
+    // Same precedence as generate: global config, then the service's
+    // settings, then the template named here
+    let mut settings = ServiceSettings::from_config(&config);
+    if let Some(service) = &service {
+        let storage = open_storage(&config.storage)?;
+        let stored = mapping::service_settings(&config, storage.as_ref(), service)?;
+        settings = settings.overlay(&stored);
+    }
+    let settings = settings.overlay(&cli);
+    if settings.template.is_none()
+        && !DOC_TYPES.contains(&name.as_str())
+        && PromptPack::load(settings.prompt_pack.as_deref())?
+            .file(&name)
+            .is_none()
+    {
+        return Err(KtmeError::InvalidInput(format!(
+            "Unknown template '{}': pass a template file, one of {} or a document type of the prompt pack",
+            name,
+            DOC_TYPES.join(", ")
+        )));
+    }
+
+    let limits = DiffLimits::from_config(&config.git);
+    let diff = if let Some(input_file) = input {
+        load_diff_from_file(&input_file)?
+    } else if staged {
+        DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?
+            .with_limits(limits)
+            .extract()?
+    } else {
+        let commit = commit.unwrap_or_else(|| "HEAD".to_string());
+        DiffExtractor::new("commit".to_string(), commit, None)?
+            .with_limits(limits)
+            .extract()?
+    };
+
+    let doc_type = settings.doc_type.as_deref().unwrap_or("general");
+
+    let ownership = if config.documentation.include_contacts {
+        ownership::analyze(None, &diff).unwrap_or_else(|e| {
+            tracing::debug!("Skipping primary contacts: {}", e);
+            Ownership::default()
+        })
+    } else {
+        Ownership::default()
+    };
+
+    let extensions = Extensions::load(&config)?;
+    let prompt = build_prompt(
+        &settings,
+        doc_type,
+        &diff,
+        &ownership,
+        &variables,
+        &extensions,
+        personas::context(
+            &config,
+            doc_type,
+            service.as_deref(),
+            &diff,
+            &Default::default(),
+        )
+        .as_deref(),
+    )?;
+
+    if output::is_json() {
+        return output::print_json(&json!({
+            "template": name,
+            "prompt": prompt,
+        }));
+    }
+
+    println!("{}", prompt);
+    Ok(())
+}
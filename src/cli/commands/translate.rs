@@ -0,0 +1,87 @@
+use crate::ai::AIClient;
+use crate::cli::detect;
+use crate::cli::output;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::http;
+use crate::storage::mapping::StorageManager;
+use crate::translation::{self, Synced};
+
+/// Translate the documents of `service` (only the one at `source` when
+/// given) into their translations, every section again when `force`.
+/// Without `service` the service is detected from the current directory,
+/// confirmed unless `yes`.
+pub async fn execute(
+    service: Option<String>,
+    yes: bool,
+    source: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let service = match service {
+        Some(service) => service,
+        None => detect::service(yes).await?,
+    };
+    tracing::info!("Translating the documentation of {}", service);
+
+    let config = Config::load()?;
+    http::configure(&config.timeouts);
+    let pairs = translation::translations(&config, &service, source.as_deref())?;
+    if pairs.is_empty() {
+        return Err(KtmeError::NotFound(format!(
+            "No translations of the documentation of {}; add one with \
+             `ktme mapping add --translation-of`",
+            service
+        )));
+    }
+
+    let docs = StorageManager::from_config(&config)?
+        .get_mapping(&service)?
+        .docs;
+    for pair in &pairs {
+        if !docs.iter().any(|doc| doc.location == pair.source) {
+            output::message(format!(
+                "! Skipping {}: {} is no longer mapped to {}",
+                pair.target, pair.source, service
+            ));
+        }
+    }
+
+    let ai = AIClient::from_config(&config.ai)?;
+    let mut results = Vec::new();
+    for doc in &docs {
+        let of_doc: Vec<_> = pairs
+            .iter()
+            .filter(|pair| pair.source == doc.location)
+            .cloned()
+            .collect();
+        if !of_doc.is_empty() {
+            results.extend(translation::sync(&config, &ai, doc, &of_doc, force).await);
+        }
+    }
+
+    if output::is_json() {
+        output::print_json(&results)?;
+    } else {
+        report(&results);
+    }
+    let failed = results
+        .iter()
+        .filter(|synced| synced.error.is_some())
+        .count();
+    if failed > 0 {
+        return Err(KtmeError::Documentation(format!(
+            "Failed to translate {} of {} document(s)",
+            failed,
+            results.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Print the outcome of syncing translations, one line each
+pub(crate) fn report(results: &[Synced]) {
+    for synced in results {
+        let marker = if synced.error.is_some() { "✗" } else { "✓" };
+        output::message(format!("{} Translation {}", marker, synced.describe()));
+    }
+}
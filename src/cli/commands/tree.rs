@@ -1,6 +1,8 @@
+use crate::cli::output;
+use crate::config::Config;
 use crate::error::Result;
 use crate::knowledge::engine::KnowledgeGraphEngine;
-use crate::storage::database::Database;
+use crate::storage::backend::open_sqlite;
 
 /// Pretty-print the knowledge tree to stdout.
 ///
@@ -8,11 +10,15 @@ use crate::storage::database::Database;
 /// - `depth`: traversal depth (default 2)
 /// - `mermaid`: also print a Mermaid flowchart block
 pub async fn execute(service: Option<String>, depth: Option<u32>, mermaid: bool) -> Result<()> {
-    let db = Database::new(None)?;
+    let db = open_sqlite(&Config::load()?.storage)?;
     let engine = KnowledgeGraphEngine::new(db);
     let effective_depth = depth.unwrap_or(2);
     let graph = engine.get_tree(service.as_deref(), effective_depth)?;
 
+    if output::is_json() {
+        return output::print_json(&graph);
+    }
+
     if graph.nodes.is_empty() {
         println!("Knowledge graph is empty. Run `ktme init` to populate it.");
         return Ok(());
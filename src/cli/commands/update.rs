@@ -1,36 +1,124 @@
-use crate::ai::{prompts::PromptTemplates, AIClient};
-use crate::config::Config;
+use crate::ai::routing::routed_client;
+use crate::ai::{prompt_pack::PromptPack, prompts::PromptTemplates, AIClient};
+use crate::cli::detect;
+use crate::cli::output;
+use crate::cli::parallel::run_limited;
+use crate::cli::progress::Progress;
+use crate::config::{Config, ConfluenceConfig};
+use crate::doc::idempotency::GenerationKey;
+use crate::doc::notices::{NoticeContext, Notices};
+use crate::doc::providers::config::ProviderConfig;
+use crate::doc::providers::confluence_oauth::ConfluenceOAuth;
+use crate::doc::providers::markdown::MarkdownProvider;
+use crate::doc::providers::{external, DocumentProvider, ProviderFactory, PublishStatus};
+use crate::doc::sections::{DocKind, SectionFormat, SectionUpdate, UpdateMode};
 use crate::doc::writers::confluence::ConfluenceWriter;
-use crate::error::Result;
-use crate::git::diff::DiffExtractor;
-use crate::storage::mapping::StorageManager;
-use std::fs;
+use crate::error::{KtmeError, Result};
+use crate::git::cache::CommitDiffCache;
+use crate::git::commit::CommitDocs;
+use crate::git::diff::{DiffExtractor, ExtractedDiff};
+use crate::git::ownership::{self, AUTO_SERVICE};
+use crate::git::reader::DiffLimits;
+use crate::http;
+use crate::shutdown;
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::lock::ServiceLock;
+use crate::storage::mapping::{DocumentLocation, ServiceMapping, StorageManager};
+use crate::storage::models::{GenerationRecord, ServiceSettings};
+use crate::translation;
+use clap::ValueEnum;
+use std::path::PathBuf;
 
+/// Update documentation, failing when the run takes longer than `timeout`
+/// (default: `[timeouts] operation`). Without `service` the service is
+/// detected from the current directory, confirmed unless `yes`.
+#[allow(clippy::too_many_arguments)]
 pub async fn execute(
+    commit: Option<String>,
+    pr: Option<u32>,
+    staged: bool,
+    service: Option<String>,
+    yes: bool,
+    doc: Option<String>,
+    provider: Option<String>,
+    section: Option<String>,
+    doc_type: Option<DocKind>,
+    mode: Option<UpdateMode>,
+    dry_run: bool,
+    force: bool,
+    jobs: Option<usize>,
+    timeout: Option<String>,
+    wait: bool,
+    commit_docs: Option<CommitDocs>,
+) -> Result<()> {
+    let service = match service {
+        Some(service) => service,
+        None => detect::service(yes).await?,
+    };
+    let timeouts = Config::load()?.timeouts;
+    let deadline = timeouts.operation_timeout(timeout.as_deref())?;
+    http::configure(&timeouts);
+    http::with_deadline(
+        "Updating documentation",
+        deadline,
+        run(
+            commit,
+            pr,
+            staged,
+            service,
+            Target {
+                doc: doc.as_deref(),
+                provider: provider.as_deref(),
+            },
+            section,
+            doc_type,
+            mode,
+            dry_run,
+            force,
+            jobs,
+            wait,
+            commit_docs.as_ref(),
+        ),
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run(
     commit: Option<String>,
     pr: Option<u32>,
     staged: bool,
     service: String,
+    target: Target<'_>,
     section: Option<String>,
+    doc_type: Option<DocKind>,
+    mode: Option<UpdateMode>,
     dry_run: bool,
+    force: bool,
+    jobs: Option<usize>,
+    wait: bool,
+    commit_docs: Option<&CommitDocs>,
 ) -> Result<()> {
     tracing::info!("Updating documentation for service: {}", service);
-
-    // Get service mapping
-    let storage = StorageManager::new()?;
-    let mapping = storage.get_mapping(&service)?;
-
-    if mapping.docs.is_empty() {
-        return Err(crate::error::KtmeError::DocumentNotFound(format!(
-            "No documentation locations mapped for service: {}",
-            service
-        )));
+    if service == AUTO_SERVICE && target.doc.is_some() {
+        return Err(KtmeError::InvalidInput(
+            "--doc updates a single service; name it with --service".to_string(),
+        ));
     }
+    let routing = Routing {
+        section: section.as_deref(),
+        doc_type,
+        mode,
+    };
 
     // Extract changes
+    let config = Config::load()?;
+    let limits = DiffLimits::from_config(&config.git);
     let diff = if let Some(commit_ref) = commit {
         tracing::info!("Using commit: {}", commit_ref);
-        let extractor = DiffExtractor::new("commit".to_string(), commit_ref, None)?;
+        let extractor = DiffExtractor::new("commit".to_string(), commit_ref, None)?
+            .with_limits(limits)
+            .with_cache(CommitDiffCache::from_config(&config));
         extractor.extract()?
     } else if let Some(pr_number) = pr {
         tracing::info!("Using PR: #{}", pr_number);
@@ -39,7 +127,8 @@ pub async fn execute(
         ));
     } else if staged {
         tracing::info!("Using staged changes");
-        let extractor = DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?;
+        let extractor = DiffExtractor::new("staged".to_string(), "staged".to_string(), None)?
+            .with_limits(limits);
         extractor.extract()?
     } else {
         return Err(crate::error::KtmeError::InvalidInput(
@@ -47,10 +136,292 @@ pub async fn execute(
         ));
     };
 
+    if service != AUTO_SERVICE {
+        return update_service(
+            &config,
+            &service,
+            target,
+            &diff,
+            routing,
+            dry_run,
+            force,
+            jobs,
+            wait,
+            commit_docs,
+        )
+        .await;
+    }
+
+    // Update every service owning some of the changed files
+    let resolution = ownership::resolve_services_for_diff(&diff)?;
+    output::message(format!("Resolved services: {}", resolution.describe()));
+    let scoped_diffs = resolution.scoped_diffs(&diff);
+    let total = scoped_diffs.len();
+    let mut failures = Vec::new();
+    for (service, scoped) in scoped_diffs {
+        if let Err(e) = update_service(
+            &config,
+            &service,
+            target,
+            &scoped,
+            routing,
+            dry_run,
+            force,
+            jobs,
+            wait,
+            commit_docs,
+        )
+        .await
+        {
+            output::message(format!("✗ {}: {}", service, e));
+            failures.push((service, e));
+        }
+    }
+
+    if total == 1 {
+        if let Some((_, e)) = failures.pop() {
+            return Err(e);
+        }
+    }
+    if !failures.is_empty() {
+        return Err(KtmeError::Documentation(format!(
+            "Documentation update failed for {} of {} services: {}",
+            failures.len(),
+            total,
+            failures
+                .iter()
+                .map(|(service, _)| service.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Documentation named on the command line with `--doc`
+#[derive(Clone, Copy, Default)]
+struct Target<'a> {
+    doc: Option<&'a str>,
+    /// Provider of `doc`, inferred from it when not given
+    provider: Option<&'a str>,
+}
+
+impl Target<'_> {
+    /// The location of `--doc`: the mapped one with its provider and section
+    /// when `mapped` has it
+    fn location(&self, mapped: &[DocumentLocation]) -> Option<DocumentLocation> {
+        let doc = self.doc?;
+        let found = mapped.iter().find(|mapped| {
+            mapped.location == doc && self.provider.is_none_or(|p| p == mapped.r#type)
+        });
+        Some(found.cloned().unwrap_or_else(|| {
+            DocumentLocation {
+                r#type: self
+                    .provider
+                    .map_or_else(|| infer_provider(doc), str::to_string),
+                location: doc.to_string(),
+                section: None,
+            }
+        }))
+    }
+}
+
+/// Provider of a `--doc` location: Confluence for page URLs and IDs,
+/// Markdown for anything else
+fn infer_provider(location: &str) -> String {
+    let confluence = location.contains("pageId=")
+        || (!location.is_empty() && location.chars().all(|c| c.is_ascii_digit()));
+    match confluence {
+        true => "confluence".to_string(),
+        false => "markdown".to_string(),
+    }
+}
+
+/// The documentation `ktme update` writes for `service`: the `--doc` of
+/// `target` alone, or every mapped location, primary first. `--doc` is only
+/// required when the service has no mapping.
+fn resolve_targets(
+    storage: &StorageManager,
+    service: &str,
+    target: Target<'_>,
+) -> Result<ServiceMapping> {
+    let mapping = match storage.get_mapping(service) {
+        Ok(mapping) => Some(mapping),
+        Err(KtmeError::MappingNotFound(_)) => None,
+        Err(e) => return Err(e),
+    };
+    let mapped = mapping.as_ref().map_or(&[][..], |m| m.docs.as_slice());
+    match (target.location(mapped), mapping) {
+        (Some(doc), Some(mapping)) => Ok(ServiceMapping {
+            docs: vec![doc],
+            ..mapping
+        }),
+        (Some(doc), None) => Ok(ServiceMapping {
+            name: service.to_string(),
+            path: None,
+            docs: vec![doc],
+            settings: ServiceSettings::default(),
+        }),
+        (None, Some(mapping)) if !mapping.docs.is_empty() => Ok(mapping),
+        (None, _) => Err(KtmeError::DocumentNotFound(format!(
+            "No documentation locations mapped for service: {}. Pass the document with --doc or map one with 'ktme mapping add {}'",
+            service, service
+        ))),
+    }
+}
+
+/// Section routing options shared by every documentation location
+#[derive(Clone, Copy)]
+struct Routing<'a> {
+    section: Option<&'a str>,
+    /// Overrides the type inferred from each location
+    doc_type: Option<DocKind>,
+    mode: Option<UpdateMode>,
+}
+
+impl Routing<'_> {
+    /// `--section` takes precedence over the section mapped for the location
+    fn for_location<'b>(&'b self, doc: &'b DocumentLocation) -> SectionUpdate<'b> {
+        SectionUpdate {
+            kind: self
+                .doc_type
+                .unwrap_or_else(|| DocKind::infer(&doc.location)),
+            section: self.section.or(doc.section.as_deref()),
+            mode: self.mode,
+        }
+    }
+
+    /// Type the update content is written for: the explicit one, or the one
+    /// all locations agree on
+    fn prompt_kind(&self, locations: &[DocumentLocation]) -> DocKind {
+        if let Some(kind) = self.doc_type {
+            return kind;
+        }
+        let mut kinds = locations.iter().map(|doc| DocKind::infer(&doc.location));
+        match kinds.next() {
+            Some(first) if kinds.all(|kind| kind == first) => first,
+            _ => DocKind::General,
+        }
+    }
+}
+
+/// Update the mapped documentation of one service with `diff`, committing
+/// the updated Markdown files when `commit_docs` is given. Changelogs that
+/// already document the source or content are skipped unless `force`.
+#[allow(clippy::too_many_arguments)]
+async fn update_service(
+    config: &Config,
+    service: &str,
+    target: Target<'_>,
+    diff: &ExtractedDiff,
+    routing: Routing<'_>,
+    dry_run: bool,
+    force: bool,
+    jobs: Option<usize>,
+    wait: bool,
+    commit_docs: Option<&CommitDocs>,
+) -> Result<()> {
+    // Get service mapping
+    let storage = StorageManager::from_config(config)?;
+    let mut mapping = resolve_targets(&storage, service, target)?;
+
+    // Command-line options override the service settings, which override
+    // the global config
+    let settings = ServiceSettings::from_config(config).overlay(&mapping.settings);
+    let routing = Routing {
+        doc_type: routing.doc_type.or_else(|| {
+            settings
+                .doc_type
+                .as_deref()
+                .and_then(|kind| DocKind::from_str(kind, true).ok())
+        }),
+        ..routing
+    };
+    mapping
+        .docs
+        .retain(|doc| settings.publishes_to(&doc.r#type, &doc.location));
+    if mapping.docs.is_empty() {
+        return Err(crate::error::KtmeError::DocumentNotFound(format!(
+            "No documentation locations of service {} match its publish setting: {}",
+            service,
+            settings.publish.join(", ")
+        )));
+    }
+    if crate::http::offline() {
+        let (local, remote): (Vec<_>, Vec<_>) = mapping
+            .docs
+            .into_iter()
+            .partition(|doc| doc.r#type == "markdown");
+        for doc in &remote {
+            output::message(format!(
+                "- Offline: skipping {} ({})",
+                doc.location, doc.r#type
+            ));
+        }
+        if local.is_empty() {
+            return Err(KtmeError::Offline(format!(
+                "Updating the {} documentation of {}",
+                remote
+                    .iter()
+                    .map(|doc| doc.r#type.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                service
+            )));
+        }
+        mapping.docs = local;
+    }
+    let filtered = diff.filtered(|f| settings.covers(&f.path));
+    if filtered.files.is_empty() && !diff.files.is_empty() {
+        output::message(format!(
+            "No changes match the include/exclude settings of {}; nothing to update",
+            service
+        ));
+        return Ok(());
+    }
+    let diff = &filtered;
+
+    // Changelog entries are only added once per source
+    let history = open_storage(&config.storage)?;
+    let source = (diff.source != "staged").then_some(diff.identifier.as_str());
+    let mut skipped = Vec::new();
+    if !force {
+        skip_documented(
+            history.as_ref(),
+            routing,
+            &mut mapping.docs,
+            source,
+            None,
+            &mut skipped,
+        );
+    }
+    if mapping.docs.is_empty() {
+        return print_nothing_to_update(service, diff, dry_run, &skipped);
+    }
+
+    if dry_run && output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "service": service,
+            "dry_run": true,
+            "source": diff.identifier,
+            "files": diff.summary.total_files,
+            "locations": mapping.docs.iter().map(|doc| serde_json::json!({
+                "type": doc.r#type,
+                "location": doc.location,
+                "routing": routing.for_location(doc).describe(),
+            })).collect::<Vec<_>>(),
+        }));
+    }
+
     if dry_run {
         println!("Dry run mode - would update the following locations:");
         for doc in &mapping.docs {
-            println!("  - {} ({})", doc.location, doc.r#type);
+            println!(
+                "  - {} ({}) -> {}",
+                doc.location,
+                doc.r#type,
+                routing.for_location(doc).describe()
+            );
         }
         println!("Changes to apply:");
         println!("  Source: {}", diff.identifier);
@@ -58,111 +429,495 @@ pub async fn execute(
         return Ok(());
     }
 
+    // Held until every location is published, so another ktme process
+    // does not publish the same service meanwhile
+    let _lock = ServiceLock::acquire(config, service, "update", wait).await?;
+
     // Generate update content
-    let ai_client = AIClient::new()?;
-    let prompt = PromptTemplates::update_documentation_prompt(&diff, section.as_deref())?;
+    let kind = routing.prompt_kind(&mapping.docs);
+    let primary_section = routing.section.or(mapping.docs[0].section.as_deref());
+    let prompt = PromptPack::load(settings.prompt_pack.as_deref())?.update_prompt(
+        diff,
+        primary_section,
+        kind,
+    )?;
+    let prompt = PromptTemplates::in_language(prompt, settings.language.as_deref());
+    let ai_client = match super::generate::ai_client_for(&config.ai, &settings)? {
+        Some(ai_client) => ai_client,
+        None => match routed_client(&config.ai, false, kind.name(), &prompt)? {
+            Some(ai_client) => ai_client,
+            None => AIClient::from_config(&config.ai)?,
+        },
+    };
+
+    // Locations that already hold the update of the same source, prompt
+    // pack and model are skipped before calling the model
+    let base_key = GenerationKey::new(
+        diff,
+        settings.prompt_pack.as_deref().unwrap_or("default"),
+        ai_client.model(),
+    )
+    .option("kind", Some(kind.name()))
+    .option("language", settings.language.as_deref());
+    let key_for = |doc: &DocumentLocation| {
+        base_key
+            .clone()
+            .option("routing", Some(&routing.for_location(doc).describe()))
+            .hash()
+    };
+    if !force {
+        skip_published(history.as_ref(), &mut mapping.docs, key_for, &mut skipped);
+        if mapping.docs.is_empty() {
+            return print_nothing_to_update(service, diff, dry_run, &skipped);
+        }
+    }
 
     tracing::info!("Generating update content...");
-    let update_content = ai_client.generate_documentation(&prompt).await?;
-
-    // Apply updates to each documentation location
-    for doc_location in &mapping.docs {
-        match doc_location.r#type.as_str() {
-            "markdown" => {
-                update_markdown_file(&doc_location.location, &update_content, section.as_deref())?;
-                println!("✓ Updated markdown file: {}", doc_location.location);
+    let progress = Progress::spinner(format!(
+        "Generating update content with {}",
+        ai_client.provider_name()
+    ));
+    let update_content = shutdown::or_cancelled(
+        "Generating update content",
+        ai_client.generate_documentation(&prompt),
+    )
+    .await?;
+    progress.finish();
+
+    if !force {
+        let hash = super::generate::content_hash(&update_content);
+        skip_documented(
+            history.as_ref(),
+            routing,
+            &mut mapping.docs,
+            None,
+            Some(&hash),
+            &mut skipped,
+        );
+        if mapping.docs.is_empty() {
+            return print_nothing_to_update(service, diff, dry_run, &skipped);
+        }
+    }
+    let parallelism = jobs.unwrap_or(config.general.parallelism);
+
+    // Publish to every documentation location concurrently
+    let progress = Progress::bar(
+        mapping.docs.len() as u64,
+        format!("Publishing to {} location(s)", mapping.docs.len()),
+    );
+    let outcomes = run_limited(&mapping.docs, parallelism, |doc_location| {
+        let update_content = &update_content;
+        let progress = &progress;
+        async move {
+            let update = routing.for_location(doc_location);
+            // Publishes already running finish; no new one starts after a
+            // shutdown signal
+            let applied = match shutdown::check("Publishing") {
+                Ok(()) => {
+                    let notice = NoticeContext {
+                        service: Some(service),
+                        doc_type: None,
+                        source,
+                    };
+                    publish_to_location(config, doc_location, update_content, &update, &notice)
+                        .await
+                }
+                Err(e) => Some(Err(e)),
+            };
+            progress.inc();
+            (doc_location, applied)
+        }
+    })
+    .await;
+    progress.finish();
+
+    // Aggregate the outcomes in mapping order
+    let total = outcomes.len();
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+    let mut written = Vec::new();
+    let mut updated = Vec::new();
+    for (doc_location, applied) in outcomes {
+        if matches!(applied, Some(Ok(()))) {
+            updated.push(doc_location);
+        }
+        if let Some(result) = &applied {
+            super::generate::record_history(
+                history.as_ref(),
+                service,
+                "update",
+                &doc_location.r#type,
+                Some(&doc_location.location),
+                diff,
+                Some(&update_content),
+                result.as_ref().err().map(|e| e.to_string()).as_deref(),
+                ai_client.model(),
+                Some(&key_for(doc_location)),
+            );
+        }
+
+        let status = match (doc_location.r#type.as_str(), applied) {
+            (_, Some(Err(e))) => {
+                output::message(format!(
+                    "✗ Failed to update {}: {}",
+                    doc_location.location, e
+                ));
+                failures.push((doc_location.location.clone(), e));
+                "failed"
+            }
+            ("markdown", Some(Ok(()))) => {
+                output::message(format!(
+                    "✓ Updated markdown file: {}",
+                    doc_location.location
+                ));
+                written.push(PathBuf::from(&doc_location.location));
+                "updated"
             }
-            "confluence" => {
-                update_confluence_page(&doc_location.location, &update_content).await?;
-                println!("✓ Updated Confluence page: {}", doc_location.location);
+            ("confluence", Some(Ok(()))) => {
+                output::message(format!(
+                    "✓ Updated Confluence page: {}",
+                    doc_location.location
+                ));
+                "updated"
             }
-            _ => {
-                println!("⚠ Unknown documentation type: {}", doc_location.r#type);
+            (provider, Some(Ok(()))) => {
+                output::message(format!(
+                    "✓ Updated {} document: {}",
+                    provider, doc_location.location
+                ));
+                "updated"
             }
+            (_, None) => {
+                output::message(format!(
+                    "⚠ Unknown documentation type: {}",
+                    doc_location.r#type
+                ));
+                "skipped"
+            }
+        };
+        results.push(serde_json::json!({
+            "type": doc_location.r#type,
+            "location": doc_location.location,
+            "status": status,
+        }));
+    }
+
+    results.extend(skipped.iter().map(skipped_json));
+
+    // Translated counterparts follow the documents just updated
+    for doc_location in updated {
+        let synced = translation::after_publish(config, service, doc_location).await;
+        written.extend(
+            synced
+                .iter()
+                .filter(|synced| synced.written && synced.target_type == "markdown")
+                .map(|synced| PathBuf::from(&synced.target)),
+        );
+        super::translate::report(&synced);
+    }
+
+    if total == 1 {
+        if let Some((_, e)) = failures.pop() {
+            return Err(e);
         }
     }
+    if !failures.is_empty() {
+        return Err(KtmeError::Documentation(format!(
+            "Failed to update {} of {} locations: {}",
+            failures.len(),
+            total,
+            failures
+                .iter()
+                .map(|(location, e)| format!("{} ({})", location, e))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
+    if let Some(options) = commit_docs {
+        super::generate::commit_written_docs(
+            config,
+            options,
+            &written,
+            &[service.to_string()],
+            &diff.identifier,
+        )
+        .await?;
+    }
+
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "service": service,
+            "dry_run": false,
+            "source": diff.identifier,
+            "locations": results,
+        }));
+    }
 
     println!("Documentation updated successfully!");
     Ok(())
 }
 
-fn update_markdown_file(file_path: &str, content: &str, section: Option<&str>) -> Result<()> {
-    let existing_content =
-        fs::read_to_string(file_path).map_err(|e| crate::error::KtmeError::Io(e))?;
+/// Move the changelogs of `docs` that already hold the entries of `source`,
+/// or content hashing to `content_hash`, to `skipped`
+fn skip_documented(
+    history: &dyn Storage,
+    routing: Routing<'_>,
+    docs: &mut Vec<DocumentLocation>,
+    source: Option<&str>,
+    content_hash: Option<&str>,
+    skipped: &mut Vec<(DocumentLocation, GenerationRecord)>,
+) {
+    if source.is_none() && content_hash.is_none() {
+        return;
+    }
+    let mut pending = Vec::new();
+    for doc in docs.drain(..) {
+        if !routing.for_location(&doc).adds_entries() {
+            pending.push(doc);
+            continue;
+        }
+        match history.find_documented(&doc.location, source, content_hash) {
+            Ok(Some(record)) => {
+                output::message(format!(
+                    "- Skipping {}: it documents {} since {} (--force updates it again)",
+                    doc.location,
+                    record
+                        .source_identifier
+                        .as_deref()
+                        .unwrap_or("this content"),
+                    record.created_at.format("%Y-%m-%d %H:%M")
+                ));
+                skipped.push((doc, record));
+            }
+            Ok(None) => pending.push(doc),
+            Err(e) => {
+                tracing::warn!("Failed to read the history of {}: {}", doc.location, e);
+                pending.push(doc);
+            }
+        }
+    }
+    *docs = pending;
+}
 
-    let updated_content = if let Some(section_name) = section {
-        // Find and update specific section
-        update_markdown_section(&existing_content, section_name, content)
-    } else {
-        // Append to end of file
-        format!(
-            "{}\n\n---\n\n## Update {}\n\n{}",
-            existing_content,
-            chrono::Utc::now().format("%Y-%m-%d"),
-            content
-        )
-    };
+/// Move the locations of `docs` whose idempotency key, as computed by
+/// `key_for`, already succeeded to `skipped`
+fn skip_published(
+    history: &dyn Storage,
+    docs: &mut Vec<DocumentLocation>,
+    key_for: impl Fn(&DocumentLocation) -> String,
+    skipped: &mut Vec<(DocumentLocation, GenerationRecord)>,
+) {
+    let mut pending = Vec::new();
+    for doc in docs.drain(..) {
+        match history.find_by_key(&doc.location, &key_for(&doc)) {
+            Ok(Some(record)) => {
+                output::message(format!(
+                    "- Skipping {}: it was updated with the same changes, prompts and model on {} (--force updates it again)",
+                    doc.location,
+                    record.created_at.format("%Y-%m-%d %H:%M")
+                ));
+                skipped.push((doc, record));
+            }
+            Ok(None) => pending.push(doc),
+            Err(e) => {
+                tracing::warn!("Failed to read the history of {}: {}", doc.location, e);
+                pending.push(doc);
+            }
+        }
+    }
+    *docs = pending;
+}
 
-    fs::write(file_path, updated_content).map_err(|e| crate::error::KtmeError::Io(e))?;
+fn skipped_json((doc, record): &(DocumentLocation, GenerationRecord)) -> serde_json::Value {
+    serde_json::json!({
+        "type": doc.r#type,
+        "location": doc.location,
+        "status": "skipped",
+        "documented_at": record.created_at,
+    })
+}
 
+/// Result of an update whose locations all document the changes already
+fn print_nothing_to_update(
+    service: &str,
+    diff: &ExtractedDiff,
+    dry_run: bool,
+    skipped: &[(DocumentLocation, GenerationRecord)],
+) -> Result<()> {
+    if output::is_json() {
+        return output::print_json(&serde_json::json!({
+            "service": service,
+            "dry_run": dry_run,
+            "source": diff.identifier,
+            "locations": skipped.iter().map(skipped_json).collect::<Vec<_>>(),
+        }));
+    }
+    println!(
+        "Documentation of {} is already up to date with {}",
+        service, diff.identifier
+    );
     Ok(())
 }
 
-fn update_markdown_section(content: &str, section_name: &str, new_content: &str) -> String {
-    let lines: Vec<&str> = content.lines().collect();
-    let mut result: Vec<String> = Vec::new();
-    let mut in_section = false;
-    let mut section_found = false;
-
-    for line in lines {
-        if line.starts_with("#") && line.to_lowercase().contains(&section_name.to_lowercase()) {
-            in_section = true;
-            section_found = true;
-            result.push(line.to_string());
-            result.push("".to_string());
-            result.extend(new_content.lines().map(|l| l.to_string()));
-        } else if in_section && line.starts_with("#") {
-            in_section = false;
-            result.push(line.to_string());
-        } else if !in_section {
-            result.push(line.to_string());
+/// Write `content` into one documentation location, with the notices
+/// `[notices]` gives it; `None` for a location type without a provider
+pub(crate) async fn publish_to_location(
+    config: &Config,
+    doc_location: &DocumentLocation,
+    content: &str,
+    update: &SectionUpdate<'_>,
+    notice: &NoticeContext<'_>,
+) -> Option<Result<()>> {
+    let notice = NoticeContext {
+        doc_type: notice.doc_type.or(Some(update.kind.name())),
+        ..*notice
+    };
+    let notices = Notices::for_location(&config.notices, doc_location, &notice);
+    match doc_location.r#type.as_str() {
+        "markdown" => {
+            Some(update_markdown_file(&doc_location.location, content, update, &notices).await)
         }
+        "confluence" => Some(
+            match crate::http::ensure_online("Publishing to Confluence") {
+                Ok(()) => {
+                    update_confluence_page(
+                        &config.confluence,
+                        &doc_location.location,
+                        content,
+                        update,
+                        &notices,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            },
+        ),
+        provider_type => match provider_config(config, provider_type) {
+            Ok(Some(provider_config)) => Some(
+                update_provider_document(
+                    provider_config,
+                    &doc_location.location,
+                    content,
+                    update,
+                    &notices,
+                )
+                .await,
+            ),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        },
     }
+}
 
-    if !section_found {
-        // Section not found, append to end
-        result.push("".to_string());
-        result.push(format!("## {}", section_name));
-        result.push("".to_string());
-        result.extend(new_content.lines().map(|l| l.to_string()));
+/// Configuration `ProviderFactory` creates a `provider_type` provider from:
+/// the one saved with `ktme provider add`, or an empty one for a plugin on
+/// PATH. `None` when there is no such provider.
+pub(crate) fn provider_config(
+    config: &Config,
+    provider_type: &str,
+) -> Result<Option<ProviderConfig>> {
+    let saved = open_storage(&config.storage)?.get_provider_config(provider_type)?;
+    if let Some(saved) = saved {
+        return Ok(Some(saved.into()));
     }
+    Ok(external::find(provider_type).map(|_| ProviderConfig {
+        id: 0,
+        provider_type: provider_type.to_string(),
+        config: serde_json::json!({}),
+        is_default: false,
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    }))
+}
 
-    result.join("\n")
+/// Write `content` into the document `id` of a provider, routed the same
+/// way as in Markdown files
+async fn update_provider_document(
+    provider_config: ProviderConfig,
+    id: &str,
+    content: &str,
+    update: &SectionUpdate<'_>,
+    notices: &Notices,
+) -> Result<()> {
+    crate::http::ensure_online(&format!("Publishing to {}", provider_config.provider_type))?;
+    let provider =
+        ProviderFactory::create(&provider_config.provider_type.clone(), provider_config)?;
+    let existing = provider.get_document(id).await?.ok_or_else(|| {
+        KtmeError::DocumentNotFound(format!("{} document {}", provider.name(), id))
+    })?;
+    let updated = notices.update(&existing.content, SectionFormat::Markdown, |existing| {
+        update.apply(existing, content, SectionFormat::Markdown)
+    });
+    match provider
+        .update_document(&existing.id, &updated)
+        .await?
+        .status
+    {
+        PublishStatus::Failed(e) => Err(KtmeError::Documentation(e)),
+        _ => Ok(()),
+    }
 }
 
-async fn update_confluence_page(location: &str, content: &str) -> Result<()> {
-    tracing::info!("Updating Confluence page at: {}", location);
+/// Write `content` into a Markdown file through the Markdown provider, which
+/// replaces it atomically and fails when it changed since it was read
+async fn update_markdown_file(
+    file_path: &str,
+    content: &str,
+    update: &SectionUpdate<'_>,
+    notices: &Notices,
+) -> Result<()> {
+    let provider = MarkdownProvider::for_file(file_path);
+    let existing = provider
+        .get_document(file_path)
+        .await?
+        .ok_or_else(|| KtmeError::DocumentNotFound(file_path.to_string()))?;
 
-    // Load Confluence configuration from config file
-    let config = Config::load()?;
-    let confluence_config = config.confluence;
+    let updated_content = notices.update(&existing.content, SectionFormat::Markdown, |existing| {
+        update.apply(existing, content, SectionFormat::Markdown)
+    });
+
+    provider
+        .update_document(file_path, &updated_content)
+        .await?;
+
+    Ok(())
+}
+
+async fn update_confluence_page(
+    confluence_config: &ConfluenceConfig,
+    location: &str,
+    content: &str,
+    update: &SectionUpdate<'_>,
+    notices: &Notices,
+) -> Result<()> {
+    tracing::info!("Updating Confluence page at: {}", location);
 
     // Validate required configuration fields
-    let base_url = confluence_config.base_url.ok_or_else(|| {
-        crate::error::KtmeError::Config(
-            "Confluence base_url not configured. Please set [confluence] base_url in config.toml"
-                .to_string(),
-        )
-    })?;
+    let (base_url, api_token) = if confluence_config.auth_type == "oauth" {
+        // Signed in with `ktme auth login confluence`
+        let access = ConfluenceOAuth::from_config(confluence_config)?
+            .access()
+            .await?;
+        (access.api_base, access.access_token)
+    } else {
+        let base_url = confluence_config.base_url.clone().ok_or_else(|| {
+            crate::error::KtmeError::Config(
+                "Confluence base_url not configured. Please set [confluence] base_url in config.toml"
+                    .to_string(),
+            )
+        })?;
 
-    let api_token = confluence_config.api_token.ok_or_else(|| {
-        crate::error::KtmeError::Config(
-            "Confluence api_token not configured. Please set [confluence] api_token in config.toml"
-                .to_string(),
-        )
-    })?;
+        let api_token = confluence_config.api_token.clone().ok_or_else(|| {
+            crate::error::KtmeError::Config(
+                "Confluence api_token not configured. Please set [confluence] api_token in config.toml"
+                    .to_string(),
+            )
+        })?;
+        (base_url, api_token)
+    };
 
-    let space_key = confluence_config.space_key.ok_or_else(|| {
+    let space_key = confluence_config.space_key.clone().ok_or_else(|| {
         crate::error::KtmeError::Config(
             "Confluence space_key not configured. Please set [confluence] space_key in config.toml"
                 .to_string(),
@@ -175,13 +930,15 @@ async fn update_confluence_page(location: &str, content: &str) -> Result<()> {
     // Create Confluence writer
     let writer = ConfluenceWriter::new(base_url, api_token, space_key);
 
-    // Update the page
-    writer.update_page(&page_id, content).await?;
+    // Update the page sections
+    writer
+        .update_page_sections(&page_id, content, update, notices)
+        .await?;
 
     Ok(())
 }
 
-fn extract_confluence_page_id(url: &str) -> Result<String> {
+pub(crate) fn extract_confluence_page_id(url: &str) -> Result<String> {
     // Try to extract page ID from URL patterns:
     // 1. https://confluence.example.com/pages/viewpage.action?pageId=123456
     // 2. https://confluence.example.com/display/SPACE/Page+Title (would need API call)
@@ -209,3 +966,152 @@ fn extract_confluence_page_id(url: &str) -> Result<String> {
         url
     )))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
+
+    fn storage() -> StorageManager {
+        let storage = StorageManager::with_storage(
+            PathBuf::from("mappings.toml"),
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+        );
+        storage
+            .add_mapping(
+                "payments".to_string(),
+                DocumentLocation {
+                    r#type: "markdown".to_string(),
+                    location: "docs/CHANGELOG.md".to_string(),
+                    section: Some("Payments".to_string()),
+                },
+            )
+            .unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_resolve_targets_uses_the_mapping() {
+        let storage = storage();
+        let mapping = resolve_targets(&storage, "payments", Target::default()).unwrap();
+        assert_eq!(mapping.docs.len(), 1);
+        assert_eq!(mapping.docs[0].location, "docs/CHANGELOG.md");
+
+        // The mapped section is used unless --section is given
+        let routing = Routing {
+            section: None,
+            doc_type: None,
+            mode: None,
+        };
+        let update = routing.for_location(&mapping.docs[0]);
+        assert_eq!(update.section, Some("Payments"));
+        assert_eq!(update.kind, DocKind::Changelog);
+        let routing = Routing {
+            section: Some("Fixes"),
+            ..routing
+        };
+        assert_eq!(
+            routing.for_location(&mapping.docs[0]).section,
+            Some("Fixes")
+        );
+
+        // --doc of a mapped location keeps its section
+        let target = Target {
+            doc: Some("docs/CHANGELOG.md"),
+            provider: None,
+        };
+        let mapping = resolve_targets(&storage, "payments", target).unwrap();
+        assert_eq!(mapping.docs[0].section.as_deref(), Some("Payments"));
+    }
+
+    #[test]
+    fn test_resolve_targets_requires_doc_without_mapping() {
+        let storage = storage();
+        assert!(matches!(
+            resolve_targets(&storage, "billing", Target::default()),
+            Err(KtmeError::DocumentNotFound(_))
+        ));
+
+        let target = Target {
+            doc: Some("https://wiki.example.com/pages/viewpage.action?pageId=42"),
+            provider: None,
+        };
+        let mapping = resolve_targets(&storage, "billing", target).unwrap();
+        assert_eq!(mapping.docs[0].r#type, "confluence");
+
+        let target = Target {
+            doc: Some("https://www.notion.so/Billing-0123"),
+            provider: Some("notion"),
+        };
+        let mapping = resolve_targets(&storage, "billing", target).unwrap();
+        assert_eq!(mapping.docs[0].r#type, "notion");
+        assert_eq!(infer_provider("docs/billing.md"), "markdown");
+    }
+
+    #[test]
+    fn test_skip_documented_changelogs() {
+        let history = SqliteStorage::new(Database::in_memory().unwrap());
+        history
+            .record_generation(
+                None,
+                "markdown",
+                None,
+                Some("docs/CHANGELOG.md"),
+                "update",
+                Some("commit"),
+                Some("3f2a9c1"),
+                Some("abc"),
+                "success",
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let routing = Routing {
+            section: None,
+            doc_type: None,
+            mode: None,
+        };
+        let doc = |location: &str| DocumentLocation {
+            r#type: "markdown".to_string(),
+            location: location.to_string(),
+            section: None,
+        };
+
+        // Only changelogs documenting the source are skipped
+        let mut docs = vec![doc("docs/CHANGELOG.md"), doc("docs/guide.md")];
+        let mut skipped = Vec::new();
+        skip_documented(
+            &history,
+            routing,
+            &mut docs,
+            Some("3f2a9c1"),
+            None,
+            &mut skipped,
+        );
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].location, "docs/guide.md");
+        assert_eq!(skipped[0].0.location, "docs/CHANGELOG.md");
+
+        let mut docs = vec![doc("docs/CHANGELOG.md")];
+        skip_documented(
+            &history,
+            routing,
+            &mut docs,
+            Some("9e8d7c6"),
+            None,
+            &mut skipped,
+        );
+        assert_eq!(docs.len(), 1);
+        skip_documented(
+            &history,
+            routing,
+            &mut docs,
+            None,
+            Some("abc"),
+            &mut skipped,
+        );
+        assert!(docs.is_empty());
+    }
+}
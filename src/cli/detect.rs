@@ -0,0 +1,101 @@
+//! Service of the current directory for commands run without `--service`
+
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::service_detector::{self, Origin, ServiceDetector};
+use crate::storage::backend::open_storage;
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Service detected for the current directory, confirmed on the terminal
+/// unless `yes` or pinned with `ktme service pin`. The service is
+/// registered in storage with the project directory as its path when it is
+/// new.
+pub async fn service(yes: bool) -> Result<String> {
+    let config = Config::load()?;
+    let cache = service_detector::detection_cache(&config);
+    let detector = ServiceDetector::new()?;
+    let detection = detector.detect_cached(cache.as_deref()).await?;
+
+    let service = if yes || detection.origin == Origin::Pinned {
+        detection.name.clone()
+    } else if std::io::stdin().is_terminal() {
+        let service = super::progress::suspended(|| confirm(&detection.name))?;
+        // Remember a name typed instead of the detected one
+        if let Some(cache) = cache.as_ref().filter(|_| service != detection.name) {
+            cache.save_service_detection(&detector.directory(), &service, false)?;
+        }
+        service
+    } else {
+        return Err(KtmeError::InvalidInput(format!(
+            "No --service given; detected '{}'. Pass --yes to use it, --service to choose one, or pin it with `ktme service pin`",
+            detection.name
+        )));
+    };
+    if service == detection.name {
+        super::output::message(format!("Using {} service '{}'", detection.origin, service));
+    } else {
+        super::output::message(format!("Using service '{}'", service));
+    }
+
+    let info = detector.get_repository_info();
+    let project_dir = info.repository_root.unwrap_or(info.current_dir);
+    record(&config, &service, &project_dir.to_string_lossy())?;
+
+    Ok(service)
+}
+
+/// Ask on stderr whether to use the `detected` service
+fn confirm(detected: &str) -> Result<String> {
+    eprint!(
+        "Detected service '{}'. Use it? [Y/n, or type another name] ",
+        detected
+    );
+    std::io::stderr().flush()?;
+
+    let mut reply = String::new();
+    std::io::stdin().lock().read_line(&mut reply)?;
+    answer(detected, &reply)
+        .ok_or_else(|| KtmeError::InvalidInput("No service selected; pass --service".to_string()))
+}
+
+/// Service chosen by `reply` to the confirmation of `detected`; `None` when
+/// it is declined
+fn answer(detected: &str, reply: &str) -> Option<String> {
+    match reply.trim() {
+        "" => Some(detected.to_string()),
+        reply if reply.eq_ignore_ascii_case("y") || reply.eq_ignore_ascii_case("yes") => {
+            Some(detected.to_string())
+        }
+        reply if reply.eq_ignore_ascii_case("n") || reply.eq_ignore_ascii_case("no") => None,
+        name => Some(name.to_string()),
+    }
+}
+
+fn record(config: &Config, service: &str, path: &str) -> Result<()> {
+    let storage = open_storage(&config.storage)?;
+    if storage.get_service_by_name(service)?.is_none() {
+        storage.create_service(
+            service,
+            Some(path),
+            Some(&format!("Detected from {}", path)),
+        )?;
+        tracing::info!("Registered detected service '{}' at {}", service, path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_accepts_declines_or_renames() {
+        assert_eq!(answer("payments", "\n").as_deref(), Some("payments"));
+        assert_eq!(answer("payments", "Yes\n").as_deref(), Some("payments"));
+        assert_eq!(answer("payments", "n\n"), None);
+        assert_eq!(
+            answer("payments", " billing-api \n").as_deref(),
+            Some("billing-api")
+        );
+    }
+}
@@ -1 +1,5 @@
 pub mod commands;
+pub mod detect;
+pub mod output;
+pub mod parallel;
+pub mod progress;
@@ -0,0 +1,80 @@
+//! Output mode shared by all commands
+//!
+//! With the global `--json` flag commands print exactly one JSON document to
+//! stdout; human-readable progress messages move to stderr so the JSON can
+//! be parsed by other tools.
+
+use crate::error::{KtmeError, Result};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable machine-readable JSON output
+pub fn set_json(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--json` is active
+pub fn is_json() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Enable or disable `--quiet`
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `--quiet` is active
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a value as pretty JSON to stdout
+pub fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Print a human-readable message: stdout normally, stderr in JSON mode.
+/// Any progress spinner is hidden while the line is printed.
+pub fn message(text: impl std::fmt::Display) {
+    super::progress::suspended(|| {
+        if is_json() {
+            eprintln!("{}", text);
+        } else {
+            println!("{}", text);
+        }
+    });
+}
+
+/// JSON document printed for a failed command
+pub fn print_error(error: &KtmeError) {
+    let class = error.class();
+    let _ = print_json(&serde_json::json!({
+        "error": error.to_string(),
+        "code": class.as_str(),
+        "exit_code": class.exit_code(),
+        "hint": error.hint(),
+        "causes": error.causes(),
+    }));
+}
+
+/// Print a failed command's error to stderr, with its causes when `verbose`
+/// and a hint on how to fix it
+pub fn report_error(error: &KtmeError, verbose: bool) {
+    let message = error.to_string();
+    super::progress::suspended(|| {
+        eprintln!("Error [{}]: {}", error.class().as_str(), message);
+        if verbose {
+            // Wrapped errors usually repeat their source in the message
+            for cause in error.causes().iter().filter(|c| !message.contains(*c)) {
+                eprintln!("  Caused by: {}", cause);
+            }
+        }
+        if let Some(hint) = error.hint() {
+            eprintln!("Hint: {}", hint);
+        }
+    });
+}
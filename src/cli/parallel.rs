@@ -0,0 +1,70 @@
+//! Bounded concurrency for commands that fan out over services or
+//! documentation locations
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::future::Future;
+
+/// Run `task` for every item with at most `limit` futures in flight and
+/// return the results in input order.
+///
+/// The futures are polled on the current task, so they may borrow from the
+/// caller and do not need to be `Send`.
+pub async fn run_limited<I, T, F, Fut>(items: I, limit: usize, mut task: F) -> Vec<T>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let limit = limit.max(1);
+    let mut pending = items.into_iter().enumerate();
+    let mut running = FuturesUnordered::new();
+    let mut results = Vec::new();
+
+    loop {
+        while running.len() < limit {
+            let Some((index, item)) = pending.next() else {
+                break;
+            };
+            let future = task(item);
+            running.push(async move { (index, future.await) });
+        }
+
+        match running.next().await {
+            Some(result) => results.push(result),
+            None => break,
+        }
+    }
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_run_limited_keeps_order_and_limit() {
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let results = run_limited(1..=6u64, 2, |n| {
+            let in_flight = &in_flight;
+            let peak = &peak;
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Later items finish first
+                tokio::time::sleep(Duration::from_millis(30 - n * 4)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                n * 10
+            }
+        })
+        .await;
+
+        assert_eq!(results, vec![10, 20, 30, 40, 50, 60]);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+}
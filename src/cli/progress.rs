@@ -0,0 +1,153 @@
+//! Spinners and progress bars for long-running stages
+//!
+//! Progress is drawn on stderr and only when it is a terminal; `--quiet` and
+//! `--json` disable it entirely. While a spinner is active, log lines and
+//! command messages are printed above it instead of through it. Stage
+//! durations are always recorded for `--timings`.
+
+use super::output;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::cell::RefCell;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The spinner currently on screen, used to keep log output from tearing it
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+const TICK: Duration = Duration::from_millis(100);
+
+/// A spinner or bar for one command; a no-op when progress is disabled
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    /// Label and start time of the current stage
+    stage: RefCell<(String, Instant)>,
+}
+
+impl Progress {
+    /// Spinner with a stage label
+    pub fn spinner(message: impl Into<String>) -> Self {
+        Self::start(
+            || {
+                ProgressBar::new_spinner().with_style(
+                    ProgressStyle::with_template("{spinner:.cyan} {msg} {elapsed:.dim}")
+                        .expect("valid spinner template"),
+                )
+            },
+            message,
+        )
+    }
+
+    /// Bar over `len` steps with a stage label
+    pub fn bar(len: u64, message: impl Into<String>) -> Self {
+        Self::start(
+            || {
+                ProgressBar::new(len).with_style(
+                    ProgressStyle::with_template(
+                        "{spinner:.cyan} [{bar:30.cyan/blue}] {pos}/{len} {msg}",
+                    )
+                    .expect("valid bar template")
+                    .progress_chars("=> "),
+                )
+            },
+            message,
+        )
+    }
+
+    fn start(create: impl FnOnce() -> ProgressBar, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let stage = RefCell::new((message.clone(), Instant::now()));
+        if !enabled() {
+            return Self { bar: None, stage };
+        }
+
+        let bar = create();
+        bar.set_message(message);
+        bar.enable_steady_tick(TICK);
+        *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(bar.clone());
+        Self {
+            bar: Some(bar),
+            stage,
+        }
+    }
+
+    /// Switch to the next stage
+    pub fn stage(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.end_stage(Some(message.clone()));
+        if let Some(bar) = &self.bar {
+            bar.set_message(message);
+        }
+    }
+
+    /// Record the duration of the current stage and start `next`
+    fn end_stage(&self, next: Option<String>) {
+        let mut stage = self.stage.borrow_mut();
+        crate::metrics::record_stage(&stage.0, stage.1.elapsed());
+        if let Some(next) = next {
+            *stage = (next, Instant::now());
+        }
+    }
+
+    /// Advance a bar by one step
+    pub fn inc(&self) {
+        if let Some(bar) = &self.bar {
+            bar.inc(1);
+        }
+    }
+
+    /// Run `f` with the spinner hidden, e.g. while writing to stdout
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
+    /// Print a command message above the spinner
+    pub fn message(&self, text: impl std::fmt::Display) {
+        self.suspend(|| output::message(text));
+    }
+
+    /// Stop and remove the spinner
+    pub fn finish(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.end_stage(None);
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+            *ACTIVE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+}
+
+/// Run `f` with the active spinner, if any, hidden
+pub fn suspended<R>(f: impl FnOnce() -> R) -> R {
+    let active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    match active {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
+
+fn enabled() -> bool {
+    !output::is_quiet() && !output::is_json() && std::io::stderr().is_terminal()
+}
+
+/// Log writer for stderr that hides the active spinner while a line is
+/// written
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        suspended(|| std::io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
@@ -0,0 +1,248 @@
+//! `${VAR}` references to environment variables in config values, e.g.
+//! `api_token = "${CONFLUENCE_TOKEN}"`, with `${VAR:-fallback}` for a
+//! default when `VAR` is unset or empty
+
+/// Environment variable referenced by a config value that is not set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    /// Dotted key of the value, e.g. "confluence.api_token"
+    pub key: String,
+    pub variable: String,
+}
+
+/// Replace the `${VAR}` references in the strings of `value` with the value
+/// of `VAR` from `lookup`; `$${` stands for a literal `${`. References to
+/// unset variables without a fallback are left in place and returned.
+pub fn interpolate(
+    value: &mut toml::Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Vec<Unresolved> {
+    let mut unresolved = Vec::new();
+    walk(value, String::new(), lookup, &mut unresolved);
+    unresolved
+}
+
+/// [`interpolate`] for the JSON configuration of a documentation provider
+pub fn interpolate_json(
+    value: &mut serde_json::Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Vec<Unresolved> {
+    let mut unresolved = Vec::new();
+    walk_json(value, String::new(), lookup, &mut unresolved);
+    unresolved
+}
+
+/// Value of the environment variable `name`, for [`interpolate`]
+pub fn env_lookup(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+fn walk(
+    value: &mut toml::Value,
+    key: String,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    unresolved: &mut Vec<Unresolved>,
+) {
+    match value {
+        toml::Value::String(raw) => {
+            let mut missing = Vec::new();
+            *raw = substitute(raw, lookup, &mut missing);
+            unresolved.extend(missing.into_iter().map(|variable| Unresolved {
+                key: key.clone(),
+                variable,
+            }));
+        }
+        toml::Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                walk(item, format!("{}[{}]", key, index), lookup, unresolved);
+            }
+        }
+        toml::Value::Table(table) => {
+            for (name, item) in table.iter_mut() {
+                let key = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", key, name)
+                };
+                walk(item, key, lookup, unresolved);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn walk_json(
+    value: &mut serde_json::Value,
+    key: String,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    unresolved: &mut Vec<Unresolved>,
+) {
+    match value {
+        serde_json::Value::String(raw) => {
+            let mut missing = Vec::new();
+            *raw = substitute(raw, lookup, &mut missing);
+            unresolved.extend(missing.into_iter().map(|variable| Unresolved {
+                key: key.clone(),
+                variable,
+            }));
+        }
+        serde_json::Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                walk_json(item, format!("{}[{}]", key, index), lookup, unresolved);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (name, item) in map.iter_mut() {
+                let key = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", key, name)
+                };
+                walk_json(item, key, lookup, unresolved);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn substitute(
+    raw: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    missing: &mut Vec<String>,
+) -> String {
+    let mut result = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start..];
+        if let Some(escaped) = after.strip_prefix("$${") {
+            result.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let reference = after
+            .strip_prefix("${")
+            .and_then(|inner| inner.find('}').map(|end| &inner[..end]))
+            .and_then(|inner| {
+                let (name, fallback) = match inner.split_once(":-") {
+                    Some((name, fallback)) => (name, Some(fallback)),
+                    None => (inner, None),
+                };
+                is_variable_name(name).then_some((inner, name, fallback))
+            });
+        match reference {
+            Some((inner, name, fallback)) => {
+                let value = lookup(name).filter(|value| fallback.is_none() || !value.is_empty());
+                match (value, fallback) {
+                    (Some(value), _) => result.push_str(&value),
+                    (None, Some(fallback)) => result.push_str(fallback),
+                    (None, None) => {
+                        missing.push(name.to_string());
+                        result.push_str(&after[..inner.len() + 3]);
+                    }
+                }
+                rest = &after[inner.len() + 3..];
+            }
+            None => {
+                result.push('$');
+                rest = &after[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "CONFLUENCE_TOKEN" => Some("secret".to_string()),
+            "HOST" => Some("wiki.example.com".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_resolves_references_in_nested_values() {
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [confluence]
+            base_url = "https://${HOST}/wiki"
+            api_token = "${CONFLUENCE_TOKEN}"
+            username = "cost is $5, literal $${HOST}"
+            space_key = "${SPACE:-DOCS}"
+            default_parent_id = "${HOST:-none}"
+
+            [[ai.fallback]]
+            api_key = "${OPENAI_KEY}"
+            "#,
+        )
+        .unwrap();
+
+        let unresolved = interpolate(&mut value, &lookup);
+
+        assert_eq!(
+            value["confluence"]["base_url"].as_str(),
+            Some("https://wiki.example.com/wiki")
+        );
+        assert_eq!(value["confluence"]["api_token"].as_str(), Some("secret"));
+        assert_eq!(
+            value["confluence"]["username"].as_str(),
+            Some("cost is $5, literal ${HOST}")
+        );
+        assert_eq!(value["confluence"]["space_key"].as_str(), Some("DOCS"));
+        assert_eq!(
+            value["confluence"]["default_parent_id"].as_str(),
+            Some("wiki.example.com")
+        );
+        assert_eq!(
+            unresolved,
+            vec![Unresolved {
+                key: "ai.fallback[0].api_key".to_string(),
+                variable: "OPENAI_KEY".to_string(),
+            }]
+        );
+        assert_eq!(
+            value["ai"]["fallback"][0]["api_key"].as_str(),
+            Some("${OPENAI_KEY}")
+        );
+    }
+
+    #[test]
+    fn test_interpolate_json_resolves_provider_config() {
+        let mut value = serde_json::json!({
+            "base_url": "https://${HOST}",
+            "api_token": "${CONFLUENCE_TOKEN}",
+            "labels": ["${LABEL:-docs}", "${MISSING}"],
+            "max_retries": 3,
+        });
+
+        let unresolved = interpolate_json(&mut value, &lookup);
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "base_url": "https://wiki.example.com",
+                "api_token": "secret",
+                "labels": ["docs", "${MISSING}"],
+                "max_retries": 3,
+            })
+        );
+        assert_eq!(
+            unresolved,
+            vec![Unresolved {
+                key: "labels[1]".to_string(),
+                variable: "MISSING".to_string(),
+            }]
+        );
+    }
+}
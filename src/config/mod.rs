@@ -1,12 +1,21 @@
+pub mod interpolate;
 pub mod types;
+pub mod validate;
 
 use crate::error::{KtmeError, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
 pub use types::*;
 
+/// Environment variable selecting the active workspace
+pub const WORKSPACE_ENV: &str = "KTME_WORKSPACE";
+
+/// Set by `ktme --offline`
+pub const OFFLINE_ENV: &str = "KTME_OFFLINE";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
@@ -21,6 +30,18 @@ pub struct Config {
     pub confluence: ConfluenceConfig,
     #[serde(default)]
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub issues: IssuesConfig,
+    #[serde(default)]
+    pub ai: AiConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+    #[serde(default)]
+    pub notices: NoticesConfig,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub policies: HashMap<String, PolicyConfig>,
 }
 
 impl Default for Config {
@@ -32,6 +53,12 @@ impl Default for Config {
             documentation: DocumentationConfig::default(),
             confluence: ConfluenceConfig::default(),
             storage: StorageConfig::default(),
+            issues: IssuesConfig::default(),
+            ai: AiConfig::default(),
+            plugins: PluginsConfig::default(),
+            timeouts: TimeoutsConfig::default(),
+            notices: NoticesConfig::default(),
+            policies: HashMap::new(),
         }
     }
 }
@@ -40,17 +67,46 @@ impl Config {
     pub fn load() -> Result<Self> {
         let config_path = Self::config_file_path()?;
 
-        if !config_path.exists() {
+        let mut config = if config_path.exists() {
+            let content = fs::read_to_string(&config_path)?;
+            Self::parse(&content)?
+        } else {
             tracing::warn!("Configuration file not found, using defaults");
-            return Ok(Self::default());
+            Self::default()
+        };
+
+        // `ktme --workspace` is passed down through the environment
+        if let Ok(workspace) = std::env::var(WORKSPACE_ENV) {
+            if !workspace.is_empty() {
+                config.storage.workspace = workspace;
+            }
         }
 
-        let content = fs::read_to_string(&config_path)?;
-        let config: Config = toml::from_str(&content)?;
+        // `ktme --offline` is passed down the same way
+        if offline_from_env() {
+            config.general.offline = true;
+        }
+
+        config.timeouts.validate()?;
+        config.git.diff_cache_ttl()?;
 
         Ok(config)
     }
 
+    /// Configuration in the TOML `content`, with its `${VAR}` references
+    /// resolved from the environment
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut value: toml::Value = toml::from_str(content)?;
+        let unresolved = interpolate::interpolate(&mut value, &interpolate::env_lookup);
+        if let Some(first) = unresolved.first() {
+            return Err(KtmeError::Config(format!(
+                "{}: environment variable {} is not set",
+                first.key, first.variable
+            )));
+        }
+        Ok(value.try_into()?)
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_file_path()?;
 
@@ -66,6 +122,27 @@ impl Config {
         Ok(())
     }
 
+    /// Copy of the configuration with its tokens, API keys and AI headers
+    /// masked, for display
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        mask(&mut config.git.github_token);
+        mask(&mut config.git.gitlab_token);
+        for host in &mut config.git.github_hosts {
+            mask(&mut host.token);
+        }
+        mask(&mut config.confluence.api_token);
+        mask(&mut config.confluence.oauth_client_secret);
+        mask(&mut config.issues.jira_api_token);
+        redact_ai(&mut config.ai);
+        config
+    }
+
+    /// Policy of `service` for `ktme run` and the MCP automated workflow
+    pub fn policy(&self, service: &str) -> PolicyConfig {
+        self.policies.get(service).cloned().unwrap_or_default()
+    }
+
     pub fn config_file_path() -> Result<PathBuf> {
         if let Ok(custom_path) = std::env::var("KTME_CONFIG") {
             return Ok(PathBuf::from(custom_path));
@@ -87,3 +164,30 @@ impl Config {
             .to_path_buf())
     }
 }
+
+/// Shown in place of a secret by [`Config::redacted`]
+pub const MASK: &str = "****";
+
+fn mask(secret: &mut Option<String>) {
+    if secret.is_some() {
+        *secret = Some(MASK.to_string());
+    }
+}
+
+fn redact_ai(ai: &mut AiConfig) {
+    mask(&mut ai.api_key);
+    for value in ai.headers.values_mut() {
+        *value = MASK.to_string();
+    }
+    for fallback in &mut ai.fallback {
+        redact_ai(fallback);
+    }
+    if let Some(summary) = &mut ai.summary {
+        redact_ai(summary);
+    }
+}
+
+/// Whether KTME_OFFLINE asks for offline mode
+pub fn offline_from_env() -> bool {
+    std::env::var(OFFLINE_ENV).is_ok_and(|value| matches!(value.as_str(), "1" | "true"))
+}
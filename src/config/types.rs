@@ -1,12 +1,27 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
     #[serde(default = "default_temp_directory")]
     pub temp_directory: String,
+    /// Level written to the JSON log file
     #[serde(default = "default_log_level")]
     pub log_level: String,
+    /// Write JSON logs to `<config dir>/logs/`, rotated daily
+    #[serde(default = "default_log_file")]
+    pub log_file: bool,
+    /// Number of daily log files to keep
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: usize,
+    /// Services generated or locations published concurrently
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+    /// Refuse outbound network calls: the mock (or a local) AI provider
+    /// generates documentation and only markdown locations are published
+    #[serde(default)]
+    pub offline: bool,
 }
 
 impl Default for GeneralConfig {
@@ -14,6 +29,10 @@ impl Default for GeneralConfig {
         Self {
             temp_directory: default_temp_directory(),
             log_level: default_log_level(),
+            log_file: default_log_file(),
+            log_max_files: default_log_max_files(),
+            parallelism: default_parallelism(),
+            offline: false,
         }
     }
 }
@@ -26,8 +45,60 @@ pub struct GitConfig {
     pub include_merge_commits: bool,
     #[serde(default = "default_max_commit_range")]
     pub max_commit_range: u32,
+    /// Per-file diff text kept when extracting; larger diffs are truncated
+    /// (0 keeps everything)
+    #[serde(default = "default_max_file_diff_bytes")]
+    pub max_file_diff_bytes: usize,
+    /// How long extracted commit diffs stay cached, as a duration such as
+    /// `12h` or `7d` (`0s` disables the cache)
+    #[serde(default = "default_diff_cache_ttl")]
+    pub diff_cache_ttl: String,
     pub github_token: Option<String>,
     pub gitlab_token: Option<String>,
+    /// GitHub Enterprise Server instances, matched by the host of the
+    /// repository's remote URL (`[[git.github_hosts]]`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub github_hosts: Vec<GitHubHostConfig>,
+}
+
+impl GitConfig {
+    /// Lifetime of cached commit diffs; `None` when the cache is disabled
+    pub fn diff_cache_ttl(&self) -> crate::error::Result<Option<chrono::Duration>> {
+        let ttl = crate::logging::parse_since(&self.diff_cache_ttl)
+            .map_err(|e| crate::error::KtmeError::Config(format!("[git] diff_cache_ttl: {}", e)))?;
+        Ok((ttl > chrono::Duration::zero()).then_some(ttl))
+    }
+}
+
+/// API base URL and token of a GitHub Enterprise Server host
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitHubHostConfig {
+    /// Host of the remote URLs, e.g. "github.example.com"
+    pub host: String,
+    /// REST API base URL; defaults to `https://<host>/api/v3`
+    pub api_url: Option<String>,
+    /// Token for this host; defaults to GH_ENTERPRISE_TOKEN or
+    /// GITHUB_ENTERPRISE_TOKEN
+    pub token: Option<String>,
+}
+
+impl GitHubHostConfig {
+    /// REST API base URL, without a trailing slash
+    pub fn api_base(&self) -> String {
+        match &self.api_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("https://{}/api/v3", self.host),
+        }
+    }
+}
+
+impl GitConfig {
+    /// GitHub Enterprise host configured for `host`, ignoring case
+    pub fn github_host(&self, host: &str) -> Option<&GitHubHostConfig> {
+        self.github_hosts
+            .iter()
+            .find(|entry| entry.host.eq_ignore_ascii_case(host))
+    }
 }
 
 impl Default for GitConfig {
@@ -36,8 +107,11 @@ impl Default for GitConfig {
             default_branch: default_branch(),
             include_merge_commits: false,
             max_commit_range: default_max_commit_range(),
+            max_file_diff_bytes: default_max_file_diff_bytes(),
+            diff_cache_ttl: default_diff_cache_ttl(),
             github_token: None,
             gitlab_token: None,
+            github_hosts: Vec::new(),
         }
     }
 }
@@ -53,6 +127,35 @@ pub struct McpConfig {
     pub temperature: f32,
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    /// Serve Prometheus metrics on `/metrics` in HTTP/daemon mode
+    #[serde(default)]
+    pub metrics: bool,
+    /// Tools the server exposes; empty exposes all of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enabled_tools: Vec<String>,
+    /// Tools withheld from the agents, e.g. `["update_documentation"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled_tools: Vec<String>,
+    /// Longest a tool call may run, e.g. "2m"
+    #[serde(default = "default_tool_timeout")]
+    pub tool_timeout: String,
+    /// Timeouts of single tools, e.g. `read_changes = "30s"`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_timeouts: HashMap<String, String>,
+    /// Largest tool output returned in a response, in bytes; longer output
+    /// is cut and the whole of it kept as a resource to read in parts
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    /// Largest JSON-RPC message accepted or sent, in bytes
+    #[serde(default = "default_max_payload_bytes")]
+    pub max_payload_bytes: usize,
+    /// File the redacted JSON-RPC traffic is appended to, as with
+    /// `mcp start --trace`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_file: Option<String>,
+    /// Keep diff bodies in the trace
+    #[serde(default)]
+    pub trace_diffs: bool,
 }
 
 impl Default for McpConfig {
@@ -63,6 +166,15 @@ impl Default for McpConfig {
             max_tokens: default_max_tokens(),
             temperature: default_temperature(),
             timeout: default_timeout(),
+            metrics: false,
+            enabled_tools: Vec::new(),
+            disabled_tools: Vec::new(),
+            tool_timeout: default_tool_timeout(),
+            tool_timeouts: HashMap::new(),
+            max_output_bytes: default_max_output_bytes(),
+            max_payload_bytes: default_max_payload_bytes(),
+            trace_file: None,
+            trace_diffs: false,
         }
     }
 }
@@ -74,6 +186,25 @@ pub struct DocumentationConfig {
     pub template_directory: Option<PathBuf>,
     #[serde(default = "default_include_metadata")]
     pub include_metadata: bool,
+    /// Add a "Primary contacts" section from blame and CODEOWNERS
+    #[serde(default = "default_include_contacts")]
+    pub include_contacts: bool,
+    /// Replace the code of generated documentation with structure-only
+    /// descriptions, as with `generate --redact-code`
+    #[serde(default)]
+    pub redact_code: bool,
+    /// Defaults for the per-service generation settings of `mapping add --set`
+    pub doc_type: Option<String>,
+    pub template: Option<String>,
+    pub language: Option<String>,
+    /// Prompt pack replacing the built-in prompts, see `ktme prompts`
+    pub prompt_pack: Option<String>,
+    #[serde(default)]
+    pub publish: Vec<String>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
 }
 
 impl Default for DocumentationConfig {
@@ -82,6 +213,15 @@ impl Default for DocumentationConfig {
             default_format: default_format(),
             template_directory: None,
             include_metadata: default_include_metadata(),
+            include_contacts: default_include_contacts(),
+            redact_code: false,
+            doc_type: None,
+            template: None,
+            language: None,
+            prompt_pack: None,
+            publish: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
         }
     }
 }
@@ -95,6 +235,17 @@ pub struct ConfluenceConfig {
     pub username: Option<String>,
     pub space_key: Option<String>,
     pub default_parent_page: Option<String>,
+    /// Client id of the Atlassian OAuth 2.0 app, for `auth_type = "oauth"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth_client_id: Option<String>,
+    /// Client secret of the OAuth app (falls back to
+    /// CONFLUENCE_OAUTH_CLIENT_SECRET)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth_client_secret: Option<String>,
+    /// Port of the local redirect listener used by `ktme auth login`
+    /// (default 8749)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth_redirect_port: Option<u16>,
 }
 
 impl Default for ConfluenceConfig {
@@ -106,18 +257,312 @@ impl Default for ConfluenceConfig {
             username: None,
             space_key: None,
             default_parent_page: None,
+            oauth_client_id: None,
+            oauth_client_secret: None,
+            oauth_redirect_port: None,
+        }
+    }
+}
+
+/// Issue tracker linking for generated documentation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuesConfig {
+    /// Add a "Related tickets" section for issue keys found in commit
+    /// messages and PR titles
+    #[serde(default = "default_issues_enabled")]
+    pub enabled: bool,
+    /// Look up ticket titles and status through the tracker APIs
+    #[serde(default)]
+    pub resolve: bool,
+    /// Only treat these Jira project keys as tickets, e.g. ["PAY", "OPS"].
+    /// When empty any `ABC-123` style key is accepted.
+    #[serde(default)]
+    pub jira_project_keys: Vec<String>,
+    pub jira_base_url: Option<String>,
+    pub jira_username: Option<String>,
+    /// Jira API token (falls back to JIRA_API_TOKEN)
+    pub jira_api_token: Option<String>,
+    /// Comment on referenced Jira tickets with the generated document location
+    #[serde(default)]
+    pub comment_on_jira: bool,
+    /// Repository (`owner/repo`) used to resolve bare `#123` references
+    pub github_repo: Option<String>,
+}
+
+impl Default for IssuesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_issues_enabled(),
+            resolve: false,
+            jira_project_keys: Vec::new(),
+            jira_base_url: None,
+            jira_username: None,
+            jira_api_token: None,
+            comment_on_jira: false,
+            github_repo: None,
+        }
+    }
+}
+
+/// AI provider selection. Without `provider` the provider is detected from
+/// OPENAI_API_KEY or ANTHROPIC_API_KEY.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AiConfig {
+    /// "openai", "anthropic", "mock", or `<name>` for a `ktme-ai-<name>`
+    /// plugin on PATH
+    pub provider: Option<String>,
+    /// API key (falls back to OPENAI_API_KEY / ANTHROPIC_API_KEY)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub temperature: Option<f32>,
+    /// Base URL of an OpenAI-compatible API such as an internal LLM gateway
+    /// (falls back to OPENAI_BASE_URL)
+    pub base_url: Option<String>,
+    /// Extra HTTP headers sent with every OpenAI-compatible request
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Providers tried in order when this one fails or times out, e.g.
+    /// `fallback = [{ provider = "openai" }, { provider = "mock" }]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<AiConfig>,
+    /// Cheaper provider condensing the changes into notes, from which this
+    /// provider writes the documentation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Box<AiConfig>>,
+    /// Model selection by document type and prompt size
+    #[serde(default, skip_serializing_if = "RoutingConfig::is_empty")]
+    pub routing: RoutingConfig,
+}
+
+/// `[ai.routing]`: the first matching rule picks the model of a generation
+/// unless the service or `--model` sets one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+}
+
+impl RoutingConfig {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// One `[[ai.routing.rules]]` entry; it matches when all its conditions hold
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Document types, e.g. "changelog" or "api-doc"; any when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub doc_types: Vec<String>,
+    /// Smallest estimated prompt size, in tokens
+    pub min_tokens: Option<usize>,
+    /// Largest estimated prompt size, in tokens
+    pub max_tokens: Option<usize>,
+    /// Provider used instead of `[ai] provider`
+    pub provider: Option<String>,
+    pub model: String,
+}
+
+/// Timeouts of outgoing API calls, as durations such as `10s`, `5m` or `1h`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeoutsConfig {
+    /// Establishing a connection (TCP and TLS)
+    #[serde(default = "default_connect_timeout")]
+    pub connect: String,
+    /// A single request, from sending it to reading the whole response.
+    /// Defaults to 5m for AI providers and 60s for everything else.
+    pub request: Option<String>,
+    /// Overall deadline of a `generate` or `update` run (`--timeout`
+    /// overrides it)
+    #[serde(default = "default_operation_timeout")]
+    pub operation: String,
+    /// Request timeouts by provider: "ai", "confluence", "notion", "jira",
+    /// "github" or "gitlab"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub providers: HashMap<String, String>,
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            connect: default_connect_timeout(),
+            request: None,
+            operation: default_operation_timeout(),
+            providers: HashMap::new(),
+        }
+    }
+}
+
+/// `[policies.<service>]`: what `ktme run` and the MCP automated workflow
+/// do for a service. Services without a policy use the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    /// Document types produced on each run; the service's doc_type when
+    /// empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub doc_types: Vec<String>,
+    /// Mapped locations published to, by provider type ("markdown",
+    /// "confluence") or location; the service's publish setting when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+    /// Hold the documentation in the review queue instead of publishing it
+    #[serde(default)]
+    pub review: bool,
+    /// Do not publish documentation with lint errors
+    #[serde(default = "default_policy_lint")]
+    pub lint: bool,
+    /// Webhook URLs (e.g. Slack incoming webhooks) sent a summary of each
+    /// run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notify: Vec<String>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            doc_types: Vec::new(),
+            targets: Vec::new(),
+            review: false,
+            lint: default_policy_lint(),
+            notify: Vec::new(),
         }
     }
 }
 
+/// `[notices]`: header and footer blocks ktme adds to the documents it
+/// publishes, e.g. a confidentiality label or a link to the source commit.
+/// `{service}`, `{doc_type}`, `{date}`, `{commit}` and `{commit_url}` are
+/// filled in; lines whose values are unknown are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoticesConfig {
+    /// Add notices; when disabled, those added before are removed. Nothing
+    /// is added or removed without a header, footer or rule.
+    #[serde(default = "default_notices_enabled")]
+    pub enabled: bool,
+    /// Block above the document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Block below the document, e.g. `*Generated by ktme*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    /// Overrides for the documents they match; later rules win
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<NoticeRule>,
+}
+
+impl Default for NoticesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_notices_enabled(),
+            header: None,
+            footer: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// `[[notices.rules]]`: notices of the documents matching every given
+/// criterion. An empty header or footer removes it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoticeRule {
+    /// Provider type, e.g. "confluence"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Document type, e.g. "api-doc" or "changelog"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_type: Option<String>,
+    /// Part of the location, e.g. `docs/public/` or a page title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+}
+
+/// Extensions loaded from disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    /// Directory holding `.wasm` analyzers and formatters (defaults to
+    /// `plugins/` next to the config file)
+    pub directory: Option<PathBuf>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub mappings_file: Option<PathBuf>,
     #[serde(default)]
     pub auto_discover: bool,
+    /// Store mappings in the database instead of mappings.toml
     #[serde(default)]
     pub use_sqlite: bool,
     pub database_file: Option<PathBuf>,
+    /// Database backend: "sqlite" (default) or "postgres". Selecting postgres
+    /// implies database-backed mappings regardless of `use_sqlite`; existing
+    /// mappings.toml entries are not imported and must be re-added with
+    /// `ktme mapping add`.
+    #[serde(default = "default_storage_backend")]
+    pub backend: String,
+    /// Connection URL for the postgres backend (falls back to KTME_DATABASE_URL)
+    pub database_url: Option<String>,
+    /// Encrypt the SQLite database with SQLCipher (requires `--features sqlcipher`)
+    #[serde(default)]
+    pub encrypt: bool,
+    /// Command printing the database passphrase, e.g. a keyring lookup such as
+    /// `secret-tool lookup service ktme`. KTME_DB_PASSPHRASE takes precedence.
+    pub passphrase_command: Option<String>,
+    /// Active workspace; services, mappings and history are isolated per
+    /// workspace. Overridden by `--workspace` / KTME_WORKSPACE.
+    #[serde(default = "default_workspace")]
+    pub workspace: String,
+    /// Per-workspace storage overrides, e.g. `[storage.workspaces.client-a]`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub workspaces: HashMap<String, WorkspaceConfig>,
+}
+
+/// Storage overrides for a single workspace
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    /// Dedicated SQLite database for this workspace
+    pub database_file: Option<PathBuf>,
+    /// Dedicated mappings.toml for this workspace
+    pub mappings_file: Option<PathBuf>,
+}
+
+impl StorageConfig {
+    /// Whether mappings and features live in the database rather than mappings.toml
+    pub fn uses_database(&self) -> bool {
+        self.use_sqlite || !self.backend.eq_ignore_ascii_case("sqlite")
+    }
+
+    /// Whether the active workspace is the default one
+    pub fn is_default_workspace(&self) -> bool {
+        self.workspace == default_workspace()
+    }
+
+    /// SQLite database file for the active workspace
+    pub fn workspace_database_file(&self) -> Option<PathBuf> {
+        self.workspaces
+            .get(&self.workspace)
+            .and_then(|w| w.database_file.clone())
+            .or_else(|| self.database_file.clone())
+    }
+
+    /// mappings.toml override for the active workspace
+    pub fn workspace_mappings_file(&self) -> Option<PathBuf> {
+        match self.workspaces.get(&self.workspace) {
+            Some(WorkspaceConfig {
+                mappings_file: Some(path),
+                ..
+            }) => Some(path.clone()),
+            _ if self.is_default_workspace() => self.mappings_file.clone(),
+            _ => None,
+        }
+    }
 }
 
 impl Default for StorageConfig {
@@ -127,6 +572,12 @@ impl Default for StorageConfig {
             auto_discover: false,
             use_sqlite: false,
             database_file: None,
+            backend: default_storage_backend(),
+            database_url: None,
+            encrypt: false,
+            passphrase_command: None,
+            workspace: default_workspace(),
+            workspaces: HashMap::new(),
         }
     }
 }
@@ -139,6 +590,18 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_log_file() -> bool {
+    true
+}
+
+fn default_log_max_files() -> usize {
+    7
+}
+
+fn default_parallelism() -> usize {
+    4
+}
+
 fn default_branch() -> String {
     "main".to_string()
 }
@@ -147,6 +610,14 @@ fn default_max_commit_range() -> u32 {
     100
 }
 
+fn default_max_file_diff_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_diff_cache_ttl() -> String {
+    "24h".to_string()
+}
+
 fn default_model() -> String {
     "claude-3-5-sonnet-20241022".to_string()
 }
@@ -159,10 +630,30 @@ fn default_temperature() -> f32 {
     0.7
 }
 
+fn default_connect_timeout() -> String {
+    "10s".to_string()
+}
+
+fn default_operation_timeout() -> String {
+    "30m".to_string()
+}
+
 fn default_timeout() -> u64 {
     120
 }
 
+fn default_tool_timeout() -> String {
+    "5m".to_string()
+}
+
+fn default_max_output_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_max_payload_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
 fn default_format() -> String {
     "markdown".to_string()
 }
@@ -171,6 +662,30 @@ fn default_include_metadata() -> bool {
     true
 }
 
+fn default_include_contacts() -> bool {
+    true
+}
+
+fn default_policy_lint() -> bool {
+    true
+}
+
+fn default_notices_enabled() -> bool {
+    true
+}
+
+fn default_issues_enabled() -> bool {
+    true
+}
+
+fn default_workspace() -> String {
+    "default".to_string()
+}
+
+fn default_storage_backend() -> String {
+    "sqlite".to_string()
+}
+
 fn default_auth_type() -> String {
     "token".to_string()
 }
@@ -0,0 +1,708 @@
+//! Diagnostics for `ktme config validate`: keys the configuration does not
+//! know, settings that only work together, unset `${VAR}` references and
+//! values that do not parse
+
+use super::interpolate::{self, Unresolved};
+use super::*;
+use crate::error::KtmeError;
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The setting cannot work as written
+    Error,
+    /// The setting is ignored or likely not what was meant
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Problem with the setting at `key`, e.g. "confluence.space_key"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub key: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Outcome of checking a config file
+#[derive(Debug)]
+pub struct Report {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The configuration, when the file deserializes
+    pub config: Option<Config>,
+}
+
+impl Report {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+}
+
+/// Check the config file `content`, resolving `${VAR}` references and
+/// credential fallbacks with `lookup`
+pub fn check(content: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Report {
+    let mut value: toml::Value = match toml::from_str(content) {
+        Ok(value) => value,
+        Err(e) => {
+            return Report {
+                diagnostics: vec![Diagnostic::error("", e.message())],
+                config: None,
+            }
+        }
+    };
+
+    let mut diagnostics: Vec<Diagnostic> = interpolate::interpolate(&mut value, lookup)
+        .into_iter()
+        .map(|Unresolved { key, variable }| {
+            Diagnostic::error(key, format!("environment variable {} is not set", variable))
+        })
+        .collect();
+
+    let mut unknown = Vec::new();
+    let config = match serde_ignored::deserialize(value, |path| unknown.push(segments(&path))) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            let e: toml::de::Error = e;
+            diagnostics.push(Diagnostic::error("", e.message()));
+            None
+        }
+    };
+    diagnostics.extend(unknown.iter().map(|path| unknown_key(path)));
+    if let Some(config) = &config {
+        diagnostics.extend(check_config(config, lookup));
+    }
+
+    Report {
+        diagnostics,
+        config,
+    }
+}
+
+/// Settings of `config` that are missing their counterpart or do not parse
+pub fn check_config(config: &Config, lookup: &dyn Fn(&str) -> Option<String>) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if !matches!(
+        config.general.log_level.to_lowercase().as_str(),
+        "trace" | "debug" | "info" | "warn" | "error"
+    ) {
+        diagnostics.push(Diagnostic::error(
+            "general.log_level",
+            format!(
+                "unknown level '{}'; expected trace, debug, info, warn or error",
+                config.general.log_level
+            ),
+        ));
+    }
+
+    if let Err(e) = config.git.diff_cache_ttl() {
+        diagnostics.push(Diagnostic::error("git.diff_cache_ttl", reason(e)));
+    }
+    for (index, host) in config.git.github_hosts.iter().enumerate() {
+        if host.host.trim().is_empty() {
+            diagnostics.push(Diagnostic::error(
+                format!("git.github_hosts[{}].host", index),
+                "host is empty",
+            ));
+        }
+    }
+
+    let timeouts = &config.timeouts;
+    let durations = [
+        ("timeouts.connect".to_string(), Some(&timeouts.connect)),
+        ("timeouts.request".to_string(), timeouts.request.as_ref()),
+        ("timeouts.operation".to_string(), Some(&timeouts.operation)),
+    ];
+    let providers = timeouts
+        .providers
+        .iter()
+        .map(|(name, value)| (format!("timeouts.providers.{}", name), Some(value)));
+    let tools = std::iter::once((
+        "mcp.tool_timeout".to_string(),
+        Some(&config.mcp.tool_timeout),
+    ))
+    .chain(
+        config
+            .mcp
+            .tool_timeouts
+            .iter()
+            .map(|(tool, value)| (format!("mcp.tool_timeouts.{}", tool), Some(value))),
+    );
+    for (key, value) in durations.into_iter().chain(providers).chain(tools) {
+        if let Some(Err(e)) = value.map(|value| crate::http::parse_timeout(value)) {
+            diagnostics.push(Diagnostic::error(key, reason(e)));
+        }
+    }
+
+    check_confluence(&config.confluence, lookup, &mut diagnostics);
+    check_issues(&config.issues, lookup, &mut diagnostics);
+    check_storage(&config.storage, lookup, &mut diagnostics);
+    check_ai(&config.ai, "ai", lookup, &mut diagnostics);
+
+    for (service, policy) in &config.policies {
+        for (index, url) in policy.notify.iter().enumerate() {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                diagnostics.push(Diagnostic::error(
+                    format!("policies.{}.notify[{}]", service, index),
+                    "not an http(s) webhook URL",
+                ));
+            }
+        }
+    }
+
+    check_notices(&config.notices, &mut diagnostics);
+
+    diagnostics
+}
+
+/// Placeholders of the notice templates that are never filled in
+fn check_notices(notices: &NoticesConfig, diagnostics: &mut Vec<Diagnostic>) {
+    let rules = notices.rules.iter().enumerate().flat_map(|(index, rule)| {
+        [
+            (format!("notices.rules[{}].header", index), &rule.header),
+            (format!("notices.rules[{}].footer", index), &rule.footer),
+        ]
+    });
+    let templates = [
+        ("notices.header".to_string(), &notices.header),
+        ("notices.footer".to_string(), &notices.footer),
+    ];
+    for (key, template) in templates.into_iter().chain(rules) {
+        let Some(template) = template else {
+            continue;
+        };
+        for name in crate::doc::notices::placeholders(template) {
+            if !crate::doc::notices::PLACEHOLDERS.contains(&name) {
+                diagnostics.push(Diagnostic::warning(
+                    key.clone(),
+                    format!(
+                        "unknown placeholder {{{}}}; known are {}",
+                        name,
+                        crate::doc::notices::PLACEHOLDERS.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn check_confluence(
+    confluence: &ConfluenceConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match (&confluence.base_url, &confluence.space_key) {
+        (Some(_), None) => diagnostics.push(Diagnostic::error(
+            "confluence.space_key",
+            "required with base_url: pages are created in this space",
+        )),
+        (None, Some(_)) => diagnostics.push(Diagnostic::error(
+            "confluence.base_url",
+            "required with space_key: the URL of the Confluence site",
+        )),
+        _ => {}
+    }
+    if confluence.base_url.is_none() {
+        return;
+    }
+
+    match confluence.auth_type.as_str() {
+        "oauth" => {
+            if confluence.oauth_client_id.is_none() {
+                diagnostics.push(Diagnostic::error(
+                    "confluence.oauth_client_id",
+                    "required with auth_type = \"oauth\"",
+                ));
+            }
+            if confluence.oauth_client_secret.is_none()
+                && lookup("CONFLUENCE_OAUTH_CLIENT_SECRET").is_none()
+            {
+                diagnostics.push(Diagnostic::error(
+                    "confluence.oauth_client_secret",
+                    "required with auth_type = \"oauth\" (or set CONFLUENCE_OAUTH_CLIENT_SECRET)",
+                ));
+            }
+        }
+        other => {
+            if other != "token" {
+                diagnostics.push(Diagnostic::warning(
+                    "confluence.auth_type",
+                    format!(
+                        "unknown auth type '{}', treated as \"token\"; expected token or oauth",
+                        other
+                    ),
+                ));
+            }
+            if confluence.api_token.is_none() {
+                diagnostics.push(Diagnostic::error(
+                    "confluence.api_token",
+                    "required with base_url unless auth_type = \"oauth\"",
+                ));
+            } else if confluence.username.is_none()
+                && confluence
+                    .base_url
+                    .as_deref()
+                    .is_some_and(|url| url.contains(".atlassian.net"))
+            {
+                diagnostics.push(Diagnostic::warning(
+                    "confluence.username",
+                    "Confluence Cloud API tokens are sent with the account email as username",
+                ));
+            }
+        }
+    }
+}
+
+fn check_issues(
+    issues: &IssuesConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let token = issues.jira_api_token.is_some() || lookup("JIRA_API_TOKEN").is_some();
+    let jira = [
+        ("issues.jira_base_url", issues.jira_base_url.is_some()),
+        ("issues.jira_username", issues.jira_username.is_some()),
+        ("issues.jira_api_token", token),
+    ];
+    // A token from the environment alone does not configure Jira
+    let configured = issues.jira_base_url.is_some() || issues.jira_username.is_some();
+    if configured {
+        for (key, _) in jira.iter().filter(|(_, set)| !set) {
+            diagnostics.push(Diagnostic::error(
+                *key,
+                "Jira lookups need jira_base_url, jira_username and jira_api_token (or JIRA_API_TOKEN)",
+            ));
+        }
+    } else if issues.comment_on_jira {
+        diagnostics.push(Diagnostic::warning(
+            "issues.comment_on_jira",
+            "has no effect without jira_base_url, jira_username and jira_api_token",
+        ));
+    }
+}
+
+fn check_storage(
+    storage: &StorageConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match storage.backend.to_lowercase().as_str() {
+        "sqlite" => {
+            if storage.database_url.is_some() {
+                diagnostics.push(Diagnostic::warning(
+                    "storage.database_url",
+                    "only used with backend = \"postgres\"",
+                ));
+            }
+        }
+        "postgres" => {
+            if storage.database_url.is_none() && lookup("KTME_DATABASE_URL").is_none() {
+                diagnostics.push(Diagnostic::error(
+                    "storage.database_url",
+                    "required with backend = \"postgres\" (or set KTME_DATABASE_URL)",
+                ));
+            }
+        }
+        other => diagnostics.push(Diagnostic::error(
+            "storage.backend",
+            format!("unknown backend '{}'; expected sqlite or postgres", other),
+        )),
+    }
+
+    if storage.encrypt
+        && storage.passphrase_command.is_none()
+        && lookup("KTME_DB_PASSPHRASE").is_none()
+    {
+        diagnostics.push(Diagnostic::error(
+            "storage.passphrase_command",
+            "required with encrypt = true (or set KTME_DB_PASSPHRASE)",
+        ));
+    }
+}
+
+fn check_ai(
+    ai: &AiConfig,
+    key: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let has_key = |variable: &str| ai.api_key.is_some() || lookup(variable).is_some();
+    match ai.provider.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("mock") => {}
+        Some("openai") => {
+            // OpenAI-compatible gateways may not need a key
+            let base_url = ai.base_url.is_some() || lookup("OPENAI_BASE_URL").is_some();
+            if !base_url && !has_key("OPENAI_API_KEY") {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.api_key", key),
+                    "required with provider = \"openai\" (or set OPENAI_API_KEY)",
+                ));
+            }
+        }
+        Some("anthropic") | Some("claude") => {
+            if !has_key("ANTHROPIC_API_KEY") {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.api_key", key),
+                    "required with provider = \"anthropic\" (or set ANTHROPIC_API_KEY)",
+                ));
+            }
+            if ai.base_url.is_some() {
+                diagnostics.push(Diagnostic::warning(
+                    format!("{}.base_url", key),
+                    "only used by OpenAI-compatible providers",
+                ));
+            }
+        }
+        Some(name) => {
+            if crate::plugins::find(crate::ai::providers::PLUGIN_PREFIX, name).is_none() {
+                diagnostics.push(Diagnostic::warning(
+                    format!("{}.provider", key),
+                    format!(
+                        "no built-in provider '{}' and no {}{} plugin on PATH",
+                        name,
+                        crate::ai::providers::PLUGIN_PREFIX,
+                        name
+                    ),
+                ));
+            }
+        }
+    }
+
+    for (index, rule) in ai.routing.rules.iter().enumerate() {
+        if let (Some(min), Some(max)) = (rule.min_tokens, rule.max_tokens) {
+            if min > max {
+                diagnostics.push(Diagnostic::error(
+                    format!("{}.routing.rules[{}]", key, index),
+                    format!("min_tokens {} is above max_tokens {}", min, max),
+                ));
+            }
+        }
+    }
+    for (index, fallback) in ai.fallback.iter().enumerate() {
+        check_ai(
+            fallback,
+            &format!("{}.fallback[{}]", key, index),
+            lookup,
+            diagnostics,
+        );
+    }
+    if let Some(summary) = &ai.summary {
+        check_ai(summary, &format!("{}.summary", key), lookup, diagnostics);
+    }
+}
+
+/// Message of a configuration error, without the error kind
+fn reason(error: KtmeError) -> String {
+    match error {
+        KtmeError::Config(message) | KtmeError::InvalidInput(message) => message,
+        other => other.to_string(),
+    }
+}
+
+/// Part of the path to an ignored key
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn segments(path: &serde_ignored::Path<'_>) -> Vec<Segment> {
+    match path {
+        serde_ignored::Path::Root => Vec::new(),
+        serde_ignored::Path::Seq { parent, index } => {
+            let mut segments = segments(parent);
+            segments.push(Segment::Index(*index));
+            segments
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            let mut segments = segments(parent);
+            segments.push(Segment::Key(key.clone()));
+            segments
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => segments(parent),
+    }
+}
+
+fn unknown_key(path: &[Segment]) -> Diagnostic {
+    let mut key = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(name) if key.is_empty() => key.push_str(name),
+            Segment::Key(name) => key.push_str(&format!(".{}", name)),
+            Segment::Index(index) => key.push_str(&format!("[{}]", index)),
+        }
+    }
+
+    let (parent, name) = match path.split_last() {
+        Some((Segment::Key(name), parent)) => (parent, name.as_str()),
+        _ => return Diagnostic::warning(key, "unknown key"),
+    };
+    let message = match suggest(name, known_keys(parent)) {
+        Some(suggestion) => format!("unknown key; did you mean `{}`?", suggestion),
+        None => "unknown key".to_string(),
+    };
+    Diagnostic::warning(key, message)
+}
+
+/// Closest of `candidates` to the misspelled `name`
+fn suggest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let limit = (name.len() / 3).max(1);
+    candidates
+        .iter()
+        .map(|candidate| (strsim::damerau_levenshtein(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Keys of the table at `path`
+fn known_keys(path: &[Segment]) -> &'static [&'static str] {
+    let path: Vec<&str> = path
+        .iter()
+        .map(|segment| match segment {
+            Segment::Key(name) => name.as_str(),
+            Segment::Index(_) => "[]",
+        })
+        .collect();
+    match path.as_slice() {
+        [] => fields_of::<Config>(),
+        ["general"] => fields_of::<GeneralConfig>(),
+        ["git"] => fields_of::<GitConfig>(),
+        ["git", "github_hosts", "[]"] => fields_of::<GitHubHostConfig>(),
+        ["mcp"] => fields_of::<McpConfig>(),
+        ["documentation"] => fields_of::<DocumentationConfig>(),
+        ["confluence"] => fields_of::<ConfluenceConfig>(),
+        ["storage"] => fields_of::<StorageConfig>(),
+        ["storage", "workspaces", _] => fields_of::<WorkspaceConfig>(),
+        ["issues"] => fields_of::<IssuesConfig>(),
+        ["plugins"] => fields_of::<PluginsConfig>(),
+        ["timeouts"] => fields_of::<TimeoutsConfig>(),
+        ["policies", _] => fields_of::<PolicyConfig>(),
+        ["notices"] => fields_of::<NoticesConfig>(),
+        ["notices", "rules", "[]"] => fields_of::<NoticeRule>(),
+        ["ai", rest @ ..] => known_ai_keys(rest),
+        _ => &[],
+    }
+}
+
+fn known_ai_keys(path: &[&str]) -> &'static [&'static str] {
+    match path {
+        [] => fields_of::<AiConfig>(),
+        ["fallback", "[]", rest @ ..] | ["summary", rest @ ..] => known_ai_keys(rest),
+        ["routing"] => fields_of::<RoutingConfig>(),
+        ["routing", "rules", "[]"] => fields_of::<RoutingRule>(),
+        _ => &[],
+    }
+}
+
+/// Field names of the struct `T`, as its `Deserialize` implementation
+/// declares them
+fn fields_of<T: DeserializeOwned>() -> &'static [&'static str] {
+    struct Probe<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> de::Deserializer<'de> for Probe<'_> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(
+            self,
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> std::result::Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Probe(&mut fields));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        (name == "CONFLUENCE_TOKEN").then(|| "secret".to_string())
+    }
+
+    fn messages(report: &Report) -> Vec<(Severity, &str, &str)> {
+        report
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.key.as_str(), d.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_accepts_a_complete_config() {
+        let report = check(
+            r#"
+            [confluence]
+            base_url = "https://wiki.example.com"
+            space_key = "DOCS"
+            username = "docs-bot"
+            api_token = "${CONFLUENCE_TOKEN}"
+            "#,
+            &lookup,
+        );
+
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+        let config = report.config.unwrap();
+        assert_eq!(config.confluence.api_token.as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn test_check_reports_unknown_keys_with_suggestions() {
+        let report = check(
+            r#"
+            [confluence]
+            base_url = "https://wiki.example.com"
+            spce_key = "DOCS"
+            api_token = "x"
+
+            [[ai.fallback]]
+            provider = "mock"
+            modle = "gpt-4"
+
+            [colour]
+            enabled = true
+
+            [notices]
+            footer = "*Generated for {servce}*"
+
+            [[notices.rules]]
+            locaton = "docs/public/"
+            enabled = false
+            "#,
+            &lookup,
+        );
+
+        let diagnostics = messages(&report);
+        assert!(diagnostics.contains(&(
+            Severity::Warning,
+            "confluence.spce_key",
+            "unknown key; did you mean `space_key`?"
+        )));
+        assert!(diagnostics.contains(&(
+            Severity::Warning,
+            "ai.fallback[0].modle",
+            "unknown key; did you mean `model`?"
+        )));
+        assert!(diagnostics.contains(&(Severity::Warning, "colour", "unknown key")));
+        assert!(diagnostics.contains(&(
+            Severity::Warning,
+            "notices.rules[0].locaton",
+            "unknown key; did you mean `location`?"
+        )));
+        assert!(diagnostics.iter().any(|(severity, key, message)| {
+            *severity == Severity::Warning
+                && *key == "notices.footer"
+                && message.starts_with("unknown placeholder {servce}")
+        }));
+        // The misspelled space key leaves base_url without its counterpart
+        assert!(diagnostics.iter().any(|(severity, key, _)| {
+            *severity == Severity::Error && *key == "confluence.space_key"
+        }));
+    }
+
+    #[test]
+    fn test_check_reports_unset_variables_and_missing_pairs() {
+        let report = check(
+            r#"
+            [storage]
+            backend = "postgres"
+
+            [issues]
+            jira_base_url = "https://jira.example.com"
+            jira_api_token = "${JIRA_TOKEN}"
+
+            [timeouts]
+            connect = "soon"
+            "#,
+            &lookup,
+        );
+
+        let keys: Vec<(Severity, &str)> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.key.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                (Severity::Error, "issues.jira_api_token"),
+                (Severity::Error, "timeouts.connect"),
+                (Severity::Error, "issues.jira_username"),
+                (Severity::Error, "storage.database_url"),
+            ]
+        );
+        assert_eq!(
+            report.diagnostics[0].message,
+            "environment variable JIRA_TOKEN is not set"
+        );
+        assert_eq!(report.count(Severity::Error), 4);
+    }
+
+    #[test]
+    fn test_check_reports_values_that_do_not_deserialize() {
+        let report = check("[git]\nmax_commit_range = \"many\"\n", &lookup);
+
+        assert!(report.config.is_none());
+        assert_eq!(report.count(Severity::Error), 1);
+    }
+
+    #[test]
+    fn test_fields_of_lists_the_struct_keys() {
+        assert!(fields_of::<ConfluenceConfig>().contains(&"space_key"));
+        assert!(fields_of::<Config>().contains(&"timeouts"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Developer annotations left in the code
+//!
+//! A `ktme:doc <section>` comment marks nearby code as worth documenting,
+//! optionally with a note after a colon:
+//!
+//! ```text
+//! // ktme:doc Retries: failed deliveries are retried three times
+//! fn deliver(event: &Event) -> Result<()> {
+//! ```
+//!
+//! Annotations in the added and unchanged lines of a diff are passed to the
+//! prompt and listed under "Developer notes" in the generated documentation.
+
+use crate::git::diff::ExtractedDiff;
+use serde::Serialize;
+
+/// Marker that starts an annotation inside a comment
+pub const MARKER: &str = "ktme:doc";
+
+/// Comment openers an annotation may follow, longest first
+const COMMENT_OPENERS: &[&str] = &["<!--", "///", "//!", "//", "/*", "--", "#", ";", "*"];
+
+/// Comment closers stripped from the end of an annotation
+const COMMENT_CLOSERS: &[&str] = &["-->", "*/"];
+
+/// One `ktme:doc` annotation of a changed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Annotation {
+    pub path: String,
+    pub section: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// First line of code after the annotation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+/// Annotations of the changed files of `diff`, in diff order; lines removed
+/// by the diff are not scanned
+pub fn collect(diff: &ExtractedDiff) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    for file in &diff.files {
+        let mut pending: Option<Annotation> = None;
+        for text in file.diff.lines().filter_map(source_line) {
+            if let Some((section, note)) = parse(text) {
+                if let Some(annotation) = pending.take() {
+                    keep(&mut annotations, annotation);
+                }
+                pending = Some(Annotation {
+                    path: file.path.clone(),
+                    section,
+                    note,
+                    code: None,
+                });
+            } else if text.trim().is_empty() || comment_body(text).is_some() {
+                continue;
+            } else if let Some(mut annotation) = pending.take() {
+                annotation.code = Some(text.trim().to_string());
+                keep(&mut annotations, annotation);
+            }
+        }
+        if let Some(annotation) = pending {
+            keep(&mut annotations, annotation);
+        }
+    }
+    annotations
+}
+
+/// Add `annotation` unless an identical one was already collected, e.g.
+/// from another commit of a range
+fn keep(annotations: &mut Vec<Annotation>, annotation: Annotation) {
+    if !annotations.contains(&annotation) {
+        annotations.push(annotation);
+    }
+}
+
+/// Annotations as prompt context; empty when there are none
+pub fn prompt_context(annotations: &[Annotation]) -> String {
+    if annotations.is_empty() {
+        return String::new();
+    }
+    let mut context = String::from(
+        "\n\nDeveloper annotations (left in the code by its authors; cover each in the section it names):",
+    );
+    for annotation in annotations {
+        context.push_str(&format!("\n- [{}] {}", annotation.section, annotation.path));
+        if let Some(note) = &annotation.note {
+            context.push_str(&format!(": {}", note));
+        }
+        if let Some(code) = &annotation.code {
+            context.push_str(&format!(" (at `{}`)", code));
+        }
+    }
+    context
+}
+
+/// Annotations as a Markdown list grouped by section, without a heading
+pub fn render_list(annotations: &[Annotation]) -> String {
+    let mut sections: Vec<(&str, Vec<&Annotation>)> = Vec::new();
+    for annotation in annotations {
+        match sections.iter_mut().find(|(s, _)| *s == annotation.section) {
+            Some((_, entries)) => entries.push(annotation),
+            None => sections.push((&annotation.section, vec![annotation])),
+        }
+    }
+
+    let mut list = String::new();
+    for (section, entries) in sections {
+        list.push_str(&format!("### {}\n\n", section));
+        for annotation in entries {
+            let location = match &annotation.code {
+                Some(code) => format!("`{}` in `{}`", code, annotation.path),
+                None => format!("`{}`", annotation.path),
+            };
+            match &annotation.note {
+                Some(note) => list.push_str(&format!("- {} ({})\n", note, location)),
+                None => list.push_str(&format!("- {}\n", location)),
+            }
+        }
+        list.push('\n');
+    }
+    list
+}
+
+/// "Developer notes" section appended to generated documentation
+pub fn render_section(annotations: &[Annotation]) -> String {
+    if annotations.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n\n## Developer notes\n\n{}",
+        render_list(annotations).trim_end()
+    )
+}
+
+/// Text of a diff line that is in the new version of the file: added and
+/// unchanged lines without their prefix
+fn source_line(line: &str) -> Option<&str> {
+    if ["+++ ", "--- ", "@@", "diff ", "index ", "\\"]
+        .iter()
+        .any(|header| line.starts_with(header))
+    {
+        return None;
+    }
+    match line.chars().next() {
+        Some('+') | Some(' ') => Some(&line[1..]),
+        Some('-') => None,
+        _ => Some(line),
+    }
+}
+
+/// Text of a comment line after its opener
+fn comment_body(text: &str) -> Option<&str> {
+    let text = text.trim_start();
+    COMMENT_OPENERS
+        .iter()
+        .find_map(|opener| text.strip_prefix(opener))
+        .map(str::trim)
+}
+
+/// Section and note of an annotation comment
+fn parse(text: &str) -> Option<(String, Option<String>)> {
+    let rest = comment_body(text)?.strip_prefix(MARKER)?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = COMMENT_CLOSERS
+        .iter()
+        .fold(rest.trim(), |rest, closer| {
+            rest.strip_suffix(closer).unwrap_or(rest)
+        })
+        .trim();
+    let (section, note) = match rest.split_once(':') {
+        Some((section, note)) => (section.trim(), Some(note.trim())),
+        None => (rest, None),
+    };
+    if section.is_empty() {
+        return None;
+    }
+    Some((
+        section.to_string(),
+        note.filter(|note| !note.is_empty()).map(str::to_string),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::FileChange;
+
+    fn file(path: &str, diff: &str) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            old_path: None,
+            status: "modified".to_string(),
+            additions: 0,
+            deletions: 0,
+            diff: diff.to_string(),
+            truncated: None,
+        }
+    }
+
+    #[test]
+    fn test_collect_annotations() {
+        let diff = ExtractedDiff {
+            files: vec![
+                file(
+                    "src/webhooks.rs",
+                    "@@ -1,4 +1,7 @@\n \
+                     use crate::Event;\n\
+                     +// ktme:doc Retries: failed deliveries are retried three times\n\
+                     +/// Deliver one event\n\
+                     +fn deliver(event: &Event) {}\n\
+                     -// ktme:doc Removed: no longer true\n \
+                     // ktme:docs is not a marker\n",
+                ),
+                file(
+                    "migrations/003.sql",
+                    "+-- ktme:doc Schema\n+CREATE TABLE retries (id INTEGER);\n+<!-- ktme:doc Retries -->\n",
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let annotations = collect(&diff);
+        assert_eq!(
+            annotations,
+            vec![
+                Annotation {
+                    path: "src/webhooks.rs".to_string(),
+                    section: "Retries".to_string(),
+                    note: Some("failed deliveries are retried three times".to_string()),
+                    code: Some("fn deliver(event: &Event) {}".to_string()),
+                },
+                Annotation {
+                    path: "migrations/003.sql".to_string(),
+                    section: "Schema".to_string(),
+                    note: None,
+                    code: Some("CREATE TABLE retries (id INTEGER);".to_string()),
+                },
+                Annotation {
+                    path: "migrations/003.sql".to_string(),
+                    section: "Retries".to_string(),
+                    note: None,
+                    code: None,
+                },
+            ]
+        );
+
+        let section = render_section(&annotations);
+        assert!(section.starts_with("\n\n## Developer notes\n\n### Retries\n\n"));
+        assert!(section.contains(
+            "- failed deliveries are retried three times (`fn deliver(event: &Event) {}` in `src/webhooks.rs`)\n- `migrations/003.sql`\n\n### Schema"
+        ));
+        assert!(prompt_context(&annotations).contains("- [Schema] migrations/003.sql"));
+        assert_eq!(render_section(&[]), "");
+    }
+}
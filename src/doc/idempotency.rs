@@ -0,0 +1,131 @@
+//! Idempotency keys of generated documentation
+//!
+//! A [`GenerationKey`] names what a generation documents and how: the source
+//! changes, the template, the model and the options that shape the output.
+//! It is computed before the AI call and stored with the generation history,
+//! so a publish whose key already succeeded for a location is skipped by
+//! `ktme generate --publish`, `ktme update` and the MCP workflow alike.
+
+use crate::git::diff::ExtractedDiff;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Canonical inputs of a generation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationKey {
+    source: String,
+    template: String,
+    model: String,
+    options: BTreeMap<String, String>,
+}
+
+impl GenerationKey {
+    /// Key of documenting `diff` with `template` and `model`
+    pub fn new(diff: &ExtractedDiff, template: &str, model: Option<&str>) -> Self {
+        Self {
+            source: source_of(diff),
+            template: template.to_string(),
+            model: model.unwrap_or_default().to_string(),
+            options: BTreeMap::new(),
+        }
+    }
+
+    /// Add an option that changes the output, e.g. the document type or the
+    /// target section; `None` leaves the key unchanged
+    pub fn option(mut self, name: &str, value: Option<&str>) -> Self {
+        if let Some(value) = value {
+            self.options.insert(name.to_string(), value.to_string());
+        }
+        self
+    }
+
+    /// SHA-256 of the canonical form, as stored in the generation history
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        let options = self
+            .options
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+        let parts = [
+            ("source", self.source.as_str()),
+            ("template", self.template.as_str()),
+            ("model", self.model.as_str()),
+        ];
+        for (name, value) in parts.into_iter().chain(options) {
+            hasher.update(name.as_bytes());
+            hasher.update([b'=']);
+            hasher.update(value.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Identifier of the changes in `diff`: the commit, range or pull request,
+/// or for staged changes (all identified as `staged`) a hash of the diff
+fn source_of(diff: &ExtractedDiff) -> String {
+    if diff.source != "staged" {
+        return format!("{}:{}", diff.source, diff.identifier);
+    }
+    let mut hasher = Sha256::new();
+    for file in &diff.files {
+        hasher.update(file.path.as_bytes());
+        hasher.update([0]);
+        hasher.update(file.diff.as_bytes());
+        hasher.update([0]);
+    }
+    format!("staged:{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::FileChange;
+
+    fn diff(source: &str, identifier: &str, change: &str) -> ExtractedDiff {
+        ExtractedDiff {
+            source: source.to_string(),
+            identifier: identifier.to_string(),
+            files: vec![FileChange {
+                path: "src/lib.rs".to_string(),
+                old_path: None,
+                status: "modified".to_string(),
+                additions: 1,
+                deletions: 0,
+                diff: change.to_string(),
+                truncated: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generation_key_is_canonical() {
+        let commit = diff("commit", "3f2a9c1", "+fn a() {}");
+        let key = GenerationKey::new(&commit, "changelog", Some("gpt-4o"))
+            .option("section", Some("Payments"))
+            .option("mode", Some("append"));
+        let reordered = GenerationKey::new(&commit, "changelog", Some("gpt-4o"))
+            .option("mode", Some("append"))
+            .option("section", Some("Payments"))
+            .option("language", None);
+        assert_eq!(key.hash(), reordered.hash());
+
+        // Any input that changes the output changes the key
+        let other_model = GenerationKey::new(&commit, "changelog", Some("gpt-4o-mini"))
+            .option("section", Some("Payments"))
+            .option("mode", Some("append"));
+        assert_ne!(key.hash(), other_model.hash());
+        let other_section = GenerationKey::new(&commit, "changelog", Some("gpt-4o"))
+            .option("section", Some("Billing"))
+            .option("mode", Some("append"));
+        assert_ne!(key.hash(), other_section.hash());
+
+        // Staged changes are told apart by their diff
+        let staged = GenerationKey::new(&diff("staged", "staged", "+a"), "general", None);
+        let same = GenerationKey::new(&diff("staged", "staged", "+a"), "general", None);
+        let changed = GenerationKey::new(&diff("staged", "staged", "+b"), "general", None);
+        assert_eq!(staged.hash(), same.hash());
+        assert_ne!(staged.hash(), changed.hash());
+    }
+}
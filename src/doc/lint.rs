@@ -0,0 +1,137 @@
+//! Checks of generated Markdown before it is published
+//!
+//! Errors mark documentation that would render broken (an unclosed code
+//! fence, an unbalanced link, a template placeholder left in); `ktme run`
+//! does not publish it unless the service's policy turns linting off.
+//! Warnings point at missing structure and never block a publish.
+
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem found in a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+impl Finding {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+/// Findings of the Markdown `content`, errors first
+pub fn check(content: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if content.trim().is_empty() {
+        findings.push(Finding::error("document is empty"));
+        return findings;
+    }
+
+    // Code blocks are left out of the other checks
+    let mut fences = 0;
+    let mut prose = String::new();
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            fences += 1;
+        } else if fences % 2 == 0 {
+            prose.push_str(line);
+            prose.push('\n');
+        }
+    }
+    if fences % 2 == 1 {
+        findings.push(Finding::error("code block is not closed"));
+    }
+
+    let open = prose.matches('[').count();
+    let close = prose.matches(']').count();
+    if open != close {
+        findings.push(Finding::error(format!(
+            "unbalanced brackets ({} '[' and {} ']'), likely a broken link",
+            open, close
+        )));
+    }
+    if let Some(start) = prose.find("{{") {
+        let placeholder: String = prose[start..].chars().take(40).collect();
+        let placeholder = placeholder.lines().next().unwrap_or_default();
+        findings.push(Finding::error(format!(
+            "template placeholder left in the document: {}",
+            placeholder
+        )));
+    }
+
+    if !prose.lines().any(|line| line.starts_with("# ")) {
+        findings.push(Finding::warning("missing title header"));
+    }
+    if !prose.lines().any(|line| line.starts_with("## ")) {
+        findings.push(Finding::warning("no sections found"));
+    }
+
+    findings.sort_by_key(|finding| finding.severity != Severity::Error);
+    findings
+}
+
+/// Whether any of `findings` is an error
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_document_has_no_findings() {
+        let content =
+            "# Payments\n\n## Changes\n\nSee [the API](api.md).\n\n```rust\nlet x = [1, 2;\n```\n";
+        assert!(check(content).is_empty());
+    }
+
+    #[test]
+    fn test_broken_markdown_is_reported_as_errors() {
+        let findings = check("Intro for {{service}}\n\nSee [the API(api.md).\n\n```\ncode\n");
+        assert!(has_errors(&findings));
+        let messages: Vec<_> = findings.iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "error: code block is not closed",
+                "error: unbalanced brackets (1 '[' and 0 ']'), likely a broken link",
+                "error: template placeholder left in the document: {{service}}",
+                "warning: missing title header",
+                "warning: no sections found",
+            ]
+        );
+        assert_eq!(check("  \n"), vec![Finding::error("document is empty")]);
+    }
+}
@@ -1,4 +1,12 @@
+pub mod annotations;
 pub mod generator;
+pub mod idempotency;
+pub mod lint;
+pub mod notices;
+pub mod personas;
 pub mod providers;
+pub mod redact;
+pub mod sections;
 pub mod templates;
 pub mod writers;
+pub mod xhtml;
@@ -0,0 +1,374 @@
+//! Header and footer notices of published documents
+//!
+//! `[notices]` configures the blocks ktme keeps above and below the
+//! documents it publishes, set apart from the content by a horizontal rule.
+//! Blocks added before are recognized by their templates, so publishing
+//! again replaces them rather than adding another copy, and removes them
+//! where notices are disabled.
+
+use crate::config::{NoticeRule, NoticesConfig};
+use crate::doc::sections::{DocKind, SectionFormat};
+use crate::doc::writers::confluence::ConfluenceWriter;
+use crate::doc::xhtml;
+use crate::git::commit::source_commit;
+use crate::storage::mapping::DocumentLocation;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Footers of the documents created by `ktme init` before notices were
+/// configurable
+const LEGACY_FOOTERS: &[&str] = &[
+    "*Generated by ktme*",
+    "*This documentation is maintained using [ktme](https://github.com/FreePeak/ktme)*",
+];
+
+/// Placeholders filled into the notice templates
+pub const PLACEHOLDERS: &[&str] = &["service", "doc_type", "date", "commit", "commit_url"];
+
+/// What the placeholders of the notices refer to
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoticeContext<'a> {
+    pub service: Option<&'a str>,
+    /// Document type; inferred from the location when unset
+    pub doc_type: Option<&'a str>,
+    /// Commit (or other source) the documentation was generated from
+    pub source: Option<&'a str>,
+}
+
+/// Notices of one document
+#[derive(Debug, Clone)]
+pub struct Notices {
+    header: Option<String>,
+    footer: Option<String>,
+    /// Whether notices are configured at all; documents are left alone
+    /// otherwise
+    managed: bool,
+    /// Lines of the notices ktme may have added before
+    known: Vec<Regex>,
+}
+
+impl Notices {
+    /// Notices `config` gives the document at `location`
+    pub fn for_location(
+        config: &NoticesConfig,
+        location: &DocumentLocation,
+        context: &NoticeContext,
+    ) -> Self {
+        let doc_type = context
+            .doc_type
+            .unwrap_or_else(|| DocKind::infer(&location.location).name());
+        let mut enabled = config.enabled;
+        let mut header = config.header.as_deref();
+        let mut footer = config.footer.as_deref();
+        for rule in config
+            .rules
+            .iter()
+            .filter(|rule| matches(rule, location, doc_type))
+        {
+            enabled = rule.enabled.unwrap_or(enabled);
+            header = rule.header.as_deref().or(header);
+            footer = rule.footer.as_deref().or(footer);
+        }
+        let (header, footer) = if enabled {
+            (header, footer)
+        } else {
+            (None, None)
+        };
+
+        let commit = context
+            .source
+            .filter(|_| {
+                [header, footer]
+                    .iter()
+                    .flatten()
+                    .any(|t| t.contains("{commit"))
+            })
+            .and_then(source_commit);
+        let values = HashMap::from([
+            ("service", context.service.map(str::to_string)),
+            ("doc_type", Some(doc_type.to_string())),
+            (
+                "date",
+                Some(chrono::Utc::now().format("%Y-%m-%d").to_string()),
+            ),
+            ("commit", commit.as_ref().map(|(id, _)| id.clone())),
+            ("commit_url", commit.and_then(|(_, url)| url)),
+        ]);
+        Self {
+            header: header.and_then(|template| render(template, &values)),
+            footer: footer.and_then(|template| render(template, &values)),
+            managed: !config.enabled
+                || config.header.is_some()
+                || config.footer.is_some()
+                || !config.rules.is_empty(),
+            known: known_lines(config),
+        }
+    }
+
+    /// `document` with its notices replaced by these
+    pub fn apply(&self, document: &str, format: SectionFormat) -> String {
+        if !self.managed {
+            return document.to_string();
+        }
+        let body = self.body(document, format);
+        if self.header.is_none() && self.footer.is_none() && body == (0..document.len()) {
+            return document.to_string();
+        }
+        let body = document[body].trim();
+        match format {
+            SectionFormat::Markdown => {
+                let mut out = String::new();
+                if let Some(header) = &self.header {
+                    out.push_str(&format!("{}\n\n---\n\n", header));
+                }
+                out.push_str(body);
+                if let Some(footer) = &self.footer {
+                    out.push_str(&format!("\n\n---\n\n{}", footer));
+                }
+                format!("{}\n", out.trim_start())
+            }
+            SectionFormat::Storage => {
+                let storage = ConfluenceWriter::markdown_to_storage_format;
+                let header = self.header.as_deref().map(storage).map(|h| h + "<hr/>");
+                let footer = self
+                    .footer
+                    .as_deref()
+                    .map(|f| format!("<hr/>{}", storage(f)));
+                format!(
+                    "{}{}{}",
+                    header.unwrap_or_default(),
+                    body,
+                    footer.unwrap_or_default()
+                )
+            }
+        }
+    }
+
+    /// `document` without the notices ktme added
+    pub fn strip(&self, document: &str, format: SectionFormat) -> String {
+        if !self.managed {
+            return document.to_string();
+        }
+        document[self.body(document, format)].to_string()
+    }
+
+    /// `document` updated by `update` without its notices, which then get
+    /// added again, so new content never ends up below the footer
+    pub fn update(
+        &self,
+        document: &str,
+        format: SectionFormat,
+        update: impl FnOnce(&str) -> String,
+    ) -> String {
+        self.apply(&update(&self.strip(document, format)), format)
+    }
+
+    /// Range of `document` between its notices
+    fn body(&self, document: &str, format: SectionFormat) -> Range<usize> {
+        let rules = rules(document, format);
+        let mut body = 0..document.len();
+        if let Some(first) = rules.first() {
+            if self.is_notice(&document[..first.start], format) {
+                body.start = first.end;
+            }
+        }
+        if let Some(last) = rules.last().filter(|last| last.start >= body.start) {
+            if self.is_notice(&document[last.end..], format) {
+                body.end = last.start;
+            }
+        }
+        body
+    }
+
+    /// Whether every line of `text` is a line of a notice
+    fn is_notice(&self, text: &str, format: SectionFormat) -> bool {
+        let text = match format {
+            SectionFormat::Markdown => text.to_string(),
+            SectionFormat::Storage => xhtml::to_markdown(text),
+        };
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        lines.next().is_some_and(|first| {
+            std::iter::once(first)
+                .chain(lines)
+                .all(|line| self.known.iter().any(|known| known.is_match(line)))
+        })
+    }
+}
+
+fn matches(rule: &NoticeRule, location: &DocumentLocation, doc_type: &str) -> bool {
+    rule.provider
+        .as_deref()
+        .is_none_or(|provider| provider.eq_ignore_ascii_case(&location.r#type))
+        && rule.doc_type.as_deref().is_none_or(|t| t == doc_type)
+        && rule.location.as_deref().is_none_or(|part| {
+            location
+                .location
+                .to_lowercase()
+                .contains(&part.to_lowercase())
+        })
+}
+
+/// `template` with its placeholders filled in from `values`, leaving out
+/// the lines with an unknown value; `None` when nothing is left
+fn render(template: &str, values: &HashMap<&str, Option<String>>) -> Option<String> {
+    let lines: Vec<String> = template
+        .lines()
+        .filter_map(|line| {
+            values
+                .iter()
+                .try_fold(line.to_string(), |line, (name, value)| {
+                    let placeholder = format!("{{{}}}", name);
+                    match value {
+                        _ if !line.contains(&placeholder) => Some(line),
+                        Some(value) => Some(line.replace(&placeholder, value)),
+                        None => None,
+                    }
+                })
+        })
+        .collect();
+    let text = lines.join("\n\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Patterns of the lines of every notice `config` can produce. Lines made
+/// of placeholders only would match any text and are left out.
+fn known_lines(config: &NoticesConfig) -> Vec<Regex> {
+    let rules = config
+        .rules
+        .iter()
+        .flat_map(|rule| [rule.header.as_deref(), rule.footer.as_deref()]);
+    [config.header.as_deref(), config.footer.as_deref()]
+        .into_iter()
+        .chain(rules)
+        .flatten()
+        .chain(LEGACY_FOOTERS.iter().copied())
+        .flat_map(str::lines)
+        .filter_map(|line| {
+            let literal = placeholder().replace_all(line.trim(), "");
+            if literal.trim().is_empty() {
+                return None;
+            }
+            let escaped = regex::escape(line.trim());
+            let pattern = placeholder().replace_all(&escaped, ".*");
+            Regex::new(&format!("^{}$", pattern)).ok()
+        })
+        .collect()
+}
+
+/// Names of the placeholders in `template`
+pub fn placeholders(template: &str) -> Vec<&str> {
+    placeholder()
+        .find_iter(template)
+        .map(|m| m.as_str().trim_matches(['\\', '{', '}']))
+        .collect()
+}
+
+/// A `{name}` placeholder, also once escaped by `regex::escape`
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\\?\{[a-z_]+\\?\}").expect("valid placeholder pattern"))
+}
+
+/// Byte ranges of the horizontal rules of `document`
+fn rules(document: &str, format: SectionFormat) -> Vec<Range<usize>> {
+    match format {
+        SectionFormat::Markdown => {
+            let mut offset = 0;
+            let mut rules = Vec::new();
+            for line in document.split_inclusive('\n') {
+                if line.trim() == "---" {
+                    rules.push(offset..offset + line.len());
+                }
+                offset += line.len();
+            }
+            rules
+        }
+        SectionFormat::Storage => {
+            static RULE: OnceLock<Regex> = OnceLock::new();
+            RULE.get_or_init(|| Regex::new(r"<hr\s*/?>").expect("valid rule pattern"))
+                .find_iter(document)
+                .map(|m| m.range())
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(r#type: &str, location: &str) -> DocumentLocation {
+        DocumentLocation {
+            r#type: r#type.to_string(),
+            location: location.to_string(),
+            section: None,
+        }
+    }
+
+    fn config() -> NoticesConfig {
+        NoticesConfig {
+            header: Some("**Confidential** - internal use only".to_string()),
+            footer: Some(
+                "*Generated by ktme for {service} on {date}*\nSource: {commit_url}".to_string(),
+            ),
+            rules: vec![NoticeRule {
+                location: Some("docs/public/".to_string()),
+                enabled: Some(false),
+                ..NoticeRule::default()
+            }],
+            ..NoticesConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_notices_are_replaced_not_repeated() {
+        let context = NoticeContext {
+            service: Some("payments"),
+            ..NoticeContext::default()
+        };
+        let notices =
+            Notices::for_location(&config(), &location("markdown", "docs/api.md"), &context);
+        let document = "# API\n\nIntro.\n\n---\n\n*Generated by ktme*\n";
+
+        let published = notices.apply(document, SectionFormat::Markdown);
+        let date = chrono::Utc::now().format("%Y-%m-%d");
+        assert_eq!(
+            published,
+            format!(
+                "**Confidential** - internal use only\n\n---\n\n# API\n\nIntro.\n\n---\n\n*Generated by ktme for payments on {}*\n",
+                date
+            )
+        );
+        assert_eq!(
+            notices.apply(&published, SectionFormat::Markdown),
+            published
+        );
+        assert_eq!(
+            notices.strip(&published, SectionFormat::Markdown).trim(),
+            "# API\n\nIntro."
+        );
+
+        let storage = notices.apply("<h1>API</h1><p>Intro.</p>", SectionFormat::Storage);
+        assert!(storage.starts_with(
+            "<p><strong>Confidential</strong> - internal use only</p><hr/><h1>API</h1>"
+        ));
+        assert_eq!(notices.apply(&storage, SectionFormat::Storage), storage);
+    }
+
+    #[test]
+    fn test_rules_disable_notices() {
+        let public = location("markdown", "docs/public/guide.md");
+        let notices = Notices::for_location(&config(), &public, &NoticeContext::default());
+        let document = "# Guide\n\n---\n\n*Generated by ktme for payments on 2026-10-01*\n";
+        assert_eq!(
+            notices.apply(document, SectionFormat::Markdown),
+            "# Guide\n"
+        );
+
+        // Content ending in a rule of its own is left alone
+        let own = "# Guide\n\n---\n\nThanks for reading\n";
+        assert_eq!(notices.apply(own, SectionFormat::Markdown), own);
+    }
+}
@@ -0,0 +1,97 @@
+//! Document types that need more than the diff
+//!
+//! Each persona mines the repository for its own context, which is added to
+//! the prompt as "Additional Context".
+
+pub mod onboarding;
+pub mod postmortem;
+pub mod release_notes;
+pub mod security_review;
+pub mod test_plan;
+
+use crate::config::Config;
+use crate::git::diff::{CommitSummary, ExtractedDiff};
+use crate::storage::mapping::{ServiceMapping, StorageManager};
+use onboarding::OnboardingContext;
+use release_notes::Audience;
+use security_review::SecurityReview;
+use std::path::PathBuf;
+use test_plan::TestCoverage;
+
+/// Command-line options of the document types
+#[derive(Debug, Clone, Default)]
+pub struct PersonaOptions {
+    /// Audience of release notes
+    pub audience: Audience,
+    /// Run `cargo audit`/`npm audit` for security reviews
+    pub audit: bool,
+}
+
+/// The commits of `diff`, oldest first; the diff itself for a single commit
+pub fn commits(diff: &ExtractedDiff) -> Vec<CommitSummary> {
+    if !diff.commits.is_empty() {
+        return diff.commits.clone();
+    }
+    vec![CommitSummary {
+        id: diff.identifier.clone(),
+        timestamp: diff.timestamp.clone(),
+        author: diff.author.clone(),
+        message: diff.message.clone(),
+        files: diff.files.iter().map(|f| f.path.clone()).collect(),
+    }]
+}
+
+/// Additional prompt context for a `doc_type` document of `service` about
+/// `diff`; `None` for document types that only need the diff
+pub fn context(
+    config: &Config,
+    doc_type: &str,
+    service: Option<&str>,
+    diff: &ExtractedDiff,
+    options: &PersonaOptions,
+) -> Option<String> {
+    match doc_type {
+        "onboarding" => {
+            let (root, mapping) = service_root(config, service);
+            let docs = mapping.map(|mapping| mapping.docs).unwrap_or_default();
+            Some(OnboardingContext::gather(&root, docs).render())
+        }
+        "postmortem" => Some(postmortem::render(diff)),
+        "release-notes" => Some(release_notes::render(diff, options.audience)),
+        "security-review" => {
+            let mut review = SecurityReview::analyze(diff);
+            if options.audit {
+                review.audit(&service_root(config, service).0);
+            }
+            Some(review.render())
+        }
+        "test-plan" => {
+            let coverage = TestCoverage::analyze(diff);
+            if coverage.lacks_tests() {
+                tracing::warn!(
+                    "{} lines of code changed without any test changes",
+                    coverage.code_lines
+                );
+            }
+            Some(coverage.render())
+        }
+        _ => None,
+    }
+}
+
+/// Directory of `service`: its mapped path when that is a directory,
+/// otherwise the current one; with the mapping, if any
+fn service_root(config: &Config, service: Option<&str>) -> (PathBuf, Option<ServiceMapping>) {
+    let mapping = service.and_then(|service| {
+        StorageManager::from_config(config)
+            .and_then(|manager| manager.get_mapping(service))
+            .ok()
+    });
+    let root = mapping
+        .as_ref()
+        .and_then(|mapping| mapping.path.as_ref())
+        .map(PathBuf::from)
+        .filter(|path| path.is_dir())
+        .unwrap_or_else(|| PathBuf::from("."));
+    (root, mapping)
+}
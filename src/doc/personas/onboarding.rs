@@ -0,0 +1,391 @@
+//! Context for `--type onboarding` guides
+//!
+//! Gathers what a new engineer needs besides the diff: setup commands from
+//! the README, Makefile and CI files, the key modules found by the symbol
+//! scanner, the maintainers from git history and the documentation already
+//! mapped to the service.
+
+use crate::git::ownership::{self, Maintainer};
+use crate::knowledge::scanner::CodebaseScanner;
+use crate::storage::mapping::DocumentLocation;
+use git2::Repository;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// README headings whose code blocks hold setup commands
+const SETUP_HEADINGS: &[&str] = &[
+    "install",
+    "setup",
+    "set up",
+    "getting started",
+    "quick start",
+    "quickstart",
+    "prerequisite",
+    "build",
+    "develop",
+    "running",
+    "test",
+];
+
+/// Commands kept per setup source
+const MAX_STEPS: usize = 15;
+
+/// Modules listed as key modules
+const MAX_MODULES: usize = 10;
+
+/// Symbols listed per key module
+const MAX_SYMBOLS: usize = 5;
+
+/// Lowest scanner confidence of a symbol (comment-derived features are lower)
+const MIN_SYMBOL_CONFIDENCE: f64 = 0.6;
+
+/// Setup commands found in one file
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SetupSource {
+    pub file: String,
+    pub steps: Vec<String>,
+}
+
+/// A source file defining many public types
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct KeyModule {
+    pub path: String,
+    pub symbols: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// Everything mined for an onboarding guide
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OnboardingContext {
+    pub setup: Vec<SetupSource>,
+    pub modules: Vec<KeyModule>,
+    pub maintainers: Vec<Maintainer>,
+    pub docs: Vec<DocumentLocation>,
+}
+
+impl OnboardingContext {
+    /// Mine the service rooted at `root` whose documentation is `docs`
+    pub fn gather(root: &Path, docs: Vec<DocumentLocation>) -> Self {
+        let mut setup = Vec::new();
+        if let Some(readme) = ["README.md", "readme.md", "README"]
+            .iter()
+            .find_map(|name| std::fs::read_to_string(root.join(name)).ok())
+        {
+            push_source(&mut setup, "README.md", readme_steps(&readme));
+        }
+        if let Ok(makefile) = std::fs::read_to_string(root.join("Makefile")) {
+            push_source(&mut setup, "Makefile", make_targets(&makefile));
+        }
+        for (file, content) in ci_files(root) {
+            push_source(&mut setup, &file, ci_steps(&content));
+        }
+
+        let maintainers = Repository::discover(root)
+            .map_err(Into::into)
+            .and_then(|repo| ownership::maintainers(&repo, repo_dir(&repo, root).as_deref()))
+            .unwrap_or_else(|e| {
+                tracing::debug!("Skipping maintainers: {}", e);
+                Vec::new()
+            });
+
+        Self {
+            setup,
+            modules: key_modules(root),
+            maintainers,
+            docs,
+        }
+    }
+
+    /// The context as the "Additional Context" of the prompt
+    pub fn render(&self) -> String {
+        let mut context = String::new();
+
+        context.push_str("Setup commands:\n");
+        if self.setup.is_empty() {
+            context.push_str("- none found\n");
+        }
+        for source in &self.setup {
+            context.push_str(&format!("From {}:\n", source.file));
+            for step in &source.steps {
+                context.push_str(&format!("- `{}`\n", step));
+            }
+        }
+
+        context.push_str("\nKey modules:\n");
+        if self.modules.is_empty() {
+            context.push_str("- none found\n");
+        }
+        for module in &self.modules {
+            context.push_str(&format!("- {}: {}", module.path, module.symbols.join(", ")));
+            if let Some(description) = &module.description {
+                context.push_str(&format!(" ({})", description));
+            }
+            context.push('\n');
+        }
+
+        context.push_str("\nMaintainers:\n");
+        if self.maintainers.is_empty() {
+            context.push_str("- unknown\n");
+        }
+        for maintainer in &self.maintainers {
+            let email = maintainer
+                .email
+                .as_deref()
+                .map(|email| format!(" <{}>", email))
+                .unwrap_or_default();
+            context.push_str(&format!(
+                "- {}{} ({} commits, last on {})\n",
+                maintainer.name,
+                email,
+                maintainer.commits,
+                maintainer.last_commit.format("%Y-%m-%d")
+            ));
+        }
+
+        context.push_str("\nExisting documentation:\n");
+        if self.docs.is_empty() {
+            context.push_str("- none mapped\n");
+        }
+        for doc in &self.docs {
+            context.push_str(&format!("- {} ({})\n", doc.location, doc.r#type));
+        }
+
+        context
+    }
+}
+
+fn push_source(setup: &mut Vec<SetupSource>, file: &str, mut steps: Vec<String>) {
+    steps.dedup();
+    steps.truncate(MAX_STEPS);
+    if !steps.is_empty() {
+        setup.push(SetupSource {
+            file: file.to_string(),
+            steps,
+        });
+    }
+}
+
+/// Commands in the code blocks of the README's setup sections
+fn readme_steps(readme: &str) -> Vec<String> {
+    let mut steps = Vec::new();
+    let mut in_setup = false;
+    let mut in_code = false;
+    for line in readme.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            if in_setup && !trimmed.is_empty() && !trimmed.starts_with('#') {
+                steps.push(trimmed.trim_start_matches("$ ").to_string());
+            }
+        } else if trimmed.starts_with('#') {
+            let heading = trimmed.trim_start_matches('#').trim().to_lowercase();
+            in_setup = SETUP_HEADINGS.iter().any(|h| heading.contains(h));
+        }
+    }
+    steps
+}
+
+/// `make <target>` for each target of a Makefile, with its `##` help text
+/// as a comment
+fn make_targets(makefile: &str) -> Vec<String> {
+    makefile
+        .lines()
+        .filter(|line| !line.starts_with(['\t', ' ', '.', '#']))
+        .filter_map(|line| {
+            let (target, rest) = line.split_once(':')?;
+            if rest.starts_with('=') || target.contains(['=', '$', '%']) {
+                return None;
+            }
+            let help = rest.split_once("##").map(|(_, help)| help.trim());
+            Some(
+                target
+                    .split_whitespace()
+                    .map(|target| match help {
+                        Some(help) => format!("make {}  # {}", target, help),
+                        None => format!("make {}", target),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+/// GitHub Actions workflows and GitLab CI configuration under `root`
+fn ci_files(root: &Path) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(root.join(".github/workflows")) {
+        let mut paths: Vec<_> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "yml" || ext == "yaml")
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                files.push((format!(".github/workflows/{}", name), content));
+            }
+        }
+    }
+    if let Ok(content) = std::fs::read_to_string(root.join(".gitlab-ci.yml")) {
+        files.push((".gitlab-ci.yml".to_string(), content));
+    }
+    files
+}
+
+/// The `run:` steps of a workflow or `script:` lines of a GitLab job
+fn ci_steps(content: &str) -> Vec<String> {
+    fn collect(value: &serde_yaml::Value, steps: &mut Vec<String>) {
+        match value {
+            serde_yaml::Value::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    match (key.as_str(), value) {
+                        (Some("run" | "script"), serde_yaml::Value::String(command)) => {
+                            steps.extend(
+                                command
+                                    .lines()
+                                    .map(str::trim)
+                                    .filter(|l| !l.is_empty())
+                                    .map(str::to_string),
+                            );
+                        }
+                        (Some("script"), serde_yaml::Value::Sequence(commands)) => {
+                            steps.extend(
+                                commands
+                                    .iter()
+                                    .filter_map(|c| c.as_str())
+                                    .map(str::to_string),
+                            );
+                        }
+                        _ => collect(value, steps),
+                    }
+                }
+            }
+            serde_yaml::Value::Sequence(values) => {
+                values.iter().for_each(|value| collect(value, steps));
+            }
+            _ => {}
+        }
+    }
+
+    let mut steps = Vec::new();
+    match serde_yaml::from_str::<serde_yaml::Value>(content) {
+        Ok(value) => collect(&value, &mut steps),
+        Err(e) => tracing::debug!("Skipping unparsable CI file: {}", e),
+    }
+    steps
+}
+
+/// Source files defining the most public types, by the symbol scanner
+fn key_modules(root: &Path) -> Vec<KeyModule> {
+    let features = match CodebaseScanner::new(root.to_path_buf()).scan() {
+        Ok(features) => features,
+        Err(e) => {
+            tracing::debug!("Skipping key modules: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut by_file: BTreeMap<String, KeyModule> = BTreeMap::new();
+    for feature in features
+        .into_iter()
+        .filter(|f| f.confidence >= MIN_SYMBOL_CONFIDENCE)
+    {
+        let path = Path::new(&feature.file_path)
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or(feature.file_path);
+        let module = by_file.entry(path.clone()).or_insert_with(|| KeyModule {
+            path,
+            symbols: Vec::new(),
+            description: None,
+        });
+        module.symbols.push(feature.name);
+        if module.description.is_none() {
+            module.description = feature.description;
+        }
+    }
+
+    let mut modules: Vec<KeyModule> = by_file.into_values().collect();
+    modules.sort_by_key(|module| std::cmp::Reverse(module.symbols.len()));
+    modules.truncate(MAX_MODULES);
+    for module in &mut modules {
+        module.symbols.truncate(MAX_SYMBOLS);
+    }
+    modules
+}
+
+/// `root` relative to the repository's working directory
+fn repo_dir(repo: &Repository, root: &Path) -> Option<String> {
+    let workdir = std::fs::canonicalize(repo.workdir()?).ok()?;
+    let root = std::fs::canonicalize(root).ok()?;
+    let relative = root.strip_prefix(workdir).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_setup_steps_from_readme_makefile_and_ci() {
+        let readme = "# Billing\n\n```bash\nignored --intro\n```\n\n## Getting Started\n\n```bash\n# install deps\n$ cargo build\ncargo test\n```\n\n## License\n\n```\nMIT\n```\n";
+        assert_eq!(readme_steps(readme), ["cargo build", "cargo test"]);
+
+        let makefile = ".PHONY: build\nVERSION := 1.0\nbuild: ## Build the service\n\tcargo build\ntest lint:\n\tcargo test\n";
+        assert_eq!(
+            make_targets(makefile),
+            ["make build  # Build the service", "make test", "make lint"]
+        );
+
+        let workflow = "on: push\njobs:\n  test:\n    steps:\n      - uses: actions/checkout@v4\n      - run: cargo fmt --check\n      - run: |\n          cargo clippy\n          cargo test\n";
+        assert_eq!(
+            ci_steps(workflow),
+            ["cargo fmt --check", "cargo clippy", "cargo test"]
+        );
+        assert_eq!(
+            ci_steps("test:\n  script:\n    - make test\n"),
+            ["make test"]
+        );
+    }
+
+    #[test]
+    fn test_gather_and_render() {
+        let root = TempDir::new().unwrap();
+        std::fs::write(root.path().join("Makefile"), "run:\n\tcargo run\n").unwrap();
+        std::fs::create_dir_all(root.path().join("src")).unwrap();
+        std::fs::write(
+            root.path().join("src/billing.rs"),
+            "/// Issued invoice\npub struct Invoice {}\npub enum PaymentStatus { Paid }\n",
+        )
+        .unwrap();
+        let docs = vec![DocumentLocation {
+            r#type: "markdown".to_string(),
+            location: "docs/billing.md".to_string(),
+            section: None,
+        }];
+
+        let context = OnboardingContext::gather(root.path(), docs);
+        assert_eq!(context.setup[0].steps, ["make run"]);
+        assert_eq!(context.modules[0].path, "src/billing.rs");
+        assert_eq!(
+            context.modules[0].description.as_deref(),
+            Some("Issued invoice")
+        );
+
+        let rendered = context.render();
+        assert!(rendered.contains("From Makefile:\n- `make run`\n"));
+        assert!(rendered.contains("- src/billing.rs: Invoice, Payment Status (Issued invoice)\n"));
+        assert!(rendered.contains("Maintainers:\n- unknown\n"));
+        assert!(rendered.contains("- docs/billing.md (markdown)\n"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Context for `--type postmortem` drafts
+//!
+//! Reconstructs the timeline of an incident from the commits of a
+//! `--range <incident-start>..<fix-commit>`; the last commit is the fix.
+
+use super::commits;
+use crate::git::diff::ExtractedDiff;
+use chrono::{DateTime, Utc};
+
+/// Files of the fix commit listed in the context
+const MAX_FIX_FILES: usize = 20;
+
+/// Timeline, incident window and fix commit as the "Additional Context" of
+/// the prompt
+pub fn render(diff: &ExtractedDiff) -> String {
+    let commits = commits(diff);
+    let mut context = String::from("Timeline (oldest first):\n");
+    for commit in &commits {
+        context.push_str(&format!(
+            "- {} `{}` {}: {} ({} files)\n",
+            format_time(&commit.timestamp),
+            commit.short_id(),
+            commit.author,
+            commit.subject(),
+            commit.files.len()
+        ));
+    }
+
+    let times: Vec<DateTime<Utc>> = commits
+        .iter()
+        .filter_map(|c| DateTime::parse_from_rfc3339(&c.timestamp).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .collect();
+    if let (Some(start), Some(end)) = (times.first(), times.last()) {
+        let duration = *end - *start;
+        context.push_str(&format!(
+            "\nIncident window: {} to {} ({}h {}m)\n",
+            start.format("%Y-%m-%d %H:%M UTC"),
+            end.format("%Y-%m-%d %H:%M UTC"),
+            duration.num_hours(),
+            duration.num_minutes() % 60
+        ));
+    }
+
+    if let Some(fix) = commits.last() {
+        context.push_str(&format!(
+            "\nFix commit: `{}` by {}: {}\n",
+            fix.short_id(),
+            fix.author,
+            fix.subject()
+        ));
+        for file in fix.files.iter().take(MAX_FIX_FILES) {
+            context.push_str(&format!("- {}\n", file));
+        }
+        if fix.files.len() > MAX_FIX_FILES {
+            context.push_str(&format!(
+                "- … and {} more\n",
+                fix.files.len() - MAX_FIX_FILES
+            ));
+        }
+    }
+
+    context
+}
+
+fn format_time(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| {
+            time.with_timezone(&Utc)
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string()
+        })
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::FileChange;
+
+    fn commit(id: &str, timestamp: &str, message: &str, path: &str) -> ExtractedDiff {
+        ExtractedDiff {
+            source: "commit".to_string(),
+            identifier: id.to_string(),
+            timestamp: timestamp.to_string(),
+            author: "alice".to_string(),
+            message: message.to_string(),
+            files: vec![FileChange {
+                path: path.to_string(),
+                old_path: None,
+                status: "modified".to_string(),
+                additions: 2,
+                deletions: 1,
+                diff: format!("+{}", message),
+                truncated: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_timeline_highlights_fix() {
+        let diff = ExtractedDiff::combine(
+            "v1.2.0..abcdef123",
+            vec![
+                commit(
+                    "abcdef1234567",
+                    "2026-03-02T11:30:00+00:00",
+                    "Fix pool exhaustion\n\nRelease connections on timeout",
+                    "src/db.rs",
+                ),
+                commit(
+                    "0123456789abc",
+                    "2026-03-02T09:00:00+00:00",
+                    "Raise request timeout",
+                    "src/db.rs",
+                ),
+            ],
+        );
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].additions, 4);
+        assert_eq!(
+            diff.message,
+            "0123456 Raise request timeout\nabcdef1 Fix pool exhaustion"
+        );
+
+        let context = render(&diff);
+        assert!(context.contains(
+            "Timeline (oldest first):\n- 2026-03-02 09:00 UTC `0123456` alice: Raise request timeout (1 files)\n"
+        ));
+        assert!(context
+            .contains("Incident window: 2026-03-02 09:00 UTC to 2026-03-02 11:30 UTC (2h 30m)"));
+        assert!(
+            context.contains("Fix commit: `abcdef1` by alice: Fix pool exhaustion\n- src/db.rs\n")
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Context for `--type release-notes`
+//!
+//! Groups the commits of a release by their conventional-commit type. For
+//! customers, internal changes such as refactors are left out; for the team,
+//! migration steps are collected from breaking changes and migration files.
+
+use super::commits;
+use crate::git::diff::{CommitSummary, ExtractedDiff};
+use serde::{Deserialize, Serialize};
+
+/// Commit types that never reach customer release notes
+const INTERNAL_TYPES: &[&str] = &["refactor", "chore", "ci", "build", "test", "style"];
+
+/// Who release notes are written for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    /// The team: every change, with migration steps
+    Internal,
+    /// Users of the service: features, fixes and breaking changes only
+    #[default]
+    Customer,
+}
+
+/// A commit message parsed as `type(scope)!: description`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    /// Commit type; `None` for messages not following the convention
+    pub kind: Option<String>,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+    /// Text of a `BREAKING CHANGE:` footer
+    pub breaking_note: Option<String>,
+}
+
+impl ConventionalCommit {
+    pub fn parse(message: &str) -> Self {
+        let subject = message.lines().next().unwrap_or_default().trim();
+        let breaking_note = message.lines().find_map(|line| {
+            line.strip_prefix("BREAKING CHANGE:")
+                .or_else(|| line.strip_prefix("BREAKING-CHANGE:"))
+                .map(|note| note.trim().to_string())
+        });
+
+        let parsed = subject.split_once(": ").and_then(|(header, description)| {
+            let (header, bang) = match header.strip_suffix('!') {
+                Some(header) => (header, true),
+                None => (header, false),
+            };
+            let (kind, scope) = match header.split_once('(') {
+                Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?.to_string())),
+                None => (header, None),
+            };
+            let valid = !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic());
+            valid.then(|| {
+                (
+                    kind.to_lowercase(),
+                    scope,
+                    bang,
+                    description.trim().to_string(),
+                )
+            })
+        });
+
+        match parsed {
+            Some((kind, scope, bang, description)) => Self {
+                kind: Some(kind),
+                scope,
+                breaking: bang || breaking_note.is_some(),
+                description,
+                breaking_note,
+            },
+            None => Self {
+                kind: None,
+                scope: None,
+                breaking: breaking_note.is_some(),
+                description: subject.to_string(),
+                breaking_note,
+            },
+        }
+    }
+
+    /// Release notes group of the commit
+    fn group(&self) -> &'static str {
+        if self.breaking {
+            return "Breaking changes";
+        }
+        match self.kind.as_deref() {
+            Some("feat") => "Features",
+            Some("fix") => "Fixes",
+            Some("perf") => "Performance",
+            Some("docs") => "Documentation",
+            Some(kind) if INTERNAL_TYPES.contains(&kind) => "Internal changes",
+            _ => "Other changes",
+        }
+    }
+
+    /// Whether `audience` should hear about the commit
+    fn visible_to(&self, audience: Audience) -> bool {
+        audience == Audience::Internal
+            || self.breaking
+            || !matches!(self.group(), "Internal changes" | "Documentation")
+    }
+}
+
+/// Order of the groups in the context
+const GROUPS: &[&str] = &[
+    "Breaking changes",
+    "Features",
+    "Fixes",
+    "Performance",
+    "Documentation",
+    "Internal changes",
+    "Other changes",
+];
+
+/// Whether `path` holds a database or data migration
+fn is_migration(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.contains("migration") || path.contains("migrate")
+}
+
+/// Grouped commits and, for internal notes, migration steps as the
+/// "Additional Context" of the prompt
+pub fn render(diff: &ExtractedDiff, audience: Audience) -> String {
+    let commits = commits(diff);
+    let parsed: Vec<(&CommitSummary, ConventionalCommit)> = commits
+        .iter()
+        .map(|commit| (commit, ConventionalCommit::parse(&commit.message)))
+        .collect();
+
+    let mut context = match audience {
+        Audience::Customer => String::from(
+            "Audience: customers. Describe what users can now do or what was fixed, in plain language. Leave out refactors, tests, CI and other internal work, file names and commit ids.\n",
+        ),
+        Audience::Internal => String::from(
+            "Audience: the engineering team. Include every change with its scope, and a Migration steps section for breaking changes and migrations.\n",
+        ),
+    };
+
+    let hidden = parsed
+        .iter()
+        .filter(|(_, commit)| !commit.visible_to(audience))
+        .count();
+    for group in GROUPS {
+        let entries: Vec<_> = parsed
+            .iter()
+            .filter(|(_, commit)| commit.visible_to(audience) && commit.group() == *group)
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        context.push_str(&format!("\n{}:\n", group));
+        for (summary, commit) in entries {
+            let scope = commit
+                .scope
+                .as_deref()
+                .map(|scope| format!("**{}**: ", scope))
+                .unwrap_or_default();
+            match audience {
+                Audience::Internal => context.push_str(&format!(
+                    "- {}{} (`{}`)\n",
+                    scope,
+                    commit.description,
+                    summary.short_id()
+                )),
+                Audience::Customer => {
+                    context.push_str(&format!("- {}{}\n", scope, commit.description))
+                }
+            }
+        }
+    }
+    if hidden > 0 {
+        context.push_str(&format!(
+            "\n{} internal change(s) are left out for this audience.\n",
+            hidden
+        ));
+    }
+
+    if audience == Audience::Internal {
+        let mut steps: Vec<String> = parsed
+            .iter()
+            .filter_map(|(_, commit)| commit.breaking_note.clone())
+            .collect();
+        let mut migrations: Vec<&str> = commits
+            .iter()
+            .flat_map(|commit| &commit.files)
+            .chain(diff.files.iter().map(|f| &f.path))
+            .map(String::as_str)
+            .filter(|path| is_migration(path))
+            .collect();
+        migrations.sort();
+        migrations.dedup();
+        steps.extend(
+            migrations
+                .iter()
+                .map(|path| format!("Apply migration {}", path)),
+        );
+        if !steps.is_empty() {
+            context.push_str("\nMigration steps:\n");
+            for step in steps {
+                context.push_str(&format!("- {}\n", step));
+            }
+        }
+    }
+
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conventional_commits() {
+        let commit =
+            ConventionalCommit::parse("feat(api)!: Drop v1 endpoints\n\nBREAKING CHANGE: use /v2");
+        assert_eq!(commit.kind.as_deref(), Some("feat"));
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+        assert!(commit.breaking);
+        assert_eq!(commit.description, "Drop v1 endpoints");
+        assert_eq!(commit.breaking_note.as_deref(), Some("use /v2"));
+
+        let plain = ConventionalCommit::parse("Update README: typo");
+        assert_eq!(plain.kind, None);
+        assert_eq!(plain.description, "Update README: typo");
+    }
+
+    #[test]
+    fn test_render_by_audience() {
+        let commit = |id: &str, message: &str, files: &[&str]| CommitSummary {
+            id: id.to_string(),
+            timestamp: String::new(),
+            author: "alice".to_string(),
+            message: message.to_string(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        };
+        let diff = ExtractedDiff {
+            commits: vec![
+                commit(
+                    "aaaaaaa1",
+                    "feat(billing): Add invoice export",
+                    &["src/export.rs"],
+                ),
+                commit(
+                    "bbbbbbb2",
+                    "refactor: Split the invoice module",
+                    &["src/invoice.rs"],
+                ),
+                commit(
+                    "ccccccc3",
+                    "fix!: Store amounts in cents",
+                    &["migrations/009_cents.sql"],
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let customer = render(&diff, Audience::Customer);
+        assert!(customer.contains("Features:\n- **billing**: Add invoice export\n"));
+        assert!(customer.contains("Breaking changes:\n- Store amounts in cents\n"));
+        assert!(!customer.contains("Split the invoice module"));
+        assert!(customer.contains("1 internal change(s) are left out"));
+        assert!(!customer.contains("Migration steps"));
+
+        let internal = render(&diff, Audience::Internal);
+        assert!(internal.contains("Internal changes:\n- Split the invoice module (`bbbbbbb`)\n"));
+        assert!(internal.contains("Migration steps:\n- Apply migration migrations/009_cents.sql\n"));
+    }
+}
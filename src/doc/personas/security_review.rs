@@ -0,0 +1,431 @@
+//! Context for `--type security-review`
+//!
+//! Picks the changed lines a security reviewer has to look at: authentication
+//! and authorization, new endpoints, input handling, secrets and crypto, and
+//! dependency changes. With `--audit`, the findings of `cargo audit` and
+//! `npm audit` for the service are added.
+
+use crate::git::diff::{ExtractedDiff, FileChange};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Command;
+
+/// Findings listed per category
+const MAX_FINDINGS: usize = 10;
+
+/// Vulnerabilities listed per audit
+const MAX_ADVISORIES: usize = 20;
+
+/// Changed-line categories and the lowercase keywords flagging them
+const CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Authentication and authorization",
+        &[
+            "auth",
+            "login",
+            "logout",
+            "password",
+            "token",
+            "jwt",
+            "session",
+            "cookie",
+            "permission",
+            "role",
+            "acl",
+            "csrf",
+            "credential",
+        ],
+    ),
+    (
+        "Input validation",
+        &[
+            "validate",
+            "sanitize",
+            "escape",
+            "deserialize",
+            "from_str",
+            "unsafe",
+            "select ",
+            "insert into",
+            "delete from",
+            "exec(",
+            "eval(",
+            "system(",
+            "subprocess",
+            "command::new",
+            "innerhtml",
+        ],
+    ),
+    (
+        "Secrets and cryptography",
+        &[
+            "secret",
+            "api_key",
+            "apikey",
+            "private_key",
+            "encrypt",
+            "decrypt",
+            "md5",
+            "sha1",
+            "hmac",
+            "tls",
+            "certificate",
+            "verify_ssl",
+            "insecure",
+        ],
+    ),
+];
+
+/// Route declarations of common web frameworks
+const ENDPOINT_PATTERNS: &[&str] = &[
+    "#[get(",
+    "#[post(",
+    "#[put(",
+    "#[patch(",
+    "#[delete(",
+    ".route(",
+    "@app.route(",
+    "@router.",
+    "@app.get(",
+    "@app.post(",
+    "router.get(",
+    "router.post(",
+    "router.put(",
+    "router.delete(",
+    "app.get(",
+    "app.post(",
+    "app.put(",
+    "app.delete(",
+    "handlefunc(",
+    "@getmapping",
+    "@postmapping",
+    "@putmapping",
+    "@deletemapping",
+    "@requestmapping",
+];
+
+/// Files declaring dependencies
+const MANIFESTS: &[&str] = &[
+    "cargo.toml",
+    "package.json",
+    "go.mod",
+    "requirements.txt",
+    "pyproject.toml",
+    "gemfile",
+    "pom.xml",
+    "build.gradle",
+];
+
+/// Files pinning dependency versions; too large to scan line by line
+const LOCKFILES: &[&str] = &[
+    "cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "go.sum",
+    "poetry.lock",
+    "gemfile.lock",
+];
+
+/// A changed line a reviewer should look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub file: String,
+    /// `+` for added, `-` for removed lines
+    pub origin: char,
+    pub line: String,
+}
+
+/// Security-relevant changes of a diff
+#[derive(Debug, Clone, Default)]
+pub struct SecurityReview {
+    /// Findings per category, in the order of [`CATEGORIES`]
+    pub categories: Vec<(&'static str, Vec<Finding>)>,
+    /// Added route declarations
+    pub endpoints: Vec<Finding>,
+    /// Added and removed lines of dependency manifests
+    pub dependencies: Vec<Finding>,
+    pub lockfiles: Vec<String>,
+    /// Results of `cargo audit` and `npm audit`
+    pub audits: Vec<AuditReport>,
+}
+
+/// Outcome of a dependency audit
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditReport {
+    pub tool: &'static str,
+    /// Vulnerable packages, or why the audit could not run
+    pub result: Result<Vec<String>, String>,
+}
+
+impl SecurityReview {
+    pub fn analyze(diff: &ExtractedDiff) -> Self {
+        let mut review = Self {
+            categories: CATEGORIES
+                .iter()
+                .map(|(name, _)| (*name, Vec::new()))
+                .collect(),
+            ..Default::default()
+        };
+        for file in &diff.files {
+            let name = file_name(&file.path);
+            if LOCKFILES.contains(&name.as_str()) {
+                review.lockfiles.push(file.path.clone());
+                continue;
+            }
+            let manifest = MANIFESTS.contains(&name.as_str());
+            for finding in changed_lines(file) {
+                let lower = finding.line.to_lowercase();
+                if manifest {
+                    review.dependencies.push(finding);
+                    continue;
+                }
+                if finding.origin == '+'
+                    && ENDPOINT_PATTERNS
+                        .iter()
+                        .any(|pattern| lower.contains(pattern))
+                {
+                    review.endpoints.push(finding.clone());
+                }
+                for ((_, keywords), (_, findings)) in
+                    CATEGORIES.iter().zip(review.categories.iter_mut())
+                {
+                    if keywords.iter().any(|keyword| lower.contains(keyword)) {
+                        findings.push(finding.clone());
+                    }
+                }
+            }
+        }
+        review
+    }
+
+    /// Run the dependency audits for the lockfiles found in `root`
+    pub fn audit(&mut self, root: &Path) {
+        if root.join("Cargo.lock").is_file() {
+            self.audits.push(AuditReport {
+                tool: "cargo audit",
+                result: run_audit(root, "cargo", &["audit", "--json"])
+                    .and_then(|report| parse_cargo_audit(&report)),
+            });
+        }
+        if root.join("package-lock.json").is_file() {
+            self.audits.push(AuditReport {
+                tool: "npm audit",
+                result: run_audit(root, "npm", &["audit", "--json"])
+                    .and_then(|report| parse_npm_audit(&report)),
+            });
+        }
+    }
+
+    /// The review as the "Additional Context" of the prompt
+    pub fn render(&self) -> String {
+        let mut context =
+            String::from("Security-relevant changes (heuristic; confirm each against the diff):\n");
+        render_findings(&mut context, "New endpoints", &self.endpoints);
+        for (name, findings) in &self.categories {
+            render_findings(&mut context, name, findings);
+        }
+        render_findings(&mut context, "Dependency changes", &self.dependencies);
+        if !self.lockfiles.is_empty() {
+            context.push_str(&format!(
+                "\nLockfiles changed: {}\n",
+                self.lockfiles.join(", ")
+            ));
+        }
+
+        for audit in &self.audits {
+            match &audit.result {
+                Ok(advisories) if advisories.is_empty() => {
+                    context.push_str(&format!("\n{}: no known vulnerabilities\n", audit.tool))
+                }
+                Ok(advisories) => {
+                    context.push_str(&format!(
+                        "\n{}: {} known vulnerabilities\n",
+                        audit.tool,
+                        advisories.len()
+                    ));
+                    for advisory in advisories.iter().take(MAX_ADVISORIES) {
+                        context.push_str(&format!("- {}\n", advisory));
+                    }
+                }
+                Err(e) => context.push_str(&format!("\n{}: not run ({})\n", audit.tool, e)),
+            }
+        }
+        context
+    }
+}
+
+fn render_findings(context: &mut String, heading: &str, findings: &[Finding]) {
+    context.push_str(&format!("\n{}:\n", heading));
+    if findings.is_empty() {
+        context.push_str("- none\n");
+    }
+    for finding in findings.iter().take(MAX_FINDINGS) {
+        let change = if finding.origin == '+' {
+            "added"
+        } else {
+            "removed"
+        };
+        context.push_str(&format!(
+            "- {} ({}): `{}`\n",
+            finding.file, change, finding.line
+        ));
+    }
+    if findings.len() > MAX_FINDINGS {
+        context.push_str(&format!("- … and {} more\n", findings.len() - MAX_FINDINGS));
+    }
+}
+
+fn file_name(path: &str) -> String {
+    path.rsplit('/').next().unwrap_or(path).to_lowercase()
+}
+
+/// Added and removed non-blank lines of `file`
+fn changed_lines(file: &FileChange) -> impl Iterator<Item = Finding> + '_ {
+    file.diff.lines().filter_map(|line| {
+        let origin = line.chars().next().filter(|c| matches!(c, '+' | '-'))?;
+        let text = line[1..].trim();
+        if text.is_empty() || line.starts_with("+++") || line.starts_with("---") {
+            return None;
+        }
+        Some(Finding {
+            file: file.path.clone(),
+            origin,
+            line: text.to_string(),
+        })
+    })
+}
+
+/// Stdout of an audit tool; the tools exit non-zero when they find
+/// vulnerabilities, so only a missing report is an error
+fn run_audit(root: &Path, program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(root)
+        .output()
+        .map_err(|e| format!("{} is not available: {}", program, e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if stdout.trim().is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.lines().next().unwrap_or("no report").to_string());
+    }
+    Ok(stdout)
+}
+
+/// Vulnerable crates from `cargo audit --json`
+pub fn parse_cargo_audit(report: &str) -> Result<Vec<String>, String> {
+    let report: Value =
+        serde_json::from_str(report).map_err(|e| format!("unreadable report: {}", e))?;
+    let list = report["vulnerabilities"]["list"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    Ok(list
+        .iter()
+        .map(|vulnerability| {
+            format!(
+                "{} {}: {} ({})",
+                vulnerability["package"]["name"].as_str().unwrap_or("?"),
+                vulnerability["package"]["version"].as_str().unwrap_or("?"),
+                vulnerability["advisory"]["title"].as_str().unwrap_or(""),
+                vulnerability["advisory"]["id"].as_str().unwrap_or("?")
+            )
+        })
+        .collect())
+}
+
+/// Vulnerable packages from `npm audit --json`
+pub fn parse_npm_audit(report: &str) -> Result<Vec<String>, String> {
+    let report: Value =
+        serde_json::from_str(report).map_err(|e| format!("unreadable report: {}", e))?;
+    if let Some(error) = report["error"]["summary"].as_str() {
+        return Err(error.to_string());
+    }
+    let Some(packages) = report["vulnerabilities"].as_object() else {
+        return Ok(Vec::new());
+    };
+    Ok(packages
+        .iter()
+        .map(|(name, vulnerability)| {
+            let advisory = vulnerability["via"]
+                .as_array()
+                .and_then(|via| via.iter().find_map(|v| v["title"].as_str()))
+                .map(|title| format!(": {}", title))
+                .unwrap_or_default();
+            format!(
+                "{} ({}){}",
+                name,
+                vulnerability["severity"].as_str().unwrap_or("unknown"),
+                advisory
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, diff: &str) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            old_path: None,
+            status: "modified".to_string(),
+            additions: 1,
+            deletions: 0,
+            diff: diff.to_string(),
+            truncated: None,
+        }
+    }
+
+    #[test]
+    fn test_analyze_security_changes() {
+        let diff = ExtractedDiff {
+            files: vec![
+                change(
+                    "src/api.rs",
+                    "+    .route(\"/admin/users\", delete(remove_user))\n-    require_role(&user, Role::Admin)?;\n fn unchanged() {}\n",
+                ),
+                change("Cargo.toml", "+openssl = \"0.10\"\n"),
+                change("Cargo.lock", "+name = \"openssl\"\n"),
+            ],
+            ..Default::default()
+        };
+
+        let review = SecurityReview::analyze(&diff);
+        assert_eq!(review.endpoints.len(), 1);
+        assert_eq!(review.categories[0].1.len(), 1);
+        assert_eq!(review.categories[0].1[0].origin, '-');
+        assert_eq!(review.dependencies[0].line, "openssl = \"0.10\"");
+        assert_eq!(review.lockfiles, ["Cargo.lock"]);
+
+        let context = review.render();
+        assert!(context.contains(
+            "New endpoints:\n- src/api.rs (added): `.route(\"/admin/users\", delete(remove_user))`\n"
+        ));
+        assert!(context.contains(
+            "Authentication and authorization:\n- src/api.rs (removed): `require_role(&user, Role::Admin)?;`\n"
+        ));
+        assert!(context.contains("Input validation:\n- none\n"));
+        assert!(context.contains("Lockfiles changed: Cargo.lock\n"));
+    }
+
+    #[test]
+    fn test_parse_audit_reports() {
+        let cargo = r#"{"vulnerabilities": {"found": true, "count": 1, "list": [
+            {"advisory": {"id": "RUSTSEC-2023-0044", "title": "openssl: use-after-free"},
+             "package": {"name": "openssl", "version": "0.10.48"}}]}}"#;
+        assert_eq!(
+            parse_cargo_audit(cargo).unwrap(),
+            ["openssl 0.10.48: openssl: use-after-free (RUSTSEC-2023-0044)"]
+        );
+
+        let npm = r#"{"vulnerabilities": {"lodash": {"name": "lodash", "severity": "high",
+            "via": [{"title": "Prototype Pollution"}]}}}"#;
+        assert_eq!(
+            parse_npm_audit(npm).unwrap(),
+            ["lodash (high): Prototype Pollution"]
+        );
+        assert!(parse_npm_audit(r#"{"error": {"summary": "no lockfile"}}"#).is_err());
+    }
+}
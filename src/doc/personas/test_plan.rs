@@ -0,0 +1,330 @@
+//! Context for `--type test-plan`
+//!
+//! Pairs the changed source files with the test changes covering them and
+//! lists the test cases the diff adds, changes or removes, found with the
+//! symbol scanner. Significant code changes without any test change are
+//! flagged.
+
+use crate::git::diff::{ExtractedDiff, FileChange};
+use crate::knowledge::scanner::{extract_test_name, is_test_attribute};
+use serde::Serialize;
+use std::path::Path;
+
+/// Changed lines of source code above which a change without tests is
+/// flagged
+const SIGNIFICANT_LINES: u32 = 50;
+
+/// Extensions of source files expected to come with tests
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "go", "py", "ts", "tsx", "js", "jsx", "java", "kt", "rb", "cs", "swift", "c", "cpp",
+];
+
+/// What the diff does to a test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestChange {
+    Added,
+    Modified,
+    Removed,
+}
+
+impl TestChange {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+        }
+    }
+}
+
+/// A test case touched by the diff
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestCase {
+    pub name: String,
+    pub file: String,
+    pub change: TestChange,
+}
+
+/// Test coverage of the changes of a diff
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TestCoverage {
+    pub test_files: Vec<String>,
+    pub test_cases: Vec<TestCase>,
+    /// Changed source files with a matching test change
+    pub covered: Vec<String>,
+    /// Changed source files without one
+    pub uncovered: Vec<String>,
+    /// Added and removed lines of source code
+    pub code_lines: u32,
+}
+
+impl TestCoverage {
+    pub fn analyze(diff: &ExtractedDiff) -> Self {
+        let mut coverage = Self::default();
+        for file in &diff.files {
+            let cases = test_cases(file);
+            if is_test_file(&file.path) {
+                coverage.test_files.push(file.path.clone());
+            }
+            coverage.test_cases.extend(cases);
+        }
+
+        for file in diff
+            .files
+            .iter()
+            .filter(|f| is_code_file(&f.path) && !is_test_file(&f.path))
+        {
+            coverage.code_lines += file.additions + file.deletions;
+            let inline_tests = coverage.test_cases.iter().any(|c| c.file == file.path);
+            let stem = file_stem(&file.path);
+            let matching_test = coverage
+                .test_files
+                .iter()
+                .any(|test| test_subject(test) == stem);
+            if inline_tests || matching_test {
+                coverage.covered.push(file.path.clone());
+            } else {
+                coverage.uncovered.push(file.path.clone());
+            }
+        }
+        coverage
+    }
+
+    /// Whether significant code changes come without any test change
+    pub fn lacks_tests(&self) -> bool {
+        self.code_lines >= SIGNIFICANT_LINES
+            && self.test_files.is_empty()
+            && self.test_cases.is_empty()
+    }
+
+    /// The coverage as the "Additional Context" of the prompt
+    pub fn render(&self) -> String {
+        let mut context = format!(
+            "Test summary: {} source file(s) changed ({} lines), {} with test changes, {} without; {} test file(s) changed.\n",
+            self.covered.len() + self.uncovered.len(),
+            self.code_lines,
+            self.covered.len(),
+            self.uncovered.len(),
+            self.test_files.len()
+        );
+        if self.lacks_tests() {
+            context.push_str(&format!(
+                "\nWARNING: {} lines of code changed without any test changes. Call this out prominently.\n",
+                self.code_lines
+            ));
+        }
+
+        context.push_str("\nTest cases:\n");
+        if self.test_cases.is_empty() {
+            context.push_str("- none\n");
+        }
+        for case in &self.test_cases {
+            context.push_str(&format!(
+                "- {} ({}, {})\n",
+                case.name,
+                case.change.as_str(),
+                case.file
+            ));
+        }
+
+        for (heading, files) in [
+            ("Source files with test changes", &self.covered),
+            ("Source files without test changes", &self.uncovered),
+        ] {
+            if !files.is_empty() {
+                context.push_str(&format!("\n{}:\n", heading));
+                for file in files {
+                    context.push_str(&format!("- {}\n", file));
+                }
+            }
+        }
+        context
+    }
+}
+
+/// Whether `path` holds tests by its location or name
+pub fn is_test_file(path: &str) -> bool {
+    let path = path.to_lowercase();
+    let name = path.rsplit('/').next().unwrap_or(&path);
+    let in_test_dir = path
+        .split('/')
+        .rev()
+        .skip(1)
+        .any(|dir| matches!(dir, "tests" | "test" | "__tests__" | "spec"));
+    in_test_dir
+        || name.starts_with("test_")
+        || file_stem(name).ends_with("_test")
+        || file_stem(name).ends_with("_spec")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+}
+
+fn is_code_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| CODE_EXTENSIONS.contains(&ext))
+}
+
+/// File name without directories and extensions
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.split('.').next().unwrap_or(name)
+}
+
+/// Stem of the source file a test file is named after, e.g. `billing` for
+/// `tests/test_billing.py` or `billing.spec.ts`
+fn test_subject(path: &str) -> &str {
+    let stem = file_stem(path);
+    let stem = stem.strip_prefix("test_").unwrap_or(stem);
+    let stem = stem.strip_suffix("_test").unwrap_or(stem);
+    stem.strip_suffix("_spec").unwrap_or(stem)
+}
+
+/// Test cases declared or changed in the diff of `file`
+fn test_cases(file: &FileChange) -> Vec<TestCase> {
+    let ext = Path::new(&file.path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    let mut cases: Vec<TestCase> = Vec::new();
+    let mut record = |name: String, change: TestChange| {
+        match cases.iter_mut().find(|case| case.name == name) {
+            // Removed and added again: changed in place
+            Some(case) if change != TestChange::Modified && case.change != change => {
+                case.change = TestChange::Modified
+            }
+            Some(_) => {}
+            None => cases.push(TestCase {
+                name,
+                file: file.path.clone(),
+                change,
+            }),
+        }
+    };
+
+    // Test whose body the following lines belong to
+    let mut current: Option<String> = None;
+    let mut after_attribute = false;
+    for line in file.diff.lines() {
+        let (origin, text) = match line.chars().next() {
+            Some(origin @ ('+' | '-' | ' ')) => (origin, &line[1..]),
+            _ => continue,
+        };
+        if let Some(name) = extract_test_name(ext, text, after_attribute) {
+            match origin {
+                '+' => record(name.clone(), TestChange::Added),
+                // The attribute also applies to the declaration replacing it
+                '-' => {
+                    record(name.clone(), TestChange::Removed);
+                    current = Some(name);
+                    continue;
+                }
+                _ => {}
+            }
+            current = Some(name);
+        } else if origin != ' ' && !text.trim().is_empty() {
+            if let Some(name) = &current {
+                record(name.clone(), TestChange::Modified);
+            }
+        }
+        if !text.trim().is_empty() {
+            after_attribute = is_test_attribute(text);
+        }
+    }
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, lines: u32, diff: &str) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            old_path: None,
+            status: "modified".to_string(),
+            additions: lines,
+            deletions: 0,
+            diff: diff.to_string(),
+            truncated: None,
+        }
+    }
+
+    #[test]
+    fn test_test_files_and_subjects() {
+        assert!(is_test_file("tests/integration_tests.rs"));
+        assert!(is_test_file("pkg/billing/billing_test.go"));
+        assert!(is_test_file("web/src/billing.spec.ts"));
+        assert!(is_test_file("app/test_billing.py"));
+        assert!(!is_test_file("src/testing.rs"));
+        assert_eq!(test_subject("app/test_billing.py"), "billing");
+        assert_eq!(test_subject("web/src/billing.spec.ts"), "billing");
+    }
+
+    #[test]
+    fn test_analyze_coverage() {
+        let diff = ExtractedDiff {
+            files: vec![
+                change("pkg/billing/billing.go", 30, "+func Total() {}\n"),
+                change(
+                    "pkg/billing/billing_test.go",
+                    5,
+                    " func TestTotal(t *testing.T) {\n+\tassert(t, Total() == 3)\n }\n+func TestRefund(t *testing.T) {}\n-func TestOld(t *testing.T) {}\n",
+                ),
+                change(
+                    "src/invoice.rs",
+                    12,
+                    "+fn round() {}\n #[test]\n-fn rounds_up() {\n+fn rounds_up_to_cents() {\n",
+                ),
+                change("src/export.rs", 40, "+pub fn export() {}\n"),
+                change("README.md", 3, "+Docs\n"),
+            ],
+            ..Default::default()
+        };
+
+        let coverage = TestCoverage::analyze(&diff);
+        let cases: Vec<(&str, TestChange)> = coverage
+            .test_cases
+            .iter()
+            .map(|case| (case.name.as_str(), case.change))
+            .collect();
+        assert_eq!(
+            cases,
+            [
+                ("TestTotal", TestChange::Modified),
+                ("TestRefund", TestChange::Added),
+                ("TestOld", TestChange::Removed),
+                ("rounds_up", TestChange::Removed),
+                ("rounds_up_to_cents", TestChange::Added),
+            ]
+        );
+        assert_eq!(
+            coverage.covered,
+            ["pkg/billing/billing.go", "src/invoice.rs"]
+        );
+        assert_eq!(coverage.uncovered, ["src/export.rs"]);
+        assert_eq!(coverage.code_lines, 82);
+        assert!(!coverage.lacks_tests());
+
+        let rendered = coverage.render();
+        assert!(rendered.contains("Test summary: 3 source file(s) changed (82 lines), 2 with test changes, 1 without; 1 test file(s) changed."));
+        assert!(rendered.contains("- TestRefund (added, pkg/billing/billing_test.go)\n"));
+        assert!(rendered.contains("Source files without test changes:\n- src/export.rs\n"));
+    }
+
+    #[test]
+    fn test_flags_untested_changes() {
+        let diff = ExtractedDiff {
+            files: vec![change("src/export.rs", 80, "+pub fn export() {}\n")],
+            ..Default::default()
+        };
+        let coverage = TestCoverage::analyze(&diff);
+        assert!(coverage.lacks_tests());
+        assert!(coverage
+            .render()
+            .contains("WARNING: 80 lines of code changed without any test changes"));
+    }
+}
@@ -9,6 +9,14 @@ fn default_extension() -> String {
     "md".to_string()
 }
 
+pub(super) fn default_max_retries() -> u32 {
+    5
+}
+
+pub(super) fn default_request_interval_ms() -> u64 {
+    100
+}
+
 /// Provider configuration from database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
@@ -47,6 +55,16 @@ pub struct ConfluenceConfig {
     pub default_labels: Vec<String>,
     #[serde(default = "default_true")]
     pub is_cloud: bool,
+    /// Sign in with OAuth 2.0 (3LO) instead of the API token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oauth: Option<super::confluence_oauth::OAuthSettings>,
+    /// Retries of a request Confluence throttles (429) or rejects as
+    /// temporarily unavailable (503)
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Minimum time between two requests, in milliseconds
+    #[serde(default = "default_request_interval_ms")]
+    pub request_interval_ms: u64,
 }
 
 /// Markdown-specific configuration
@@ -57,6 +75,9 @@ pub struct MarkdownConfig {
     pub extension: String,
     #[serde(default = "default_true")]
     pub auto_create_dirs: bool,
+    /// Keep the previous content of an updated file as `<file>.bak`
+    #[serde(default)]
+    pub backup: bool,
 }
 
 /// Notion-specific configuration
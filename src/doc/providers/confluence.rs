@@ -1,34 +1,81 @@
 use super::{
-    config::ConfluenceConfig, Document, DocumentMetadata, DocumentProvider, PublishResult,
+    config::{self, ConfluenceConfig},
+    confluence_oauth::{ConfluenceOAuth, OAuthSettings, TokenStore},
+    parse_offset, Document, DocumentMetadata, DocumentPage, DocumentProvider, PublishResult,
     PublishStatus,
 };
-use crate::error::{KtmeError, Result};
+use crate::doc::sections::{SectionFormat, SectionedDocument, UpdateMode};
+use crate::doc::writers::confluence::ConfluenceWriter;
+use crate::doc::xhtml;
+use crate::error::{ErrorClass, KtmeError, Result};
 use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// First wait before retrying a throttled request without Retry-After;
+/// doubled on every further attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait before a retry, whatever Retry-After asks for
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Results requested per `content/search` call
+const SEARCH_PAGE_SIZE: usize = 50;
+
+/// Page fields read along with the page: body, version, creation history
+/// and labels
+const PAGE_EXPAND: &str = "body.storage,version,space,history,metadata.labels";
 
 /// Confluence provider for publishing documentation
 pub struct ConfluenceProvider {
     config: ConfluenceConfig,
     client: reqwest::Client,
     auth_header: String,
+    /// Set when the config signs in with OAuth; requests then go through the
+    /// Atlassian API gateway with the stored access token
+    oauth: Option<ConfluenceOAuth>,
+    /// When the last request was sent, to keep `request_interval_ms`
+    /// between requests
+    last_request: tokio::sync::Mutex<Option<Instant>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ConfluencePage {
-    id: String,
-    title: String,
-    #[serde(rename = "_expandable")]
-    expandable: Option<HashMap<String, String>>,
-    #[serde(rename = "_links")]
-    links: Option<HashMap<String, String>>,
-    version: Option<ConfluenceVersion>,
+struct ConfluenceVersion {
+    number: i32,
+    /// When this version was saved (read only)
+    #[serde(default, skip_serializing)]
+    when: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ConfluenceVersion {
-    number: i32,
+struct ConfluenceUser {
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PageHistory {
+    #[serde(rename = "createdDate")]
+    created_date: Option<String>,
+    #[serde(rename = "createdBy")]
+    created_by: Option<ConfluenceUser>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PageMetadata {
+    labels: LabelList,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LabelList {
+    results: Vec<Label>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Label {
+    name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -42,6 +89,10 @@ struct PageContent {
     page_type: String,
     status: String,
     version: Option<ConfluenceVersion>,
+    #[serde(default, skip_serializing)]
+    history: Option<PageHistory>,
+    #[serde(default, skip_serializing)]
+    metadata: Option<PageMetadata>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -70,20 +121,16 @@ struct PageUpdate {
     version: ConfluenceVersion,
 }
 
+/// One page of `content/search` results
 #[derive(Debug, Serialize, Deserialize)]
 struct SearchResponse {
-    results: Vec<SearchResult>,
+    results: Vec<PageContent>,
     size: i32,
     #[serde(rename = "start")]
     start_index: i32,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct SearchResult {
-    id: String,
-    title: String,
-    excerpt: Option<String>,
-    url: String,
+    /// Holds `next` while more results follow
+    #[serde(rename = "_links", default)]
+    links: HashMap<String, String>,
 }
 
 impl ConfluenceProvider {
@@ -106,63 +153,155 @@ impl ConfluenceProvider {
             )
         };
 
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("confluence")
             .user_agent("ktme/1.0")
             .build()
             .expect("Failed to create HTTP client");
 
+        let oauth = config.oauth.clone().and_then(|settings| {
+            TokenStore::default_store()
+                .map(|store| ConfluenceOAuth::new(settings, &config.base_url, store))
+                .map_err(|e| tracing::warn!("Confluence OAuth unavailable: {}", e))
+                .ok()
+        });
+
         Self {
             config,
             client,
             auth_header: auth,
+            oauth,
+            last_request: tokio::sync::Mutex::new(None),
         }
     }
 
+    /// Provider for the `[confluence]` section of the config file
+    pub fn from_settings(settings: &crate::config::ConfluenceConfig) -> Result<Self> {
+        let base_url = settings.base_url.clone().ok_or_else(|| {
+            KtmeError::Config(
+                "Confluence base_url not configured. Please set [confluence] base_url in config.toml"
+                    .to_string(),
+            )
+        })?;
+        let oauth = if settings.auth_type == "oauth" {
+            Some(OAuthSettings::from_config(settings)?)
+        } else {
+            None
+        };
+        Ok(Self::new(ConfluenceConfig {
+            is_cloud: base_url.contains(".atlassian.net"),
+            base_url,
+            username: settings.username.clone().unwrap_or_default(),
+            api_token: settings.api_token.clone(),
+            space_key: settings.space_key.clone().unwrap_or_default(),
+            default_parent_id: settings.default_parent_page.clone(),
+            default_labels: Vec::new(),
+            oauth,
+            max_retries: config::default_max_retries(),
+            request_interval_ms: config::default_request_interval_ms(),
+        }))
+    }
+
     fn api_url(&self, path: &str) -> String {
-        let base = self.config.base_url.trim_end_matches('/');
-        format!("{}/rest/api/{}", base, path.trim_start_matches('/'))
+        rest_url(&self.config.base_url, path)
     }
 
+    /// Wait until `request_interval_ms` has passed since the last request
+    async fn pace(&self) {
+        let interval = Duration::from_millis(self.config.request_interval_ms);
+        let mut last = self.last_request.lock().await;
+        if let Some(elapsed) = last.map(|time| time.elapsed()) {
+            if elapsed < interval {
+                tokio::time::sleep(interval - elapsed).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+
+    /// Send a request, retrying it while Confluence throttles it (429) or is
+    /// temporarily unavailable (503)
     async fn make_request<T: for<'de> Deserialize<'de>>(
         &self,
         method: reqwest::Method,
         endpoint: &str,
         body: Option<serde_json::Value>,
     ) -> Result<T> {
-        let url = self.api_url(endpoint);
+        crate::http::ensure_online("Calling the Confluence API")?;
+        let mut attempt = 0;
+        loop {
+            let (url, auth_header) = match &self.oauth {
+                Some(oauth) => {
+                    let access = oauth.access().await?;
+                    (
+                        rest_url(&access.api_base, endpoint),
+                        format!("Bearer {}", access.access_token),
+                    )
+                }
+                None => (self.api_url(endpoint), self.auth_header.clone()),
+            };
+
+            let mut request = self
+                .client
+                .request(method.clone(), &url)
+                .header("Authorization", auth_header)
+                .header("Accept", "application/json");
+
+            if let Some(body) = &body {
+                request = request.json(body);
+            }
 
-        let mut request = self
-            .client
-            .request(method, &url)
-            .header("Authorization", &self.auth_header)
-            .header("Accept", "application/json");
+            self.pace().await;
+            let response = request
+                .send()
+                .await
+                .map_err(|e| crate::http::request_error("Confluence request failed", e))?;
+
+            let status = response.status();
+            let throttled = is_throttled(status);
+            if throttled && attempt < self.config.max_retries {
+                let delay = retry_delay(response.headers(), attempt);
+                attempt += 1;
+                tracing::warn!(
+                    status = status.as_u16(),
+                    attempt,
+                    delay_secs = delay.as_secs_f64(),
+                    "Confluence throttled the request, retrying"
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
 
-        if let Some(body) = body {
-            request = request.json(&body);
-        }
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                if is_quota_error(status, &error_text) {
+                    return Err(KtmeError::QuotaExceeded {
+                        service: "Confluence",
+                        message: format!("{} - {}", status, error_text),
+                    });
+                }
+                let retries = if throttled {
+                    format!(" (gave up after {} retries)", attempt)
+                } else {
+                    String::new()
+                };
+                return Err(KtmeError::from_status(
+                    "Confluence",
+                    status,
+                    KtmeError::ApiError(format!(
+                        "Confluence API error: {} - {}{}",
+                        status, error_text, retries
+                    )),
+                ));
+            }
 
-        let response = request
-            .send()
-            .await
-            .map_err(|e| KtmeError::NetworkError(e.to_string()))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(KtmeError::ApiError(format!(
-                "Confluence API error: {} - {}",
-                status, error_text
-            )));
+            return response
+                .json()
+                .await
+                .map_err(|e| KtmeError::DeserializationError(e.to_string()));
         }
-
-        response
-            .json()
-            .await
-            .map_err(|e| KtmeError::DeserializationError(e.to_string()))
     }
 
     async fn get_page_by_id(&self, page_id: &str) -> Result<Option<PageContent>> {
-        let endpoint = format!("content/{}?expand=body.storage,version,space", page_id);
+        let endpoint = format!("content/{}?expand={}", page_id, PAGE_EXPAND);
 
         match self
             .make_request::<PageContent>(reqwest::Method::GET, &endpoint, None)
@@ -174,19 +313,15 @@ impl ConfluenceProvider {
         }
     }
 
-    async fn search_page_by_title(&self, title: &str) -> Result<Option<ConfluencePage>> {
+    /// The page titled `title` with its body, in a single request
+    async fn search_page_by_title(&self, title: &str) -> Result<Option<PageContent>> {
         let query = format!("title=\"{}\" and space={}", title, self.config.space_key);
         let endpoint = format!(
-            "content/search?cql={}&limit=1&expand=version,space",
-            urlencoding::encode(&query)
+            "content/search?cql={}&limit=1&expand={}",
+            urlencoding::encode(&query),
+            PAGE_EXPAND
         );
 
-        #[derive(Debug, Serialize, Deserialize)]
-        struct SearchResponse {
-            results: Vec<ConfluencePage>,
-            size: i32,
-        }
-
         let response: SearchResponse = self
             .make_request(reqwest::Method::GET, &endpoint, None)
             .await?;
@@ -194,6 +329,106 @@ impl ConfluenceProvider {
         Ok(response.results.into_iter().next())
     }
 
+    /// One page of the results of `cql`, with their bodies, starting at
+    /// result `start`; the flag tells whether more results follow
+    async fn search_page(
+        &self,
+        cql: &str,
+        start: usize,
+        limit: usize,
+    ) -> Result<(Vec<PageContent>, bool)> {
+        let endpoint = format!(
+            "content/search?cql={}&expand={}&start={}&limit={}",
+            urlencoding::encode(cql),
+            PAGE_EXPAND,
+            start,
+            limit
+        );
+        let response: SearchResponse = self
+            .make_request(reqwest::Method::GET, &endpoint, None)
+            .await?;
+        let more = response.links.contains_key("next") && !response.results.is_empty();
+        Ok((response.results, more))
+    }
+
+    /// Pages matching `cql` with their bodies, following `start`/`limit`
+    /// pagination until `limit` pages are read or no results are left
+    async fn search_pages(&self, cql: &str, limit: Option<usize>) -> Result<Vec<PageContent>> {
+        let mut pages = Vec::new();
+        loop {
+            let page_size = limit.map_or(SEARCH_PAGE_SIZE, |limit| {
+                (limit - pages.len()).min(SEARCH_PAGE_SIZE)
+            });
+            let (results, more) = self.search_page(cql, pages.len(), page_size).await?;
+            pages.extend(results);
+            if !more || limit.is_some_and(|limit| pages.len() >= limit) {
+                return Ok(pages);
+            }
+        }
+    }
+
+    /// Replace the body of `current`, the page as last fetched, with the
+    /// Markdown `content`; unchanged when the page already reads as it
+    async fn replace_markdown(&self, current: PageContent, content: &str) -> Result<PublishResult> {
+        let storage = if xhtml::to_markdown(&current.body.storage.value).trim() == content.trim() {
+            current.body.storage.value.clone()
+        } else {
+            ConfluenceWriter::markdown_to_storage_format(content)
+        };
+        self.replace_content(current, &storage).await
+    }
+
+    /// Replace the body of `current`, the page as last fetched
+    async fn replace_content(&self, current: PageContent, content: &str) -> Result<PublishResult> {
+        if current.body.storage.value == content {
+            return Ok(PublishResult {
+                document_id: current.id,
+                url: String::new(),
+                version: current
+                    .version
+                    .as_ref()
+                    .map(|v| v.number as u32)
+                    .unwrap_or(1),
+                status: PublishStatus::NoChanges,
+            });
+        }
+
+        let doc = Document {
+            id: current.id.clone(),
+            title: current.title.clone(),
+            content: content.to_string(),
+            url: None,
+            parent_id: None,
+            metadata: DocumentMetadata::default(),
+        };
+
+        let updated_page = self
+            .update_page(&current.id, &doc, current.version.as_ref())
+            .await?;
+
+        let url = if self.config.is_cloud {
+            format!(
+                "{}/wiki/spaces/{}/pages/{}",
+                self.config.base_url.trim_end_matches('/'),
+                updated_page.space.key,
+                updated_page.id
+            )
+        } else {
+            format!(
+                "{}/pages/viewpage.action?pageId={}",
+                self.config.base_url.trim_end_matches('/'),
+                updated_page.id
+            )
+        };
+
+        Ok(PublishResult {
+            document_id: current.id,
+            url,
+            version: updated_page.version.map(|v| v.number as u32).unwrap_or(2),
+            status: PublishStatus::Updated,
+        })
+    }
+
     async fn create_page(&self, doc: &Document) -> Result<PageContent> {
         let page = PageContent {
             id: String::new(),
@@ -210,6 +445,8 @@ impl ConfluenceProvider {
             page_type: "page".to_string(),
             status: "current".to_string(),
             version: None,
+            history: None,
+            metadata: None,
         };
 
         let endpoint = "content";
@@ -228,15 +465,23 @@ impl ConfluenceProvider {
             }]);
         }
 
-        // Add labels if configured
-        if !self.config.default_labels.is_empty() {
-            let mut labels = vec![];
-            for label in &self.config.default_labels {
-                labels.push(serde_json::json!({
-                    "prefix": "global",
-                    "name": label
-                }));
+        // Labels of the document and the configured default labels
+        let mut labels: Vec<&String> = self.config.default_labels.iter().collect();
+        for label in &doc.metadata.labels {
+            if !labels.contains(&label) {
+                labels.push(label);
             }
+        }
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .into_iter()
+                .map(|label| {
+                    serde_json::json!({
+                        "prefix": "global",
+                        "name": label
+                    })
+                })
+                .collect();
             body["metadata"]["labels"] = serde_json::json!(labels);
         }
 
@@ -244,16 +489,16 @@ impl ConfluenceProvider {
             .await
     }
 
-    async fn update_page(&self, page_id: &str, doc: &Document) -> Result<PageContent> {
-        // Get current page to retrieve version number
-        let current_page = self
-            .get_page_by_id(page_id)
-            .await?
-            .ok_or_else(|| KtmeError::DocumentNotFound(page_id.to_string()))?;
-
-        // Increment version
+    /// Replace the page; `current` is its version as last fetched
+    async fn update_page(
+        &self,
+        page_id: &str,
+        doc: &Document,
+        current: Option<&ConfluenceVersion>,
+    ) -> Result<PageContent> {
         let new_version = ConfluenceVersion {
-            number: current_page.version.as_ref().map_or(1, |v| v.number + 1),
+            number: current.map_or(1, |v| v.number + 1),
+            when: None,
         };
 
         let update = PageUpdate {
@@ -277,6 +522,31 @@ impl ConfluenceProvider {
             .await
     }
 
+    /// Change the title of a page, keeping its content; returns the previous
+    /// title, `None` when the page does not exist
+    pub async fn retitle_page(
+        &self,
+        page_id: &str,
+        title: impl FnOnce(&str) -> String,
+    ) -> Result<Option<String>> {
+        let Some(current) = self.get_page_by_id(page_id).await? else {
+            return Ok(None);
+        };
+        let doc = Document {
+            id: current.id.clone(),
+            title: title(&current.title),
+            content: current.body.storage.value.clone(),
+            url: None,
+            parent_id: None,
+            metadata: DocumentMetadata::default(),
+        };
+        if doc.title != current.title {
+            self.update_page(page_id, &doc, current.version.as_ref())
+                .await?;
+        }
+        Ok(Some(current.title))
+    }
+
     fn convert_to_document(&self, page: PageContent) -> Document {
         let url = if self.config.is_cloud {
             format!(
@@ -296,16 +566,10 @@ impl ConfluenceProvider {
         Document {
             id: page.id,
             title: page.title,
-            content: page.body.storage.value,
+            content: xhtml::to_markdown(&page.body.storage.value),
             url: Some(url),
             parent_id: None,
-            metadata: DocumentMetadata {
-                created_at: None,
-                updated_at: None,
-                author: None,
-                version: page.version.map(|v| v.number as u32),
-                labels: vec![],
-            },
+            metadata: page_metadata(page.version, page.history, page.metadata),
         }
     }
 }
@@ -324,6 +588,7 @@ impl DocumentProvider for ConfluenceProvider {
             .await
         {
             Ok(_) => Ok(true),
+            Err(e) if e.class() == ErrorClass::Auth => Err(e),
             Err(_) => Ok(false),
         }
     }
@@ -337,14 +602,10 @@ impl DocumentProvider for ConfluenceProvider {
     }
 
     async fn find_document(&self, title: &str) -> Result<Option<Document>> {
-        match self.search_page_by_title(title).await {
-            Ok(Some(page)) => {
-                let page_content = self.get_page_by_id(&page.id).await?;
-                Ok(page_content.map(|p| self.convert_to_document(p)))
-            }
-            Ok(None) => Ok(None),
-            Err(e) => Err(e),
-        }
+        Ok(self
+            .search_page_by_title(title)
+            .await?
+            .map(|page| self.convert_to_document(page)))
     }
 
     async fn create_document(&self, doc: &Document) -> Result<PublishResult> {
@@ -353,7 +614,11 @@ impl DocumentProvider for ConfluenceProvider {
             return Err(KtmeError::DocumentExists(doc.title.clone()));
         }
 
-        let page = self.create_page(doc).await?;
+        let doc = Document {
+            content: ConfluenceWriter::markdown_to_storage_format(&doc.content),
+            ..doc.clone()
+        };
+        let page = self.create_page(&doc).await?;
 
         let url = if self.config.is_cloud {
             format!(
@@ -384,52 +649,7 @@ impl DocumentProvider for ConfluenceProvider {
             .await?
             .ok_or_else(|| KtmeError::DocumentNotFound(id.to_string()))?;
 
-        // Check if content is the same
-        if current_page.body.storage.value == content {
-            return Ok(PublishResult {
-                document_id: id.to_string(),
-                url: String::new(),
-                version: current_page
-                    .version
-                    .as_ref()
-                    .map(|v| v.number as u32)
-                    .unwrap_or(1),
-                status: PublishStatus::NoChanges,
-            });
-        }
-
-        let doc = Document {
-            id: id.to_string(),
-            title: current_page.title.clone(),
-            content: content.to_string(),
-            url: None,
-            parent_id: None,
-            metadata: DocumentMetadata::default(),
-        };
-
-        let updated_page = self.update_page(id, &doc).await?;
-
-        let url = if self.config.is_cloud {
-            format!(
-                "{}/wiki/spaces/{}/pages/{}",
-                self.config.base_url.trim_end_matches('/'),
-                updated_page.space.key,
-                updated_page.id
-            )
-        } else {
-            format!(
-                "{}/pages/viewpage.action?pageId={}",
-                self.config.base_url.trim_end_matches('/'),
-                updated_page.id
-            )
-        };
-
-        Ok(PublishResult {
-            document_id: id.to_string(),
-            url,
-            version: updated_page.version.map(|v| v.number as u32).unwrap_or(2),
-            status: PublishStatus::Updated,
-        })
+        self.replace_markdown(current_page, content).await
     }
 
     async fn update_section(
@@ -443,46 +663,16 @@ impl DocumentProvider for ConfluenceProvider {
             .await?
             .ok_or_else(|| KtmeError::DocumentNotFound(id.to_string()))?;
 
-        // For Confluence, we'll append the section content
-        let section_header = format!("h2. {}", section);
-        let new_content = if current_page.body.storage.value.contains(&section_header) {
-            // Replace existing section
-            let start_pattern = format!("h2. {}", section);
-            let start = current_page
-                .body
-                .storage
-                .value
-                .find(&start_pattern)
-                .unwrap_or(0);
-
-            let next_h2 = current_page.body.storage.value[start + 1..]
-                .find("h2. ")
-                .map(|pos| start + 1 + pos);
-
-            if let Some(_end) = next_h2 {
-                format!(
-                    "{}\n{}\n\n{}",
-                    &current_page.body.storage.value[..start],
-                    &section_header,
-                    content
-                )
-            } else {
-                format!(
-                    "{}\n{}\n\n{}",
-                    &current_page.body.storage.value[..start],
-                    &section_header,
-                    content
-                )
-            }
-        } else {
-            // Append new section
-            format!(
-                "{}\n\nh2. {}\n\n{}",
-                current_page.body.storage.value, section, content
-            )
-        };
+        let mut document =
+            SectionedDocument::parse(&current_page.body.storage.value, SectionFormat::Storage);
+        document.update_section(
+            section,
+            &ConfluenceWriter::markdown_to_storage_format(content),
+            UpdateMode::Replace,
+        );
+        let new_content = document.render();
 
-        self.update_document(id, &new_content).await
+        self.replace_content(current_page, &new_content).await
     }
 
     async fn delete_document(&self, id: &str) -> Result<()> {
@@ -494,44 +684,62 @@ impl DocumentProvider for ConfluenceProvider {
         Ok(())
     }
 
-    async fn list_documents(&self, container: &str) -> Result<Vec<Document>> {
-        let cql = format!("space={}", urlencoding::encode(container));
-        let endpoint = format!("content/search?cql={}&expand=version,space&limit=100", cql);
-
-        let response: SearchResponse = self
-            .make_request(reqwest::Method::GET, &endpoint, None)
+    async fn list_documents(
+        &self,
+        container: &str,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<DocumentPage> {
+        let start = parse_offset(cursor)?;
+        let cql = format!("space=\"{}\"", cql_escape(container));
+        let (pages, more) = self
+            .search_page(&cql, start, limit.unwrap_or(SEARCH_PAGE_SIZE))
             .await?;
-
-        let mut documents = Vec::new();
-        for result in response.results {
-            if let Some(page) = self.get_page_by_id(&result.id).await? {
-                documents.push(self.convert_to_document(page));
-            }
-        }
-
-        Ok(documents)
+        let next_cursor = more.then(|| (start + pages.len()).to_string());
+        Ok(DocumentPage {
+            documents: pages
+                .into_iter()
+                .map(|page| self.convert_to_document(page))
+                .collect(),
+            next_cursor,
+        })
     }
 
-    async fn search_documents(&self, query: &str) -> Result<Vec<Document>> {
+    async fn search_documents(&self, query: &str, limit: Option<usize>) -> Result<Vec<Document>> {
         let cql = format!(
-            "space={} and text~\"{}\"",
-            urlencoding::encode(&self.config.space_key),
-            urlencoding::encode(query)
+            "space=\"{}\" and text~\"{}\"",
+            cql_escape(&self.config.space_key),
+            cql_escape(query)
         );
-        let endpoint = format!("content/search?cql={}&expand=version,space&limit=50", cql);
-
-        let response: SearchResponse = self
-            .make_request(reqwest::Method::GET, &endpoint, None)
-            .await?;
+        Ok(self
+            .search_pages(&cql, limit)
+            .await?
+            .into_iter()
+            .map(|page| self.convert_to_document(page))
+            .collect())
+    }
 
-        let mut documents = Vec::new();
-        for result in response.results {
-            if let Some(page) = self.get_page_by_id(&result.id).await? {
-                documents.push(self.convert_to_document(page));
+    /// Fetches the current pages with one search per 50 ids instead of one
+    /// request per page
+    async fn bulk_update(&self, updates: Vec<(String, String)>) -> Result<Vec<PublishResult>> {
+        let mut current = HashMap::new();
+        for chunk in updates.chunks(SEARCH_PAGE_SIZE) {
+            let ids: Vec<&str> = chunk.iter().map(|(id, _)| id.as_str()).collect();
+            let cql = format!("id in ({})", ids.join(","));
+            for page in self.search_pages(&cql, Some(chunk.len())).await? {
+                current.insert(page.id.clone(), page);
             }
         }
 
-        Ok(documents)
+        let mut results = Vec::with_capacity(updates.len());
+        for (id, content) in updates {
+            let result = match current.remove(&id) {
+                Some(page) => self.replace_markdown(page, &content).await,
+                None => Err(KtmeError::DocumentNotFound(id.clone())),
+            };
+            results.push(result.unwrap_or_else(|e| PublishResult::failed(&id, e)));
+        }
+        Ok(results)
     }
 
     fn config(&self) -> &super::config::ProviderConfig {
@@ -550,6 +758,79 @@ impl DocumentProvider for ConfluenceProvider {
     }
 }
 
+fn rest_url(base: &str, path: &str) -> String {
+    let base = base.trim_end_matches('/');
+    format!("{}/rest/api/{}", base, path.trim_start_matches('/'))
+}
+
+fn page_metadata(
+    version: Option<ConfluenceVersion>,
+    history: Option<PageHistory>,
+    metadata: Option<PageMetadata>,
+) -> DocumentMetadata {
+    let (created_at, author) = match history {
+        Some(history) => (
+            history.created_date,
+            history.created_by.and_then(|user| user.display_name),
+        ),
+        None => (None, None),
+    };
+    DocumentMetadata {
+        created_at,
+        updated_at: version.as_ref().and_then(|v| v.when.clone()),
+        author,
+        version: version.map(|v| v.number as u32),
+        labels: metadata
+            .map(|m| m.labels.results.into_iter().map(|l| l.name).collect())
+            .unwrap_or_default(),
+        snippets: vec![],
+    }
+}
+
+/// `value` for use inside a quoted CQL string
+fn cql_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn is_throttled(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::TOO_MANY_REQUESTS | reqwest::StatusCode::SERVICE_UNAVAILABLE
+    )
+}
+
+/// Whether a failed response says the space or site ran out of storage
+fn is_quota_error(status: reqwest::StatusCode, body: &str) -> bool {
+    let body = body.to_lowercase();
+    status == reqwest::StatusCode::INSUFFICIENT_STORAGE
+        || (status.is_client_error() && (body.contains("quota") || body.contains("storage limit")))
+}
+
+/// Wait before retry number `attempt + 1`: Retry-After when the response
+/// has one, exponential backoff otherwise
+fn retry_delay(headers: &reqwest::header::HeaderMap, attempt: u32) -> Duration {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, chrono::Utc::now()))
+        .unwrap_or_else(|| INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(attempt)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Retry-After as delay seconds or an HTTP date
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -564,6 +845,9 @@ mod tests {
             default_parent_id: None,
             default_labels: vec!["documentation".to_string()],
             is_cloud: true,
+            oauth: None,
+            max_retries: 5,
+            request_interval_ms: 100,
         };
 
         let provider = ConfluenceProvider::new(config);
@@ -581,6 +865,9 @@ mod tests {
             default_parent_id: None,
             default_labels: vec![],
             is_cloud: true,
+            oauth: None,
+            max_retries: 5,
+            request_interval_ms: 100,
         };
 
         let provider = ConfluenceProvider::new(config);
@@ -593,4 +880,116 @@ mod tests {
             "https://example.atlassian.net/rest/api/content/123"
         );
     }
+
+    #[test]
+    fn test_page_metadata() {
+        let page: PageContent = serde_json::from_value(serde_json::json!({
+            "id": "42",
+            "title": "Payments",
+            "space": {"key": "DEV"},
+            "body": {"storage": {"value": "<p>Hi</p>", "representation": "storage"}},
+            "type": "page",
+            "status": "current",
+            "version": {"number": 3, "when": "2026-10-02T10:00:00.000Z"},
+            "history": {
+                "createdDate": "2026-09-01T08:00:00.000Z",
+                "createdBy": {"displayName": "Ada Lovelace"}
+            },
+            "metadata": {"labels": {"results": [{"name": "payments"}, {"name": "api"}]}}
+        }))
+        .unwrap();
+
+        let metadata = page_metadata(page.version, page.history, page.metadata);
+        assert_eq!(
+            metadata.created_at.as_deref(),
+            Some("2026-09-01T08:00:00.000Z")
+        );
+        assert_eq!(
+            metadata.updated_at.as_deref(),
+            Some("2026-10-02T10:00:00.000Z")
+        );
+        assert_eq!(metadata.author.as_deref(), Some("Ada Lovelace"));
+        assert_eq!(metadata.version, Some(3));
+        assert_eq!(metadata.labels, ["payments", "api"]);
+
+        // Read-only fields are not sent back on update
+        let version = serde_json::to_value(ConfluenceVersion {
+            number: 4,
+            when: Some("2026-10-02T10:00:00.000Z".to_string()),
+        })
+        .unwrap();
+        assert_eq!(version, serde_json::json!({"number": 4}));
+    }
+
+    #[test]
+    fn test_search_response_pagination() {
+        let page: SearchResponse = serde_json::from_value(serde_json::json!({
+            "results": [{
+                "id": "42",
+                "title": "Payments",
+                "space": {"key": "DEV"},
+                "body": {"storage": {"value": "<p>Hi</p>", "representation": "storage"}},
+                "type": "page",
+                "status": "current",
+                "version": {"number": 3}
+            }],
+            "start": 0,
+            "size": 1,
+            "_links": {"next": "/rest/api/content/search?start=1", "base": "https://x"}
+        }))
+        .unwrap();
+        assert!(page.links.contains_key("next"));
+        assert_eq!(page.results[0].body.storage.value, "<p>Hi</p>");
+
+        assert_eq!(cql_escape(r#"say "hi""#), r#"say \"hi\""#);
+    }
+
+    #[test]
+    fn test_retry_after_seconds_and_date() {
+        let now = chrono::DateTime::parse_from_rfc3339("2026-10-17T08:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after("30", now), Some(Duration::from_secs(30)));
+        assert_eq!(
+            parse_retry_after("Sat, 17 Oct 2026 08:00:45 GMT", now),
+            Some(Duration::from_secs(45))
+        );
+        assert_eq!(
+            parse_retry_after("Sat, 17 Oct 2026 07:59:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_and_caps() {
+        let empty = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_delay(&empty, 0), Duration::from_secs(1));
+        assert_eq!(retry_delay(&empty, 3), Duration::from_secs(8));
+        assert_eq!(retry_delay(&empty, 10), MAX_RETRY_DELAY);
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(retry_delay(&headers, 4), Duration::from_secs(7));
+        headers.insert(reqwest::header::RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(retry_delay(&headers, 0), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_quota_errors() {
+        assert!(is_quota_error(
+            reqwest::StatusCode::FORBIDDEN,
+            r#"{"message":"You have exceeded your storage quota"}"#
+        ));
+        assert!(is_quota_error(
+            reqwest::StatusCode::INSUFFICIENT_STORAGE,
+            ""
+        ));
+        assert!(!is_quota_error(
+            reqwest::StatusCode::FORBIDDEN,
+            r#"{"message":"Not permitted to edit this page"}"#
+        ));
+        assert!(is_throttled(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_throttled(reqwest::StatusCode::BAD_REQUEST));
+    }
 }
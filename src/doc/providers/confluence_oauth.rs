@@ -0,0 +1,724 @@
+//! OAuth 2.0 authorization-code flow (3LO) for Confluence Cloud
+//!
+//! `ktme auth login confluence` opens the Atlassian consent page, receives
+//! the authorization code on a local redirect listener and exchanges it for
+//! an access and a refresh token. Tokens are kept in the OS keyring when ktme
+//! is built with the `keyring` feature, otherwise in `oauth-tokens.json` in
+//! the config directory (readable by the owner only). Access tokens are
+//! refreshed shortly before they expire; Atlassian rotates the refresh token
+//! on every refresh.
+//!
+//! OAuth requests go through `api.atlassian.com` with the site's cloud id
+//! instead of the site URL, so callers use [`OAuthAccess::api_base`] as the
+//! Confluence base URL.
+
+use crate::error::{KtmeError, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Atlassian authorization server
+pub const AUTH_URL: &str = "https://auth.atlassian.com";
+
+/// Atlassian API gateway for OAuth requests
+pub const API_URL: &str = "https://api.atlassian.com";
+
+/// Scopes requested when none are configured
+const DEFAULT_SCOPES: &[&str] = &[
+    "read:confluence-content.all",
+    "write:confluence-content",
+    "read:confluence-space.summary",
+    "search:confluence",
+    "offline_access",
+];
+
+/// How long the redirect listener waits for the browser
+const LOGIN_TIMEOUT_SECS: u64 = 300;
+
+/// Access tokens this close to expiry are refreshed before use
+const REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Serializes refreshes: a rotated refresh token is only valid once
+static REFRESH_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Credentials of the OAuth 2.0 app registered in the Atlassian developer
+/// console
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuthSettings {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Port of the redirect listener; the app's callback URL must be
+    /// `http://localhost:<port>/callback`
+    #[serde(default = "default_redirect_port")]
+    pub redirect_port: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
+fn default_redirect_port() -> u16 {
+    8749
+}
+
+impl OAuthSettings {
+    /// Settings from the `[confluence]` section, for `auth_type = "oauth"`.
+    /// The client secret falls back to CONFLUENCE_OAUTH_CLIENT_SECRET.
+    pub fn from_config(config: &crate::config::ConfluenceConfig) -> Result<Self> {
+        let client_id = config.oauth_client_id.clone().ok_or_else(|| {
+            KtmeError::Config(
+                "Confluence OAuth needs [confluence] oauth_client_id in config.toml".to_string(),
+            )
+        })?;
+        let client_secret = config
+            .oauth_client_secret
+            .clone()
+            .or_else(|| std::env::var("CONFLUENCE_OAUTH_CLIENT_SECRET").ok())
+            .ok_or_else(|| {
+                KtmeError::Config(
+                    "Confluence OAuth needs [confluence] oauth_client_secret or CONFLUENCE_OAUTH_CLIENT_SECRET"
+                        .to_string(),
+                )
+            })?;
+
+        Ok(Self {
+            client_id,
+            client_secret,
+            redirect_port: config
+                .oauth_redirect_port
+                .unwrap_or_else(default_redirect_port),
+            scopes: Vec::new(),
+        })
+    }
+
+    fn scopes(&self) -> String {
+        if self.scopes.is_empty() {
+            DEFAULT_SCOPES.join(" ")
+        } else {
+            self.scopes.join(" ")
+        }
+    }
+}
+
+/// Tokens of one signed-in Confluence site
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    /// Atlassian cloud id of the site
+    pub cloud_id: String,
+}
+
+impl OAuthTokens {
+    fn needs_refresh(&self) -> bool {
+        self.expires_at - Duration::seconds(REFRESH_MARGIN_SECS) <= Utc::now()
+    }
+}
+
+/// A valid access token and the base URL to use it with
+#[derive(Debug, Clone)]
+pub struct OAuthAccess {
+    pub access_token: String,
+    /// Confluence base URL through the API gateway, e.g.
+    /// `https://api.atlassian.com/ex/confluence/<cloud id>/wiki`
+    pub api_base: String,
+}
+
+/// Where signed-in tokens are kept
+#[derive(Debug, Clone)]
+pub enum TokenStore {
+    /// The OS credential store (macOS Keychain, Windows Credential Manager,
+    /// Secret Service)
+    #[cfg(feature = "keyring")]
+    Keyring,
+    /// A JSON file of tokens by site
+    File(PathBuf),
+}
+
+/// Keyring service name of the stored tokens
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "ktme";
+
+impl TokenStore {
+    /// The keyring with the `keyring` feature, `oauth-tokens.json` in the
+    /// config directory otherwise
+    pub fn default_store() -> Result<Self> {
+        #[cfg(feature = "keyring")]
+        {
+            Ok(Self::Keyring)
+        }
+        #[cfg(not(feature = "keyring"))]
+        {
+            Ok(Self::File(
+                crate::config::Config::config_dir()?.join("oauth-tokens.json"),
+            ))
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            #[cfg(feature = "keyring")]
+            Self::Keyring => "the OS keyring".to_string(),
+            Self::File(path) => path.display().to_string(),
+        }
+    }
+
+    pub fn load(&self, key: &str) -> Result<Option<OAuthTokens>> {
+        match self {
+            #[cfg(feature = "keyring")]
+            Self::Keyring => match keyring_entry(key)?.get_password() {
+                Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(keyring_error(e)),
+            },
+            Self::File(path) => Ok(Self::read_file(path)?.remove(key)),
+        }
+    }
+
+    pub fn save(&self, key: &str, tokens: &OAuthTokens) -> Result<()> {
+        match self {
+            #[cfg(feature = "keyring")]
+            Self::Keyring => keyring_entry(key)?
+                .set_password(&serde_json::to_string(tokens)?)
+                .map_err(keyring_error),
+            Self::File(path) => {
+                let mut all = Self::read_file(path)?;
+                all.insert(key.to_string(), tokens.clone());
+                Self::write_file(path, &all)
+            }
+        }
+    }
+
+    /// Remove the tokens of `key`; false when there were none
+    pub fn delete(&self, key: &str) -> Result<bool> {
+        match self {
+            #[cfg(feature = "keyring")]
+            Self::Keyring => match keyring_entry(key)?.delete_credential() {
+                Ok(()) => Ok(true),
+                Err(keyring::Error::NoEntry) => Ok(false),
+                Err(e) => Err(keyring_error(e)),
+            },
+            Self::File(path) => {
+                let mut all = Self::read_file(path)?;
+                let removed = all.remove(key).is_some();
+                if removed {
+                    Self::write_file(path, &all)?;
+                }
+                Ok(removed)
+            }
+        }
+    }
+
+    fn read_file(path: &Path) -> Result<HashMap<String, OAuthTokens>> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_file(path: &Path, tokens: &HashMap<String, OAuthTokens>) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(tokens)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(key: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, key).map_err(keyring_error)
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_error(e: keyring::Error) -> KtmeError {
+    KtmeError::Storage(format!("OS keyring: {}", e))
+}
+
+/// Token endpoint response
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+    refresh_token: Option<String>,
+}
+
+/// Entry of `accessible-resources`: a site the token grants access to
+#[derive(Deserialize)]
+struct AccessibleResource {
+    id: String,
+    url: String,
+}
+
+/// OAuth client for one Confluence Cloud site
+#[derive(Debug, Clone)]
+pub struct ConfluenceOAuth {
+    settings: OAuthSettings,
+    site_url: String,
+    store: TokenStore,
+    auth_url: String,
+    api_url: String,
+    client: reqwest::Client,
+}
+
+impl ConfluenceOAuth {
+    /// Client for the site at `site_url`, e.g. `https://company.atlassian.net`
+    /// (a trailing `/wiki` is ignored)
+    pub fn new(settings: OAuthSettings, site_url: &str, store: TokenStore) -> Self {
+        Self {
+            settings,
+            site_url: normalize_site(site_url),
+            store,
+            auth_url: AUTH_URL.to_string(),
+            api_url: API_URL.to_string(),
+            client: crate::http::client("confluence")
+                .build()
+                .unwrap_or_else(|_| reqwest::Client::new()),
+        }
+    }
+
+    /// Client for the `[confluence]` section of the config file
+    pub fn from_config(config: &crate::config::ConfluenceConfig) -> Result<Self> {
+        let site_url = config.base_url.as_deref().ok_or_else(|| {
+            KtmeError::Config(
+                "Confluence base_url not configured. Please set [confluence] base_url in config.toml"
+                    .to_string(),
+            )
+        })?;
+        Ok(Self::new(
+            OAuthSettings::from_config(config)?,
+            site_url,
+            TokenStore::default_store()?,
+        ))
+    }
+
+    /// Use other authorization and API servers
+    #[cfg(test)]
+    fn with_endpoints(mut self, auth_url: &str, api_url: &str) -> Self {
+        self.auth_url = auth_url.trim_end_matches('/').to_string();
+        self.api_url = api_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn site_url(&self) -> &str {
+        &self.site_url
+    }
+
+    pub fn store(&self) -> &TokenStore {
+        &self.store
+    }
+
+    pub fn redirect_uri(&self) -> String {
+        format!("http://localhost:{}/callback", self.settings.redirect_port)
+    }
+
+    /// Consent page the user signs in on
+    pub fn authorize_url(&self, state: &str) -> String {
+        format!(
+            "{}/authorize?audience=api.atlassian.com&client_id={}&scope={}&redirect_uri={}&state={}&response_type=code&prompt=consent",
+            self.auth_url,
+            urlencoding::encode(&self.settings.client_id),
+            urlencoding::encode(&self.settings.scopes()),
+            urlencoding::encode(&self.redirect_uri()),
+            urlencoding::encode(state)
+        )
+    }
+
+    /// Run the authorization-code flow and store the tokens. `show` receives
+    /// the consent page URL once the redirect listener is ready.
+    pub async fn login(&self, show: impl FnOnce(&str)) -> Result<OAuthTokens> {
+        crate::http::ensure_online("Signing in to Confluence")?;
+        let listener = TcpListener::bind(("127.0.0.1", self.settings.redirect_port))
+            .await
+            .map_err(|e| {
+                KtmeError::Config(format!(
+                    "Cannot listen for the OAuth redirect on port {}: {}",
+                    self.settings.redirect_port, e
+                ))
+            })?;
+        let state = uuid::Uuid::new_v4().simple().to_string();
+        show(&self.authorize_url(&state));
+
+        let code = tokio::time::timeout(
+            std::time::Duration::from_secs(LOGIN_TIMEOUT_SECS),
+            receive_code(&listener, &state),
+        )
+        .await
+        .map_err(|_| {
+            KtmeError::NetworkError(format!(
+                "No OAuth redirect received within {} seconds",
+                LOGIN_TIMEOUT_SECS
+            ))
+        })??;
+
+        let response = self
+            .token_request(serde_json::json!({
+                "grant_type": "authorization_code",
+                "client_id": self.settings.client_id,
+                "client_secret": self.settings.client_secret,
+                "code": code,
+                "redirect_uri": self.redirect_uri(),
+            }))
+            .await?;
+        let cloud_id = self.cloud_id(&response.access_token).await?;
+        let tokens = OAuthTokens {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: Utc::now() + Duration::seconds(response.expires_in),
+            cloud_id,
+        };
+        self.store.save(&self.store_key(), &tokens)?;
+
+        Ok(tokens)
+    }
+
+    /// The stored tokens, if signed in
+    pub fn tokens(&self) -> Result<Option<OAuthTokens>> {
+        self.store.load(&self.store_key())
+    }
+
+    /// Forget the stored tokens; false when not signed in
+    pub fn logout(&self) -> Result<bool> {
+        self.store.delete(&self.store_key())
+    }
+
+    /// A valid access token, refreshed and stored again when it is about to
+    /// expire
+    pub async fn access(&self) -> Result<OAuthAccess> {
+        let _guard = REFRESH_LOCK.lock().await;
+
+        let mut tokens = self.tokens()?.ok_or_else(|| KtmeError::NotSignedIn {
+            service: "Confluence",
+            reason: format!("no OAuth tokens for {}", self.site_url),
+        })?;
+
+        if tokens.needs_refresh() {
+            let refresh_token = tokens.refresh_token.clone().ok_or_else(|| {
+                KtmeError::NotSignedIn {
+                    service: "Confluence",
+                    reason: "the access token expired and there is no refresh token (request the offline_access scope)".to_string(),
+                }
+            })?;
+            tracing::info!("Refreshing the Confluence OAuth access token");
+            let response = self
+                .token_request(serde_json::json!({
+                    "grant_type": "refresh_token",
+                    "client_id": self.settings.client_id,
+                    "client_secret": self.settings.client_secret,
+                    "refresh_token": refresh_token,
+                }))
+                .await?;
+            tokens.access_token = response.access_token;
+            tokens.expires_at = Utc::now() + Duration::seconds(response.expires_in);
+            if response.refresh_token.is_some() {
+                tokens.refresh_token = response.refresh_token;
+            }
+            self.store.save(&self.store_key(), &tokens)?;
+        }
+
+        Ok(OAuthAccess {
+            api_base: format!("{}/ex/confluence/{}/wiki", self.api_url, tokens.cloud_id),
+            access_token: tokens.access_token,
+        })
+    }
+
+    fn store_key(&self) -> String {
+        format!("confluence-oauth:{}", self.site_url)
+    }
+
+    async fn token_request(&self, body: serde_json::Value) -> Result<TokenResponse> {
+        let response = self
+            .client
+            .post(format!("{}/oauth/token", self.auth_url))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| crate::http::request_error("OAuth token request failed", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_body = response.text().await.unwrap_or_default();
+            // invalid_grant: the code or refresh token expired or was revoked
+            if status.is_client_error() {
+                return Err(KtmeError::NotSignedIn {
+                    service: "Confluence",
+                    reason: format!(
+                        "the token request was rejected ({}): {}",
+                        status, error_body
+                    ),
+                });
+            }
+            return Err(KtmeError::ApiError(format!(
+                "OAuth token request failed ({}): {}",
+                status, error_body
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| KtmeError::DeserializationError(format!("OAuth token response: {}", e)))
+    }
+
+    /// Cloud id of the configured site among the sites the token can access
+    async fn cloud_id(&self, access_token: &str) -> Result<String> {
+        let response = self
+            .client
+            .get(format!("{}/oauth/token/accessible-resources", self.api_url))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .map_err(|e| {
+                crate::http::request_error("Listing accessible Atlassian sites failed", e)
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(KtmeError::from_status(
+                "Confluence",
+                status,
+                KtmeError::ApiError(format!(
+                    "Listing accessible Atlassian sites failed: {}",
+                    status
+                )),
+            ));
+        }
+        let resources: Vec<AccessibleResource> = response
+            .json()
+            .await
+            .map_err(|e| KtmeError::DeserializationError(e.to_string()))?;
+
+        resources
+            .iter()
+            .find(|resource| normalize_site(&resource.url) == self.site_url)
+            .map(|resource| resource.id.clone())
+            .ok_or_else(|| {
+                KtmeError::Config(format!(
+                    "The OAuth app was not granted access to {}; accessible sites: {}",
+                    self.site_url,
+                    resources
+                        .iter()
+                        .map(|r| r.url.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+/// `https://company.atlassian.net/wiki/` -> `https://company.atlassian.net`
+fn normalize_site(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    url.strip_suffix("/wiki").unwrap_or(url).to_string()
+}
+
+/// Serve the redirect listener until the browser delivers the authorization
+/// code for `state`
+async fn receive_code(listener: &TcpListener, state: &str) -> Result<String> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut buffer = vec![0; 8192];
+        let read = stream.read(&mut buffer).await?;
+        let request = String::from_utf8_lossy(&buffer[..read]);
+        let target = request
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/");
+
+        let Ok(url) = reqwest::Url::parse(&format!("http://localhost{}", target)) else {
+            continue;
+        };
+        if url.path() != "/callback" {
+            // Browsers also ask for /favicon.ico
+            let _ = stream
+                .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+                .await;
+            continue;
+        }
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+        let (result, page) = if let Some(error) = params.get("error") {
+            (
+                Err(KtmeError::NotSignedIn {
+                    service: "Confluence",
+                    reason: format!("authorization was denied: {}", error),
+                }),
+                "Authorization was denied. You can close this window.",
+            )
+        } else if params.get("state").map(String::as_str) != Some(state) {
+            (
+                Err(KtmeError::InvalidInput(
+                    "OAuth redirect with an unexpected state".to_string(),
+                )),
+                "Unexpected sign-in response. Run ktme auth login again.",
+            )
+        } else if let Some(code) = params.get("code") {
+            (
+                Ok(code.clone()),
+                "Signed in to ktme. You can close this window.",
+            )
+        } else {
+            (
+                Err(KtmeError::InvalidInput(
+                    "OAuth redirect without an authorization code".to_string(),
+                )),
+                "Sign-in failed. Run ktme auth login again.",
+            )
+        };
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            page.len(),
+            page
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// HTTP server answering each request with the next canned JSON body and
+    /// returning the request lines it saw
+    fn serve(responses: Vec<&'static str>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let mut requests = Vec::new();
+            for body in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = vec![0; 8192];
+                let read = stream.read(&mut buffer).unwrap();
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                requests.push(request);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn settings(port: u16) -> OAuthSettings {
+        OAuthSettings {
+            client_id: "app".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_port: port,
+            scopes: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_flow_stores_tokens() {
+        let dir = TempDir::new().unwrap();
+        let (server, handle) = serve(vec![
+            r#"{"access_token": "access-1", "expires_in": 3600, "refresh_token": "refresh-1"}"#,
+            r#"[{"id": "other", "url": "https://other.atlassian.net"}, {"id": "cloud-42", "url": "https://docs.atlassian.net"}]"#,
+        ]);
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let oauth = ConfluenceOAuth::new(
+            settings(port),
+            "https://docs.atlassian.net/wiki/",
+            TokenStore::File(dir.path().join("tokens.json")),
+        )
+        .with_endpoints(&server, &server);
+
+        let tokens = oauth
+            .login(|url| {
+                let url = reqwest::Url::parse(url).unwrap();
+                let state = url
+                    .query_pairs()
+                    .find(|(k, _)| k == "state")
+                    .unwrap()
+                    .1
+                    .into_owned();
+                let redirect = format!(
+                    "http://127.0.0.1:{}/callback?code=abc&state={}",
+                    port, state
+                );
+                std::thread::spawn(move || reqwest::blocking::get(redirect).unwrap().text());
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(tokens.cloud_id, "cloud-42");
+        let requests = handle.join().unwrap();
+        assert!(requests[0].contains(r#""grant_type":"authorization_code""#));
+        assert!(requests[0].contains(r#""code":"abc""#));
+        assert!(requests[1].contains("Bearer access-1"));
+
+        let access = oauth.access().await.unwrap();
+        assert_eq!(access.access_token, "access-1");
+        assert_eq!(
+            access.api_base,
+            format!("{}/ex/confluence/cloud-42/wiki", server)
+        );
+        assert!(oauth.logout().unwrap());
+        assert!(oauth.tokens().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed() {
+        let dir = TempDir::new().unwrap();
+        let (server, handle) = serve(vec![
+            r#"{"access_token": "access-2", "expires_in": 3600, "refresh_token": "refresh-2"}"#,
+        ]);
+        let oauth = ConfluenceOAuth::new(
+            settings(0),
+            "https://docs.atlassian.net",
+            TokenStore::File(dir.path().join("tokens.json")),
+        )
+        .with_endpoints(&server, &server);
+        oauth
+            .store()
+            .save(
+                &oauth.store_key(),
+                &OAuthTokens {
+                    access_token: "access-1".to_string(),
+                    refresh_token: Some("refresh-1".to_string()),
+                    expires_at: Utc::now(),
+                    cloud_id: "cloud-42".to_string(),
+                },
+            )
+            .unwrap();
+
+        let access = oauth.access().await.unwrap();
+        assert_eq!(access.access_token, "access-2");
+        let requests = handle.join().unwrap();
+        assert!(requests[0].contains(r#""refresh_token":"refresh-1""#));
+
+        let stored = oauth.tokens().unwrap().unwrap();
+        assert_eq!(stored.refresh_token.as_deref(), Some("refresh-2"));
+        assert!(!stored.needs_refresh());
+
+        let signed_out = ConfluenceOAuth::new(
+            settings(0),
+            "https://elsewhere.atlassian.net",
+            TokenStore::File(dir.path().join("tokens.json")),
+        );
+        assert!(matches!(
+            signed_out.access().await,
+            Err(KtmeError::NotSignedIn { .. })
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+//! Document providers implemented by external executables
+//!
+//! An executable named `ktme-provider-<name>` on `PATH` provides the
+//! `<name>` provider, speaking the JSON-over-stdio contract described in
+//! [`crate::plugins`]:
+//!
+//! ```text
+//! request:  {"operation": "update_section", "config": {...}, "params": {"id": "...", "section": "...", "content": "..."}}
+//! response: {"result": {"document_id": "...", "url": "...", "version": 2, "status": "Updated"}}
+//!       or: {"error": "page 42 is locked"}
+//! ```
+//!
+//! `config` is the provider's stored configuration. Results use the same JSON
+//! shapes as `Document`, `DocumentPage` and `PublishResult`; operations that
+//! return nothing reply with `{"result": null}`.
+
+use super::{config::ProviderConfig, Document, DocumentPage, DocumentProvider, PublishResult};
+use crate::error::Result;
+use crate::plugins::Plugin;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+
+/// File name prefix of provider plugins
+pub const PLUGIN_PREFIX: &str = "ktme-provider-";
+
+/// The plugin providing `name`, if one is on `PATH`
+pub fn find(name: &str) -> Option<Plugin> {
+    crate::plugins::find(PLUGIN_PREFIX, name)
+}
+
+/// Provider backed by a `ktme-provider-<name>` executable
+pub struct ExternalProvider {
+    plugin: Plugin,
+    config: ProviderConfig,
+}
+
+impl ExternalProvider {
+    pub fn new(plugin: Plugin, config: ProviderConfig) -> Self {
+        Self { plugin, config }
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        operation: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        self.plugin
+            .invoke(operation, &self.config.config, params)
+            .await
+    }
+}
+
+#[async_trait]
+impl DocumentProvider for ExternalProvider {
+    fn name(&self) -> &str {
+        &self.plugin.name
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        self.call("health_check", serde_json::json!({})).await
+    }
+
+    async fn get_document(&self, id: &str) -> Result<Option<Document>> {
+        self.call("get_document", serde_json::json!({ "id": id }))
+            .await
+    }
+
+    async fn find_document(&self, title: &str) -> Result<Option<Document>> {
+        self.call("find_document", serde_json::json!({ "title": title }))
+            .await
+    }
+
+    async fn create_document(&self, doc: &Document) -> Result<PublishResult> {
+        self.call("create_document", serde_json::json!({ "document": doc }))
+            .await
+    }
+
+    async fn update_document(&self, id: &str, content: &str) -> Result<PublishResult> {
+        self.call(
+            "update_document",
+            serde_json::json!({ "id": id, "content": content }),
+        )
+        .await
+    }
+
+    async fn update_section(
+        &self,
+        id: &str,
+        section: &str,
+        content: &str,
+    ) -> Result<PublishResult> {
+        self.call(
+            "update_section",
+            serde_json::json!({ "id": id, "section": section, "content": content }),
+        )
+        .await
+    }
+
+    async fn delete_document(&self, id: &str) -> Result<()> {
+        self.call::<serde_json::Value>("delete_document", serde_json::json!({ "id": id }))
+            .await
+            .map(|_| ())
+    }
+
+    async fn list_documents(
+        &self,
+        container: &str,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<DocumentPage> {
+        self.call(
+            "list_documents",
+            serde_json::json!({ "container": container, "cursor": cursor, "limit": limit }),
+        )
+        .await
+    }
+
+    async fn search_documents(&self, query: &str, limit: Option<usize>) -> Result<Vec<Document>> {
+        self.call(
+            "search_documents",
+            serde_json::json!({ "query": query, "limit": limit }),
+        )
+        .await
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::doc::providers::PublishStatus;
+    use crate::plugins::tests::write_plugin;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_external_provider_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let plugin = write_plugin(
+            dir.path(),
+            PLUGIN_PREFIX,
+            "wiki",
+            r#"read request
+case "$request" in
+  *'"operation":"update_section"'*)
+    case "$request" in *'"space":"DOCS"'*) ;; *) exit 1 ;; esac
+    echo '{"result": {"document_id": "42", "url": "https://wiki/42", "version": 3, "status": "Updated"}}' ;;
+  *'"operation":"get_document"'*)
+    echo '{"result": null}' ;;
+  *)
+    echo "unexpected request: $request" >&2; exit 1 ;;
+esac
+"#,
+        );
+        let provider = ExternalProvider::new(
+            plugin,
+            ProviderConfig {
+                id: 1,
+                provider_type: "wiki".to_string(),
+                config: serde_json::json!({ "space": "DOCS" }),
+                is_default: false,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            },
+        );
+
+        let result = provider
+            .update_section("42", "Usage", "Run it.")
+            .await
+            .unwrap();
+        assert_eq!(result.document_id, "42");
+        assert_eq!(result.version, 3);
+        assert!(matches!(result.status, PublishStatus::Updated));
+
+        assert!(provider.get_document("missing").await.unwrap().is_none());
+        assert_eq!(provider.name(), "wiki");
+    }
+}
@@ -1,21 +1,74 @@
 use super::{
-    config::MarkdownConfig, Document, DocumentMetadata, DocumentProvider, PublishResult,
-    PublishStatus,
+    config::MarkdownConfig, parse_offset, Document, DocumentMetadata, DocumentPage,
+    DocumentProvider, PublishResult, PublishStatus,
 };
+use crate::doc::sections::{SectionFormat, SectionedDocument, UpdateMode};
 use crate::error::{KtmeError, Result};
+use crate::git::ownership::pattern_matches;
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+/// Files at the base path whose gitignore-style rules hide documents from
+/// listing and search
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ktmeignore"];
+
+/// Characters kept on each side of a match in search snippets
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Snippets kept per search result
+const MAX_SNIPPETS: usize = 3;
 
 /// Markdown file provider
 pub struct MarkdownProvider {
     config: MarkdownConfig,
     base_path: PathBuf,
+    /// Stamps of the files as `get_document` or `find_document` last read
+    /// them, checked and cleared when they are written back so that edits
+    /// made in between are not overwritten
+    stamps: Mutex<HashMap<PathBuf, FileStamp>>,
 }
 
 impl MarkdownProvider {
     pub fn new(config: MarkdownConfig) -> Self {
         let base_path = PathBuf::from(&config.base_path);
-        Self { config, base_path }
+        Self {
+            config,
+            base_path,
+            stamps: Mutex::default(),
+        }
+    }
+
+    /// Provider of the single file at `location`, keeping its extension
+    pub fn for_file(location: &str) -> Self {
+        let extension = Path::new(location)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        Self::new(MarkdownConfig {
+            base_path: String::new(),
+            extension: extension.to_string(),
+            auto_create_dirs: true,
+            backup: false,
+        })
+    }
+
+    /// Replace the document `id` with `content`, creating it when missing
+    pub async fn write_document(&self, id: &str, content: &str) -> Result<PublishResult> {
+        let path = self.resolve_path(id);
+        if path.exists() {
+            return self.update_document(id, content).await;
+        }
+
+        self.write_file(&path, content, None)?;
+        Ok(PublishResult {
+            document_id: id.to_string(),
+            url: path.to_string_lossy().to_string(),
+            version: 1,
+            status: PublishStatus::Created,
+        })
     }
 
     fn resolve_path(&self, location: &str) -> PathBuf {
@@ -41,14 +94,122 @@ impl MarkdownProvider {
         std::fs::read_to_string(path).map_err(KtmeError::Io)
     }
 
-    fn write_file(&self, path: &Path, content: &str) -> Result<()> {
+    /// Content of `path` with the stamp to check it against before writing
+    fn read_for_update(&self, path: &Path) -> Result<(String, FileStamp)> {
+        let stamp = FileStamp::of(path)?;
+        Ok((self.read_file(path)?, stamp))
+    }
+
+    /// Content of `path` to write back, checked against the stamp taken
+    /// when `get_document` or `find_document` last read it, if one did. The
+    /// stamp is cleared: the next write checks against a new read.
+    fn read_for_write(&self, path: &Path) -> Result<(String, FileStamp)> {
+        let (content, stamp) = self.read_for_update(path)?;
+        let read = self
+            .stamps
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(path);
+        Ok((content, read.unwrap_or(stamp)))
+    }
+
+    /// Remember `stamp` for the next write of the file, replacing the
+    /// stamp of any earlier read
+    fn remember(&self, path: PathBuf, stamp: FileStamp) {
+        self.stamps
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path, stamp);
+    }
+
+    /// Forget the stamp of `path` once it was written or deleted
+    fn forget(&self, path: &Path) {
+        self.stamps
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(path);
+    }
+
+    /// Document `id` with the stamp of its file, without remembering it;
+    /// listing and searching read many files that are not written back
+    fn read_document(&self, id: &str) -> Result<Option<(Document, FileStamp)>> {
+        let path = self.resolve_path(id);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let (content, stamp) = self.read_for_update(&path)?;
+        let metadata = self.file_metadata(&path)?;
+        let title = heading_title(&content).unwrap_or_else(|| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Untitled")
+                .to_string()
+        });
+
+        Ok(Some((
+            Document {
+                id: id.to_string(),
+                title,
+                content,
+                url: Some(path.to_string_lossy().to_string()),
+                parent_id: path
+                    .parent()
+                    .and_then(|p| p.to_str())
+                    .map(|s| s.to_string()),
+                metadata,
+            },
+            stamp,
+        )))
+    }
+
+    /// Document read by `get_document` or `find_document`, whose stamp is
+    /// kept for the update that may follow
+    fn read_for_caller(&self, read: Option<(Document, FileStamp)>) -> Option<Document> {
+        let (doc, stamp) = read?;
+        self.remember(self.resolve_path(&doc.id), stamp);
+        Some(doc)
+    }
+
+    /// Replace `path` as [`replace_file`] does. With `expected` the write
+    /// fails when the file changed since that stamp was taken.
+    fn write_file(&self, path: &Path, content: &str, expected: Option<FileStamp>) -> Result<()> {
         if self.config.auto_create_dirs {
-            if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent).map_err(KtmeError::Io)?;
-            }
+            let parent = path.parent().unwrap_or(Path::new("."));
+            std::fs::create_dir_all(parent).map_err(KtmeError::Io)?;
         }
+        replace_file(path, content, expected, self.config.backup)
+    }
 
-        std::fs::write(path, content).map_err(KtmeError::Io)
+    /// Documents under the base path, relative to it and sorted, without
+    /// the ones hidden by the ignore files
+    fn document_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.base_path.exists() {
+            return Ok(Vec::new());
+        }
+        let ignore = IgnoreRules::load(&self.base_path);
+        let mut files = Vec::new();
+        let walker = WalkDir::new(&self.base_path)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != ".git");
+        for entry in walker {
+            let entry = entry.map_err(|e| KtmeError::Io(e.into()))?;
+            let is_document = entry.file_type().is_file()
+                && entry.path().extension().and_then(|s| s.to_str())
+                    == Some(self.config.extension.as_str());
+            if !is_document {
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(&self.base_path) else {
+                continue;
+            };
+            if !ignore.is_ignored(&relative.to_string_lossy().replace('\\', "/")) {
+                files.push(relative.to_path_buf());
+            }
+        }
+        files.sort();
+        Ok(files)
     }
 
     fn file_metadata(&self, path: &Path) -> Result<DocumentMetadata> {
@@ -80,6 +241,7 @@ impl MarkdownProvider {
             author: None,
             version: None,
             labels: vec![],
+            snippets: vec![],
         })
     }
 }
@@ -98,36 +260,49 @@ impl DocumentProvider for MarkdownProvider {
     }
 
     async fn get_document(&self, id: &str) -> Result<Option<Document>> {
-        let path = self.resolve_path(id);
+        let read = self.read_document(id)?;
+        Ok(self.read_for_caller(read))
+    }
 
-        if !path.exists() {
+    /// Find a document by glob (`guides/**/setup*.md`), path, or title: the
+    /// first `# heading` or the file name, ignoring case
+    async fn find_document(&self, title: &str) -> Result<Option<Document>> {
+        if title.contains(['*', '?', '[']) {
+            let pattern = glob::Pattern::new(title).map_err(|e| {
+                KtmeError::InvalidInput(format!("Invalid pattern '{}': {}", title, e))
+            })?;
+            let options = glob::MatchOptions {
+                require_literal_separator: true,
+                ..glob::MatchOptions::new()
+            };
+            for path in self.document_files()? {
+                if pattern.matches_path_with(&path, options) {
+                    let read = self.read_document(&path.to_string_lossy())?;
+                    return Ok(self.read_for_caller(read));
+                }
+            }
             return Ok(None);
         }
 
-        let content = self.read_file(&path)?;
-        let metadata = self.file_metadata(&path)?;
-        let title = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("Untitled")
-            .to_string();
-
-        Ok(Some(Document {
-            id: id.to_string(),
-            title,
-            content,
-            url: Some(path.to_string_lossy().to_string()),
-            parent_id: path
-                .parent()
-                .and_then(|p| p.to_str())
-                .map(|s| s.to_string()),
-            metadata,
-        }))
-    }
-
-    async fn find_document(&self, title: &str) -> Result<Option<Document>> {
         let search_path = self.resolve_path(title);
-        self.get_document(&search_path.to_string_lossy()).await
+        if search_path.exists() {
+            let read = self.read_document(&search_path.to_string_lossy())?;
+            return Ok(self.read_for_caller(read));
+        }
+
+        let wanted = title.to_lowercase();
+        for path in self.document_files()? {
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_lowercase);
+            if let Some((doc, stamp)) = self.read_document(&path.to_string_lossy())? {
+                if doc.title.to_lowercase() == wanted || stem.as_deref() == Some(&wanted) {
+                    return Ok(self.read_for_caller(Some((doc, stamp))));
+                }
+            }
+        }
+        Ok(None)
     }
 
     async fn create_document(&self, doc: &Document) -> Result<PublishResult> {
@@ -139,7 +314,7 @@ impl DocumentProvider for MarkdownProvider {
             ));
         }
 
-        self.write_file(&path, &doc.content)?;
+        self.write_file(&path, &doc.content, None)?;
 
         Ok(PublishResult {
             document_id: doc.id.clone(),
@@ -156,7 +331,7 @@ impl DocumentProvider for MarkdownProvider {
             return Err(KtmeError::DocumentNotFound(id.to_string()));
         }
 
-        let old_content = self.read_file(&path)?;
+        let (old_content, stamp) = self.read_for_write(&path)?;
 
         if old_content == content {
             return Ok(PublishResult {
@@ -167,7 +342,7 @@ impl DocumentProvider for MarkdownProvider {
             });
         }
 
-        self.write_file(&path, content)?;
+        self.write_file(&path, content, Some(stamp))?;
 
         Ok(PublishResult {
             document_id: id.to_string(),
@@ -189,36 +364,13 @@ impl DocumentProvider for MarkdownProvider {
             return Err(KtmeError::DocumentNotFound(id.to_string()));
         }
 
-        let old_content = self.read_file(&path)?;
-
-        // Simple section replacement - look for a header and replace content under it
-        let section_header = format!("## {}", section);
-        let next_header = "\n## ";
-
-        let new_content = if let Some(start) = old_content.find(&section_header) {
-            if let Some(end) = old_content[start..].find(next_header) {
-                let _end_pos = start + end;
-                format!(
-                    "{}\n{}\n{}",
-                    &old_content[..start],
-                    &section_header,
-                    content
-                )
-            } else {
-                // No next header, replace till end
-                format!(
-                    "{}\n{}\n{}",
-                    &old_content[..start],
-                    &section_header,
-                    content
-                )
-            }
-        } else {
-            // Section not found, append to end
-            format!("{}\n\n## {}\n{}", old_content, section, content)
-        };
+        let (old_content, stamp) = self.read_for_write(&path)?;
 
-        self.write_file(&path, &new_content)?;
+        let mut document = SectionedDocument::parse(&old_content, SectionFormat::Markdown);
+        document.update_section(section, content, UpdateMode::Replace);
+        let new_content = document.render();
+
+        self.write_file(&path, &new_content, Some(stamp))?;
 
         Ok(PublishResult {
             document_id: id.to_string(),
@@ -234,78 +386,54 @@ impl DocumentProvider for MarkdownProvider {
         if path.exists() {
             std::fs::remove_file(&path).map_err(KtmeError::Io)?;
         }
+        self.forget(&path);
 
         Ok(())
     }
 
-    async fn list_documents(&self, container: &str) -> Result<Vec<Document>> {
-        let container_path = self.base_path.join(container);
-
-        if !container_path.exists() {
-            return Ok(vec![]);
-        }
-
+    async fn list_documents(
+        &self,
+        container: &str,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<DocumentPage> {
+        // Sorted so that offsets stay stable between pages
+        let ids: Vec<PathBuf> = self
+            .document_files()?
+            .into_iter()
+            .filter(|path| path.parent() == Some(Path::new(container)))
+            .collect();
+
+        let start = parse_offset(cursor)?;
+        let end = limit.map_or(ids.len(), |limit| (start + limit).min(ids.len()));
         let mut documents = Vec::new();
-
-        let entries = std::fs::read_dir(&container_path).map_err(KtmeError::Io)?;
-
-        for entry in entries {
-            let entry = entry.map_err(KtmeError::Io)?;
-            let path = entry.path();
-
-            if path
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|s| s == self.config.extension)
-                .unwrap_or(false)
-            {
-                if let Some(id) = path.file_name().and_then(|s| s.to_str()) {
-                    if let Some(doc) = self.get_document(id).await? {
-                        documents.push(doc);
-                    }
-                }
+        for id in ids.iter().take(end).skip(start) {
+            if let Some((doc, _)) = self.read_document(&id.to_string_lossy())? {
+                documents.push(doc);
             }
         }
 
-        Ok(documents)
+        Ok(DocumentPage {
+            documents,
+            next_cursor: (end < ids.len()).then(|| end.to_string()),
+        })
     }
 
-    async fn search_documents(&self, query: &str) -> Result<Vec<Document>> {
+    /// Documents whose title or content contains `query`, ignoring case,
+    /// with snippets of the matching lines
+    async fn search_documents(&self, query: &str, limit: Option<usize>) -> Result<Vec<Document>> {
+        let query = query.to_lowercase();
         let mut matches = Vec::new();
 
-        fn search_dir(
-            path: &Path,
-            query: &str,
-            matches: &mut Vec<std::path::PathBuf>,
-        ) -> Result<()> {
-            if path.is_dir() {
-                for entry in std::fs::read_dir(path).map_err(KtmeError::Io)? {
-                    let entry = entry.map_err(KtmeError::Io)?;
-                    search_dir(&entry.path(), query, matches)?;
-                }
-            } else {
-                matches.push(path.to_path_buf());
+        for path in self.document_files()? {
+            if limit.is_some_and(|limit| matches.len() >= limit) {
+                break;
             }
-            Ok(())
-        }
-
-        let mut file_paths = Vec::new();
-        search_dir(&self.base_path, query, &mut file_paths)?;
-
-        // Now check each file for content matching
-        for path in file_paths {
-            if path
-                .extension()
-                .and_then(|s| s.to_str())
-                .map(|s| s == self.config.extension)
-                .unwrap_or(false)
-            {
-                if let Some(id) = path.file_name().and_then(|s| s.to_str()) {
-                    if let Ok(Some(doc)) = self.get_document(id).await {
-                        if doc.content.contains(query) || doc.title.contains(query) {
-                            matches.push(doc);
-                        }
-                    }
+            if let Some((mut doc, _)) = self.read_document(&path.to_string_lossy())? {
+                let snippets = snippets(&doc.content, &query);
+                if !snippets.is_empty() || doc.title.to_lowercase().contains(&query) {
+                    doc.metadata.snippets = snippets;
+                    matches.push(doc);
                 }
             }
         }
@@ -329,6 +457,153 @@ impl DocumentProvider for MarkdownProvider {
     }
 }
 
+/// Size and modification time of a file, to notice changes by other
+/// processes between reading and replacing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<std::time::SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path).map_err(KtmeError::Io)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Write `content` to `path`, creating its directory, so that an
+/// interrupted write never leaves half a file behind
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(KtmeError::Io)?;
+    }
+    replace_file(path, content, None, false)
+}
+
+/// Replace `path` through a temporary file and a rename, so that readers
+/// never see half a document; the write fails when the file no longer has
+/// the `expected` stamp, and keeps the previous content when `backup`
+fn replace_file(
+    path: &Path,
+    content: &str,
+    expected: Option<FileStamp>,
+    backup: bool,
+) -> Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("document");
+    let temp = parent.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let written = std::fs::File::create(&temp).and_then(|mut file| {
+        use std::io::Write;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    let result = written.map_err(KtmeError::Io).and_then(|_| {
+        if let Some(expected) = expected {
+            if FileStamp::of(path)? != expected {
+                return Err(KtmeError::DocumentChanged(path.display().to_string()));
+            }
+        }
+        if backup && path.exists() {
+            std::fs::copy(path, backup_path(path)).map_err(KtmeError::Io)?;
+        }
+        std::fs::rename(&temp, path).map_err(KtmeError::Io)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// `<file>.bak` next to `path`
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Rules of the ignore files; the last matching rule wins and `!pattern`
+/// brings back a path an earlier rule hid
+struct IgnoreRules(Vec<(String, bool)>);
+
+impl IgnoreRules {
+    fn load(base: &Path) -> Self {
+        let mut rules = Vec::new();
+        for name in IGNORE_FILES {
+            let Ok(content) = std::fs::read_to_string(base.join(name)) else {
+                continue;
+            };
+            for line in content.lines().map(str::trim) {
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                match line.strip_prefix('!') {
+                    Some(pattern) => rules.push((pattern.to_string(), false)),
+                    None => rules.push((line.to_string(), true)),
+                }
+            }
+        }
+        Self(rules)
+    }
+
+    fn is_ignored(&self, path: &str) -> bool {
+        self.0
+            .iter()
+            .rev()
+            .find(|(pattern, _)| pattern_matches(pattern, path))
+            .is_some_and(|(_, ignored)| *ignored)
+    }
+}
+
+/// Text of the first `# heading`
+fn heading_title(content: &str) -> Option<String> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+/// Lines of `content` containing `query` (lowercase), cut to
+/// `SNIPPET_CONTEXT` characters around the match
+pub(crate) fn snippets(content: &str, query: &str) -> Vec<String> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    content
+        .lines()
+        .filter_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            let lower: Vec<char> = chars
+                .iter()
+                .map(|c| c.to_lowercase().next().unwrap_or(*c))
+                .collect();
+            let at = lower
+                .windows(query.len())
+                .position(|window| window == query.as_slice())?;
+            let start = at.saturating_sub(SNIPPET_CONTEXT);
+            let end = (at + query.len() + SNIPPET_CONTEXT).min(chars.len());
+            let mut snippet: String = chars[start..end].iter().collect();
+            snippet = snippet.trim().to_string();
+            if start > 0 {
+                snippet.insert(0, '…');
+            }
+            if end < chars.len() {
+                snippet.push('…');
+            }
+            Some(snippet)
+        })
+        .take(MAX_SNIPPETS)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -341,6 +616,7 @@ mod tests {
             base_path: temp_dir.path().to_string_lossy().to_string(),
             extension: "md".to_string(),
             auto_create_dirs: true,
+            backup: false,
         };
 
         let provider = MarkdownProvider::new(config);
@@ -363,7 +639,7 @@ mod tests {
 
         // Test get document
         let retrieved = provider.get_document("test").await.unwrap().unwrap();
-        assert_eq!(retrieved.title, "test");
+        assert_eq!(retrieved.title, "Test Content");
         assert_eq!(retrieved.content, doc.content);
 
         // Test update document
@@ -384,4 +660,204 @@ mod tests {
         let deleted = provider.get_document("test").await.unwrap();
         assert!(deleted.is_none());
     }
+
+    #[tokio::test]
+    async fn test_list_pages_and_bulk_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let provider = MarkdownProvider::new(MarkdownConfig {
+            base_path: temp_dir.path().to_string_lossy().to_string(),
+            extension: "md".to_string(),
+            auto_create_dirs: true,
+            backup: false,
+        });
+        for name in ["c", "a", "b"] {
+            std::fs::write(temp_dir.path().join(format!("{}.md", name)), name).unwrap();
+        }
+
+        let first = provider.list_documents("", None, Some(2)).await.unwrap();
+        let titles: Vec<_> = first.documents.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(titles, ["a", "b"]);
+        let cursor = first.next_cursor.unwrap();
+        let second = provider
+            .list_documents("", Some(&cursor), Some(2))
+            .await
+            .unwrap();
+        assert_eq!(second.documents.len(), 1);
+        assert_eq!(second.documents[0].title, "c");
+        assert!(second.next_cursor.is_none());
+
+        let results = provider
+            .bulk_update(vec![
+                ("a".to_string(), "updated".to_string()),
+                ("missing".to_string(), "x".to_string()),
+                ("b".to_string(), "b".to_string()),
+            ])
+            .await
+            .unwrap();
+        assert!(matches!(results[0].status, PublishStatus::Updated));
+        assert!(matches!(results[1].status, PublishStatus::Failed(_)));
+        assert!(matches!(results[2].status, PublishStatus::NoChanges));
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("a.md")).unwrap(),
+            "updated"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_and_search_honor_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let provider = MarkdownProvider::new(MarkdownConfig {
+            base_path: base.to_string_lossy().to_string(),
+            extension: "md".to_string(),
+            auto_create_dirs: true,
+            backup: false,
+        });
+        std::fs::create_dir_all(base.join("guides/setup")).unwrap();
+        std::fs::create_dir_all(base.join("build")).unwrap();
+        std::fs::write(
+            base.join("guides/setup/install.md"),
+            "# Installing Payments\n\nRun the Migrations before starting the service.\n",
+        )
+        .unwrap();
+        std::fs::write(base.join("build/generated.md"), "# Generated\nmigrations\n").unwrap();
+        std::fs::write(base.join("draft.md"), "# Draft\nmigrations\n").unwrap();
+        std::fs::write(base.join(".gitignore"), "build/\n").unwrap();
+        std::fs::write(base.join(".ktmeignore"), "draft.md\n").unwrap();
+
+        let found = provider.find_document("guides/**/inst*.md").await.unwrap();
+        assert_eq!(found.unwrap().title, "Installing Payments");
+        let found = provider
+            .find_document("installing payments")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, "guides/setup/install.md");
+        assert!(provider.find_document("*.txt").await.unwrap().is_none());
+
+        let results = provider.search_documents("MIGRATIONS", None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].metadata.snippets,
+            ["Run the Migrations before starting the service."]
+        );
+    }
+
+    #[test]
+    fn test_snippets_cut_long_lines() {
+        let line = format!("{}needle{}", "a".repeat(60), "b".repeat(60));
+        let snippet = &snippets(&line, "needle")[0];
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert_eq!(snippet.chars().count(), 40 + 6 + 40 + 2);
+    }
+
+    #[tokio::test]
+    async fn test_writes_keep_backup_and_detect_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let provider = MarkdownProvider::new(MarkdownConfig {
+            base_path: temp_dir.path().to_string_lossy().to_string(),
+            extension: "md".to_string(),
+            auto_create_dirs: true,
+            backup: true,
+        });
+        let path = temp_dir.path().join("guide.md");
+        std::fs::write(&path, "# Guide\n\nFirst").unwrap();
+
+        provider
+            .update_document("guide", "# Guide\n\nSecond")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Guide\n\nSecond");
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("guide.md.bak")).unwrap(),
+            "# Guide\n\nFirst"
+        );
+
+        // Another process edits the file after it was read
+        let (_, stamp) = provider.read_for_update(&path).unwrap();
+        std::fs::write(&path, "# Guide\n\nEdited elsewhere").unwrap();
+        let error = provider
+            .write_file(&path, "# Guide\n\nThird", Some(stamp))
+            .unwrap_err();
+        assert!(matches!(error, KtmeError::DocumentChanged(_)));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Guide\n\nEdited elsewhere"
+        );
+
+        // ...or after get_document read it for an update
+        let read = provider.get_document("guide").await.unwrap().unwrap();
+        assert_eq!(read.content, "# Guide\n\nEdited elsewhere");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&path, "# Guide\n\nEdited again elsewhere").unwrap();
+        let error = provider
+            .update_document("guide", "# Guide\n\nFourth")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, KtmeError::DocumentChanged(_)));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Guide\n\nEdited again elsewhere"
+        );
+
+        // Reading again takes the edit into account
+        provider.get_document("guide").await.unwrap().unwrap();
+        provider
+            .update_document("guide", "# Guide\n\nFifth")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Guide\n\nFifth");
+
+        // No temporary files are left behind
+        let names: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert!(
+            names.iter().all(|name| !name.ends_with(".tmp")),
+            "{:?}",
+            names
+        );
+    }
+
+    #[tokio::test]
+    async fn test_only_reads_for_update_keep_stamps() {
+        let temp_dir = TempDir::new().unwrap();
+        let provider = MarkdownProvider::new(MarkdownConfig {
+            base_path: temp_dir.path().to_string_lossy().to_string(),
+            extension: "md".to_string(),
+            auto_create_dirs: true,
+            backup: false,
+        });
+        let path = temp_dir.path().join("guide.md");
+        std::fs::write(&path, "# Guide\n\nFirst").unwrap();
+        std::fs::write(temp_dir.path().join("notes.md"), "# Notes\n").unwrap();
+
+        // Listing and searching remember nothing
+        let base = temp_dir.path().to_string_lossy().to_string();
+        provider.list_documents(&base, None, None).await.unwrap();
+        provider.search_documents("guide", None).await.unwrap();
+        assert!(provider.stamps.lock().unwrap().is_empty());
+
+        // An edit made after a listing does not fail the next update
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&path, "# Guide\n\nEdited elsewhere").unwrap();
+        provider
+            .update_section("guide", "Guide", "Second")
+            .await
+            .unwrap();
+
+        // Read, edit elsewhere, read again: the write goes through and
+        // clears the stamp
+        provider.get_document("guide").await.unwrap().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&path, "# Guide\n\nEdited again").unwrap();
+        provider.get_document("guide").await.unwrap().unwrap();
+        provider
+            .update_document("guide", "# Guide\n\nThird")
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Guide\n\nThird");
+        assert!(provider.stamps.lock().unwrap().is_empty());
+    }
 }
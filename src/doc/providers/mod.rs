@@ -1,9 +1,11 @@
 pub mod config;
 pub mod confluence;
+pub mod confluence_oauth;
+pub mod external;
 pub mod markdown;
 pub mod notion;
 
-use crate::error::Result;
+use crate::error::{KtmeError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +14,8 @@ use serde::{Deserialize, Serialize};
 pub struct Document {
     pub id: String,
     pub title: String,
+    /// Markdown; providers storing another format (Confluence storage
+    /// XHTML) convert it when reading and writing
     pub content: String,
     pub url: Option<String>,
     pub parent_id: Option<String>,
@@ -25,6 +29,9 @@ pub struct DocumentMetadata {
     pub author: Option<String>,
     pub version: Option<u32>,
     pub labels: Vec<String>,
+    /// Lines around the matches of a `search_documents` query
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snippets: Vec<String>,
 }
 
 /// Result of a publish operation
@@ -36,6 +43,26 @@ pub struct PublishResult {
     pub status: PublishStatus,
 }
 
+impl PublishResult {
+    /// Result of a document in a bulk operation that could not be published
+    pub fn failed(id: &str, error: KtmeError) -> Self {
+        Self {
+            document_id: id.to_string(),
+            url: String::new(),
+            version: 0,
+            status: PublishStatus::Failed(error.to_string()),
+        }
+    }
+}
+
+/// One page of the documents of a container
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentPage {
+    pub documents: Vec<Document>,
+    /// Passed as `cursor` to read the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PublishStatus {
     Created,
@@ -50,7 +77,8 @@ pub trait DocumentProvider: Send + Sync {
     /// Get provider name (e.g., "confluence", "google_docs")
     fn name(&self) -> &str;
 
-    /// Check if provider is properly configured and accessible
+    /// Check if provider is properly configured and accessible; an error
+    /// when it rejects the credentials
     async fn health_check(&self) -> Result<bool>;
 
     /// Get a document by ID
@@ -65,60 +93,119 @@ pub trait DocumentProvider: Send + Sync {
     /// Update an existing document
     async fn update_document(&self, id: &str, content: &str) -> Result<PublishResult>;
 
-    /// Update a specific section within a document
+    /// Replace the content of a section (and its subsections), adding the
+    /// section when the document does not have it
     async fn update_section(&self, id: &str, section: &str, content: &str)
         -> Result<PublishResult>;
 
     /// Delete a document
     async fn delete_document(&self, id: &str) -> Result<()>;
 
-    /// List documents in a container (space, folder, etc.)
-    async fn list_documents(&self, container: &str) -> Result<Vec<Document>>;
+    /// List one page of the documents in a container (space, folder, etc.),
+    /// starting at `cursor` (the first page without one). `limit` caps the
+    /// page size; without it the provider picks one.
+    async fn list_documents(
+        &self,
+        container: &str,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<DocumentPage>;
+
+    /// Search for documents, returning at most `limit` matches when given
+    async fn search_documents(&self, query: &str, limit: Option<usize>) -> Result<Vec<Document>>;
 
-    /// Search for documents
-    async fn search_documents(&self, query: &str) -> Result<Vec<Document>>;
+    /// Replace the content of several documents, as `(id, content)` pairs.
+    /// A document that cannot be updated gets a `Failed` result instead of
+    /// stopping the others. The default updates them one at a time;
+    /// providers with cheaper batch access override it.
+    async fn bulk_update(&self, updates: Vec<(String, String)>) -> Result<Vec<PublishResult>> {
+        let mut results = Vec::with_capacity(updates.len());
+        for (id, content) in updates {
+            let result = self.update_document(&id, &content).await;
+            results.push(result.unwrap_or_else(|e| PublishResult::failed(&id, e)));
+        }
+        Ok(results)
+    }
 
     /// Get provider-specific configuration
     fn config(&self) -> &config::ProviderConfig;
 }
 
+/// Offset encoded in a `list_documents` cursor of providers that page by
+/// position
+pub(crate) fn parse_offset(cursor: Option<&str>) -> Result<usize> {
+    cursor.map_or(Ok(0), |cursor| {
+        cursor
+            .parse()
+            .map_err(|_| KtmeError::InvalidInput(format!("Invalid cursor '{}'", cursor)))
+    })
+}
+
 /// Provider factory for creating provider instances
 pub struct ProviderFactory;
 
 impl ProviderFactory {
+    /// Provider of `provider_type`, with the `${VAR}` references in its saved
+    /// configuration resolved from the environment
     pub fn create(
         provider_type: &str,
-        config: config::ProviderConfig,
+        mut config: config::ProviderConfig,
     ) -> Result<Box<dyn DocumentProvider>> {
+        let unresolved = crate::config::interpolate::interpolate_json(
+            &mut config.config,
+            &crate::config::interpolate::env_lookup,
+        );
+        if let Some(first) = unresolved.first() {
+            return Err(KtmeError::Config(format!(
+                "{} provider {}: environment variable {} is not set",
+                provider_type, first.key, first.variable
+            )));
+        }
+
+        match provider_type {
+            "confluence" => Ok(Box::new(confluence::ConfluenceProvider::new(typed_config(
+                &config.config,
+            )?))),
+            "markdown" => Ok(Box::new(markdown::MarkdownProvider::new(typed_config(
+                &config.config,
+            )?))),
+            "notion" => Ok(Box::new(notion::NotionProvider::new(typed_config(
+                &config.config,
+            )?))),
+            // Any other provider is served by a `ktme-provider-<name>` plugin on PATH
+            _ => match external::find(provider_type) {
+                Some(plugin) => Ok(Box::new(external::ExternalProvider::new(plugin, config))),
+                None => Err(unsupported(provider_type)),
+            },
+        }
+    }
+
+    /// Check `config` against the configuration of `provider_type` before it
+    /// is saved; plugin providers validate their own configuration
+    pub fn validate(provider_type: &str, config: &serde_json::Value) -> Result<()> {
         match provider_type {
-            "confluence" => {
-                let confluence_config: config::ConfluenceConfig =
-                    serde_json::from_value(config.config.clone())
-                        .map_err(|e| crate::error::KtmeError::Config(e.to_string()))?;
-                Ok(Box::new(confluence::ConfluenceProvider::new(
-                    confluence_config,
-                )))
-            }
-            "markdown" => {
-                let markdown_config: config::MarkdownConfig =
-                    serde_json::from_value(config.config.clone())
-                        .map_err(|e| crate::error::KtmeError::Config(e.to_string()))?;
-                Ok(Box::new(markdown::MarkdownProvider::new(markdown_config)))
-            }
-            "notion" => {
-                let notion_config: config::NotionConfig =
-                    serde_json::from_value(config.config.clone())
-                        .map_err(|e| crate::error::KtmeError::Config(e.to_string()))?;
-                Ok(Box::new(notion::NotionProvider::new(notion_config)))
-            }
-            _ => Err(crate::error::KtmeError::UnsupportedProvider(format!(
-                "Provider '{}' is not supported",
-                provider_type
-            ))),
+            "confluence" => typed_config::<config::ConfluenceConfig>(config).map(drop),
+            "markdown" => typed_config::<config::MarkdownConfig>(config).map(drop),
+            "notion" => typed_config::<config::NotionConfig>(config).map(drop),
+            _ if external::find(provider_type).is_some() => Ok(()),
+            _ => Err(unsupported(provider_type)),
         }
     }
 }
 
+fn typed_config<T: serde::de::DeserializeOwned>(config: &serde_json::Value) -> Result<T> {
+    serde_json::from_value(config.clone()).map_err(|e| KtmeError::Config(e.to_string()))
+}
+
+fn unsupported(provider_type: &str) -> KtmeError {
+    KtmeError::UnsupportedProvider(format!(
+        "Provider '{}' is not supported and no {}{} plugin was found on PATH",
+        provider_type,
+        external::PLUGIN_PREFIX,
+        provider_type
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,6 +230,21 @@ mod tests {
         assert!(provider.is_ok());
     }
 
+    #[test]
+    fn test_provider_factory_reports_unset_variables() {
+        let config = config::ProviderConfig {
+            id: 1,
+            provider_type: "markdown".to_string(),
+            config: serde_json::json!({ "base_path": "${KTME_TEST_UNSET_DOCS_DIR}" }),
+            is_default: false,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+
+        let error = ProviderFactory::create("markdown", config).err().unwrap();
+        assert!(error.to_string().contains("KTME_TEST_UNSET_DOCS_DIR"));
+    }
+
     #[test]
     fn test_provider_factory_unsupported() {
         let config = config::ProviderConfig {
@@ -157,4 +259,20 @@ mod tests {
         let provider = ProviderFactory::create("unsupported", config);
         assert!(provider.is_err());
     }
+
+    #[test]
+    fn test_provider_factory_validate() {
+        assert!(
+            ProviderFactory::validate("markdown", &serde_json::json!({ "base_path": "docs" }))
+                .is_ok()
+        );
+        assert!(matches!(
+            ProviderFactory::validate("confluence", &serde_json::json!({ "base_url": "x" })),
+            Err(KtmeError::Config(_))
+        ));
+        assert!(matches!(
+            ProviderFactory::validate("unsupported", &serde_json::json!({})),
+            Err(KtmeError::UnsupportedProvider(_))
+        ));
+    }
 }
@@ -1,5 +1,7 @@
-use super::{config::NotionConfig, Document, DocumentMetadata, DocumentProvider, PublishResult};
-use crate::error::{KtmeError, Result};
+use super::{
+    config::NotionConfig, Document, DocumentMetadata, DocumentPage, DocumentProvider, PublishResult,
+};
+use crate::error::{ErrorClass, KtmeError, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -165,7 +167,7 @@ struct SearchResponse {
 
 impl NotionProvider {
     pub fn new(config: NotionConfig) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("notion")
             .user_agent("ktme/1.0")
             .build()
             .expect("Failed to create HTTP client");
@@ -183,6 +185,7 @@ impl NotionProvider {
         endpoint: &str,
         body: Option<serde_json::Value>,
     ) -> Result<T> {
+        crate::http::ensure_online("Calling the Notion API")?;
         let url = self.api_url(endpoint);
 
         let mut request = self
@@ -199,15 +202,16 @@ impl NotionProvider {
         let response = request
             .send()
             .await
-            .map_err(|e| KtmeError::NetworkError(e.to_string()))?;
+            .map_err(|e| crate::http::request_error("Notion request failed", e))?;
 
         let status = response.status();
         if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(KtmeError::ApiError(format!(
-                "Notion API error: {} - {}",
-                status, error_text
-            )));
+            return Err(KtmeError::from_status(
+                "Notion",
+                status,
+                KtmeError::ApiError(format!("Notion API error: {} - {}", status, error_text)),
+            ));
         }
 
         response
@@ -413,6 +417,7 @@ impl DocumentProvider for NotionProvider {
             .await
         {
             Ok(_) => Ok(true),
+            Err(e) if e.class() == ErrorClass::Auth => Err(e),
             Err(_) => Ok(false),
         }
     }
@@ -436,6 +441,7 @@ impl DocumentProvider for NotionProvider {
                         author: None,
                         version: None,
                         labels: vec![],
+                        snippets: vec![],
                     },
                 }))
             }
@@ -481,14 +487,22 @@ impl DocumentProvider for NotionProvider {
         ))
     }
 
-    async fn list_documents(&self, _container: &str) -> Result<Vec<Document>> {
-        let body = serde_json::json!({
+    async fn list_documents(
+        &self,
+        _container: &str,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<DocumentPage> {
+        let mut body = serde_json::json!({
             "filter": {
                 "property": "object",
                 "value": "page"
             },
-            "page_size": 100
+            "page_size": limit.unwrap_or(100).min(100)
         });
+        if let Some(cursor) = cursor {
+            body["start_cursor"] = serde_json::json!(cursor);
+        }
 
         let response: SearchResponse = self
             .make_request(reqwest::Method::POST, "search", Some(body))
@@ -501,17 +515,20 @@ impl DocumentProvider for NotionProvider {
             }
         }
 
-        Ok(documents)
+        Ok(DocumentPage {
+            documents,
+            next_cursor: response.next_cursor.filter(|_| response.has_more),
+        })
     }
 
-    async fn search_documents(&self, query: &str) -> Result<Vec<Document>> {
+    async fn search_documents(&self, query: &str, limit: Option<usize>) -> Result<Vec<Document>> {
         let body = serde_json::json!({
             "query": query,
             "filter": {
                 "property": "object",
                 "value": "page"
             },
-            "page_size": 50
+            "page_size": limit.unwrap_or(50).min(100)
         });
 
         let response: SearchResponse = self
@@ -0,0 +1,198 @@
+//! Code-free documentation for sharing outside the team
+//!
+//! With `--redact-code` the literal code of generated documentation is
+//! replaced by a description of its structure: a fenced block becomes a note
+//! naming its language, size and the functions and types it declares, and
+//! inline code that reads as a statement rather than a name is left out.
+//! Diagrams are kept.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Languages of fenced blocks that are diagrams rather than code
+const DIAGRAMS: &[&str] = &["mermaid", "plantuml", "dot", "graphviz"];
+
+/// Declarations listed in the description of a block before "and N more"
+const MAX_DECLARATIONS: usize = 5;
+
+/// Written in place of inline code that was left out
+const OMITTED: &str = "*(code omitted)*";
+
+/// Instruction added to the prompt, so the model describes the code in
+/// prose and pseudocode in the first place
+pub const PROMPT_INSTRUCTION: &str = "The documentation will be shared outside the \
+     team: do not quote source code. Describe what the code does in prose, or in \
+     short language-neutral pseudocode, and refer to functions and types by name only.";
+
+/// `markdown` with its code blocks and statement-like inline code replaced
+/// by structure-only descriptions
+pub fn redact_code(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut lines = markdown.lines();
+    while let Some(line) = lines.next() {
+        let Some((fence, language)) = opening_fence(line) else {
+            out.push(redact_inline(line));
+            continue;
+        };
+        let mut code = Vec::new();
+        let mut closed = false;
+        for line in lines.by_ref() {
+            if is_closing_fence(line, fence) {
+                closed = true;
+                break;
+            }
+            code.push(line);
+        }
+        if DIAGRAMS.contains(&language.to_lowercase().as_str()) {
+            out.push(line.to_string());
+            out.extend(code.iter().map(|l| l.to_string()));
+            if closed {
+                out.push(fence.to_string());
+            }
+            continue;
+        }
+        let indent = &line[..line.len() - line.trim_start().len()];
+        out.push(format!("{}> {}", indent, describe(language, &code)));
+    }
+    let mut redacted = out.join("\n");
+    if markdown.ends_with('\n') {
+        redacted.push('\n');
+    }
+    redacted
+}
+
+/// Fence and language of a line opening a fenced code block
+fn opening_fence(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    if length < 3 {
+        return None;
+    }
+    let language = trimmed[length..].split_whitespace().next().unwrap_or("");
+    if marker == '`' && language.contains('`') {
+        return None;
+    }
+    Some((&trimmed[..length], language))
+}
+
+fn is_closing_fence(line: &str, fence: &str) -> bool {
+    let trimmed = line.trim();
+    let marker = fence.chars().next().unwrap_or('`');
+    trimmed.len() >= fence.len() && trimmed.chars().all(|c| c == marker)
+}
+
+/// Description of a code block: its language, size and declarations
+fn describe(language: &str, code: &[&str]) -> String {
+    let lines = code.iter().filter(|l| !l.trim().is_empty()).count();
+    let mut text = match (language, lines) {
+        (_, 0) => "*Code omitted".to_string(),
+        ("", 1) => "*Code omitted: 1 line".to_string(),
+        ("", n) => format!("*Code omitted: {} lines", n),
+        (language, 1) => format!("*Code omitted: 1 line of {}", language),
+        (language, n) => format!("*Code omitted: {} lines of {}", n, language),
+    };
+    let declarations = declarations(code);
+    if !declarations.is_empty() {
+        let shown: Vec<String> = declarations
+            .iter()
+            .take(MAX_DECLARATIONS)
+            .map(|(kind, name)| format!("{} `{}`", kind, name))
+            .collect();
+        text.push_str(&format!(" declaring {}", shown.join(", ")));
+        if declarations.len() > MAX_DECLARATIONS {
+            text.push_str(&format!(
+                " and {} more",
+                declarations.len() - MAX_DECLARATIONS
+            ));
+        }
+    }
+    text.push_str(".*");
+    text
+}
+
+/// Kind and name of the functions, types and tables declared in `code`, in
+/// order and without repetitions
+fn declarations(code: &[&str]) -> Vec<(&'static str, String)> {
+    static DECLARATION: OnceLock<Regex> = OnceLock::new();
+    let pattern = DECLARATION.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b(fn|def|func|function|struct|class|interface|trait|enum|impl|create table)\s+(?:if not exists\s+)?([A-Za-z_][A-Za-z0-9_.]*)",
+        )
+        .expect("valid declaration pattern")
+    });
+    let mut found: Vec<(&'static str, String)> = Vec::new();
+    for line in code {
+        for captures in pattern.captures_iter(line) {
+            let kind = match captures[1].to_lowercase().as_str() {
+                "fn" | "def" | "func" | "function" => "function",
+                "struct" => "struct",
+                "class" => "class",
+                "interface" => "interface",
+                "trait" => "trait",
+                "enum" => "enum",
+                "impl" => "implementation of",
+                _ => "table",
+            };
+            let declaration = (kind, captures[2].to_string());
+            if !found.contains(&declaration) {
+                found.push(declaration);
+            }
+        }
+    }
+    found
+}
+
+/// `line` without its inline code that reads as a statement; names such as
+/// `retry_count` or `Vec<String>` are kept
+fn redact_inline(line: &str) -> String {
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    INLINE
+        .get_or_init(|| Regex::new(r"`([^`\n]+)`").expect("valid inline code pattern"))
+        .replace_all(line, |captures: &regex::Captures| {
+            let code = &captures[1];
+            let statement = code.contains(';')
+                || code.contains('{')
+                || code.contains('}')
+                || code.contains(" = ")
+                || code.contains("==")
+                || code.contains("=>")
+                || code.split_whitespace().count() > 6;
+            if statement {
+                OMITTED.to_string()
+            } else {
+                captures[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_blocks_become_descriptions() {
+        let markdown = "# Retries\n\nFailed charges are retried by `retry_charge`.\n\n```rust\npub fn retry_charge(id: &str) -> Result<()> {\n    let attempts = 3;\n    Ok(())\n}\n\nstruct RetryPolicy;\n```\n\nSet `max = 3; backoff = 2` to tune it.\n\n```mermaid\ngraph TD; A-->B\n```\n";
+        assert_eq!(
+            redact_code(markdown),
+            "# Retries\n\nFailed charges are retried by `retry_charge`.\n\n> *Code omitted: 5 lines of rust declaring function `retry_charge`, struct `RetryPolicy`.*\n\nSet *(code omitted)* to tune it.\n\n```mermaid\ngraph TD; A-->B\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_unclosed_and_nested_fences() {
+        assert_eq!(
+            redact_code("Before\n~~~\nSELECT 1;\nCREATE TABLE payments (id INT);"),
+            "Before\n> *Code omitted: 2 lines declaring table `payments`.*"
+        );
+        // A longer fence encloses shorter ones
+        assert_eq!(
+            redact_code("````markdown\n```sh\nrm -rf build\n```\n````\nAfter\n"),
+            "> *Code omitted: 3 lines of markdown.*\nAfter\n"
+        );
+    }
+}
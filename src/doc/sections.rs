@@ -0,0 +1,870 @@
+//! Section model shared by the Markdown and Confluence writers
+//!
+//! A document is a preamble followed by a flat list of headed sections. A
+//! section spans its subsections, up to the next heading of the same or a
+//! higher level. Update content is routed into sections by document type and
+//! combined with the existing text according to an [`UpdateMode`].
+
+use regex::Regex;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+/// Title of the changelog section collecting entries for the next release
+const UNRELEASED: &str = "[Unreleased]";
+
+/// How new content is combined with an existing section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum UpdateMode {
+    /// Add the content after the existing section content
+    Append,
+    /// Replace the section content, including its subsections
+    Replace,
+    /// Add the content before the existing section content; new
+    /// subsections go above the existing ones
+    Prepend,
+}
+
+impl UpdateMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Append => "append",
+            Self::Replace => "replace",
+            Self::Prepend => "prepend",
+        }
+    }
+}
+
+/// Documentation type, deciding where update content is routed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DocKind {
+    /// Entries go under the `[Unreleased]` section
+    Changelog,
+    /// Endpoint sections are merged into the matching sections of the document
+    #[value(name = "api-doc")]
+    ApiDoc,
+    /// Content goes into a dated update section
+    General,
+}
+
+impl DocKind {
+    /// Name on the command line and in `[ai.routing]` rules
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Changelog => "changelog",
+            Self::ApiDoc => "api-doc",
+            Self::General => "general",
+        }
+    }
+
+    /// Infer the type from a documentation location such as `CHANGELOG.md`
+    pub fn infer(location: &str) -> Self {
+        let name = location
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or(location)
+            .to_lowercase();
+        if name.contains("changelog") {
+            Self::Changelog
+        } else if name.contains("api") || name.contains("endpoint") {
+            Self::ApiDoc
+        } else {
+            Self::General
+        }
+    }
+}
+
+/// Heading syntax of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionFormat {
+    /// `#`-style Markdown headings
+    Markdown,
+    /// Confluence storage format (XHTML) with `<h1>`..`<h6>` headings
+    Storage,
+}
+
+impl SectionFormat {
+    /// Section body holding `text`
+    fn block(&self, text: &str) -> String {
+        let text = text.trim();
+        match self {
+            Self::Markdown if text.is_empty() => "\n".to_string(),
+            Self::Markdown => format!("\n{}\n\n", text),
+            Self::Storage => text.to_string(),
+        }
+    }
+
+    /// Section body combining `existing` and `new`
+    fn combine(&self, existing: &str, new: &str, mode: UpdateMode) -> String {
+        if new.trim().is_empty() {
+            return existing.to_string();
+        }
+        let (existing, new) = (existing.trim(), new.trim());
+        let separator = match self {
+            Self::Markdown => "\n\n",
+            Self::Storage => "",
+        };
+        match mode {
+            _ if existing.is_empty() => self.block(new),
+            UpdateMode::Prepend => self.block(&format!("{}{}{}", new, separator, existing)),
+            _ => self.block(&format!("{}{}{}", existing, separator, new)),
+        }
+    }
+
+    /// Whether `text` holds anything besides headings
+    fn has_entries(&self, text: &str) -> bool {
+        match self {
+            Self::Markdown => text
+                .lines()
+                .any(|line| !line.trim().is_empty() && markdown_heading(line).is_none()),
+            Self::Storage => {
+                let text = storage_heading().replace_all(text, "");
+                !storage_tag().replace_all(&text, "").trim().is_empty()
+            }
+        }
+    }
+
+    fn heading(&self, title: &str, level: usize) -> String {
+        match self {
+            Self::Markdown => format!("{} {}\n", "#".repeat(level), title),
+            Self::Storage => format!("<h{0}>{1}</h{0}>", level, html_escape::encode_text(title)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Section {
+    title: String,
+    level: usize,
+    /// Heading as written in the document, including its line break
+    heading: String,
+    body: String,
+}
+
+/// A document split into headed sections
+#[derive(Debug, Clone)]
+pub struct SectionedDocument {
+    format: SectionFormat,
+    preamble: String,
+    sections: Vec<Section>,
+}
+
+impl SectionedDocument {
+    pub fn parse(text: &str, format: SectionFormat) -> Self {
+        let (preamble, sections) = match format {
+            SectionFormat::Markdown => parse_markdown(text),
+            SectionFormat::Storage => parse_storage(text),
+        };
+        Self {
+            format,
+            preamble,
+            sections,
+        }
+    }
+
+    /// Title and body of every section, in document order; subsections are
+    /// separate entries
+    pub fn sections(&self) -> Vec<(&str, &str)> {
+        self.sections
+            .iter()
+            .map(|section| (section.title.as_str(), section.body.as_str()))
+            .collect()
+    }
+
+    /// The preamble, then each section with its heading, as written;
+    /// together they render the document
+    pub fn parts(&self) -> Vec<String> {
+        std::iter::once(self.preamble.clone())
+            .chain(
+                self.sections
+                    .iter()
+                    .map(|section| format!("{}{}", section.heading, section.body)),
+            )
+            .filter(|part| !part.trim().is_empty())
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = self.preamble.clone();
+        for section in &self.sections {
+            text.push_str(&section.heading);
+            text.push_str(&section.body);
+        }
+        match self.format {
+            SectionFormat::Markdown if !text.trim().is_empty() => {
+                format!("{}\n", text.trim_end())
+            }
+            _ => text,
+        }
+    }
+
+    /// Index of the section titled `title`, ignoring case and inline markup;
+    /// falls back to the first title containing it
+    fn find(&self, title: &str) -> Option<usize> {
+        let range = 0..self.sections.len();
+        self.find_exact(range.clone(), title).or_else(|| {
+            let wanted = normalize_title(title);
+            range.into_iter().find(|&i| {
+                !wanted.is_empty() && normalize_title(&self.sections[i].title).contains(&wanted)
+            })
+        })
+    }
+
+    fn find_exact(&self, range: Range<usize>, title: &str) -> Option<usize> {
+        let wanted = normalize_title(title);
+        range
+            .into_iter()
+            .find(|&i| normalize_title(&self.sections[i].title) == wanted)
+    }
+
+    /// End (exclusive) of section `index` including its subsections
+    fn extent(&self, index: usize) -> usize {
+        let level = self.sections[index].level;
+        self.sections[index + 1..]
+            .iter()
+            .position(|s| s.level <= level)
+            .map_or(self.sections.len(), |p| index + 1 + p)
+    }
+
+    /// Index of the first section below the document title
+    fn first_subsection(&self) -> usize {
+        self.sections
+            .iter()
+            .position(|s| s.level >= 2)
+            .unwrap_or(self.sections.len())
+    }
+
+    /// Put `content` into the section titled `title`, adding the section at
+    /// the end of the document when it does not exist
+    pub fn update_section(&mut self, title: &str, content: &str, mode: UpdateMode) {
+        let content = self.content(content);
+        match self.find(title) {
+            Some(index) => self.merge(index, content, mode),
+            None => self.insert_section(self.sections.len(), title, 2, content),
+        }
+    }
+
+    /// Add changelog entries under `[Unreleased]`, creating the section
+    /// above the released versions when missing
+    fn update_unreleased(&mut self, content: &str, mode: UpdateMode) {
+        let content = self.content(content);
+        match self.find(UNRELEASED) {
+            Some(index) => self.merge(index, content, mode),
+            None => self.insert_section(self.first_subsection(), UNRELEASED, 2, content),
+        }
+    }
+
+    /// Merge every endpoint section of `content` into the section with the
+    /// same title; endpoints without one are added at the end and text
+    /// outside any endpoint goes into a dated update section
+    fn merge_endpoints(&mut self, content: &str, mode: UpdateMode) {
+        let mut content = self.content(content);
+        let preamble = std::mem::take(&mut content.preamble);
+        for group in content.into_groups() {
+            match self.find_exact(0..self.sections.len(), &group[0].title) {
+                Some(index) => {
+                    let endpoint = self.group_content(&group);
+                    self.merge(index, endpoint, mode);
+                }
+                None => {
+                    let level = group[0].level;
+                    let at = self.sections.len();
+                    self.separate_before(at);
+                    let sections = self.adopt(group, level);
+                    self.sections.extend(sections);
+                }
+            }
+        }
+        if !preamble.trim().is_empty() {
+            let preamble = self.content(&preamble);
+            self.add_dated_section(preamble, UpdateMode::Append);
+        }
+    }
+
+    /// Add `content` as an `Update <date>` section at the end of the
+    /// document, or above the existing sections with `prepend`
+    fn add_dated_section(&mut self, content: SectionedDocument, mode: UpdateMode) {
+        let title = format!("Update {}", chrono::Utc::now().format("%Y-%m-%d"));
+        let at = match mode {
+            UpdateMode::Prepend => self.first_subsection(),
+            _ => self.sections.len(),
+        };
+        self.insert_section(at, &title, 2, content);
+    }
+
+    fn insert_section(&mut self, at: usize, title: &str, level: usize, content: Self) {
+        self.separate_before(at);
+        self.sections.insert(
+            at,
+            Section {
+                title: title.to_string(),
+                level,
+                heading: self.format.heading(title, level),
+                body: String::new(),
+            },
+        );
+        self.merge(at, content, UpdateMode::Replace);
+    }
+
+    /// Combine `content` with section `index`; subsections of `content` are
+    /// merged into the subsections with the same title
+    fn merge(&mut self, index: usize, content: Self, mode: UpdateMode) {
+        let level = self.sections[index].level;
+        if mode == UpdateMode::Replace {
+            let end = self.extent(index);
+            self.sections[index].body = self.format.block(&content.preamble);
+            let children: Vec<Section> = content
+                .into_groups()
+                .into_iter()
+                .flat_map(|group| self.adopt(group, level + 1))
+                .collect();
+            self.sections.splice(index + 1..end, children);
+            return;
+        }
+
+        let body = &self.sections[index].body;
+        self.sections[index].body = self.format.combine(body, &content.preamble, mode);
+
+        let mut prepended = 0;
+        for group in content.into_groups() {
+            let end = self.extent(index);
+            match self.find_exact(index + 1..end, &group[0].title) {
+                Some(child) => {
+                    let child_content = self.group_content(&group);
+                    self.merge(child, child_content, mode);
+                }
+                None => {
+                    let at = match mode {
+                        UpdateMode::Prepend => index + 1 + prepended,
+                        _ => end,
+                    };
+                    self.separate_before(at);
+                    let sections = self.adopt(group, level + 1);
+                    prepended += sections.len();
+                    self.sections.splice(at..at, sections);
+                }
+            }
+        }
+    }
+
+    /// `content` without the entries section `index` and its subsections
+    /// already hold: Markdown lines, or list items in storage format
+    fn new_entries(&self, index: usize, content: &str) -> String {
+        let existing: String = self.sections[index..self.extent(index)]
+            .iter()
+            .map(|section| section.body.as_str())
+            .collect();
+        match self.format {
+            SectionFormat::Markdown => {
+                let known: HashSet<&str> = existing
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .collect();
+                let lines: Vec<&str> = content
+                    .lines()
+                    .filter(|line| markdown_heading(line).is_some() || !known.contains(line.trim()))
+                    .collect();
+                without_empty_headings(&lines).join("\n")
+            }
+            SectionFormat::Storage => {
+                let known: HashSet<&str> = storage_item()
+                    .find_iter(&existing)
+                    .map(|item| item.as_str())
+                    .collect();
+                let items =
+                    storage_item().replace_all(content, |item: &regex::Captures| {
+                        match known.contains(&item[0]) {
+                            true => String::new(),
+                            false => item[0].to_string(),
+                        }
+                    });
+                static EMPTY_LIST: OnceLock<Regex> = OnceLock::new();
+                EMPTY_LIST
+                    .get_or_init(|| {
+                        Regex::new(r"<(ul|ol)>\s*</(ul|ol)>").expect("valid list pattern")
+                    })
+                    .replace_all(&items, "")
+                    .into_owned()
+            }
+        }
+    }
+
+    /// Parse update content in the format of this document
+    fn content(&self, text: &str) -> Self {
+        Self::parse(&format!("{}\n", text.trim_end()), self.format)
+    }
+
+    /// Split into groups of a top-level section and its subsections
+    fn into_groups(self) -> Vec<Vec<Section>> {
+        let Some(top) = self.sections.first().map(|s| s.level) else {
+            return Vec::new();
+        };
+        let mut groups: Vec<Vec<Section>> = Vec::new();
+        for section in self.sections {
+            match groups.last_mut() {
+                Some(group) if section.level > top => group.push(section),
+                _ => groups.push(vec![section]),
+            }
+        }
+        groups
+    }
+
+    /// The body and subsections of a group as content to merge
+    fn group_content(&self, group: &[Section]) -> Self {
+        Self {
+            format: self.format,
+            preamble: group[0].body.clone(),
+            sections: group[1..].to_vec(),
+        }
+    }
+
+    /// Shift a group so its top section is at `level` and normalize the
+    /// spacing of its bodies
+    fn adopt(&self, group: Vec<Section>, level: usize) -> Vec<Section> {
+        let shift = level as isize - group[0].level as isize;
+        group
+            .into_iter()
+            .map(|mut section| {
+                let level = (section.level as isize + shift).clamp(1, 6) as usize;
+                if level != section.level {
+                    section.level = level;
+                    section.heading = self.format.heading(&section.title, level);
+                }
+                section.body = self.format.block(&section.body);
+                section
+            })
+            .collect()
+    }
+
+    /// Leave a blank line before a heading inserted at `at`
+    fn separate_before(&mut self, at: usize) {
+        if self.format != SectionFormat::Markdown {
+            return;
+        }
+        let previous = match at {
+            0 => &mut self.preamble,
+            _ => &mut self.sections[at - 1].body,
+        };
+        let text = previous.trim_end_matches('\n');
+        *previous = if !text.trim().is_empty() {
+            format!("{}\n\n", text)
+        } else if at > 0 {
+            "\n".to_string()
+        } else {
+            String::new()
+        };
+    }
+}
+
+/// Where and how update content is written into a document
+#[derive(Debug, Clone, Copy)]
+pub struct SectionUpdate<'a> {
+    pub kind: DocKind,
+    /// Target section; takes precedence over the routing of `kind`
+    pub section: Option<&'a str>,
+    /// Defaults to `replace` for a target section and API endpoints and to
+    /// `append` otherwise
+    pub mode: Option<UpdateMode>,
+}
+
+impl SectionUpdate<'_> {
+    /// Where the content goes, e.g. `[Unreleased] (append)`
+    pub fn describe(&self) -> String {
+        let mode = self.effective_mode();
+        let target = match (self.section, self.kind) {
+            (Some(section), _) => format!("section '{}'", section),
+            (None, DocKind::Changelog) => UNRELEASED.to_string(),
+            (None, DocKind::ApiDoc) => "endpoint sections".to_string(),
+            (None, DocKind::General) if mode == UpdateMode::Replace => "whole document".to_string(),
+            (None, DocKind::General) => "dated update section".to_string(),
+        };
+        format!("{} ({})", target, mode.as_str())
+    }
+
+    fn effective_mode(&self) -> UpdateMode {
+        self.mode.unwrap_or(match (self.section, self.kind) {
+            (Some(_), _) | (None, DocKind::ApiDoc) => UpdateMode::Replace,
+            _ => UpdateMode::Append,
+        })
+    }
+
+    /// Existing section of `doc` that changelog entries are added to; `None`
+    /// for other updates, which may repeat content
+    fn changelog_target(&self, doc: &SectionedDocument) -> Option<usize> {
+        match self.adds_entries() {
+            true => doc.find(self.section.unwrap_or(UNRELEASED)),
+            false => None,
+        }
+    }
+
+    /// Whether the update adds changelog entries, which are never repeated
+    pub fn adds_entries(&self) -> bool {
+        self.kind == DocKind::Changelog && self.effective_mode() != UpdateMode::Replace
+    }
+
+    /// Write `content` into `document`; both must be in `format`. Changelog
+    /// entries already in the target section are not added again.
+    pub fn apply(&self, document: &str, content: &str, format: SectionFormat) -> String {
+        let mut doc = SectionedDocument::parse(document, format);
+        let mode = self.effective_mode();
+        let new_entries;
+        let content = match self.changelog_target(&doc) {
+            Some(index) => {
+                new_entries = doc.new_entries(index, content);
+                if !format.has_entries(&new_entries) {
+                    // Every entry is documented already
+                    return document.to_string();
+                }
+                new_entries.as_str()
+            }
+            None => content,
+        };
+        if let Some(section) = self.section {
+            doc.update_section(section, content, mode);
+            return doc.render();
+        }
+
+        match self.kind {
+            DocKind::Changelog => doc.update_unreleased(content, mode),
+            DocKind::ApiDoc => doc.merge_endpoints(content, mode),
+            DocKind::General => match mode {
+                UpdateMode::Replace => return doc.content(content).render(),
+                mode => {
+                    let content = doc.content(content);
+                    doc.add_dated_section(content, mode);
+                }
+            },
+        }
+        doc.render()
+    }
+}
+
+fn parse_markdown(text: &str) -> (String, Vec<Section>) {
+    let mut preamble = String::new();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some((level, title)) = markdown_heading(line) {
+            sections.push(Section {
+                title,
+                level,
+                heading: line.to_string(),
+                body: String::new(),
+            });
+            continue;
+        }
+
+        match sections.last_mut() {
+            Some(section) => section.body.push_str(line),
+            None => preamble.push_str(line),
+        }
+    }
+
+    (preamble, sections)
+}
+
+/// `lines` without the Markdown headings that have no entries left below
+/// them, up to the next heading of the same or a higher level
+fn without_empty_headings<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|&(i, line)| match markdown_heading(line) {
+            Some((level, _)) => lines[i + 1..]
+                .iter()
+                .take_while(|next| markdown_heading(next).is_none_or(|(l, _)| l > level))
+                .any(|next| !next.trim().is_empty() && markdown_heading(next).is_none()),
+            None => true,
+        })
+        .map(|(_, line)| *line)
+        .collect()
+}
+
+/// Level and title of an ATX heading line
+fn markdown_heading(line: &str) -> Option<(usize, String)> {
+    let line = line.trim_end();
+    let level = line.len() - line.trim_start_matches('#').len();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim().to_string()))
+}
+
+fn storage_heading() -> &'static Regex {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    HEADING.get_or_init(|| {
+        Regex::new(r"(?s)<h([1-6])(?:\s[^>]*)?>(.*?)</h[1-6]>").expect("valid heading pattern")
+    })
+}
+
+fn storage_tag() -> &'static Regex {
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    TAG.get_or_init(|| Regex::new(r"<[^>]+>").expect("valid tag pattern"))
+}
+
+/// A list item, the entry of a changelog in storage format
+fn storage_item() -> &'static Regex {
+    static ITEM: OnceLock<Regex> = OnceLock::new();
+    ITEM.get_or_init(|| Regex::new(r"(?s)<li(?:\s[^>]*)?>.*?</li>").expect("valid item pattern"))
+}
+
+fn parse_storage(text: &str) -> (String, Vec<Section>) {
+    let heading = storage_heading();
+    let tag = storage_tag();
+
+    let mut preamble = String::new();
+    let mut sections: Vec<Section> = Vec::new();
+    let mut last = 0;
+    for captures in heading.captures_iter(text) {
+        let whole = captures.get(0).expect("match");
+        let between = &text[last..whole.start()];
+        match sections.last_mut() {
+            Some(section) => section.body.push_str(between),
+            None => preamble.push_str(between),
+        }
+        let title = tag.replace_all(&captures[2], "");
+        sections.push(Section {
+            title: html_escape::decode_html_entities(title.trim()).to_string(),
+            level: captures[1].parse().unwrap_or(1),
+            heading: whole.as_str().to_string(),
+            body: String::new(),
+        });
+        last = whole.end();
+    }
+    match sections.last_mut() {
+        Some(section) => section.body.push_str(&text[last..]),
+        None => preamble.push_str(&text[last..]),
+    }
+
+    (preamble, sections)
+}
+
+/// Title compared case-insensitively and without inline markup, so
+/// `[Unreleased]` matches `unreleased` and `` `GET /users` `` matches
+/// `GET /users`
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| !matches!(c, '`' | '[' | ']' | '*'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(document: &str, content: &str, update: SectionUpdate) -> String {
+        update.apply(document, content, SectionFormat::Markdown)
+    }
+
+    fn section(title: &str, mode: Option<UpdateMode>) -> SectionUpdate<'_> {
+        SectionUpdate {
+            kind: DocKind::General,
+            section: Some(title),
+            mode,
+        }
+    }
+
+    const GUIDE: &str = "# Guide\n\nIntro\n\n## Usage\n\nOld usage\n\n### Flags\n\nOld flags\n\n## FAQ\n\nQuestions\n";
+
+    #[test]
+    fn test_parse_roundtrip_ignores_code_fences() {
+        let text = "# Title\n\n```sh\n# not a heading\n```\n\n## Next\nBody";
+        let doc = SectionedDocument::parse(text, SectionFormat::Markdown);
+        let titles: Vec<_> = doc.sections.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["Title", "Next"]);
+        assert_eq!(doc.render(), format!("{}\n", text));
+    }
+
+    #[test]
+    fn test_replace_section_drops_subsections() {
+        let result = apply(GUIDE, "New usage", section("usage", None));
+        assert_eq!(
+            result,
+            "# Guide\n\nIntro\n\n## Usage\n\nNew usage\n\n## FAQ\n\nQuestions\n"
+        );
+    }
+
+    #[test]
+    fn test_append_and_prepend_section() {
+        let appended = apply(
+            GUIDE,
+            "More usage",
+            section("Usage", Some(UpdateMode::Append)),
+        );
+        assert!(appended.contains("## Usage\n\nOld usage\n\nMore usage\n\n### Flags\n\nOld flags"));
+
+        let prepended = apply(GUIDE, "First", section("Usage", Some(UpdateMode::Prepend)));
+        assert!(prepended.contains("## Usage\n\nFirst\n\nOld usage\n\n### Flags"));
+    }
+
+    #[test]
+    fn test_append_merges_matching_subsections() {
+        let result = apply(
+            GUIDE,
+            "### Flags\n\n`--verbose`\n\n### Examples\n\nRun it",
+            section("Usage", Some(UpdateMode::Append)),
+        );
+        assert!(result
+            .contains("### Flags\n\nOld flags\n\n`--verbose`\n\n### Examples\n\nRun it\n\n## FAQ"));
+    }
+
+    #[test]
+    fn test_missing_section_is_added_at_end() {
+        let result = apply(GUIDE, "Notes", section("Limits", None));
+        assert!(result.ends_with("## FAQ\n\nQuestions\n\n## Limits\n\nNotes\n"));
+    }
+
+    #[test]
+    fn test_changelog_entries_go_under_unreleased() {
+        let update = SectionUpdate {
+            kind: DocKind::Changelog,
+            section: None,
+            mode: None,
+        };
+        let changelog = "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Search\n\n## [1.0.0]\n\n- First release\n";
+        let result = apply(
+            changelog,
+            "### Added\n\n- Export\n\n### Fixed\n\n- Crash",
+            update,
+        );
+        assert_eq!(
+            result,
+            "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Search\n\n- Export\n\n### Fixed\n\n- Crash\n\n## [1.0.0]\n\n- First release\n"
+        );
+
+        let created = apply(
+            "# Changelog\n\n## [1.0.0]\n\n- First release\n",
+            "- Export",
+            update,
+        );
+        assert_eq!(
+            created,
+            "# Changelog\n\n## [Unreleased]\n\n- Export\n\n## [1.0.0]\n\n- First release\n"
+        );
+    }
+
+    #[test]
+    fn test_changelog_entries_are_not_repeated() {
+        let update = SectionUpdate {
+            kind: DocKind::Changelog,
+            section: None,
+            mode: None,
+        };
+        let changelog = "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Export\n\n## [1.0.0]\n\n- First release\n";
+        assert_eq!(apply(changelog, "### Added\n\n- Export", update), changelog);
+
+        let merged = apply(
+            changelog,
+            "### Added\n\n- Export\n\n### Fixed\n\n- Crash",
+            update,
+        );
+        assert_eq!(
+            merged,
+            "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Export\n\n### Fixed\n\n- Crash\n\n## [1.0.0]\n\n- First release\n"
+        );
+
+        // Replacing the section may repeat what it held
+        let replace = SectionUpdate {
+            mode: Some(UpdateMode::Replace),
+            ..update
+        };
+        assert!(!replace.adds_entries());
+
+        let page = "<h2>[Unreleased]</h2><ul><li>Export</li></ul>";
+        assert_eq!(
+            update.apply(page, "<ul><li>Export</li></ul>", SectionFormat::Storage),
+            page
+        );
+        assert_eq!(
+            update.apply(
+                page,
+                "<ul><li>Export</li><li>Import</li></ul>",
+                SectionFormat::Storage
+            ),
+            "<h2>[Unreleased]</h2><ul><li>Export</li></ul><ul><li>Import</li></ul>"
+        );
+    }
+
+    #[test]
+    fn test_api_endpoints_merge_into_matching_sections() {
+        let update = SectionUpdate {
+            kind: DocKind::ApiDoc,
+            section: None,
+            mode: None,
+        };
+        let api = "# API\n\n## Users\n\n### GET /users\n\nLists users\n\n### DELETE /users/{id}\n\nDeletes a user\n";
+        let result = apply(
+            api,
+            "### `GET /users`\n\nLists users, paginated\n\n### POST /users\n\nCreates a user",
+            update,
+        );
+        assert_eq!(
+            result,
+            "# API\n\n## Users\n\n### GET /users\n\nLists users, paginated\n\n### DELETE /users/{id}\n\nDeletes a user\n\n### POST /users\n\nCreates a user\n"
+        );
+    }
+
+    #[test]
+    fn test_general_update_without_section() {
+        let update = SectionUpdate {
+            kind: DocKind::General,
+            section: None,
+            mode: None,
+        };
+        let result = apply("# Notes\n\nText", "Changed", update);
+        assert!(result.starts_with("# Notes\n\nText\n\n## Update "));
+        assert!(result.ends_with("\n\nChanged\n"));
+
+        let replaced = apply(
+            "# Notes\n\nText",
+            "# Fresh",
+            SectionUpdate {
+                mode: Some(UpdateMode::Replace),
+                ..update
+            },
+        );
+        assert_eq!(replaced, "# Fresh\n");
+    }
+
+    #[test]
+    fn test_storage_format_sections() {
+        let page = "<p>Intro</p><h2>Usage</h2><p>Old</p><h2 id=\"faq\">FAQ</h2><p>Q</p>";
+        let result = section("Usage", None).apply(page, "<p>New</p>", SectionFormat::Storage);
+        assert_eq!(
+            result,
+            "<p>Intro</p><h2>Usage</h2><p>New</p><h2 id=\"faq\">FAQ</h2><p>Q</p>"
+        );
+
+        let added = section("Limits & quotas", Some(UpdateMode::Append)).apply(
+            page,
+            "<p>None</p>",
+            SectionFormat::Storage,
+        );
+        assert!(added.ends_with("<h2>Limits &amp; quotas</h2><p>None</p>"));
+    }
+
+    #[test]
+    fn test_doc_kind_infer() {
+        assert_eq!(DocKind::infer("docs/CHANGELOG.md"), DocKind::Changelog);
+        assert_eq!(
+            DocKind::infer("/srv/docs/api-reference.md"),
+            DocKind::ApiDoc
+        );
+        assert_eq!(DocKind::infer("README.md"), DocKind::General);
+    }
+}
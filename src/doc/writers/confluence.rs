@@ -1,3 +1,5 @@
+use crate::doc::notices::Notices;
+use crate::doc::sections::{SectionFormat, SectionUpdate};
 use crate::error::{KtmeError, Result};
 use serde::{Deserialize, Serialize};
 
@@ -10,7 +12,7 @@ pub struct ConfluenceWriter {
 
 impl ConfluenceWriter {
     pub fn new(base_url: String, api_token: String, space_key: String) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("confluence")
             .user_agent("ktme-cli")
             .build()
             .unwrap_or_else(|_| reqwest::Client::new());
@@ -54,9 +56,7 @@ impl ConfluenceWriter {
             .json(&body)
             .send()
             .await
-            .map_err(|e| {
-                KtmeError::NetworkError(format!("Failed to create Confluence page: {}", e))
-            })?;
+            .map_err(|e| crate::http::request_error("Failed to create Confluence page", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -64,10 +64,14 @@ impl ConfluenceWriter {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(KtmeError::Confluence(format!(
-                "Failed to create page ({}): {}",
-                status, error_body
-            )));
+            return Err(KtmeError::from_status(
+                "Confluence",
+                status,
+                KtmeError::Confluence(format!(
+                    "Failed to create page ({}): {}",
+                    status, error_body
+                )),
+            ));
         }
 
         let created_page: CreatePageResponse = response.json().await.map_err(|e| {
@@ -78,25 +82,70 @@ impl ConfluenceWriter {
         Ok(created_page.id)
     }
 
-    pub async fn update_page(&self, page_id: &str, content: &str) -> Result<()> {
-        tracing::info!("Updating Confluence page: {}", page_id);
+    /// Write Markdown `content` into the sections of a page chosen by `update`
+    /// and refresh the page's `notices`
+    pub async fn update_page_sections(
+        &self,
+        page_id: &str,
+        content: &str,
+        update: &SectionUpdate<'_>,
+        notices: &Notices,
+    ) -> Result<()> {
+        tracing::info!("Updating sections of Confluence page: {}", page_id);
+
+        let current_page = self.get_page(page_id).await?;
+        let existing = current_page
+            .body
+            .as_ref()
+            .map(|body| body.storage.value.as_str())
+            .unwrap_or_default();
+        let storage_content = notices.update(existing, SectionFormat::Storage, |existing| {
+            update.apply(
+                existing,
+                &Self::markdown_to_storage_format(content),
+                SectionFormat::Storage,
+            )
+        });
+
+        self.put_page(page_id, current_page, storage_content).await
+    }
 
-        // First, get the current page to retrieve version number
-        let get_url = format!("{}/rest/api/content/{}", self.base_url, page_id);
-        let current_page: GetPageResponse = self
+    /// Current title, version and storage body of a page
+    async fn get_page(&self, page_id: &str) -> Result<GetPageResponse> {
+        let get_url = format!(
+            "{}/rest/api/content/{}?expand=body.storage,version",
+            self.base_url, page_id
+        );
+        let response = self
             .client
             .get(&get_url)
             .header("Authorization", format!("Bearer {}", self.api_token))
             .send()
             .await
-            .map_err(|e| KtmeError::NetworkError(format!("Failed to get page: {}", e)))?
+            .map_err(|e| crate::http::request_error("Failed to get page", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(KtmeError::from_status(
+                "Confluence",
+                status,
+                KtmeError::Confluence(format!("Failed to get page {} ({})", page_id, status)),
+            ));
+        }
+
+        response
             .json()
             .await
-            .map_err(|e| KtmeError::DeserializationError(format!("Failed to parse page: {}", e)))?;
-
-        // Convert Markdown to Confluence Storage Format
-        let storage_content = Self::markdown_to_storage_format(content);
+            .map_err(|e| KtmeError::DeserializationError(format!("Failed to parse page: {}", e)))
+    }
 
+    /// Replace the body of `current_page` with `storage_content` as a new version
+    async fn put_page(
+        &self,
+        page_id: &str,
+        current_page: GetPageResponse,
+        storage_content: String,
+    ) -> Result<()> {
         // Prepare update request
         let body = UpdatePageRequest {
             version: Version {
@@ -122,7 +171,7 @@ impl ConfluenceWriter {
             .json(&body)
             .send()
             .await
-            .map_err(|e| KtmeError::NetworkError(format!("Failed to update page: {}", e)))?;
+            .map_err(|e| crate::http::request_error("Failed to update page", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -130,10 +179,14 @@ impl ConfluenceWriter {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(KtmeError::Confluence(format!(
-                "Failed to update page ({}): {}",
-                status, error_body
-            )));
+            return Err(KtmeError::from_status(
+                "Confluence",
+                status,
+                KtmeError::Confluence(format!(
+                    "Failed to update page ({}): {}",
+                    status, error_body
+                )),
+            ));
         }
 
         tracing::info!("Successfully updated Confluence page: {}", page_id);
@@ -142,7 +195,7 @@ impl ConfluenceWriter {
 
     /// Convert Markdown to Confluence Storage Format (basic conversion)
     /// This handles: headings, paragraphs, bold, italic, code blocks, lists
-    fn markdown_to_storage_format(markdown: &str) -> String {
+    pub(crate) fn markdown_to_storage_format(markdown: &str) -> String {
         use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Parser, Tag, TagEnd};
 
         let mut html = String::new();
@@ -296,6 +349,17 @@ struct CreatePageResponse {
 struct GetPageResponse {
     title: String,
     version: Version,
+    body: Option<GetPageBody>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetPageBody {
+    storage: GetPageStorage,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetPageStorage {
+    value: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
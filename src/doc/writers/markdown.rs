@@ -1,4 +1,5 @@
 use crate::doc::generator::DocumentGenerator;
+use crate::doc::sections::{SectionFormat, SectionedDocument, UpdateMode};
 use crate::error::Result;
 use std::path::Path;
 
@@ -44,44 +45,9 @@ impl MarkdownWriter {
 
     /// Update a specific section in the markdown document
     fn update_specific_section(existing: &str, section_name: &str, new_content: &str) -> String {
-        let lines: Vec<&str> = existing.lines().collect();
-        let mut result: Vec<String> = Vec::new();
-        let mut in_target_section = false;
-        let mut section_found = false;
-        let section_header = format!("## {}", section_name);
-
-        for line in lines {
-            if line.trim() == section_header.trim()
-                || (line.starts_with("##")
-                    && line.to_lowercase().contains(&section_name.to_lowercase()))
-            {
-                // Found the target section
-                in_target_section = true;
-                section_found = true;
-                result.push(line.to_string());
-                result.push("".to_string());
-                result.extend(new_content.lines().map(|l| l.to_string()));
-                result.push("".to_string());
-            } else if in_target_section && line.starts_with("##") {
-                // Reached next section, stop replacing
-                in_target_section = false;
-                result.push(line.to_string());
-            } else if !in_target_section {
-                // Keep lines outside target section
-                result.push(line.to_string());
-            }
-            // Skip lines inside the target section (they're being replaced)
-        }
-
-        if !section_found {
-            // Section not found, append to end
-            result.push("".to_string());
-            result.push(section_header);
-            result.push("".to_string());
-            result.extend(new_content.lines().map(|l| l.to_string()));
-        }
-
-        result.join("\n")
+        let mut document = SectionedDocument::parse(existing, SectionFormat::Markdown);
+        document.update_section(section_name, new_content, UpdateMode::Replace);
+        document.render()
     }
 }
 
@@ -0,0 +1,597 @@
+//! Confluence storage format (XHTML) to Markdown
+//!
+//! Pages read from Confluence are converted so that agents, `ktme update`
+//! and the AI prompts see the same Markdown as in local documents. The
+//! conversion covers what ktme writes (see
+//! `ConfluenceWriter::markdown_to_storage_format`) plus the markup common in
+//! hand-written pages: tables, info/note/warning panels, task lists, page
+//! links and images. Other macros keep their body text, or are dropped when
+//! they have none (e.g. a table of contents).
+
+/// Elements laid out as blocks; any other element is rendered inline
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "hr",
+    "table",
+    "div",
+    "section",
+    "ac:structured-macro",
+    "ac:layout",
+    "ac:layout-section",
+    "ac:layout-cell",
+    "ac:rich-text-body",
+    "ac:task-list",
+];
+
+/// Elements that never have content
+const VOID_ELEMENTS: &[&str] = &["br", "hr", "img", "col", "meta", "input"];
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Element(Element),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find_map(|node| match node {
+            Node::Element(el) if el.name == name => Some(el),
+            _ => None,
+        })
+    }
+
+    /// Value of the `<ac:parameter ac:name="name">` of a macro
+    fn parameter(&self, name: &str) -> Option<String> {
+        self.children.iter().find_map(|node| match node {
+            Node::Element(el) if el.name == "ac:parameter" && el.attr("ac:name") == Some(name) => {
+                Some(text(&el.children))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Markdown of the Confluence storage format `storage`
+pub fn to_markdown(storage: &str) -> String {
+    let nodes = parse(storage);
+    let markdown = blocks(&nodes, "\n\n");
+    if markdown.is_empty() {
+        markdown
+    } else {
+        format!("{}\n", markdown)
+    }
+}
+
+/// Element tree of `input`. Unclosed elements end with their parent, and
+/// stray closing tags are ignored.
+fn parse(input: &str) -> Vec<Node> {
+    let mut stack: Vec<Element> = vec![Element {
+        name: String::new(),
+        attrs: Vec::new(),
+        children: Vec::new(),
+    }];
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut stack, &decode(rest));
+            break;
+        };
+        if start > 0 {
+            push_text(&mut stack, &decode(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            push_text(&mut stack, &cdata[..end]);
+            rest = cdata.get(end + 3..).unwrap_or_default();
+            continue;
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map(|end| &comment[end + 3..])
+                .unwrap_or_default();
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            push_text(&mut stack, &decode(rest));
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(depth) = stack.iter().rposition(|el| el.name == name) {
+                while stack.len() > depth.max(1) {
+                    close(&mut stack);
+                }
+            }
+        } else if !tag.starts_with('!') && !tag.starts_with('?') {
+            let self_closing = tag.ends_with('/');
+            let element = element(tag.trim_end_matches('/'));
+            if self_closing || VOID_ELEMENTS.contains(&element.name.as_str()) {
+                push(&mut stack, Node::Element(element));
+            } else {
+                stack.push(element);
+            }
+        }
+    }
+
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    stack.pop().map(|root| root.children).unwrap_or_default()
+}
+
+/// Index of the `>` ending the tag at the start of `input`, skipping
+/// quoted attribute values
+fn tag_end(input: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in input.char_indices().skip(1) {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Element of the opening tag `tag`, without its `<>`
+fn element(tag: &str) -> Element {
+    let tag = tag.trim();
+    let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+    let mut attrs = Vec::new();
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        let value = rest[eq + 1..].trim_start();
+        let (value, remaining) = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => {
+                let end = value[1..].find(q).map(|i| i + 1).unwrap_or(value.len());
+                (&value[1..end], value.get(end + 1..).unwrap_or_default())
+            }
+            _ => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+        };
+        attrs.push((key, decode(value)));
+        rest = remaining;
+    }
+    Element {
+        name: name.to_ascii_lowercase(),
+        attrs,
+        children: Vec::new(),
+    }
+}
+
+fn decode(text: &str) -> String {
+    html_escape::decode_html_entities(text).into_owned()
+}
+
+fn push(stack: &mut [Element], node: Node) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if !text.is_empty() {
+        push(stack, Node::Text(text.to_string()));
+    }
+}
+
+fn close(stack: &mut Vec<Element>) {
+    if let Some(element) = stack.pop() {
+        push(stack, Node::Element(element));
+    }
+}
+
+fn is_block(node: &Node) -> bool {
+    matches!(node, Node::Element(el) if BLOCK_ELEMENTS.contains(&el.name.as_str()))
+}
+
+/// Markdown blocks of `nodes` joined by `separator`; runs of inline
+/// content become paragraphs
+fn blocks(nodes: &[Node], separator: &str) -> String {
+    let mut out: Vec<String> = Vec::new();
+    let mut run: Vec<&Node> = Vec::new();
+    let flush = |run: &mut Vec<&Node>, out: &mut Vec<String>| {
+        let paragraph = inline_nodes(run.iter().copied());
+        let paragraph = paragraph.trim();
+        if !paragraph.is_empty() {
+            out.push(paragraph.to_string());
+        }
+        run.clear();
+    };
+
+    for node in nodes {
+        match node {
+            Node::Element(el) if is_block(node) => {
+                flush(&mut run, &mut out);
+                let block = block(el);
+                if !block.trim().is_empty() {
+                    out.push(block.trim_end().to_string());
+                }
+            }
+            _ => run.push(node),
+        }
+    }
+    flush(&mut run, &mut out);
+    out.join(separator)
+}
+
+fn block(el: &Element) -> String {
+    match el.name.as_str() {
+        "p" => inline(&el.children).trim().to_string(),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = el.name[1..].parse().unwrap_or(1);
+            format!("{} {}", "#".repeat(level), inline(&el.children).trim())
+        }
+        "ul" => list(el, false),
+        "ol" => list(el, true),
+        "pre" => fence("", &text(&el.children)),
+        "blockquote" => quote(&blocks(&el.children, "\n\n")),
+        "hr" => "---".to_string(),
+        "table" => table(el),
+        "ac:task-list" => tasks(el),
+        "ac:structured-macro" => structured_macro(el),
+        _ => blocks(&el.children, "\n\n"),
+    }
+}
+
+fn structured_macro(el: &Element) -> String {
+    let name = el.attr("ac:name").unwrap_or_default();
+    match name {
+        "code" | "noformat" => {
+            let language = el.parameter("language").unwrap_or_default();
+            let body = el
+                .child("ac:plain-text-body")
+                .map(|body| text(&body.children))
+                .unwrap_or_default();
+            fence(&language, &body)
+        }
+        "info" | "note" | "tip" | "warning" | "panel" => {
+            let body = el
+                .child("ac:rich-text-body")
+                .map(|body| blocks(&body.children, "\n\n"))
+                .unwrap_or_default();
+            let label = match el.parameter("title").filter(|t| !t.trim().is_empty()) {
+                Some(title) => title.trim().to_string(),
+                None => capitalize(name),
+            };
+            quote(&format!("**{}:** {}", label, body))
+        }
+        _ => el
+            .child("ac:rich-text-body")
+            .or_else(|| el.child("ac:plain-text-body"))
+            .map(|body| blocks(&body.children, "\n\n"))
+            .unwrap_or_default(),
+    }
+}
+
+fn list(el: &Element, ordered: bool) -> String {
+    let mut number = el
+        .attr("start")
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1);
+    let mut items = Vec::new();
+    for node in &el.children {
+        let Node::Element(item) = node else {
+            continue;
+        };
+        if item.name != "li" {
+            continue;
+        }
+        let marker = if ordered {
+            format!("{}. ", number)
+        } else {
+            "- ".to_string()
+        };
+        number += 1;
+        items.push(indent(&marker, &blocks(&item.children, "\n")));
+    }
+    items.join("\n")
+}
+
+fn tasks(el: &Element) -> String {
+    let mut items = Vec::new();
+    for node in &el.children {
+        let Node::Element(task) = node else {
+            continue;
+        };
+        if task.name != "ac:task" {
+            continue;
+        }
+        let done = task
+            .child("ac:task-status")
+            .is_some_and(|status| text(&status.children).trim() == "complete");
+        let body = task
+            .child("ac:task-body")
+            .map(|body| blocks(&body.children, "\n"))
+            .unwrap_or_default();
+        let marker = if done { "- [x] " } else { "- [ ] " };
+        items.push(indent(marker, &body));
+    }
+    items.join("\n")
+}
+
+fn table(el: &Element) -> String {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    collect_rows(el, &mut rows);
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+
+    let line = |cells: &[String]| {
+        let mut cells = cells.to_vec();
+        cells.resize(columns, String::new());
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = vec![line(&rows[0]), line(&vec!["---".to_string(); columns])];
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    lines.join("\n")
+}
+
+fn collect_rows(el: &Element, rows: &mut Vec<Vec<String>>) {
+    for node in &el.children {
+        let Node::Element(child) = node else {
+            continue;
+        };
+        match child.name.as_str() {
+            "tr" => rows.push(
+                child
+                    .children
+                    .iter()
+                    .filter_map(|cell| match cell {
+                        Node::Element(cell) if cell.name == "td" || cell.name == "th" => Some(
+                            blocks(&cell.children, " ")
+                                .replace('\n', " ")
+                                .replace('|', "\\|"),
+                        ),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+            _ => {}
+        }
+    }
+}
+
+fn inline(nodes: &[Node]) -> String {
+    inline_nodes(nodes.iter())
+}
+
+fn inline_nodes<'a>(nodes: impl Iterator<Item = &'a Node>) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(text) => {
+                let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.starts_with(char::is_whitespace) && !out.ends_with([' ', '\n']) {
+                    out.push(' ');
+                }
+                out.push_str(&collapsed);
+                if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+                    out.push(' ');
+                }
+            }
+            Node::Element(el) => out.push_str(&inline_element(el)),
+        }
+    }
+    out
+}
+
+fn inline_element(el: &Element) -> String {
+    match el.name.as_str() {
+        "strong" | "b" => wrap("**", &inline(&el.children)),
+        "em" | "i" => wrap("*", &inline(&el.children)),
+        "s" | "del" | "strike" => wrap("~~", &inline(&el.children)),
+        "code" | "tt" => wrap("`", &text(&el.children)),
+        "br" => "  \n".to_string(),
+        "a" => {
+            let label = inline(&el.children);
+            match el.attr("href") {
+                Some(href) => format!("[{}]({})", label.trim(), href),
+                None => label,
+            }
+        }
+        "img" => format!("![]({})", el.attr("src").unwrap_or_default()),
+        "ac:image" => {
+            let source = el
+                .child("ri:url")
+                .and_then(|url| url.attr("ri:value"))
+                .or_else(|| {
+                    el.child("ri:attachment")
+                        .and_then(|file| file.attr("ri:filename"))
+                })
+                .unwrap_or_default();
+            format!("![{}]({})", el.attr("ac:alt").unwrap_or_default(), source)
+        }
+        "ac:link" => {
+            let label = el
+                .child("ac:link-body")
+                .map(|body| inline(&body.children))
+                .or_else(|| {
+                    el.child("ac:plain-text-link-body")
+                        .map(|body| text(&body.children))
+                })
+                .filter(|label| !label.trim().is_empty());
+            let page = el
+                .child("ri:page")
+                .and_then(|page| page.attr("ri:content-title"));
+            match (label, page) {
+                (Some(label), _) => label.trim().to_string(),
+                (None, Some(page)) => page.to_string(),
+                (None, None) => String::new(),
+            }
+        }
+        "ac:parameter" | "ac:emoticon" | "ac:placeholder" => String::new(),
+        _ if el.name.starts_with("ri:") => String::new(),
+        _ => inline(&el.children),
+    }
+}
+
+/// Raw text of `nodes`, as in code
+fn text(nodes: &[Node]) -> String {
+    nodes
+        .iter()
+        .map(|node| match node {
+            Node::Text(text) => text.clone(),
+            Node::Element(el) if el.name == "br" => "\n".to_string(),
+            Node::Element(el) => text(&el.children),
+        })
+        .collect()
+}
+
+/// `content` between `marker`s, the spaces around it kept outside
+fn wrap(marker: &str, content: &str) -> String {
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        return content.to_string();
+    }
+    let leading = if content.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trailing = if content.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing)
+}
+
+fn fence(language: &str, code: &str) -> String {
+    format!("```{}\n{}\n```", language.trim(), code.trim_matches('\n'))
+}
+
+fn quote(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `content` after `marker`, its further lines indented to line up
+fn indent(marker: &str, content: &str) -> String {
+    let padding = " ".repeat(marker.len());
+    let mut lines = content.lines();
+    let mut out = format!("{}{}", marker, lines.next().unwrap_or_default());
+    for line in lines {
+        out.push('\n');
+        if !line.is_empty() {
+            out.push_str(&padding);
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doc::writers::confluence::ConfluenceWriter;
+
+    #[test]
+    fn test_storage_format_to_markdown() {
+        let storage = "<h1>Payments</h1><p>Handles <strong>card</strong> and <em>bank</em> \
+            payments &amp; refunds, see <a href=\"https://example.com/api\">the API</a>.</p>\
+            <h2>Usage</h2><ul><li>Call <code>charge</code><ul><li>with an amount</li></ul></li>\
+            <li>Check the result</li></ul><ol start=\"3\"><li>Third</li><li>Fourth</li></ol>\
+            <ac:structured-macro ac:name=\"code\"><ac:parameter ac:name=\"language\">rust\
+            </ac:parameter><ac:plain-text-body><![CDATA[if a < b {\n    pay();\n}]]>\
+            </ac:plain-text-body></ac:structured-macro>\
+            <ac:structured-macro ac:name=\"warning\"><ac:rich-text-body><p>Amounts are in \
+            cents.</p></ac:rich-text-body></ac:structured-macro>\
+            <ac:structured-macro ac:name=\"toc\" /><hr/>\
+            <table><tbody><tr><th>Code</th><th>Meaning</th></tr>\
+            <tr><td>402</td><td>Declined | retry</td></tr></tbody></table>\
+            <p>Line one<br/>line two with <ac:link><ri:page ri:content-title=\"Refunds\" />\
+            </ac:link>&nbsp;page</p>";
+
+        assert_eq!(
+            to_markdown(storage),
+            "# Payments\n\n\
+             Handles **card** and *bank* payments & refunds, see [the API](https://example.com/api).\n\n\
+             ## Usage\n\n\
+             - Call `charge`\n  - with an amount\n- Check the result\n\n\
+             3. Third\n4. Fourth\n\n\
+             ```rust\nif a < b {\n    pay();\n}\n```\n\n\
+             > **Warning:** Amounts are in cents.\n\n\
+             ---\n\n\
+             | Code | Meaning |\n| --- | --- |\n| 402 | Declined \\| retry |\n\n\
+             Line one  \nline two with Refunds page\n"
+        );
+    }
+
+    #[test]
+    fn test_task_lists_and_malformed_markup() {
+        let storage = "<ac:task-list><ac:task><ac:task-status>complete</ac:task-status>\
+            <ac:task-body>Ship it</ac:task-body></ac:task><ac:task>\
+            <ac:task-status>incomplete</ac:task-status><ac:task-body>Announce</ac:task-body>\
+            </ac:task></ac:task-list><p>Unclosed <strong>bold</p></div><p>End";
+        assert_eq!(
+            to_markdown(storage),
+            "- [x] Ship it\n- [ ] Announce\n\nUnclosed **bold**\n\nEnd\n"
+        );
+        assert_eq!(to_markdown(""), "");
+    }
+
+    #[test]
+    fn test_markdown_written_by_ktme_reads_back_unchanged() {
+        let markdown = "# Payments API\n\n\
+            Charges **cards** and *wallets*, see [docs](https://example.com).\n\n\
+            ## Changes\n\n\
+            - Added `refund`\n- Removed `void`\n\n\
+            1. Migrate\n2. Deploy\n\n\
+            ```rust\nfn refund() {}\n```\n\n\
+            > Deprecated in 2.0\n\n\
+            ---\n";
+        let storage = ConfluenceWriter::markdown_to_storage_format(markdown);
+        assert_eq!(to_markdown(&storage), markdown);
+    }
+}
@@ -0,0 +1,455 @@
+//! High-level API for embedding ktme in other Rust tools
+//!
+//! [`KtmeEngine`] bundles configuration, the AI client and the repository to
+//! read changes from, so bots and CI plugins can run the extract → generate →
+//! publish pipeline without shelling out to the CLI.
+//!
+//! ```no_run
+//! use ktme::engine::{DiffSource, GenerateOptions, KtmeEngine, PublishOptions};
+//!
+//! # async fn run() -> ktme::Result<()> {
+//! let engine = KtmeEngine::builder()
+//!     .config(ktme::config::Config::load()?)
+//!     .repository("/path/to/repo")
+//!     .build()?;
+//!
+//! let diff = engine.extract(DiffSource::Commit("HEAD".to_string()))?;
+//! let doc = engine
+//!     .generate("payments", &diff, &GenerateOptions::default())
+//!     .await?;
+//! engine
+//!     .publish("payments", &doc.documentation, &PublishOptions::default())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::ai::{prompt_pack::PromptPack, AIClient};
+use crate::cli::commands::generate::{format_documentation, load_custom_template};
+use crate::cli::commands::update::publish_to_location;
+use crate::config::Config;
+use crate::doc::annotations;
+use crate::doc::notices::NoticeContext;
+use crate::doc::personas;
+use crate::doc::providers::PublishStatus;
+use crate::doc::sections::{DocKind, SectionUpdate, UpdateMode};
+use crate::error::{KtmeError, Result};
+use crate::git::cache::CommitDiffCache;
+use crate::git::diff::{DiffExtractor, ExtractedDiff};
+use crate::git::ownership::{self, Ownership};
+use crate::git::reader::DiffLimits;
+use crate::git::remote::{token_for, RemoteCheckout};
+use crate::issues::{IssueLinker, RelatedTicket};
+use crate::plugins::extensions::Extensions;
+use crate::storage::mapping::{DocumentLocation, ServiceSearchResult, StorageManager};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Changes to document
+#[derive(Debug, Clone)]
+pub enum DiffSource {
+    /// A commit, branch or tag in the engine's repository
+    Commit(String),
+    /// Changes staged in the engine's repository
+    Staged,
+    /// A commit or branch fetched from a remote repository
+    Remote { url: String, reference: String },
+}
+
+/// What to search for with [`KtmeEngine::search`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchKind {
+    /// Service names, descriptions and paths
+    #[default]
+    Service,
+    Feature,
+    Keyword,
+}
+
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// `general`, `changelog` or `api-doc`
+    pub doc_type: String,
+    /// Prompt template file with `{{...}}` placeholders
+    pub template: Option<PathBuf>,
+    /// Values of the template's variables
+    pub variables: HashMap<String, String>,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            doc_type: "general".to_string(),
+            template: None,
+            variables: HashMap::new(),
+        }
+    }
+}
+
+/// Documentation generated for one service
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedDoc {
+    pub service: String,
+    pub doc_type: String,
+    /// Documentation as returned by the AI provider
+    pub documentation: String,
+    /// Markdown document with header, related tickets and primary contacts,
+    /// as written by `ktme generate --output`
+    pub rendered: String,
+    pub related_tickets: Vec<RelatedTicket>,
+    pub primary_contacts: Ownership,
+    pub provider: String,
+}
+
+/// Section routing for [`KtmeEngine::publish`]; see `ktme update`
+#[derive(Debug, Clone, Default)]
+pub struct PublishOptions {
+    pub section: Option<String>,
+    /// Overrides the type inferred from each location
+    pub doc_type: Option<DocKind>,
+    pub mode: Option<UpdateMode>,
+    /// Service and source commit filled into the `[notices]` of the
+    /// documents
+    pub service: Option<String>,
+    pub source: Option<String>,
+}
+
+/// Outcome of publishing to one documentation location
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishedLocation {
+    pub r#type: String,
+    pub location: String,
+    pub status: PublishStatus,
+}
+
+/// Embeddable ktme pipeline; create one with [`KtmeEngine::builder`]
+pub struct KtmeEngine {
+    config: Config,
+    repository: Option<PathBuf>,
+    ai_client: AIClient,
+    extensions: Extensions,
+}
+
+#[derive(Default)]
+pub struct KtmeEngineBuilder {
+    config: Option<Config>,
+    repository: Option<PathBuf>,
+    ai_client: Option<AIClient>,
+}
+
+impl KtmeEngineBuilder {
+    /// Configuration to use instead of the ktme config file
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Repository to extract changes from (defaults to the current directory)
+    pub fn repository(mut self, path: impl Into<PathBuf>) -> Self {
+        self.repository = Some(path.into());
+        self
+    }
+
+    /// AI client to use instead of the one detected from the environment
+    pub fn ai_client(mut self, ai_client: AIClient) -> Self {
+        self.ai_client = Some(ai_client);
+        self
+    }
+
+    pub fn build(self) -> Result<KtmeEngine> {
+        let config = match self.config {
+            Some(config) => config,
+            None => Config::load()?,
+        };
+        // API clients built from here on use the engine's timeouts
+        config.timeouts.validate()?;
+        crate::http::configure(&config.timeouts);
+        crate::http::set_offline(config.general.offline);
+        let ai_client = match self.ai_client {
+            Some(ai_client) => ai_client,
+            None => AIClient::from_config(&config.ai)?,
+        };
+
+        let extensions = Extensions::load(&config)?;
+
+        Ok(KtmeEngine {
+            config,
+            repository: self.repository,
+            ai_client,
+            extensions,
+        })
+    }
+}
+
+impl KtmeEngine {
+    pub fn builder() -> KtmeEngineBuilder {
+        KtmeEngineBuilder::default()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Model of the engine's AI client, when its provider names one
+    pub fn model(&self) -> Option<&str> {
+        self.ai_client.model()
+    }
+
+    /// Extract changes, with per-file diffs capped by `git.max_file_diff_bytes`
+    pub fn extract(&self, source: DiffSource) -> Result<ExtractedDiff> {
+        let limits = DiffLimits::from_config(&self.config.git);
+        let repository = self.repository_path();
+        match source {
+            DiffSource::Commit(reference) => {
+                DiffExtractor::new("commit".to_string(), reference, repository.as_deref())?
+                    .with_limits(limits)
+                    .with_cache(CommitDiffCache::from_config(&self.config))
+                    .extract()
+            }
+            DiffSource::Staged => DiffExtractor::new(
+                "staged".to_string(),
+                "staged".to_string(),
+                repository.as_deref(),
+            )?
+            .with_limits(limits)
+            .extract(),
+            DiffSource::Remote { url, reference } => {
+                let token = token_for(&self.config.git, &url);
+                RemoteCheckout::fetch(&url, &reference, token.as_deref(), limits)?.read_commit()
+            }
+        }
+    }
+
+    /// Generate documentation for `service` from `diff`.
+    ///
+    /// Unlike `ktme generate` nothing is written: the generation history,
+    /// knowledge graph and Jira comments are left untouched.
+    pub async fn generate(
+        &self,
+        service: &str,
+        diff: &ExtractedDiff,
+        options: &GenerateOptions,
+    ) -> Result<GeneratedDoc> {
+        let primary_contacts = if self.config.documentation.include_contacts {
+            let repository = self.repository_path();
+            ownership::analyze(repository.as_deref(), diff).unwrap_or_else(|e| {
+                tracing::debug!("Skipping primary contacts: {}", e);
+                Ownership::default()
+            })
+        } else {
+            Ownership::default()
+        };
+
+        let context = personas::context(
+            &self.config,
+            &options.doc_type,
+            Some(service),
+            diff,
+            &Default::default(),
+        );
+        let prompt = match &options.template {
+            Some(template) => load_custom_template(
+                &template.to_string_lossy(),
+                diff,
+                &primary_contacts,
+                &options.variables,
+                context.as_deref(),
+            )?,
+            None => PromptPack::load(self.config.documentation.prompt_pack.as_deref())?
+                .documentation_prompt(diff, &options.doc_type, context.as_deref())?,
+        };
+        let prompt = self.extensions.annotate(prompt, diff);
+        let documentation = self
+            .extensions
+            .format(self.ai_client.generate_documentation(&prompt).await?);
+
+        let related_tickets = if self.config.issues.enabled {
+            let linker = IssueLinker::new(
+                self.config.issues.clone(),
+                self.config.git.github_token.clone(),
+            );
+            let default_repo = diff.source.strip_prefix("github-pr-");
+            linker.resolve(&linker.detect(diff), default_repo).await
+        } else {
+            Vec::new()
+        };
+
+        Ok(GeneratedDoc {
+            service: service.to_string(),
+            doc_type: options.doc_type.clone(),
+            rendered: format_documentation(
+                &documentation,
+                &options.doc_type,
+                service,
+                &related_tickets,
+                &annotations::collect(diff),
+                &primary_contacts,
+            ),
+            documentation,
+            related_tickets,
+            primary_contacts,
+            provider: self.ai_client.provider_name().to_string(),
+        })
+    }
+
+    /// Write `content` into every documentation location mapped for
+    /// `service`
+    pub async fn publish(
+        &self,
+        service: &str,
+        content: &str,
+        options: &PublishOptions,
+    ) -> Result<Vec<PublishedLocation>> {
+        let mapping = StorageManager::from_config(&self.config)?.get_mapping(service)?;
+        if mapping.docs.is_empty() {
+            return Err(KtmeError::DocumentNotFound(format!(
+                "No documentation locations mapped for service: {}",
+                service
+            )));
+        }
+        let options = PublishOptions {
+            service: options
+                .service
+                .clone()
+                .or_else(|| Some(service.to_string())),
+            ..options.clone()
+        };
+        Ok(self.publish_to(&mapping.docs, content, &options).await)
+    }
+
+    /// Write `content` into the given documentation locations. Failures are
+    /// reported per location.
+    pub async fn publish_to(
+        &self,
+        locations: &[DocumentLocation],
+        content: &str,
+        options: &PublishOptions,
+    ) -> Vec<PublishedLocation> {
+        let mut published = Vec::new();
+        for doc_location in locations {
+            let update = SectionUpdate {
+                kind: options
+                    .doc_type
+                    .unwrap_or_else(|| DocKind::infer(&doc_location.location)),
+                section: options.section.as_deref(),
+                mode: options.mode,
+            };
+            let notice = NoticeContext {
+                service: options.service.as_deref(),
+                doc_type: None,
+                source: options.source.as_deref(),
+            };
+            let status =
+                match publish_to_location(&self.config, doc_location, content, &update, &notice)
+                    .await
+                {
+                    Some(Ok(())) => PublishStatus::Updated,
+                    Some(Err(e)) => PublishStatus::Failed(e.to_string()),
+                    None => PublishStatus::Failed(format!(
+                        "Unknown documentation type: {}",
+                        doc_location.r#type
+                    )),
+                };
+            published.push(PublishedLocation {
+                r#type: doc_location.r#type.clone(),
+                location: doc_location.location.clone(),
+                status,
+            });
+        }
+        published
+    }
+
+    pub fn search(&self, query: &str, kind: SearchKind) -> Result<Vec<ServiceSearchResult>> {
+        let storage = StorageManager::from_config(&self.config)?;
+        match kind {
+            SearchKind::Service => storage.search_services(query),
+            SearchKind::Feature => storage.search_by_feature(query),
+            SearchKind::Keyword => storage.search_by_keyword(query),
+        }
+    }
+
+    /// Map `service` to a documentation location: a Markdown file path or
+    /// a Confluence page URL
+    pub fn map_service(&self, service: &str, location: DocumentLocation) -> Result<()> {
+        if location.r#type == "markdown" && !Path::new(&location.location).exists() {
+            return Err(KtmeError::Config(format!(
+                "File does not exist: {}",
+                location.location
+            )));
+        }
+        StorageManager::from_config(&self.config)?.add_mapping(service.to_string(), location)
+    }
+
+    fn repository_path(&self) -> Option<String> {
+        self.repository
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::providers::AIProviderFactory;
+    use tempfile::TempDir;
+
+    fn engine(repository: &Path) -> KtmeEngine {
+        KtmeEngine::builder()
+            .config(Config::default())
+            .repository(repository)
+            .ai_client(AIClient::with_provider(
+                AIProviderFactory::create_mock().unwrap(),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_engine_extract_generate_publish() {
+        let dir = TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "pub fn api() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("lib.rs")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Add api", &tree, &[])
+            .unwrap();
+
+        let engine = engine(dir.path());
+        let diff = engine
+            .extract(DiffSource::Commit("HEAD".to_string()))
+            .unwrap();
+        assert_eq!(diff.files.len(), 1);
+
+        let doc = engine
+            .generate("engine-test", &diff, &GenerateOptions::default())
+            .await
+            .unwrap();
+        assert!(doc.rendered.starts_with("# Documentation for engine-test"));
+        assert_eq!(doc.provider, "Mock");
+
+        let notes = dir.path().join("notes.md");
+        std::fs::write(&notes, "# Notes\n\n## Usage\n\nOld\n").unwrap();
+        let published = engine
+            .publish_to(
+                &[DocumentLocation {
+                    r#type: "markdown".to_string(),
+                    location: notes.to_string_lossy().into_owned(),
+                    section: None,
+                }],
+                "New usage",
+                &PublishOptions {
+                    section: Some("Usage".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(published[0].status, PublishStatus::Updated));
+        assert_eq!(
+            std::fs::read_to_string(&notes).unwrap(),
+            "# Notes\n\n## Usage\n\nNew usage\n"
+        );
+    }
+}
@@ -22,6 +22,27 @@ pub enum KtmeError {
     #[error("Confluence API error: {0}")]
     Confluence(String),
 
+    #[error("{service} rejected the credentials ({status})")]
+    Unauthorized { service: &'static str, status: u16 },
+
+    #[error("Not signed in to {service}: {reason}")]
+    NotSignedIn {
+        service: &'static str,
+        reason: String,
+    },
+
+    #[error("{service} storage quota exceeded: {message}")]
+    QuotaExceeded {
+        service: &'static str,
+        message: String,
+    },
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("{0} needs network access, which offline mode disables")]
+    Offline(String),
+
     #[error("Service mapping not found: {0}")]
     MappingNotFound(String),
 
@@ -67,12 +88,296 @@ pub enum KtmeError {
     #[error("Document already exists: {0}")]
     DocumentExists(String),
 
+    #[error("Document changed since it was read: {0}")]
+    DocumentChanged(String),
+
     #[error("Unsupported operation: {0}")]
     UnsupportedOperation(String),
 
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Another generation is in progress: {0}")]
+    GenerationInProgress(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
+
+/// Class of a failure; each class has its own process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    General,
+    Usage,
+    Config,
+    Auth,
+    NotFound,
+    Network,
+    Remote,
+    Git,
+    Storage,
+    Io,
+    Data,
+    Cancelled,
+}
+
+/// Exit codes by error class, shown in `ktme --help` and the man page
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0   success
+  1   general failure (documentation generation, unknown errors)
+  2   invalid arguments or input
+  3   configuration error
+  4   authentication rejected by a remote service
+  5   service mapping or document not found
+  6   network failure
+  7   remote API error
+  8   git error
+  9   storage error
+  10  file system error
+  11  malformed data
+  130 cancelled by SIGINT or SIGTERM";
+
+impl ErrorClass {
+    /// Stable identifier reported as `code` in JSON output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::General => "general",
+            Self::Usage => "usage",
+            Self::Config => "config",
+            Self::Auth => "auth",
+            Self::NotFound => "not_found",
+            Self::Network => "network",
+            Self::Remote => "remote",
+            Self::Git => "git",
+            Self::Storage => "storage",
+            Self::Io => "io",
+            Self::Data => "data",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::General => 1,
+            Self::Usage => 2,
+            Self::Config => 3,
+            Self::Auth => 4,
+            Self::NotFound => 5,
+            Self::Network => 6,
+            Self::Remote => 7,
+            Self::Git => 8,
+            Self::Storage => 9,
+            Self::Io => 10,
+            Self::Data => 11,
+            Self::Cancelled => crate::shutdown::EXIT_CODE,
+        }
+    }
+}
+
+impl KtmeError {
+    /// `Unauthorized` for a 401/403 response from `service`, `otherwise` for
+    /// any other failure status
+    pub fn from_status(
+        service: &'static str,
+        status: reqwest::StatusCode,
+        otherwise: KtmeError,
+    ) -> Self {
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+                Self::Unauthorized {
+                    service,
+                    status: status.as_u16(),
+                }
+            }
+            _ => otherwise,
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::InvalidInput(_)
+            | Self::UnsupportedProvider(_)
+            | Self::UnsupportedOperation(_)
+            | Self::DocumentExists(_)
+            | Self::Offline(_) => ErrorClass::Usage,
+            Self::Config(_) | Self::ConfigError(_) | Self::TomlDe(_) | Self::TomlSer(_) => {
+                ErrorClass::Config
+            }
+            Self::Unauthorized { .. } | Self::NotSignedIn { .. } => ErrorClass::Auth,
+            Self::Http(e)
+                if matches!(
+                    e.status(),
+                    Some(reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN)
+                ) =>
+            {
+                ErrorClass::Auth
+            }
+            Self::MappingNotFound(_) | Self::DocumentNotFound(_) | Self::NotFound(_) => {
+                ErrorClass::NotFound
+            }
+            Self::NetworkError(_) | Self::Timeout(_) => ErrorClass::Network,
+            Self::Http(e) if e.status().is_some() => ErrorClass::Remote,
+            Self::Http(_) => ErrorClass::Network,
+            Self::Confluence(_) | Self::ApiError(_) | Self::Mcp(_) | Self::QuotaExceeded { .. } => {
+                ErrorClass::Remote
+            }
+            Self::Git(_) => ErrorClass::Git,
+            Self::Storage(_) => ErrorClass::Storage,
+            Self::Io(_) => ErrorClass::Io,
+            Self::Serialization(_)
+            | Self::DeserializationError(_)
+            | Self::SerializationError(_) => ErrorClass::Data,
+            Self::Documentation(_)
+            | Self::DocumentChanged(_)
+            | Self::GenerationInProgress(_)
+            | Self::Unknown(_) => ErrorClass::General,
+            Self::Cancelled(_) => ErrorClass::Cancelled,
+        }
+    }
+
+    /// Next step for the user, where there is an obvious one
+    pub fn hint(&self) -> Option<String> {
+        let hint = match self {
+            Self::Unauthorized { service, .. } => match *service {
+                "Confluence" => "Check confluence.api_token (and confluence.username for basic auth) in the config file, or run `ktme auth login confluence` for auth_type = \"oauth\"",
+                "Claude" => "Check that ANTHROPIC_API_KEY holds a valid API key",
+                "OpenAI" => "Check that OPENAI_API_KEY holds a valid API key",
+                "GitHub" => "Set GITHUB_TOKEN (or git.github_token) to a token with read access to the repository",
+                "GitLab" => "Set GITLAB_TOKEN (or git.gitlab_token) to a token with read_api scope",
+                "Jira" => "Check issues.jira_username and issues.jira_api_token (or JIRA_API_TOKEN)",
+                "Notion" => "Check the Notion integration token and that the page is shared with it",
+                _ => return None,
+            }
+            .to_string(),
+            Self::NotSignedIn { service, .. } => format!(
+                "Run `ktme auth login {}`",
+                service.to_lowercase()
+            ),
+            Self::Config(_) | Self::ConfigError(_) | Self::TomlDe(_) | Self::TomlSer(_) => {
+                "Run `ktme config validate`, or `ktme config init` to write a default config file"
+                    .to_string()
+            }
+            Self::QuotaExceeded { .. } => {
+                "Free up space (old attachments and page versions count against the quota) or ask an administrator to raise the storage limit"
+                    .to_string()
+            }
+            Self::MappingNotFound(service) => format!(
+                "Map the service with `ktme mapping add {} --file <path>`; `ktme mapping list` shows the mapped services",
+                service
+            ),
+            Self::DocumentNotFound(_) => {
+                "Check the documentation locations with `ktme mapping list`".to_string()
+            }
+            Self::DocumentChanged(_) => {
+                "Run the command again to apply the change on top of the current content"
+                    .to_string()
+            }
+            Self::GenerationInProgress(_) => {
+                "Pass --wait to wait for it; the lock of a run that crashed expires after the operation timeout"
+                    .to_string()
+            }
+            Self::Git(e) => match e.code() {
+                git2::ErrorCode::NotFound if e.class() == git2::ErrorClass::Repository => {
+                    "Run ktme inside a Git repository".to_string()
+                }
+                git2::ErrorCode::NotFound => {
+                    "Check that the commit or reference exists (`git log --oneline`)".to_string()
+                }
+                _ => return None,
+            },
+            Self::Offline(_) => {
+                "Run without --offline and with [general] offline = false to allow network access"
+                    .to_string()
+            }
+            Self::Timeout(_) => {
+                "Raise the deadline with --timeout or [timeouts] operation in the config file"
+                    .to_string()
+            }
+            Self::Http(e) if e.is_timeout() => {
+                "Raise [timeouts] request (or the provider's entry under [timeouts.providers]) in the config file"
+                    .to_string()
+            }
+            Self::NetworkError(_) | Self::Http(_) if self.class() == ErrorClass::Network => {
+                "Check the network connection and the configured base URLs; --verbose shows the underlying cause"
+                    .to_string()
+            }
+            Self::Storage(_) => "Check the [storage] section of the config file".to_string(),
+            Self::InvalidInput(_) => "See `ktme <command> --help` for usage".to_string(),
+            _ => return None,
+        };
+        Some(hint)
+    }
+
+    /// Messages of the underlying errors, outermost first
+    pub fn causes(&self) -> Vec<String> {
+        let mut causes = Vec::new();
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+        causes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct_and_documented() {
+        let classes = [
+            ErrorClass::General,
+            ErrorClass::Usage,
+            ErrorClass::Config,
+            ErrorClass::Auth,
+            ErrorClass::NotFound,
+            ErrorClass::Network,
+            ErrorClass::Remote,
+            ErrorClass::Git,
+            ErrorClass::Storage,
+            ErrorClass::Io,
+            ErrorClass::Data,
+            ErrorClass::Cancelled,
+        ];
+        let mut codes: Vec<_> = classes.iter().map(|c| c.exit_code()).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), classes.len());
+        for code in codes {
+            assert!(EXIT_CODES_HELP.contains(&format!("\n  {} ", code)));
+        }
+    }
+
+    #[test]
+    fn test_auth_status_maps_to_auth_class_with_hint() {
+        let error = KtmeError::from_status(
+            "Confluence",
+            reqwest::StatusCode::UNAUTHORIZED,
+            KtmeError::Confluence("401".to_string()),
+        );
+        assert_eq!(error.class(), ErrorClass::Auth);
+        assert_eq!(error.class().exit_code(), 4);
+        assert!(error.hint().unwrap().contains("confluence.api_token"));
+
+        let other = KtmeError::from_status(
+            "Confluence",
+            reqwest::StatusCode::BAD_GATEWAY,
+            KtmeError::Confluence("502".to_string()),
+        );
+        assert_eq!(other.class(), ErrorClass::Remote);
+    }
+
+    #[test]
+    fn test_causes_follow_source_chain() {
+        let error = KtmeError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "missing file",
+        ));
+        assert_eq!(error.class(), ErrorClass::Io);
+        assert_eq!(error.causes(), ["missing file"]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Cache of extracted commit diffs
+//!
+//! A commit never changes, so its diff is kept in the diff cache table for
+//! `[git] diff_cache_ttl` and shared by every run against the same
+//! workspace, e.g. parallel generation jobs after `ktme cache warm`.
+
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use crate::git::reader::DiffLimits;
+use crate::metrics;
+use crate::storage::backend::{open_storage, Storage};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+/// Name of the diff cache in metrics
+pub const DIFF_CACHE: &str = "diff";
+
+/// Commit diffs cached in the workspace database
+#[derive(Clone)]
+pub struct CommitDiffCache {
+    storage: Arc<dyn Storage>,
+    ttl: Duration,
+}
+
+impl std::fmt::Debug for CommitDiffCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommitDiffCache")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+impl CommitDiffCache {
+    pub fn new(storage: Box<dyn Storage>, ttl: Duration) -> Self {
+        Self {
+            storage: Arc::from(storage),
+            ttl,
+        }
+    }
+
+    /// Cache of the configured workspace, kept in the configured storage
+    /// backend; `None` when `diff_cache_ttl` is `0s` or the backend cannot be
+    /// opened
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let ttl = config.git.diff_cache_ttl().ok()??;
+        let storage = open_storage(&config.storage)
+            .map_err(|e| tracing::debug!("Diff cache unavailable: {}", e))
+            .ok()?;
+        Some(Self::new(storage, ttl))
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cached diff of `commit` in `repository` read with `limits`
+    pub fn get(
+        &self,
+        repository: &str,
+        commit: &str,
+        limits: &DiffLimits,
+    ) -> Option<ExtractedDiff> {
+        let cached = self
+            .storage
+            .get_cached_diff(&source_type(limits), commit, Some(repository))
+            .map_err(|e| tracing::debug!("Diff cache lookup failed: {}", e))
+            .ok()
+            .flatten()
+            .and_then(|entry| serde_json::from_str(&entry.diff_json).ok());
+        metrics::record_cache_lookup(DIFF_CACHE, cached.is_some());
+        cached
+    }
+
+    /// Cache `diff`, the diff of `commit` in `repository`; a failure to cache
+    /// does not fail the extraction
+    pub fn put(&self, repository: &str, commit: &str, limits: &DiffLimits, diff: &ExtractedDiff) {
+        let result = serde_json::to_string(diff)
+            .map_err(KtmeError::from)
+            .and_then(|json| {
+                self.storage.cache_diff(
+                    &source_type(limits),
+                    commit,
+                    Some(repository),
+                    &json,
+                    Some(Utc::now() + self.ttl),
+                )
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to cache the diff of {}: {}", commit, e);
+        }
+    }
+
+    /// Drop the expired entries of the cache
+    pub fn prune(&self) -> Result<u64> {
+        self.storage.clear_expired_diffs()
+    }
+}
+
+/// Entries are keyed by the size limit too, as a diff truncated under one
+/// limit is not the diff under another
+fn source_type(limits: &DiffLimits) -> String {
+    match limits.max_file_bytes {
+        Some(max) => format!("commit:{}", max),
+        None => "commit".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::DiffExtractor;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
+    use git2::Repository;
+    use tempfile::TempDir;
+
+    /// Repository with a file changed by each of `count` commits
+    fn repository(count: usize) -> (TempDir, Vec<String>) {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        let mut commits = Vec::new();
+        for number in 0..count {
+            std::fs::write(dir.path().join("lib.rs"), format!("// {}\n", number)).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(std::path::Path::new("lib.rs")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            let id = repo
+                .commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    &format!("Change {}", number),
+                    &tree,
+                    &parents,
+                )
+                .unwrap();
+            commits.push(id.to_string());
+        }
+        (dir, commits)
+    }
+
+    #[test]
+    fn test_range_extraction_fills_the_cache() {
+        let (dir, commits) = repository(3);
+        let path = dir.path().to_string_lossy().to_string();
+        let cache = CommitDiffCache::new(
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+            Duration::hours(1),
+        );
+        let limits = DiffLimits::default();
+        let range = format!("{}..HEAD", commits[0]);
+
+        let extractor = DiffExtractor::new("range".to_string(), range.clone(), Some(&path))
+            .unwrap()
+            .with_cache(Some(cache.clone()));
+        let diffs = extractor.extract_range(&range).unwrap();
+        assert_eq!(diffs.len(), 2);
+
+        let repository = extractor.get_repository_info().unwrap().path;
+        let cached = cache.get(&repository, &commits[2], &limits).unwrap();
+        assert_eq!(cached.message.trim(), "Change 2");
+        assert!(cache.get(&repository, &commits[0], &limits).is_none());
+        // A different size limit is a different diff
+        let truncated = DiffLimits {
+            max_file_bytes: Some(10),
+            spill_path: None,
+        };
+        assert!(cache.get(&repository, &commits[2], &truncated).is_none());
+
+        // Cached diffs are served as extracted
+        let again = extractor.extract_range(&range).unwrap();
+        assert_eq!(
+            again.iter().map(|d| &d.identifier).collect::<Vec<_>>(),
+            diffs.iter().map(|d| &d.identifier).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_expired_entries_are_misses() {
+        let (dir, commits) = repository(1);
+        let path = dir.path().to_string_lossy().to_string();
+        let cache = CommitDiffCache::new(
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+            Duration::seconds(-1),
+        );
+        let extractor = DiffExtractor::new("commit".to_string(), "HEAD".to_string(), Some(&path))
+            .unwrap()
+            .with_cache(Some(cache.clone()));
+        extractor.extract().unwrap();
+
+        let repository = extractor.get_repository_info().unwrap().path;
+        assert!(cache
+            .get(&repository, &commits[0], &DiffLimits::default())
+            .is_none());
+        assert_eq!(cache.prune().unwrap(), 1);
+    }
+}
@@ -0,0 +1,368 @@
+//! Committing generated documentation
+//!
+//! With `--commit-docs` the documentation files written by `generate` or
+//! `update` are staged and committed with a message linking the source
+//! commit, optionally on a new branch that is pushed to `origin` and proposed
+//! as a GitHub pull request or GitLab merge request.
+
+use crate::config::GitConfig;
+use crate::error::{KtmeError, Result};
+use crate::git::providers::{github::GitHubProvider, gitlab::GitLabProvider};
+use crate::git::remote::parse_remote_url;
+use git2::{BranchType, Cred, CredentialType, PushOptions, RemoteCallbacks, Repository, Signature};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Remote that branches are pushed to
+const REMOTE: &str = "origin";
+
+/// What to do with the written documentation files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommitDocs {
+    /// Branch created from HEAD for the commit; the current branch without one
+    pub branch: Option<String>,
+    /// Push the branch to `origin`
+    pub push: bool,
+    /// Open a pull (merge) request of the pushed branch
+    pub open_pr: bool,
+}
+
+/// A commit of documentation files
+#[derive(Debug, Clone, Serialize)]
+pub struct DocsCommit {
+    pub id: String,
+    pub branch: String,
+    pub files: Vec<String>,
+    pub pull_request: Option<String>,
+}
+
+/// Hosting service of a remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Host {
+    GitHub { repo: String },
+    GitLab { web_url: String, project: String },
+}
+
+impl Host {
+    /// Host of an HTTPS or SSH remote URL such as `git@github.com:owner/repo.git`
+    fn from_remote_url(url: &str) -> Option<Self> {
+        let (host, path) = parse_remote_url(url)?;
+        if host == "github.com" {
+            Some(Self::GitHub { repo: path })
+        } else if host.contains("gitlab") {
+            Some(Self::GitLab {
+                web_url: format!("https://{}", host),
+                project: path,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn commit_url(&self, id: &str) -> String {
+        match self {
+            Self::GitHub { repo } => format!("https://github.com/{}/commit/{}", repo, id),
+            Self::GitLab { web_url, project } => {
+                format!("{}/{}/-/commit/{}", web_url, project, id)
+            }
+        }
+    }
+}
+
+/// Commit message for documentation of `services` generated from `source`
+fn commit_message(services: &[String], source: &str, source_url: Option<&str>) -> String {
+    let short = if source.len() == 40 && source.chars().all(|c| c.is_ascii_hexdigit()) {
+        &source[..7]
+    } else {
+        source
+    };
+    let mut message = format!(
+        "docs({}): update documentation for {}\n\nGenerated by ktme from {}.",
+        services.join(", "),
+        short,
+        source
+    );
+    if let Some(url) = source_url {
+        message.push_str(&format!("\n\nSource: {}", url));
+    }
+    message
+}
+
+/// Short id of the commit `source` of the repository in the current
+/// directory, with its web URL when `origin` is on GitHub or GitLab
+pub fn source_commit(source: &str) -> Option<(String, Option<String>)> {
+    let repo = Repository::discover(".").ok()?;
+    let commit = repo.revparse_single(source).ok()?.peel_to_commit().ok()?;
+    let id = commit.id().to_string();
+    let url = repo
+        .find_remote(REMOTE)
+        .ok()
+        .and_then(|remote| remote.url().and_then(Host::from_remote_url))
+        .map(|host| host.commit_url(&id));
+    Some((id[..7].to_string(), url))
+}
+
+/// Stage `files`, commit them to the repository containing them and, as
+/// `options` ask, push the branch and open a pull request. Returns `None`
+/// when the files hold no changes.
+pub async fn commit_docs(
+    files: &[PathBuf],
+    services: &[String],
+    source: &str,
+    options: &CommitDocs,
+    git: &GitConfig,
+) -> Result<Option<DocsCommit>> {
+    let Some(first) = files.first() else {
+        return Ok(None);
+    };
+    let repo = Repository::discover(first.parent().unwrap_or(Path::new(".")))?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| KtmeError::InvalidInput("--commit-docs needs a working tree".to_string()))?
+        .canonicalize()?;
+
+    let mut staged = Vec::new();
+    let mut index = repo.index()?;
+    for file in files {
+        let path = file.canonicalize()?;
+        match path.strip_prefix(&workdir) {
+            Ok(relative) => {
+                index.add_path(relative)?;
+                staged.push(relative.to_string_lossy().to_string());
+            }
+            Err(_) => tracing::warn!(
+                "{} is outside the repository at {}, not committed",
+                file.display(),
+                workdir.display()
+            ),
+        }
+    }
+    let head = repo.head()?.peel_to_commit()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    if staged.is_empty() || tree.id() == head.tree_id() {
+        return Ok(None);
+    }
+    index.write()?;
+
+    let base = repo.head()?.shorthand().map(str::to_string);
+    let branch = match &options.branch {
+        Some(name) => {
+            match repo.find_branch(name, BranchType::Local) {
+                Ok(existing) if existing.get().target() != Some(head.id()) => {
+                    return Err(KtmeError::InvalidInput(format!(
+                        "Branch {} already exists; pass another --branch",
+                        name
+                    )));
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    repo.branch(name, &head, false)?;
+                }
+            }
+            // The new branch starts at HEAD, so the working tree stays as is
+            repo.set_head(&format!("refs/heads/{}", name))?;
+            name.clone()
+        }
+        None => base.clone().ok_or_else(|| {
+            KtmeError::InvalidInput("HEAD is detached; pass --branch".to_string())
+        })?,
+    };
+
+    let host = repo
+        .find_remote(REMOTE)
+        .ok()
+        .and_then(|remote| remote.url().and_then(Host::from_remote_url));
+    let source_url = host
+        .as_ref()
+        .filter(|_| repo.revparse_single(source).is_ok())
+        .map(|host| host.commit_url(source));
+    let message = commit_message(services, source, source_url.as_deref());
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("ktme", "ktme@localhost"))?;
+    let id = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &[&head],
+    )?;
+
+    let mut commit = DocsCommit {
+        id: id.to_string(),
+        branch: branch.clone(),
+        files: staged,
+        pull_request: None,
+    };
+    if !options.push {
+        return Ok(Some(commit));
+    }
+
+    crate::http::ensure_online("Pushing documentation")?;
+    let token = match &host {
+        Some(Host::GitHub { .. }) => {
+            GitHubProvider::from_config(git.github_token.clone()).api_token()
+        }
+        Some(Host::GitLab { .. }) => {
+            GitLabProvider::from_config(git.gitlab_token.clone()).api_token()
+        }
+        None => None,
+    };
+    push(&repo, &branch, token.as_deref())?;
+
+    if options.open_pr {
+        let base = base
+            .filter(|base| *base != branch)
+            .ok_or_else(|| KtmeError::InvalidInput("--open-pr needs --branch".to_string()))?;
+        let title = message.lines().next().unwrap_or_default();
+        commit.pull_request = Some(match &host {
+            Some(Host::GitHub { repo }) => {
+                GitHubProvider::from_config(git.github_token.clone())
+                    .create_pull_request(repo, &branch, &base, title, &message)
+                    .await?
+            }
+            Some(Host::GitLab { project, .. }) => {
+                GitLabProvider::from_config(git.gitlab_token.clone())
+                    .create_merge_request(project, &branch, &base, title, &message)
+                    .await?
+            }
+            None => {
+                return Err(KtmeError::UnsupportedProvider(format!(
+                    "--open-pr needs a GitHub or GitLab '{}' remote",
+                    REMOTE
+                )))
+            }
+        });
+    }
+    Ok(Some(commit))
+}
+
+/// Push `branch` to `origin`, authenticating HTTPS remotes with `token`
+/// and SSH remotes with the SSH agent
+fn push(repo: &Repository, branch: &str, token: Option<&str>) -> Result<()> {
+    let mut remote = repo.find_remote(REMOTE)?;
+    let git_config = repo.config()?;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        match token {
+            Some(token) => {
+                let username = if url.contains("gitlab") {
+                    "oauth2"
+                } else {
+                    "x-access-token"
+                };
+                Cred::userpass_plaintext(username, token)
+            }
+            None => Cred::credential_helper(&git_config, url, username),
+        }
+    });
+    callbacks.push_update_reference(|reference, status| match status {
+        Some(message) => Err(git2::Error::from_str(&format!(
+            "{} was rejected: {}",
+            reference, message
+        ))),
+        None => Ok(()),
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+    remote.push(&[refspec.as_str()], Some(&mut options))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_from_remote_url() {
+        assert_eq!(
+            Host::from_remote_url("git@github.com:acme/payments.git"),
+            Some(Host::GitHub {
+                repo: "acme/payments".to_string()
+            })
+        );
+        assert_eq!(
+            Host::from_remote_url("https://token@gitlab.example.com/platform/payments.git"),
+            Some(Host::GitLab {
+                web_url: "https://gitlab.example.com".to_string(),
+                project: "platform/payments".to_string()
+            })
+        );
+        assert_eq!(
+            Host::from_remote_url("https://example.com/acme/payments"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_commit_message_links_source() {
+        let source = "0123456789abcdef0123456789abcdef01234567";
+        let host = Host::GitHub {
+            repo: "acme/payments".to_string(),
+        };
+        let message = commit_message(
+            &["payments".to_string()],
+            source,
+            Some(&host.commit_url(source)),
+        );
+        assert!(message.starts_with("docs(payments): update documentation for 0123456\n\n"));
+        assert!(message.ends_with(&format!(
+            "Source: https://github.com/acme/payments/commit/{}",
+            source
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_commit_docs_on_new_branch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# Payments\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("README.md")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        let docs = dir.path().join("docs/api.md");
+        std::fs::create_dir_all(docs.parent().unwrap()).unwrap();
+        std::fs::write(&docs, "# API\n").unwrap();
+        let options = CommitDocs {
+            branch: Some("docs/update-api".to_string()),
+            ..Default::default()
+        };
+        let commit = commit_docs(
+            std::slice::from_ref(&docs),
+            &["payments".to_string()],
+            "HEAD",
+            &options,
+            &GitConfig::default(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(commit.branch, "docs/update-api");
+        assert_eq!(commit.files, ["docs/api.md"]);
+        assert_eq!(repo.head().unwrap().shorthand(), Some("docs/update-api"));
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert!(head.message().unwrap().starts_with("docs(payments): "));
+
+        // Nothing left to commit
+        let again = commit_docs(
+            &[docs],
+            &["payments".to_string()],
+            "HEAD",
+            &CommitDocs::default(),
+            &GitConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert!(again.is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Result;
-use crate::git::reader::GitReader;
+use crate::git::cache::CommitDiffCache;
+use crate::git::reader::{DiffLimits, GitReader};
 use serde::{Deserialize, Serialize};
 
 // Unit tests are in tests/ directory to avoid access issues
@@ -9,6 +10,7 @@ pub struct DiffExtractor {
     pub source: String,
     pub identifier: String,
     git_reader: GitReader,
+    cache: Option<CommitDiffCache>,
 }
 
 impl DiffExtractor {
@@ -18,15 +20,29 @@ impl DiffExtractor {
             source,
             identifier,
             git_reader,
+            cache: None,
         })
     }
 
+    /// Cap per-file diff sizes while extracting
+    pub fn with_limits(mut self, limits: DiffLimits) -> Self {
+        self.git_reader = self.git_reader.with_limits(limits);
+        self
+    }
+
+    /// Read commit diffs through `cache`; spilled extractions bypass it
+    pub fn with_cache(mut self, cache: Option<CommitDiffCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
     pub fn extract(&self) -> Result<ExtractedDiff> {
         tracing::info!("Extracting diff from {} {}", self.source, self.identifier);
 
         match self.source.as_str() {
-            "commit" => self.git_reader.read_commit(&self.identifier),
+            "commit" => self.read_commit(&self.identifier),
             "staged" => self.git_reader.read_staged(),
+            "snapshot" => self.git_reader.read_snapshot(&self.identifier),
             _ => Err(crate::error::KtmeError::InvalidInput(format!(
                 "Unsupported source type: {}",
                 self.source
@@ -34,11 +50,56 @@ impl DiffExtractor {
         }
     }
 
+    /// Extract `source` `identifier` from each repository in `paths` and
+    /// merge the diffs (see [`ExtractedDiff::merge_repositories`]). A commit
+    /// or range missing from a repository leaves that repository out.
+    pub fn extract_repositories(
+        source: &str,
+        identifier: &str,
+        paths: &[String],
+        limits: DiffLimits,
+        cache: Option<&CommitDiffCache>,
+    ) -> Result<ExtractedDiff> {
+        let mut diffs = Vec::new();
+        for path in paths {
+            tracing::info!("Extracting {} {} from {}", source, identifier, path);
+            let extractor = Self::new(source.to_string(), identifier.to_string(), Some(path))?
+                .with_limits(limits.clone())
+                .with_cache(cache.cloned());
+            let diff = match source {
+                "range" => extractor
+                    .extract_range(identifier)
+                    .map(|diffs| ExtractedDiff::combine(identifier, diffs)),
+                _ => extractor.extract(),
+            };
+            match diff {
+                Ok(diff) => diffs.push((path.clone(), diff)),
+                Err(crate::error::KtmeError::Git(e)) if source != "staged" => {
+                    tracing::warn!("Skipping {}: {}", path, e.message());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if diffs.is_empty() {
+            return Err(crate::error::KtmeError::NotFound(format!(
+                "{} not found in any of the repositories {}",
+                identifier,
+                paths.join(", ")
+            )));
+        }
+        Ok(ExtractedDiff::merge_repositories(diffs))
+    }
+
     pub fn extract_range(&self, range: &str) -> Result<Vec<ExtractedDiff>> {
         tracing::info!("Extracting diff range from {} {}", self.source, range);
 
         match self.source.as_str() {
-            "commit" | "range" => self.git_reader.read_commit_range(range),
+            "commit" | "range" => self
+                .git_reader
+                .commit_ids(range)?
+                .iter()
+                .map(|id| self.read_commit(id))
+                .collect(),
             _ => Err(crate::error::KtmeError::InvalidInput(format!(
                 "Range extraction not supported for source type: {}",
                 self.source
@@ -46,6 +107,24 @@ impl DiffExtractor {
         }
     }
 
+    /// Diff of the commit `reference` points to, from the cache when it
+    /// holds the commit
+    fn read_commit(&self, reference: &str) -> Result<ExtractedDiff> {
+        let limits = self.git_reader.limits();
+        let Some(cache) = self.cache.as_ref().filter(|_| limits.spill_path.is_none()) else {
+            return self.git_reader.read_commit(reference);
+        };
+
+        let repository = self.git_reader.get_repository_path()?;
+        let commit = self.git_reader.resolve_commit(reference)?;
+        if let Some(diff) = cache.get(&repository, &commit, limits) {
+            return Ok(diff);
+        }
+        let diff = self.git_reader.read_commit(&commit)?;
+        cache.put(&repository, &commit, limits, &diff);
+        Ok(diff)
+    }
+
     pub fn get_repository_info(&self) -> Result<RepositoryInfo> {
         let path = self.git_reader.get_repository_path()?;
         let branch = self.git_reader.get_current_branch()?;
@@ -87,15 +166,283 @@ pub struct ExtractedDiff {
     pub message: String,
     pub files: Vec<FileChange>,
     pub summary: DiffSummary,
+    /// Pull request context, present when the diff was extracted from a PR
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<PullRequestMetadata>,
+    /// Commits of a combined range, oldest first; empty for a single commit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<CommitSummary>,
+    /// Repositories of a merged multi-repository diff; empty for a diff of
+    /// one repository
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<RepositoryChanges>,
+}
+
+/// Changes of one repository of a merged multi-repository diff
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RepositoryChanges {
+    /// Directory name of the repository, prefixed to the paths of its files
+    pub name: String,
+    pub path: String,
+    pub identifier: String,
+    pub message: String,
+    pub files: Vec<String>,
+}
+
+impl RepositoryChanges {
+    /// Whether `file` (a path of the merged diff) belongs to this repository
+    pub fn contains(&self, file: &str) -> bool {
+        file.strip_prefix(&self.name)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+/// One commit of a combined range
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommitSummary {
+    pub id: String,
+    pub timestamp: String,
+    pub author: String,
+    pub message: String,
+    pub files: Vec<String>,
+}
+
+impl CommitSummary {
+    /// First line of the commit message
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default().trim()
+    }
+
+    /// Abbreviated commit id
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(7)]
+    }
+}
+
+impl ExtractedDiff {
+    /// One diff for the commits of `range`: the changes of each file are
+    /// concatenated in commit order and the commits are kept as a timeline
+    pub fn combine(range: &str, mut diffs: Vec<ExtractedDiff>) -> ExtractedDiff {
+        diffs.sort_by_key(|diff| chrono::DateTime::parse_from_rfc3339(&diff.timestamp).ok());
+
+        let mut files: Vec<FileChange> = Vec::new();
+        let mut authors: Vec<&str> = Vec::new();
+        for diff in &diffs {
+            if !authors.contains(&diff.author.as_str()) {
+                authors.push(&diff.author);
+            }
+            for file in &diff.files {
+                match files.iter_mut().find(|f| f.path == file.path) {
+                    Some(existing) => {
+                        existing.additions += file.additions;
+                        existing.deletions += file.deletions;
+                        existing.diff.push('\n');
+                        existing.diff.push_str(&file.diff);
+                        existing.truncated = existing.truncated.take().or(file.truncated.clone());
+                    }
+                    None => files.push(file.clone()),
+                }
+            }
+        }
+
+        let commits: Vec<CommitSummary> = diffs
+            .iter()
+            .map(|diff| CommitSummary {
+                id: diff.identifier.clone(),
+                timestamp: diff.timestamp.clone(),
+                author: diff.author.clone(),
+                message: diff.message.clone(),
+                files: diff.files.iter().map(|f| f.path.clone()).collect(),
+            })
+            .collect();
+
+        ExtractedDiff {
+            source: "range".to_string(),
+            identifier: range.to_string(),
+            timestamp: diffs
+                .last()
+                .map(|d| d.timestamp.clone())
+                .unwrap_or_default(),
+            author: authors.join(", "),
+            message: commits
+                .iter()
+                .map(|c| format!("{} {}", c.short_id(), c.subject()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            summary: DiffSummary {
+                total_files: files.len() as u32,
+                total_additions: files.iter().map(|f| f.additions).sum(),
+                total_deletions: files.iter().map(|f| f.deletions).sum(),
+            },
+            files,
+            commits,
+            pull_request: None,
+            repositories: Vec::new(),
+        }
+    }
+
+    /// One diff for the changes read from several repositories, given as
+    /// `(path, diff)`: the file paths are prefixed with the directory name of
+    /// their repository, and each repository is kept as a section
+    pub fn merge_repositories(diffs: Vec<(String, ExtractedDiff)>) -> ExtractedDiff {
+        let mut files = Vec::new();
+        let mut commits = Vec::new();
+        let mut authors: Vec<&str> = Vec::new();
+        let mut repositories: Vec<RepositoryChanges> = Vec::new();
+        for (index, (path, diff)) in diffs.iter().enumerate() {
+            let mut name = std::path::Path::new(path)
+                .canonicalize()
+                .unwrap_or_else(|_| path.into())
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone());
+            if repositories.iter().any(|r| r.name == name) {
+                name = format!("{}-{}", name, index + 1);
+            }
+            let prefixed = |file: &str| format!("{}/{}", name, file);
+
+            if !authors.contains(&diff.author.as_str()) {
+                authors.push(&diff.author);
+            }
+            files.extend(diff.files.iter().map(|file| FileChange {
+                path: prefixed(&file.path),
+                old_path: file.old_path.as_deref().map(prefixed),
+                ..file.clone()
+            }));
+            commits.extend(diff.commits.iter().map(|commit| CommitSummary {
+                files: commit.files.iter().map(|f| prefixed(f)).collect(),
+                ..commit.clone()
+            }));
+            repositories.push(RepositoryChanges {
+                files: diff.files.iter().map(|f| prefixed(&f.path)).collect(),
+                name,
+                path: path.clone(),
+                identifier: diff.identifier.clone(),
+                message: diff.message.clone(),
+            });
+        }
+
+        let short = |id: &str| {
+            if id.len() == 40 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+                id[..7].to_string()
+            } else {
+                id.to_string()
+            }
+        };
+        ExtractedDiff {
+            source: diffs
+                .first()
+                .map(|(_, d)| d.source.clone())
+                .unwrap_or_default(),
+            identifier: repositories
+                .iter()
+                .map(|r| format!("{}@{}", r.name, short(&r.identifier)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            timestamp: diffs
+                .iter()
+                .map(|(_, d)| &d.timestamp)
+                .max_by_key(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                .cloned()
+                .unwrap_or_default(),
+            author: authors.join(", "),
+            message: repositories
+                .iter()
+                .map(|r| format!("[{}] {}", r.name, r.message.trim()))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            summary: DiffSummary {
+                total_files: files.len() as u32,
+                total_additions: files.iter().map(|f| f.additions).sum(),
+                total_deletions: files.iter().map(|f| f.deletions).sum(),
+            },
+            files,
+            commits,
+            pull_request: None,
+            repositories,
+        }
+    }
+
+    /// Copy of the diff limited to files under `dir` (repository-relative),
+    /// with the summary recomputed
+    pub fn scoped_to(&self, dir: &str) -> ExtractedDiff {
+        let dir = dir.trim_matches('/');
+        self.filtered(|f| {
+            let under = |path: &str| path == dir || path.starts_with(&format!("{}/", dir));
+            dir.is_empty() || under(&f.path) || f.old_path.as_deref().is_some_and(under)
+        })
+    }
+
+    /// Copy of the diff with only the files matching `keep`, with the summary
+    /// recomputed
+    pub fn filtered(&self, keep: impl Fn(&FileChange) -> bool) -> ExtractedDiff {
+        let files: Vec<FileChange> = self.files.iter().filter(|f| keep(f)).cloned().collect();
+
+        ExtractedDiff {
+            summary: DiffSummary {
+                total_files: files.len() as u32,
+                total_additions: files.iter().map(|f| f.additions).sum(),
+                total_deletions: files.iter().map(|f| f.deletions).sum(),
+            },
+            files,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PullRequestMetadata {
+    pub number: u32,
+    pub title: String,
+    pub description: String,
+    pub url: String,
+    pub labels: Vec<String>,
+    /// Issues referenced by closing keywords, e.g. `#12` or `owner/repo#12`
+    pub linked_issues: Vec<String>,
+    pub review_comments: Vec<ReviewComment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewComment {
+    pub author: String,
+    /// File the comment is attached to; `None` for top-level review bodies
+    pub path: Option<String>,
+    pub body: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileChange {
     pub path: String,
+    /// Previous path of a renamed or copied file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
     pub status: String,
     pub additions: u32,
     pub deletions: u32,
     pub diff: String,
+    /// Present when `diff` was cut at the configured size limit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<DiffTruncation>,
+}
+
+impl FileChange {
+    /// `old → new` for renamed or copied files, otherwise the path
+    pub fn display_path(&self) -> String {
+        match &self.old_path {
+            Some(old_path) if *old_path != self.path => format!("{} → {}", old_path, self.path),
+            _ => self.path.clone(),
+        }
+    }
+}
+
+/// Size of a file diff before it was truncated
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffTruncation {
+    pub original_bytes: u64,
+    pub original_lines: u32,
+    /// File holding the full diff, when spilling was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_file: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -1,3 +1,8 @@
+pub mod cache;
+pub mod commit;
 pub mod diff;
+pub mod ownership;
 pub mod providers;
 pub mod reader;
+pub mod remote;
+pub mod staleness;
@@ -0,0 +1,524 @@
+//! Ownership of the code touched by a diff
+//!
+//! Combines blame of the changed files (who wrote the code being changed)
+//! with CODEOWNERS entries into the "Primary contacts" of generated
+//! documentation, finds the maintainers of a directory from its history,
+//! and resolves which services a diff belongs to for `--service auto`.
+
+use crate::error::{KtmeError, Result};
+use crate::git::diff::{ExtractedDiff, FileChange};
+use crate::storage::mapping::StorageManager;
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{BlameOptions, Oid, Repository};
+use glob::{MatchOptions, Pattern};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Where CODEOWNERS is looked up, in GitHub's order of precedence
+const CODEOWNERS_LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Files blamed per diff; enough to find the main authors of large changes
+const MAX_BLAMED_FILES: usize = 50;
+
+/// Contacts listed in generated documentation
+const MAX_CONTACTS: usize = 5;
+
+/// An author of the changed code
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Contact {
+    pub name: String,
+    pub email: Option<String>,
+    /// Lines of the changed files last touched by this author
+    pub lines: usize,
+}
+
+/// Authors and CODEOWNERS entries for a diff
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Ownership {
+    pub contacts: Vec<Contact>,
+    pub code_owners: Vec<String>,
+}
+
+impl Ownership {
+    pub fn is_empty(&self) -> bool {
+        self.contacts.is_empty() && self.code_owners.is_empty()
+    }
+
+    /// Markdown list of the contacts, without a heading
+    pub fn render_list(&self) -> String {
+        let mut list = String::new();
+        for contact in &self.contacts {
+            match &contact.email {
+                Some(email) => list.push_str(&format!(
+                    "- {} <{}> ({} lines)\n",
+                    contact.name, email, contact.lines
+                )),
+                None => list.push_str(&format!("- {} ({} lines)\n", contact.name, contact.lines)),
+            }
+        }
+        if !self.code_owners.is_empty() {
+            list.push_str(&format!("- Code owners: {}\n", self.code_owners.join(", ")));
+        }
+        list
+    }
+
+    /// "Primary contacts" section appended to generated documentation
+    pub fn render_section(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        format!("\n\n## Primary contacts\n\n{}", self.render_list())
+    }
+}
+
+/// One CODEOWNERS rule
+#[derive(Debug, Clone)]
+struct CodeOwnersRule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+/// Parsed CODEOWNERS file
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<CodeOwnersRule>,
+}
+
+impl CodeOwners {
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let pattern = parts.next()?.to_string();
+                let owners = parts
+                    .take_while(|part| !part.starts_with('#'))
+                    .map(str::to_string)
+                    .collect();
+                Some(CodeOwnersRule { pattern, owners })
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// CODEOWNERS of the repository at `root`, if it has one
+    pub fn load(root: &Path) -> Option<Self> {
+        CODEOWNERS_LOCATIONS
+            .iter()
+            .find_map(|location| std::fs::read_to_string(root.join(location)).ok())
+            .map(|content| Self::parse(&content))
+    }
+
+    /// Owners of `path`; the last matching rule wins
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| pattern_matches(&rule.pattern, path))
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// gitignore-style matching as used by CODEOWNERS
+pub(crate) fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let anchored = pattern.starts_with('/') || pattern.trim_end_matches('/').contains('/');
+    let directory = pattern.ends_with('/');
+    let pattern = pattern.trim_matches('/');
+    if pattern == "*" || pattern.is_empty() {
+        return true;
+    }
+    let Ok(glob) = Pattern::new(pattern) else {
+        return false;
+    };
+
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+
+    // A pattern matches a path or any directory containing it
+    let components: Vec<&str> = path.split('/').collect();
+    let last = if directory {
+        components.len() - 1
+    } else {
+        components.len()
+    };
+    if anchored {
+        (1..=last).any(|end| glob.matches_with(&components[..end].join("/"), options))
+    } else {
+        components[..last]
+            .iter()
+            .any(|part| glob.matches_with(part, options))
+    }
+}
+
+/// Blame the files changed by `diff` in the repository at `repo_path` (or
+/// the current one) and match them against CODEOWNERS
+pub fn analyze(repo_path: Option<&str>, diff: &ExtractedDiff) -> Result<Ownership> {
+    let repo = match repo_path {
+        Some(path) => Repository::open(path)?,
+        None => Repository::open_from_env()?,
+    };
+
+    // Blame the code as it was before the change
+    let base = Oid::from_str(&diff.identifier)
+        .ok()
+        .and_then(|oid| repo.find_commit(oid).ok())
+        .and_then(|commit| commit.parent_id(0).ok())
+        .or_else(|| repo.head().ok().and_then(|head| head.target()));
+
+    let mut authors: HashMap<String, Contact> = HashMap::new();
+    if let Some(base) = base {
+        for file in diff
+            .files
+            .iter()
+            .filter(|f| f.status != "added")
+            .take(MAX_BLAMED_FILES)
+        {
+            let path = file.old_path.as_deref().unwrap_or(&file.path);
+            let mut options = BlameOptions::new();
+            options.newest_commit(base);
+            let blame = match repo.blame_file(Path::new(path), Some(&mut options)) {
+                Ok(blame) => blame,
+                Err(e) => {
+                    tracing::debug!("Cannot blame {}: {}", path, e);
+                    continue;
+                }
+            };
+            for hunk in blame.iter() {
+                let signature = hunk.final_signature();
+                let name = signature.name().unwrap_or("Unknown").to_string();
+                let email = signature.email().map(str::to_string);
+                let key = email.clone().unwrap_or_else(|| name.clone());
+                authors
+                    .entry(key)
+                    .or_insert_with(|| Contact {
+                        name,
+                        email,
+                        lines: 0,
+                    })
+                    .lines += hunk.lines_in_hunk();
+            }
+        }
+    }
+
+    let mut contacts: Vec<Contact> = authors.into_values().collect();
+    contacts.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.name.cmp(&b.name)));
+    contacts.truncate(MAX_CONTACTS);
+
+    let mut code_owners = Vec::new();
+    if let Some(codeowners) = repo.workdir().and_then(CodeOwners::load) {
+        for file in &diff.files {
+            for owner in codeowners.owners_of(&file.path) {
+                if !code_owners.contains(owner) {
+                    code_owners.push(owner.clone());
+                }
+            }
+        }
+    }
+
+    Ok(Ownership {
+        contacts,
+        code_owners,
+    })
+}
+
+/// Commits searched for the maintainers of a directory
+const MAX_HISTORY_COMMITS: usize = 500;
+
+/// A frequent committer to a part of the repository
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Maintainer {
+    pub name: String,
+    pub email: Option<String>,
+    pub commits: usize,
+    pub last_commit: DateTime<Utc>,
+}
+
+/// Authors of the recent commits touching `dir` (the whole repository when
+/// `None`), most commits first
+pub fn maintainers(repo: &Repository, dir: Option<&str>) -> Result<Vec<Maintainer>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+
+    let dir = dir.map(|d| d.trim_matches('/')).filter(|d| !d.is_empty());
+    let mut authors: HashMap<String, Maintainer> = HashMap::new();
+    for oid in revwalk.take(MAX_HISTORY_COMMITS) {
+        let commit = repo.find_commit(oid?)?;
+        if let Some(dir) = dir {
+            let parent_tree = commit.parent(0).ok().map(|p| p.tree()).transpose()?;
+            let mut options = git2::DiffOptions::new();
+            options.pathspec(dir);
+            let diff = repo.diff_tree_to_tree(
+                parent_tree.as_ref(),
+                Some(&commit.tree()?),
+                Some(&mut options),
+            )?;
+            if diff.deltas().len() == 0 {
+                continue;
+            }
+        }
+
+        let author = commit.author();
+        let name = author.name().unwrap_or("Unknown").to_string();
+        let email = author.email().map(str::to_string);
+        let time = Utc
+            .timestamp_opt(author.when().seconds(), 0)
+            .single()
+            .unwrap_or_default();
+        let maintainer = authors
+            .entry(email.clone().unwrap_or_else(|| name.clone()))
+            .or_insert_with(|| Maintainer {
+                name,
+                email,
+                commits: 0,
+                last_commit: time,
+            });
+        maintainer.commits += 1;
+        maintainer.last_commit = maintainer.last_commit.max(time);
+    }
+
+    let mut maintainers: Vec<Maintainer> = authors.into_values().collect();
+    maintainers.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+    maintainers.truncate(MAX_CONTACTS);
+    Ok(maintainers)
+}
+
+/// `--service` value that resolves services from the changed files
+pub const AUTO_SERVICE: &str = "auto";
+
+/// Services owning the files of a diff
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServiceResolution {
+    /// Changed files per service
+    pub services: BTreeMap<String, Vec<String>>,
+    /// Files no service could be found for
+    pub unresolved: Vec<String>,
+}
+
+impl ServiceResolution {
+    /// The diff of each resolved service, limited to its files
+    pub fn scoped_diffs(&self, diff: &ExtractedDiff) -> Vec<(String, ExtractedDiff)> {
+        self.services
+            .iter()
+            .map(|(service, files)| (service.clone(), diff.filtered(|f| files.contains(&f.path))))
+            .collect()
+    }
+
+    /// One line per service, e.g. `api (3 files)`
+    pub fn describe(&self) -> String {
+        self.services
+            .iter()
+            .map(|(service, files)| format!("{} ({} files)", service, files.len()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Assign each changed file to a service: the service whose mapped path
+/// contains it (deepest path wins), otherwise a service named after one of
+/// the file's CODEOWNERS (`@org/payments` → `payments`)
+pub fn resolve_services(
+    diff: &ExtractedDiff,
+    service_dirs: &[(String, String)],
+    codeowners: Option<&CodeOwners>,
+    known_services: &[String],
+) -> ServiceResolution {
+    let mut resolution = ServiceResolution::default();
+
+    for file in &diff.files {
+        match resolve_file(file, service_dirs, codeowners, known_services) {
+            Some(service) => resolution
+                .services
+                .entry(service)
+                .or_default()
+                .push(file.path.clone()),
+            None => resolution.unresolved.push(file.path.clone()),
+        }
+    }
+
+    resolution
+}
+
+fn resolve_file(
+    file: &FileChange,
+    service_dirs: &[(String, String)],
+    codeowners: Option<&CodeOwners>,
+    known_services: &[String],
+) -> Option<String> {
+    let under = |path: &str, dir: &str| {
+        let dir = dir.trim_matches('/');
+        dir.is_empty() || path == dir || path.starts_with(&format!("{}/", dir))
+    };
+    let by_path = service_dirs
+        .iter()
+        .filter(|(_, dir)| {
+            under(&file.path, dir) || file.old_path.as_deref().is_some_and(|p| under(p, dir))
+        })
+        .max_by_key(|(_, dir)| dir.trim_matches('/').len())
+        .map(|(service, _)| service.clone());
+    if by_path.is_some() {
+        return by_path;
+    }
+
+    codeowners?.owners_of(&file.path).iter().find_map(|owner| {
+        let slug = owner.trim_start_matches('@').rsplit('/').next()?;
+        known_services
+            .iter()
+            .find(|service| service.eq_ignore_ascii_case(slug))
+            .cloned()
+    })
+}
+
+/// Repository-relative directory of a service's mapped path, if it has one
+pub fn service_dir_in_repo(service: &str) -> Option<String> {
+    service_dir(&StorageManager::new().ok()?, service)
+}
+
+fn service_dir(manager: &StorageManager, service: &str) -> Option<String> {
+    let path = manager.get_mapping(service).ok()?.path?;
+    let path = std::fs::canonicalize(path).ok()?;
+    let repo = Repository::discover(&path).ok()?;
+    let workdir = std::fs::canonicalize(repo.workdir()?).ok()?;
+    let relative = path.strip_prefix(workdir).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Resolve the services of `diff` from the stored service paths and the
+/// current repository's CODEOWNERS
+pub fn resolve_services_for_diff(diff: &ExtractedDiff) -> Result<ServiceResolution> {
+    let repo = Repository::open_from_env().ok();
+    resolve_services_with(diff, &StorageManager::new()?, repo.as_ref())
+}
+
+/// Resolve the services of `diff` from the service paths of `manager` and
+/// the CODEOWNERS of `repo`
+pub fn resolve_services_with(
+    diff: &ExtractedDiff,
+    manager: &StorageManager,
+    repo: Option<&Repository>,
+) -> Result<ServiceResolution> {
+    let known_services = manager.list_services()?;
+    let service_dirs: Vec<(String, String)> = known_services
+        .iter()
+        .filter_map(|service| service_dir(manager, service).map(|dir| (service.clone(), dir)))
+        .collect();
+    let codeowners = repo.and_then(|repo| repo.workdir().and_then(CodeOwners::load));
+
+    let resolution = resolve_services(diff, &service_dirs, codeowners.as_ref(), &known_services);
+    if resolution.services.is_empty() {
+        return Err(KtmeError::InvalidInput(format!(
+            "Could not resolve a service for any of the {} changed files; map service paths or pass --service explicitly",
+            diff.files.len()
+        )));
+    }
+    if !resolution.unresolved.is_empty() {
+        tracing::warn!(
+            "No service found for {} file(s): {}",
+            resolution.unresolved.len(),
+            resolution.unresolved.join(", ")
+        );
+    }
+
+    Ok(resolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codeowners_last_match_wins() {
+        let codeowners = CodeOwners::parse(
+            "# Default owners\n\
+             *       @org/everyone\n\
+             *.rs    @rustaceans\n\
+             /docs/  @org/docs # documentation team\n\
+             src/api/ @alice @bob\n",
+        );
+
+        assert_eq!(codeowners.owners_of("README.md"), ["@org/everyone"]);
+        assert_eq!(codeowners.owners_of("src/lib.rs"), ["@rustaceans"]);
+        assert_eq!(codeowners.owners_of("docs/guide.md"), ["@org/docs"]);
+        assert_eq!(
+            codeowners.owners_of("src/api/routes.rs"),
+            ["@alice", "@bob"]
+        );
+        // Anchored directory patterns do not match deeper namesakes
+        assert_eq!(codeowners.owners_of("vendor/docs/x.md"), ["@org/everyone"]);
+    }
+
+    #[test]
+    fn test_resolve_services() {
+        let file = |path: &str| FileChange {
+            path: path.to_string(),
+            old_path: None,
+            status: "modified".to_string(),
+            additions: 1,
+            deletions: 0,
+            diff: String::new(),
+            truncated: None,
+        };
+        let diff = ExtractedDiff {
+            source: "commit".to_string(),
+            identifier: "abc".to_string(),
+            timestamp: String::new(),
+            author: String::new(),
+            message: String::new(),
+            files: vec![
+                file("services/api/src/main.rs"),
+                file("services/api/admin/page.rs"),
+                file("libs/billing/lib.rs"),
+                file("README.md"),
+            ],
+            summary: Default::default(),
+            pull_request: None,
+            commits: Vec::new(),
+            repositories: Vec::new(),
+        };
+        let service_dirs = vec![
+            ("api".to_string(), "services/api".to_string()),
+            ("admin".to_string(), "services/api/admin".to_string()),
+        ];
+        let codeowners = CodeOwners::parse("/libs/billing/ @org/Billing\n");
+        let known = vec![
+            "api".to_string(),
+            "admin".to_string(),
+            "billing".to_string(),
+        ];
+
+        let resolution = resolve_services(&diff, &service_dirs, Some(&codeowners), &known);
+        assert_eq!(resolution.services["api"], ["services/api/src/main.rs"]);
+        assert_eq!(resolution.services["admin"], ["services/api/admin/page.rs"]);
+        assert_eq!(resolution.services["billing"], ["libs/billing/lib.rs"]);
+        assert_eq!(resolution.unresolved, ["README.md"]);
+
+        let scoped = resolution.scoped_diffs(&diff);
+        assert_eq!(scoped.len(), 3);
+        assert_eq!(scoped[0].0, "admin");
+        assert_eq!(scoped[0].1.summary.total_files, 1);
+    }
+
+    #[test]
+    fn test_render_section() {
+        let ownership = Ownership {
+            contacts: vec![Contact {
+                name: "Alice".to_string(),
+                email: Some("alice@example.com".to_string()),
+                lines: 42,
+            }],
+            code_owners: vec!["@org/api".to_string()],
+        };
+
+        assert_eq!(
+            ownership.render_section(),
+            "\n\n## Primary contacts\n\n- Alice <alice@example.com> (42 lines)\n- Code owners: @org/api\n"
+        );
+        assert_eq!(Ownership::default().render_section(), "");
+    }
+}
@@ -1,5 +1,9 @@
+use crate::config::GitConfig;
 use crate::error::{KtmeError, Result};
-use crate::git::diff::{DiffSummary, ExtractedDiff, FileChange};
+use crate::git::diff::{
+    DiffSummary, ExtractedDiff, FileChange, PullRequestMetadata, ReviewComment,
+};
+use regex::Regex;
 use serde::Deserialize;
 use std::env;
 
@@ -14,6 +18,28 @@ struct GitHubPullRequest {
     base: GitHubRef,
     created_at: String,
     user: GitHubUser,
+    #[serde(default)]
+    html_url: String,
+    #[serde(default)]
+    labels: Vec<GitHubLabel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubLabel {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubReviewComment {
+    user: Option<GitHubUser>,
+    path: Option<String>,
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubReview {
+    user: Option<GitHubUser>,
+    body: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,25 +68,70 @@ struct GitHubUser {
 #[derive(Debug, Deserialize)]
 struct GitHubPullRequestFile {
     filename: String,
+    #[serde(default)]
+    previous_filename: Option<String>,
     status: String,
     additions: u32,
     deletions: u32,
     patch: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GitHubIssue {
+    pub title: String,
+    pub state: String,
+    pub html_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubCreatedPullRequest {
+    html_url: String,
+}
+
+/// REST API of github.com
+const GITHUB_API: &str = "https://api.github.com";
+
 pub struct GitHubProvider {
     api_token: Option<String>,
+    api_base: String,
     client: reqwest::Client,
 }
 
 impl GitHubProvider {
     pub fn new(api_token: Option<String>) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("github")
             .user_agent("ktme-cli")
             .build()
             .unwrap_or_else(|_| reqwest::Client::new());
 
-        Self { api_token, client }
+        Self {
+            api_token,
+            api_base: GITHUB_API.to_string(),
+            client,
+        }
+    }
+
+    /// Call the REST API at `api_base`, e.g. `https://github.example.com/api/v3`
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = api_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Provider for repositories hosted on `host`: a GitHub Enterprise
+    /// Server configured in `[[git.github_hosts]]`, github.com otherwise
+    pub fn for_host(git: &GitConfig, host: &str) -> Self {
+        let Some(enterprise) = git.github_host(host) else {
+            return Self::from_config(git.github_token.clone());
+        };
+        let token = enterprise
+            .token
+            .clone()
+            .or_else(|| env::var("GH_ENTERPRISE_TOKEN").ok())
+            .or_else(|| env::var("GITHUB_ENTERPRISE_TOKEN").ok());
+        if token.is_none() {
+            tracing::warn!("No token configured for GitHub Enterprise host {}", host);
+        }
+        Self::new(token).with_api_base(enterprise.api_base())
     }
 
     /// Create a new provider using config or environment variable
@@ -94,8 +165,8 @@ impl GitHubProvider {
 
         // Fetch PR metadata
         let pr_url = format!(
-            "https://api.github.com/repos/{}/{}/pulls/{}",
-            owner, repo_name, pr_number
+            "{}/repos/{}/{}/pulls/{}",
+            self.api_base, owner, repo_name, pr_number
         );
 
         let pr: GitHubPullRequest = self.fetch_json(&pr_url).await?;
@@ -109,10 +180,12 @@ impl GitHubProvider {
             .into_iter()
             .map(|f| FileChange {
                 path: f.filename,
+                old_path: f.previous_filename,
                 status: Self::normalize_status(&f.status),
                 additions: f.additions,
                 deletions: f.deletions,
                 diff: f.patch.unwrap_or_default(),
+                truncated: None,
             })
             .collect();
 
@@ -122,20 +195,154 @@ impl GitHubProvider {
             total_deletions: file_changes.iter().map(|f| f.deletions).sum(),
         };
 
+        // Review discussion is useful context but not essential to the diff
+        let review_comments = match self.fetch_review_comments(&pr_url).await {
+            Ok(comments) => comments,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to fetch review comments for PR #{}: {}",
+                    pr_number,
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        let description = pr.body.unwrap_or_default();
+        let metadata = PullRequestMetadata {
+            number: pr.number,
+            title: pr.title.clone(),
+            linked_issues: Self::parse_linked_issues(&description),
+            description: description.clone(),
+            url: pr.html_url,
+            labels: pr.labels.into_iter().map(|l| l.name).collect(),
+            review_comments,
+        };
+
         Ok(ExtractedDiff {
             source: format!("github-pr-{}", repo),
             identifier: format!("#{}", pr_number),
             timestamp: pr.created_at,
             author: pr.user.login,
-            message: format!("{}\n\n{}", pr.title, pr.body.unwrap_or_default()),
+            message: format!("{}\n\n{}", pr.title, description),
             files: file_changes,
             summary,
+            pull_request: Some(metadata),
+            commits: Vec::new(),
+            repositories: Vec::new(),
         })
     }
 
+    /// Fetch a single issue (or PR) by number
+    pub async fn fetch_issue(&self, repo: &str, number: u32) -> Result<GitHubIssue> {
+        let (owner, repo_name) = repo.split_once('/').ok_or_else(|| {
+            KtmeError::InvalidInput(format!(
+                "Invalid repository format '{}'. Expected 'owner/repo'",
+                repo
+            ))
+        })?;
+
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}",
+            self.api_base, owner, repo_name, number
+        );
+        self.fetch_json(&url).await
+    }
+
+    /// Fetch review bodies and inline review comments for a PR
+    async fn fetch_review_comments(&self, pr_url: &str) -> Result<Vec<ReviewComment>> {
+        let reviews: Vec<GitHubReview> = self.fetch_json(&format!("{}/reviews", pr_url)).await?;
+        let inline: Vec<GitHubReviewComment> =
+            self.fetch_json(&format!("{}/comments", pr_url)).await?;
+
+        let login = |user: Option<GitHubUser>| user.map(|u| u.login).unwrap_or_default();
+
+        let mut comments: Vec<ReviewComment> = reviews
+            .into_iter()
+            .filter_map(|r| {
+                let body = r.body.filter(|b| !b.trim().is_empty())?;
+                Some(ReviewComment {
+                    author: login(r.user),
+                    path: None,
+                    body,
+                })
+            })
+            .collect();
+
+        comments.extend(inline.into_iter().map(|c| ReviewComment {
+            author: login(c.user),
+            path: c.path,
+            body: c.body,
+        }));
+
+        Ok(comments)
+    }
+
+    /// Extract issues referenced with closing keywords ("Fixes #12",
+    /// "closes owner/repo#34") from a PR description
+    fn parse_linked_issues(description: &str) -> Vec<String> {
+        let re = Regex::new(
+            r"(?i)\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?)\s*:?\s+([\w.-]+/[\w.-]+)?#(\d+)",
+        )
+        .expect("valid linked issue regex");
+
+        let mut issues = Vec::new();
+        for caps in re.captures_iter(description) {
+            let issue = match caps.get(1) {
+                Some(repo) => format!("{}#{}", repo.as_str(), &caps[2]),
+                None => format!("#{}", &caps[2]),
+            };
+            if !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        }
+        issues
+    }
+
+    /// Open a pull request of `head` into `base` and return its URL
+    pub async fn create_pull_request(
+        &self,
+        repo: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<String> {
+        let url = format!("{}/repos/{}/pulls", self.api_base, repo);
+        let request = serde_json::json!({
+            "title": title,
+            "head": head,
+            "base": base,
+            "body": body,
+        });
+        let created: GitHubCreatedPullRequest = self.post_json(&url, &request).await?;
+        Ok(created.html_url)
+    }
+
+    /// Token used for API calls, if any
+    pub(crate) fn api_token(&self) -> Option<String> {
+        self.api_token.clone()
+    }
+
     /// Fetch JSON from GitHub API with authentication
     async fn fetch_json<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
-        let mut request = self.client.get(url);
+        self.send_json(self.client.get(url)).await
+    }
+
+    /// POST a JSON body to the GitHub API with authentication
+    async fn post_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        self.send_json(self.client.post(url).json(body)).await
+    }
+
+    async fn send_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        crate::http::ensure_online("Calling the GitHub API")?;
 
         // Add authentication if token is available
         if let Some(token) = &self.api_token {
@@ -147,9 +354,10 @@ impl GitHubProvider {
             .header("Accept", "application/vnd.github.v3+json")
             .header("X-GitHub-Api-Version", "2022-11-28");
 
-        let response = request.send().await.map_err(|e| {
-            KtmeError::NetworkError(format!("Failed to fetch from GitHub API: {}", e))
-        })?;
+        let response = request
+            .send()
+            .await
+            .map_err(|e| crate::http::request_error("Failed to fetch from GitHub API", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -158,10 +366,14 @@ impl GitHubProvider {
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
 
-            return Err(KtmeError::ApiError(format!(
-                "GitHub API request failed with status {}: {}",
-                status, error_body
-            )));
+            return Err(KtmeError::from_status(
+                "GitHub",
+                status,
+                KtmeError::ApiError(format!(
+                    "GitHub API request failed with status {}: {}",
+                    status, error_body
+                )),
+            ));
         }
 
         response.json().await.map_err(|e| {
@@ -198,6 +410,27 @@ mod tests {
         assert!(provider.api_token.is_none());
     }
 
+    #[test]
+    fn test_provider_for_enterprise_host() {
+        let git = GitConfig {
+            github_token: Some("public-token".to_string()),
+            github_hosts: vec![crate::config::GitHubHostConfig {
+                host: "github.example.com".to_string(),
+                api_url: None,
+                token: Some("enterprise-token".to_string()),
+            }],
+            ..Default::default()
+        };
+
+        let enterprise = GitHubProvider::for_host(&git, "GitHub.example.com");
+        assert_eq!(enterprise.api_base, "https://github.example.com/api/v3");
+        assert_eq!(enterprise.api_token.as_deref(), Some("enterprise-token"));
+
+        let public = GitHubProvider::for_host(&git, "github.com");
+        assert_eq!(public.api_base, GITHUB_API);
+        assert_eq!(public.api_token.as_deref(), Some("public-token"));
+    }
+
     #[test]
     fn test_normalize_status() {
         assert_eq!(GitHubProvider::normalize_status("added"), "added");
@@ -207,6 +440,16 @@ mod tests {
         assert_eq!(GitHubProvider::normalize_status("unknown"), "modified");
     }
 
+    #[test]
+    fn test_parse_linked_issues() {
+        let body = "Adds retries.\n\nFixes #12, closes FreePeak/ktme#34\nResolves: #12\nSee #99";
+        assert_eq!(
+            GitHubProvider::parse_linked_issues(body),
+            vec!["#12".to_string(), "FreePeak/ktme#34".to_string()]
+        );
+        assert!(GitHubProvider::parse_linked_issues("No references").is_empty());
+    }
+
     #[tokio::test]
     async fn test_invalid_repo_format() {
         let provider = GitHubProvider::new(None);
@@ -1,3 +1,4 @@
+use crate::config::GitConfig;
 use crate::error::{KtmeError, Result};
 use crate::git::diff::{DiffSummary, ExtractedDiff, FileChange};
 use serde::Deserialize;
@@ -39,6 +40,11 @@ struct GitLabUser {
     username: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct GitLabCreatedMergeRequest {
+    web_url: String,
+}
+
 pub struct GitLabProvider {
     api_token: Option<String>,
     base_url: String,
@@ -51,7 +57,7 @@ impl GitLabProvider {
     }
 
     pub fn new_with_url(api_token: Option<String>, base_url: String) -> Self {
-        let client = reqwest::Client::builder()
+        let client = crate::http::client("gitlab")
             .user_agent("ktme-cli")
             .build()
             .unwrap_or_else(|_| reqwest::Client::new());
@@ -82,6 +88,16 @@ impl GitLabProvider {
         Self::new_with_url(token, base_url)
     }
 
+    /// Provider for projects hosted on `host`; a self-managed instance is
+    /// reached at `https://<host>` unless GITLAB_URL is set
+    pub fn for_host(git: &GitConfig, host: &str) -> Self {
+        let provider = Self::from_config(git.gitlab_token.clone());
+        if host == "gitlab.com" || env::var("GITLAB_URL").is_ok() {
+            return provider;
+        }
+        Self::new_with_url(provider.api_token, format!("https://{}", host))
+    }
+
     pub async fn fetch_merge_request(
         &self,
         project: &str,
@@ -114,10 +130,12 @@ impl GitLabProvider {
 
                 FileChange {
                     path: c.new_path.clone(),
+                    old_path: c.renamed_file.then(|| c.old_path.clone()),
                     status,
                     additions,
                     deletions,
                     diff: c.diff,
+                    truncated: None,
                 }
             })
             .collect();
@@ -136,21 +154,70 @@ impl GitLabProvider {
             message: format!("{}\n\n{}", mr.title, mr.description.unwrap_or_default()),
             files: file_changes,
             summary,
+            pull_request: None,
+            commits: Vec::new(),
+            repositories: Vec::new(),
         })
     }
 
+    /// Open a merge request of `source` into `target` and return its URL
+    pub async fn create_merge_request(
+        &self,
+        project: &str,
+        source: &str,
+        target: &str,
+        title: &str,
+        description: &str,
+    ) -> Result<String> {
+        let url = format!(
+            "{}/api/v4/projects/{}/merge_requests",
+            self.base_url,
+            urlencoding::encode(project)
+        );
+        let request = serde_json::json!({
+            "source_branch": source,
+            "target_branch": target,
+            "title": title,
+            "description": description,
+        });
+        let created: GitLabCreatedMergeRequest = self.post_json(&url, &request).await?;
+        Ok(created.web_url)
+    }
+
+    /// Token used for API calls, if any
+    pub(crate) fn api_token(&self) -> Option<String> {
+        self.api_token.clone()
+    }
+
     /// Fetch JSON from GitLab API with authentication
     async fn fetch_json<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
-        let mut request = self.client.get(url);
+        self.send_json(self.client.get(url)).await
+    }
+
+    /// POST a JSON body to the GitLab API with authentication
+    async fn post_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        self.send_json(self.client.post(url).json(body)).await
+    }
+
+    async fn send_json<T: for<'de> Deserialize<'de>>(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> Result<T> {
+        crate::http::ensure_online("Calling the GitLab API")?;
 
         // Add authentication if token is available
         if let Some(token) = &self.api_token {
             request = request.header("PRIVATE-TOKEN", token);
         }
 
-        let response = request.send().await.map_err(|e| {
-            KtmeError::NetworkError(format!("Failed to fetch from GitLab API: {}", e))
-        })?;
+        let response = request
+            .send()
+            .await
+            .map_err(|e| crate::http::request_error("Failed to fetch from GitLab API", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -159,10 +226,14 @@ impl GitLabProvider {
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
 
-            return Err(KtmeError::ApiError(format!(
-                "GitLab API request failed with status {}: {}",
-                status, error_body
-            )));
+            return Err(KtmeError::from_status(
+                "GitLab",
+                status,
+                KtmeError::ApiError(format!(
+                    "GitLab API request failed with status {}: {}",
+                    status, error_body
+                )),
+            ));
         }
 
         response.json().await.map_err(|e| {
@@ -1,2 +1,158 @@
 pub mod github;
 pub mod gitlab;
+
+use crate::config::GitConfig;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use crate::git::remote::parse_remote_url;
+use github::GitHubProvider;
+use gitlab::GitLabProvider;
+
+/// Service hosting the pull requests of a repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hosting {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl Hosting {
+    /// Hosting service named on the command line (`--provider`)
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "github" => Ok(Self::GitHub),
+            "gitlab" => Ok(Self::GitLab),
+            "bitbucket" => Ok(Self::Bitbucket),
+            _ => Err(KtmeError::UnsupportedProvider(format!(
+                "Unknown provider: {}",
+                name
+            ))),
+        }
+    }
+
+    /// Hosting service of remotes on `host`: github.com or a configured
+    /// GitHub Enterprise host, or a host named after GitLab or Bitbucket
+    fn detect(git: &GitConfig, host: &str) -> Option<Self> {
+        if host == "github.com" || git.github_host(host).is_some() {
+            Some(Self::GitHub)
+        } else if host.contains("gitlab") {
+            Some(Self::GitLab)
+        } else if host.contains("bitbucket") {
+            Some(Self::Bitbucket)
+        } else {
+            None
+        }
+    }
+}
+
+/// Repository whose pull requests are read: its hosting service, host and
+/// `owner/repo` (or GitLab `group/project`) slug
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequestSource {
+    pub hosting: Hosting,
+    pub host: String,
+    pub slug: String,
+}
+
+impl PullRequestSource {
+    /// Source of the `origin` remote of the repository at the current
+    /// directory; `provider` names the hosting service when the host does
+    /// not tell it
+    pub fn detect(git: &GitConfig, provider: Option<&str>) -> Result<Self> {
+        let repo = git2::Repository::open_from_env()?;
+        let remote = repo.find_remote("origin")?;
+        let url = remote
+            .url()
+            .ok_or_else(|| KtmeError::InvalidInput("No remote URL found".to_string()))?;
+        Self::from_remote_url(git, url, provider)
+    }
+
+    pub fn from_remote_url(git: &GitConfig, url: &str, provider: Option<&str>) -> Result<Self> {
+        let (host, slug) = parse_remote_url(url).ok_or_else(|| {
+            KtmeError::InvalidInput(format!("Could not parse the remote URL {}", url))
+        })?;
+        let hosting = match provider {
+            Some(name) => Hosting::from_name(name)?,
+            None => Hosting::detect(git, &host).ok_or_else(|| {
+                KtmeError::InvalidInput(format!(
+                    "Cannot tell the hosting service of {}; pass --provider github or gitlab",
+                    url
+                ))
+            })?,
+        };
+        tracing::debug!("Detected {:?} repository {} on {}", hosting, slug, host);
+        Ok(Self {
+            hosting,
+            host,
+            slug,
+        })
+    }
+
+    /// Pull (merge) request `number` of the repository as a diff
+    pub async fn fetch(&self, git: &GitConfig, number: u32) -> Result<ExtractedDiff> {
+        crate::http::ensure_online(&format!("Extracting PR #{}", number))?;
+        match self.hosting {
+            Hosting::GitHub => {
+                GitHubProvider::for_host(git, &self.host)
+                    .fetch_pull_request(&self.slug, number)
+                    .await
+            }
+            Hosting::GitLab => {
+                GitLabProvider::for_host(git, &self.host)
+                    .fetch_merge_request(&self.slug, number)
+                    .await
+            }
+            Hosting::Bitbucket => Err(KtmeError::UnsupportedProvider(format!(
+                "Bitbucket pull requests are not supported yet ({})",
+                self.slug
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pull_request_source_from_remote_url() {
+        let git = GitConfig {
+            github_hosts: vec![crate::config::GitHubHostConfig {
+                host: "git.example.com".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let detect = |url: &str| {
+            PullRequestSource::from_remote_url(&git, url, None)
+                .map(|source| (source.hosting, source.slug))
+                .ok()
+        };
+
+        assert_eq!(
+            detect("git@github.com:acme/api.git"),
+            Some((Hosting::GitHub, "acme/api".to_string()))
+        );
+        assert_eq!(
+            detect("https://git.example.com/acme/api"),
+            Some((Hosting::GitHub, "acme/api".to_string()))
+        );
+        assert_eq!(
+            detect("https://gitlab.example.com/platform/team/api.git"),
+            Some((Hosting::GitLab, "platform/team/api".to_string()))
+        );
+        assert_eq!(
+            detect("git@bitbucket.org:acme/api.git"),
+            Some((Hosting::Bitbucket, "acme/api".to_string()))
+        );
+        assert_eq!(detect("https://code.example.org/acme/api"), None);
+
+        let named = PullRequestSource::from_remote_url(
+            &git,
+            "https://code.example.org/acme/api",
+            Some("gitlab"),
+        )
+        .unwrap();
+        assert_eq!(named.hosting, Hosting::GitLab);
+    }
+}
@@ -1,10 +1,36 @@
+use crate::config::GitConfig;
 use crate::error::Result;
-use crate::git::diff::ExtractedDiff;
+use crate::git::diff::{DiffTruncation, ExtractedDiff};
 use chrono::{DateTime, Utc};
-use git2::{Commit, Diff, DiffOptions, Oid, Repository, Status, StatusOptions};
+use git2::{
+    Commit, Diff, DiffFindOptions, DiffOptions, Oid, Patch, Repository, Status, StatusOptions,
+};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Size limits applied while reading diffs
+#[derive(Debug, Clone, Default)]
+pub struct DiffLimits {
+    /// Per-file diff text kept in memory; the rest is only counted
+    pub max_file_bytes: Option<usize>,
+    /// File receiving the full diff of every truncated file
+    pub spill_path: Option<PathBuf>,
+}
+
+impl DiffLimits {
+    /// Limits from the `[git]` configuration; 0 disables truncation
+    pub fn from_config(config: &GitConfig) -> Self {
+        Self {
+            max_file_bytes: (config.max_file_diff_bytes > 0).then_some(config.max_file_diff_bytes),
+            spill_path: None,
+        }
+    }
+}
 
 pub struct GitReader {
     repo: Repository,
+    limits: DiffLimits,
 }
 
 impl std::fmt::Debug for GitReader {
@@ -28,6 +54,7 @@ impl Clone for GitReader {
             .expect("Repository must have a workdir");
         Self {
             repo: Repository::open(path).expect("Should be able to reopen repository"),
+            limits: self.limits.clone(),
         }
     }
 }
@@ -40,7 +67,24 @@ impl GitReader {
             Repository::open_from_env()?
         };
 
-        Ok(Self { repo })
+        Ok(Self {
+            repo,
+            limits: DiffLimits::default(),
+        })
+    }
+
+    /// Reader for the repository containing `path`
+    pub fn discover(path: &Path) -> Result<Self> {
+        Ok(Self {
+            repo: Repository::discover(path)?,
+            limits: DiffLimits::default(),
+        })
+    }
+
+    /// Cap per-file diff sizes, optionally spilling full diffs to a file
+    pub fn with_limits(mut self, limits: DiffLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
     pub fn get_repository_path(&self) -> Result<String> {
@@ -85,9 +129,78 @@ impl GitReader {
         self.extract_tree_diff("staged", "staged", &head_commit.tree()?, &tree)
     }
 
+    /// Read every file of the repository as it was at `at`, a tag, branch,
+    /// commit or date (`YYYY-MM-DD`, the last commit on HEAD that day), as
+    /// additions to an empty tree. The trees are read from the object
+    /// database, so the working directory is left untouched. The snapshot is
+    /// labeled with the version `git describe --tags` gives its commit.
+    pub fn read_snapshot(&self, at: &str) -> Result<ExtractedDiff> {
+        tracing::info!("Reading repository snapshot at {}", at);
+
+        let commit = match self.resolve_reference(at) {
+            Ok(oid) => self.repo.find_object(oid, None)?.peel_to_commit()?,
+            Err(e) => match chrono::NaiveDate::parse_from_str(at, "%Y-%m-%d") {
+                Ok(date) => self.commit_on(date)?,
+                Err(_) => return Err(e),
+            },
+        };
+        let empty_tree = self.repo.find_tree(self.repo.treebuilder(None)?.write()?)?;
+        let id = commit.id().to_string();
+        let mut snapshot = self.extract_tree_diff(&id, "snapshot", &empty_tree, &commit.tree()?)?;
+
+        let mut describe = git2::DescribeOptions::new();
+        describe.describe_tags();
+        let version = commit
+            .as_object()
+            .describe(&describe)
+            .and_then(|d| d.format(None))
+            .unwrap_or_else(|_| at.to_string());
+        let date = DateTime::from_timestamp(commit.time().seconds(), 0)
+            .map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| snapshot.timestamp.clone());
+        snapshot.message = format!(
+            "Repository snapshot at {} ({}, {})\n\n{}",
+            version,
+            &id[..7],
+            date,
+            snapshot.message
+        );
+        snapshot.identifier = version;
+        Ok(snapshot)
+    }
+
+    /// Last commit on HEAD made on or before `date`
+    fn commit_on(&self, date: chrono::NaiveDate) -> Result<Commit<'_>> {
+        let end_of_day = date
+            .and_hms_opt(23, 59, 59)
+            .map(|time| time.and_utc().timestamp())
+            .unwrap_or_default();
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if commit.time().seconds() <= end_of_day {
+                return Ok(commit);
+            }
+        }
+        Err(crate::error::KtmeError::NotFound(format!(
+            "No commit on or before {}",
+            date
+        )))
+    }
+
     pub fn read_commit_range(&self, range: &str) -> Result<Vec<ExtractedDiff>> {
         tracing::info!("Reading commit range: {}", range);
 
+        self.commit_ids(range)?
+            .iter()
+            .map(|id| self.read_commit(id))
+            .collect()
+    }
+
+    /// Ids of the commits of `range` (`start..end`), newest first
+    pub fn commit_ids(&self, range: &str) -> Result<Vec<String>> {
         let parts: Vec<&str> = range.split("..").collect();
         if parts.len() != 2 {
             return Err(crate::error::KtmeError::InvalidInput(
@@ -106,14 +219,23 @@ impl GitReader {
             .push_range(&format!("{}..{}", start_oid, end_oid))
             .map_err(|e| crate::error::KtmeError::Git(e))?;
 
-        let mut diffs = Vec::new();
-        for oid in revwalk {
-            let oid = oid.map_err(|e| crate::error::KtmeError::Git(e))?;
-            let commit = self.repo.find_commit(oid)?;
-            diffs.push(self.extract_commit_diff(&commit)?);
-        }
+        revwalk
+            .map(|oid| {
+                oid.map(|oid| oid.to_string())
+                    .map_err(crate::error::KtmeError::Git)
+            })
+            .collect()
+    }
+
+    /// Full id of the commit `reference` points to
+    pub fn resolve_commit(&self, reference: &str) -> Result<String> {
+        let oid = self.resolve_reference(reference)?;
+        Ok(self.repo.find_commit(oid)?.id().to_string())
+    }
 
-        Ok(diffs)
+    /// Size limits applied while reading diffs
+    pub fn limits(&self) -> &DiffLimits {
+        &self.limits
     }
 
     pub fn get_current_branch(&self) -> Result<String> {
@@ -226,16 +348,25 @@ impl GitReader {
         let mut diff_opts = DiffOptions::new();
         diff_opts.context_lines(3).include_unmodified(false);
 
-        let diff = self
+        let mut diff = self
             .repo
             .diff_tree_to_tree(Some(old_tree), Some(new_tree), Some(&mut diff_opts))
             .map_err(|e| crate::error::KtmeError::Git(e))?;
 
+        // Report moved files as renames instead of a delete plus an add
+        let mut find_opts = DiffFindOptions::new();
+        find_opts.renames(true).copies(true);
+        diff.find_similar(Some(&mut find_opts))?;
+
         let mut files = Vec::new();
         let mut total_additions = 0;
         let mut total_deletions = 0;
+        let mut spill = match &self.limits.spill_path {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        };
 
-        for delta in diff.deltas() {
+        for (index, delta) in diff.deltas().enumerate() {
             let path = delta
                 .new_file()
                 .path()
@@ -254,20 +385,30 @@ impl GitReader {
                 _ => "unknown",
             };
 
-            let (additions, deletions, diff_text) =
-                self.get_file_stats(&diff, delta.old_file().id(), delta.new_file().id())?;
+            let old_path = matches!(delta.status(), git2::Delta::Renamed | git2::Delta::Copied)
+                .then(|| delta.old_file().path())
+                .flatten()
+                .map(|p| p.to_string_lossy().to_string());
+
+            let (additions, deletions, diff_text, truncated) =
+                self.get_file_stats(&diff, index, &path, spill.as_mut())?;
 
             total_additions += additions;
             total_deletions += deletions;
 
             files.push(crate::git::diff::FileChange {
                 path,
+                old_path,
                 status: status.to_string(),
                 additions,
                 deletions,
                 diff: diff_text,
+                truncated,
             });
         }
+        if let Some(mut spill) = spill {
+            spill.flush()?;
+        }
 
         // Get commit info if we have a commit ID
         let (author, message, timestamp) = if let Ok(oid) = Oid::from_str(identifier) {
@@ -306,37 +447,36 @@ impl GitReader {
                 total_additions,
                 total_deletions,
             },
+            pull_request: None,
+            commits: Vec::new(),
+            repositories: Vec::new(),
         })
     }
 
+    /// Read the diff of one file, line by line. Text beyond the size limit
+    /// is counted but not kept; the full diff of a truncated file is written
+    /// to the spill file, if any.
     fn get_file_stats(
         &self,
         diff: &Diff,
-        _old_id: git2::Oid,
-        _new_id: git2::Oid,
-    ) -> Result<(u32, u32, String)> {
+        index: usize,
+        path: &str,
+        mut spill: Option<&mut BufWriter<File>>,
+    ) -> Result<(u32, u32, String, Option<DiffTruncation>)> {
         let mut additions = 0u32;
         let mut deletions = 0u32;
         let mut diff_text = String::new();
+        let mut total_bytes = 0u64;
+        let mut total_lines = 0u32;
+        let mut truncated = false;
 
-        diff.foreach(
-            &mut |delta, _hunk| {
-                let path = delta
-                    .new_file()
-                    .path()
-                    .or_else(|| delta.old_file().path())
-                    .unwrap_or_else(|| std::path::Path::new(""))
-                    .to_string_lossy();
-
-                tracing::debug!("Processing file: {}", path);
-                true
-            },
-            None,
-            Some(&mut |_delta, hunk| {
-                tracing::debug!("Hunk: {} lines", hunk.old_lines());
-                true
-            }),
-            Some(&mut |_delta, _hunk, line| {
+        let Some(patch) = Patch::from_diff(diff, index)? else {
+            return Ok((0, 0, diff_text, None));
+        };
+
+        for hunk in 0..patch.num_hunks() {
+            for line in 0..patch.num_lines_in_hunk(hunk)? {
+                let line = patch.line_in_hunk(hunk, line)?;
                 match line.origin() {
                     '+' | ' ' => additions += 1,
                     '-' => deletions += 1,
@@ -351,17 +491,63 @@ impl GitReader {
                     '<' => " ",
                     _ => "",
                 };
-
-                diff_text.push_str(&format!(
+                let text = format!(
                     "{}{}\n",
                     prefix,
                     String::from_utf8_lossy(line.content()).trim_end()
-                ));
-                true
-            }),
-        )
-        .map_err(|e| crate::error::KtmeError::Git(e))?;
+                );
+                total_bytes += text.len() as u64;
+                total_lines += 1;
+
+                if !truncated
+                    && self
+                        .limits
+                        .max_file_bytes
+                        .is_some_and(|max| diff_text.len() + text.len() > max)
+                {
+                    truncated = true;
+                    tracing::debug!("Truncating diff of {} at {} bytes", path, diff_text.len());
+                    if let Some(spill) = spill.as_mut() {
+                        write!(spill, "diff --git a/{0} b/{0}\n{1}", path, diff_text)?;
+                    }
+                }
+
+                if !truncated {
+                    diff_text.push_str(&text);
+                } else if let Some(spill) = spill.as_mut() {
+                    spill.write_all(text.as_bytes())?;
+                }
+            }
+        }
 
-        Ok((additions, deletions, diff_text))
+        if !truncated {
+            return Ok((additions, deletions, diff_text, None));
+        }
+
+        let kept_lines = diff_text.lines().count() as u32;
+        let spill_file = self
+            .limits
+            .spill_path
+            .as_ref()
+            .map(|p| p.display().to_string());
+        diff_text.push_str(&format!(
+            "... diff truncated: {} more lines{}\n",
+            total_lines - kept_lines,
+            spill_file
+                .as_ref()
+                .map(|p| format!(", full diff in {}", p))
+                .unwrap_or_default()
+        ));
+
+        Ok((
+            additions,
+            deletions,
+            diff_text,
+            Some(DiffTruncation {
+                original_bytes: total_bytes,
+                original_lines: total_lines,
+                spill_file,
+            }),
+        ))
     }
 }
@@ -0,0 +1,214 @@
+//! Extraction from remote repositories without a working copy
+//!
+//! The requested commit is shallow-fetched into a temporary bare repository
+//! that is removed again when the checkout is dropped.
+
+use crate::config::GitConfig;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use crate::git::providers::github::GitHubProvider;
+use crate::git::reader::{DiffLimits, GitReader};
+use git2::{Cred, FetchOptions, RemoteCallbacks, Repository};
+use std::path::PathBuf;
+
+/// Commits fetched: the requested one and its parent, so the diff has a base
+const FETCH_DEPTH: i32 = 2;
+
+/// A temporary bare repository holding a shallow fetch of one commit
+pub struct RemoteCheckout {
+    dir: PathBuf,
+    reader: GitReader,
+}
+
+impl RemoteCheckout {
+    /// Fetch `reference` (a full commit hash or a branch/tag name) from `url`.
+    /// `token` authenticates HTTPS remotes such as private GitHub or GitLab
+    /// repositories.
+    pub fn fetch(
+        url: &str,
+        reference: &str,
+        token: Option<&str>,
+        limits: DiffLimits,
+    ) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("ktme-remote-{}", uuid::Uuid::new_v4()));
+        tracing::info!("Fetching {} from {} into {}", reference, url, dir.display());
+
+        let result = Self::fetch_into(&dir, url, reference, token);
+        if result.is_err() {
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+        let reader = result?.with_limits(limits);
+
+        Ok(Self { dir, reader })
+    }
+
+    fn fetch_into(
+        dir: &std::path::Path,
+        url: &str,
+        reference: &str,
+        token: Option<&str>,
+    ) -> Result<GitReader> {
+        let repo = Repository::init_bare(dir)?;
+        let mut remote = repo.remote_anonymous(url)?;
+
+        let mut callbacks = RemoteCallbacks::new();
+        if let Some(token) = token {
+            let gitlab = parse_remote_url(url).is_some_and(|(host, _)| is_gitlab_host(&host));
+            let username = if gitlab { "oauth2" } else { "x-access-token" };
+            // Checked again on the URL asking for credentials, which may
+            // differ from `url` after a redirect
+            callbacks.credentials(move |url, _, _| {
+                if !is_secure_transport(url) {
+                    return Err(git2::Error::from_str(&format!(
+                        "Refusing to send credentials over an unencrypted connection to {}",
+                        url
+                    )));
+                }
+                Cred::userpass_plaintext(username, token)
+            });
+        }
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks).depth(FETCH_DEPTH);
+
+        remote
+            .fetch(&[reference], Some(&mut options), None)
+            .map_err(|e| {
+                KtmeError::Git(git2::Error::from_str(&format!(
+                    "Failed to fetch '{}' from {}: {}",
+                    reference,
+                    url,
+                    e.message()
+                )))
+            })?;
+
+        GitReader::new(Some(&dir.to_string_lossy()))
+    }
+
+    /// Diff of the fetched commit against its parent
+    pub fn read_commit(&self) -> Result<ExtractedDiff> {
+        self.reader.read_commit("FETCH_HEAD")
+    }
+}
+
+impl Drop for RemoteCheckout {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            tracing::debug!("Failed to remove {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+/// Host (without port) and repository path (`owner/repo`, without `.git`)
+/// of an HTTPS or SSH remote URL such as `git@github.com:owner/repo.git`
+pub fn parse_remote_url(url: &str) -> Option<(String, String)> {
+    let (host, path) = match url.strip_prefix("git@") {
+        Some(rest) => rest.split_once(':')?,
+        None => {
+            let rest = url.split_once("://")?.1;
+            let rest = rest.rsplit_once('@').map_or(rest, |(_, rest)| rest);
+            rest.split_once('/')?
+        }
+    };
+    let host = host.split(':').next().unwrap_or(host);
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    (!host.is_empty() && !path.is_empty()).then(|| (host.to_lowercase(), path.to_string()))
+}
+
+/// Token authenticating fetches from `url`: the token of a matching GitHub
+/// Enterprise host, the GitHub token for github.com or the GitLab token for
+/// gitlab.com and the host of GITLAB_URL. Other hosts get no token, so that
+/// a remote of an unknown server is never sent the user's credentials, and
+/// neither are plain `http://` remotes.
+pub fn token_for(git: &GitConfig, url: &str) -> Option<String> {
+    if !is_secure_transport(url) {
+        return None;
+    }
+    let (host, _) = parse_remote_url(url)?;
+    if git.github_host(&host).is_some() {
+        GitHubProvider::for_host(git, &host).api_token()
+    } else if host == "github.com" {
+        git.github_token.clone()
+    } else if is_gitlab_host(&host) {
+        git.gitlab_token.clone()
+    } else {
+        None
+    }
+}
+
+/// Whether `url` is an HTTPS or SSH remote, over which a token is encrypted
+fn is_secure_transport(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("https://") || url.starts_with("ssh://") || url.starts_with("git@")
+}
+
+/// Whether `host` is gitlab.com or the self-managed instance of GITLAB_URL
+fn is_gitlab_host(host: &str) -> bool {
+    host == "gitlab.com"
+        || std::env::var("GITLAB_URL")
+            .ok()
+            .and_then(|url| reqwest::Url::parse(&url).ok())
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+            .is_some_and(|gitlab| gitlab == host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote_url() {
+        let parsed = |host: &str, path: &str| Some((host.to_string(), path.to_string()));
+        assert_eq!(
+            parse_remote_url("git@github.com:acme/payments.git"),
+            parsed("github.com", "acme/payments")
+        );
+        assert_eq!(
+            parse_remote_url("https://token@GitHub.example.com/acme/payments/"),
+            parsed("github.example.com", "acme/payments")
+        );
+        assert_eq!(
+            parse_remote_url("ssh://git@github.example.com:2222/acme/payments.git"),
+            parsed("github.example.com", "acme/payments")
+        );
+        assert_eq!(parse_remote_url("/srv/git/payments"), None);
+    }
+
+    #[test]
+    fn test_tokens_only_go_to_known_hosts() {
+        let git = GitConfig {
+            github_token: Some("ghp-public".to_string()),
+            gitlab_token: Some("glpat-public".to_string()),
+            github_hosts: vec![crate::config::GitHubHostConfig {
+                host: "github.example.com".to_string(),
+                token: Some("ghe-token".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let token = |url: &str| token_for(&git, url);
+        assert_eq!(
+            token("https://github.com/acme/payments.git").as_deref(),
+            Some("ghp-public")
+        );
+        assert_eq!(
+            token("git@github.example.com:acme/payments.git").as_deref(),
+            Some("ghe-token")
+        );
+        assert_eq!(
+            token("https://gitlab.com/acme/payments.git").as_deref(),
+            Some("glpat-public")
+        );
+
+        // Unknown servers, including look-alikes, get nothing
+        assert_eq!(token("https://evil.example/x.git"), None);
+        assert_eq!(token("https://gitlab.evil.example/acme/payments.git"), None);
+        assert_eq!(token("https://evil.example/gitlab/payments.git"), None);
+        assert_eq!(token("https://github.com.evil.example/acme/x.git"), None);
+        assert_eq!(token("/srv/git/payments"), None);
+
+        // Known hosts get nothing over plain HTTP either
+        assert_eq!(token("http://github.com/acme/payments.git"), None);
+        assert_eq!(token("HTTP://gitlab.com/acme/payments.git"), None);
+        assert_eq!(token("http://github.example.com/acme/payments.git"), None);
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, TimeZone, Utc};
+use git2::Repository;
+use std::path::Path;
+
+/// Latest commit of a service repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head {
+    pub commit: String,
+    pub time: DateTime<Utc>,
+}
+
+/// A service is stale when it was never generated successfully, or when its
+/// repository has commits newer than the last successful generation
+pub fn is_stale(last_success: Option<DateTime<Utc>>, head_time: Option<DateTime<Utc>>) -> bool {
+    match (last_success, head_time) {
+        (None, _) => true,
+        (Some(success), Some(head)) => head > success,
+        (Some(_), None) => false,
+    }
+}
+
+/// HEAD commit of the repository containing `path`
+pub fn head(path: &str) -> Option<Head> {
+    let repo = Repository::discover(Path::new(path)).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(Head {
+        commit: commit.id().to_string(),
+        time: Utc.timestamp_opt(commit.time().seconds(), 0).single()?,
+    })
+}
+
+/// Commits reachable from HEAD of the repository containing `path` that are
+/// newer than `since` (all of them without `since`)
+pub fn commits_since(path: &str, since: Option<DateTime<Utc>>) -> Option<usize> {
+    let repo = Repository::discover(Path::new(path)).ok()?;
+    let mut walk = repo.revwalk().ok()?;
+    walk.push_head().ok()?;
+    let since = since.map(|since| since.timestamp());
+    let mut count = 0;
+    for oid in walk {
+        let commit = repo.find_commit(oid.ok()?).ok()?;
+        if since.is_none_or(|since| commit.time().seconds() > since) {
+            count += 1;
+        }
+    }
+    Some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_commits_since_counts_newer_commits() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let mut parent = None;
+        for seconds in [1_700_000_000, 1_700_000_600, 1_700_001_200] {
+            let signature =
+                git2::Signature::new("Dev", "dev@example.com", &git2::Time::new(seconds, 0))
+                    .unwrap();
+            let parents: Vec<git2::Commit> = parent
+                .map(|id| repo.find_commit(id).unwrap())
+                .into_iter()
+                .collect();
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            parent = Some(
+                repo.commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    "Change",
+                    &tree,
+                    &parents,
+                )
+                .unwrap(),
+            );
+        }
+        let path = dir.path().to_string_lossy();
+
+        let generated = Utc.timestamp_opt(1_700_000_300, 0).single();
+        assert_eq!(commits_since(&path, generated), Some(2));
+        assert_eq!(commits_since(&path, None), Some(3));
+        assert_eq!(
+            head(&path).map(|head| head.time.timestamp()),
+            Some(1_700_001_200)
+        );
+        assert_eq!(commits_since("/nonexistent/ktme", None), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Shared settings of outgoing HTTP clients
+//!
+//! API clients (AI providers, Confluence, Notion, Jira, GitHub, GitLab) are
+//! built with [`client`], which applies the connect and request timeouts of
+//! the `[timeouts]` config section so that an unresponsive server fails the
+//! call instead of hanging it. [`with_deadline`] bounds a whole operation such
+//! as a `generate` run.
+//!
+//! In offline mode (`ktme --offline` or `[general] offline = true`)
+//! [`ensure_online`] refuses every network operation.
+
+use crate::config::{Config, TimeoutsConfig};
+use crate::error::{KtmeError, Result};
+use crate::logging::parse_since;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Request timeout of AI providers when none is configured; generating a
+/// long document can take minutes
+const DEFAULT_AI_REQUEST: Duration = Duration::from_secs(300);
+
+/// Request timeout of every other provider when none is configured
+const DEFAULT_REQUEST: Duration = Duration::from_secs(60);
+
+static TIMEOUTS: OnceLock<TimeoutsConfig> = OnceLock::new();
+
+static OFFLINE: OnceLock<bool> = OnceLock::new();
+
+/// Turn offline mode on or off. Without a call `[general] offline` of the
+/// config file (or `ktme --offline`) decides.
+pub fn set_offline(offline: bool) {
+    let _ = OFFLINE.set(offline);
+}
+
+/// Whether outbound network calls are disabled
+pub fn offline() -> bool {
+    *OFFLINE.get_or_init(|| {
+        Config::load()
+            .map(|c| c.general.offline)
+            .unwrap_or_else(|_| crate::config::offline_from_env())
+    })
+}
+
+/// Fail with [`KtmeError::Offline`] when `action` would need the network in
+/// offline mode
+pub fn ensure_online(action: &str) -> Result<()> {
+    if offline() {
+        return Err(KtmeError::Offline(action.to_string()));
+    }
+    Ok(())
+}
+
+/// Whether `url` points at this machine, such as a local LLM server
+pub fn is_local_url(url: &str) -> bool {
+    let Some(host) = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_owned))
+    else {
+        return false;
+    };
+    host == "localhost"
+        || host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Use `config` for clients built from now on. Without a call the
+/// `[timeouts]` section of the config file is used.
+pub fn configure(config: &TimeoutsConfig) {
+    let _ = TIMEOUTS.set(config.clone());
+}
+
+fn timeouts() -> &'static TimeoutsConfig {
+    TIMEOUTS.get_or_init(|| Config::load().map(|c| c.timeouts).unwrap_or_default())
+}
+
+/// Client builder for `provider` with the configured timeouts
+pub fn client(provider: &str) -> reqwest::ClientBuilder {
+    let timeouts = timeouts();
+    reqwest::Client::builder()
+        .connect_timeout(timeouts.connect_timeout())
+        .timeout(timeouts.request_timeout(provider))
+}
+
+/// Fail `future` with [`KtmeError::Timeout`] when it does not complete
+/// within `deadline`
+pub async fn with_deadline<T>(
+    operation: &str,
+    deadline: Duration,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::time::timeout(deadline, future)
+        .await
+        .map_err(|_| KtmeError::Timeout(format!("{} after {:?}", operation, deadline)))?
+}
+
+/// Error of a request that got no response: [`KtmeError::Timeout`] when
+/// it ran out of time, [`KtmeError::NetworkError`] otherwise
+pub fn request_error(context: &str, error: reqwest::Error) -> KtmeError {
+    if error.is_timeout() {
+        KtmeError::Timeout(format!("{}: {}", context, error))
+    } else {
+        KtmeError::NetworkError(format!("{}: {}", context, error))
+    }
+}
+
+/// Parse a timeout such as `90s`, `5m` or `1h`
+pub fn parse_timeout(value: &str) -> Result<Duration> {
+    parse_since(value)?
+        .to_std()
+        .ok()
+        .filter(|d| !d.is_zero())
+        .ok_or_else(|| KtmeError::InvalidInput(format!("Invalid timeout '{}'", value)))
+}
+
+impl TimeoutsConfig {
+    /// Check every configured duration
+    pub fn validate(&self) -> Result<()> {
+        let values = [
+            Some(&self.connect),
+            self.request.as_ref(),
+            Some(&self.operation),
+        ];
+        for value in values.into_iter().flatten().chain(self.providers.values()) {
+            parse_timeout(value).map_err(|e| KtmeError::Config(format!("[timeouts] {}", e)))?;
+        }
+        Ok(())
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        parse_timeout(&self.connect).unwrap_or(Duration::from_secs(10))
+    }
+
+    /// Request timeout of `provider`: its own entry, then `request`, then
+    /// the built-in default
+    pub fn request_timeout(&self, provider: &str) -> Duration {
+        self.providers
+            .get(provider)
+            .or(self.request.as_ref())
+            .and_then(|value| parse_timeout(value).ok())
+            .unwrap_or(if provider == "ai" {
+                DEFAULT_AI_REQUEST
+            } else {
+                DEFAULT_REQUEST
+            })
+    }
+
+    /// Deadline of a whole run, `--timeout` when given
+    pub fn operation_timeout(&self, cli: Option<&str>) -> Result<Duration> {
+        match cli {
+            Some(value) => parse_timeout(value),
+            None => parse_timeout(&self.operation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_resolution() {
+        let mut config = TimeoutsConfig::default();
+        assert_eq!(config.connect_timeout(), Duration::from_secs(10));
+        assert_eq!(config.request_timeout("ai"), DEFAULT_AI_REQUEST);
+        assert_eq!(config.request_timeout("jira"), DEFAULT_REQUEST);
+        assert_eq!(
+            config.operation_timeout(None).unwrap(),
+            Duration::from_secs(30 * 60)
+        );
+        assert_eq!(
+            config.operation_timeout(Some("120s")).unwrap(),
+            Duration::from_secs(120)
+        );
+
+        config.request = Some("45s".to_string());
+        config
+            .providers
+            .insert("confluence".to_string(), "2m".to_string());
+        assert_eq!(config.request_timeout("ai"), Duration::from_secs(45));
+        assert_eq!(
+            config.request_timeout("confluence"),
+            Duration::from_secs(120)
+        );
+        assert!(config.validate().is_ok());
+
+        config
+            .providers
+            .insert("notion".to_string(), "0s".to_string());
+        assert!(matches!(config.validate(), Err(KtmeError::Config(_))));
+        assert!(config.operation_timeout(Some("soon")).is_err());
+    }
+
+    #[test]
+    fn test_local_urls() {
+        assert!(is_local_url("http://localhost:11434/v1"));
+        assert!(is_local_url("http://127.0.0.1:8080"));
+        assert!(is_local_url("http://[::1]:8080/v1"));
+        assert!(!is_local_url("https://api.openai.com/v1"));
+        assert!(!is_local_url("not a url"));
+    }
+
+    #[tokio::test]
+    async fn test_deadline() {
+        let slow = with_deadline("slow call", Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        });
+        assert!(matches!(slow.await, Err(KtmeError::Timeout(_))));
+
+        let fast = with_deadline("fast call", Duration::from_secs(5), async { Ok(1) });
+        assert_eq!(fast.await.unwrap(), 1);
+    }
+}
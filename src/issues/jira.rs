@@ -0,0 +1,144 @@
+use crate::error::{KtmeError, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+struct JiraIssue {
+    fields: JiraIssueFields,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraIssueFields {
+    summary: String,
+    status: Option<JiraStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraStatus {
+    name: String,
+}
+
+/// Title and workflow status of a Jira ticket
+#[derive(Debug, Clone)]
+pub struct JiraTicket {
+    pub summary: String,
+    pub status: Option<String>,
+}
+
+/// Minimal Jira REST client for ticket lookups and comments
+pub struct JiraClient {
+    base_url: String,
+    auth_header: String,
+    client: reqwest::Client,
+}
+
+impl JiraClient {
+    pub fn new(base_url: &str, username: &str, api_token: &str) -> Self {
+        let encoded = general_purpose::STANDARD.encode(format!("{}:{}", username, api_token));
+
+        let client = crate::http::client("jira")
+            .user_agent("ktme-cli")
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth_header: format!("Basic {}", encoded),
+            client,
+        }
+    }
+
+    /// Browser URL for a ticket
+    pub fn browse_url(&self, key: &str) -> String {
+        format!("{}/browse/{}", self.base_url, key)
+    }
+
+    /// Check that Jira is reachable and accepts the credentials
+    pub async fn check(&self) -> Result<()> {
+        let url = format!("{}/rest/api/2/myself", self.base_url);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", &self.auth_header)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(|e| crate::http::request_error("Failed to reach Jira", e))?;
+
+        if !response.status().is_success() {
+            return Err(KtmeError::from_status(
+                "Jira",
+                response.status(),
+                KtmeError::ApiError(format!("Jira answered with status {}", response.status())),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_issue(&self, key: &str) -> Result<JiraTicket> {
+        let url = format!(
+            "{}/rest/api/2/issue/{}?fields=summary,status",
+            self.base_url, key
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", &self.auth_header)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(|e| crate::http::request_error("Failed to reach Jira", e))?;
+
+        if !response.status().is_success() {
+            return Err(KtmeError::from_status(
+                "Jira",
+                response.status(),
+                KtmeError::ApiError(format!(
+                    "Jira lookup of {} failed with status {}",
+                    key,
+                    response.status()
+                )),
+            ));
+        }
+
+        let issue: JiraIssue = response.json().await.map_err(|e| {
+            KtmeError::DeserializationError(format!("Failed to parse Jira issue {}: {}", key, e))
+        })?;
+
+        Ok(JiraTicket {
+            summary: issue.fields.summary,
+            status: issue.fields.status.map(|s| s.name),
+        })
+    }
+
+    pub async fn add_comment(&self, key: &str, body: &str) -> Result<()> {
+        let url = format!("{}/rest/api/2/issue/{}/comment", self.base_url, key);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", &self.auth_header)
+            .json(&json!({ "body": body }))
+            .send()
+            .await
+            .map_err(|e| crate::http::request_error("Failed to reach Jira", e))?;
+
+        if !response.status().is_success() {
+            return Err(KtmeError::from_status(
+                "Jira",
+                response.status(),
+                KtmeError::ApiError(format!(
+                    "Commenting on {} failed with status {}",
+                    key,
+                    response.status()
+                )),
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Issue tracker linking for generated documentation
+//!
+//! Detects Jira keys (`PAY-123`) and GitHub references (`#42`,
+//! `owner/repo#42`) in commit messages and PR titles, optionally resolves
+//! them through the tracker APIs, and renders a "Related tickets" section.
+
+pub mod jira;
+
+use crate::config::IssuesConfig;
+use crate::git::diff::ExtractedDiff;
+use crate::git::providers::github::GitHubProvider;
+use jira::JiraClient;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Uppercase prefixes that look like Jira keys but are not tickets
+const NON_TICKET_PREFIXES: &[&str] = &["UTF", "ISO", "RFC", "SHA", "HTTP", "TLS", "X"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssueRef {
+    Jira(String),
+    GitHub { repo: Option<String>, number: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedTicket {
+    /// "jira" or "github"
+    pub tracker: String,
+    pub key: String,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub status: Option<String>,
+}
+
+/// Find issue references in free text, in order of first appearance
+pub fn detect_issue_refs(text: &str, config: &IssuesConfig) -> Vec<IssueRef> {
+    let jira_re = Regex::new(r"\b([A-Z][A-Z0-9]+)-(\d+)\b").expect("valid Jira key regex");
+    let github_re = Regex::new(r"(?:\b([\w.-]+/[\w.-]+))?#(\d+)\b").expect("valid issue regex");
+
+    let mut found: Vec<(usize, IssueRef)> = Vec::new();
+
+    for caps in jira_re.captures_iter(text) {
+        let project = &caps[1];
+        let accepted = if config.jira_project_keys.is_empty() {
+            !NON_TICKET_PREFIXES.contains(&project)
+        } else {
+            config.jira_project_keys.iter().any(|k| k == project)
+        };
+        if accepted {
+            let m = caps.get(0).expect("whole match");
+            found.push((m.start(), IssueRef::Jira(m.as_str().to_string())));
+        }
+    }
+
+    for caps in github_re.captures_iter(text) {
+        let Ok(number) = caps[2].parse::<u32>() else {
+            continue;
+        };
+        let m = caps.get(0).expect("whole match");
+        found.push((
+            m.start(),
+            IssueRef::GitHub {
+                repo: caps.get(1).map(|r| r.as_str().to_string()),
+                number,
+            },
+        ));
+    }
+
+    found.sort_by_key(|(pos, _)| *pos);
+
+    let mut refs: Vec<IssueRef> = Vec::new();
+    for (_, issue) in found {
+        if !refs.contains(&issue) {
+            refs.push(issue);
+        }
+    }
+    refs
+}
+
+/// Resolves detected references and renders them into documentation
+pub struct IssueLinker {
+    config: IssuesConfig,
+    jira: Option<JiraClient>,
+    /// Only created when lookups are enabled
+    github: Option<GitHubProvider>,
+}
+
+impl IssueLinker {
+    pub fn new(mut config: IssuesConfig, github_token: Option<String>) -> Self {
+        // Tickets are still linked offline, just not looked up or commented on
+        if crate::http::offline() {
+            config.resolve = false;
+            config.comment_on_jira = false;
+        }
+
+        let jira_token = config
+            .jira_api_token
+            .clone()
+            .or_else(|| env::var("JIRA_API_TOKEN").ok());
+
+        let jira = match (&config.jira_base_url, &config.jira_username, jira_token) {
+            (Some(url), Some(user), Some(token)) => Some(JiraClient::new(url, user, &token)),
+            _ => None,
+        };
+
+        Self {
+            jira,
+            github: config
+                .resolve
+                .then(|| GitHubProvider::from_config(github_token)),
+            config,
+        }
+    }
+
+    /// References in the commit message and PR title/linked issues of a diff
+    pub fn detect(&self, diff: &ExtractedDiff) -> Vec<IssueRef> {
+        let mut text = diff.message.clone();
+        if let Some(pr) = &diff.pull_request {
+            text.push('\n');
+            text.push_str(&pr.title);
+            for issue in &pr.linked_issues {
+                text.push('\n');
+                text.push_str(issue);
+            }
+        }
+        detect_issue_refs(&text, &self.config)
+    }
+
+    /// Build ticket entries, looking up titles and status when enabled.
+    /// Lookup failures are logged and leave the ticket unresolved.
+    pub async fn resolve(
+        &self,
+        refs: &[IssueRef],
+        default_repo: Option<&str>,
+    ) -> Vec<RelatedTicket> {
+        let mut tickets = Vec::new();
+
+        for issue in refs {
+            let ticket = match issue {
+                IssueRef::Jira(key) => self.resolve_jira(key).await,
+                IssueRef::GitHub { repo, number } => {
+                    let repo = repo
+                        .as_deref()
+                        .or(default_repo)
+                        .or(self.config.github_repo.as_deref());
+                    self.resolve_github(repo, *number).await
+                }
+            };
+            tickets.push(ticket);
+        }
+
+        tickets
+    }
+
+    async fn resolve_jira(&self, key: &str) -> RelatedTicket {
+        let mut ticket = RelatedTicket {
+            tracker: "jira".to_string(),
+            key: key.to_string(),
+            url: self.jira.as_ref().map(|j| j.browse_url(key)),
+            title: None,
+            status: None,
+        };
+
+        if let (true, Some(jira)) = (self.config.resolve, &self.jira) {
+            match jira.get_issue(key).await {
+                Ok(issue) => {
+                    ticket.title = Some(issue.summary);
+                    ticket.status = issue.status;
+                }
+                Err(e) => tracing::warn!("Could not resolve {}: {}", key, e),
+            }
+        }
+
+        ticket
+    }
+
+    async fn resolve_github(&self, repo: Option<&str>, number: u32) -> RelatedTicket {
+        let mut ticket = RelatedTicket {
+            tracker: "github".to_string(),
+            key: match repo {
+                Some(repo) => format!("{}#{}", repo, number),
+                None => format!("#{}", number),
+            },
+            url: repo.map(|r| format!("https://github.com/{}/issues/{}", r, number)),
+            title: None,
+            status: None,
+        };
+
+        if let (Some(github), Some(repo)) = (&self.github, repo) {
+            match github.fetch_issue(repo, number).await {
+                Ok(issue) => {
+                    ticket.url = Some(issue.html_url);
+                    ticket.title = Some(issue.title);
+                    ticket.status = Some(issue.state);
+                }
+                Err(e) => tracing::warn!("Could not resolve {}: {}", ticket.key, e),
+            }
+        }
+
+        ticket
+    }
+
+    /// Post the document location to each referenced Jira ticket
+    pub async fn comment_on_jira(&self, tickets: &[RelatedTicket], doc_location: &str) {
+        if !self.config.comment_on_jira {
+            return;
+        }
+        let Some(jira) = &self.jira else {
+            tracing::warn!("issues.comment_on_jira is set but Jira credentials are missing");
+            return;
+        };
+
+        let body = format!("Documentation generated by ktme: {}", doc_location);
+        for ticket in tickets.iter().filter(|t| t.tracker == "jira") {
+            match jira.add_comment(&ticket.key, &body).await {
+                Ok(()) => tracing::info!("Commented on {}", ticket.key),
+                Err(e) => tracing::warn!("Failed to comment on {}: {}", ticket.key, e),
+            }
+        }
+    }
+}
+
+/// Markdown "Related tickets" section; empty when there are no tickets
+pub fn render_related_tickets(tickets: &[RelatedTicket]) -> String {
+    if tickets.is_empty() {
+        return String::new();
+    }
+
+    let mut section = String::from("\n\n## Related tickets\n\n");
+    for ticket in tickets {
+        let label = match &ticket.url {
+            Some(url) => format!("[{}]({})", ticket.key, url),
+            None => ticket.key.clone(),
+        };
+        let mut line = format!("- {}", label);
+        if let Some(title) = &ticket.title {
+            line.push_str(&format!(" — {}", title));
+        }
+        if let Some(status) = &ticket.status {
+            line.push_str(&format!(" ({})", status));
+        }
+        section.push_str(&line);
+        section.push('\n');
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_jira_and_github_refs() {
+        let config = IssuesConfig::default();
+        let refs = detect_issue_refs(
+            "PAY-42: handle UTF-8 names (fixes #7, see FreePeak/ktme#9, PAY-42)",
+            &config,
+        );
+        assert_eq!(
+            refs,
+            vec![
+                IssueRef::Jira("PAY-42".to_string()),
+                IssueRef::GitHub {
+                    repo: None,
+                    number: 7
+                },
+                IssueRef::GitHub {
+                    repo: Some("FreePeak/ktme".to_string()),
+                    number: 9
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_respects_project_keys() {
+        let config = IssuesConfig {
+            jira_project_keys: vec!["OPS".to_string()],
+            ..Default::default()
+        };
+        let refs = detect_issue_refs("OPS-1 and PAY-2", &config);
+        assert_eq!(refs, vec![IssueRef::Jira("OPS-1".to_string())]);
+    }
+
+    #[test]
+    fn test_render_related_tickets() {
+        assert!(render_related_tickets(&[]).is_empty());
+
+        let section = render_related_tickets(&[
+            RelatedTicket {
+                tracker: "jira".to_string(),
+                key: "PAY-42".to_string(),
+                url: Some("https://jira.example.com/browse/PAY-42".to_string()),
+                title: Some("Retry failed payments".to_string()),
+                status: Some("In Review".to_string()),
+            },
+            RelatedTicket {
+                tracker: "github".to_string(),
+                key: "#7".to_string(),
+                url: None,
+                title: None,
+                status: None,
+            },
+        ]);
+        assert!(section.contains("## Related tickets"));
+        assert!(section.contains(
+            "- [PAY-42](https://jira.example.com/browse/PAY-42) — Retry failed payments (In Review)"
+        ));
+        assert!(section.contains("- #7\n"));
+    }
+}
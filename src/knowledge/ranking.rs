@@ -0,0 +1,181 @@
+//! Relevance ranking of features: scores decay while a feature goes unused
+//! and grow with its retrievals by searches and MCP tools, so search results
+//! favor the areas agents actually rely on.
+
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::Storage;
+use crate::storage::models::FeatureUsage;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// How relevance scores move at each ranking
+#[derive(Debug, Clone, Copy)]
+pub struct RankingPolicy {
+    /// Days for the score of an unused feature to halve
+    pub half_life_days: f64,
+    /// Share of the distance to a score of 1 closed by each retrieval
+    pub usage_boost: f64,
+}
+
+impl Default for RankingPolicy {
+    fn default() -> Self {
+        Self {
+            half_life_days: 30.0,
+            usage_boost: 0.1,
+        }
+    }
+}
+
+/// New relevance score of a feature
+#[derive(Debug, Clone, Serialize)]
+pub struct RankChange {
+    pub feature_id: String,
+    pub previous: f64,
+    pub score: f64,
+    /// Retrievals since the previous ranking
+    pub uses: i64,
+}
+
+impl RankingPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if self.half_life_days.is_nan() || self.half_life_days <= 0.0 {
+            return Err(KtmeError::InvalidInput(format!(
+                "Half-life must be a positive number of days, got {}",
+                self.half_life_days
+            )));
+        }
+        if !(0.0..1.0).contains(&self.usage_boost) {
+            return Err(KtmeError::InvalidInput(format!(
+                "Usage boost must be at least 0 and below 1, got {}",
+                self.usage_boost
+            )));
+        }
+        Ok(())
+    }
+
+    /// Score of a feature at `now`: its score decayed over the time since it
+    /// was last ranked, then moved towards 1 by each retrieval since. Scores
+    /// stay between 0 and 1.
+    pub fn score(&self, usage: &FeatureUsage, now: DateTime<Utc>) -> f64 {
+        let days = (now - usage.ranked_since).num_seconds().max(0) as f64 / 86_400.0;
+        let decayed =
+            usage.relevance_score.clamp(0.0, 1.0) * 0.5f64.powf(days / self.half_life_days);
+        let uses = usage.usage_count.clamp(0, i32::MAX as i64) as i32;
+        1.0 - (1.0 - decayed) * (1.0 - self.usage_boost).powi(uses)
+    }
+}
+
+/// Rank every feature of the workspace at `now`, storing the new scores
+/// unless `dry_run` is set. Changes are returned highest score first.
+pub fn rank(
+    storage: &dyn Storage,
+    policy: &RankingPolicy,
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<Vec<RankChange>> {
+    policy.validate()?;
+
+    let mut changes = Vec::new();
+    for usage in storage.feature_usage()? {
+        let score = policy.score(&usage, now);
+        if !dry_run {
+            storage.apply_feature_ranking(&usage.feature_id, score, usage.usage_count, now)?;
+        }
+        changes.push(RankChange {
+            feature_id: usage.feature_id,
+            previous: usage.relevance_score,
+            score,
+            uses: usage.usage_count,
+        });
+    }
+    changes.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
+    use crate::storage::models::FeatureType;
+    use chrono::Duration;
+
+    #[test]
+    fn test_score_decays_unused_and_boosts_used_features() {
+        let policy = RankingPolicy::default();
+        let now = Utc::now();
+        let usage = |score: f64, uses: i64, days: i64| FeatureUsage {
+            feature_id: "f".to_string(),
+            relevance_score: score,
+            usage_count: uses,
+            last_used_at: None,
+            ranked_since: now - Duration::days(days),
+        };
+
+        assert!((policy.score(&usage(0.8, 0, 30), now) - 0.4).abs() < 1e-9);
+        assert!((policy.score(&usage(0.8, 0, 0), now) - 0.8).abs() < 1e-9);
+        // One retrieval closes a tenth of the distance to 1
+        assert!((policy.score(&usage(0.0, 1, 0), now) - 0.1).abs() < 1e-9);
+        assert!(policy.score(&usage(0.4, 5, 30), now) > policy.score(&usage(0.8, 0, 30), now));
+        assert!(policy.score(&usage(1.0, 1000, 0), now) <= 1.0);
+
+        assert!(RankingPolicy {
+            half_life_days: 0.0,
+            ..policy
+        }
+        .validate()
+        .is_err());
+        assert!(RankingPolicy {
+            usage_boost: 1.0,
+            ..policy
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_rank_applies_scores_and_clears_counted_usage() {
+        let storage = SqliteStorage::new(Database::in_memory().unwrap());
+        let service = storage.create_service("shop", None, None).unwrap();
+        for id in ["checkout", "legacy"] {
+            storage
+                .create_feature(
+                    id,
+                    service.id,
+                    id,
+                    None,
+                    FeatureType::Api,
+                    Vec::new(),
+                    serde_json::json!({}),
+                )
+                .unwrap();
+            storage.update_feature_relevance(id, 0.5).unwrap();
+        }
+        storage
+            .record_feature_usage(&["checkout", "checkout"])
+            .unwrap();
+
+        let later = Utc::now() + Duration::days(30);
+        let preview = rank(&storage, &RankingPolicy::default(), later, true).unwrap();
+        assert_eq!(storage.feature_usage().unwrap()[0].usage_count, 2);
+        assert_eq!(preview[0].feature_id, "checkout");
+
+        let changes = rank(&storage, &RankingPolicy::default(), later, false).unwrap();
+        let checkout = &changes[0];
+        assert_eq!(
+            (checkout.feature_id.as_str(), checkout.uses),
+            ("checkout", 2)
+        );
+        let legacy = &changes[1];
+        assert!((legacy.score - 0.25).abs() < 1e-3);
+        assert!(checkout.score > legacy.score);
+
+        let stored = storage.get_feature("legacy").unwrap().unwrap();
+        assert!((stored.relevance_score - legacy.score).abs() < 1e-9);
+        let usage = storage.feature_usage().unwrap();
+        assert!(usage.iter().all(|u| u.usage_count == 0));
+        // Ranking again at the same time changes nothing
+        let again = rank(&storage, &RankingPolicy::default(), later, false).unwrap();
+        assert!((again[1].score - legacy.score).abs() < 1e-6);
+    }
+}
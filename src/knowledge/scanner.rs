@@ -211,6 +211,55 @@ impl CodebaseScanner {
     }
 }
 
+/// Name of the test case declared on `line` of a file with extension `ext`:
+/// Rust `fn test_*` (or any function after a test attribute, see
+/// `after_test_attribute`), Go `func Test*`, Python `def test_*` and
+/// JavaScript/TypeScript `it(...)`/`test(...)`.
+pub fn extract_test_name(ext: &str, line: &str, after_test_attribute: bool) -> Option<String> {
+    let trimmed = line.trim();
+    let identifier = |rest: &str| -> Option<String> {
+        let name: String = rest
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        (!name.is_empty()).then_some(name)
+    };
+
+    match ext {
+        "rs" => {
+            let rest = trimmed
+                .trim_start_matches("pub ")
+                .trim_start_matches("async ")
+                .strip_prefix("fn ")?;
+            let name = identifier(rest)?;
+            (after_test_attribute || name.starts_with("test_")).then_some(name)
+        }
+        "go" => identifier(trimmed.strip_prefix("func ")?).filter(|n| n.starts_with("Test")),
+        "py" => identifier(trimmed.trim_start_matches("async ").strip_prefix("def ")?)
+            .filter(|n| n.starts_with("test")),
+        "ts" | "tsx" | "js" | "jsx" => {
+            let rest = ["it(", "test(", "it.only(", "test.only("]
+                .iter()
+                .find_map(|prefix| trimmed.strip_prefix(prefix))?;
+            let quote = rest
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '\'' | '"' | '`'))?;
+            let name = rest[1..].split(quote).next()?;
+            (!name.is_empty()).then(|| name.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Whether `line` is a Rust test attribute such as `#[test]` or
+/// `#[tokio::test]`
+pub fn is_test_attribute(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with("#[") && trimmed.trim_end_matches(']').ends_with("test")
+        || trimmed.starts_with("#[tokio::test")
+}
+
 // ---- helpers ----
 
 fn extract_rust_name(line: &str) -> Option<String> {
@@ -359,6 +408,36 @@ mod tests {
         assert_eq!(classify_by_name("AppConfig"), FeatureType::Config);
     }
 
+    #[test]
+    fn test_extract_test_name() {
+        assert_eq!(
+            extract_test_name("rs", "    fn test_total() {", false).as_deref(),
+            Some("test_total")
+        );
+        assert_eq!(
+            extract_test_name("rs", "async fn rounds_up() {", true).as_deref(),
+            Some("rounds_up")
+        );
+        assert_eq!(extract_test_name("rs", "fn helper() {", false), None);
+        assert_eq!(
+            extract_test_name("go", "func TestTotal(t *testing.T) {", false).as_deref(),
+            Some("TestTotal")
+        );
+        assert_eq!(
+            extract_test_name("py", "    def test_total(self):", false).as_deref(),
+            Some("test_total")
+        );
+        assert_eq!(
+            extract_test_name("ts", "  it('sums the lines', () => {", false).as_deref(),
+            Some("sums the lines")
+        );
+        assert!(is_test_attribute("    #[test]"));
+        assert!(is_test_attribute(
+            "#[tokio::test(flavor = \"multi_thread\")]"
+        ));
+        assert!(!is_test_attribute("#[derive(Debug)]"));
+    }
+
     #[test]
     fn test_to_feature_name() {
         assert_eq!(to_feature_name("UserRepository"), "User Repository");
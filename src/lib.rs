@@ -3,16 +3,29 @@
 //! Rust-based CLI tool and MCP server for automated documentation generation.
 
 pub mod ai;
+pub mod analysis;
 pub mod cli;
 pub mod config;
 pub mod doc;
+pub mod engine;
 pub mod error;
 pub mod git;
+pub mod http;
+pub mod issues;
 pub mod knowledge;
+pub mod logging;
 pub mod mcp;
+pub mod metrics;
+pub mod plugins;
+pub mod release;
 pub mod service_detector;
+pub mod shutdown;
 pub mod skill;
 pub mod storage;
+pub mod translation;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod workflow;
 
 pub use error::{KtmeError, Result};
 pub use skill::{Action, Skill, SkillConfig, SkillExecutor, SkillMatcher, Trigger};
@@ -26,3 +39,16 @@ pub enum InitMode {
     Sync,
     Research,
 }
+
+/// Scaffold flavor of the `docs/` folder created by `ktme init`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum InitTemplate {
+    /// README and changelog only
+    Minimal,
+    /// Architecture, API endpoints and changelog of a deployable service
+    Service,
+    /// Public API, usage guides and examples of a library
+    Library,
+    /// Several services with their architecture, operations and runbooks
+    Platform,
+}
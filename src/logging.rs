@@ -0,0 +1,188 @@
+//! Structured JSON log files
+//!
+//! Every command appends JSON lines to a daily-rotated file under
+//! `~/.config/ktme/logs/`, independent of console logging, so MCP stdio and
+//! daemon runs leave a trace. `ktme logs` reads them back.
+
+use crate::config::{Config, GeneralConfig};
+use crate::error::{KtmeError, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// Log files are named `ktme.<date>.log`
+const LOG_FILE_PREFIX: &str = "ktme";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Directory holding the JSON log files
+pub fn log_dir() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("logs"))
+}
+
+/// Non-blocking writer for the rotating log file. The guard flushes pending
+/// lines when dropped and must live until the process exits.
+pub fn file_writer(config: &GeneralConfig) -> Result<(NonBlocking, WorkerGuard)> {
+    let dir = log_dir()?;
+    fs::create_dir_all(&dir)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(config.log_max_files.max(1))
+        .build(&dir)
+        .map_err(|e| KtmeError::Config(format!("Cannot open log directory: {}", e)))?;
+
+    Ok(tracing_appender::non_blocking(appender))
+}
+
+/// One line of the JSON log
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    /// Structured fields other than the message
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LogEntry {
+    /// Parse a line written by the JSON formatter; `None` for anything else
+    pub fn parse(line: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let timestamp = DateTime::parse_from_rfc3339(value.get("timestamp")?.as_str()?)
+            .ok()?
+            .with_timezone(&Utc);
+
+        let mut fields = value
+            .get("fields")
+            .and_then(|f| f.as_object())
+            .cloned()
+            .unwrap_or_default();
+        let message = match fields.remove("message") {
+            Some(serde_json::Value::String(message)) => message,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+
+        Some(Self {
+            timestamp,
+            level: value.get("level")?.as_str()?.to_string(),
+            target: value
+                .get("target")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            message,
+            fields,
+        })
+    }
+
+    /// Whether the entry is at `level` or more severe
+    pub fn at_least(&self, level: tracing::Level) -> bool {
+        self.level
+            .parse::<tracing::Level>()
+            .map(|l| l <= level)
+            .unwrap_or(true)
+    }
+
+    /// Human-readable single line
+    pub fn render(&self) -> String {
+        let mut line = format!(
+            "{} {:>5} {}: {}",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.level,
+            self.target,
+            self.message
+        );
+        for (key, value) in &self.fields {
+            match value {
+                serde_json::Value::String(s) => line.push_str(&format!(" {}={}", key, s)),
+                other => line.push_str(&format!(" {}={}", key, other)),
+            }
+        }
+        line
+    }
+}
+
+/// Log files in `dir`, oldest first
+pub fn log_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(LOG_FILE_PREFIX) && n.ends_with(LOG_FILE_SUFFIX))
+        })
+        .collect();
+    // Date-stamped names sort chronologically
+    files.sort();
+    Ok(files)
+}
+
+/// Parse a relative duration such as `30s`, `15m`, `1h` or `7d`
+pub fn parse_since(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let invalid = || {
+        KtmeError::InvalidInput(format!(
+            "Invalid duration '{}': expected a number followed by s, m, h or d",
+            value
+        ))
+    };
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+
+    match unit {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("30s").unwrap(), Duration::seconds(30));
+        assert_eq!(parse_since("15m").unwrap(), Duration::minutes(15));
+        assert_eq!(parse_since("1h").unwrap(), Duration::hours(1));
+        assert_eq!(parse_since("7d").unwrap(), Duration::days(7));
+        assert!(parse_since("h").is_err());
+        assert!(parse_since("10").is_err());
+        assert!(parse_since("2w").is_err());
+    }
+
+    #[test]
+    fn test_parse_log_entry() {
+        let line = r#"{"timestamp":"2026-10-17T08:30:00.123456Z","level":"WARN","fields":{"message":"Retrying request","attempt":2},"target":"ktme::ai::client"}"#;
+        let entry = LogEntry::parse(line).unwrap();
+
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.message, "Retrying request");
+        assert!(entry.at_least(tracing::Level::WARN));
+        assert!(!entry.at_least(tracing::Level::ERROR));
+        assert_eq!(
+            entry.render(),
+            "2026-10-17 08:30:00  WARN ktme::ai::client: Retrying request attempt=2"
+        );
+
+        assert!(LogEntry::parse("not json").is_none());
+    }
+}
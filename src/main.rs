@@ -1,28 +1,45 @@
-use clap::{Parser, Subcommand};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::engine::ArgValueCompleter;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 mod ai;
 mod cli;
 mod config;
 mod doc;
+mod engine;
 mod error;
 mod git;
+mod http;
+mod issues;
 mod knowledge;
+mod logging;
 mod mcp;
+mod metrics;
+mod plugins;
+mod release;
 mod service_detector;
+mod shutdown;
 mod storage;
+mod translation;
+#[cfg(feature = "tui")]
+mod tui;
+mod workflow;
 
 mod analysis;
 mod enhance;
 mod research;
 
+use cli::commands::completions::complete_service;
 use error::Result;
-use ktme::InitMode;
+use ktme::{InitMode, InitTemplate};
 
 #[derive(Parser)]
 #[command(name = "ktme")]
 #[command(author, version, about = "Knowledge Transfer Me - Automated documentation generation", long_about = None)]
+#[command(after_long_help = error::EXIT_CODES_HELP)]
 struct Cli {
+    /// Debug logging, and the underlying causes of a failure
     #[arg(short, long, global = true)]
     verbose: bool,
 
@@ -32,6 +49,32 @@ struct Cli {
     #[arg(long, global = true)]
     config: Option<String>,
 
+    /// Workspace to operate in (defaults to storage.workspace from config)
+    #[arg(long, global = true)]
+    workspace: Option<String>,
+
+    /// Print machine-readable JSON instead of human-readable output
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Print stage durations and AI usage to stderr when the command ends
+    #[arg(long, global = true)]
+    timings: bool,
+
+    /// Make no network calls: use the mock (or a local) AI provider and
+    /// publish to markdown only (defaults to general.offline)
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Save every AI response in DIR, keyed by the prompt hash
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<String>,
+
+    /// Answer AI prompts from the responses recorded in DIR instead of
+    /// calling the provider
+    #[arg(long, global = true, value_name = "DIR")]
+    replay: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -49,11 +92,45 @@ enum Commands {
         #[arg(long, group = "source")]
         pr: Option<u32>,
 
+        /// Hosting service of the pull request: github, gitlab or bitbucket
+        /// (detected from the origin remote by default)
         #[arg(long, requires = "pr")]
         provider: Option<String>,
 
+        /// Remote repository URL to fetch the commit from, instead of the
+        /// current working copy
+        #[arg(long, requires = "commit", value_name = "URL")]
+        repo: Option<String>,
+
+        /// Local repositories to read the commit or staged changes from,
+        /// merged into one diff with a section per repository
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "PATH",
+            conflicts_with_all = ["pr", "repo", "spill"]
+        )]
+        repos: Vec<String>,
+
+        /// Write to a file instead of stdout
         #[arg(short, long)]
         output: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = cli::commands::extract::ExtractFormat::Json)]
+        format: cli::commands::extract::ExtractFormat,
+
+        /// Pretty-print JSON on stdout (files are always pretty-printed)
+        #[arg(long)]
+        pretty: bool,
+
+        /// Truncate each file's diff beyond this many bytes (0 keeps
+        /// everything; defaults to git.max_file_diff_bytes)
+        #[arg(long, value_name = "BYTES")]
+        max_file_diff: Option<usize>,
+
+        /// Write the full diff of truncated files to this file
+        #[arg(long, value_name = "FILE")]
+        spill: Option<String>,
     },
 
     /// Generate documentation from code changes
@@ -67,23 +144,264 @@ enum Commands {
         #[arg(long, group = "source")]
         pr: Option<u32>,
 
+        /// Hosting service of the pull request: github, gitlab or bitbucket
+        /// (detected from the origin remote by default)
+        #[arg(long, requires = "pr")]
+        provider: Option<String>,
+
         #[arg(long, group = "source")]
         staged: bool,
 
-        #[arg(long, required = true)]
-        service: String,
+        /// Commit range `start..end` documented as one change, e.g. from the
+        /// start of an incident to its fix
+        #[arg(long, group = "source")]
+        range: Option<String>,
+
+        /// Tag, branch, commit or date (YYYY-MM-DD) whose repository state
+        /// is documented in full, labeled with its version; read from git
+        /// without touching the working directory
+        #[arg(long, group = "source")]
+        at: Option<String>,
+
+        /// Repositories to read the changes from, merged into one diff with
+        /// a section per repository (defaults to the `repositories` setting
+        /// of the service)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_name = "PATH",
+            conflicts_with_all = ["input", "pr"]
+        )]
+        repos: Vec<String>,
 
+        /// Service to document; repeat or comma-separate to generate for
+        /// several services concurrently, or `auto` to resolve the services
+        /// from their mapped paths and CODEOWNERS (detected from the current
+        /// directory when omitted)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            add = ArgValueCompleter::new(complete_service)
+        )]
+        service: Vec<String>,
+
+        /// Use the detected service without asking when --service is omitted
+        #[arg(long, short = 'y')]
+        yes: bool,
+
+        /// Document type: changelog, api-doc, readme, commit-message, general,
+        /// onboarding (a guide for new engineers; defaults to the latest
+        /// commit when no source is given), postmortem (use with --range),
+        /// release-notes (see --audience), security-review (see --audit) or
+        /// test-plan (test cases changed and source files left without test
+        /// changes)
         #[arg(long)]
         r#type: Option<String>,
 
         #[arg(long)]
         format: Option<String>,
 
+        /// Output file; must contain {service} when generating for several
+        /// services
         #[arg(long)]
         output: Option<String>,
 
         #[arg(long)]
         template: Option<String>,
+
+        /// Services generated at the same time (defaults to general.parallelism)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Regenerate even when the diff and template are unchanged since
+        /// the last run
+        #[arg(long)]
+        force: bool,
+
+        /// Hold the documentation in the review queue (`ktme queue`)
+        /// instead of publishing it
+        #[arg(long)]
+        queue: bool,
+
+        /// Value of a prompt template variable; repeat for several
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+
+        /// Audience of release notes
+        #[arg(long, value_enum, default_value_t)]
+        audience: doc::personas::release_notes::Audience,
+
+        /// Run `cargo audit`/`npm audit` in the service directory for
+        /// security reviews
+        #[arg(long)]
+        audit: bool,
+
+        /// Also publish to the service's mapped locations matching this
+        /// provider type, location or part of one, e.g. `Releases`
+        #[arg(long, value_name = "TARGET")]
+        publish: Option<String>,
+
+        /// Give up when the run takes longer than this, e.g. 120s or 10m
+        /// (defaults to timeouts.operation)
+        #[arg(long, value_name = "DURATION")]
+        timeout: Option<String>,
+
+        /// Wait for another ktme process publishing the same service to
+        /// finish instead of failing
+        #[arg(long)]
+        wait: bool,
+
+        /// Commit the documentation files written to a git repository,
+        /// linking the source commit in the message
+        #[arg(long)]
+        commit_docs: bool,
+
+        /// Branch created from HEAD for the documentation commit, e.g.
+        /// docs/update-payments
+        #[arg(long, requires = "commit_docs")]
+        branch: Option<String>,
+
+        /// Push the documentation commit to `origin`
+        #[arg(long, requires = "commit_docs")]
+        push: bool,
+
+        /// Open a pull request (GitHub) or merge request (GitLab) of the
+        /// pushed branch
+        #[arg(long, requires_all = ["push", "branch"])]
+        open_pr: bool,
+
+        /// Describe code in prose and pseudocode instead of quoting it, and
+        /// replace the code blocks left with structure-only descriptions,
+        /// e.g. for docs shared with vendors or in public demos
+        #[arg(long)]
+        redact_code: bool,
+    },
+
+    /// Generate documentation for the history of a service, one document per
+    /// commit or per week of a commit range, oldest first
+    Backfill {
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: String,
+
+        /// Commit range `start..end` to document, e.g. v1.0.0..v2.0.0
+        #[arg(long)]
+        range: String,
+
+        /// Generate one document per commit or per week of commits
+        #[arg(long, value_enum, default_value_t)]
+        per: cli::commands::backfill::BackfillPeriod,
+
+        /// Document type (see `generate --type`)
+        #[arg(long)]
+        r#type: Option<String>,
+
+        /// Prompt template file
+        #[arg(long)]
+        template: Option<String>,
+
+        /// Write each document to this file; `{bucket}` is replaced by the
+        /// short commit id or the week, e.g. docs/history/{bucket}.md
+        #[arg(long)]
+        output: Option<String>,
+
+        /// Publish each document, in order, to the service's mapped
+        /// locations matching this provider type, location or part of one
+        #[arg(long, value_name = "TARGET")]
+        publish: Option<String>,
+
+        /// Hold the documents in the review queue (`ktme queue`) instead of
+        /// publishing them
+        #[arg(long)]
+        queue: bool,
+
+        /// Regenerate even when a document's diff and template are unchanged
+        /// since the last run
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Extract, generate, lint, publish and notify in one step, as the
+    /// service's `[policies.<service>]` entry says (the same workflow as the
+    /// MCP automated_documentation_workflow tool)
+    Run {
+        /// Service to document, or `auto` for every service owning some of
+        /// the changed files (detected from the current directory when
+        /// omitted)
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Use the detected service without asking when --service is omitted
+        #[arg(long, short = 'y')]
+        yes: bool,
+
+        /// Changes to document: `staged`, a commit, branch or tag, or a file
+        /// written by `ktme extract`
+        #[arg(long, default_value = "staged")]
+        source: String,
+
+        /// Give up when the run takes longer than this, e.g. 120s or 10m
+        /// (defaults to timeouts.operation)
+        #[arg(long, value_name = "DURATION")]
+        timeout: Option<String>,
+
+        /// Wait for another ktme process publishing the same service to
+        /// finish instead of failing
+        #[arg(long)]
+        wait: bool,
+    },
+
+    /// Translate the documents of a service into their translated
+    /// counterparts (added with `ktme mapping add --translation-of`),
+    /// only the sections that changed since the last translation
+    Translate {
+        /// Service whose documents to translate (detected from the current
+        /// directory when omitted)
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Use the detected service without asking when --service is omitted
+        #[arg(long, short = 'y')]
+        yes: bool,
+
+        /// Only translate the mapped document at this location
+        #[arg(long)]
+        source: Option<String>,
+
+        /// Translate every section again
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Write mapped documents back through their providers, e.g. after
+    /// changing [notices] or templates, so that every page picks up the new
+    /// formatting
+    Republish {
+        /// Republish the documents of every mapped service
+        #[arg(long, required_unless_present = "service")]
+        all: bool,
+
+        /// Only republish the documents of this service
+        #[arg(long, conflicts_with = "all", add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Only republish documents of this provider type, e.g. confluence
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Largest number of documents written to remote providers per
+        /// period, e.g. 10/min, 1/s or 100/h
+        #[arg(long, default_value = "10/min")]
+        rate: String,
+
+        /// List the documents without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Manage the cache of extracted commit diffs
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
     },
 
     /// Update existing documentation
@@ -97,14 +415,81 @@ enum Commands {
         #[arg(long, group = "source")]
         staged: bool,
 
-        #[arg(long, required = true)]
-        service: String,
+        /// Service to update, or `auto` to update every service owning some
+        /// of the changed files (detected from the current directory when
+        /// omitted)
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Use the detected service without asking when --service is omitted
+        #[arg(long, short = 'y')]
+        yes: bool,
+
+        /// Document to update instead of the mapped ones; only needed when
+        /// the service has no mapping
+        #[arg(long, value_name = "LOCATION")]
+        doc: Option<String>,
 
+        /// Provider of --doc, e.g. notion (by default confluence for page
+        /// URLs and IDs, markdown otherwise)
+        #[arg(long, requires = "doc")]
+        provider: Option<String>,
+
+        /// Section to update; overrides the mapped section and the routing
+        /// by documentation type
         #[arg(long)]
         section: Option<String>,
 
+        /// Documentation type deciding where content is routed (inferred
+        /// from each location's file name by default)
+        #[arg(long, value_enum)]
+        r#type: Option<doc::sections::DocKind>,
+
+        /// How content is combined with the target section (default:
+        /// replace for --section and API docs, append otherwise)
+        #[arg(long, value_enum)]
+        mode: Option<doc::sections::UpdateMode>,
+
         #[arg(long)]
         dry_run: bool,
+
+        /// Update changelogs that already document the source commit or the
+        /// generated content; entries they hold are still not repeated
+        #[arg(long)]
+        force: bool,
+
+        /// Locations published at the same time (defaults to general.parallelism)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Give up when the run takes longer than this, e.g. 120s or 10m
+        /// (defaults to timeouts.operation)
+        #[arg(long, value_name = "DURATION")]
+        timeout: Option<String>,
+
+        /// Wait for another ktme process publishing the same service to
+        /// finish instead of failing
+        #[arg(long)]
+        wait: bool,
+
+        /// Commit the documentation files written to a git repository,
+        /// linking the source commit in the message
+        #[arg(long)]
+        commit_docs: bool,
+
+        /// Branch created from HEAD for the documentation commit, e.g.
+        /// docs/update-payments
+        #[arg(long, requires = "commit_docs")]
+        branch: Option<String>,
+
+        /// Push the documentation commit to `origin`
+        #[arg(long, requires = "commit_docs")]
+        push: bool,
+
+        /// Open a pull request (GitHub) or merge request (GitLab) of the
+        /// pushed branch
+        #[arg(long, requires_all = ["push", "branch"])]
+        open_pr: bool,
     },
 
     /// Manage service-to-document mappings
@@ -119,6 +504,12 @@ enum Commands {
         command: McpCommands,
     },
 
+    /// Show, pin or unpin the service detected for the current directory
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommands,
+    },
+
     /// Search services by features or keywords
     Search {
         query: String,
@@ -134,12 +525,107 @@ enum Commands {
         command: ConfigCommands,
     },
 
+    /// Provider, AI and WASM extension plugins
+    Plugins {
+        #[command(subcommand)]
+        command: PluginsCommands,
+    },
+
+    /// Document providers saved in the database
+    #[command(alias = "provider")]
+    Providers {
+        #[command(subcommand)]
+        command: ProvidersCommands,
+    },
+
+    /// Sign in to documentation providers with OAuth
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommands,
+    },
+
+    /// Review generated documentation held by `generate --queue`
+    Queue {
+        #[command(subcommand)]
+        command: QueueCommands,
+    },
+
+    /// List and resume multi-step operations such as backfills
+    Jobs {
+        #[command(subcommand)]
+        command: JobsCommands,
+    },
+
+    /// Work with prompt templates
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+
+    /// Export the knowledge base
+    Export {
+        #[command(subcommand)]
+        command: ExportCommands,
+    },
+
+    /// Manage the database schema
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+
+    /// Link the features of the knowledge graph and search them
+    Feature {
+        #[command(subcommand)]
+        command: FeatureCommands,
+    },
+
+    /// Import documentation written before ktme into the knowledge graph
+    Import {
+        #[command(subcommand)]
+        command: ImportCommands,
+    },
+
+    /// Answer a question from the documentation, citing its sources
+    Ask {
+        /// The question, e.g. "how do payment retries work?"
+        question: String,
+
+        /// Only use the documentation of this service
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Documentation excerpts to quote to the AI provider
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+
+    /// Find documents and sections of a service with highly similar content
+    Dedupe {
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: String,
+
+        /// Minimum similarity (0 to 1) of a reported pair
+        #[arg(long, default_value_t = 0.8)]
+        threshold: f64,
+    },
+
+    /// Export and inspect the prompts sent to the AI provider
+    Prompts {
+        #[command(subcommand)]
+        command: PromptsCommands,
+    },
+
     /// Initialize project documentation and knowledge graph
     Init {
         #[arg(long, help = "Project directory path (defaults to current directory)")]
         path: Option<String>,
 
-        #[arg(long, help = "Service name (auto-detected if not provided)")]
+        #[arg(
+            long,
+            help = "Service name (auto-detected if not provided)",
+            add = ArgValueCompleter::new(complete_service)
+        )]
         service: Option<String>,
 
         #[arg(long, help = "Force re-initialization even if already initialized")]
@@ -148,6 +634,26 @@ enum Commands {
         #[arg(long, value_enum, default_value = "fresh", help = "Init mode")]
         mode: InitMode,
 
+        #[arg(
+            long,
+            value_enum,
+            default_value = "service",
+            help = "Scaffold flavor of the docs/ folder (fresh mode)"
+        )]
+        template: InitTemplate,
+
+        #[arg(
+            long,
+            help = "Map the service to the generated changelog and API docs and record its path"
+        )]
+        with_mapping: bool,
+
+        #[arg(
+            long,
+            help = "Install a post-commit hook that runs 'ktme update' for the service"
+        )]
+        with_hooks: bool,
+
         #[arg(long, help = "Preview changes without applying")]
         dry_run: bool,
 
@@ -155,9 +661,22 @@ enum Commands {
         output: Option<String>,
     },
 
+    /// Generate an index of all services and their documentation
+    Index {
+        #[arg(long, help = "Write the index to this file, e.g. docs/INDEX.md")]
+        output: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "PAGE",
+            help = "Publish the index as this Confluence page (page ID or URL)"
+        )]
+        confluence: Option<String>,
+    },
+
     /// Display the knowledge tree map for services and features
     Tree {
-        #[arg(long, help = "Filter to a specific service name")]
+        #[arg(long, help = "Filter to a specific service name", add = ArgValueCompleter::new(complete_service))]
         service: Option<String>,
 
         #[arg(
@@ -170,6 +689,55 @@ enum Commands {
         #[arg(long, help = "Also print a Mermaid flowchart")]
         mermaid: bool,
     },
+
+    /// Update ktme to the latest release, verifying the download against
+    /// the release checksums
+    SelfUpdate {
+        /// Only report whether a newer release exists, e.g. in CI images
+        #[arg(long)]
+        check_only: bool,
+
+        /// Replace the binary even when Homebrew, npm or cargo installed it
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+
+        #[arg(
+            long = "static",
+            help = "Self-contained script without service name completion"
+        )]
+        static_script: bool,
+    },
+
+    /// Generate the ktme man page
+    Man {
+        #[arg(short, long, help = "Write to a file instead of stdout")]
+        output: Option<String>,
+    },
+
+    /// Show the JSON log file written by every command
+    Logs {
+        #[arg(short, long, help = "Keep printing new log lines")]
+        follow: bool,
+
+        #[arg(long, help = "Only entries newer than this, e.g. 30m, 1h, 2d")]
+        since: Option<String>,
+
+        #[arg(long, help = "Minimum level: error, warn, info, debug or trace")]
+        level: Option<String>,
+
+        #[arg(short = 'n', long, help = "Only the last N entries")]
+        lines: Option<usize>,
+    },
+
+    /// Interactive dashboard of services, generation history and logs
+    #[cfg(feature = "tui")]
+    Tui,
 }
 
 #[derive(Subcommand)]
@@ -182,19 +750,64 @@ enum MappingCommands {
         url: Option<String>,
         #[arg(long, group = "location")]
         file: Option<String>,
+        /// Section of the document updates go to, e.g. `Changelog`
+        #[arg(long)]
+        section: Option<String>,
+        /// Generation setting for the service, e.g. `--set model=gpt-4o`
+        /// (doc_type, template, model, language, prompt_pack, publish, include,
+        /// exclude, repositories)
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        settings: Vec<String>,
+        /// Add the document as the translation of this mapped location,
+        /// kept in sync whenever the location is published
+        #[arg(long, value_name = "LOCATION", requires = "language")]
+        translation_of: Option<String>,
+        /// Language of the translation, e.g. `Japanese`
+        #[arg(long, requires = "translation_of")]
+        language: Option<String>,
     },
 
     /// List all service mappings
     List {
-        #[arg(long)]
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
         service: Option<String>,
     },
 
     /// Get mapping for a specific service
-    Get { service: String },
+    Get {
+        #[arg(add = ArgValueCompleter::new(complete_service))]
+        service: String,
+        /// Also show the author, timestamps, version and labels of each
+        /// documentation location, read from its provider
+        #[arg(long)]
+        details: bool,
+    },
 
     /// Remove a service mapping
-    Remove { service: String },
+    Remove {
+        #[arg(add = ArgValueCompleter::new(complete_service))]
+        service: String,
+    },
+
+    /// Rename a service, keeping its mappings, features and history
+    Rename {
+        #[arg(add = ArgValueCompleter::new(complete_service))]
+        old: String,
+        new: String,
+        /// Also replace the name in the titles of the mapped documents
+        #[arg(long)]
+        update_titles: bool,
+    },
+
+    /// Check that mapped files and pages exist, their providers are
+    /// configured and service paths are still on disk
+    Validate {
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+        /// Remove the locations of documents that no longer exist
+        #[arg(long)]
+        fix: bool,
+    },
 
     /// Discover services automatically
     Discover {
@@ -206,6 +819,26 @@ enum MappingCommands {
     Edit,
 }
 
+#[derive(Subcommand)]
+enum ServiceCommands {
+    /// Show the service of the current directory and the other candidates
+    Detect {
+        /// Detect again instead of reading the detection cache
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Always use this service in the current repository, whatever is
+    /// detected
+    Pin {
+        #[arg(add = ArgValueCompleter::new(complete_service))]
+        name: String,
+    },
+
+    /// Remove the service pinned to the current repository
+    Unpin,
+}
+
 #[derive(Subcommand)]
 enum McpCommands {
     /// Start MCP server
@@ -218,6 +851,16 @@ enum McpCommands {
 
         #[arg(long)]
         stdio: bool,
+
+        /// Only expose these tools (comma-separated), replacing `[mcp]
+        /// enabled_tools`
+        #[arg(long, value_delimiter = ',')]
+        tools: Vec<String>,
+
+        /// Append the JSON-RPC traffic, with secrets and diffs redacted, to
+        /// this file
+        #[arg(long, value_name = "FILE")]
+        trace: Option<String>,
     },
 
     /// Check MCP server status
@@ -225,6 +868,28 @@ enum McpCommands {
 
     /// Stop MCP server
     Stop,
+
+    /// Show the tool calls agents made, newest first
+    Audit {
+        #[arg(
+            long,
+            default_value = "24h",
+            help = "Only calls newer than this, e.g. 30m, 1h, 2d"
+        )]
+        since: String,
+
+        #[arg(long, help = "Only calls of this tool")]
+        tool: Option<String>,
+    },
+
+    /// Print a session captured with `mcp start --trace`
+    Trace {
+        /// The trace file
+        file: String,
+
+        #[arg(long, help = "One line per message, without the messages")]
+        summary: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -238,38 +903,409 @@ enum ConfigCommands {
     /// Set configuration value
     Set { key: String, value: String },
 
-    /// Validate configuration
-    Validate,
+    /// Report unknown keys, settings missing their counterpart and unset
+    /// `${VAR}` references; exits non-zero when there are errors
+    Validate {
+        /// Also connect to Confluence, Jira and the AI provider (sends the
+        /// AI provider a one-word prompt)
+        #[arg(long)]
+        online: bool,
+    },
 }
 
-fn setup_logging(verbose: bool, quiet: bool, is_stdio: bool) {
-    // Skip logging entirely in STDIO mode to avoid JSON parsing issues
-    if is_stdio {
-        return;
-    }
+#[derive(Subcommand)]
+enum PluginsCommands {
+    /// List plugins on PATH and WASM extensions in the plugins directory
+    List,
+}
 
-    let log_level = if quiet {
-        tracing::Level::ERROR
-    } else if verbose {
-        tracing::Level::DEBUG
+#[derive(Subcommand)]
+enum ProvidersCommands {
+    /// Save a provider's configuration, checked against its type
+    Add {
+        /// Provider type: confluence, markdown, notion or a plugin name
+        provider: String,
+
+        /// Configuration as JSON
+        #[arg(long, value_name = "JSON", group = "provider_config")]
+        config: Option<String>,
+
+        /// File holding the configuration as JSON
+        #[arg(long, group = "provider_config")]
+        file: Option<String>,
+
+        /// Make it the default provider
+        #[arg(long)]
+        default: bool,
+    },
+
+    /// List saved providers, secrets hidden
+    List,
+
+    /// Make a saved provider the default
+    SetDefault { provider: String },
+
+    /// Remove a saved provider
+    Remove { provider: String },
+
+    /// Check every saved provider and fail when the default one is down
+    Status {
+        /// Time each health check may take, e.g. 10s (defaults to the
+        /// provider's request timeout)
+        #[arg(long, value_name = "DURATION")]
+        timeout: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthCommands {
+    /// Sign in through the browser and store the tokens
+    Login {
+        #[arg(value_parser = ["confluence"])]
+        provider: String,
+
+        #[arg(long, help = "Print the sign-in URL instead of opening a browser")]
+        no_browser: bool,
+    },
+
+    /// Forget the stored tokens
+    Logout {
+        #[arg(value_parser = ["confluence"])]
+        provider: String,
+    },
+
+    /// Show whether ktme is signed in
+    Status {
+        #[arg(value_parser = ["confluence"])]
+        provider: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueCommands {
+    /// List queued documentation
+    List {
+        /// Status to list
+        #[arg(long, default_value = "pending", value_parser = ["pending", "approved", "rejected", "all"])]
+        status: String,
+    },
+
+    /// Show a queued document
+    Show { id: i64 },
+
+    /// Publish a queued document
+    Approve { id: i64 },
+
+    /// Reject a queued document without publishing it
+    Reject {
+        id: i64,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum JobsCommands {
+    /// List jobs, newest first
+    List {
+        /// Status to list
+        #[arg(long, default_value = "all", value_parser = ["running", "failed", "completed", "all"])]
+        status: String,
+    },
+
+    /// Show a job and the state of its steps
+    Show { id: i64 },
+
+    /// Run the steps of a failed or interrupted job that are not done
+    Resume { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum ExportCommands {
+    /// Render services, features, mappings and their Markdown documents as a
+    /// searchable static HTML site
+    Site {
+        /// Directory to write the site to
+        #[arg(long, default_value = "site")]
+        output: String,
+    },
+
+    /// Bundle the config (without secrets), database, mappings, templates
+    /// and prompt packs, to move them to another machine
+    State {
+        /// Bundle to write, e.g. ktme-state.tar.zst
+        file: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Extract the commits of a range into the diff cache ahead of
+    /// generation, e.g. before parallel CI jobs
+    Warm {
+        /// Commit range `start..end`, e.g. origin/main..HEAD
+        #[arg(long)]
+        range: String,
+
+        /// Local repositories to read the range from (defaults to the
+        /// current one)
+        #[arg(long, value_delimiter = ',', value_name = "PATH")]
+        repos: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Apply pending schema migrations; each runs in a transaction and is
+    /// checked against its recorded checksum
+    Migrate {
+        /// List the migrations and whether each is applied, pending or
+        /// changed since it was applied
+        #[arg(long, conflicts_with = "down_to")]
+        status: bool,
+
+        /// Revert the migrations above this version, dropping their tables
+        /// and columns (development only)
+        #[arg(long, value_name = "VERSION")]
+        down_to: Option<i64>,
+    },
+
+    /// Print the statements that create the current schema
+    Schema {
+        /// Only the table and the indexes and triggers on it
+        #[arg(long)]
+        table: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum FeatureCommands {
+    /// Relate two features, e.g. `ktme feature link checkout payments`
+    /// records that checkout depends on payments
+    Link {
+        /// Feature id or name
+        from: String,
+
+        /// Feature id or name
+        to: String,
+
+        /// depends-on, implements, extends, uses, configures, tests,
+        /// deploys or other
+        #[arg(long, default_value = "depends-on")]
+        relation: String,
+
+        /// How strongly the features are related, 0 to 1
+        #[arg(long, default_value_t = 1.0)]
+        strength: f64,
+
+        /// Service to look feature names up in
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+    },
+
+    /// Remove the relations from one feature to another
+    Unlink {
+        from: String,
+        to: String,
+
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+    },
+
+    /// Search features by name, description and indexed content
+    Search {
+        query: String,
+
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Also list the features related to the matches
+        #[arg(long)]
+        related: bool,
+
+        /// Relations to follow from a match (implies --related, default 1)
+        #[arg(long)]
+        depth: Option<u32>,
+
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+
+    /// Decay the relevance of features that go unused and boost the ones
+    /// retrieved by searches and MCP tools since the last ranking; run it
+    /// periodically, e.g. daily from cron
+    Rank {
+        /// Days for the relevance of an unused feature to halve
+        #[arg(long, default_value_t = 30.0)]
+        half_life_days: f64,
+
+        /// Share of the distance to a relevance of 1 closed by each retrieval
+        #[arg(long, default_value_t = 0.1)]
+        usage_boost: f64,
+
+        /// Print the new scores without storing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportCommands {
+    /// Index existing Markdown files or Confluence pages as features of a
+    /// service, searchable by agents, and map them to it
+    Docs {
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: String,
+
+        /// Directory (or file) of Markdown documents
+        #[arg(long, required_unless_present = "space", conflicts_with = "space")]
+        path: Option<String>,
+
+        /// Key of a Confluence space to import every page of
+        #[arg(long)]
+        space: Option<String>,
+
+        /// Only index the documents, without mapping them to the service
+        #[arg(long)]
+        no_mapping: bool,
+    },
+
+    /// Restore a bundle written by `ktme export state`
+    State {
+        /// The bundle
+        file: String,
+
+        /// Replace the existing config, database and files (kept as .bak)
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PromptsCommands {
+    /// Write the built-in prompts as editable files (defaults to the
+    /// prompts directory next to the config file)
+    Export {
+        /// Directory to write to
+        #[arg(long, conflicts_with = "pack")]
+        dir: Option<String>,
+
+        /// Start a prompt pack with this name
+        #[arg(long)]
+        pack: Option<String>,
+
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Show which file or built-in prompt each prompt comes from
+    List {
+        /// Prompt pack name or directory (defaults to
+        /// documentation.prompt_pack)
+        #[arg(long)]
+        pack: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateCommands {
+    /// Print the rendered prompt without calling the AI provider
+    Preview {
+        /// Template file, or a built-in document type (changelog, api-doc,
+        /// readme, commit-message, general, onboarding, postmortem,
+        /// release-notes, security-review, test-plan)
+        name: String,
+
+        /// Commit to render the prompt for (defaults to HEAD)
+        #[arg(long, group = "source")]
+        commit: Option<String>,
+
+        #[arg(long, group = "source")]
+        staged: bool,
+
+        #[arg(long, group = "source")]
+        input: Option<String>,
+
+        /// Apply this service's settings, such as its language
+        #[arg(long, add = ArgValueCompleter::new(complete_service))]
+        service: Option<String>,
+
+        /// Value of a template variable; repeat for several
+        #[arg(long = "var", value_name = "NAME=VALUE")]
+        vars: Vec<String>,
+    },
+}
+
+/// Set up console and log file output. The returned guard flushes the log
+/// file and must be kept alive until exit.
+fn setup_logging(verbose: bool, quiet: bool, is_stdio: bool) -> Option<WorkerGuard> {
+    let general = config::Config::load()
+        .map(|c| c.general)
+        .unwrap_or_default();
+
+    // Skip console logging in STDIO mode to avoid JSON parsing issues
+    let console = (!is_stdio).then(|| {
+        let log_level = if quiet {
+            tracing::Level::ERROR
+        } else if verbose {
+            tracing::Level::DEBUG
+        } else {
+            std::env::var("KTME_LOG_LEVEL")
+                .ok()
+                .and_then(|l| l.parse().ok())
+                .unwrap_or(tracing::Level::INFO)
+        };
+
+        // Logs go to stderr so command output can be piped, printed above
+        // any active progress spinner
+        tracing_subscriber::fmt::layer()
+            .with_writer(|| cli::progress::LogWriter)
+            .with_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| format!("ktme={}", log_level).into()),
+            )
+    });
+
+    // The JSON log file is written in every mode, including MCP stdio
+    let mut guard = None;
+    let file = if general.log_file {
+        match logging::file_writer(&general) {
+            Ok((writer, file_guard)) => {
+                guard = Some(file_guard);
+                let level = if verbose { "debug" } else { &general.log_level };
+                Some(
+                    tracing_subscriber::fmt::layer()
+                        .json()
+                        .with_writer(writer)
+                        .with_filter(tracing_subscriber::EnvFilter::new(format!(
+                            "ktme={}",
+                            level
+                        ))),
+                )
+            }
+            Err(e) => {
+                eprintln!("Warning: file logging disabled: {}", e);
+                None
+            }
+        }
     } else {
-        std::env::var("KTME_LOG_LEVEL")
-            .ok()
-            .and_then(|l| l.parse().ok())
-            .unwrap_or(tracing::Level::INFO)
+        None
     };
 
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("ktme={}", log_level).into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(console)
+        .with(file)
         .init();
+
+    guard
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Answers shell completion requests (COMPLETE=<shell>) and exits
+    clap_complete::CompleteEnv::with_factory(Cli::command).complete();
+
     let cli = Cli::parse();
 
     // Check if we're in stdio mode for MCP
@@ -282,62 +1318,270 @@ async fn main() -> Result<()> {
         false
     };
 
-    setup_logging(cli.verbose, cli.quiet, is_stdio);
+    let log_guard = setup_logging(cli.verbose, cli.quiet, is_stdio);
+
+    if let Some(workspace) = &cli.workspace {
+        std::env::set_var(config::WORKSPACE_ENV, workspace);
+    }
+    if cli.offline {
+        std::env::set_var(config::OFFLINE_ENV, "1");
+        http::set_offline(true);
+    }
+    if let Some(dir) = &cli.record {
+        std::env::set_var(ai::fixtures::RECORD_ENV, dir);
+    }
+    if let Some(dir) = &cli.replay {
+        std::env::set_var(ai::fixtures::REPLAY_ENV, dir);
+    }
+    cli::output::set_json(cli.json);
+    cli::output::set_quiet(cli.quiet);
+
+    tracing::info!("Starting ktme v{}", env!("CARGO_PKG_VERSION"));
+
+    shutdown::install();
+    let started = std::time::Instant::now();
+    let result = shutdown::run_command(run(cli.command)).await;
+
+    if cli.timings {
+        eprint!("{}", metrics::summary(started.elapsed()));
+    }
 
-    // Only log if not in stdio mode
-    if !is_stdio {
-        tracing::info!("Starting ktme v{}", env!("CARGO_PKG_VERSION"));
+    // Failures are reported as a JSON document too so scripts can parse them,
+    // and the exit code tells the error class apart
+    if let Err(e) = &result {
+        if cli.json {
+            cli::output::print_error(e);
+            tracing::error!("{}", e);
+        } else {
+            cli::output::report_error(e, cli.verbose);
+        }
+        drop(log_guard);
+        std::process::exit(e.class().exit_code());
     }
 
-    match cli.command {
+    Ok(())
+}
+
+async fn run(command: Commands) -> Result<()> {
+    match command {
         Commands::Extract {
             commit,
             staged,
             pr,
             provider,
+            repo,
+            repos,
             output,
+            format,
+            pretty,
+            max_file_diff,
+            spill,
         } => {
-            cli::commands::extract::execute(commit, staged, pr, provider, output).await?;
+            cli::commands::extract::execute(
+                commit,
+                staged,
+                pr,
+                provider,
+                repo,
+                repos,
+                output,
+                format,
+                pretty,
+                max_file_diff,
+                spill,
+            )
+            .await?;
         }
         Commands::Generate {
             commit,
             input,
             pr,
+            provider,
             staged,
+            range,
+            at,
+            repos,
             service,
+            yes,
             r#type,
             format,
             output,
             template,
+            jobs,
+            force,
+            queue,
+            vars,
+            audience,
+            audit,
+            publish,
+            timeout,
+            wait,
+            commit_docs,
+            branch,
+            push,
+            open_pr,
+            redact_code,
         } => {
+            let commit_docs = commit_docs.then_some(git::commit::CommitDocs {
+                branch,
+                push,
+                open_pr,
+            });
             cli::commands::generate::execute(
-                commit, input, pr, staged, service, r#type, format, output, template,
+                commit,
+                input,
+                pr,
+                provider,
+                staged,
+                range,
+                at,
+                repos,
+                service,
+                yes,
+                r#type,
+                format,
+                output,
+                template,
+                jobs,
+                force,
+                queue,
+                vars,
+                audience,
+                audit,
+                publish,
+                timeout,
+                wait,
+                commit_docs,
+                redact_code,
+            )
+            .await?;
+        }
+        Commands::Backfill {
+            service,
+            range,
+            per,
+            r#type,
+            template,
+            output,
+            publish,
+            queue,
+            force,
+        } => {
+            cli::commands::backfill::execute(
+                service, range, per, r#type, template, output, publish, queue, force,
             )
             .await?;
         }
+        Commands::Run {
+            service,
+            yes,
+            source,
+            timeout,
+            wait,
+        } => {
+            cli::commands::run::execute(service, yes, source, timeout, wait).await?;
+        }
+        Commands::Translate {
+            service,
+            yes,
+            source,
+            force,
+        } => {
+            cli::commands::translate::execute(service, yes, source, force).await?;
+        }
+        Commands::Republish {
+            all,
+            service,
+            provider,
+            rate,
+            dry_run,
+        } => {
+            cli::commands::republish::execute(all, service, provider, &rate, dry_run).await?;
+        }
+        Commands::Cache { command } => match command {
+            CacheCommands::Warm { range, repos } => {
+                cli::commands::cache::warm(range, repos).await?;
+            }
+        },
         Commands::Update {
             commit,
             pr,
             staged,
             service,
+            yes,
+            doc,
+            provider,
             section,
+            r#type,
+            mode,
             dry_run,
+            force,
+            jobs,
+            timeout,
+            wait,
+            commit_docs,
+            branch,
+            push,
+            open_pr,
         } => {
-            cli::commands::update::execute(commit, pr, staged, service, section, dry_run).await?;
+            let commit_docs = commit_docs.then_some(git::commit::CommitDocs {
+                branch,
+                push,
+                open_pr,
+            });
+            cli::commands::update::execute(
+                commit,
+                pr,
+                staged,
+                service,
+                yes,
+                doc,
+                provider,
+                section,
+                r#type,
+                mode,
+                dry_run,
+                force,
+                jobs,
+                timeout,
+                wait,
+                commit_docs,
+            )
+            .await?;
         }
         Commands::Mapping { command } => match command {
-            MappingCommands::Add { service, url, file } => {
-                cli::commands::mapping::add(service, url, file).await?;
+            MappingCommands::Add {
+                service,
+                url,
+                file,
+                section,
+                settings,
+                translation_of,
+                language,
+            } => {
+                let translation = translation_of.zip(language);
+                cli::commands::mapping::add(service, url, file, section, settings, translation)
+                    .await?;
             }
             MappingCommands::List { service } => {
                 cli::commands::mapping::list(service).await?;
             }
-            MappingCommands::Get { service } => {
-                cli::commands::mapping::get(service).await?;
+            MappingCommands::Get { service, details } => {
+                cli::commands::mapping::get(service, details).await?;
             }
             MappingCommands::Remove { service } => {
                 cli::commands::mapping::remove(service).await?;
             }
+            MappingCommands::Rename {
+                old,
+                new,
+                update_titles,
+            } => {
+                cli::commands::mapping::rename(old, new, update_titles).await?;
+            }
+            MappingCommands::Validate { service, fix } => {
+                cli::commands::mapping::validate(service, fix).await?;
+            }
             MappingCommands::Discover { directory } => {
                 cli::commands::mapping::discover(directory).await?;
             }
@@ -345,13 +1589,26 @@ async fn main() -> Result<()> {
                 cli::commands::mapping::edit().await?;
             }
         },
+        Commands::Service { command } => match command {
+            ServiceCommands::Detect { refresh } => {
+                cli::commands::service::detect(refresh).await?;
+            }
+            ServiceCommands::Pin { name } => {
+                cli::commands::service::pin(name).await?;
+            }
+            ServiceCommands::Unpin => {
+                cli::commands::service::unpin().await?;
+            }
+        },
         Commands::Mcp { command } => match command {
             McpCommands::Start {
                 config,
                 daemon,
                 stdio,
+                tools,
+                trace,
             } => {
-                cli::commands::mcp::start(config, daemon, stdio).await?;
+                cli::commands::mcp::start(config, daemon, stdio, tools, trace).await?;
             }
             McpCommands::Status => {
                 cli::commands::mcp::status().await?;
@@ -359,6 +1616,12 @@ async fn main() -> Result<()> {
             McpCommands::Stop => {
                 cli::commands::mcp::stop().await?;
             }
+            McpCommands::Audit { since, tool } => {
+                cli::commands::mcp::audit(since, tool).await?;
+            }
+            McpCommands::Trace { file, summary } => {
+                cli::commands::mcp::trace(file, summary).await?;
+            }
         },
         Commands::Search {
             query,
@@ -377,8 +1640,164 @@ async fn main() -> Result<()> {
             ConfigCommands::Set { key, value } => {
                 cli::commands::config::set(key, value).await?;
             }
-            ConfigCommands::Validate => {
-                cli::commands::config::validate().await?;
+            ConfigCommands::Validate { online } => {
+                cli::commands::config::validate(online).await?;
+            }
+        },
+        Commands::Plugins { command } => match command {
+            PluginsCommands::List => {
+                cli::commands::plugins::list().await?;
+            }
+        },
+        Commands::Providers { command } => match command {
+            ProvidersCommands::Add {
+                provider,
+                config,
+                file,
+                default,
+            } => {
+                cli::commands::providers::add(provider, config, file, default).await?;
+            }
+            ProvidersCommands::List => {
+                cli::commands::providers::list().await?;
+            }
+            ProvidersCommands::SetDefault { provider } => {
+                cli::commands::providers::set_default(provider).await?;
+            }
+            ProvidersCommands::Remove { provider } => {
+                cli::commands::providers::remove(provider).await?;
+            }
+            ProvidersCommands::Status { timeout } => {
+                cli::commands::providers::status(timeout).await?;
+            }
+        },
+        Commands::Auth { command } => match command {
+            AuthCommands::Login {
+                provider,
+                no_browser,
+            } => {
+                cli::commands::auth::login(provider, no_browser).await?;
+            }
+            AuthCommands::Logout { provider } => {
+                cli::commands::auth::logout(provider).await?;
+            }
+            AuthCommands::Status { provider } => {
+                cli::commands::auth::status(provider).await?;
+            }
+        },
+        Commands::Jobs { command } => match command {
+            JobsCommands::List { status } => {
+                cli::commands::jobs::list(status).await?;
+            }
+            JobsCommands::Show { id } => {
+                cli::commands::jobs::show(id).await?;
+            }
+            JobsCommands::Resume { id } => {
+                cli::commands::jobs::resume(id).await?;
+            }
+        },
+        Commands::Queue { command } => match command {
+            QueueCommands::List { status } => {
+                cli::commands::queue::list(status).await?;
+            }
+            QueueCommands::Show { id } => {
+                cli::commands::queue::show(id).await?;
+            }
+            QueueCommands::Approve { id } => {
+                cli::commands::queue::approve(id).await?;
+            }
+            QueueCommands::Reject { id, reason } => {
+                cli::commands::queue::reject(id, reason).await?;
+            }
+        },
+        Commands::Export { command } => match command {
+            ExportCommands::Site { output } => {
+                cli::commands::export::site(output).await?;
+            }
+            ExportCommands::State { file } => {
+                cli::commands::export::state(file).await?;
+            }
+        },
+        Commands::Ask {
+            question,
+            service,
+            limit,
+        } => {
+            cli::commands::ask::execute(question, service, limit).await?;
+        }
+        Commands::Dedupe { service, threshold } => {
+            cli::commands::dedupe::execute(service, threshold).await?;
+        }
+        Commands::Db { command } => match command {
+            DbCommands::Migrate { status, down_to } => {
+                cli::commands::db::migrate(status, down_to).await?;
+            }
+            DbCommands::Schema { table } => {
+                cli::commands::db::schema(table).await?;
+            }
+        },
+        Commands::Feature { command } => match command {
+            FeatureCommands::Link {
+                from,
+                to,
+                relation,
+                strength,
+                service,
+            } => {
+                cli::commands::feature::link(from, to, relation, strength, service).await?;
+            }
+            FeatureCommands::Unlink { from, to, service } => {
+                cli::commands::feature::unlink(from, to, service).await?;
+            }
+            FeatureCommands::Search {
+                query,
+                service,
+                related,
+                depth,
+                limit,
+            } => {
+                cli::commands::feature::search(query, service, related, depth, limit).await?;
+            }
+            FeatureCommands::Rank {
+                half_life_days,
+                usage_boost,
+                dry_run,
+            } => {
+                cli::commands::feature::rank(half_life_days, usage_boost, dry_run).await?;
+            }
+        },
+        Commands::Import { command } => match command {
+            ImportCommands::Docs {
+                service,
+                path,
+                space,
+                no_mapping,
+            } => {
+                cli::commands::import::docs(service, path, space, no_mapping).await?;
+            }
+            ImportCommands::State { file, force } => {
+                cli::commands::import::state(file, force).await?;
+            }
+        },
+        Commands::Prompts { command } => match command {
+            PromptsCommands::Export { dir, pack, force } => {
+                cli::commands::prompts::export(dir, pack, force).await?;
+            }
+            PromptsCommands::List { pack } => {
+                cli::commands::prompts::list(pack).await?;
+            }
+        },
+        Commands::Template { command } => match command {
+            TemplateCommands::Preview {
+                name,
+                commit,
+                staged,
+                input,
+                service,
+                vars,
+            } => {
+                cli::commands::template::preview(name, commit, staged, input, service, vars)
+                    .await?;
             }
         },
         Commands::Init {
@@ -386,10 +1805,23 @@ async fn main() -> Result<()> {
             service,
             force,
             mode,
+            template,
+            with_mapping,
+            with_hooks,
             dry_run,
             output,
         } => {
-            cli::commands::init::execute(path, service, force, mode, dry_run, output).await?;
+            let wiring = cli::commands::init::Wiring {
+                mapping: with_mapping,
+                hooks: with_hooks,
+            };
+            cli::commands::init::execute(
+                path, service, force, mode, template, wiring, dry_run, output,
+            )
+            .await?;
+        }
+        Commands::Index { output, confluence } => {
+            cli::commands::index::execute(output, confluence).await?;
         }
         Commands::Tree {
             service,
@@ -398,6 +1830,30 @@ async fn main() -> Result<()> {
         } => {
             cli::commands::tree::execute(service, depth, mermaid).await?;
         }
+        Commands::SelfUpdate { check_only, force } => {
+            cli::commands::self_update::execute(check_only, force).await?;
+        }
+        Commands::Completions {
+            shell,
+            static_script,
+        } => {
+            cli::commands::completions::completions(shell, Cli::command(), static_script)?;
+        }
+        Commands::Man { output } => {
+            cli::commands::completions::man(Cli::command(), output)?;
+        }
+        Commands::Logs {
+            follow,
+            since,
+            level,
+            lines,
+        } => {
+            cli::commands::logs::execute(follow, since, level, lines).await?;
+        }
+        #[cfg(feature = "tui")]
+        Commands::Tui => {
+            tui::run()?;
+        }
     }
 
     Ok(())
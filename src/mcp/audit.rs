@@ -0,0 +1,100 @@
+//! Audit log of MCP tool calls, read back with `ktme mcp audit`
+
+use crate::mcp::tools::ToolContext;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Characters of an argument value kept in the log; diffs and document
+/// contents are cut to this length
+const MAX_ARGUMENT_LEN: usize = 120;
+
+/// Outcome of a tool call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallStatus {
+    Success,
+    Error,
+    /// Refused because the tool is not enabled on this server
+    Denied,
+}
+
+impl CallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Error => "error",
+            Self::Denied => "denied",
+        }
+    }
+}
+
+/// `arguments` as JSON with long string values cut, so the log holds what
+/// was asked for without the diffs and documents passed along
+pub fn summarize_arguments(arguments: &Value) -> String {
+    fn cut(value: &Value) -> Value {
+        match value {
+            Value::String(text) if text.chars().count() > MAX_ARGUMENT_LEN => {
+                let kept: String = text.chars().take(MAX_ARGUMENT_LEN).collect();
+                Value::String(format!("{}… ({} chars)", kept, text.chars().count()))
+            }
+            Value::Array(items) => Value::Array(items.iter().map(cut).collect()),
+            Value::Object(fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), cut(value)))
+                    .collect(),
+            ),
+            _ => value.clone(),
+        }
+    }
+
+    cut(arguments).to_string()
+}
+
+/// Record a tool call in the workspace of `context`; a failure to write
+/// the log never fails the call
+pub fn record(
+    context: &ToolContext,
+    transport: &str,
+    tool: &str,
+    arguments: &Value,
+    elapsed: Duration,
+    status: CallStatus,
+    error: Option<&str>,
+) {
+    let recorded = context.storage().and_then(|storage| {
+        storage.record_tool_call(
+            tool,
+            &summarize_arguments(arguments),
+            transport,
+            elapsed.as_millis() as i64,
+            status.as_str(),
+            error,
+        )
+    });
+    if let Err(e) = recorded {
+        tracing::warn!("Failed to record MCP tool call {}: {}", tool, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summarize_arguments_cuts_long_values() {
+        let diff = "+".repeat(500);
+        let summary = summarize_arguments(&json!({
+            "service": "api",
+            "changes": diff,
+            "paths": ["src/lib.rs"]
+        }));
+        let summary: Value = serde_json::from_str(&summary).unwrap();
+
+        assert_eq!(summary["service"], "api");
+        assert_eq!(summary["paths"][0], "src/lib.rs");
+        let changes = summary["changes"].as_str().unwrap();
+        assert!(changes.ends_with("… (500 chars)"));
+        assert!(changes.len() < 200);
+    }
+}
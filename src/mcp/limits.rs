@@ -0,0 +1,327 @@
+//! Limits of MCP tool calls
+//!
+//! A tool call runs for at most `[mcp] tool_timeout`, or its entry in
+//! `tool_timeouts`. Text output longer than `max_output_bytes` is cut: the
+//! whole of it is kept in a temporary file that agents read in parts as the
+//! `ktme://outputs/<id>` resource, following the hint at the end of each
+//! part. Messages larger than `max_payload_bytes` are refused both ways, so
+//! a huge diff never reaches a stdio client in one line.
+
+use crate::config::McpConfig;
+use crate::error::{KtmeError, Result};
+use crate::http::parse_timeout;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// URI prefix of the kept tool outputs
+pub const OUTPUT_URI: &str = "ktme://outputs/";
+
+/// How long kept tool outputs stay readable
+const OUTPUT_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Smallest part of an output returned at once, so that a tiny limit
+/// still makes progress through the resource
+const MIN_OUTPUT_BYTES: usize = 1024;
+
+/// Timeouts and size limits of a server's tool calls
+#[derive(Debug, Clone)]
+pub struct ToolLimits {
+    timeout: Duration,
+    /// By tool name without the `ktme_` prefix
+    timeouts: HashMap<String, Duration>,
+    max_output_bytes: usize,
+    max_payload_bytes: usize,
+}
+
+impl Default for ToolLimits {
+    fn default() -> Self {
+        Self::from_config(&McpConfig::default()).expect("valid default limits")
+    }
+}
+
+impl ToolLimits {
+    pub fn from_config(config: &McpConfig) -> Result<Self> {
+        let invalid =
+            |key: String, e: KtmeError| KtmeError::Config(format!("[mcp] {}: {}", key, e));
+        let timeouts = config
+            .tool_timeouts
+            .iter()
+            .map(|(tool, value)| {
+                parse_timeout(value)
+                    .map(|timeout| (base_name(tool).to_string(), timeout))
+                    .map_err(|e| invalid(format!("tool_timeouts.{}", tool), e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            timeout: parse_timeout(&config.tool_timeout)
+                .map_err(|e| invalid("tool_timeout".to_string(), e))?,
+            timeouts,
+            max_output_bytes: config.max_output_bytes.max(MIN_OUTPUT_BYTES),
+            max_payload_bytes: config.max_payload_bytes,
+        })
+    }
+
+    /// Longest `tool` may run
+    pub fn timeout(&self, tool: &str) -> Duration {
+        self.timeouts
+            .get(base_name(tool))
+            .copied()
+            .unwrap_or(self.timeout)
+    }
+
+    /// Error of a `tool` call that ran out of time
+    pub fn timed_out(&self, tool: &str) -> KtmeError {
+        KtmeError::Timeout(format!(
+            "Tool {} did not finish within {}s; raise [mcp] tool_timeout or tool_timeouts.{}",
+            tool,
+            self.timeout(tool).as_secs(),
+            base_name(tool)
+        ))
+    }
+
+    /// `output` of a tool, cut at `max_output_bytes` with a hint at the
+    /// resource holding the rest
+    pub fn truncate(&self, output: String) -> String {
+        if output.len() <= self.max_output_bytes {
+            return output;
+        }
+        match keep(&output) {
+            Ok(uri) => page(&output, &uri, 0, self.max_output_bytes),
+            Err(e) => {
+                tracing::warn!("Failed to keep the full tool output: {}", e);
+                let end = floor_char_boundary(&output, self.max_output_bytes);
+                format!(
+                    "{}\n\n[Output truncated at {} of {} bytes; the full output could not be kept: {}]",
+                    &output[..end],
+                    end,
+                    output.len(),
+                    e
+                )
+            }
+        }
+    }
+
+    /// Result of `resources/read` for a kept output, from the `offset`
+    /// given in its URI
+    pub fn read_resource(&self, uri: &str) -> Result<Value> {
+        let not_found = || KtmeError::NotFound(format!("Resource {}", uri));
+        let rest = uri.strip_prefix(OUTPUT_URI).ok_or_else(not_found)?;
+        let (id, offset) = match rest.split_once("?offset=") {
+            Some((id, offset)) => (
+                id,
+                offset
+                    .parse::<usize>()
+                    .map_err(|_| KtmeError::InvalidInput(format!("Invalid offset in {}", uri)))?,
+            ),
+            None => (rest, 0),
+        };
+        // The id names a file, so it must be nothing but a UUID
+        let id = Uuid::parse_str(id).map_err(|_| not_found())?;
+        let output =
+            fs::read_to_string(output_dir().join(format!("{}.txt", id))).map_err(|_| {
+                KtmeError::NotFound(format!("Resource {} (expired or never kept)", uri))
+            })?;
+        if offset > output.len() || !output.is_char_boundary(offset) {
+            return Err(KtmeError::InvalidInput(format!(
+                "Offset {} is not a position in the {} bytes of {}",
+                offset,
+                output.len(),
+                id
+            )));
+        }
+        let base = format!("{}{}", OUTPUT_URI, id);
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "text/plain",
+                "text": page(&output, &base, offset, self.max_output_bytes)
+            }]
+        }))
+    }
+
+    /// Largest message accepted or sent, in bytes
+    pub fn max_payload_bytes(&self) -> usize {
+        self.max_payload_bytes
+    }
+
+    /// Whether a message of `size` bytes is larger than `max_payload_bytes`
+    pub fn exceeds_payload(&self, size: usize) -> bool {
+        size > self.max_payload_bytes
+    }
+
+    /// Error response to a request of `size` bytes when it is larger than
+    /// `max_payload_bytes`. Such a request is refused before it is parsed,
+    /// so the response has no id
+    pub fn refuse_request(&self, size: usize) -> Option<Value> {
+        self.exceeds_payload(size).then(|| {
+            json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {
+                    "code": -32600,
+                    "message": "Request too large",
+                    "data": format!(
+                        "The request has {} bytes, more than [mcp] max_payload_bytes ({})",
+                        size, self.max_payload_bytes
+                    )
+                }
+            })
+        })
+    }
+
+    /// `response`, or an error in its place when it is larger than
+    /// `max_payload_bytes`
+    pub fn fit_response(&self, response: Value) -> Value {
+        let size = response.to_string().len();
+        if size <= self.max_payload_bytes {
+            return response;
+        }
+        tracing::warn!(
+            "Refusing to send a response of {} bytes (max_payload_bytes {})",
+            size,
+            self.max_payload_bytes
+        );
+        json!({
+            "jsonrpc": "2.0",
+            "id": response.get("id").cloned().unwrap_or(Value::Null),
+            "error": {
+                "code": -32603,
+                "message": "Response too large",
+                "data": format!(
+                    "The response has {} bytes, more than [mcp] max_payload_bytes ({})",
+                    size, self.max_payload_bytes
+                )
+            }
+        })
+    }
+}
+
+/// Tool name without the `ktme_` prefix agents may see
+fn base_name(tool: &str) -> &str {
+    tool.trim().strip_prefix("ktme_").unwrap_or(tool.trim())
+}
+
+fn output_dir() -> PathBuf {
+    std::env::temp_dir().join("ktme-mcp-outputs")
+}
+
+/// Write `output` to a new file of the outputs directory, removing the
+/// expired ones; returns its URI
+fn keep(output: &str) -> Result<String> {
+    let dir = output_dir();
+    fs::create_dir_all(&dir)?;
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > OUTPUT_TTL);
+        if expired {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    let id = Uuid::new_v4();
+    fs::write(dir.join(format!("{}.txt", id)), output)?;
+    Ok(format!("{}{}", OUTPUT_URI, id))
+}
+
+/// At most `max` bytes of `text` from `offset`, ending in a hint at the
+/// next part of the resource `uri` when there is more
+fn page(text: &str, uri: &str, offset: usize, max: usize) -> String {
+    let end = floor_char_boundary(text, offset.saturating_add(max)).max(offset);
+    let part = &text[offset..end];
+    if end >= text.len() {
+        return part.to_string();
+    }
+    format!(
+        "{}\n\n[Output truncated: bytes {}-{} of {}. Read on with resources/read of \"{}?offset={}\"]",
+        part,
+        offset,
+        end,
+        text.len(),
+        uri,
+        end
+    )
+}
+
+/// Largest char boundary of `text` at or before `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    (0..=index)
+        .rev()
+        .find(|i| text.is_char_boundary(*i))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_output_is_read_in_parts() {
+        let limits = ToolLimits::from_config(&McpConfig {
+            max_output_bytes: 1024,
+            tool_timeouts: HashMap::from([("ktme_read_changes".to_string(), "30s".to_string())]),
+            ..McpConfig::default()
+        })
+        .unwrap();
+        assert_eq!(limits.timeout("read_changes"), Duration::from_secs(30));
+        assert_eq!(limits.timeout("list_services"), Duration::from_secs(300));
+
+        let output = "é".repeat(1500);
+        let first = limits.truncate(output.clone());
+        assert!(first.starts_with(&"é".repeat(512)));
+        let uri = first
+            .split('"')
+            .nth(1)
+            .expect("continuation hint")
+            .to_string();
+        assert!(uri.starts_with(OUTPUT_URI) && uri.ends_with("?offset=1024"));
+
+        let mut read = first[..1024].to_string();
+        let mut next = Some(uri);
+        while let Some(uri) = next.take() {
+            let text = limits.read_resource(&uri).unwrap()["contents"][0]["text"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            match text.split_once("\n\n[Output truncated") {
+                Some((part, hint)) => {
+                    read.push_str(part);
+                    next = hint.split('"').nth(1).map(str::to_string);
+                }
+                None => read.push_str(&text),
+            }
+        }
+        assert_eq!(read, output);
+
+        assert!(limits
+            .read_resource("ktme://outputs/../../etc/passwd")
+            .is_err());
+        assert_eq!(limits.truncate("short".to_string()), "short");
+    }
+
+    #[test]
+    fn test_payload_limit() {
+        let limits = ToolLimits::from_config(&McpConfig {
+            max_payload_bytes: 100,
+            ..McpConfig::default()
+        })
+        .unwrap();
+        assert!(limits.refuse_request(50).is_none());
+        let refused = limits.refuse_request(150).unwrap();
+        assert_eq!(refused["error"]["code"], -32600);
+        assert!(refused["id"].is_null());
+        let response = json!({"jsonrpc": "2.0", "id": 7, "result": {"text": "x".repeat(200)}});
+        let fitted = limits.fit_response(response);
+        assert_eq!(fitted["id"], 7);
+        assert_eq!(fitted["error"]["message"], "Response too large");
+    }
+}
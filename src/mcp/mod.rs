@@ -1,5 +1,10 @@
+pub mod audit;
 pub mod client;
+pub mod limits;
 pub mod protocol;
+pub mod sampling;
 pub mod server;
 pub mod stdio_server;
 pub mod tools;
+pub mod trace;
+pub mod version;
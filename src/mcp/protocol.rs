@@ -1,12 +1,95 @@
+use crate::config::McpConfig;
 use crate::error::Result;
-use crate::mcp::tools::McpTools;
+use crate::mcp::audit::{self, CallStatus};
+use crate::mcp::limits::ToolLimits;
+use crate::mcp::tools::{McpTools, SearchFilters, ToolContext};
+use crate::mcp::trace::Tracer;
+use crate::mcp::version::ProtocolVersion;
 use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// Tools a server exposes: `[mcp] enabled_tools` (or `--tools`) minus
+/// `[mcp] disabled_tools`. Names may carry the `ktme_` prefix agents see.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolFilter {
+    /// Only these tools; all of them when empty
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+}
+
+impl ToolFilter {
+    /// Filter of the `[mcp]` config; `tools` from the command line replaces
+    /// `enabled_tools`
+    pub fn from_config(config: &McpConfig, tools: Vec<String>) -> Self {
+        let filter = Self {
+            enabled: if tools.is_empty() {
+                config.enabled_tools.clone()
+            } else {
+                tools
+            },
+            disabled: config.disabled_tools.clone(),
+        };
+        let known: Vec<String> = McpProtocolHandler::get_tools_list()
+            .iter()
+            .filter_map(|tool| tool["name"].as_str().map(str::to_string))
+            .collect();
+        for name in filter.enabled.iter().chain(&filter.disabled) {
+            if !known.iter().any(|tool| tool == Self::base_name(name)) {
+                tracing::warn!("Unknown MCP tool in the tool filter: {}", name);
+            }
+        }
+        filter
+    }
+
+    pub fn allows(&self, tool: &str) -> bool {
+        let tool = Self::base_name(tool);
+        let listed = |names: &[String]| names.iter().any(|name| Self::base_name(name) == tool);
+        (self.enabled.is_empty() || listed(&self.enabled)) && !listed(&self.disabled)
+    }
+
+    fn base_name(name: &str) -> &str {
+        name.trim().strip_prefix("ktme_").unwrap_or(name.trim())
+    }
+}
+
+/// Add the arguments every tool accepts to choose what it runs against
+/// (see [`ToolContext::for_call`]) to the schema of `tool`
+fn with_context_arguments(mut tool: Value) -> Value {
+    if let Some(properties) = tool
+        .pointer_mut("/inputSchema/properties")
+        .and_then(Value::as_object_mut)
+    {
+        properties.insert(
+            "repo_path".to_string(),
+            json!({
+                "type": "string",
+                "description": "Repository to run against, absolute or relative to the server's working directory (default: the working directory)"
+            }),
+        );
+        properties.insert(
+            "workspace".to_string(),
+            json!({
+                "type": "string",
+                "description": "Workspace whose services and history are used (default: the server's workspace)"
+            }),
+        );
+    }
+    tool
+}
 
 /// Shared MCP protocol handler for JSON-RPC 2.0 message processing
 #[derive(Clone)]
 pub struct McpProtocolHandler {
     server_name: String,
     server_version: String,
+    tools: ToolFilter,
+    limits: ToolLimits,
+    transport: String,
+    trace: Option<Arc<Tracer>>,
+    /// Answer messages that are not JSON with a parse error
+    parse_errors: bool,
+    /// Revision negotiated in the last `initialize`
+    version: Arc<Mutex<ProtocolVersion>>,
 }
 
 impl McpProtocolHandler {
@@ -14,19 +97,143 @@ impl McpProtocolHandler {
         Self {
             server_name,
             server_version,
+            tools: ToolFilter::default(),
+            limits: ToolLimits::default(),
+            transport: "stdio".to_string(),
+            trace: None,
+            parse_errors: false,
+            version: Arc::default(),
         }
     }
 
+    /// Transport the calls arrive on, as recorded in the audit log
+    pub fn with_transport(mut self, transport: &str) -> Self {
+        self.transport = transport.to_string();
+        self
+    }
+
+    /// Only expose the tools allowed by `tools`
+    pub fn with_tool_filter(mut self, tools: ToolFilter) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Time and size limits of the tool calls
+    pub fn with_limits(mut self, limits: ToolLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Capture the traffic with `trace`
+    pub fn with_trace(mut self, trace: Option<Arc<Tracer>>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Answer messages that are not JSON with a `-32700` error without an
+    /// id, as JSON-RPC asks; otherwise they are only logged
+    pub fn with_parse_errors(mut self) -> Self {
+        self.parse_errors = true;
+        self
+    }
+
+    /// Time and size limits of the tool calls
+    pub fn limits(&self) -> &ToolLimits {
+        &self.limits
+    }
+
+    /// Protocol revision negotiated with the client
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        *self.version.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The tools this server exposes
+    pub fn available_tools(&self) -> Vec<Value> {
+        let version = self.protocol_version();
+        Self::get_tools_list()
+            .into_iter()
+            .filter(|tool| {
+                tool["name"]
+                    .as_str()
+                    .is_some_and(|name| self.tools.allows(name))
+            })
+            .map(with_context_arguments)
+            .map(|tool| version.describe_tool(tool))
+            .collect()
+    }
+
     /// Handle incoming JSON-RPC message
     /// Returns Some(response) if a response should be sent, None for notifications
     pub async fn handle_message(&self, message: &str) -> Result<Option<Value>> {
+        if let Some(trace) = &self.trace {
+            trace.received(message);
+        }
+        let response = self
+            .respond(message)
+            .await?
+            .map(|response| self.limits.fit_response(response));
+        if let (Some(trace), Some(response)) = (&self.trace, &response) {
+            trace.sent(response);
+        }
+        Ok(response)
+    }
+
+    /// Response to `message` as [`Self::handle_message`], with a failure to
+    /// handle it answered by an internal error; `None` for notifications
+    pub async fn answer(&self, message: &str) -> Option<Value> {
+        match self.handle_message(message).await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::error!("Error handling message: {}", e);
+                let request = serde_json::from_str::<Value>(message).ok()?;
+                let id = request.get("id").filter(|id| !id.is_null())?;
+                Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": -32603,
+                        "message": "Internal error",
+                        "data": e.to_string()
+                    }
+                }))
+            }
+        }
+    }
+
+    /// Response to a message of `size` bytes that the transport discarded
+    /// unread for being larger than `max_payload_bytes`
+    pub fn refuse_unread(&self, size: usize) -> Option<Value> {
+        tracing::warn!("Refusing a message of {} bytes", size);
+        let refused = self.limits.refuse_request(size);
+        if let (Some(trace), Some(response)) = (&self.trace, &refused) {
+            trace.sent(response);
+        }
+        refused
+    }
+
+    async fn respond(&self, message: &str) -> Result<Option<Value>> {
+        // Measured before parsing, so a huge message costs no more than its
+        // read; whether it was a request or a notification is unknown
+        if let Some(refused) = self.limits.refuse_request(message.len()) {
+            tracing::warn!("Refusing a message of {} bytes", message.len());
+            return Ok(Some(refused));
+        }
+
         let request: Value = match serde_json::from_str(message) {
             Ok(req) => req,
             Err(e) => {
                 tracing::error!("Invalid JSON received: {}", e);
-                // For invalid JSON, we can't determine if it was a request or notification
-                // According to JSON-RPC spec, we should not send any response for parse errors
-                return Ok(None);
+                // Whether it was a request or a notification is unknown
+                return Ok(self.parse_errors.then(|| {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": {
+                            "code": -32700,
+                            "message": "Parse error"
+                        }
+                    })
+                }));
             }
         };
 
@@ -57,9 +264,11 @@ impl McpProtocolHandler {
 
         // Route to appropriate handler
         match method {
-            "initialize" => self.handle_initialize(id, is_notification),
+            "initialize" => self.handle_initialize(&request, id, is_notification),
             "tools/list" => self.handle_tools_list(id, is_notification),
             "tools/call" => self.handle_tools_call(&request, id, is_notification).await,
+            "resources/list" => self.handle_resources_list(id, is_notification),
+            "resources/read" => self.handle_resources_read(&request, id, is_notification),
             "ping" => self.handle_ping(id, is_notification),
             _ => self.handle_unknown_method(method, id, is_notification),
         }
@@ -67,6 +276,7 @@ impl McpProtocolHandler {
 
     fn handle_initialize(
         &self,
+        request: &Value,
         id: Option<&Value>,
         is_notification: bool,
     ) -> Result<Option<Value>> {
@@ -75,17 +285,22 @@ impl McpProtocolHandler {
             return Ok(None);
         }
 
+        // Documentation is written by the client's model when it offers sampling
+        crate::mcp::sampling::set_offered(
+            request
+                .pointer("/params/capabilities/sampling")
+                .is_some_and(|sampling| !sampling.is_null()),
+        );
+
+        let version = ProtocolVersion::from_initialize(request);
+        *self.version.lock().unwrap_or_else(|e| e.into_inner()) = version;
+
         let response = json!({
             "jsonrpc": "2.0",
             "id": id,
             "result": {
-                "protocolVersion": "2024-11-05",
-                "capabilities": {
-                    "tools": {
-                        "listChanged": false
-                    },
-                    "logging": {}
-                },
+                "protocolVersion": version.as_str(),
+                "capabilities": version.capabilities(),
                 "serverInfo": {
                     "name": self.server_name,
                     "version": self.server_version
@@ -105,7 +320,7 @@ impl McpProtocolHandler {
             return Ok(None);
         }
 
-        let tools = Self::get_tools_list();
+        let tools = self.available_tools();
         let response = json!({
             "jsonrpc": "2.0",
             "id": id,
@@ -133,28 +348,56 @@ impl McpProtocolHandler {
         let empty_args = json!({});
         let arguments = params.get("arguments").unwrap_or(&empty_args);
 
-        match Self::execute_tool(tool_name, arguments).await {
+        let started = std::time::Instant::now();
+        let allowed = self.tools.allows(tool_name);
+        let base = ToolContext::from_env()?;
+        let context = base.for_call(arguments);
+        let audited = context.as_ref().unwrap_or(&base).clone();
+        let result = match context {
+            _ if !allowed => Err(crate::error::KtmeError::InvalidInput(format!(
+                "Tool {} is not enabled on this server",
+                tool_name
+            ))),
+            Ok(context) => self.call_tool(context, tool_name, arguments).await,
+            Err(e) => Err(e),
+        };
+        crate::metrics::record_tool_call(tool_name, started.elapsed(), result.is_ok());
+        let status = match &result {
+            Ok(_) => CallStatus::Success,
+            Err(_) if !allowed => CallStatus::Denied,
+            Err(_) => CallStatus::Error,
+        };
+        audit::record(
+            &audited,
+            &self.transport,
+            tool_name,
+            arguments,
+            started.elapsed(),
+            status,
+            result.as_ref().err().map(|e| e.to_string()).as_deref(),
+        );
+
+        match result {
             Ok(result) => {
                 let response = json!({
                     "jsonrpc": "2.0",
                     "id": id,
-                    "result": {
-                        "content": [{
-                            "type": "text",
-                            "text": result
-                        }]
-                    }
+                    "result": self.protocol_version().tool_result(result)
                 });
                 Ok(Some(response))
             }
             Err(e) => {
+                let data = match e.hint() {
+                    Some(hint) => format!("{}\nHint: {}", e, hint),
+                    None => e.to_string(),
+                };
                 let response = json!({
                     "jsonrpc": "2.0",
                     "id": id,
                     "error": {
                         "code": -32000,
-                        "message": "Tool execution failed",
-                        "data": e.to_string()
+                        "message": format!("Tool execution failed ({})", e.class().as_str()),
+                        "data": data
                     }
                 });
                 Ok(Some(response))
@@ -162,6 +405,83 @@ impl McpProtocolHandler {
         }
     }
 
+    /// Run `tool_name` as its own task, given up on after its timeout, with
+    /// its output cut to the size limit
+    async fn call_tool(
+        &self,
+        context: ToolContext,
+        tool_name: &str,
+        arguments: &Value,
+    ) -> Result<String> {
+        let (name, args) = (tool_name.to_string(), arguments.clone());
+        let mut call =
+            tokio::spawn(
+                async move { Self::execute_tool(&McpTools::new(context), &name, &args).await },
+            );
+        match tokio::time::timeout(self.limits.timeout(tool_name), &mut call).await {
+            Ok(Ok(output)) => output.map(|output| self.limits.truncate(output)),
+            Ok(Err(e)) => Err(crate::error::KtmeError::Mcp(format!(
+                "Tool {} stopped: {}",
+                tool_name, e
+            ))),
+            Err(_) => {
+                call.abort();
+                Err(self.limits.timed_out(tool_name))
+            }
+        }
+    }
+
+    /// Tool outputs are only reachable through the URIs their first part
+    /// ends with, so none are listed
+    fn handle_resources_list(
+        &self,
+        id: Option<&Value>,
+        is_notification: bool,
+    ) -> Result<Option<Value>> {
+        if is_notification {
+            return Ok(None);
+        }
+        Ok(Some(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": {
+                "resources": []
+            }
+        })))
+    }
+
+    fn handle_resources_read(
+        &self,
+        request: &Value,
+        id: Option<&Value>,
+        is_notification: bool,
+    ) -> Result<Option<Value>> {
+        if is_notification {
+            return Ok(None);
+        }
+        let uri = request
+            .pointer("/params/uri")
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let response = match self.limits.read_resource(uri) {
+            Ok(result) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result
+            }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": -32002,
+                    "message": "Resource not available",
+                    "data": e.to_string()
+                }
+            }),
+        };
+        Ok(Some(response))
+    }
+
     fn handle_ping(&self, id: Option<&Value>, is_notification: bool) -> Result<Option<Value>> {
         if is_notification {
             return Ok(None);
@@ -283,13 +603,30 @@ impl McpProtocolHandler {
             }),
             json!({
                 "name": "search_services",
-                "description": "Search services by query with relevance scoring",
+                "description": "Search services by name, description, path and the features they own; returns a JSON page of services with relevance scores and the matching features",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
                         "query": {
                             "type": "string",
                             "description": "Search query string"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Results per page (default 10, at most 100)"
+                        },
+                        "offset": {
+                            "type": "integer",
+                            "description": "Results to skip; pass next_offset of the previous page"
+                        },
+                        "min_score": {
+                            "type": "number",
+                            "description": "Lowest relevance score of a result"
+                        },
+                        "feature_type": {
+                            "type": "string",
+                            "enum": ["api", "ui", "business_logic", "config", "database", "security", "performance", "testing", "deployment", "other"],
+                            "description": "Only services owning matching features of this type"
                         }
                     },
                     "required": ["query"]
@@ -311,21 +648,64 @@ impl McpProtocolHandler {
             }),
             json!({
                 "name": "search_by_keyword",
-                "description": "Search services by keyword with flexible matching",
+                "description": "Search features and the indexed content of their documents by keyword; returns a JSON page of matches, most relevant first",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
                         "keyword": {
                             "type": "string",
                             "description": "Keyword to search for"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Results per page (default 10, at most 100)"
+                        },
+                        "offset": {
+                            "type": "integer",
+                            "description": "Results to skip; pass next_offset of the previous page"
+                        },
+                        "min_score": {
+                            "type": "number",
+                            "description": "Lowest relevance score of a result"
+                        },
+                        "feature_type": {
+                            "type": "string",
+                            "enum": ["api", "ui", "business_logic", "config", "database", "security", "performance", "testing", "deployment", "other"],
+                            "description": "Only features of this type"
+                        },
+                        "service": {
+                            "type": "string",
+                            "description": "Only features of this service"
                         }
                     },
                     "required": ["keyword"]
                 }
             }),
             json!({
-                "name": "automated_documentation_workflow",
-                "description": "Automated workflow: extract changes → generate documentation → save to mapped location",
+                "name": "search_documentation",
+                "description": "Search the content of generated and published documentation (mapped documents, the search index and saved providers) and return ranked snippets with document URLs",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Words to look for in the documentation"
+                        },
+                        "service": {
+                            "type": "string",
+                            "description": "Only search the documents of this service"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of documents (default 10)"
+                        }
+                    },
+                    "required": ["query"]
+                }
+            }),
+            json!({
+                "name": "read_documentation",
+                "description": "Read the documentation mapped to a service as Markdown; Confluence pages are converted from their storage format so they can be compared with, edited into or passed along with generated documentation",
                 "inputSchema": {
                     "type": "object",
                     "properties": {
@@ -333,6 +713,59 @@ impl McpProtocolHandler {
                             "type": "string",
                             "description": "Service name"
                         },
+                        "location": {
+                            "type": "string",
+                            "description": "Only read the mapped document at this location (file path or page URL)"
+                        }
+                    },
+                    "required": ["service"]
+                }
+            }),
+            json!({
+                "name": "stale_docs_report",
+                "description": "List services whose documentation lags behind HEAD, with the commits since the last successful generation and its date",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "service": {
+                            "type": "string",
+                            "description": "Only report this service"
+                        },
+                        "include_fresh": {
+                            "type": "boolean",
+                            "description": "Also list services whose documentation is up to date (default false)"
+                        }
+                    }
+                }
+            }),
+            json!({
+                "name": "summarize_commit_history",
+                "description": "Summarize the commits of a range as a short narrative grouped by area, without generating or publishing a document (standup notes, PR descriptions)",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "range": {
+                            "type": "string",
+                            "description": "Commit range (e.g., 'v1.2.0..HEAD'), or a single ref meaning '<ref>..HEAD'"
+                        },
+                        "max_commits": {
+                            "type": "integer",
+                            "description": "Newest commits to summarize (default 100)"
+                        }
+                    },
+                    "required": ["range"]
+                }
+            }),
+            json!({
+                "name": "automated_documentation_workflow",
+                "description": "Automated workflow: extract changes → generate documentation → save to mapped location; services with a [policies.<service>] entry run the same extract → generate → lint → publish → notify workflow as `ktme run`",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "service": {
+                            "type": "string",
+                            "description": "Service name, or 'auto' to resolve the services owning the changed files from service paths and CODEOWNERS"
+                        },
                         "source": {
                             "type": "string",
                             "description": "Source identifier (commit hash, 'staged', or file path)"
@@ -451,15 +884,104 @@ impl McpProtocolHandler {
                     }
                 }
             }),
+            json!({
+                "name": "link_features",
+                "description": "Relate two features of the knowledge graph, e.g. record that checkout depends on payments",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "from": {
+                            "type": "string",
+                            "description": "Id or name of the feature that depends on, implements, tests... the other"
+                        },
+                        "to": {
+                            "type": "string",
+                            "description": "Id or name of the related feature"
+                        },
+                        "relation": {
+                            "type": "string",
+                            "enum": ["depends_on", "implements", "extends", "uses", "configures", "tests", "deploys", "other"],
+                            "description": "Relation type (default depends_on)"
+                        },
+                        "strength": {
+                            "type": "number",
+                            "description": "How strongly the features are related, 0 to 1 (default 1)"
+                        },
+                        "service": {
+                            "type": "string",
+                            "description": "Service to look feature names up in"
+                        }
+                    },
+                    "required": ["from", "to"]
+                }
+            }),
+            json!({
+                "name": "unlink_features",
+                "description": "Remove the relations from one feature to another",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "from": {
+                            "type": "string",
+                            "description": "Feature id or name"
+                        },
+                        "to": {
+                            "type": "string",
+                            "description": "Feature id or name"
+                        },
+                        "service": {
+                            "type": "string",
+                            "description": "Service to look feature names up in"
+                        }
+                    },
+                    "required": ["from", "to"]
+                }
+            }),
+            json!({
+                "name": "search_features",
+                "description": "Search the features of the knowledge graph, optionally with the features related to the matches",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Words to match against feature names, descriptions and indexed content"
+                        },
+                        "service": {
+                            "type": "string",
+                            "description": "Only search the features of this service"
+                        },
+                        "include_related": {
+                            "type": "boolean",
+                            "description": "Also return the features related to the matches, with how they are related (default false)"
+                        },
+                        "depth": {
+                            "type": "integer",
+                            "description": "Relations to follow from a match (default 1)"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of matches (default 20)"
+                        }
+                    },
+                    "required": ["query"]
+                }
+            }),
         ]
     }
 
-    /// Execute a tool by name with given arguments
-    pub async fn execute_tool(tool_name: &str, arguments: &Value) -> Result<String> {
+    /// Execute a tool by name with given arguments; the name may carry the
+    /// `ktme_` prefix agents see
+    pub async fn execute_tool(
+        tools: &McpTools,
+        tool_name: &str,
+        arguments: &Value,
+    ) -> Result<String> {
+        let tool_name = ToolFilter::base_name(tool_name);
         match tool_name {
             "read_changes" => {
                 if let Some(source) = arguments.get("source").and_then(|s| s.as_str()) {
-                    McpTools::read_changes(source)
+                    tools.read_changes(source)
                 } else {
                     Err(crate::error::KtmeError::InvalidInput(
                         "Missing 'source' parameter".to_string(),
@@ -468,14 +990,15 @@ impl McpProtocolHandler {
             }
             "get_service_mapping" => {
                 if let Some(service) = arguments.get("service").and_then(|s| s.as_str()) {
-                    McpTools::get_service_mapping(service)
+                    tools.get_service_mapping(service)
                 } else {
                     Err(crate::error::KtmeError::InvalidInput(
                         "Missing 'service' parameter".to_string(),
                     ))
                 }
             }
-            "list_services" => McpTools::list_services()
+            "list_services" => tools
+                .list_services()
                 .map(|services| format!("Services: {}", services.join(", "))),
             "generate_documentation" => {
                 let service = arguments
@@ -487,7 +1010,7 @@ impl McpProtocolHandler {
                     .and_then(|c| c.as_str())
                     .unwrap_or("");
                 let format = arguments.get("format").and_then(|f| f.as_str());
-                McpTools::generate_documentation(service, changes, format)
+                tools.generate_documentation(service, changes, format)
             }
             "update_documentation" => {
                 let service = arguments
@@ -502,11 +1025,12 @@ impl McpProtocolHandler {
                     .get("content")
                     .and_then(|c| c.as_str())
                     .unwrap_or("");
-                McpTools::update_documentation(service, doc_path, content)
+                tools.update_documentation(service, doc_path, content)
             }
             "search_services" => {
                 if let Some(query) = arguments.get("query").and_then(|q| q.as_str()) {
-                    McpTools::search_services(query)
+                    SearchFilters::from_arguments(arguments)
+                        .and_then(|filters| tools.search_services(query, &filters))
                 } else {
                     Err(crate::error::KtmeError::InvalidInput(
                         "Missing 'query' parameter".to_string(),
@@ -515,16 +1039,62 @@ impl McpProtocolHandler {
             }
             "search_by_feature" => {
                 if let Some(feature) = arguments.get("feature").and_then(|f| f.as_str()) {
-                    McpTools::search_by_feature(feature)
+                    tools.search_by_feature(feature)
                 } else {
                     Err(crate::error::KtmeError::InvalidInput(
                         "Missing 'feature' parameter".to_string(),
                     ))
                 }
             }
+            "search_documentation" => {
+                if let Some(query) = arguments.get("query").and_then(|q| q.as_str()) {
+                    let service = arguments.get("service").and_then(|s| s.as_str());
+                    let limit = arguments
+                        .get("limit")
+                        .and_then(|l| l.as_u64())
+                        .map(|l| l as usize);
+                    tools.search_documentation(query, service, limit).await
+                } else {
+                    Err(crate::error::KtmeError::InvalidInput(
+                        "Missing 'query' parameter".to_string(),
+                    ))
+                }
+            }
+            "read_documentation" => {
+                if let Some(service) = arguments.get("service").and_then(|s| s.as_str()) {
+                    let location = arguments.get("location").and_then(|l| l.as_str());
+                    tools.read_documentation(service, location).await
+                } else {
+                    Err(crate::error::KtmeError::InvalidInput(
+                        "Missing 'service' parameter".to_string(),
+                    ))
+                }
+            }
+            "stale_docs_report" => {
+                let service = arguments.get("service").and_then(|s| s.as_str());
+                let include_fresh = arguments
+                    .get("include_fresh")
+                    .and_then(|f| f.as_bool())
+                    .unwrap_or(false);
+                tools.stale_docs_report(service, include_fresh)
+            }
+            "summarize_commit_history" => {
+                if let Some(range) = arguments.get("range").and_then(|r| r.as_str()) {
+                    let max_commits = arguments
+                        .get("max_commits")
+                        .and_then(|m| m.as_u64())
+                        .map(|m| m as usize);
+                    tools.summarize_commit_history(range, max_commits).await
+                } else {
+                    Err(crate::error::KtmeError::InvalidInput(
+                        "Missing 'range' parameter".to_string(),
+                    ))
+                }
+            }
             "search_by_keyword" => {
                 if let Some(keyword) = arguments.get("keyword").and_then(|k| k.as_str()) {
-                    McpTools::search_by_keyword(keyword)
+                    SearchFilters::from_arguments(arguments)
+                        .and_then(|filters| tools.search_by_keyword(keyword, &filters))
                 } else {
                     Err(crate::error::KtmeError::InvalidInput(
                         "Missing 'keyword' parameter".to_string(),
@@ -540,25 +1110,25 @@ impl McpProtocolHandler {
                     .get("source")
                     .and_then(|s| s.as_str())
                     .unwrap_or("");
-                McpTools::automated_documentation_workflow(service, source)
+                tools.automated_documentation_workflow(service, source)
             }
-            "detect_service_name" => McpTools::detect_service_name(),
-            "get_repository_info" => McpTools::get_repository_info(),
+            "detect_service_name" => tools.detect_service_name(),
+            "get_repository_info" => tools.get_repository_info(),
             "scan_documentation" => {
                 let path = arguments.get("path").and_then(|p| p.as_str());
-                McpTools::scan_documentation(path)
+                tools.scan_documentation(path)
             }
             "validate_documentation" => {
                 let path = arguments.get("path").and_then(|p| p.as_str());
-                McpTools::validate_documentation(path)
+                tools.validate_documentation(path)
             }
             "detect_tech_stack" => {
                 let path = arguments.get("path").and_then(|p| p.as_str());
-                McpTools::detect_tech_stack(path)
+                tools.detect_tech_stack(path)
             }
             "find_documentation_todos" => {
                 let path = arguments.get("path").and_then(|p| p.as_str());
-                McpTools::find_documentation_todos(path)
+                tools.find_documentation_todos(path)
             }
             "get_knowledge_tree" => {
                 let service = arguments.get("service").and_then(|s| s.as_str());
@@ -570,13 +1140,51 @@ impl McpProtocolHandler {
                     .get("include_mermaid")
                     .and_then(|m| m.as_bool())
                     .unwrap_or(false);
-                McpTools::get_knowledge_tree(service, depth, include_mermaid)
+                tools.get_knowledge_tree(service, depth, include_mermaid)
             }
             "get_feature_context" => {
                 let feature_id = arguments.get("feature_id").and_then(|f| f.as_str());
                 let feature_name = arguments.get("feature_name").and_then(|f| f.as_str());
                 let service_name = arguments.get("service_name").and_then(|s| s.as_str());
-                McpTools::get_feature_context(feature_id, feature_name, service_name)
+                tools.get_feature_context(feature_id, feature_name, service_name)
+            }
+            "link_features" | "unlink_features" => {
+                let from = arguments.get("from").and_then(|f| f.as_str());
+                let to = arguments.get("to").and_then(|t| t.as_str());
+                let service = arguments.get("service").and_then(|s| s.as_str());
+                match (from, to) {
+                    (Some(from), Some(to)) if tool_name == "link_features" => {
+                        let relation = arguments.get("relation").and_then(|r| r.as_str());
+                        let strength = arguments.get("strength").and_then(|s| s.as_f64());
+                        tools.link_features(from, to, relation, strength, service)
+                    }
+                    (Some(from), Some(to)) => tools.unlink_features(from, to, service),
+                    _ => Err(crate::error::KtmeError::InvalidInput(
+                        "Missing 'from' or 'to' parameter".to_string(),
+                    )),
+                }
+            }
+            "search_features" => {
+                if let Some(query) = arguments.get("query").and_then(|q| q.as_str()) {
+                    let service = arguments.get("service").and_then(|s| s.as_str());
+                    let include_related = arguments
+                        .get("include_related")
+                        .and_then(|r| r.as_bool())
+                        .unwrap_or(false);
+                    let depth = arguments
+                        .get("depth")
+                        .and_then(|d| d.as_u64())
+                        .map(|d| d as u32);
+                    let limit = arguments
+                        .get("limit")
+                        .and_then(|l| l.as_u64())
+                        .map(|l| l as u32);
+                    tools.search_features(query, service, include_related, depth, limit)
+                } else {
+                    Err(crate::error::KtmeError::InvalidInput(
+                        "Missing 'query' parameter".to_string(),
+                    ))
+                }
             }
             _ => Err(crate::error::KtmeError::InvalidInput(format!(
                 "Unknown tool: {}",
@@ -604,6 +1212,31 @@ mod tests {
         assert_eq!(resp["result"]["serverInfo"]["name"], "test-server");
     }
 
+    #[tokio::test]
+    async fn test_initialize_negotiates_the_protocol_version() {
+        let handler = McpProtocolHandler::new("test-server".to_string(), "0.1.0".to_string());
+        let response = handler
+            .handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}"#)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2025-06-18");
+        let tools = handler.available_tools();
+        assert!(tools.iter().all(|tool| tool["annotations"].is_object()));
+
+        // An older client is not sent the newer fields
+        let response = handler
+            .handle_message(r#"{"jsonrpc":"2.0","id":2,"method":"initialize","params":{"protocolVersion":"2024-11-05"}}"#)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+        assert!(handler
+            .available_tools()
+            .iter()
+            .all(|tool| tool.get("annotations").is_none()));
+    }
+
     #[tokio::test]
     async fn test_handle_tools_list() {
         let handler = McpProtocolHandler::new("test-server".to_string(), "0.1.0".to_string());
@@ -618,6 +1251,48 @@ mod tests {
         assert!(resp["result"]["tools"].as_array().unwrap().len() > 0);
     }
 
+    #[tokio::test]
+    async fn test_tool_filter_limits_list_and_calls() {
+        let config = McpConfig {
+            enabled_tools: vec![
+                "ktme_search_services".to_string(),
+                "list_services".to_string(),
+            ],
+            disabled_tools: vec!["list_services".to_string()],
+            ..Default::default()
+        };
+        let filter = ToolFilter::from_config(&config, Vec::new());
+        assert!(filter.allows("search_services"));
+        assert!(!filter.allows("list_services"));
+        assert!(!filter.allows("update_documentation"));
+        assert!(
+            ToolFilter::from_config(&config, vec!["update_documentation".to_string()])
+                .allows("update_documentation")
+        );
+
+        let handler = McpProtocolHandler::new("test-server".to_string(), "0.1.0".to_string())
+            .with_tool_filter(filter);
+        let list = handler
+            .handle_message(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#)
+            .await
+            .unwrap()
+            .unwrap();
+        let names: Vec<&str> = list["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|tool| tool["name"].as_str())
+            .collect();
+        assert_eq!(names, vec!["search_services"]);
+
+        let call = r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"update_documentation","arguments":{}}}"#;
+        let response = handler.handle_message(call).await.unwrap().unwrap();
+        assert!(response["error"]["data"]
+            .as_str()
+            .unwrap()
+            .contains("Tool update_documentation is not enabled"));
+    }
+
     #[tokio::test]
     async fn test_handle_ping() {
         let handler = McpProtocolHandler::new("test-server".to_string(), "0.1.0".to_string());
@@ -0,0 +1,300 @@
+//! MCP sampling: documentation text written by the model of the connected
+//! client (`sampling/createMessage`) instead of a provider needing ktme's own
+//! API keys
+
+use crate::ai::providers::AIProvider;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+/// Tokens asked for when `[ai] max_tokens` is not set
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+const SYSTEM_PROMPT: &str =
+    "You are a technical writer documenting code changes. Answer with the requested text only.";
+
+static SAMPLER: OnceLock<Arc<SamplingClient>> = OnceLock::new();
+
+/// Sends `sampling/createMessage` requests to the client and hands the
+/// responses back to the waiting callers
+pub struct SamplingClient {
+    outgoing: mpsc::UnboundedSender<Value>,
+    pending: Mutex<HashMap<String, oneshot::Sender<Result<Value>>>>,
+    next_id: AtomicU64,
+    offered: AtomicBool,
+}
+
+impl SamplingClient {
+    /// Client writing its requests to `outgoing`, the messages sent to the
+    /// MCP client
+    pub fn new(outgoing: mpsc::UnboundedSender<Value>) -> Self {
+        Self {
+            outgoing,
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            offered: AtomicBool::new(false),
+        }
+    }
+
+    /// Record whether the client declared the `sampling` capability
+    pub fn set_offered(&self, offered: bool) {
+        self.offered.store(offered, Ordering::SeqCst);
+    }
+
+    pub fn offered(&self) -> bool {
+        self.offered.load(Ordering::SeqCst)
+    }
+
+    /// Hand `message` to the request waiting for it; false when it is not
+    /// the response to a sampling request
+    pub fn handle_response(&self, message: &str) -> bool {
+        let Ok(message) = serde_json::from_str::<Value>(message) else {
+            return false;
+        };
+        if message.get("method").is_some() {
+            return false;
+        }
+        let Some(id) = message.get("id").and_then(|id| id.as_str()) else {
+            return false;
+        };
+        let waiting = self.pending.lock().unwrap().remove(id);
+        match waiting {
+            Some(waiting) => {
+                let _ = waiting.send(Ok(message));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ids of the requests still waiting for a response
+    pub fn pending_ids(&self) -> Vec<String> {
+        self.pending.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Fail the request `id` with `error` when it is still waiting, e.g.
+    /// because its response could not be read; false when it is not
+    pub fn fail(&self, id: &str, error: KtmeError) -> bool {
+        let waiting = self.pending.lock().unwrap().remove(id);
+        match waiting {
+            Some(waiting) => {
+                let _ = waiting.send(Err(error));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Text the client's model answers to `prompt`
+    pub async fn create_message(
+        &self,
+        prompt: &str,
+        max_tokens: u32,
+        timeout: Duration,
+    ) -> Result<String> {
+        let id = format!(
+            "ktme-sampling-{}",
+            self.next_id.fetch_add(1, Ordering::SeqCst)
+        );
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id.clone(), sender);
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "sampling/createMessage",
+            "params": {
+                "messages": [{
+                    "role": "user",
+                    "content": { "type": "text", "text": prompt }
+                }],
+                "systemPrompt": SYSTEM_PROMPT,
+                "includeContext": "none",
+                "maxTokens": max_tokens
+            }
+        });
+        if self.outgoing.send(request).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(KtmeError::Mcp("The MCP client is disconnected".to_string()));
+        }
+
+        let response = match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(response)) => response?,
+            Ok(Err(_)) => return Err(KtmeError::Mcp("The MCP client is disconnected".to_string())),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Err(KtmeError::Timeout(format!(
+                    "The MCP client did not answer the sampling request within {}s",
+                    timeout.as_secs()
+                )));
+            }
+        };
+
+        if let Some(error) = response.get("error") {
+            return Err(KtmeError::Mcp(format!(
+                "Sampling request rejected by the MCP client: {}",
+                error
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown error")
+            )));
+        }
+        response
+            .pointer("/result/content/text")
+            .and_then(|text| text.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                KtmeError::Mcp(
+                    "Sampling response of the MCP client has no text content".to_string(),
+                )
+            })
+    }
+}
+
+/// Sampling client of the stdio server, sending its requests to `outgoing`
+pub fn install(outgoing: mpsc::UnboundedSender<Value>) -> Arc<SamplingClient> {
+    SAMPLER
+        .get_or_init(|| Arc::new(SamplingClient::new(outgoing)))
+        .clone()
+}
+
+/// Record whether the connected client offers sampling; ignored when the
+/// transport cannot send requests to the client
+pub fn set_offered(offered: bool) {
+    if let Some(sampler) = SAMPLER.get() {
+        sampler.set_offered(offered);
+        if offered {
+            tracing::info!("MCP client offers sampling; documentation is written by its model");
+        }
+    }
+}
+
+/// Provider writing through the client's model, when the client offers
+/// sampling
+pub fn provider() -> Option<SamplingProvider> {
+    SAMPLER
+        .get()
+        .filter(|sampler| sampler.offered())
+        .map(|sampler| SamplingProvider::new(sampler.clone()))
+}
+
+/// AI provider answering prompts through MCP sampling
+pub struct SamplingProvider {
+    client: Arc<SamplingClient>,
+    max_tokens: u32,
+    timeout: Duration,
+}
+
+impl SamplingProvider {
+    pub fn new(client: Arc<SamplingClient>) -> Self {
+        let config = Config::load().unwrap_or_default();
+        Self {
+            client,
+            max_tokens: config.ai.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            timeout: config.timeouts.request_timeout("ai"),
+        }
+    }
+}
+
+#[async_trait]
+impl AIProvider for SamplingProvider {
+    async fn generate(&self, prompt: &str) -> Result<String> {
+        self.client
+            .create_message(prompt, self.max_tokens, self.timeout)
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        "MCP sampling"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sampling_request_and_response() {
+        let (outgoing, mut requests) = mpsc::unbounded_channel();
+        let client = Arc::new(SamplingClient::new(outgoing));
+
+        let asking = client.clone();
+        let answer = tokio::spawn(async move {
+            asking
+                .create_message("Summarize", 512, Duration::from_secs(5))
+                .await
+        });
+
+        let request = requests.recv().await.unwrap();
+        assert_eq!(request["method"], "sampling/createMessage");
+        assert_eq!(request["params"]["maxTokens"], 512);
+        assert_eq!(
+            request["params"]["messages"][0]["content"]["text"],
+            "Summarize"
+        );
+
+        assert!(!client.handle_response(r#"{"jsonrpc":"2.0","id":7,"result":{}}"#));
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "role": "assistant",
+                "content": { "type": "text", "text": "Summary" },
+                "model": "client-model"
+            }
+        });
+        assert!(client.handle_response(&response.to_string()));
+        assert_eq!(answer.await.unwrap().unwrap(), "Summary");
+    }
+
+    #[tokio::test]
+    async fn test_sampling_rejected_by_client() {
+        let (outgoing, mut requests) = mpsc::unbounded_channel();
+        let client = Arc::new(SamplingClient::new(outgoing));
+
+        let asking = client.clone();
+        let answer = tokio::spawn(async move {
+            asking
+                .create_message("Summarize", 512, Duration::from_secs(5))
+                .await
+        });
+        let request = requests.recv().await.unwrap();
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": { "code": -1, "message": "User rejected sampling request" }
+        });
+        client.handle_response(&response.to_string());
+
+        let error = answer.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("User rejected"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_sampling_request() {
+        let (outgoing, mut requests) = mpsc::unbounded_channel();
+        let client = Arc::new(SamplingClient::new(outgoing));
+
+        let asking = client.clone();
+        let answer = tokio::spawn(async move {
+            asking
+                .create_message("Summarize", 512, Duration::from_secs(5))
+                .await
+        });
+        let request = requests.recv().await.unwrap();
+        let id = request["id"].as_str().unwrap();
+        assert_eq!(client.pending_ids(), vec![id.to_string()]);
+
+        assert!(client.fail(id, KtmeError::Mcp("Response too large".to_string())));
+        assert!(!client.fail(id, KtmeError::Mcp("Response too large".to_string())));
+        let error = answer.await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("Response too large"));
+        assert!(client.pending_ids().is_empty());
+    }
+}
@@ -1,8 +1,11 @@
 use crate::ai::AIClient;
+use crate::config::Config;
 use crate::error::Result;
-use crate::mcp::protocol::McpProtocolHandler;
-use serde_json::Value;
-use std::io::{self, BufRead, BufReader, Write};
+use crate::mcp::limits::ToolLimits;
+use crate::mcp::protocol::{McpProtocolHandler, ToolFilter};
+use crate::mcp::stdio_server::StdioServer;
+use crate::mcp::trace::Tracer;
+use crate::shutdown;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -11,6 +14,12 @@ pub struct ServerConfig {
     pub server_name: String,
     pub transport: String,
     pub port: Option<u16>,
+    /// Tools exposed to the agents
+    pub tools: ToolFilter,
+    /// Timeouts and size limits of the tool calls
+    pub limits: ToolLimits,
+    /// Capture of the traffic, with `--trace`
+    pub trace: Option<Arc<Tracer>>,
 }
 
 impl Default for ServerConfig {
@@ -19,6 +28,9 @@ impl Default for ServerConfig {
             server_name: "ktme-mcp-server".to_string(),
             transport: "stdio".to_string(),
             port: None,
+            tools: ToolFilter::default(),
+            limits: ToolLimits::default(),
+            trace: None,
         }
     }
 }
@@ -26,12 +38,15 @@ impl Default for ServerConfig {
 #[derive(Clone)]
 pub struct ServerState {
     pub running: Arc<RwLock<bool>>,
+    /// Serve `/metrics` (`mcp.metrics` in config)
+    pub metrics: bool,
 }
 
 impl ServerState {
     pub fn new() -> Self {
         Self {
             running: Arc::new(RwLock::new(true)),
+            metrics: Config::load().map(|c| c.mcp.metrics).unwrap_or(false),
         }
     }
 
@@ -57,7 +72,11 @@ impl McpServer {
         let protocol_handler = McpProtocolHandler::new(
             config.server_name.clone(),
             env!("CARGO_PKG_VERSION").to_string(),
-        );
+        )
+        .with_tool_filter(config.tools.clone())
+        .with_limits(config.limits.clone())
+        .with_trace(config.trace.clone())
+        .with_transport(&config.transport);
         let mut server = Self {
             config,
             protocol_handler,
@@ -117,42 +136,9 @@ impl McpServer {
 
     async fn run_stdio_server(&self) -> Result<()> {
         tracing::info!("Starting STDIO MCP server");
-
-        let stdin = io::stdin();
-        let mut reader = BufReader::new(stdin);
-        let mut stdout = io::stdout();
-
-        // Don't send init response immediately - wait for initialize request
-
-        loop {
-            let mut line = String::new();
-            match reader.read_line(&mut line) {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
-                        continue;
-                    }
-
-                    match self.handle_message(trimmed, &mut stdout).await {
-                        Ok(should_continue) => {
-                            if !should_continue {
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("Error handling message: {}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Error reading from stdin: {}", e);
-                    break;
-                }
-            }
-        }
-
-        Ok(())
+        StdioServer::from_handler(self.protocol_handler.clone())
+            .run()
+            .await
     }
 
     async fn run_sse_server(&self, port: u16) -> Result<()> {
@@ -194,6 +180,11 @@ impl McpServer {
                     // Periodic check for shutdown
                     continue;
                 }
+                _ = shutdown::cancelled() => {
+                    tracing::info!("Shutting down HTTP server");
+                    state.shutdown().await;
+                    break;
+                }
             }
         }
 
@@ -244,7 +235,7 @@ impl McpServer {
         // Handle different endpoints
         match (method, path) {
             ("GET", "/status") => {
-                let tools_count = McpProtocolHandler::get_tools_list().len();
+                let tools_count = protocol_handler.available_tools().len();
                 let status_json = serde_json::json!({
                     "status": "running",
                     "version": env!("CARGO_PKG_VERSION"),
@@ -260,6 +251,16 @@ impl McpServer {
                 writer.write_all(response.as_bytes()).await?;
                 writer.flush().await?;
             }
+            ("GET", "/metrics") if state.metrics => {
+                let body = crate::metrics::render_prometheus();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                writer.write_all(response.as_bytes()).await?;
+                writer.flush().await?;
+            }
             ("POST", "/shutdown") => {
                 state.shutdown().await;
 
@@ -326,49 +327,6 @@ impl McpServer {
         Ok(())
     }
 
-    async fn handle_message(&self, message: &str, writer: &mut impl Write) -> Result<bool> {
-        tracing::debug!("Received: {}", message);
-
-        match self.protocol_handler.handle_message(message).await {
-            Ok(Some(response)) => {
-                self.send_response(&response, writer)?;
-            }
-            Ok(None) => {
-                // Notification - no response needed
-            }
-            Err(e) => {
-                tracing::error!("Error handling message: {}", e);
-                // Try to parse the request to get the ID for error response
-                if let Ok(parsed_request) = serde_json::from_str::<Value>(message) {
-                    if let Some(request_id) = parsed_request.get("id") {
-                        if !request_id.is_null() {
-                            let error_response = serde_json::json!({
-                                "jsonrpc": "2.0",
-                                "id": request_id,
-                                "error": {
-                                    "code": -32603,
-                                    "message": "Internal error",
-                                    "data": e.to_string()
-                                }
-                            });
-                            self.send_response(&error_response, writer)?;
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(true)
-    }
-
-    fn send_response(&self, response: &Value, writer: &mut impl Write) -> Result<()> {
-        let response_str = response.to_string();
-        writer.write_all(response_str.as_bytes())?;
-        writer.write_all(b"\n")?;
-        writer.flush()?;
-        Ok(())
-    }
-
     pub async fn stop(&self) -> Result<()> {
         tracing::info!("Stopping MCP server");
         Ok(())
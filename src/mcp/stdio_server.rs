@@ -1,401 +1,319 @@
-use crate::error::Result;
-use crate::mcp::tools::McpTools;
-use serde_json::{json, Value};
-use std::io::{self, BufRead, BufReader, Write};
+use crate::error::{KtmeError, Result};
+use crate::mcp::limits::ToolLimits;
+use crate::mcp::protocol::{McpProtocolHandler, ToolFilter};
+use crate::mcp::sampling::{self, SamplingClient};
+use crate::mcp::trace::Tracer;
+use crate::shutdown;
+use serde_json::Value;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
 
+/// The `mcp start --stdio` server: JSON-RPC messages, one per line, answered
+/// by the [`McpProtocolHandler`] of the other transports so that every
+/// transport lists and runs the same tools. Calls run concurrently, and when
+/// the client offers sampling the documentation is written by its model.
 pub struct StdioServer {
-    #[allow(dead_code)] // Tools field will be used when MCP server is fully implemented
-    tools: McpTools,
+    handler: McpProtocolHandler,
 }
 
 impl StdioServer {
     pub fn new() -> Self {
-        Self { tools: McpTools }
+        Self {
+            handler: McpProtocolHandler::new(
+                "ktme-mcp-server".to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            )
+            .with_parse_errors(),
+        }
+    }
+
+    /// Only expose the tools allowed by `filter`
+    pub fn with_tool_filter(mut self, filter: ToolFilter) -> Self {
+        self.handler = self.handler.with_tool_filter(filter);
+        self
+    }
+
+    /// Time and size limits of the tool calls
+    pub fn with_limits(mut self, limits: ToolLimits) -> Self {
+        self.handler = self.handler.with_limits(limits);
+        self
+    }
+
+    /// Capture the traffic with `trace`
+    pub fn with_trace(mut self, trace: Option<Arc<Tracer>>) -> Self {
+        self.handler = self.handler.with_trace(trace);
+        self
+    }
+
+    /// Server answering with `handler`
+    pub fn from_handler(handler: McpProtocolHandler) -> Self {
+        Self { handler }
     }
 
     pub async fn run(&self) -> Result<()> {
-        let stdin = io::stdin();
-        let mut reader = BufReader::new(stdin);
+        // Responses and the server's own sampling requests share stdout
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<Value>();
+        let sampler = sampling::install(outgoing.clone());
         let mut stdout = io::stdout();
 
-        loop {
-            let mut line = String::new();
-            match reader.read_line(&mut line) {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
-                        continue;
+        // stdin is read on its own thread so that a shutdown signal is
+        // noticed while waiting for the next message, and the answer to a
+        // sampling request arrives while the tool call that sent it waits
+        let (lines, mut lines_rx) = mpsc::unbounded_channel::<Incoming>();
+        let limit = self.handler.limits().max_payload_bytes();
+        let reading = sampler.clone();
+        std::thread::spawn(move || {
+            let mut stdin = io::stdin().lock();
+            loop {
+                match read_message(&mut stdin, limit, &reading) {
+                    Ok(Some(message)) => {
+                        if lines.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::error!("Error reading from stdin: {}", e);
+                        break;
                     }
+                }
+            }
+        });
 
-                    // Parse and handle the JSON-RPC message
-                    let request: Value = match serde_json::from_str(trimmed) {
-                        Ok(r) => r,
-                        Err(_) => {
-                            // Send parse error response
-                            let error_response = json!({
-                                "jsonrpc": "2.0",
-                                "id": null,
-                                "error": {
-                                    "code": -32700,
-                                    "message": "Parse error"
+        let mut calls = JoinSet::new();
+        loop {
+            tokio::select! {
+                line = lines_rx.recv() => {
+                    let line = match line {
+                        Some(Incoming::Line(line)) => line,
+                        Some(Incoming::Oversized { size, sampling_ids }) => {
+                            // The answer to a sampling request fails the
+                            // tool call waiting for it; anything else is
+                            // refused without an id
+                            let mut answered = false;
+                            for id in &sampling_ids {
+                                answered |= sampler.fail(id, too_large(size, limit));
+                            }
+                            if !answered {
+                                if let Some(refused) = self.handler.refuse_unread(size) {
+                                    let _ = outgoing.send(refused);
                                 }
-                            });
-                            let _ = stdout.write_all(error_response.to_string().as_bytes());
-                            let _ = stdout.write_all(b"\n");
-                            let _ = stdout.flush();
+                            }
                             continue;
                         }
+                        None => break, // EOF
                     };
+                    let trimmed = line.trim();
+                    if trimmed.is_empty() || sampler.handle_response(trimmed) {
+                        continue;
+                    }
 
-                    match self.handle_message(&request, &mut stdout) {
-                        Ok(should_continue) => {
-                            if !should_continue {
-                                break;
-                            }
+                    let handler = self.handler.clone();
+                    let outgoing = outgoing.clone();
+                    let message = trimmed.to_string();
+                    calls.spawn(async move {
+                        if let Some(response) = handler.answer(&message).await {
+                            let _ = outgoing.send(response);
                         }
-                        Err(_) => {
-                            // Extract the request ID for proper error response
-                            let request_id = request.get("id").cloned().unwrap_or(json!(null));
+                    });
+                }
+                Some(message) = outgoing_rx.recv() => {
+                    Self::send_response(&message, &mut stdout)?;
+                }
+                Some(_) = calls.join_next(), if !calls.is_empty() => {}
+                _ = shutdown::cancelled() => {
+                    tracing::info!("Shutting down STDIO MCP server");
+                    break;
+                }
+            }
+        }
 
-                            // Send error response
-                            let error_response = json!({
-                                "jsonrpc": "2.0",
-                                "id": request_id,
-                                "error": {
-                                    "code": -32603,
-                                    "message": "Internal error"
-                                }
-                            });
-                            let _ = stdout.write_all(error_response.to_string().as_bytes());
-                            let _ = stdout.write_all(b"\n");
-                            let _ = stdout.flush();
-                        }
+        // Answer the calls still running before exiting; after a shutdown
+        // signal they get the grace period to finish
+        let drain = async {
+            while !calls.is_empty() {
+                tokio::select! {
+                    Some(message) = outgoing_rx.recv() => {
+                        Self::send_response(&message, &mut stdout)?;
                     }
+                    _ = calls.join_next() => {}
                 }
-                Err(_) => break,
             }
+            Ok::<_, crate::error::KtmeError>(())
+        };
+        let deadline = async {
+            shutdown::cancelled().await;
+            tokio::time::sleep(shutdown::GRACE_PERIOD).await;
+        };
+        let drained = tokio::select! {
+            drained = drain => Some(drained),
+            _ = deadline => None,
+        };
+        match drained {
+            Some(drained) => drained?,
+            None => {
+                tracing::warn!("Abandoning {} unfinished tool call(s)", calls.len());
+                calls.abort_all();
+            }
+        }
+        while let Ok(message) = outgoing_rx.try_recv() {
+            Self::send_response(&message, &mut stdout)?;
         }
 
         Ok(())
     }
 
-    fn handle_message(&self, request: &Value, writer: &mut impl Write) -> Result<bool> {
-        let method = request.get("method").and_then(|m| m.as_str()).unwrap_or("");
+    fn send_response(response: &Value, writer: &mut impl Write) -> Result<()> {
+        let response_str = response.to_string();
+        writer.write_all(response_str.as_bytes())?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
 
-        let id = request.get("id");
+/// A line of stdin
+#[derive(Debug, PartialEq)]
+enum Incoming {
+    Line(String),
+    /// A line of `size` bytes, larger than `max_payload_bytes`, discarded
+    /// unparsed; `sampling_ids` are the pending sampling requests it names
+    Oversized {
+        size: usize,
+        sampling_ids: Vec<String>,
+    },
+}
 
-        // Check if this is a notification (no ID field)
-        // According to JSON-RPC spec, we should never respond to notifications
-        let is_notification = id.is_none();
+/// Next line of `reader`, of which at most `limit` bytes are kept in memory;
+/// `None` at EOF
+fn read_message(
+    reader: &mut impl BufRead,
+    limit: usize,
+    sampler: &SamplingClient,
+) -> io::Result<Option<Incoming>> {
+    let mut line = Vec::new();
+    let read = reader
+        .by_ref()
+        .take(limit as u64 + 1)
+        .read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    if line.len() <= limit {
+        let line =
+            String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        return Ok(Some(Incoming::Line(line)));
+    }
 
-        match method {
-            "initialize" => {
-                // Only send response if this is a request (has ID), not a notification
-                if !is_notification {
-                    let mut response = json!({
-                        "jsonrpc": "2.0",
-                        "result": {
-                            "protocolVersion": "2024-11-05",
-                            "capabilities": {
-                                "tools": {
-                                    "listChanged": false
-                                },
-                                "logging": {}
-                            },
-                            "serverInfo": {
-                                "name": "ktme-mcp-server",
-                                "version": "0.1.0"
-                            }
-                        }
-                    });
-                    // Only add ID field if this is not a notification
-                    if let Some(request_id) = id {
-                        response["id"] = request_id.clone();
-                    }
-                    self.send_response(&response, writer)?;
-                }
+    // Discard the rest of the line, looking for the ids of the pending
+    // sampling requests as `"<id>"` in case it is the answer to one
+    let needles: Vec<(String, Vec<u8>)> = sampler
+        .pending_ids()
+        .into_iter()
+        .map(|id| {
+            let needle = format!("\"{}\"", id).into_bytes();
+            (id, needle)
+        })
+        .collect();
+    let overlap = needles.iter().map(|(_, n)| n.len()).max().unwrap_or(1) - 1;
+    let mut sampling_ids = Vec::new();
+    let mut size = line.len();
+    let mut window = line;
+    let mut done = false;
+    loop {
+        for (id, needle) in &needles {
+            if !sampling_ids.contains(id) && window.windows(needle.len()).any(|w| w == needle) {
+                sampling_ids.push(id.clone());
             }
-            "tools/list" => {
-                // Only send response if this is a request (has ID), not a notification
-                if !is_notification {
-                    let tools = vec![
-                        json!({
-                            "name": "read_changes",
-                            "description": "Read extracted code changes from Git",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "source": {
-                                        "type": "string",
-                                        "description": "Source identifier (commit hash, 'staged', or file path)"
-                                    }
-                                },
-                                "required": ["source"]
-                            }
-                        }),
-                        json!({
-                            "name": "get_service_mapping",
-                            "description": "Get documentation location for a service",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "service": {
-                                        "type": "string",
-                                        "description": "Service name"
-                                    }
-                                },
-                                "required": ["service"]
-                            }
-                        }),
-                        json!({
-                            "name": "list_services",
-                            "description": "List all mapped services",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {}
-                            }
-                        }),
-                        json!({
-                            "name": "generate_documentation",
-                            "description": "Generate documentation from code changes",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "service": {
-                                        "type": "string",
-                                        "description": "Service name"
-                                    },
-                                    "changes": {
-                                        "type": "string",
-                                        "description": "JSON string of extracted changes"
-                                    },
-                                    "format": {
-                                        "type": "string",
-                                        "description": "Output format (markdown, json)",
-                                        "enum": ["markdown", "json"]
-                                    }
-                                },
-                                "required": ["service", "changes"]
-                            }
-                        }),
-                        json!({
-                            "name": "update_documentation",
-                            "description": "Update existing documentation",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "service": {
-                                        "type": "string",
-                                        "description": "Service name"
-                                    },
-                                    "doc_path": {
-                                        "type": "string",
-                                        "description": "Path to documentation file"
-                                    },
-                                    "content": {
-                                        "type": "string",
-                                        "description": "Content to append/update"
-                                    }
-                                },
-                                "required": ["service", "doc_path", "content"]
-                            }
-                        }),
-                        json!({
-                            "name": "ktme_get_knowledge_tree",
-                            "description": "Return the knowledge tree (services -> features -> sub-features) as JSON, optionally with a Mermaid diagram",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "service": {
-                                        "type": "string",
-                                        "description": "Optional service name filter. Omit to return all services."
-                                    },
-                                    "depth": {
-                                        "type": "integer",
-                                        "description": "Traversal depth: 0=services only, 1=+features, 2+=+relations (default: 2)",
-                                        "default": 2
-                                    },
-                                    "include_mermaid": {
-                                        "type": "boolean",
-                                        "description": "Append a Mermaid flowchart to the JSON output (default: false)",
-                                        "default": false
-                                    }
-                                }
-                            }
-                        }),
-                        json!({
-                            "name": "ktme_get_feature_context",
-                            "description": "Return all context for a single feature: description, service, documents, parents, and children",
-                            "inputSchema": {
-                                "type": "object",
-                                "properties": {
-                                    "feature_id": {
-                                        "type": "string",
-                                        "description": "Feature UUID. If provided, feature_name and service_name are ignored."
-                                    },
-                                    "feature_name": {
-                                        "type": "string",
-                                        "description": "Feature name (case-insensitive). Requires service_name."
-                                    },
-                                    "service_name": {
-                                        "type": "string",
-                                        "description": "Service name, used together with feature_name for lookup."
-                                    }
-                                }
-                            }
-                        }),
-                    ];
+        }
+        if done {
+            break;
+        }
+        window.drain(..window.len().saturating_sub(overlap));
 
-                    // Build response without ID field initially
-                    let mut response = json!({
-                        "jsonrpc": "2.0",
-                        "result": {
-                            "tools": tools
-                        }
-                    });
-                    // Only add ID field if this is not a notification
-                    if let Some(request_id) = id {
-                        response["id"] = request_id.clone();
-                    }
-                    self.send_response(&response, writer)?;
-                }
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        let (content, used) = match chunk.iter().position(|&b| b == b'\n') {
+            Some(newline) => {
+                done = true;
+                (&chunk[..newline], newline + 1)
             }
-            "tools/call" => {
-                // Only send response if this is a request (has ID), not a notification
-                if !is_notification {
-                    let empty_params = json!({});
-                    let params = request.get("params").unwrap_or(&empty_params);
-                    let tool_name = params.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            None => (chunk, chunk.len()),
+        };
+        size += content.len();
+        window.extend_from_slice(content);
+        reader.consume(used);
+    }
 
-                    let empty_args = json!({});
-                    let arguments = params.get("arguments").unwrap_or(&empty_args);
+    Ok(Some(Incoming::Oversized { size, sampling_ids }))
+}
 
-                    let result = match tool_name {
-                        "read_changes" => {
-                            if let Some(source) = arguments.get("source").and_then(|s| s.as_str()) {
-                                McpTools::read_changes(source)
-                                    .unwrap_or_else(|e| format!("Error: {}", e))
-                            } else {
-                                "Error: No source provided".to_string()
-                            }
-                        }
-                        "get_service_mapping" => {
-                            if let Some(service) = arguments.get("service").and_then(|s| s.as_str())
-                            {
-                                McpTools::get_service_mapping(service)
-                                    .unwrap_or_else(|e| format!("Error: {}", e))
-                            } else {
-                                "Error: No service provided".to_string()
-                            }
-                        }
-                        "list_services" => McpTools::list_services()
-                            .map(|s| format!("Services: {}", s.join(", ")))
-                            .unwrap_or_else(|e| format!("Error: {}", e)),
-                        "generate_documentation" => {
-                            let service = arguments
-                                .get("service")
-                                .and_then(|s| s.as_str())
-                                .unwrap_or("");
-                            let changes = arguments
-                                .get("changes")
-                                .and_then(|c| c.as_str())
-                                .unwrap_or("");
-                            let format = arguments.get("format").and_then(|f| f.as_str());
+/// Error of a sampling request whose response of `size` bytes was refused
+fn too_large(size: usize, limit: usize) -> KtmeError {
+    KtmeError::Mcp(format!(
+        "The sampling response has {} bytes, more than [mcp] max_payload_bytes ({})",
+        size, limit
+    ))
+}
 
-                            McpTools::generate_documentation(service, changes, format)
-                                .unwrap_or_else(|e| format!("Error: {}", e))
-                        }
-                        "update_documentation" => {
-                            let service = arguments
-                                .get("service")
-                                .and_then(|s| s.as_str())
-                                .unwrap_or("");
-                            let doc_path = arguments
-                                .get("doc_path")
-                                .and_then(|d| d.as_str())
-                                .unwrap_or("");
-                            let content = arguments
-                                .get("content")
-                                .and_then(|c| c.as_str())
-                                .unwrap_or("");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
 
-                            McpTools::update_documentation(service, doc_path, content)
-                                .unwrap_or_else(|e| format!("Error: {}", e))
-                        }
-                        "ktme_get_knowledge_tree" => {
-                            let service = arguments.get("service").and_then(|s| s.as_str());
-                            let depth = arguments
-                                .get("depth")
-                                .and_then(|d| d.as_u64())
-                                .map(|d| d as u32);
-                            let include_mermaid = arguments
-                                .get("include_mermaid")
-                                .and_then(|v| v.as_bool())
-                                .unwrap_or(false);
+    #[test]
+    fn test_read_message_caps_long_lines() {
+        let (outgoing, _requests) = mpsc::unbounded_channel();
+        let sampler = SamplingClient::new(outgoing);
+        let input = format!("{}\n{}\n{}", "short", "x".repeat(100), "last");
+        // A small buffer, so that the long line is discarded in chunks
+        let mut reader = io::BufReader::with_capacity(8, Cursor::new(input));
 
-                            McpTools::get_knowledge_tree(service, depth, include_mermaid)
-                                .unwrap_or_else(|e| format!("Error: {}", e))
-                        }
-                        "ktme_get_feature_context" => {
-                            let feature_id = arguments.get("feature_id").and_then(|v| v.as_str());
-                            let feature_name =
-                                arguments.get("feature_name").and_then(|v| v.as_str());
-                            let service_name =
-                                arguments.get("service_name").and_then(|v| v.as_str());
+        let mut read = || read_message(&mut reader, 20, &sampler).unwrap();
+        assert_eq!(read(), Some(Incoming::Line("short".to_string())));
+        assert_eq!(
+            read(),
+            Some(Incoming::Oversized {
+                size: 100,
+                sampling_ids: Vec::new()
+            })
+        );
+        assert_eq!(read(), Some(Incoming::Line("last".to_string())));
+        assert_eq!(read(), None);
+    }
 
-                            McpTools::get_feature_context(feature_id, feature_name, service_name)
-                                .unwrap_or_else(|e| format!("Error: {}", e))
-                        }
-                        _ => {
-                            format!("Unknown tool: {}", tool_name)
-                        }
-                    };
+    #[tokio::test]
+    async fn test_oversized_sampling_response_names_its_request() {
+        let (outgoing, mut requests) = mpsc::unbounded_channel();
+        let sampler = std::sync::Arc::new(SamplingClient::new(outgoing));
+        let asking = sampler.clone();
+        tokio::spawn(async move {
+            asking
+                .create_message("Summarize", 512, std::time::Duration::from_secs(5))
+                .await
+        });
+        let request = requests.recv().await.unwrap();
 
-                    // Build response without ID field initially
-                    let mut response = json!({
-                        "jsonrpc": "2.0",
-                        "result": {
-                            "content": [{
-                                "type": "text",
-                                "text": result
-                            }]
-                        }
-                    });
-                    // Only add ID field if this is not a notification
-                    if let Some(request_id) = id {
-                        response["id"] = request_id.clone();
-                    }
-                    self.send_response(&response, writer)?;
-                }
-            }
-            _ => {
-                // Only send response if this is a request (has ID), not a notification
-                if !is_notification {
-                    let mut response = json!({
-                        "jsonrpc": "2.0",
-                        "error": {
-                            "code": -32601,
-                            "message": "Method not found"
-                        }
-                    });
-                    // Only add ID field if this is not a notification
-                    if let Some(request_id) = id {
-                        response["id"] = request_id.clone();
-                    }
-                    self.send_response(&response, writer)?;
-                }
+        // The id comes after the large content
+        let response = json!({
+            "jsonrpc": "2.0",
+            "result": { "content": { "type": "text", "text": "y".repeat(200) } },
+            "id": request["id"],
+        });
+        let mut reader = io::BufReader::with_capacity(16, Cursor::new(format!("{}\n", response)));
+        match read_message(&mut reader, 50, &sampler).unwrap() {
+            Some(Incoming::Oversized { sampling_ids, .. }) => {
+                assert_eq!(sampling_ids, vec![request["id"].as_str().unwrap()])
             }
+            other => panic!("expected an oversized line, got {:?}", other),
         }
-
-        Ok(true)
-    }
-
-    fn send_response(&self, response: &Value, writer: &mut impl Write) -> Result<()> {
-        let response_str = response.to_string();
-        writer.write_all(response_str.as_bytes())?;
-        writer.write_all(b"\n")?;
-        writer.flush()?;
-        Ok(())
     }
 }
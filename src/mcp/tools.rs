@@ -1,28 +1,174 @@
 use crate::ai::AIClient;
-use crate::error::Result;
-use crate::git::reader::GitReader;
+use crate::cli::commands::generate::record_history;
+use crate::config::{Config, StorageConfig};
+use crate::doc::idempotency::GenerationKey;
+use crate::doc::lint;
+use crate::doc::providers::markdown::MarkdownProvider;
+use crate::engine::KtmeEngine;
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use crate::git::ownership::{self, AUTO_SERVICE};
+use crate::git::reader::{DiffLimits, GitReader};
+use crate::mcp::sampling;
 use crate::service_detector::ServiceDetector;
-use crate::storage::mapping::StorageManager;
-use serde_json;
+use crate::shutdown;
+use crate::storage::backend::{open_sqlite, open_storage, Storage};
+use crate::storage::lock::ServiceLock;
+use crate::storage::mapping::{ServiceSearchResult, StorageManager};
+use crate::storage::models::{FeatureType, SearchQuery};
+use crate::workflow;
+use serde_json::{self, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Results per page of the paginated search tools, at most
+const MAX_PAGE_SIZE: usize = 100;
+
+/// Matching features read to find the services owning them
+const MAX_FEATURE_MATCHES: u32 = 1000;
+
+/// What a tool call runs against: the repository it reads and the
+/// configuration (and so the workspace) it uses. Each call carries its own
+/// context instead of relying on the server's working directory, so that
+/// concurrent calls for different repositories do not interfere.
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    /// Repository or project directory; relative paths in the arguments
+    /// are resolved against it
+    pub repo_path: PathBuf,
+    pub config: Arc<Config>,
+}
 
-pub struct McpTools;
+impl ToolContext {
+    pub fn new(repo_path: PathBuf, config: Config) -> Self {
+        Self {
+            repo_path,
+            config: Arc::new(config),
+        }
+    }
+
+    /// Context of the server process: its working directory and the loaded
+    /// configuration
+    pub fn from_env() -> Result<Self> {
+        Ok(Self::new(std::env::current_dir()?, Config::load()?))
+    }
+
+    /// This context narrowed by the optional `repo_path` and `workspace`
+    /// arguments of a tool call
+    pub fn for_call(&self, arguments: &Value) -> Result<Self> {
+        let mut context = self.clone();
+        if let Some(repo_path) = arguments.get("repo_path").and_then(|p| p.as_str()) {
+            let path = self.resolve(repo_path);
+            if !path.is_dir() {
+                return Err(KtmeError::InvalidInput(format!(
+                    "repo_path {} is not a directory",
+                    path.display()
+                )));
+            }
+            context.repo_path = path;
+        }
+        if let Some(workspace) = arguments.get("workspace").and_then(|w| w.as_str()) {
+            if workspace.is_empty() {
+                return Err(KtmeError::InvalidInput("workspace is empty".to_string()));
+            }
+            Arc::make_mut(&mut context.config).storage.workspace = workspace.to_string();
+        }
+        Ok(context)
+    }
+
+    /// `path` relative to the repository of this context
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        self.repo_path.join(path)
+    }
+
+    /// Storage backend of this context's workspace, shared by all calls
+    /// for that workspace
+    pub fn storage(&self) -> Result<Arc<dyn Storage>> {
+        shared_storage(&self.config.storage)
+    }
+
+    /// Mappings of this context's workspace
+    fn storage_manager(&self) -> Result<StorageManager> {
+        StorageManager::from_config(&self.config)
+    }
+
+    /// Reader for the repository with the configured diff size limits
+    fn git_reader(&self) -> Result<GitReader> {
+        Ok(GitReader::discover(&self.repo_path)?
+            .with_limits(DiffLimits::from_config(&self.config.git)))
+    }
+}
+
+/// Storage backend of the workspace selected by `config`, opened once per
+/// server process
+fn shared_storage(config: &StorageConfig) -> Result<Arc<dyn Storage>> {
+    static STORAGES: OnceLock<Mutex<HashMap<String, Arc<dyn Storage>>>> = OnceLock::new();
+
+    let mut storages = STORAGES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap();
+    if let Some(storage) = storages.get(&config.workspace) {
+        return Ok(storage.clone());
+    }
+    let storage: Arc<dyn Storage> = Arc::from(open_storage(config)?);
+    storages.insert(config.workspace.clone(), storage.clone());
+    Ok(storage)
+}
+
+/// MCP tools, run against the context of one call
+pub struct McpTools {
+    context: ToolContext,
+}
+
+/// Run `future` for a synchronous tool: inside the server's runtime on a
+/// blocking section, so that its other tasks keep reading the client's
+/// messages, otherwise on a runtime of its own
+fn block_on<F: std::future::Future>(future: F) -> Result<F::Output> {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => Ok(tokio::task::block_in_place(|| handle.block_on(future))),
+        Err(_) => {
+            let rt = tokio::runtime::Runtime::new().map_err(|e| {
+                crate::error::KtmeError::Storage(format!("Failed to create runtime: {}", e))
+            })?;
+            Ok(rt.block_on(future))
+        }
+    }
+}
+
+/// Replace the Markdown file at `path` with `content` through the Markdown
+/// provider, creating it when missing
+fn write_markdown(path: &Path, content: &str) -> Result<()> {
+    let location = path.to_string_lossy();
+    block_on(MarkdownProvider::for_file(&location).write_document(&location, content))??;
+    Ok(())
+}
 
 impl McpTools {
-    pub fn read_changes(file_path: &str) -> Result<String> {
+    pub fn new(context: ToolContext) -> Self {
+        Self { context }
+    }
+
+    pub fn context(&self) -> &ToolContext {
+        &self.context
+    }
+
+    pub fn read_changes(&self, file_path: &str) -> Result<String> {
         tracing::info!("MCP Tool: read_changes({})", file_path);
 
         // Check if file_path is a Git reference or a file
         if file_path.starts_with("commit:") {
             let commit_ref = &file_path[7..]; // Remove "commit:" prefix
-            let reader = GitReader::new(None)?;
+            let reader = self.context.git_reader()?;
             let diff = reader.read_commit(commit_ref)?;
             Ok(serde_json::to_string_pretty(&diff)?)
         } else if file_path == "staged" {
-            let reader = GitReader::new(None)?;
+            let reader = self.context.git_reader()?;
             let diff = reader.read_staged()?;
             Ok(serde_json::to_string_pretty(&diff)?)
         } else if file_path.contains("..") {
-            let reader = GitReader::new(None)?;
+            let reader = self.context.git_reader()?;
             let diffs = reader.read_commit_range(file_path)?;
             Ok(serde_json::to_string_pretty(&diffs)?)
         } else if file_path == "HEAD"
@@ -31,31 +177,33 @@ impl McpTools {
             || file_path.len() == 40
         {
             // Handle raw commit hashes and Git references
-            let reader = GitReader::new(None)?;
+            let reader = self.context.git_reader()?;
             let diff = reader.read_commit(file_path)?;
             Ok(serde_json::to_string_pretty(&diff)?)
         } else {
             // Try to read as a file containing diff content
-            std::fs::read_to_string(file_path).map_err(|e| crate::error::KtmeError::Io(e))
+            std::fs::read_to_string(self.context.resolve(file_path))
+                .map_err(crate::error::KtmeError::Io)
         }
     }
 
-    pub fn get_service_mapping(service: &str) -> Result<String> {
+    pub fn get_service_mapping(&self, service: &str) -> Result<String> {
         tracing::info!("MCP Tool: get_service_mapping({})", service);
 
-        let storage = StorageManager::new()?;
+        let storage = self.context.storage_manager()?;
         let mapping = storage.get_mapping(service)?;
         Ok(serde_json::to_string_pretty(&mapping)?)
     }
 
-    pub fn list_services() -> Result<Vec<String>> {
+    pub fn list_services(&self) -> Result<Vec<String>> {
         tracing::info!("MCP Tool: list_services()");
 
-        let storage = StorageManager::new()?;
+        let storage = self.context.storage_manager()?;
         storage.list_services()
     }
 
     pub fn generate_documentation(
+        &self,
         service: &str,
         changes: &str,
         format: Option<&str>,
@@ -67,7 +215,7 @@ impl McpTools {
         );
 
         // Auto-initialize service if not present
-        Self::ensure_service_initialized(service)?;
+        self.ensure_service_initialized(service)?;
 
         // Parse the changes
         let diff: crate::git::diff::ExtractedDiff =
@@ -76,17 +224,15 @@ impl McpTools {
             })?;
 
         // Try to use AI for intelligent documentation generation
-        match AIClient::new() {
+        match self.ai_client() {
             Ok(ai_client) => {
-                tracing::info!("Using AI client for documentation generation");
-                // Create async runtime for AI call
-                let rt = tokio::runtime::Runtime::new().map_err(|e| {
-                    crate::error::KtmeError::Storage(format!("Failed to create runtime: {}", e))
-                })?;
-
-                rt.block_on(Self::generate_ai_documentation_async(
+                tracing::info!(
+                    "Using {} for documentation generation",
+                    ai_client.provider_name()
+                );
+                block_on(Self::generate_ai_documentation_async(
                     &ai_client, service, &diff, format,
-                ))
+                ))?
             }
             Err(_) => {
                 tracing::warn!("AI client not available, falling back to basic documentation");
@@ -95,17 +241,22 @@ impl McpTools {
         }
     }
 
-    fn ensure_service_initialized(service: &str) -> Result<()> {
-        use crate::storage::database::Database;
-        use crate::storage::repository::ServiceRepository;
+    /// The client's model through MCP sampling when the client offers it,
+    /// otherwise the configured AI provider
+    fn ai_client(&self) -> Result<AIClient> {
+        match sampling::provider() {
+            Some(provider) => Ok(AIClient::with_provider(Box::new(provider))),
+            None => AIClient::from_config(&self.context.config.ai),
+        }
+    }
 
-        let db = Database::new(None)?;
-        let service_repo = ServiceRepository::new(db);
+    fn ensure_service_initialized(&self, service: &str) -> Result<()> {
+        let storage = self.context.storage()?;
 
         // Check if service exists, create if not
-        if service_repo.get_by_name(service)?.is_none() {
+        if storage.get_service_by_name(service)?.is_none() {
             tracing::info!("Auto-initializing service: {}", service);
-            service_repo.create(service, None, Some(&format!("Auto-initialized via MCP")))?;
+            storage.create_service(service, None, Some("Auto-initialized via MCP"))?;
         }
 
         Ok(())
@@ -127,6 +278,7 @@ impl McpTools {
             - Total files: {}\n\
             - Additions: {}\n\
             - Deletions: {}\n\n\
+            {}\n\n\
             Please generate:\n\
             1. A clear overview of what changed\n\
             2. Technical details of the implementation\n\
@@ -139,16 +291,22 @@ impl McpTools {
             diff.author,
             diff.timestamp,
             diff.files.iter()
-                .map(|f| format!("  - {}: {} (+{}/-{})", f.path, f.status, f.additions, f.deletions))
+                .map(|f| format!("  - {}: {} (+{}/-{})", f.display_path(), f.status, f.additions, f.deletions))
                 .collect::<Vec<_>>()
                 .join("\n"),
             diff.summary.total_files,
             diff.summary.total_additions,
             diff.summary.total_deletions,
+            crate::ai::prompts::PromptTemplates::format_pull_request_context(diff).trim_start(),
             format.unwrap_or("markdown")
         );
 
-        match ai_client.generate_documentation(&prompt).await {
+        match shutdown::or_cancelled(
+            "Generating documentation",
+            ai_client.generate_documentation(&prompt),
+        )
+        .await
+        {
             Ok(documentation) => Ok(documentation),
             Err(e) => {
                 tracing::warn!(
@@ -190,7 +348,7 @@ impl McpTools {
                     diff.summary.total_additions,
                     diff.summary.total_deletions,
                     diff.files.iter()
-                        .map(|f| format!("- **{}**: {} ({}, +{}/-{})", f.path, f.status, f.path, f.additions, f.deletions))
+                        .map(|f| format!("- **{}**: {} ({}, +{}/-{})", f.display_path(), f.status, f.path, f.additions, f.deletions))
                         .collect::<Vec<_>>()
                         .join("\n"),
                     diff.message
@@ -199,65 +357,128 @@ impl McpTools {
         }
     }
 
-    pub fn update_documentation(service: &str, doc_path: &str, content: &str) -> Result<String> {
+    pub fn update_documentation(
+        &self,
+        service: &str,
+        doc_path: &str,
+        content: &str,
+    ) -> Result<String> {
         tracing::info!(
             "MCP Tool: update_documentation(service={}, doc_path={})",
             service,
             doc_path
         );
 
-        // For now, just write to the file
-        std::fs::write(doc_path, content).map_err(|e| crate::error::KtmeError::Io(e))?;
+        // Fails while a CLI run publishes the same service
+        let _lock =
+            ServiceLock::try_acquire(&self.context.config, service, "update_documentation")?;
+
+        write_markdown(&self.context.resolve(doc_path), content)?;
 
         Ok(format!("Documentation updated at {}", doc_path))
     }
 
-    /// Search services by query string
-    pub fn search_services(query: &str) -> Result<String> {
-        tracing::info!("MCP Tool: search_services(query={})", query);
-
-        let storage = StorageManager::new()?;
-        let results = storage.search_services(query)?;
-
-        if results.is_empty() {
-            return Ok(format!("No services found matching: {}", query));
-        }
-
-        let mut output = format!("Search Results for '{}':\n\n", query);
-        for (idx, result) in results.iter().enumerate() {
-            output.push_str(&format!(
-                "{}. **{}** (Relevance: {:.1})\n",
-                idx + 1,
-                result.name,
-                result.relevance_score
-            ));
+    /// Search services by name, description and path, and by the features
+    /// they own. Returns a page of services as JSON.
+    ///
+    /// Filters:
+    ///   - `limit`/`offset`: page size (default 10) and services to skip
+    ///   - `min_score`: lowest relevance score of a service
+    ///   - `feature_type`: only services owning matching features of this type
+    pub fn search_services(&self, query: &str, filters: &SearchFilters) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: search_services(query={}, filters={:?})",
+            query,
+            filters
+        );
 
-            if let Some(ref desc) = result.description {
-                output.push_str(&format!("   Description: {}\n", desc));
-            }
+        let manager = self.context.storage_manager()?;
+        let storage = self.context.storage()?;
+        let mut services = if filters.feature_type.is_some() {
+            Vec::new()
+        } else {
+            manager.search_services(query)?
+        };
 
-            if let Some(ref path) = result.path {
-                output.push_str(&format!("   Path: {}\n", path));
+        // Services owning matching features score a point per feature
+        let features = storage.search_features(&SearchQuery {
+            query: query.to_string(),
+            service_ids: None,
+            feature_types: filters.feature_type.map(|t| vec![t]),
+            content_types: None,
+            limit: Some(MAX_FEATURE_MATCHES),
+            offset: None,
+            min_score: None,
+            similarity_threshold: None,
+            include_related: false,
+            depth: None,
+        })?;
+        let mut owned: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for feature in features {
+            let names = owned.entry(feature.service_name).or_default();
+            if !names.contains(&feature.feature_name) {
+                names.push(feature.feature_name);
             }
-
-            if !result.docs.is_empty() {
-                output.push_str("   Documentation:\n");
-                for doc in &result.docs {
-                    output.push_str(&format!("     - {}\n", doc));
+        }
+        let mut matched_features = HashMap::new();
+        for (name, features) in owned {
+            match services.iter_mut().find(|service| service.name == name) {
+                Some(service) => service.relevance_score += features.len() as f32,
+                None => {
+                    let mapping = manager.get_mapping(&name).ok();
+                    services.push(ServiceSearchResult {
+                        path: mapping.as_ref().and_then(|m| m.path.clone()),
+                        description: storage
+                            .get_service_by_name(&name)?
+                            .and_then(|s| s.description),
+                        docs: mapping
+                            .map(|m| {
+                                m.docs
+                                    .into_iter()
+                                    .map(|d| format!("{}: {}", d.r#type, d.location))
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                        relevance_score: features.len() as f32,
+                        name: name.clone(),
+                    });
                 }
             }
-
-            output.push('\n');
+            matched_features.insert(name, features);
         }
 
-        Ok(output)
+        services.retain(|service| {
+            filters
+                .min_score
+                .is_none_or(|min| service.relevance_score as f64 >= min)
+        });
+        services.sort_by(|a, b| {
+            b.relevance_score
+                .total_cmp(&a.relevance_score)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        let page: Vec<Value> = services
+            .into_iter()
+            .skip(filters.offset())
+            .take(filters.limit() + 1)
+            .map(|service| {
+                let mut value = serde_json::to_value(&service).unwrap_or_default();
+                value["matched_features"] = matched_features
+                    .remove(&service.name)
+                    .unwrap_or_default()
+                    .into();
+                value
+            })
+            .collect();
+
+        filters.page(query, page)
     }
 
     /// Search services by feature
-    pub fn search_by_feature(feature: &str) -> Result<String> {
+    pub fn search_by_feature(&self, feature: &str) -> Result<String> {
         tracing::info!("MCP Tool: search_by_feature(feature={})", feature);
 
-        let storage = StorageManager::new()?;
+        let storage = self.context.storage_manager()?;
         let results = storage.search_by_feature(feature)?;
 
         if results.is_empty() {
@@ -276,36 +497,388 @@ impl McpTools {
         Ok(output)
     }
 
-    /// Search services by keyword
-    pub fn search_by_keyword(keyword: &str) -> Result<String> {
-        tracing::info!("MCP Tool: search_by_keyword(keyword={})", keyword);
+    /// Search features, including the indexed content of their documents,
+    /// by keyword. Returns a page of matches as JSON, most relevant first.
+    ///
+    /// Filters:
+    ///   - `limit`/`offset`: page size (default 10) and matches to skip
+    ///   - `min_score`: lowest relevance score of a match
+    ///   - `feature_type`: only features of this type
+    ///   - `service`: only features of this service
+    pub fn search_by_keyword(&self, keyword: &str, filters: &SearchFilters) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: search_by_keyword(keyword={}, filters={:?})",
+            keyword,
+            filters
+        );
 
-        let storage = StorageManager::new()?;
-        let results = storage.search_by_keyword(keyword)?;
+        let storage = self.context.storage()?;
+        let service_ids = match &filters.service {
+            Some(name) => Some(vec![
+                storage
+                    .get_service_by_name(name)?
+                    .ok_or_else(|| KtmeError::NotFound(format!("Service '{}' not found", name)))?
+                    .id,
+            ]),
+            None => None,
+        };
+        let results = storage.search_features(&SearchQuery {
+            query: keyword.to_string(),
+            service_ids,
+            feature_types: filters.feature_type.map(|t| vec![t]),
+            content_types: None,
+            // One more than a page tells whether there is a next one
+            limit: Some(filters.limit() as u32 + 1),
+            offset: Some(filters.offset() as u32),
+            min_score: filters.min_score,
+            similarity_threshold: None,
+            include_related: false,
+            depth: None,
+        })?;
+        crate::cli::commands::feature::record_usage(
+            storage.as_ref(),
+            results
+                .iter()
+                .take(filters.limit())
+                .map(|result| result.feature_id.as_str()),
+        );
 
-        if results.is_empty() {
-            return Ok(format!("No services found matching keyword: {}", keyword));
+        filters.page(keyword, results)
+    }
+
+    /// Search the content of generated and published documentation: the
+    /// documents mapped to services, the search index and the documents of
+    /// the saved providers. Returns ranked snippets with document URLs.
+    ///
+    /// Parameters:
+    ///   - `query`: words to look for
+    ///   - `service`: only search the documents of this service; saved
+    ///     providers are searched only without it
+    ///   - `limit`: maximum number of documents (default 10)
+    pub async fn search_documentation(
+        &self,
+        query: &str,
+        service: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: search_documentation(query={}, service={:?}, limit={:?})",
+            query,
+            service,
+            limit
+        );
+
+        use crate::doc::providers::config::MarkdownConfig;
+        use crate::doc::providers::{DocumentProvider, ProviderFactory};
+
+        if query.trim().is_empty() {
+            return Err(crate::error::KtmeError::InvalidInput(
+                "Search query is empty".to_string(),
+            ));
         }
+        let limit = limit.unwrap_or(10).max(1);
+        let config = &self.context.config;
+        let storage = self.context.storage_manager()?;
+        let services = match service {
+            Some(service) => vec![service.to_string()],
+            None => storage.list_services()?,
+        };
 
-        let mut output = format!("Keyword search results for '{}':\n\n", keyword);
-        for result in results {
+        let mut hits = Vec::new();
+        for name in &services {
+            for doc in storage.get_mapping(name)?.docs {
+                let path = self.context.resolve(&doc.location);
+                let local = |base_path: &str| {
+                    MarkdownProvider::new(MarkdownConfig {
+                        base_path: base_path.to_string(),
+                        extension: "md".to_string(),
+                        auto_create_dirs: false,
+                        backup: false,
+                    })
+                };
+                let documents = if path.is_dir() {
+                    local(&path.to_string_lossy())
+                        .search_documents(query, None)
+                        .await
+                } else if path.is_file() {
+                    local(".")
+                        .get_document(&path.to_string_lossy())
+                        .await
+                        .map(|document| document.into_iter().collect())
+                } else {
+                    continue;
+                };
+                match documents {
+                    Ok(documents) => hits.extend(documents.into_iter().filter_map(|document| {
+                        DocumentHit::from_document(query, document, Some(name), "mapping")
+                    })),
+                    Err(e) => tracing::warn!("Could not search {}: {}", doc.location, e),
+                }
+            }
+        }
+
+        match storage.search_features_by_text(query, Some(limit as u32 * 2)) {
+            Ok(results) => hits.extend(
+                results
+                    .into_iter()
+                    .filter(|result| service.is_none_or(|service| result.service_name == service))
+                    .filter_map(|result| DocumentHit::from_index(query, result)),
+            ),
+            Err(e) => tracing::debug!("Search index unavailable: {}", e),
+        }
+
+        if service.is_none() {
+            let providers = open_storage(&config.storage)
+                .and_then(|storage| storage.list_provider_configs())
+                .unwrap_or_default();
+            for provider in providers {
+                let found =
+                    match ProviderFactory::create(&provider.provider_type, provider.clone().into())
+                    {
+                        Ok(instance) => instance.search_documents(query, Some(limit)).await,
+                        Err(e) => Err(e),
+                    };
+                match found {
+                    Ok(documents) => hits.extend(documents.into_iter().filter_map(|document| {
+                        DocumentHit::from_document(query, document, None, &provider.provider_type)
+                    })),
+                    Err(e) => tracing::warn!("Could not search {}: {}", provider.provider_type, e),
+                }
+            }
+        }
+
+        let hits = DocumentHit::rank(hits, limit);
+        if let Ok(storage) = self.context.storage() {
+            crate::cli::commands::feature::record_usage(
+                storage.as_ref(),
+                hits.iter().filter_map(|hit| hit.feature_id.as_deref()),
+            );
+        }
+        if hits.is_empty() {
+            return Ok(format!("No documentation found matching: {}", query));
+        }
+
+        let mut output = format!("Documentation matching '{}':\n\n", query);
+        for (idx, hit) in hits.iter().enumerate() {
             output.push_str(&format!(
-                "• **{}** (Score: {:.1})\n",
-                result.name, result.relevance_score
+                "{}. **{}** (Score: {})\n   URL: {}\n",
+                idx + 1,
+                hit.title,
+                hit.score,
+                hit.url
             ));
+            if let Some(ref service) = hit.service {
+                output.push_str(&format!("   Service: {}\n", service));
+            }
+            output.push_str(&format!("   Source: {}\n", hit.source));
+            for snippet in &hit.snippets {
+                output.push_str(&format!("   > {}\n", snippet));
+            }
+            output.push('\n');
+        }
 
-            if let Some(ref path) = result.path {
-                output.push_str(&format!("  Path: {}\n", path));
+        Ok(output)
+    }
+
+    /// Content of the documents mapped to `service` as Markdown, Confluence
+    /// pages converted from their storage format, each after a comment
+    /// naming its location.
+    ///
+    /// Parameters:
+    ///   - `service`: service whose documentation is read
+    ///   - `location`: only read the mapped document at this location
+    pub async fn read_documentation(
+        &self,
+        service: &str,
+        location: Option<&str>,
+    ) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: read_documentation(service={}, location={:?})",
+            service,
+            location
+        );
+
+        let docs: Vec<_> = self
+            .context
+            .storage_manager()?
+            .get_mapping(service)?
+            .docs
+            .into_iter()
+            .filter(|doc| location.is_none_or(|location| doc.location == location))
+            .collect();
+        if docs.is_empty() {
+            return Err(KtmeError::DocumentNotFound(match location {
+                Some(location) => format!(
+                    "{} is not a documentation location of service {}",
+                    location, service
+                ),
+                None => format!("No documentation locations mapped for service {}", service),
+            }));
+        }
+
+        let mut output = String::new();
+        for doc in docs {
+            let mut resolved = doc.clone();
+            if doc.r#type == "markdown" {
+                resolved.location = self.context.resolve(&doc.location).display().to_string();
             }
+            let content =
+                match crate::cli::commands::mapping::read_location(&self.context.config, &resolved)
+                    .await
+                {
+                    Ok(Some(document)) => document.content,
+                    Ok(None) => "(document not found)\n".to_string(),
+                    Err(e) => format!("(could not read the document: {})\n", e),
+                };
+            output.push_str(&format!(
+                "<!-- {} ({}) -->\n{}\n",
+                doc.location, doc.r#type, content
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Services whose documentation lags behind their repository HEAD, most
+    /// commits behind first.
+    ///
+    /// Parameters:
+    ///   - `service`: optional service name filter; None reports all services
+    ///   - `include_fresh`: when true, also lists services that are up to date
+    pub fn stale_docs_report(&self, service: Option<&str>, include_fresh: bool) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: stale_docs_report(service={:?}, include_fresh={})",
+            service,
+            include_fresh
+        );
 
-            output.push_str(&format!("  Documents: {}\n\n", result.docs.len()));
+        use crate::git::staleness;
+        use std::collections::HashMap;
+
+        let manager = self.context.storage_manager()?;
+        let storage = self.context.storage()?;
+        let names_by_id: HashMap<i64, String> = storage
+            .list_services()?
+            .into_iter()
+            .map(|s| (s.id, s.name))
+            .collect();
+        let history = storage.recent_generations(500)?;
+
+        let names = match service {
+            Some(service) => vec![service.to_string()],
+            None => manager.list_services()?,
+        };
+        let mut services = Vec::new();
+        for name in names {
+            let path = manager.get_mapping(&name).ok().and_then(|m| m.path);
+            let records: Vec<_> = history
+                .iter()
+                .filter(|r| r.service_id.and_then(|id| names_by_id.get(&id)) == Some(&name))
+                .collect();
+            let last_success = records
+                .iter()
+                .find(|r| r.status == "success")
+                .map(|r| r.created_at);
+            let head = path.as_deref().and_then(staleness::head);
+            let stale = staleness::is_stale(last_success, head.as_ref().map(|h| h.time));
+            if !stale && !include_fresh {
+                continue;
+            }
+            services.push(serde_json::json!({
+                "service": name,
+                "path": path,
+                "stale": stale,
+                "commits_behind": path
+                    .as_deref()
+                    .and_then(|path| staleness::commits_since(path, last_success)),
+                "last_generated": last_success,
+                "last_status": records.first().map(|r| r.status.clone()),
+                "head": head.as_ref().map(|h| h.commit[..7].to_string()),
+                "head_time": head.map(|h| h.time),
+            }));
         }
+        services.sort_by_key(|s| std::cmp::Reverse(s["commits_behind"].as_u64().unwrap_or(0)));
+
+        let stale = services.iter().filter(|s| s["stale"] == true).count();
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "stale": stale,
+            "services": services,
+            "suggestion": (stale > 0).then_some(
+                "Run automated_documentation_workflow for the stale services"
+            ),
+        }))?)
+    }
+
+    /// Narrative summary of the commits of a range, grouped by area, without
+    /// generating or publishing a document. Falls back to a plain list of
+    /// the commits by area when no AI provider is configured.
+    ///
+    /// Parameters:
+    ///   - `range`: `start..end`, or a single ref meaning `ref..HEAD`
+    ///   - `max_commits`: newest commits summarized (default 100)
+    pub async fn summarize_commit_history(
+        &self,
+        range: &str,
+        max_commits: Option<usize>,
+    ) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: summarize_commit_history(range={}, max_commits={:?})",
+            range,
+            max_commits
+        );
 
+        use crate::ai::prompts::PromptTemplates;
+
+        let range = if range.contains("..") {
+            range.to_string()
+        } else {
+            format!("{}..HEAD", range)
+        };
+        let mut diffs = self.context.git_reader()?.read_commit_range(&range)?;
+        if diffs.is_empty() {
+            return Ok(format!("No commits in {}", range));
+        }
+        diffs.sort_by_key(|diff| {
+            std::cmp::Reverse(chrono::DateTime::parse_from_rfc3339(&diff.timestamp).ok())
+        });
+        diffs.truncate(max_commits.unwrap_or(100).max(1));
+        let history = ExtractedDiff::combine(&range, diffs);
+
+        // The mock provider answers with a canned document, not a summary
+        match self.ai_client() {
+            Ok(ai_client) if ai_client.provider_name() != "Mock" => {
+                let prompt = PromptTemplates::commit_history_prompt(&history);
+                match shutdown::or_cancelled(
+                    "Summarizing commits",
+                    ai_client.generate_documentation(&prompt),
+                )
+                .await
+                {
+                    Ok(summary) => return Ok(summary),
+                    Err(e) => {
+                        tracing::warn!("AI summary failed: {}, falling back to the commit list", e)
+                    }
+                }
+            }
+            _ => tracing::warn!("AI client not available, falling back to the commit list"),
+        }
+
+        let mut output = format!(
+            "## Changes in {}\n\n{} commits by {}\n",
+            range,
+            history.commits.len(),
+            history.author
+        );
+        for (area, commits) in PromptTemplates::commit_areas(&history) {
+            output.push_str(&format!("\n### {}\n", area));
+            for commit in commits {
+                output.push_str(&format!("- {} ({})\n", commit.subject(), commit.short_id()));
+            }
+        }
         Ok(output)
     }
 
     /// Automated workflow: extract → generate → save
-    pub fn automated_documentation_workflow(service: &str, source: &str) -> Result<String> {
+    pub fn automated_documentation_workflow(&self, service: &str, source: &str) -> Result<String> {
         tracing::info!(
             "MCP Tool: automated_documentation_workflow(service={}, source={})",
             service,
@@ -313,47 +886,147 @@ impl McpTools {
         );
 
         // Step 1: Extract changes
-        let changes = Self::read_changes(source)?;
+        let changes = self.read_changes(source)?;
+
+        if service == AUTO_SERVICE {
+            let diff: ExtractedDiff = serde_json::from_str(&changes)?;
+            let repo = git2::Repository::discover(&self.context.repo_path).ok();
+            let resolution = ownership::resolve_services_with(
+                &diff,
+                &self.context.storage_manager()?,
+                repo.as_ref(),
+            )?;
+            let mut report = format!("Resolved services: {}\n", resolution.describe());
+            for (service, scoped) in resolution.scoped_diffs(&diff) {
+                let changes = serde_json::to_string_pretty(&scoped)?;
+                report.push_str(&self.document_service(&service, source, &changes)?);
+            }
+            return Ok(report);
+        }
 
-        // Step 2: Generate documentation
-        let doc_content = Self::generate_documentation(service, &changes, Some("markdown"))?;
+        self.document_service(service, source, &changes)
+    }
 
-        // Step 3: Get service mapping to determine where to save
-        let storage = StorageManager::new()?;
+    /// Generate documentation for `service` from extracted changes and save
+    /// it to the service's markdown mapping, or run its policy when it has
+    /// one
+    fn document_service(&self, service: &str, source: &str, changes: &str) -> Result<String> {
+        if self.context.config.policies.contains_key(service) {
+            let diff: ExtractedDiff = serde_json::from_str(changes).map_err(|_| {
+                crate::error::KtmeError::InvalidInput("Invalid changes format".to_string())
+            })?;
+            return self.run_policy(service, &diff);
+        }
+
+        // Step 2: Skip mapped documentation that already holds these changes
+        let storage = self.context.storage_manager()?;
         let mapping = storage.get_mapping(service)?;
+        let primary_doc = mapping.docs.iter().find(|d| d.r#type == "markdown");
+        let diff: ExtractedDiff = serde_json::from_str(changes).map_err(|_| {
+            crate::error::KtmeError::InvalidInput("Invalid changes format".to_string())
+        })?;
+        let model = self
+            .ai_client()
+            .ok()
+            .and_then(|c| c.model().map(str::to_string));
+        let key = GenerationKey::new(&diff, "mcp-workflow", model.as_deref())
+            .option("format", Some("markdown"))
+            .hash();
+        let history = self.context.storage()?;
+        if let Some(primary_doc) = primary_doc {
+            if let Some(record) = history.find_by_key(&primary_doc.location, &key)? {
+                return Ok(format!(
+                    "✓ Automated workflow skipped {}: {} already documents these changes (since {})\n",
+                    service,
+                    primary_doc.location,
+                    record.created_at.format("%Y-%m-%d %H:%M")
+                ));
+            }
+        }
+
+        // Step 3: Generate documentation
+        let doc_content = self.generate_documentation(service, changes, Some("markdown"))?;
 
         // Step 4: Save documentation
-        if let Some(primary_doc) = mapping.docs.iter().find(|d| d.r#type == "markdown") {
-            std::fs::write(&primary_doc.location, doc_content)
-                .map_err(|e| crate::error::KtmeError::Io(e))?;
+        if let Some(primary_doc) = primary_doc {
+            write_markdown(&self.context.resolve(&primary_doc.location), &doc_content)?;
+            record_history(
+                history.as_ref(),
+                service,
+                "generate",
+                "markdown",
+                Some(&primary_doc.location),
+                &diff,
+                Some(&doc_content),
+                None,
+                model.as_deref(),
+                Some(&key),
+            );
 
             Ok(format!("✓ Automated workflow completed!\n  ✓ Extracted changes from {}\n  ✓ Generated documentation for {}\n  ✓ Saved to: {}\n", source, service, primary_doc.location))
         } else {
             // Save to default location
             let default_path = format!("/tmp/{}-documentation.md", service);
-            std::fs::write(&default_path, doc_content)
-                .map_err(|e| crate::error::KtmeError::Io(e))?;
+            write_markdown(Path::new(&default_path), &doc_content)?;
 
             Ok(format!("✓ Automated workflow completed!\n  ✓ Extracted changes from {}\n  ✓ Generated documentation for {}\n  ✓ Saved to: {} (no markdown mapping found)\n", source, service, default_path))
         }
     }
 
+    /// Run the `[policies.<service>]` workflow shared with `ktme run`:
+    /// generate its document types, lint them, publish them or queue them
+    /// for review, and notify its webhooks
+    fn run_policy(&self, service: &str, diff: &ExtractedDiff) -> Result<String> {
+        let engine = KtmeEngine::builder()
+            .config(self.context.config.as_ref().clone())
+            .repository(self.context.repo_path.clone())
+            .ai_client(self.ai_client()?)
+            .build()?;
+        let report = block_on(workflow::run(&engine, service, diff, false))??;
+
+        let mut output = format!(
+            "{} Automated workflow ran the policy of {} on {}\n",
+            if report.failed() { "✗" } else { "✓" },
+            service,
+            report.source
+        );
+        if report.documents.is_empty() {
+            output.push_str("  - No changes to document\n");
+        }
+        for (document, line) in report.documents.iter().zip(report.describe()) {
+            output.push_str(&format!("  - {}\n", line));
+            for finding in &document.lint {
+                output.push_str(&format!("    - {}\n", finding));
+            }
+        }
+        for notification in &report.notifications {
+            match &notification.error {
+                Some(error) => output.push_str(&format!(
+                    "  - Notifying {} failed: {}\n",
+                    notification.url, error
+                )),
+                None => output.push_str(&format!("  - Notified {}\n", notification.url)),
+            }
+        }
+        Ok(output)
+    }
+
     /// Detect service name from current directory with AI fallback
-    pub fn detect_service_name() -> Result<String> {
+    pub fn detect_service_name(&self) -> Result<String> {
         tracing::info!("MCP Tool: detect_service_name()");
 
-        let detector = ServiceDetector::new()?;
-
-        // Use blocking execution for async function in sync context
-        let rt = tokio::runtime::Runtime::new().map_err(|e| {
-            crate::error::KtmeError::Storage(format!("Failed to create runtime: {}", e))
-        })?;
+        let detector = ServiceDetector::from_directory(self.context.repo_path.clone());
 
-        let service_name = rt.block_on(detector.detect_with_ai_fallback())?;
+        let cache = crate::service_detector::detection_cache(&self.context.config);
+        let detection = block_on(detector.detect_cached(cache.as_deref()))??;
+        let service_name = detection.name;
 
         let repo_info = detector.get_repository_info();
 
-        let mut result = format!("**Detected Service Name:** {}\n\n", service_name);
+        let mut result = format!(
+            "**Detected Service Name:** {} ({})\n\n",
+            service_name, detection.origin
+        );
 
         if repo_info.is_git_repository {
             if let Some(ref repo_root) = repo_info.repository_root {
@@ -369,14 +1042,29 @@ impl McpTools {
             repo_info.current_dir.display()
         ));
 
+        let others: Vec<_> = detector
+            .candidates()
+            .into_iter()
+            .filter(|candidate| candidate.name != service_name)
+            .collect();
+        if !others.is_empty() {
+            result.push_str("\n**Other Candidates:**\n");
+            for candidate in others {
+                result.push_str(&format!(
+                    "- {} ({}, confidence {:.2})\n",
+                    candidate.name, candidate.source, candidate.confidence
+                ));
+            }
+        }
+
         Ok(result)
     }
 
     /// Get repository information
-    pub fn get_repository_info() -> Result<String> {
+    pub fn get_repository_info(&self) -> Result<String> {
         tracing::info!("MCP Tool: get_repository_info()");
 
-        let detector = ServiceDetector::new()?;
+        let detector = ServiceDetector::from_directory(self.context.repo_path.clone());
         let repo_info = detector.get_repository_info();
 
         let mut result = format!("**Repository Information:**\n\n");
@@ -398,11 +1086,11 @@ impl McpTools {
     }
 
     /// Scan documentation and return statistics
-    pub fn scan_documentation(path: Option<&str>) -> Result<String> {
+    pub fn scan_documentation(&self, path: Option<&str>) -> Result<String> {
         tracing::info!("MCP Tool: scan_documentation(path={:?})", path);
 
         let project_path = path.unwrap_or(".");
-        let project_dir = std::path::PathBuf::from(project_path);
+        let project_dir = self.context.resolve(project_path);
         let docs_dir = project_dir.join("docs");
 
         if !docs_dir.exists() {
@@ -455,11 +1143,11 @@ impl McpTools {
     }
 
     /// Validate documentation for common issues
-    pub fn validate_documentation(path: Option<&str>) -> Result<String> {
+    pub fn validate_documentation(&self, path: Option<&str>) -> Result<String> {
         tracing::info!("MCP Tool: validate_documentation(path={:?})", path);
 
         let project_path = path.unwrap_or(".");
-        let project_dir = std::path::PathBuf::from(project_path);
+        let project_dir = self.context.resolve(project_path);
         let docs_dir = project_dir.join("docs");
 
         if !docs_dir.exists() {
@@ -480,19 +1168,12 @@ impl McpTools {
                     if let Ok(content) = std::fs::read_to_string(&path) {
                         let filename = path.file_name().unwrap().to_string_lossy();
 
-                        if !content.starts_with("# ") {
-                            validation_warnings.push(format!("{}: Missing title header", filename));
-                        }
-
-                        if !content.contains("## ") {
-                            validation_warnings.push(format!("{}: No sections found", filename));
-                        }
-
-                        let open_brackets = content.matches("[").count();
-                        let close_brackets = content.matches("]").count();
-                        if open_brackets != close_brackets {
-                            validation_errors
-                                .push(format!("{}: Potentially broken links detected", filename));
+                        for finding in lint::check(&content) {
+                            let message = format!("{}: {}", filename, finding.message);
+                            match finding.severity {
+                                lint::Severity::Error => validation_errors.push(message),
+                                lint::Severity::Warning => validation_warnings.push(message),
+                            }
                         }
                     }
                 }
@@ -525,11 +1206,11 @@ impl McpTools {
     }
 
     /// Detect technology stack for a project
-    pub fn detect_tech_stack(path: Option<&str>) -> Result<String> {
+    pub fn detect_tech_stack(&self, path: Option<&str>) -> Result<String> {
         tracing::info!("MCP Tool: detect_tech_stack(path={:?})", path);
 
         let project_path = path.unwrap_or(".");
-        let project_dir = std::path::PathBuf::from(project_path);
+        let project_dir = self.context.resolve(project_path);
 
         let mut result = format!(
             "# Technology Stack Report\n\n**Path:** {}\n\n",
@@ -585,11 +1266,11 @@ impl McpTools {
     }
 
     /// Find TODO markers in documentation
-    pub fn find_documentation_todos(path: Option<&str>) -> Result<String> {
+    pub fn find_documentation_todos(&self, path: Option<&str>) -> Result<String> {
         tracing::info!("MCP Tool: find_documentation_todos(path={:?})", path);
 
         let project_path = path.unwrap_or(".");
-        let project_dir = std::path::PathBuf::from(project_path);
+        let project_dir = self.context.resolve(project_path);
         let docs_dir = project_dir.join("docs");
 
         if !docs_dir.exists() {
@@ -647,6 +1328,7 @@ impl McpTools {
     ///   - `depth`: traversal depth (0 = services only, 1 = +features, 2+ = +relations)
     ///   - `include_mermaid`: when true, appends a Mermaid flowchart to the JSON output
     pub fn get_knowledge_tree(
+        &self,
         service: Option<&str>,
         depth: Option<u32>,
         include_mermaid: bool,
@@ -659,9 +1341,8 @@ impl McpTools {
         );
 
         use crate::knowledge::engine::KnowledgeGraphEngine;
-        use crate::storage::database::Database;
 
-        let db = Database::new(None)?;
+        let db = open_sqlite(&self.context.config.storage)?;
         let engine = KnowledgeGraphEngine::new(db);
         let graph = engine.get_tree(service, depth.unwrap_or(2))?;
 
@@ -683,6 +1364,7 @@ impl McpTools {
     /// Lookup is by `feature_id` (UUID string). If not supplied, both
     /// `feature_name` and `service_name` must be provided to locate the feature.
     pub fn get_feature_context(
+        &self,
         feature_id: Option<&str>,
         feature_name: Option<&str>,
         service_name: Option<&str>,
@@ -695,10 +1377,9 @@ impl McpTools {
         );
 
         use crate::knowledge::engine::KnowledgeGraphEngine;
-        use crate::storage::database::Database;
         use crate::storage::repository::{FeatureRepository, ServiceRepository};
 
-        let db = Database::new(None)?;
+        let db = open_sqlite(&self.context.config.storage)?;
 
         let resolved_id = if let Some(id) = feature_id {
             id.to_string()
@@ -737,7 +1418,492 @@ impl McpTools {
 
         let engine = KnowledgeGraphEngine::new(db);
         let ctx = engine.get_feature_context(&resolved_id)?;
+        crate::cli::commands::feature::record_usage(
+            self.context.storage()?.as_ref(),
+            std::iter::once(resolved_id.as_str()),
+        );
 
         serde_json::to_string_pretty(&ctx).map_err(|e| crate::error::KtmeError::Serialization(e))
     }
+
+    /// Relate two features of the knowledge graph.
+    ///
+    /// Parameters:
+    ///   - `from`, `to`: feature ids or names; `from` is the one that depends
+    ///     on, implements, tests... `to`
+    ///   - `relation`: relation type, `depends_on` by default
+    ///   - `strength`: 0 to 1, 1.0 by default
+    ///   - `service`: service to look feature names up in
+    pub fn link_features(
+        &self,
+        from: &str,
+        to: &str,
+        relation: Option<&str>,
+        strength: Option<f64>,
+        service: Option<&str>,
+    ) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: link_features(from={}, to={}, relation={:?}, service={:?})",
+            from,
+            to,
+            relation,
+            service
+        );
+
+        use crate::cli::commands::feature;
+
+        let relation_type = relation.unwrap_or("depends_on").parse()?;
+        let storage = self.context.storage()?;
+        let (relation, created) = feature::link_features(
+            storage.as_ref(),
+            from,
+            to,
+            relation_type,
+            strength.unwrap_or(1.0),
+            service,
+        )?;
+
+        serde_json::to_string_pretty(&serde_json::json!({
+            "relation": relation,
+            "created": created,
+        }))
+        .map_err(KtmeError::Serialization)
+    }
+
+    /// Remove the relations from feature `from` to feature `to`.
+    pub fn unlink_features(&self, from: &str, to: &str, service: Option<&str>) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: unlink_features(from={}, to={}, service={:?})",
+            from,
+            to,
+            service
+        );
+
+        let storage = self.context.storage()?;
+        let removed =
+            crate::cli::commands::feature::unlink_features(storage.as_ref(), from, to, service)?;
+
+        serde_json::to_string_pretty(&serde_json::json!({ "removed": removed }))
+            .map_err(KtmeError::Serialization)
+    }
+
+    /// Search the features of the knowledge graph.
+    ///
+    /// Parameters:
+    ///   - `query`: words to match against names, descriptions and indexed content
+    ///   - `service`: optional service name filter
+    ///   - `include_related`: also return the features related to the matches,
+    ///     with how they are related in `related_via`
+    ///   - `depth`: relations to follow from a match (default 1)
+    ///   - `limit`: maximum number of matches (default 20)
+    pub fn search_features(
+        &self,
+        query: &str,
+        service: Option<&str>,
+        include_related: bool,
+        depth: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<String> {
+        tracing::info!(
+            "MCP Tool: search_features(query={}, service={:?}, include_related={}, depth={:?})",
+            query,
+            service,
+            include_related,
+            depth
+        );
+
+        let storage = self.context.storage()?;
+        let results = crate::cli::commands::feature::search_features(
+            storage.as_ref(),
+            query,
+            service,
+            include_related,
+            depth,
+            limit.unwrap_or(20),
+        )?;
+
+        serde_json::to_string_pretty(&results).map_err(KtmeError::Serialization)
+    }
+}
+
+/// Paging and filters of the `search_services` and `search_by_keyword`
+/// tools
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub min_score: Option<f64>,
+    pub feature_type: Option<FeatureType>,
+    pub service: Option<String>,
+}
+
+impl SearchFilters {
+    /// Filters given in the arguments of a tool call
+    pub fn from_arguments(arguments: &Value) -> Result<Self> {
+        let feature_type = match arguments.get("feature_type").and_then(|t| t.as_str()) {
+            Some(name) => {
+                let feature_type = FeatureType::from(name);
+                if feature_type == FeatureType::Other && name != "other" {
+                    return Err(KtmeError::InvalidInput(format!(
+                        "Unknown feature type '{}'",
+                        name
+                    )));
+                }
+                Some(feature_type)
+            }
+            None => None,
+        };
+        Ok(Self {
+            limit: arguments
+                .get("limit")
+                .and_then(|l| l.as_u64())
+                .map(|l| l as usize),
+            offset: arguments
+                .get("offset")
+                .and_then(|o| o.as_u64())
+                .map(|o| o as usize),
+            min_score: arguments.get("min_score").and_then(|m| m.as_f64()),
+            feature_type,
+            service: arguments
+                .get("service")
+                .and_then(|s| s.as_str())
+                .map(str::to_string),
+        })
+    }
+
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(10).clamp(1, MAX_PAGE_SIZE)
+    }
+
+    fn offset(&self) -> usize {
+        self.offset.unwrap_or(0)
+    }
+
+    /// JSON page of `results`, which hold up to one result past the page
+    fn page<T: serde::Serialize>(&self, query: &str, mut results: Vec<T>) -> Result<String> {
+        let has_more = results.len() > self.limit();
+        results.truncate(self.limit());
+        let next_offset = has_more.then(|| self.offset() + self.limit());
+
+        serde_json::to_string_pretty(&serde_json::json!({
+            "query": query,
+            "offset": self.offset(),
+            "limit": self.limit(),
+            "has_more": has_more,
+            "next_offset": next_offset,
+            "results": results,
+        }))
+        .map_err(KtmeError::Serialization)
+    }
+}
+
+/// Document matching a `search_documentation` query
+#[derive(Debug)]
+struct DocumentHit {
+    title: String,
+    url: String,
+    service: Option<String>,
+    source: String,
+    snippets: Vec<String>,
+    score: usize,
+    /// Feature whose indexed content matched
+    feature_id: Option<String>,
+}
+
+impl DocumentHit {
+    /// Hit for a document returned by a provider; `None` when no word of
+    /// the query appears in it and the provider gave no snippets
+    fn from_document(
+        query: &str,
+        document: crate::doc::providers::Document,
+        service: Option<&str>,
+        source: &str,
+    ) -> Option<Self> {
+        let mut snippets = document.metadata.snippets;
+        if snippets.is_empty() {
+            snippets = query_snippets(&document.content, query);
+        }
+        let score = relevance(query, &document.title, &document.content);
+        if score == 0 && snippets.is_empty() {
+            return None;
+        }
+        Some(Self {
+            url: document.url.unwrap_or(document.id),
+            title: document.title,
+            service: service.map(str::to_string),
+            source: source.to_string(),
+            snippets,
+            score: score.max(1),
+            feature_id: None,
+        })
+    }
+
+    /// Hit for indexed content of a feature, located by its mapped document
+    fn from_index(query: &str, result: crate::storage::models::SearchResult) -> Option<Self> {
+        let content = match result.description {
+            Some(ref description) => format!("{}\n{}", result.content, description),
+            None => result.content.clone(),
+        };
+        let score = relevance(query, &result.feature_name, &content);
+        if score == 0 {
+            return None;
+        }
+        Some(Self {
+            url: result
+                .path
+                .unwrap_or_else(|| format!("feature:{}", result.feature_id)),
+            title: result.feature_name,
+            service: Some(result.service_name),
+            source: format!("index ({})", result.content_type),
+            snippets: query_snippets(&content, query),
+            score,
+            feature_id: Some(result.feature_id),
+        })
+    }
+
+    /// Best `limit` hits, one per document, highest score first
+    fn rank(mut hits: Vec<Self>, limit: usize) -> Vec<Self> {
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+        let mut seen = std::collections::HashSet::new();
+        hits.retain(|hit| seen.insert(hit.url.clone()));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// Lines around the query in `content`, or around its first word that
+/// appears when the whole query does not
+fn query_snippets(content: &str, query: &str) -> Vec<String> {
+    let query = query.to_lowercase();
+    let snippets = crate::doc::providers::markdown::snippets(content, query.trim());
+    if !snippets.is_empty() {
+        return snippets;
+    }
+    query
+        .split_whitespace()
+        .map(|word| crate::doc::providers::markdown::snippets(content, word))
+        .find(|snippets| !snippets.is_empty())
+        .unwrap_or_default()
+}
+
+/// Occurrences of the words of `query` in `content`, matches in the title
+/// counting three times
+fn relevance(query: &str, title: &str, content: &str) -> usize {
+    let title = title.to_lowercase();
+    let content = content.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .map(|word| title.matches(word).count() * 3 + content.matches(word).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    /// Repository at `dir` with a single commit `message`
+    fn commit_repo(dir: &std::path::Path, message: &str) {
+        let repo = git2::Repository::init(dir).unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let signature = git2::Signature::now("Dev", "dev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_context_for_call_selects_repo_and_workspace() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("api")).unwrap();
+        let base = ToolContext::new(dir.path().to_path_buf(), Config::default());
+
+        let context = base
+            .for_call(&json!({ "repo_path": "api", "workspace": "client-a" }))
+            .unwrap();
+        assert_eq!(context.repo_path, dir.path().join("api"));
+        assert_eq!(context.config.storage.workspace, "client-a");
+        assert_eq!(base.config.storage.workspace, "default");
+
+        assert_eq!(base.for_call(&json!({})).unwrap().repo_path, dir.path());
+        assert!(base.for_call(&json!({ "repo_path": "missing" })).is_err());
+        assert!(base.for_call(&json!({ "workspace": "" })).is_err());
+    }
+
+    #[test]
+    fn test_tools_read_the_repository_of_their_context() {
+        let api = TempDir::new().unwrap();
+        let web = TempDir::new().unwrap();
+        commit_repo(api.path(), "Add invoices endpoint");
+        commit_repo(web.path(), "Add checkout page");
+
+        let read = |dir: &TempDir| {
+            let tools = McpTools::new(ToolContext::new(
+                dir.path().to_path_buf(),
+                Config::default(),
+            ));
+            let diff: ExtractedDiff =
+                serde_json::from_str(&tools.read_changes("HEAD").unwrap()).unwrap();
+            diff.message
+        };
+        assert_eq!(read(&api), "Add invoices endpoint");
+        assert_eq!(read(&web), "Add checkout page");
+    }
+
+    #[test]
+    fn test_search_tools_page_and_filter_results() {
+        let dir = TempDir::new().unwrap();
+        let mut config = Config::default();
+        config.storage.use_sqlite = true;
+        config.storage.database_file = Some(dir.path().join("ktme.db"));
+        // Storage is shared per workspace across the process
+        config.storage.workspace = "search-tools-test".to_string();
+        let tools = McpTools::new(ToolContext::new(dir.path().to_path_buf(), config));
+        let storage = tools.context().storage().unwrap();
+        let billing = storage.create_service("billing", None, None).unwrap();
+        let shop = storage
+            .create_service("shop", None, Some("Storefront"))
+            .unwrap();
+        for (service_id, name, feature_type, score) in [
+            (billing.id, "Invoice API", FeatureType::Api, 0.9),
+            (
+                billing.id,
+                "Invoice export",
+                FeatureType::BusinessLogic,
+                0.5,
+            ),
+            (shop.id, "Invoice page", FeatureType::Ui, 0.2),
+        ] {
+            let id = uuid::Uuid::new_v4().to_string();
+            storage
+                .create_feature(&id, service_id, name, None, feature_type, vec![], json!({}))
+                .unwrap();
+            storage.update_feature_relevance(&id, score).unwrap();
+        }
+
+        let keyword = |filters: Value| -> Value {
+            let filters = SearchFilters::from_arguments(&filters).unwrap();
+            serde_json::from_str(&tools.search_by_keyword("invoice", &filters).unwrap()).unwrap()
+        };
+        let names = |page: &Value, field: &str| -> Vec<String> {
+            page["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|result| result[field].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let first = keyword(json!({ "limit": 2 }));
+        assert_eq!(
+            names(&first, "feature_name"),
+            vec!["Invoice API", "Invoice export"]
+        );
+        assert_eq!(
+            (first["has_more"].clone(), first["next_offset"].clone()),
+            (json!(true), json!(2))
+        );
+        let second = keyword(json!({ "limit": 2, "offset": 2 }));
+        assert_eq!(names(&second, "feature_name"), vec!["Invoice page"]);
+        assert_eq!(second["has_more"], false);
+        assert!(second["next_offset"].is_null());
+
+        assert_eq!(
+            keyword(json!({ "min_score": 0.4 }))["results"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            names(&keyword(json!({ "feature_type": "ui" })), "feature_name"),
+            vec!["Invoice page"]
+        );
+        assert_eq!(
+            names(&keyword(json!({ "service": "shop" })), "service_name"),
+            vec!["shop"]
+        );
+        assert!(tools
+            .search_by_keyword(
+                "invoice",
+                &SearchFilters {
+                    service: Some("missing".to_string()),
+                    ..Default::default()
+                }
+            )
+            .is_err());
+        assert!(SearchFilters::from_arguments(&json!({ "feature_type": "gadget" })).is_err());
+
+        // Services score a point per matching feature they own
+        let services = |filters: Value| -> Value {
+            let filters = SearchFilters::from_arguments(&filters).unwrap();
+            serde_json::from_str(&tools.search_services("invoice", &filters).unwrap()).unwrap()
+        };
+        let all = services(json!({}));
+        assert_eq!(names(&all, "name"), vec!["billing", "shop"]);
+        assert_eq!(
+            all["results"][0]["matched_features"],
+            json!(["Invoice API", "Invoice export"])
+        );
+        assert_eq!(all["results"][1]["description"], "Storefront");
+        assert_eq!(
+            names(&services(json!({ "min_score": 2.0 })), "name"),
+            vec!["billing"]
+        );
+        assert_eq!(
+            names(&services(json!({ "feature_type": "ui" })), "name"),
+            vec!["shop"]
+        );
+    }
+
+    fn hit(url: &str, score: usize) -> DocumentHit {
+        DocumentHit {
+            title: url.to_string(),
+            url: url.to_string(),
+            service: None,
+            source: "mapping".to_string(),
+            snippets: Vec::new(),
+            score,
+            feature_id: None,
+        }
+    }
+
+    #[test]
+    fn test_relevance_counts_title_matches_more() {
+        assert_eq!(relevance("rate limit", "Rate limits", "no match"), 6);
+        assert_eq!(relevance("rate", "Auth", "Rate and RATE"), 2);
+        assert_eq!(relevance("missing", "Auth", "content"), 0);
+    }
+
+    #[test]
+    fn test_rank_keeps_best_hit_per_document() {
+        let ranked = DocumentHit::rank(vec![hit("a.md", 1), hit("b.md", 5), hit("a.md", 3)], 10);
+        let order: Vec<_> = ranked
+            .iter()
+            .map(|hit| (hit.url.as_str(), hit.score))
+            .collect();
+        assert_eq!(order, vec![("b.md", 5), ("a.md", 3)]);
+
+        assert_eq!(
+            DocumentHit::rank(vec![hit("a.md", 1), hit("b.md", 2)], 1).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_query_snippets_fall_back_to_words() {
+        let content = "# Limits\nRequests are throttled per token.\n";
+        assert_eq!(
+            query_snippets(content, "throttled"),
+            vec!["Requests are throttled per token."]
+        );
+        assert_eq!(
+            query_snippets(content, "token expiry"),
+            vec!["Requests are throttled per token."]
+        );
+        assert!(query_snippets(content, "webhooks").is_empty());
+    }
 }
@@ -0,0 +1,223 @@
+//! Capture of the JSON-RPC traffic of an MCP server
+//!
+//! With `ktme mcp start --trace <file>` (or `[mcp] trace_file`) every message
+//! received and sent is appended to the file as a JSON line; never to
+//! stdout, which belongs to the protocol in stdio mode. Values of secret
+//! keys and token-like strings are always masked, and diffs (with the
+//! `changes` and `content` arguments carrying them) are replaced by their
+//! size unless `[mcp] trace_diffs` is set. `ktme mcp trace` prints a capture.
+
+use crate::config::McpConfig;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Parts of key names whose string values are secrets
+const SECRET_KEYS: &[&str] = &[
+    "token",
+    "secret",
+    "password",
+    "api_key",
+    "apikey",
+    "authorization",
+    "cookie",
+    "credential",
+];
+
+/// Keys whose string values are diffs or documents
+const DIFF_KEYS: &[&str] = &["diff", "patch", "changes", "content"];
+
+/// Characters kept of a message that is not JSON
+const MAX_RAW_LEN: usize = 2000;
+
+/// Whether a message went from the client to the server or back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Received from the client
+    In,
+    /// Sent to the client
+    Out,
+}
+
+/// One captured message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub at: DateTime<Utc>,
+    pub direction: Direction,
+    pub message: Value,
+}
+
+/// Appends the redacted messages of a session to the trace file
+#[derive(Debug)]
+pub struct Tracer {
+    file: Mutex<File>,
+    /// Keep diff bodies
+    diffs: bool,
+}
+
+impl Tracer {
+    /// Tracer writing to `path` (`--trace`) or `[mcp] trace_file`; `None`
+    /// when neither is set
+    pub fn from_config(config: &McpConfig, path: Option<&str>) -> Result<Option<Arc<Self>>> {
+        let Some(path) = path.or(config.trace_file.as_deref()) else {
+            return Ok(None);
+        };
+        let path = Path::new(path);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Some(Arc::new(Self {
+            file: Mutex::new(file),
+            diffs: config.trace_diffs,
+        })))
+    }
+
+    /// Record the raw `message` received from the client
+    pub fn received(&self, message: &str) {
+        let message = match serde_json::from_str::<Value>(message) {
+            Ok(value) => redact(&value, self.diffs),
+            Err(_) => {
+                let kept: String = message.chars().take(MAX_RAW_LEN).collect();
+                Value::String(mask_tokens(&kept))
+            }
+        };
+        self.write(Direction::In, message);
+    }
+
+    /// Record `message` sent to the client
+    pub fn sent(&self, message: &Value) {
+        self.write(Direction::Out, redact(message, self.diffs));
+    }
+
+    fn write(&self, direction: Direction, message: Value) {
+        let entry = TraceEntry {
+            at: Utc::now(),
+            direction,
+            message,
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(std::io::Error::from)
+            .and_then(|line| {
+                let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+                writeln!(file, "{}", line)
+            });
+        if let Err(e) = written {
+            tracing::warn!("Failed to write the MCP trace: {}", e);
+        }
+    }
+}
+
+/// `value` with secrets masked and, unless `diffs`, diff bodies replaced by
+/// their size. JSON held in strings, such as tool results, is redacted too.
+pub fn redact(value: &Value, diffs: bool) -> Value {
+    redact_value(None, value, diffs)
+}
+
+fn redact_value(key: Option<&str>, value: &Value, diffs: bool) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), redact_value(Some(name), value, diffs)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| redact_value(key, item, diffs))
+                .collect(),
+        ),
+        Value::String(text) => Value::String(redact_string(key, text, diffs)),
+        _ => value.clone(),
+    }
+}
+
+fn redact_string(key: Option<&str>, text: &str, diffs: bool) -> String {
+    let key = key.map(str::to_lowercase);
+    if key
+        .as_deref()
+        .is_some_and(|key| SECRET_KEYS.iter().any(|secret| key.contains(secret)))
+    {
+        return "[redacted]".to_string();
+    }
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        if let Ok(json) = serde_json::from_str::<Value>(text) {
+            return redact_value(key.as_deref(), &json, diffs).to_string();
+        }
+    }
+    if !diffs
+        && (key.as_deref().is_some_and(|key| DIFF_KEYS.contains(&key)) || is_diff(text))
+        && !text.is_empty()
+    {
+        return format!("[redacted: {} bytes]", text.len());
+    }
+    mask_tokens(text)
+}
+
+/// Whether `text` is a unified diff
+fn is_diff(text: &str) -> bool {
+    text.starts_with("diff --git") || text.starts_with("@@ ") || text.contains("\n@@ -")
+}
+
+/// `text` with access tokens and authorization headers masked
+fn mask_tokens(text: &str) -> String {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN
+        .get_or_init(|| {
+            Regex::new(
+                r"\b(?:gh[pousr]_[A-Za-z0-9]{16,}|github_pat_[A-Za-z0-9_]{16,}|glpat-[A-Za-z0-9_\-]{16,}|xox[abpr]-[A-Za-z0-9\-]{10,}|sk-(?:ant-)?[A-Za-z0-9_\-]{16,})|\bBearer\s+[A-Za-z0-9._~+/=\-]{8,}",
+            )
+            .expect("valid token pattern")
+        })
+        .replace_all(text, "[redacted]")
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_secrets_and_diffs() {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {
+                "name": "generate_documentation",
+                "arguments": {
+                    "service": "api",
+                    "changes": "{\"files\": [{\"path\": \"src/lib.rs\", \"diff\": \"@@ -1 +1 @@\\n-a\\n+b\"}]}",
+                    "api_token": "hunter2",
+                    "note": "use Bearer abcdefghijkl and ghp_0123456789abcdefghij"
+                }
+            }
+        });
+        let redacted = redact(&request, false);
+        let arguments = &redacted["params"]["arguments"];
+        assert_eq!(arguments["service"], "api");
+        assert_eq!(arguments["api_token"], "[redacted]");
+        assert_eq!(arguments["note"], "use [redacted] and [redacted]");
+        let changes: Value = serde_json::from_str(arguments["changes"].as_str().unwrap()).unwrap();
+        assert_eq!(changes["files"][0]["path"], "src/lib.rs");
+        assert_eq!(changes["files"][0]["diff"], "[redacted: 17 bytes]");
+
+        // Diffs are kept on request, secrets never are
+        let kept = redact(&request, true);
+        assert!(kept["params"]["arguments"]["changes"]
+            .as_str()
+            .unwrap()
+            .contains("+b"));
+        assert_eq!(kept["params"]["arguments"]["api_token"], "[redacted]");
+    }
+}
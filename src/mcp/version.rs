@@ -0,0 +1,201 @@
+//! MCP protocol revisions and their negotiation
+//!
+//! During `initialize` the server answers with the revision the client asked
+//! for when it supports it, otherwise with the newest supported revision not
+//! newer than the request, so that an older client is never sent features it
+//! does not know. Tool annotations (2025-03-26), tool titles and structured
+//! tool results (2025-06-18) are only added for the revisions defining them.
+
+use serde_json::{json, Value};
+
+/// A supported protocol revision, oldest first so that revisions compare by
+/// age
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    #[default]
+    V2024_11_05,
+    V2025_03_26,
+    V2025_06_18,
+}
+
+/// Tools that change documents, mappings or the knowledge graph
+const WRITING_TOOLS: &[&str] = &[
+    "update_documentation",
+    "automated_documentation_workflow",
+    "link_features",
+    "unlink_features",
+];
+
+/// Writing tools whose changes replace or remove what was there
+const DESTRUCTIVE_TOOLS: &[&str] = &["update_documentation", "unlink_features"];
+
+impl ProtocolVersion {
+    /// Supported revisions, newest first
+    pub const SUPPORTED: [Self; 3] = [Self::V2025_06_18, Self::V2025_03_26, Self::V2024_11_05];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::V2024_11_05 => "2024-11-05",
+            Self::V2025_03_26 => "2025-03-26",
+            Self::V2025_06_18 => "2025-06-18",
+        }
+    }
+
+    /// Revision to speak with a client that asked for `requested`. Clients
+    /// from before the negotiation get the oldest revision, those asking for
+    /// an unknown one the newest revision not newer than it (revisions are
+    /// dates, so they compare as strings).
+    pub fn negotiate(requested: Option<&str>) -> Self {
+        let Some(requested) = requested.map(str::trim).filter(|r| !r.is_empty()) else {
+            return Self::V2024_11_05;
+        };
+        Self::SUPPORTED
+            .into_iter()
+            .find(|version| version.as_str() <= requested)
+            .unwrap_or(Self::V2024_11_05)
+    }
+
+    /// Revision asked for in the params of an `initialize` request, with the
+    /// negotiation logged
+    pub fn from_initialize(request: &Value) -> Self {
+        let requested = request
+            .pointer("/params/protocolVersion")
+            .and_then(Value::as_str);
+        let version = Self::negotiate(requested);
+        match requested {
+            Some(requested) if requested != version.as_str() => tracing::info!(
+                "Client asked for MCP protocol {}, negotiated {}",
+                requested,
+                version.as_str()
+            ),
+            Some(_) => tracing::info!("Negotiated MCP protocol {}", version.as_str()),
+            None => tracing::info!(
+                "Client sent no protocol version, negotiated MCP protocol {}",
+                version.as_str()
+            ),
+        }
+        version
+    }
+
+    /// Capabilities declared in the `initialize` result; ktme declares the
+    /// same ones in every supported revision
+    pub fn capabilities(self) -> Value {
+        json!({
+            "tools": {
+                "listChanged": false
+            },
+            "resources": {},
+            "logging": {}
+        })
+    }
+
+    /// `tool` of `tools/list` with the fields of this revision: annotations
+    /// telling clients which tools only read, and a display title
+    pub fn describe_tool(self, mut tool: Value) -> Value {
+        let name = tool["name"].as_str().unwrap_or("").to_string();
+        let base = name.strip_prefix("ktme_").unwrap_or(&name);
+        if self >= Self::V2025_03_26 {
+            let writes = WRITING_TOOLS.contains(&base);
+            let mut annotations = json!({
+                "readOnlyHint": !writes,
+                "openWorldHint": false
+            });
+            if writes {
+                annotations["destructiveHint"] = json!(DESTRUCTIVE_TOOLS.contains(&base));
+                annotations["idempotentHint"] = json!(false);
+            }
+            tool["annotations"] = annotations;
+        }
+        if self >= Self::V2025_06_18 {
+            tool["title"] = json!(title(base));
+        }
+        tool
+    }
+
+    /// `tools/call` result of the text `output`; from 2025-06-18 on, output
+    /// that is a JSON object is also given as `structuredContent`
+    pub fn tool_result(self, output: String) -> Value {
+        let structured = (self >= Self::V2025_06_18)
+            .then(|| serde_json::from_str::<Value>(&output).ok())
+            .flatten()
+            .filter(Value::is_object);
+        let mut result = json!({
+            "content": [{
+                "type": "text",
+                "text": output
+            }]
+        });
+        if let Some(structured) = structured {
+            result["structuredContent"] = structured;
+        }
+        result
+    }
+}
+
+/// Display title of a tool, e.g. "Read changes" for `read_changes`
+fn title(name: &str) -> String {
+    let words = name.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("2025-06-18")),
+            ProtocolVersion::V2025_06_18
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("2025-03-26")),
+            ProtocolVersion::V2025_03_26
+        );
+        // Unknown revisions fall back to the newest one not newer than them
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("2026-01-01")),
+            ProtocolVersion::V2025_06_18
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("2025-04-01")),
+            ProtocolVersion::V2025_03_26
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("2024-10-07")),
+            ProtocolVersion::V2024_11_05
+        );
+        assert_eq!(
+            ProtocolVersion::negotiate(None),
+            ProtocolVersion::V2024_11_05
+        );
+    }
+
+    #[test]
+    fn test_features_follow_the_revision() {
+        let tool = json!({"name": "ktme_update_documentation", "inputSchema": {}});
+        let old = ProtocolVersion::V2024_11_05.describe_tool(tool.clone());
+        assert!(old.get("annotations").is_none() && old.get("title").is_none());
+
+        let annotated = ProtocolVersion::V2025_03_26.describe_tool(tool.clone());
+        assert_eq!(annotated["annotations"]["readOnlyHint"], false);
+        assert_eq!(annotated["annotations"]["destructiveHint"], true);
+        assert!(annotated.get("title").is_none());
+
+        let latest = ProtocolVersion::V2025_06_18.describe_tool(tool);
+        assert_eq!(latest["title"], "Update documentation");
+
+        let output = r#"{"services": []}"#.to_string();
+        assert!(ProtocolVersion::V2025_03_26
+            .tool_result(output.clone())
+            .get("structuredContent")
+            .is_none());
+        let result = ProtocolVersion::V2025_06_18.tool_result(output);
+        assert_eq!(result["structuredContent"]["services"], json!([]));
+        assert_eq!(result["content"][0]["type"], "text");
+    }
+}
@@ -0,0 +1,371 @@
+//! In-process metrics shared by the CLI and the MCP server
+//!
+//! Counters are kept in memory for the lifetime of the process. The HTTP
+//! server exposes them in Prometheus text format on `/metrics` when
+//! `mcp.metrics` is enabled, and `--timings` prints a summary after a CLI
+//! command.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the latency histogram buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::default()));
+
+#[derive(Debug, Clone, Default)]
+struct Latency {
+    calls: u64,
+    errors: u64,
+    sum_seconds: f64,
+    /// Non-cumulative counts per bucket; the last slot is +Inf
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+}
+
+impl Latency {
+    fn observe(&mut self, elapsed: Duration, success: bool) {
+        let seconds = elapsed.as_secs_f64();
+        self.calls += 1;
+        if !success {
+            self.errors += 1;
+        }
+        self.sum_seconds += seconds;
+        let slot = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[slot] += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.calls
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum_seconds);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.calls);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct AiUsage {
+    latency: Latency,
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+/// Lookups of a cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheLookups {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheLookups {
+    /// Share of lookups answered from the cache, between 0 and 1
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Registry {
+    tools: BTreeMap<String, Latency>,
+    ai: BTreeMap<String, AiUsage>,
+    caches: BTreeMap<String, CacheLookups>,
+    stages: Vec<(String, Duration)>,
+}
+
+impl Registry {
+    pub fn record_tool_call(&mut self, tool: &str, elapsed: Duration, success: bool) {
+        self.tools
+            .entry(tool.to_string())
+            .or_default()
+            .observe(elapsed, success);
+    }
+
+    pub fn record_ai_request(&mut self, provider: &str, elapsed: Duration, success: bool) {
+        self.ai
+            .entry(provider.to_string())
+            .or_default()
+            .latency
+            .observe(elapsed, success);
+    }
+
+    pub fn record_ai_tokens(&mut self, provider: &str, input: u64, output: u64) {
+        let usage = self.ai.entry(provider.to_string()).or_default();
+        usage.input_tokens += input;
+        usage.output_tokens += output;
+    }
+
+    pub fn record_stage(&mut self, stage: &str, elapsed: Duration) {
+        self.stages.push((stage.to_string(), elapsed));
+    }
+
+    pub fn record_cache_lookup(&mut self, cache: &str, hit: bool) {
+        let lookups = self.caches.entry(cache.to_string()).or_default();
+        if hit {
+            lookups.hits += 1;
+        } else {
+            lookups.misses += 1;
+        }
+    }
+
+    pub fn cache_lookups(&self, cache: &str) -> CacheLookups {
+        self.caches.get(cache).copied().unwrap_or_default()
+    }
+
+    /// Prometheus text exposition format
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP ktme_tool_calls_total MCP tool invocations by outcome\n");
+        out.push_str("# TYPE ktme_tool_calls_total counter\n");
+        for (tool, stats) in &self.tools {
+            let tool = escape_label(tool);
+            let _ = writeln!(
+                out,
+                "ktme_tool_calls_total{{tool=\"{}\",status=\"ok\"}} {}",
+                tool,
+                stats.calls - stats.errors
+            );
+            let _ = writeln!(
+                out,
+                "ktme_tool_calls_total{{tool=\"{}\",status=\"error\"}} {}",
+                tool, stats.errors
+            );
+        }
+
+        out.push_str("# HELP ktme_tool_duration_seconds MCP tool latency\n");
+        out.push_str("# TYPE ktme_tool_duration_seconds histogram\n");
+        for (tool, stats) in &self.tools {
+            let labels = format!("tool=\"{}\"", escape_label(tool));
+            stats.render(&mut out, "ktme_tool_duration_seconds", &labels);
+        }
+
+        out.push_str("# HELP ktme_ai_requests_total AI provider requests by outcome\n");
+        out.push_str("# TYPE ktme_ai_requests_total counter\n");
+        for (provider, usage) in &self.ai {
+            let provider = escape_label(provider);
+            let stats = &usage.latency;
+            let _ = writeln!(
+                out,
+                "ktme_ai_requests_total{{provider=\"{}\",status=\"ok\"}} {}",
+                provider,
+                stats.calls - stats.errors
+            );
+            let _ = writeln!(
+                out,
+                "ktme_ai_requests_total{{provider=\"{}\",status=\"error\"}} {}",
+                provider, stats.errors
+            );
+        }
+
+        out.push_str("# HELP ktme_ai_request_duration_seconds AI provider latency\n");
+        out.push_str("# TYPE ktme_ai_request_duration_seconds histogram\n");
+        for (provider, usage) in &self.ai {
+            let labels = format!("provider=\"{}\"", escape_label(provider));
+            usage
+                .latency
+                .render(&mut out, "ktme_ai_request_duration_seconds", &labels);
+        }
+
+        out.push_str("# HELP ktme_ai_tokens_total Tokens reported by AI providers\n");
+        out.push_str("# TYPE ktme_ai_tokens_total counter\n");
+        for (provider, usage) in &self.ai {
+            let provider = escape_label(provider);
+            let _ = writeln!(
+                out,
+                "ktme_ai_tokens_total{{provider=\"{}\",kind=\"input\"}} {}",
+                provider, usage.input_tokens
+            );
+            let _ = writeln!(
+                out,
+                "ktme_ai_tokens_total{{provider=\"{}\",kind=\"output\"}} {}",
+                provider, usage.output_tokens
+            );
+        }
+
+        out.push_str("# HELP ktme_cache_lookups_total Cache lookups by result\n");
+        out.push_str("# TYPE ktme_cache_lookups_total counter\n");
+        for (cache, lookups) in &self.caches {
+            let cache = escape_label(cache);
+            let _ = writeln!(
+                out,
+                "ktme_cache_lookups_total{{cache=\"{}\",result=\"hit\"}} {}",
+                cache, lookups.hits
+            );
+            let _ = writeln!(
+                out,
+                "ktme_cache_lookups_total{{cache=\"{}\",result=\"miss\"}} {}",
+                cache, lookups.misses
+            );
+        }
+
+        out
+    }
+
+    /// Human-readable summary for `--timings`
+    pub fn summary(&self, total: Duration) -> String {
+        let mut out = format!("Timings (total {}):\n", format_duration(total));
+
+        for (stage, elapsed) in &self.stages {
+            let _ = writeln!(out, "  {:<40} {:>9}", stage, format_duration(*elapsed));
+        }
+        for (provider, usage) in &self.ai {
+            let stats = &usage.latency;
+            let _ = writeln!(
+                out,
+                "  AI {}: {} request(s), {} error(s), {} total, {} input / {} output tokens",
+                provider,
+                stats.calls,
+                stats.errors,
+                format_duration(Duration::from_secs_f64(stats.sum_seconds)),
+                usage.input_tokens,
+                usage.output_tokens
+            );
+        }
+        for (tool, stats) in &self.tools {
+            let _ = writeln!(
+                out,
+                "  Tool {}: {} call(s), {} error(s), {} total",
+                tool,
+                stats.calls,
+                stats.errors,
+                format_duration(Duration::from_secs_f64(stats.sum_seconds))
+            );
+        }
+        for (cache, lookups) in &self.caches {
+            let _ = writeln!(
+                out,
+                "  Cache {}: {} hit(s), {} miss(es), {:.0}% hit rate",
+                cache,
+                lookups.hits,
+                lookups.misses,
+                lookups.hit_rate() * 100.0
+            );
+        }
+
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_duration(elapsed: Duration) -> String {
+    if elapsed.as_secs() > 0 {
+        format!("{:.2}s", elapsed.as_secs_f64())
+    } else {
+        format!("{}ms", elapsed.as_millis())
+    }
+}
+
+fn with_registry<R>(f: impl FnOnce(&mut Registry) -> R) -> R {
+    f(&mut REGISTRY.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Record one MCP tool invocation
+pub fn record_tool_call(tool: &str, elapsed: Duration, success: bool) {
+    with_registry(|r| r.record_tool_call(tool, elapsed, success));
+}
+
+/// Record one AI provider request
+pub fn record_ai_request(provider: &str, elapsed: Duration, success: bool) {
+    with_registry(|r| r.record_ai_request(provider, elapsed, success));
+}
+
+/// Record token usage reported by an AI provider
+pub fn record_ai_tokens(provider: &str, input: u64, output: u64) {
+    with_registry(|r| r.record_ai_tokens(provider, input, output));
+}
+
+/// Record how long a named stage of a CLI command took
+pub fn record_stage(stage: &str, elapsed: Duration) {
+    with_registry(|r| r.record_stage(stage, elapsed));
+}
+
+/// Record a lookup in the named cache and whether it was a hit
+pub fn record_cache_lookup(cache: &str, hit: bool) {
+    with_registry(|r| r.record_cache_lookup(cache, hit));
+}
+
+/// Lookups of the named cache so far in this process
+pub fn cache_lookups(cache: &str) -> CacheLookups {
+    with_registry(|r| r.cache_lookups(cache))
+}
+
+/// Metrics of this process in Prometheus text format
+pub fn render_prometheus() -> String {
+    with_registry(|r| r.render_prometheus())
+}
+
+/// `--timings` summary of this process
+pub fn summary(total: Duration) -> String {
+    with_registry(|r| r.summary(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_output() {
+        let mut registry = Registry::default();
+        registry.record_tool_call("list_services", Duration::from_millis(20), true);
+        registry.record_tool_call("list_services", Duration::from_millis(300), false);
+        registry.record_ai_request("Claude", Duration::from_secs(3), true);
+        registry.record_ai_tokens("Claude", 1200, 350);
+        registry.record_cache_lookup("diff", true);
+        registry.record_cache_lookup("diff", false);
+        registry.record_cache_lookup("diff", true);
+
+        let text = registry.render_prometheus();
+        assert!(text.contains("ktme_tool_calls_total{tool=\"list_services\",status=\"ok\"} 1"));
+        assert!(text.contains("ktme_tool_calls_total{tool=\"list_services\",status=\"error\"} 1"));
+        assert!(text
+            .contains("ktme_tool_duration_seconds_bucket{tool=\"list_services\",le=\"0.05\"} 1"));
+        assert!(
+            text.contains("ktme_tool_duration_seconds_bucket{tool=\"list_services\",le=\"0.5\"} 2")
+        );
+        assert!(text.contains("ktme_tool_duration_seconds_count{tool=\"list_services\"} 2"));
+        assert!(text.contains("ktme_ai_requests_total{provider=\"Claude\",status=\"ok\"} 1"));
+        assert!(text.contains("ktme_ai_tokens_total{provider=\"Claude\",kind=\"input\"} 1200"));
+        assert!(text.contains("# TYPE ktme_ai_request_duration_seconds histogram"));
+        assert!(text.contains("ktme_cache_lookups_total{cache=\"diff\",result=\"hit\"} 2"));
+        assert!(text.contains("ktme_cache_lookups_total{cache=\"diff\",result=\"miss\"} 1"));
+        assert!((registry.cache_lookups("diff").hit_rate() - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_summary_lists_stages_and_ai_usage() {
+        let mut registry = Registry::default();
+        registry.record_stage("Extracting changes", Duration::from_millis(40));
+        registry.record_ai_request("Mock", Duration::from_millis(5), true);
+
+        let summary = registry.summary(Duration::from_millis(1500));
+        assert!(summary.starts_with("Timings (total 1.50s):"));
+        assert!(summary.contains("Extracting changes"));
+        assert!(summary.contains("40ms"));
+        assert!(summary.contains("AI Mock: 1 request(s), 0 error(s)"));
+    }
+}
@@ -0,0 +1,188 @@
+//! WASM analyzers and formatters from the plugins directory
+//!
+//! Every `*.wasm` file in the directory is one extension. Analyzers inspect
+//! the extracted diff and add notes to the documentation prompt; formatters
+//! rewrite the generated documentation. Extensions are only run when ktme is
+//! built with the `wasm` feature; see `plugins::wasm` for the module contract.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::git::diff::ExtractedDiff;
+use std::path::{Path, PathBuf};
+
+/// A `.wasm` file in the plugins directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionFile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The configured plugins directory
+pub fn directory(config: &Config) -> Result<PathBuf> {
+    match &config.plugins.directory {
+        Some(directory) => Ok(directory.clone()),
+        None => Ok(Config::config_dir()?.join("plugins")),
+    }
+}
+
+/// `.wasm` files in `dir`, sorted by name
+pub fn files(dir: &Path) -> Vec<ExtensionFile> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<ExtensionFile> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|e| e == "wasm"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some(ExtensionFile { name, path })
+        })
+        .collect();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    files
+}
+
+/// Roles of the extension in `file` ("analyzer", "formatter")
+pub fn describe(file: &ExtensionFile) -> Result<Vec<&'static str>> {
+    #[cfg(feature = "wasm")]
+    {
+        super::wasm::WasmExtension::load(&file.name, &file.path).map(|e| e.kinds())
+    }
+    #[cfg(not(feature = "wasm"))]
+    {
+        let _ = file;
+        Err(crate::error::KtmeError::UnsupportedOperation(
+            "ktme was built without the `wasm` feature".to_string(),
+        ))
+    }
+}
+
+/// The extensions loaded for one run. Failing extensions are logged and
+/// skipped so they cannot lose generated documentation.
+#[derive(Default)]
+pub struct Extensions {
+    #[cfg(feature = "wasm")]
+    loaded: Vec<super::wasm::WasmExtension>,
+}
+
+impl Extensions {
+    /// Load every extension in the configured plugins directory
+    pub fn load(config: &Config) -> Result<Self> {
+        let dir = directory(config)?;
+        let files = files(&dir);
+
+        #[cfg(feature = "wasm")]
+        {
+            let loaded = files
+                .iter()
+                .filter_map(
+                    |file| match super::wasm::WasmExtension::load(&file.name, &file.path) {
+                        Ok(extension) => Some(extension),
+                        Err(e) => {
+                            tracing::warn!(
+                                "Skipping WASM extension {}: {}",
+                                file.path.display(),
+                                e
+                            );
+                            None
+                        }
+                    },
+                )
+                .collect();
+            Ok(Self { loaded })
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            if !files.is_empty() {
+                tracing::warn!(
+                    "Ignoring {} WASM extension(s) in {}: ktme was built without the `wasm` feature",
+                    files.len(),
+                    dir.display()
+                );
+            }
+            Ok(Self::default())
+        }
+    }
+
+    /// `prompt` followed by the notes of every analyzer
+    pub fn annotate(&self, prompt: String, diff: &ExtractedDiff) -> String {
+        let notes = self.analyze(diff);
+        if notes.is_empty() {
+            return prompt;
+        }
+        let notes: Vec<String> = notes.iter().map(|note| format!("- {}", note)).collect();
+        format!(
+            "{}\n\n## Analyzer Notes\nTake these findings into account:\n{}",
+            prompt,
+            notes.join("\n")
+        )
+    }
+
+    /// Notes of every analyzer on `diff`
+    pub fn analyze(&self, diff: &ExtractedDiff) -> Vec<String> {
+        #[cfg(feature = "wasm")]
+        {
+            let mut notes = Vec::new();
+            for extension in self.loaded.iter().filter(|e| e.is_analyzer()) {
+                match extension.analyze(diff) {
+                    Ok(found) => notes.extend(found),
+                    Err(e) => tracing::warn!("{}", e),
+                }
+            }
+            notes
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            let _ = diff;
+            Vec::new()
+        }
+    }
+
+    /// `content` passed through every formatter in name order
+    pub fn format(&self, content: String) -> String {
+        #[cfg(feature = "wasm")]
+        {
+            let mut content = content;
+            for extension in self.loaded.iter().filter(|e| e.is_formatter()) {
+                match extension.format(&content) {
+                    Ok(formatted) => content = formatted,
+                    Err(e) => tracing::warn!("{}", e),
+                }
+            }
+            content
+        }
+        #[cfg(not(feature = "wasm"))]
+        {
+            content
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_extension_files() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("redact.wasm"), b"").unwrap();
+        std::fs::write(dir.path().join("api-lint.wasm"), b"").unwrap();
+        std::fs::write(dir.path().join("README.md"), b"").unwrap();
+        std::fs::create_dir(dir.path().join("nested.wasm")).unwrap();
+
+        let names: Vec<String> = files(dir.path()).into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["api-lint", "redact"]);
+        assert!(files(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_no_extensions_leave_content_unchanged() {
+        let extensions = Extensions::default();
+        let diff = ExtractedDiff::default();
+
+        assert_eq!(extensions.annotate("prompt".to_string(), &diff), "prompt");
+        assert_eq!(extensions.format("# Docs".to_string()), "# Docs");
+    }
+}
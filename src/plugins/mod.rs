@@ -0,0 +1,258 @@
+//! External plugin executables
+//!
+//! Plugins are executables on `PATH` named `<prefix><name>`, e.g.
+//! `ktme-provider-wiki` or `ktme-ai-gateway`. Every call runs the executable
+//! once, writes a single JSON request to its stdin and reads a single JSON
+//! response from its stdout:
+//!
+//! ```text
+//! request:  {"operation": "...", "config": {...}, "params": {...}}
+//! response: {"result": ...}
+//!       or: {"error": "message"}
+//! ```
+//!
+//! Anything a plugin writes to stderr is logged at debug level.
+//!
+//! Sandboxed WASM analyzers and formatters are loaded from the plugins
+//! directory instead; see [`extensions`].
+
+pub mod extensions;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use crate::error::{KtmeError, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+
+/// An executable plugin found on `PATH`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Plugins on `PATH` whose file name starts with `prefix`, sorted by name.
+/// When several directories hold a plugin with the same name, the first one
+/// on `PATH` wins.
+pub fn discover(prefix: &str) -> Vec<Plugin> {
+    match std::env::var_os("PATH") {
+        Some(path) => discover_in(std::env::split_paths(&path), prefix),
+        None => Vec::new(),
+    }
+}
+
+/// The plugin `<prefix><name>`, if one is on `PATH`
+pub fn find(prefix: &str, name: &str) -> Option<Plugin> {
+    discover(prefix)
+        .into_iter()
+        .find(|plugin| plugin.name == name)
+}
+
+fn discover_in(dirs: impl IntoIterator<Item = PathBuf>, prefix: &str) -> Vec<Plugin> {
+    let mut plugins: Vec<Plugin> = Vec::new();
+
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = plugin_name(&path, prefix) else {
+                continue;
+            };
+            if !is_executable(&path) || plugins.iter().any(|p| p.name == name) {
+                continue;
+            }
+            plugins.push(Plugin { name, path });
+        }
+    }
+
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+fn plugin_name(path: &Path, prefix: &str) -> Option<String> {
+    let file_name = if cfg!(windows) {
+        path.file_stem()?
+    } else {
+        path.file_name()?
+    };
+    let name = file_name.to_str()?.strip_prefix(prefix)?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("exe"))
+}
+
+#[derive(Deserialize)]
+struct PluginResponse {
+    #[serde(default)]
+    result: serde_json::Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl Plugin {
+    /// Run `operation` and decode the plugin's `result`
+    pub async fn invoke<T: DeserializeOwned>(
+        &self,
+        operation: &str,
+        config: &serde_json::Value,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        let request = serde_json::json!({
+            "operation": operation,
+            "config": config,
+            "params": params,
+        });
+        tracing::debug!("Calling plugin {} ({})", self.path.display(), operation);
+
+        let mut child = tokio::process::Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                KtmeError::UnsupportedProvider(format!(
+                    "Failed to start plugin {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(request.to_string().as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+        }
+        let output = child.wait_with_output().await?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            tracing::debug!("{}: {}", self.name, stderr.trim());
+        }
+
+        let response: PluginResponse = match serde_json::from_slice(&output.stdout) {
+            Ok(response) => response,
+            Err(_) if !output.status.success() => {
+                return Err(KtmeError::ApiError(format!(
+                    "Plugin '{}' failed ({}): {}",
+                    self.name,
+                    output.status,
+                    stderr.trim()
+                )))
+            }
+            Err(e) => {
+                return Err(KtmeError::DeserializationError(format!(
+                    "Plugin '{}' returned an invalid response to {}: {}",
+                    self.name, operation, e
+                )))
+            }
+        };
+
+        if let Some(error) = response.error {
+            return Err(KtmeError::ApiError(format!(
+                "Plugin '{}' failed to {}: {}",
+                self.name, operation, error
+            )));
+        }
+
+        serde_json::from_value(response.result).map_err(|e| {
+            KtmeError::DeserializationError(format!(
+                "Plugin '{}' returned an invalid {} result: {}",
+                self.name, operation, e
+            ))
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+pub(crate) mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    /// Write an executable `#!/bin/sh` plugin named `<prefix><name>`
+    pub(crate) fn write_plugin(dir: &Path, prefix: &str, name: &str, script: &str) -> Plugin {
+        let path = dir.join(format!("{}{}", prefix, name));
+        std::fs::write(&path, format!("#!/bin/sh\n{}", script)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Plugin {
+            name: name.to_string(),
+            path,
+        }
+    }
+
+    #[test]
+    fn test_discover_plugins() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        let wiki = write_plugin(first.path(), "ktme-provider-", "wiki", "exit 0\n");
+        write_plugin(second.path(), "ktme-provider-", "wiki", "exit 0\n");
+        write_plugin(second.path(), "ktme-provider-", "intranet", "exit 0\n");
+        write_plugin(second.path(), "ktme-ai-", "gateway", "exit 0\n");
+        std::fs::write(second.path().join("ktme-provider-notes"), "").unwrap();
+
+        let plugins = discover_in(
+            vec![first.path().into(), second.path().into()],
+            "ktme-provider-",
+        );
+
+        let names: Vec<&str> = plugins.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["intranet", "wiki"]);
+        assert_eq!(plugins[1], wiki);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_errors() {
+        let dir = TempDir::new().unwrap();
+        let plugin = write_plugin(
+            dir.path(),
+            "ktme-provider-",
+            "wiki",
+            r#"read request
+case "$request" in
+  *'"operation":"delete_document"'*) echo '{"error": "page is locked"}' ;;
+  *'"operation":"get_document"'*) echo 'not json' ;;
+  *) echo "boom" >&2; exit 2 ;;
+esac
+"#,
+        );
+        let config = serde_json::json!({});
+
+        let error = plugin
+            .invoke::<serde_json::Value>("delete_document", &config, serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("page is locked"));
+
+        let error = plugin
+            .invoke::<serde_json::Value>("get_document", &config, serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, KtmeError::DeserializationError(_)));
+
+        let error = plugin
+            .invoke::<bool>("health_check", &config, serde_json::json!({}))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("boom"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Sandboxed WASM extensions
+//!
+//! An extension is a core WebAssembly module exporting `memory`,
+//! `ktme_alloc(len: i32) -> i32` and at least one of:
+//!
+//! - `ktme_analyze(ptr: i32, len: i32) -> i64`: receives the extracted diff
+//!   as JSON and returns a JSON array of note strings
+//! - `ktme_format(ptr: i32, len: i32) -> i64`: receives the generated
+//!   documentation and returns the rewritten text
+//!
+//! Inputs are written to a buffer obtained from `ktme_alloc`; results are
+//! returned as `(ptr << 32) | len` in the module's memory. The host API is a
+//! single import, `ktme.log(ptr: i32, len: i32)`, which writes a message to
+//! the ktme log. Modules get no WASI, so they cannot reach files, the network
+//! or the environment, and every call runs in a fresh instance with bounded
+//! fuel and memory.
+
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use std::path::Path;
+use wasmtime::{
+    Caller, Config as EngineConfig, Engine, Extern, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+/// Fuel available to a single call, roughly one unit per instruction
+const FUEL_PER_CALL: u64 = 1_000_000_000;
+
+/// Linear memory an extension may grow to
+const MAX_MEMORY_BYTES: usize = 64 << 20;
+
+struct HostState {
+    name: String,
+    limits: StoreLimits,
+}
+
+/// A loaded analyzer and/or formatter module
+pub struct WasmExtension {
+    name: String,
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+    analyzer: bool,
+    formatter: bool,
+}
+
+impl WasmExtension {
+    /// Compile the module at `path` and check its exports
+    pub fn load(name: &str, path: &Path) -> Result<Self> {
+        let mut config = EngineConfig::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| wasm_error(name, e))?;
+        let bytes = std::fs::read(path)?;
+        let module = Module::new(&engine, bytes).map_err(|e| wasm_error(name, e))?;
+
+        let exports: Vec<&str> = module.exports().map(|e| e.name()).collect();
+        let analyzer = exports.contains(&"ktme_analyze");
+        let formatter = exports.contains(&"ktme_format");
+        if !analyzer && !formatter {
+            return Err(KtmeError::InvalidInput(format!(
+                "WASM extension '{}' exports neither ktme_analyze nor ktme_format",
+                name
+            )));
+        }
+        for required in ["memory", "ktme_alloc"] {
+            if !exports.contains(&required) {
+                return Err(KtmeError::InvalidInput(format!(
+                    "WASM extension '{}' does not export {}",
+                    name, required
+                )));
+            }
+        }
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap(
+                "ktme",
+                "log",
+                |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                    if let Some(message) = read_export_memory(&mut caller, ptr, len) {
+                        tracing::info!("[{}] {}", caller.data().name, message);
+                    }
+                },
+            )
+            .map_err(|e| wasm_error(name, e))?;
+
+        Ok(Self {
+            name: name.to_string(),
+            engine,
+            module,
+            linker,
+            analyzer,
+            formatter,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_analyzer(&self) -> bool {
+        self.analyzer
+    }
+
+    pub fn is_formatter(&self) -> bool {
+        self.formatter
+    }
+
+    pub fn kinds(&self) -> Vec<&'static str> {
+        let mut kinds = Vec::new();
+        if self.analyzer {
+            kinds.push("analyzer");
+        }
+        if self.formatter {
+            kinds.push("formatter");
+        }
+        kinds
+    }
+
+    /// Notes on `diff`
+    pub fn analyze(&self, diff: &ExtractedDiff) -> Result<Vec<String>> {
+        let output = self.call("ktme_analyze", &serde_json::to_vec(diff)?)?;
+        serde_json::from_slice(&output).map_err(|e| {
+            KtmeError::DeserializationError(format!(
+                "WASM extension '{}' returned invalid notes: {}",
+                self.name, e
+            ))
+        })
+    }
+
+    /// `content` rewritten by the formatter
+    pub fn format(&self, content: &str) -> Result<String> {
+        let output = self.call("ktme_format", content.as_bytes())?;
+        String::from_utf8(output).map_err(|e| {
+            KtmeError::DeserializationError(format!(
+                "WASM extension '{}' returned invalid UTF-8: {}",
+                self.name, e
+            ))
+        })
+    }
+
+    fn call(&self, export: &str, input: &[u8]) -> Result<Vec<u8>> {
+        let error = |e| wasm_error(&self.name, e);
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                name: self.name.clone(),
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(MAX_MEMORY_BYTES)
+                    .build(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(error)?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .map_err(error)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| error(wasmtime::Error::msg("`memory` is not a memory")))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "ktme_alloc")
+            .map_err(error)?;
+        let run = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, export)
+            .map_err(error)?;
+
+        let len = i32::try_from(input.len())
+            .map_err(|_| error(wasmtime::Error::msg("input exceeds 2 GiB")))?;
+        let ptr = alloc.call(&mut store, len).map_err(error)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| error(e.into()))?;
+
+        let packed = run.call(&mut store, (ptr, len)).map_err(error)? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        let output = memory
+            .data(&store)
+            .get(out_ptr..out_ptr.saturating_add(out_len))
+            .ok_or_else(|| error(wasmtime::Error::msg("result is outside of memory")))?;
+        Ok(output.to_vec())
+    }
+}
+
+fn read_export_memory(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Option<String> {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        return None;
+    };
+    let (start, len) = (ptr as u32 as usize, len as u32 as usize);
+    let bytes = memory.data(&caller).get(start..start.saturating_add(len))?;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn wasm_error(name: &str, error: wasmtime::Error) -> KtmeError {
+    KtmeError::Documentation(format!("WASM extension '{}' failed: {:#}", name, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Formatter appending a footer after the input, and an analyzer with a
+    /// fixed note
+    const EXTENSION: &str = r#"
+        (module
+          (import "ktme" "log" (func $log (param i32 i32)))
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 1024))
+          (data (i32.const 0) "\n\n_Checked_")
+          (data (i32.const 64) "[\"Public API changed\"]")
+          (func (export "ktme_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (i32.add (local.get $len) (i32.const 16))))
+            (local.get $ptr))
+          (func (export "ktme_format") (param $ptr i32) (param $len i32) (result i64)
+            (call $log (i32.const 3) (i32.const 7))
+            (memory.copy (i32.add (local.get $ptr) (local.get $len)) (i32.const 0) (i32.const 11))
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (i32.add (local.get $len) (i32.const 11)))))
+          (func (export "ktme_analyze") (param i32 i32) (result i64)
+            (i64.or (i64.shl (i64.const 64) (i64.const 32)) (i64.const 22))))
+    "#;
+
+    fn write_extension(dir: &TempDir, name: &str, wat: &str) -> std::path::PathBuf {
+        let path = dir.path().join(format!("{}.wasm", name));
+        std::fs::write(&path, wat).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_wasm_extension_calls() {
+        let dir = TempDir::new().unwrap();
+        let path = write_extension(&dir, "footer", EXTENSION);
+        let extension = WasmExtension::load("footer", &path).unwrap();
+
+        assert_eq!(extension.kinds(), vec!["analyzer", "formatter"]);
+        assert_eq!(
+            extension.format("# Payments").unwrap(),
+            "# Payments\n\n_Checked_"
+        );
+        assert_eq!(
+            extension.analyze(&ExtractedDiff::default()).unwrap(),
+            vec!["Public API changed".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_wasm_extension_sandbox() {
+        let dir = TempDir::new().unwrap();
+
+        let path = write_extension(&dir, "empty", r#"(module (memory (export "memory") 1))"#);
+        assert!(WasmExtension::load("empty", &path).is_err());
+
+        let looping = r#"
+            (module
+              (memory (export "memory") 1)
+              (func (export "ktme_alloc") (param i32) (result i32) (i32.const 0))
+              (func (export "ktme_format") (param i32 i32) (result i64)
+                (loop $forever (br $forever))
+                (i64.const 0)))
+        "#;
+        let path = write_extension(&dir, "looping", looping);
+        let extension = WasmExtension::load("looping", &path).unwrap();
+        let error = extension.format("# Docs").unwrap_err();
+        assert!(error.to_string().contains("looping"));
+    }
+}
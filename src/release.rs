@@ -0,0 +1,251 @@
+//! Release metadata of ktme on GitHub
+//!
+//! `ktme self-update` reads the latest release from the GitHub releases API
+//! (or `KTME_RELEASES_URL`, e.g. a mirror), picks the binary built for this
+//! platform by the release workflow and checks it against the release's
+//! `SHA256SUMS` asset. Binaries installed by a package manager are left to
+//! it; see [`InstallMethod`].
+
+use crate::error::{KtmeError, Result};
+use semver::Version;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Latest release of the ktme repository
+pub const RELEASES_API: &str = "https://api.github.com/repos/FreePeak/ktme/releases/latest";
+
+/// Overrides [`RELEASES_API`]
+pub const RELEASES_URL_ENV: &str = "KTME_RELEASES_URL";
+
+/// Asset listing the SHA-256 of every binary of a release, as written by
+/// `sha256sum`
+pub const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// A published release
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    /// e.g. `v0.4.0`
+    pub tag_name: String,
+    #[serde(default)]
+    pub html_url: String,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+/// A file attached to a release
+#[derive(Debug, Clone, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// Version of the release, from its tag
+    pub fn version(&self) -> Result<Version> {
+        parse_version(&self.tag_name)
+    }
+
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// Version of the running binary
+pub fn current_version() -> Version {
+    parse_version(env!("CARGO_PKG_VERSION")).expect("valid package version")
+}
+
+/// Parse a version or tag such as `v0.4.0`
+pub fn parse_version(tag: &str) -> Result<Version> {
+    Version::parse(tag.trim().trim_start_matches('v'))
+        .map_err(|e| KtmeError::InvalidInput(format!("Invalid release version '{}': {}", tag, e)))
+}
+
+/// Name of the release asset built for this platform, as in the release
+/// workflow; `None` on platforms without a published binary
+pub fn platform_asset() -> Option<&'static str> {
+    if cfg!(all(
+        target_os = "linux",
+        target_arch = "x86_64",
+        target_env = "musl"
+    )) {
+        Some("ktme-linux-x64-musl")
+    } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+        Some("ktme-linux-x64")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("ktme-darwin-x64")
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("ktme-darwin-arm64")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("ktme-windows-x64.exe")
+    } else {
+        None
+    }
+}
+
+/// The latest release
+pub async fn latest() -> Result<Release> {
+    crate::http::ensure_online("Checking for a new ktme release")?;
+    let url = std::env::var(RELEASES_URL_ENV).unwrap_or_else(|_| RELEASES_API.to_string());
+    let response = request(&url)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await
+        .map_err(|e| crate::http::request_error("Failed to fetch the latest release", e))?;
+    if !response.status().is_success() {
+        return Err(KtmeError::NetworkError(format!(
+            "Fetching the latest release from {} failed with {}",
+            url,
+            response.status()
+        )));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| KtmeError::NetworkError(format!("Invalid release metadata: {}", e)))
+}
+
+/// Content of `asset`
+pub async fn download(asset: &Asset) -> Result<Vec<u8>> {
+    crate::http::ensure_online("Downloading a ktme release")?;
+    let response = request(&asset.browser_download_url)
+        .send()
+        .await
+        .map_err(|e| {
+            crate::http::request_error(&format!("Failed to download {}", asset.name), e)
+        })?;
+    if !response.status().is_success() {
+        return Err(KtmeError::NetworkError(format!(
+            "Downloading {} failed with {}",
+            asset.name,
+            response.status()
+        )));
+    }
+    let bytes = response.bytes().await.map_err(|e| {
+        crate::http::request_error(&format!("Failed to download {}", asset.name), e)
+    })?;
+    Ok(bytes.to_vec())
+}
+
+fn request(url: &str) -> reqwest::RequestBuilder {
+    let client = crate::http::client("github")
+        .user_agent("ktme-cli")
+        .build()
+        .unwrap_or_default();
+    let request = client.get(url);
+    // A token raises the API rate limit of shared CI runners
+    match std::env::var("GITHUB_TOKEN") {
+        Ok(token) if url.starts_with("https://api.github.com/") => request.bearer_auth(token),
+        _ => request,
+    }
+}
+
+/// Check `binary` against its line in the `sums` of a release
+pub fn verify_checksum(binary: &[u8], name: &str, sums: &str) -> Result<()> {
+    let expected = sums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, file)| file.trim().trim_start_matches('*') == name)
+        .map(|(sum, _)| sum.to_lowercase())
+        .ok_or_else(|| {
+            KtmeError::NotFound(format!("Checksum of {} in {}", name, CHECKSUMS_ASSET))
+        })?;
+    let actual = format!("{:x}", Sha256::digest(binary));
+    if actual != expected {
+        return Err(KtmeError::InvalidInput(format!(
+            "Checksum mismatch for {}: expected {}, downloaded {}",
+            name, expected, actual
+        )));
+    }
+    Ok(())
+}
+
+/// How the running binary was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallMethod {
+    Homebrew,
+    Npm,
+    Cargo,
+    /// A downloaded release binary, which `ktme self-update` replaces
+    Standalone,
+}
+
+impl InstallMethod {
+    /// Guess from the (canonical) path of the binary
+    pub fn detect(exe: &Path) -> Self {
+        let path = exe.to_string_lossy().replace('\\', "/");
+        if path.contains("/Cellar/") || path.contains("/homebrew/") || path.contains("/linuxbrew/")
+        {
+            Self::Homebrew
+        } else if path.contains("/node_modules/") {
+            Self::Npm
+        } else if path.contains("/.cargo/bin/") {
+            Self::Cargo
+        } else {
+            Self::Standalone
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Homebrew => "homebrew",
+            Self::Npm => "npm",
+            Self::Cargo => "cargo",
+            Self::Standalone => "standalone",
+        }
+    }
+
+    /// Command updating a binary of this installation method
+    pub fn upgrade_command(self) -> &'static str {
+        match self {
+            Self::Homebrew => "brew upgrade ktme",
+            Self::Npm => "npm install -g ktme-cli@latest",
+            Self::Cargo => "cargo install ktme --force",
+            Self::Standalone => "ktme self-update",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_checksum() {
+        let binary = b"ktme binary";
+        let sum = format!("{:x}", Sha256::digest(binary));
+        let sums = format!(
+            "{}  ktme-darwin-arm64\n{} *ktme-linux-x64\n",
+            "0".repeat(64),
+            sum
+        );
+        assert!(verify_checksum(binary, "ktme-linux-x64", &sums).is_ok());
+        assert!(verify_checksum(binary, "ktme-darwin-arm64", &sums).is_err());
+        assert!(verify_checksum(binary, "ktme-windows-x64.exe", &sums).is_err());
+    }
+
+    #[test]
+    fn test_versions_and_install_methods() {
+        assert!(parse_version("v0.10.0").unwrap() > parse_version("0.9.1").unwrap());
+        assert!(parse_version("v1.0.0-rc.1").unwrap() < parse_version("1.0.0").unwrap());
+        assert!(parse_version("latest").is_err());
+
+        assert_eq!(
+            InstallMethod::detect(Path::new("/opt/homebrew/Cellar/ktme/0.3.0/bin/ktme")),
+            InstallMethod::Homebrew
+        );
+        assert_eq!(
+            InstallMethod::detect(Path::new("/usr/lib/node_modules/ktme/bin/ktme")),
+            InstallMethod::Npm
+        );
+        assert_eq!(
+            InstallMethod::detect(Path::new("/home/dev/.cargo/bin/ktme")),
+            InstallMethod::Cargo
+        );
+        assert_eq!(
+            InstallMethod::detect(Path::new("/usr/local/bin/ktme")),
+            InstallMethod::Standalone
+        );
+    }
+}
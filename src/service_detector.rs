@@ -1,7 +1,83 @@
 use crate::ai::AIClient;
+use crate::config::Config;
 use crate::error::{KtmeError, Result};
+use crate::storage::backend::{open_storage, Storage};
+use regex::Regex;
+use serde::Serialize;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Confidence of the package declared in the current directory
+const PACKAGE: f32 = 0.9;
+/// Confidence of a solution file, which usually groups several services
+const SOLUTION: f32 = 0.7;
+/// Confidence lost per directory between a manifest and the current one
+const DEPTH_PENALTY: f32 = 0.15;
+const MIN_PACKAGE: f32 = 0.3;
+/// Confidence of the workspace member containing the current directory
+const MEMBER: f32 = 0.95;
+/// Confidence of the other members, listed at the workspace root
+const OTHER_MEMBER: f32 = 0.4;
+const GIT_REPOSITORY: f32 = 0.5;
+const DIRECTORY: f32 = 0.2;
+
+/// Possible service name of a directory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    pub name: String,
+    /// Where the name was found, e.g. "Cargo.toml workspace member crates/api"
+    pub source: String,
+    /// Between 0 and 1
+    pub confidence: f32,
+}
+
+/// Where the service of a directory came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// Set with `ktme service pin`
+    Pinned,
+    /// Detected by an earlier command in the same directory
+    Cached,
+    Detected,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let origin = match self {
+            Self::Pinned => "pinned",
+            Self::Cached => "cached",
+            Self::Detected => "detected",
+        };
+        f.write_str(origin)
+    }
+}
+
+/// Service of a directory
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Detection {
+    pub name: String,
+    pub origin: Origin,
+}
+
+/// Detections and pins of the configured workspace, kept in the configured
+/// storage backend; `None` when it cannot be opened
+pub fn detection_cache(config: &Config) -> Option<Box<dyn Storage>> {
+    open_storage(&config.storage)
+        .map_err(|e| tracing::debug!("Service detection cache unavailable: {}", e))
+        .ok()
+}
+
+impl Candidate {
+    fn new(name: impl Into<String>, source: impl Into<String>, confidence: f32) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+            confidence,
+        }
+    }
+}
 
 pub struct ServiceDetector {
     current_dir: PathBuf,
@@ -18,30 +94,22 @@ impl ServiceDetector {
         Self { current_dir: dir }
     }
 
-    /// Detect service name using multiple strategies
+    /// Detect service name using multiple strategies: the manifests of the
+    /// current directory and its parents, the repository directory, and
+    /// finally the current directory name
     pub async fn detect_service_name(&self) -> Result<String> {
-        // Strategy 1: Get directory name containing .git
-        if let Some(name) = self.detect_from_git_repository()? {
-            tracing::info!("Detected service name from Git repository: {}", name);
-            return Ok(name);
-        }
-
-        // Strategy 2: Check for common project indicators
-        if let Some(name) = self.detect_from_project_indicators()? {
-            tracing::info!("Detected service name from project indicators: {}", name);
-            return Ok(name);
+        match self.candidates().into_iter().next() {
+            Some(candidate) => {
+                tracing::info!(
+                    "Detected service name from {}: {} ({:.2})",
+                    candidate.source,
+                    candidate.name,
+                    candidate.confidence
+                );
+                Ok(candidate.name)
+            }
+            None => Ok("unknown-service".to_string()),
         }
-
-        // Strategy 3: Use current directory name as fallback
-        let dir_name = self
-            .current_dir
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown-service")
-            .to_string();
-
-        tracing::info!("Using directory name as service: {}", dir_name);
-        Ok(dir_name)
     }
 
     /// Detect service name with AI agent fallback
@@ -58,124 +126,137 @@ impl ServiceDetector {
         self.ask_ai_for_service_name().await
     }
 
-    /// Find the directory containing .git and return its name
-    fn detect_from_git_repository(&self) -> Result<Option<String>> {
-        let mut current_path = self.current_dir.clone();
-
-        loop {
-            let git_path = current_path.join(".git");
-
-            if git_path.exists() {
-                // Found .git directory, return the parent directory name
-                if let Some(dir_name) = current_path.file_name() {
-                    return Ok(dir_name.to_str().map(|s| s.to_string()));
-                }
-                break;
-            }
-
-            // Move up to parent directory
-            if !current_path.pop() {
-                // Reached root directory without finding .git
-                break;
-            }
-        }
-
-        Ok(None)
-    }
-
-    /// Detect service name from common project files
-    fn detect_from_project_indicators(&self) -> Result<Option<String>> {
-        // Check for Cargo.toml (Rust)
-        let cargo_toml = self.current_dir.join("Cargo.toml");
-        if cargo_toml.exists() {
-            if let Some(name) = self.extract_name_from_cargo_toml(&cargo_toml)? {
-                return Ok(Some(name));
-            }
+    /// Service of the current directory: the one pinned to its repository,
+    /// the one detected in it before, or [`Self::detect_with_ai_fallback`],
+    /// recorded in `cache` for the next call
+    pub async fn detect_cached(&self, cache: Option<&dyn Storage>) -> Result<Detection> {
+        let Some(cache) = cache else {
+            return Ok(Detection {
+                name: self.detect_with_ai_fallback().await?,
+                origin: Origin::Detected,
+            });
+        };
+
+        if let Some(pin) = cache.get_service_detection(&self.pin_directory(), true)? {
+            return Ok(Detection {
+                name: pin.service_name,
+                origin: Origin::Pinned,
+            });
         }
-
-        // Check for package.json (Node.js)
-        let package_json = self.current_dir.join("package.json");
-        if package_json.exists() {
-            if let Some(name) = self.extract_name_from_package_json(&package_json)? {
-                return Ok(Some(name));
-            }
+        let directory = directory_key(&self.current_dir);
+        if let Some(cached) = cache.get_service_detection(&directory, false)? {
+            return Ok(Detection {
+                name: cached.service_name,
+                origin: Origin::Cached,
+            });
         }
 
-        // Check for go.mod (Go)
-        let go_mod = self.current_dir.join("go.mod");
-        if go_mod.exists() {
-            if let Some(name) = self.extract_name_from_go_mod(&go_mod)? {
-                return Ok(Some(name));
-            }
+        let name = self.detect_with_ai_fallback().await?;
+        if let Err(e) = cache.save_service_detection(&directory, &name, false) {
+            tracing::warn!("Failed to cache the detected service: {}", e);
         }
+        Ok(Detection {
+            name,
+            origin: Origin::Detected,
+        })
+    }
 
-        // Check for pyproject.toml (Python)
-        let pyproject_toml = self.current_dir.join("pyproject.toml");
-        if pyproject_toml.exists() {
-            if let Some(name) = self.extract_name_from_pyproject_toml(&pyproject_toml)? {
-                return Ok(Some(name));
-            }
-        }
+    /// Directory a `ktme service pin` applies to: the repository root, or
+    /// the current directory outside a repository
+    pub fn pin_directory(&self) -> String {
+        directory_key(
+            &self
+                .get_git_repository_root()
+                .unwrap_or_else(|| self.current_dir.clone()),
+        )
+    }
 
-        Ok(None)
+    /// Directory detections are cached for
+    pub fn directory(&self) -> String {
+        directory_key(&self.current_dir)
     }
 
-    fn extract_name_from_cargo_toml(&self, path: &PathBuf) -> Result<Option<String>> {
-        let content = fs::read_to_string(path)?;
-        for line in content.lines() {
-            if line.trim().starts_with("name = ") {
-                let name_part = line.split('=').nth(1).unwrap_or("").trim();
-                let name = name_part.trim_matches('"');
-                if !name.is_empty() {
-                    return Ok(Some(name.to_string()));
+    /// Possible service names of the current directory, most likely first.
+    ///
+    /// Manifests are read from the current directory up to the repository
+    /// root; a workspace member containing the current directory ranks
+    /// above the package of the directory itself, and manifests further up
+    /// rank lower.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        let mut candidates = Vec::new();
+        let repo_root = self.get_git_repository_root();
+
+        let dirs: Vec<&Path> = match &repo_root {
+            Some(root) => self
+                .current_dir
+                .ancestors()
+                .take_while(|dir| dir.starts_with(root))
+                .collect(),
+            None => vec![self.current_dir.as_path()],
+        };
+        for (depth, dir) in dirs.into_iter().enumerate() {
+            for parse in MANIFESTS {
+                if let Some(manifest) = parse(dir) {
+                    self.add_manifest(dir, depth, manifest, &mut candidates);
                 }
             }
         }
-        Ok(None)
-    }
 
-    fn extract_name_from_package_json(&self, path: &PathBuf) -> Result<Option<String>> {
-        let content = fs::read_to_string(path)?;
-        for line in content.lines() {
-            if line.trim().starts_with("\"name\":") {
-                let name_part = line.split(':').nth(1).unwrap_or("").trim();
-                let name = name_part.trim_matches('"').trim_matches(',');
-                if !name.is_empty() {
-                    return Ok(Some(name.to_string()));
-                }
-            }
+        if let Some(name) = repo_root.as_deref().and_then(dir_name) {
+            candidates.push(Candidate::new(name, "Git repository", GIT_REPOSITORY));
+        }
+        if let Some(name) = dir_name(&self.current_dir) {
+            candidates.push(Candidate::new(name, "directory name", DIRECTORY));
         }
-        Ok(None)
+
+        // Keep the best score of every name
+        candidates.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        let mut seen = std::collections::HashSet::new();
+        candidates.retain(|candidate| seen.insert(candidate.name.clone()));
+        candidates
     }
 
-    fn extract_name_from_go_mod(&self, path: &PathBuf) -> Result<Option<String>> {
-        let content = fs::read_to_string(path)?;
-        for line in content.lines() {
-            if line.trim().starts_with("module ") {
-                let module_part = line.strip_prefix("module ").unwrap_or("").trim();
-                if let Some(name) = module_part.split('/').last() {
-                    let name = name.trim_matches('"');
-                    if !name.is_empty() {
-                        return Ok(Some(name.to_string()));
-                    }
-                }
-            }
+    fn add_manifest(
+        &self,
+        dir: &Path,
+        depth: usize,
+        manifest: Manifest,
+        candidates: &mut Vec<Candidate>,
+    ) {
+        if let Some(name) = manifest.name {
+            let confidence = (manifest.confidence - depth as f32 * DEPTH_PENALTY).max(MIN_PACKAGE);
+            candidates.push(Candidate::new(name, manifest.source.clone(), confidence));
         }
-        Ok(None)
-    }
 
-    fn extract_name_from_pyproject_toml(&self, path: &PathBuf) -> Result<Option<String>> {
-        let content = fs::read_to_string(path)?;
-        for line in content.lines() {
-            if line.trim().starts_with("name = ") {
-                let name_part = line.split('=').nth(1).unwrap_or("").trim();
-                let name = name_part.trim_matches('"');
-                if !name.is_empty() {
-                    return Ok(Some(name.to_string()));
-                }
+        for member_dir in manifest.members.iter().flat_map(|m| member_dirs(dir, m)) {
+            let contains_current = self.current_dir.starts_with(&member_dir);
+            // Every member is a candidate at the workspace root
+            if !contains_current && depth > 0 {
+                continue;
             }
+            let Some(name) = package_name(&member_dir) else {
+                continue;
+            };
+            let member = member_dir
+                .strip_prefix(dir)
+                .unwrap_or(&member_dir)
+                .to_string_lossy()
+                .into_owned();
+            let confidence = if contains_current {
+                MEMBER
+            } else {
+                OTHER_MEMBER
+            };
+            candidates.push(Candidate::new(
+                name,
+                format!("{} workspace member {}", manifest.source, member),
+                confidence,
+            ));
         }
-        Ok(None)
     }
 
     fn is_valid_service_name(&self, name: &str) -> bool {
@@ -299,6 +380,301 @@ pub struct RepositoryInfo {
     pub is_git_repository: bool,
 }
 
+/// Package name and workspace members declared by a manifest
+struct Manifest {
+    source: String,
+    name: Option<String>,
+    /// Member directories relative to the manifest, possibly with globs
+    members: Vec<String>,
+    /// Confidence of `name` in the current directory
+    confidence: f32,
+}
+
+impl Manifest {
+    fn new(source: &str, name: Option<String>, members: Vec<String>) -> Self {
+        Self {
+            source: source.to_string(),
+            name: name.filter(|name| !name.trim().is_empty()),
+            members,
+            confidence: PACKAGE,
+        }
+    }
+}
+
+type ManifestParser = fn(&Path) -> Option<Manifest>;
+
+const MANIFESTS: &[ManifestParser] = &[
+    cargo_manifest,
+    npm_manifest,
+    go_manifest,
+    python_manifest,
+    maven_manifest,
+    gradle_manifest,
+    csproj_manifest,
+    solution_manifest,
+];
+
+/// Name of the package in `dir`, or the directory name for manifests that
+/// do not declare one
+fn package_name(dir: &Path) -> Option<String> {
+    MANIFESTS
+        .iter()
+        .filter_map(|parse| parse(dir))
+        .find_map(|manifest| manifest.name)
+        .or_else(|| dir.is_dir().then(|| dir_name(dir)).flatten())
+}
+
+/// Directories matched by the workspace member `pattern` of the manifest
+/// in `dir`
+fn member_dirs(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let pattern = pattern.trim_end_matches('/');
+    if !pattern.contains('*') {
+        return vec![dir.join(pattern)];
+    }
+    let mut dirs: Vec<PathBuf> = glob::glob(&dir.join(pattern).to_string_lossy())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// `dir` as stored in the detection cache: absolute, with symlinks resolved
+fn directory_key(dir: &Path) -> String {
+    dir.canonicalize()
+        .unwrap_or_else(|_| dir.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn dir_name(dir: &Path) -> Option<String> {
+    dir.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+}
+
+fn read(dir: &Path, file: &str) -> Option<String> {
+    fs::read_to_string(dir.join(file)).ok()
+}
+
+fn toml_str(value: &toml::Value, path: &[&str]) -> Option<String> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(|value| value.as_str())
+        .map(str::to_string)
+}
+
+fn toml_strings(value: &toml::Value, path: &[&str]) -> Vec<String> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(|value| value.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.as_str().map(str::to_string))
+        .collect()
+}
+
+/// `[package] name` and `[workspace] members` of a Cargo.toml
+fn cargo_manifest(dir: &Path) -> Option<Manifest> {
+    let cargo: toml::Value = toml::from_str(&read(dir, "Cargo.toml")?).ok()?;
+    Some(Manifest::new(
+        "Cargo.toml",
+        toml_str(&cargo, &["package", "name"]),
+        toml_strings(&cargo, &["workspace", "members"]),
+    ))
+}
+
+/// Name of a package.json without its npm scope, and its workspaces
+fn npm_manifest(dir: &Path) -> Option<Manifest> {
+    let package: serde_json::Value = serde_json::from_str(&read(dir, "package.json")?).ok()?;
+    let name = package["name"]
+        .as_str()
+        .map(|name| match name.split_once('/') {
+            Some((scope, name)) if scope.starts_with('@') => name.to_string(),
+            _ => name.to_string(),
+        });
+    let workspaces = match &package["workspaces"] {
+        serde_json::Value::Array(patterns) => patterns.clone(),
+        workspaces => workspaces["packages"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default(),
+    };
+    let members = workspaces
+        .iter()
+        .filter_map(|pattern| pattern.as_str().map(str::to_string))
+        .collect();
+    Some(Manifest::new("package.json", name, members))
+}
+
+/// Last element of the go.mod module path, skipping a major version suffix
+fn go_manifest(dir: &Path) -> Option<Manifest> {
+    let content = read(dir, "go.mod")?;
+    let module = content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().trim_matches('"'))?;
+    let mut segments = module.rsplit('/');
+    let name = match segments.next() {
+        Some(last) if is_major_version(last) => segments.next(),
+        last => last,
+    };
+    Some(Manifest::new(
+        "go.mod",
+        name.map(str::to_string),
+        Vec::new(),
+    ))
+}
+
+fn is_major_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// `[project] name` of a pyproject.toml, or `[tool.poetry] name`
+fn python_manifest(dir: &Path) -> Option<Manifest> {
+    let project: toml::Value = toml::from_str(&read(dir, "pyproject.toml")?).ok()?;
+    let name = toml_str(&project, &["project", "name"])
+        .or_else(|| toml_str(&project, &["tool", "poetry", "name"]));
+    Some(Manifest::new("pyproject.toml", name, Vec::new()))
+}
+
+/// `artifactId` of the project in a pom.xml (not of its parent, plugins or
+/// dependencies) and its modules
+fn maven_manifest(dir: &Path) -> Option<Manifest> {
+    static NESTED: OnceLock<Regex> = OnceLock::new();
+    static ARTIFACT_ID: OnceLock<Regex> = OnceLock::new();
+    static MODULE: OnceLock<Regex> = OnceLock::new();
+
+    let content = read(dir, "pom.xml")?;
+    let nested = NESTED.get_or_init(|| {
+        Regex::new(
+            r"(?s)<!--.*?-->|<(parent|dependencies|dependencyManagement|build|profiles|reporting)>.*?</(parent|dependencies|dependencyManagement|build|profiles|reporting)>",
+        )
+        .expect("valid nested element pattern")
+    });
+    let project = nested.replace_all(&content, "");
+    let name = ARTIFACT_ID
+        .get_or_init(|| {
+            Regex::new(r"<artifactId>\s*([^<\s]+)\s*</artifactId>")
+                .expect("valid artifactId pattern")
+        })
+        .captures(&project)
+        .map(|captures| captures[1].to_string());
+    let members = MODULE
+        .get_or_init(|| {
+            Regex::new(r"<module>\s*([^<]+?)\s*</module>").expect("valid module pattern")
+        })
+        .captures_iter(&project)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    Some(Manifest::new("pom.xml", name, members))
+}
+
+/// `rootProject.name` and included projects of a Gradle settings file; a
+/// build file alone names the project after its directory
+fn gradle_manifest(dir: &Path) -> Option<Manifest> {
+    static ROOT_NAME: OnceLock<Regex> = OnceLock::new();
+    static INCLUDE: OnceLock<Regex> = OnceLock::new();
+    static QUOTED: OnceLock<Regex> = OnceLock::new();
+
+    let settings = ["settings.gradle.kts", "settings.gradle"]
+        .into_iter()
+        .find_map(|file| read(dir, file).map(|content| (file, content)));
+    let Some((file, content)) = settings else {
+        let build = ["build.gradle.kts", "build.gradle"]
+            .into_iter()
+            .find(|file| dir.join(file).exists())?;
+        return Some(Manifest::new(build, dir_name(dir), Vec::new()));
+    };
+
+    let name = ROOT_NAME
+        .get_or_init(|| {
+            Regex::new(r#"rootProject\.name\s*=\s*["']([^"']+)["']"#)
+                .expect("valid rootProject pattern")
+        })
+        .captures(&content)
+        .map(|captures| captures[1].to_string())
+        .or_else(|| dir_name(dir));
+    let quoted =
+        QUOTED.get_or_init(|| Regex::new(r#"["']:?([^"']+)["']"#).expect("valid quoted pattern"));
+    let members = INCLUDE
+        .get_or_init(|| Regex::new(r"(?m)^\s*include\b(.*)$").expect("valid include pattern"))
+        .captures_iter(&content)
+        .flat_map(|captures| {
+            quoted
+                .captures_iter(captures.get(1).map_or("", |m| m.as_str()))
+                .map(|project| project[1].replace(':', "/"))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    Some(Manifest::new(file, name, members))
+}
+
+fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    files.sort();
+    files
+}
+
+/// `AssemblyName` of a C# project, or the name of its project file
+fn csproj_manifest(dir: &Path) -> Option<Manifest> {
+    static ASSEMBLY_NAME: OnceLock<Regex> = OnceLock::new();
+
+    let path = files_with_extension(dir, "csproj").into_iter().next()?;
+    let content = fs::read_to_string(&path).ok()?;
+    let name = ASSEMBLY_NAME
+        .get_or_init(|| {
+            Regex::new(r"<AssemblyName>\s*([^<]+?)\s*</AssemblyName>")
+                .expect("valid AssemblyName pattern")
+        })
+        .captures(&content)
+        .map(|captures| captures[1].to_string())
+        .or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        });
+    Some(Manifest::new("C# project", name, Vec::new()))
+}
+
+/// Name of a .sln file and the directories of its C# projects
+fn solution_manifest(dir: &Path) -> Option<Manifest> {
+    static PROJECT: OnceLock<Regex> = OnceLock::new();
+
+    let path = files_with_extension(dir, "sln").into_iter().next()?;
+    let content = fs::read_to_string(&path).ok()?;
+    let members = PROJECT
+        .get_or_init(|| {
+            Regex::new(r#"Project\("[^"]*"\)\s*=\s*"[^"]*",\s*"([^"]+\.csproj)""#)
+                .expect("valid solution project pattern")
+        })
+        .captures_iter(&content)
+        .filter_map(|captures| {
+            let project = captures[1].replace('\\', "/");
+            Path::new(&project)
+                .parent()
+                .map(|parent| parent.to_string_lossy().into_owned())
+        })
+        .filter(|member| !member.is_empty())
+        .collect();
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned());
+    Some(Manifest {
+        confidence: SOLUTION,
+        ..Manifest::new("solution", name, members)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -320,4 +696,161 @@ mod tests {
         assert!(!detector.is_valid_service_name("unknown-service"));
         assert!(!detector.is_valid_service_name("app"));
     }
+
+    fn write(dir: &Path, file: &str, content: &str) {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn names(detector: &ServiceDetector) -> Vec<String> {
+        detector.candidates().into_iter().map(|c| c.name).collect()
+    }
+
+    #[test]
+    fn test_candidates_pick_the_cargo_workspace_member_of_the_current_directory() {
+        let repo = tempfile::tempdir().unwrap();
+        fs::create_dir(repo.path().join(".git")).unwrap();
+        write(
+            repo.path(),
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"crates/*\",\n]\n\n[workspace.package]\nname = \"ignored\"\n",
+        );
+        write(
+            repo.path(),
+            "crates/api/Cargo.toml",
+            "[package]\nversion = \"0.1.0\"\nname = \"payments-api\"\n\n[dependencies]\nname = \"1\"\n",
+        );
+        write(
+            repo.path(),
+            "crates/worker/Cargo.toml",
+            "[package]\nname = \"payments-worker\"\n",
+        );
+
+        let detector = ServiceDetector::from_directory(repo.path().join("crates/api/src"));
+        let candidates = detector.candidates();
+        assert_eq!(candidates[0].name, "payments-api");
+        assert_eq!(
+            candidates[0].source,
+            "Cargo.toml workspace member crates/api"
+        );
+        assert!(!names(&detector).contains(&"payments-worker".to_string()));
+
+        // At the workspace root every member is a candidate
+        let detector = ServiceDetector::from_directory(repo.path().to_path_buf());
+        let candidates = detector.candidates();
+        let repo_name = dir_name(repo.path()).unwrap();
+        assert_eq!(candidates[0].name, repo_name);
+        assert!(candidates
+            .iter()
+            .any(|c| c.name == "payments-worker" && c.confidence == OTHER_MEMBER));
+    }
+
+    #[tokio::test]
+    async fn test_detect_cached_prefers_the_pin_then_the_cache() {
+        let repo = tempfile::tempdir().unwrap();
+        fs::create_dir(repo.path().join(".git")).unwrap();
+        write(repo.path(), "api/package.json", r#"{"name": "orders-api"}"#);
+        let storage = crate::storage::backend::SqliteStorage::new(
+            crate::storage::database::Database::in_memory().unwrap(),
+        );
+        let cache: &dyn Storage = &storage;
+        let detector = ServiceDetector::from_directory(repo.path().join("api"));
+
+        let first = detector.detect_cached(Some(cache)).await.unwrap();
+        assert_eq!(first.name, "orders-api");
+        assert_eq!(first.origin, Origin::Detected);
+
+        // Later runs read the cache even when the manifest changed
+        write(repo.path(), "api/package.json", r#"{"name": "renamed"}"#);
+        let second = detector.detect_cached(Some(cache)).await.unwrap();
+        assert_eq!(second.name, "orders-api");
+        assert_eq!(second.origin, Origin::Cached);
+
+        cache
+            .save_service_detection(&detector.pin_directory(), "orders", true)
+            .unwrap();
+        let pinned = detector.detect_cached(Some(cache)).await.unwrap();
+        assert_eq!(pinned.name, "orders");
+        assert_eq!(pinned.origin, Origin::Pinned);
+    }
+
+    #[test]
+    fn test_candidates_parse_npm_go_and_python_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "package.json",
+            "{\n  \"version\": \"1.0.0\",\n  \"name\":\n    \"@acme/checkout-web\"\n}",
+        );
+        write(
+            dir.path(),
+            "go.mod",
+            "module github.com/acme/ledger/v2\n\ngo 1.22\n",
+        );
+        write(
+            dir.path(),
+            "pyproject.toml",
+            "[tool.poetry]\nname = \"risk-engine\"\n",
+        );
+
+        let candidates = ServiceDetector::from_directory(dir.path().to_path_buf()).candidates();
+        let found: Vec<(&str, &str)> = candidates
+            .iter()
+            .take(3)
+            .map(|c| (c.name.as_str(), c.source.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("checkout-web", "package.json"),
+                ("ledger", "go.mod"),
+                ("risk-engine", "pyproject.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_candidates_parse_maven_gradle_and_csharp_projects() {
+        let repo = tempfile::tempdir().unwrap();
+        fs::create_dir(repo.path().join(".git")).unwrap();
+        write(
+            repo.path(),
+            "pom.xml",
+            "<project>\n  <parent>\n    <artifactId>spring-boot-starter-parent</artifactId>\n  </parent>\n  <artifactId>orders</artifactId>\n  <modules>\n    <module>orders-service</module>\n  </modules>\n</project>",
+        );
+        write(
+            repo.path(),
+            "orders-service/pom.xml",
+            "<project><artifactId>orders-service</artifactId><dependencies><dependency><artifactId>lombok</artifactId></dependency></dependencies></project>",
+        );
+        let detector = ServiceDetector::from_directory(repo.path().join("orders-service"));
+        assert_eq!(names(&detector)[..2], ["orders-service", "orders"]);
+
+        let gradle = tempfile::tempdir().unwrap();
+        write(
+            gradle.path(),
+            "settings.gradle.kts",
+            "rootProject.name = \"inventory\"\ninclude(\":inventory-api\", \":tools:importer\")\n",
+        );
+        write(gradle.path(), "tools/importer/build.gradle.kts", "");
+        let detector = ServiceDetector::from_directory(gradle.path().to_path_buf());
+        let candidates = names(&detector);
+        assert_eq!(candidates[0], "inventory");
+        assert!(candidates.contains(&"importer".to_string()));
+
+        let dotnet = tempfile::tempdir().unwrap();
+        write(
+            dotnet.path(),
+            "Billing.sln",
+            "Project(\"{FAE04EC0}\") = \"Billing.Api\", \"src\\\\Billing.Api\\\\Billing.Api.csproj\", \"{1}\"\nEndProject\n",
+        );
+        write(
+            dotnet.path(),
+            "src/Billing.Api/Billing.Api.csproj",
+            "<Project Sdk=\"Microsoft.NET.Sdk.Web\"><PropertyGroup><AssemblyName>billing-api</AssemblyName></PropertyGroup></Project>",
+        );
+        let detector = ServiceDetector::from_directory(dotnet.path().join("src/Billing.Api"));
+        assert_eq!(names(&detector)[0], "billing-api");
+    }
 }
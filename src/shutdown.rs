@@ -0,0 +1,185 @@
+//! Graceful shutdown on SIGINT and SIGTERM
+//!
+//! The first signal cancels the running command: work waiting on an AI
+//! provider or a publish stops, its generation history is recorded as
+//! cancelled, interrupted jobs stay resumable and servers stop accepting
+//! requests. A second signal, or work that does not stop within
+//! [`GRACE_PERIOD`], exits immediately.
+
+use crate::error::{KtmeError, Result};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// How long cancelled work has to record its state before the process exits
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Exit code after a cancellation, as shells report for SIGINT
+pub const EXIT_CODE: i32 = 130;
+
+/// Shutdown state shared by the work of a process
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Shutdown {
+    /// Cancel the work watching this state
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Completes once shutdown is requested
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.requested() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run `work` unless shutdown is requested first; `what` names the
+    /// work in the error
+    pub async fn or_cancelled<T>(
+        &self,
+        what: &str,
+        work: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        tokio::select! {
+            result = work => result,
+            _ = self.cancelled() => Err(cancelled_error(what)),
+        }
+    }
+}
+
+/// Shutdown state of this process, requested by signals
+fn process() -> &'static Shutdown {
+    static PROCESS: OnceLock<Shutdown> = OnceLock::new();
+    PROCESS.get_or_init(Shutdown::default)
+}
+
+/// Listen for SIGINT and SIGTERM for the rest of the process
+pub fn install() {
+    tokio::spawn(async {
+        if signal().await.is_err() {
+            return;
+        }
+        tracing::warn!("Shutdown requested, cancelling the running work");
+        request();
+
+        tokio::select! {
+            _ = signal() => tracing::warn!("Second signal, exiting immediately"),
+            _ = tokio::time::sleep(GRACE_PERIOD * 2) => {
+                tracing::warn!("Work did not stop in time, exiting")
+            }
+        }
+        std::process::exit(EXIT_CODE);
+    });
+}
+
+#[cfg(unix)]
+async fn signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+/// Cancel the running work, as a signal does
+pub fn request() {
+    process().request();
+}
+
+/// Whether shutdown was requested
+pub fn requested() -> bool {
+    process().requested()
+}
+
+/// Completes once shutdown is requested
+pub async fn cancelled() {
+    process().cancelled().await;
+}
+
+fn cancelled_error(what: &str) -> KtmeError {
+    KtmeError::Cancelled(format!("{} was interrupted by a shutdown signal", what))
+}
+
+/// An error when shutdown was requested, so that no new `what` starts
+pub fn check(what: &str) -> Result<()> {
+    match requested() {
+        true => Err(cancelled_error(what)),
+        false => Ok(()),
+    }
+}
+
+/// Run `work` unless shutdown is requested first; `what` names the work in
+/// the error
+pub async fn or_cancelled<T>(what: &str, work: impl Future<Output = Result<T>>) -> Result<T> {
+    process().or_cancelled(what, work).await
+}
+
+/// Run a whole command. After a shutdown request the command gets
+/// [`GRACE_PERIOD`] to notice the cancellation and record its state before
+/// it is dropped.
+pub async fn run_command<T>(work: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::pin!(work);
+    tokio::select! {
+        result = &mut work => return result,
+        _ = cancelled() => {}
+    }
+    match tokio::time::timeout(GRACE_PERIOD, &mut work).await {
+        Ok(result) => result,
+        Err(_) => Err(cancelled_error("The command")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_pending_work() {
+        let shutdown = std::sync::Arc::new(Shutdown::default());
+        let finished = shutdown.or_cancelled("Quick work", async { Ok(1) }).await;
+        assert_eq!(finished.unwrap(), 1);
+
+        let watching = shutdown.clone();
+        let waiting = tokio::spawn(async move {
+            watching
+                .or_cancelled("Publishing", async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(())
+                })
+                .await
+        });
+        tokio::task::yield_now().await;
+        shutdown.request();
+
+        let error = waiting.await.unwrap().unwrap_err();
+        assert!(matches!(error, KtmeError::Cancelled(_)));
+        assert_eq!(error.class().exit_code(), EXIT_CODE);
+
+        // Work started after the request is cancelled at once
+        let late = shutdown.or_cancelled("Late work", std::future::pending::<Result<()>>());
+        assert!(late.await.is_err());
+        assert!(!requested());
+    }
+}
@@ -0,0 +1,1294 @@
+use crate::config::StorageConfig;
+use crate::error::{KtmeError, Result};
+use crate::storage::database::{Database, DatabaseStats};
+use crate::storage::models::{
+    DiffCache, DocumentMapping, Feature, FeatureRelation, FeatureType, FeatureUsage,
+    GenerationLock, GenerationRecord, Job, PendingDoc, ProviderConfig, RelationType,
+    SearchContentType, SearchQuery, SearchResult, Service, ServiceDetection, ServiceSettings,
+    ToolCall, Translation,
+};
+use crate::storage::repository::{
+    DiffCacheRepository, DocumentMappingRepository, FeatureRelationRepository, FeatureRepository,
+    GenerationHistoryRepository, GenerationLockRepository, JobRepository, McpAuditRepository,
+    PendingDocRepository, ProviderConfigRepository, ServiceDetectionRepository, ServiceRepository,
+    TranslationRepository,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Storage backend abstraction shared by the SQLite and Postgres implementations.
+///
+/// Covers everything ktme keeps per workspace: services, document mappings,
+/// features and their relations, generation history, the review queue,
+/// resumable jobs, service detection, translations, the diff cache,
+/// document provider configurations, the MCP audit log and generation locks.
+pub trait Storage: Send + Sync {
+    /// Backend name ("sqlite", "postgres")
+    fn backend_name(&self) -> &'static str;
+
+    /// Check that the backend is reachable
+    fn health_check(&self) -> Result<bool>;
+
+    /// Row counts for the main tables
+    fn stats(&self) -> Result<DatabaseStats>;
+
+    // Services
+    fn create_service(
+        &self,
+        name: &str,
+        path: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<Service>;
+    fn get_service_by_name(&self, name: &str) -> Result<Option<Service>>;
+    fn list_services(&self) -> Result<Vec<Service>>;
+    fn delete_service(&self, name: &str) -> Result<bool>;
+    /// Rename a service, keeping its mappings, features, history and queued
+    /// documentation; false when there is no `old` service
+    fn rename_service(&self, old: &str, new: &str) -> Result<bool>;
+    fn service_settings(&self, service_id: i64) -> Result<ServiceSettings>;
+    fn set_service_settings(&self, service_id: i64, settings: &ServiceSettings) -> Result<()>;
+
+    // Document mappings
+    fn add_mapping(
+        &self,
+        service_id: i64,
+        provider: &str,
+        location: &str,
+        title: Option<&str>,
+        section: Option<&str>,
+        is_primary: bool,
+    ) -> Result<DocumentMapping>;
+    fn mappings_for_service(&self, service_id: i64) -> Result<Vec<DocumentMapping>>;
+    fn delete_mapping(&self, id: i64) -> Result<bool>;
+
+    // Features
+    #[allow(clippy::too_many_arguments)]
+    fn create_feature(
+        &self,
+        id: &str,
+        service_id: i64,
+        name: &str,
+        description: Option<&str>,
+        feature_type: FeatureType,
+        tags: Vec<String>,
+        metadata: serde_json::Value,
+    ) -> Result<Feature>;
+    fn get_feature(&self, id: &str) -> Result<Option<Feature>>;
+    fn features_for_service(&self, service_id: i64) -> Result<Vec<Feature>>;
+    fn search_features(&self, query: &SearchQuery) -> Result<Vec<SearchResult>>;
+    fn update_feature_relevance(&self, feature_id: &str, score: f64) -> Result<()>;
+    fn delete_feature(&self, id: &str) -> Result<bool>;
+    /// Make `content` searchable for a feature, replacing its previous
+    /// content of the same type
+    fn index_feature_content(
+        &self,
+        feature_id: &str,
+        content_type: SearchContentType,
+        content: &str,
+    ) -> Result<()>;
+    /// Count a retrieval of each feature by a search or an MCP tool
+    fn record_feature_usage(&self, feature_ids: &[&str]) -> Result<()>;
+    /// Usage of every feature since its relevance was last ranked
+    fn feature_usage(&self) -> Result<Vec<FeatureUsage>>;
+    /// Store a ranked relevance score and clear the `counted_uses`
+    /// retrievals it accounts for
+    fn apply_feature_ranking(
+        &self,
+        feature_id: &str,
+        score: f64,
+        counted_uses: i64,
+        ranked_at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    // Feature relations
+    /// Record that `parent_id` relates to `child_id`, e.g. depends on it
+    fn create_relation(
+        &self,
+        id: &str,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: RelationType,
+        strength: f64,
+        metadata: serde_json::Value,
+    ) -> Result<FeatureRelation>;
+    /// Relations in either direction of a feature, strongest first
+    fn relations_of(&self, feature_id: &str) -> Result<Vec<FeatureRelation>>;
+    /// Remove the relations from `parent_id` to `child_id`
+    fn delete_relation(&self, parent_id: &str, child_id: &str) -> Result<bool>;
+
+    // Generation history
+    #[allow(clippy::too_many_arguments)]
+    fn record_generation(
+        &self,
+        service_id: Option<i64>,
+        provider: &str,
+        document_id: Option<&str>,
+        document_url: Option<&str>,
+        action: &str,
+        source_type: Option<&str>,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+        status: &str,
+        error_message: Option<&str>,
+        model: Option<&str>,
+        idempotency_key: Option<&str>,
+    ) -> Result<i64>;
+    fn recent_generations(&self, limit: usize) -> Result<Vec<GenerationRecord>>;
+    /// Latest successful generation that wrote `source_identifier`, or
+    /// content hashing to `content_hash`, to the document at `location`
+    fn find_documented(
+        &self,
+        location: &str,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+    ) -> Result<Option<GenerationRecord>>;
+    /// Latest successful generation with `idempotency_key` that wrote to the
+    /// document at `location`
+    fn find_by_key(
+        &self,
+        location: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<GenerationRecord>>;
+
+    // Review queue
+    fn queue_doc(
+        &self,
+        service: &str,
+        doc_type: &str,
+        source_identifier: Option<&str>,
+        target: Option<&str>,
+        content: &str,
+    ) -> Result<i64>;
+    fn get_pending_doc(&self, id: i64) -> Result<Option<PendingDoc>>;
+    /// Queued documentation, oldest first; every status when `status` is `None`
+    fn list_pending_docs(&self, status: Option<&str>) -> Result<Vec<PendingDoc>>;
+    /// Move a pending item to `status`; false when it is not pending
+    fn review_pending_doc(
+        &self,
+        id: i64,
+        status: &str,
+        reviewer: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<bool>;
+
+    // Resumable jobs
+    /// Record a running job with `steps`, all pending
+    fn create_job(
+        &self,
+        kind: &str,
+        description: &str,
+        params: &str,
+        steps: &[String],
+    ) -> Result<i64>;
+    fn get_job(&self, id: i64) -> Result<Option<Job>>;
+    /// Jobs, newest first; every status when `status` is `None`
+    fn list_jobs(&self, status: Option<&str>) -> Result<Vec<Job>>;
+    /// Record the outcome of step `position` of job `id`
+    fn update_job_step(
+        &self,
+        id: i64,
+        position: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()>;
+    fn update_job_status(&self, id: i64, status: &str, error: Option<&str>) -> Result<()>;
+
+    // Service detection
+    /// Record `service_name` for `directory`, replacing the previous
+    /// detection or pin
+    fn save_service_detection(
+        &self,
+        directory: &str,
+        service_name: &str,
+        pinned: bool,
+    ) -> Result<()>;
+    fn get_service_detection(
+        &self,
+        directory: &str,
+        pinned: bool,
+    ) -> Result<Option<ServiceDetection>>;
+    fn remove_service_detection(&self, directory: &str, pinned: bool) -> Result<bool>;
+    /// Forget the detections (not the pins) of the directories in `directory`
+    fn clear_service_detections(&self, directory: &str) -> Result<u64>;
+
+    // Translations
+    /// Pair the document at `target` with the `source` document of
+    /// `service`; pairing a target again replaces its source and language
+    /// and forgets its translated sections
+    fn add_translation(
+        &self,
+        service: &str,
+        source: &str,
+        target_type: &str,
+        target: &str,
+        language: &str,
+    ) -> Result<()>;
+    /// Translations of `service`, only those of `source` when given
+    fn list_translations(&self, service: &str, source: Option<&str>) -> Result<Vec<Translation>>;
+    /// Record the translated sections of translation `id` as synced now
+    fn save_translation_sections(&self, id: i64, sections: &BTreeMap<String, String>)
+        -> Result<()>;
+    fn remove_translations(&self, service: &str) -> Result<u64>;
+
+    // Diff cache
+    /// Cache `diff_json` under `source_type`, `source_identifier` and
+    /// `repository_path` until `expires_at`, replacing the previous entry
+    fn cache_diff(
+        &self,
+        source_type: &str,
+        source_identifier: &str,
+        repository_path: Option<&str>,
+        diff_json: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+    /// Cached entry that has not expired
+    fn get_cached_diff(
+        &self,
+        source_type: &str,
+        source_identifier: &str,
+        repository_path: Option<&str>,
+    ) -> Result<Option<DiffCache>>;
+    /// Drop the expired entries; returns how many were dropped
+    fn clear_expired_diffs(&self) -> Result<u64>;
+
+    // Document providers
+    /// Save the configuration of a `provider_type` provider, replacing the
+    /// previous one
+    fn save_provider_config(
+        &self,
+        provider_type: &str,
+        config: &serde_json::Value,
+        is_default: bool,
+    ) -> Result<()>;
+    fn get_provider_config(&self, provider_type: &str) -> Result<Option<ProviderConfig>>;
+    /// Saved providers, the default first
+    fn list_provider_configs(&self) -> Result<Vec<ProviderConfig>>;
+    /// Make `provider_type` the only default provider
+    fn set_default_provider(&self, provider_type: &str) -> Result<()>;
+    fn delete_provider_config(&self, provider_type: &str) -> Result<bool>;
+
+    // MCP audit log
+    /// Record a tool call of the MCP server
+    fn record_tool_call(
+        &self,
+        tool: &str,
+        arguments: &str,
+        transport: &str,
+        duration_ms: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()>;
+    /// Tool calls made since `since`, newest first
+    fn tool_calls_since(&self, since: DateTime<Utc>) -> Result<Vec<ToolCall>>;
+
+    // Generation locks
+    /// Take the lock of `resource` for `owner` until `expires_at`, unless
+    /// another owner holds it and it has not expired. Returns whether the
+    /// lock was taken; taking it again as its owner extends it.
+    fn try_acquire_lock(
+        &self,
+        resource: &str,
+        owner: &str,
+        purpose: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool>;
+    /// Current holder of the lock of `resource`, expired or not
+    fn lock_holder(&self, resource: &str) -> Result<Option<GenerationLock>>;
+    /// Release the lock of `resource` if `owner` still holds it
+    fn release_lock(&self, resource: &str, owner: &str) -> Result<bool>;
+}
+
+/// Open the storage backend selected by `[storage] backend`
+pub fn open_storage(config: &StorageConfig) -> Result<Box<dyn Storage>> {
+    match config.backend.to_lowercase().as_str() {
+        "sqlite" => {
+            let db = Database::open(config)?;
+            Ok(Box::new(SqliteStorage::new(db)))
+        }
+        "postgres" | "postgresql" => open_postgres(&postgres_url(config)?, &config.workspace),
+        other => Err(KtmeError::Config(format!(
+            "Unsupported storage backend: {} (expected 'sqlite' or 'postgres')",
+            other
+        ))),
+    }
+}
+
+/// Open the local SQLite database directly.
+///
+/// The knowledge tree and feature context queries are not part of `Storage`
+/// yet, so callers fail clearly instead of reading an empty local database
+/// while the configured backend lives elsewhere.
+pub fn open_sqlite(config: &StorageConfig) -> Result<Database> {
+    if !config.backend.eq_ignore_ascii_case("sqlite") {
+        return Err(KtmeError::UnsupportedOperation(format!(
+            "The knowledge tree is only available with the sqlite backend (configured: {})",
+            config.backend
+        )));
+    }
+
+    Database::open(config)
+}
+
+/// Connection URL for the postgres backend, from config or KTME_DATABASE_URL
+fn postgres_url(config: &StorageConfig) -> Result<String> {
+    config
+        .database_url
+        .clone()
+        .or_else(|| std::env::var("KTME_DATABASE_URL").ok())
+        .ok_or_else(|| {
+            KtmeError::Config(
+                "Postgres backend requires storage.database_url or KTME_DATABASE_URL".to_string(),
+            )
+        })
+}
+
+#[cfg(feature = "postgres")]
+fn open_postgres(url: &str, workspace: &str) -> Result<Box<dyn Storage>> {
+    let storage = crate::storage::postgres::PostgresStorage::connect(url, workspace)?;
+    Ok(Box::new(storage))
+}
+
+#[cfg(not(feature = "postgres"))]
+fn open_postgres(_url: &str, _workspace: &str) -> Result<Box<dyn Storage>> {
+    Err(KtmeError::Config(
+        "ktme was built without Postgres support; rebuild with `--features postgres`".to_string(),
+    ))
+}
+
+/// Add the features connected to `results` through feature relations, in any
+/// service and up to `query.depth` hops away (1 by default), when the query
+/// asks for related features. A related feature scores the relevance of the feature it was
+/// reached from, weighted by the strength of the relation and halved at each
+/// hop, and says how it is connected in `related_via`.
+pub(crate) fn with_related(
+    storage: &dyn Storage,
+    query: &SearchQuery,
+    mut results: Vec<SearchResult>,
+) -> Result<Vec<SearchResult>> {
+    if !query.include_related || results.is_empty() {
+        return Ok(results);
+    }
+
+    let services: HashMap<i64, String> = storage
+        .list_services()?
+        .into_iter()
+        .map(|service| (service.id, service.name))
+        .collect();
+    let mut seen: HashSet<String> = results.iter().map(|r| r.feature_id.clone()).collect();
+    let mut frontier: Vec<(String, String, f64)> = results
+        .iter()
+        .map(|r| {
+            (
+                r.feature_id.clone(),
+                r.feature_name.clone(),
+                r.relevance_score,
+            )
+        })
+        .collect();
+
+    for _ in 0..query.depth.unwrap_or(1) {
+        let mut next = Vec::new();
+        for (feature_id, feature_name, score) in &frontier {
+            for relation in storage.relations_of(feature_id)? {
+                let outgoing = relation.parent_feature_id == *feature_id;
+                let other_id = if outgoing {
+                    &relation.child_feature_id
+                } else {
+                    &relation.parent_feature_id
+                };
+                if seen.contains(other_id) {
+                    continue;
+                }
+                let Some(other) = storage.get_feature(other_id)? else {
+                    continue;
+                };
+                seen.insert(other.id.clone());
+                let relevance = score * relation.strength / 2.0;
+                next.push((other.id.clone(), other.name.clone(), relevance));
+
+                if query
+                    .feature_types
+                    .as_ref()
+                    .is_some_and(|types| !types.is_empty() && !types.contains(&other.feature_type))
+                {
+                    continue;
+                }
+                let related_via = if outgoing {
+                    format!("{} {} {}", feature_name, relation.relation_type, other.name)
+                } else {
+                    format!("{} {} {}", other.name, relation.relation_type, feature_name)
+                };
+                results.push(SearchResult {
+                    feature_id: other.id,
+                    service_name: services.get(&other.service_id).cloned().unwrap_or_default(),
+                    content: other
+                        .description
+                        .clone()
+                        .unwrap_or_else(|| other.name.clone()),
+                    feature_name: other.name,
+                    feature_type: other.feature_type,
+                    description: other.description,
+                    relevance_score: relevance,
+                    content_type: SearchContentType::FeatureName,
+                    path: None,
+                    tags: other.tags,
+                    related_via: Some(related_via),
+                });
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    Ok(results)
+}
+
+// ============================================================================
+// SQLite Storage
+// ============================================================================
+
+/// SQLite implementation backed by the existing repositories
+pub struct SqliteStorage {
+    db: Database,
+}
+
+impl SqliteStorage {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn backend_name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn health_check(&self) -> Result<bool> {
+        self.db.health_check()
+    }
+
+    fn stats(&self) -> Result<DatabaseStats> {
+        self.db.stats()
+    }
+
+    fn create_service(
+        &self,
+        name: &str,
+        path: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<Service> {
+        ServiceRepository::new(self.db.clone()).create(name, path, description)
+    }
+
+    fn get_service_by_name(&self, name: &str) -> Result<Option<Service>> {
+        ServiceRepository::new(self.db.clone()).get_by_name(name)
+    }
+
+    fn list_services(&self) -> Result<Vec<Service>> {
+        ServiceRepository::new(self.db.clone()).list()
+    }
+
+    fn delete_service(&self, name: &str) -> Result<bool> {
+        ServiceRepository::new(self.db.clone()).delete(name)
+    }
+
+    fn rename_service(&self, old: &str, new: &str) -> Result<bool> {
+        ServiceRepository::new(self.db.clone()).rename(old, new)
+    }
+
+    fn service_settings(&self, service_id: i64) -> Result<ServiceSettings> {
+        ServiceRepository::new(self.db.clone()).get_settings(service_id)
+    }
+
+    fn set_service_settings(&self, service_id: i64, settings: &ServiceSettings) -> Result<()> {
+        ServiceRepository::new(self.db.clone()).set_settings(service_id, settings)
+    }
+
+    fn add_mapping(
+        &self,
+        service_id: i64,
+        provider: &str,
+        location: &str,
+        title: Option<&str>,
+        section: Option<&str>,
+        is_primary: bool,
+    ) -> Result<DocumentMapping> {
+        DocumentMappingRepository::new(self.db.clone())
+            .add(service_id, provider, location, title, section, is_primary)
+    }
+
+    fn mappings_for_service(&self, service_id: i64) -> Result<Vec<DocumentMapping>> {
+        DocumentMappingRepository::new(self.db.clone()).get_for_service(service_id)
+    }
+
+    fn delete_mapping(&self, id: i64) -> Result<bool> {
+        DocumentMappingRepository::new(self.db.clone()).delete(id)
+    }
+
+    fn create_feature(
+        &self,
+        id: &str,
+        service_id: i64,
+        name: &str,
+        description: Option<&str>,
+        feature_type: FeatureType,
+        tags: Vec<String>,
+        metadata: serde_json::Value,
+    ) -> Result<Feature> {
+        FeatureRepository::new(self.db.clone()).create(
+            id,
+            service_id,
+            name,
+            description,
+            feature_type,
+            tags,
+            metadata,
+        )
+    }
+
+    fn get_feature(&self, id: &str) -> Result<Option<Feature>> {
+        FeatureRepository::new(self.db.clone()).get_by_id(id)
+    }
+
+    fn features_for_service(&self, service_id: i64) -> Result<Vec<Feature>> {
+        FeatureRepository::new(self.db.clone()).list_by_service(service_id)
+    }
+
+    fn search_features(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        let results = FeatureRepository::new(self.db.clone()).search(query)?;
+        with_related(self, query, results)
+    }
+
+    fn update_feature_relevance(&self, feature_id: &str, score: f64) -> Result<()> {
+        FeatureRepository::new(self.db.clone()).update_relevance_score(feature_id, score)
+    }
+
+    fn delete_feature(&self, id: &str) -> Result<bool> {
+        FeatureRepository::new(self.db.clone()).delete(id)
+    }
+
+    fn index_feature_content(
+        &self,
+        feature_id: &str,
+        content_type: SearchContentType,
+        content: &str,
+    ) -> Result<()> {
+        FeatureRepository::new(self.db.clone()).upsert_search_index(
+            feature_id,
+            content_type,
+            content,
+        )
+    }
+
+    fn record_feature_usage(&self, feature_ids: &[&str]) -> Result<()> {
+        FeatureRepository::new(self.db.clone()).record_usage(feature_ids)
+    }
+
+    fn feature_usage(&self) -> Result<Vec<FeatureUsage>> {
+        FeatureRepository::new(self.db.clone()).usage()
+    }
+
+    fn apply_feature_ranking(
+        &self,
+        feature_id: &str,
+        score: f64,
+        counted_uses: i64,
+        ranked_at: DateTime<Utc>,
+    ) -> Result<()> {
+        FeatureRepository::new(self.db.clone()).apply_ranking(
+            feature_id,
+            score,
+            counted_uses,
+            ranked_at,
+        )
+    }
+
+    fn create_relation(
+        &self,
+        id: &str,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: RelationType,
+        strength: f64,
+        metadata: serde_json::Value,
+    ) -> Result<FeatureRelation> {
+        FeatureRelationRepository::new(self.db.clone()).create(
+            id,
+            parent_id,
+            child_id,
+            relation_type,
+            strength,
+            metadata,
+        )
+    }
+
+    fn relations_of(&self, feature_id: &str) -> Result<Vec<FeatureRelation>> {
+        let repo = FeatureRelationRepository::new(self.db.clone());
+        let mut relations = repo.list_for_parent(feature_id)?;
+        relations.extend(repo.list_for_child(feature_id)?);
+        relations.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+        Ok(relations)
+    }
+
+    fn delete_relation(&self, parent_id: &str, child_id: &str) -> Result<bool> {
+        FeatureRelationRepository::new(self.db.clone()).delete_between(parent_id, child_id)
+    }
+
+    fn record_generation(
+        &self,
+        service_id: Option<i64>,
+        provider: &str,
+        document_id: Option<&str>,
+        document_url: Option<&str>,
+        action: &str,
+        source_type: Option<&str>,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+        status: &str,
+        error_message: Option<&str>,
+        model: Option<&str>,
+        idempotency_key: Option<&str>,
+    ) -> Result<i64> {
+        GenerationHistoryRepository::new(self.db.clone()).record(
+            service_id,
+            provider,
+            document_id,
+            document_url,
+            action,
+            source_type,
+            source_identifier,
+            content_hash,
+            status,
+            error_message,
+            model,
+            idempotency_key,
+        )
+    }
+
+    fn recent_generations(&self, limit: usize) -> Result<Vec<GenerationRecord>> {
+        GenerationHistoryRepository::new(self.db.clone()).get_recent(limit)
+    }
+
+    fn find_documented(
+        &self,
+        location: &str,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+    ) -> Result<Option<GenerationRecord>> {
+        GenerationHistoryRepository::new(self.db.clone()).find_documented(
+            location,
+            source_identifier,
+            content_hash,
+        )
+    }
+
+    fn find_by_key(
+        &self,
+        location: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<GenerationRecord>> {
+        GenerationHistoryRepository::new(self.db.clone()).find_by_key(location, idempotency_key)
+    }
+
+    fn queue_doc(
+        &self,
+        service: &str,
+        doc_type: &str,
+        source_identifier: Option<&str>,
+        target: Option<&str>,
+        content: &str,
+    ) -> Result<i64> {
+        PendingDocRepository::new(self.db.clone()).create(
+            service,
+            doc_type,
+            source_identifier,
+            target,
+            content,
+        )
+    }
+
+    fn get_pending_doc(&self, id: i64) -> Result<Option<PendingDoc>> {
+        PendingDocRepository::new(self.db.clone()).get(id)
+    }
+
+    fn list_pending_docs(&self, status: Option<&str>) -> Result<Vec<PendingDoc>> {
+        PendingDocRepository::new(self.db.clone()).list(status)
+    }
+
+    fn review_pending_doc(
+        &self,
+        id: i64,
+        status: &str,
+        reviewer: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<bool> {
+        PendingDocRepository::new(self.db.clone()).review(id, status, reviewer, note)
+    }
+
+    fn create_job(
+        &self,
+        kind: &str,
+        description: &str,
+        params: &str,
+        steps: &[String],
+    ) -> Result<i64> {
+        JobRepository::new(self.db.clone()).create(kind, description, params, steps)
+    }
+
+    fn get_job(&self, id: i64) -> Result<Option<Job>> {
+        JobRepository::new(self.db.clone()).get(id)
+    }
+
+    fn list_jobs(&self, status: Option<&str>) -> Result<Vec<Job>> {
+        JobRepository::new(self.db.clone()).list(status)
+    }
+
+    fn update_job_step(
+        &self,
+        id: i64,
+        position: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        JobRepository::new(self.db.clone()).update_step(id, position, status, error)
+    }
+
+    fn update_job_status(&self, id: i64, status: &str, error: Option<&str>) -> Result<()> {
+        JobRepository::new(self.db.clone()).update_status(id, status, error)
+    }
+
+    fn save_service_detection(
+        &self,
+        directory: &str,
+        service_name: &str,
+        pinned: bool,
+    ) -> Result<()> {
+        ServiceDetectionRepository::new(self.db.clone()).save(directory, service_name, pinned)
+    }
+
+    fn get_service_detection(
+        &self,
+        directory: &str,
+        pinned: bool,
+    ) -> Result<Option<ServiceDetection>> {
+        ServiceDetectionRepository::new(self.db.clone()).get(directory, pinned)
+    }
+
+    fn remove_service_detection(&self, directory: &str, pinned: bool) -> Result<bool> {
+        ServiceDetectionRepository::new(self.db.clone()).remove(directory, pinned)
+    }
+
+    fn clear_service_detections(&self, directory: &str) -> Result<u64> {
+        ServiceDetectionRepository::new(self.db.clone()).clear_detected(directory)
+    }
+
+    fn add_translation(
+        &self,
+        service: &str,
+        source: &str,
+        target_type: &str,
+        target: &str,
+        language: &str,
+    ) -> Result<()> {
+        TranslationRepository::new(self.db.clone()).add(
+            service,
+            source,
+            target_type,
+            target,
+            language,
+        )
+    }
+
+    fn list_translations(&self, service: &str, source: Option<&str>) -> Result<Vec<Translation>> {
+        TranslationRepository::new(self.db.clone()).list(service, source)
+    }
+
+    fn save_translation_sections(
+        &self,
+        id: i64,
+        sections: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        TranslationRepository::new(self.db.clone()).save_sections(id, sections)
+    }
+
+    fn remove_translations(&self, service: &str) -> Result<u64> {
+        TranslationRepository::new(self.db.clone()).remove_service(service)
+    }
+
+    fn cache_diff(
+        &self,
+        source_type: &str,
+        source_identifier: &str,
+        repository_path: Option<&str>,
+        diff_json: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        DiffCacheRepository::new(self.db.clone()).set(
+            source_type,
+            source_identifier,
+            repository_path,
+            diff_json,
+            expires_at,
+        )
+    }
+
+    fn get_cached_diff(
+        &self,
+        source_type: &str,
+        source_identifier: &str,
+        repository_path: Option<&str>,
+    ) -> Result<Option<DiffCache>> {
+        DiffCacheRepository::new(self.db.clone()).get(
+            source_type,
+            source_identifier,
+            repository_path,
+        )
+    }
+
+    fn clear_expired_diffs(&self) -> Result<u64> {
+        DiffCacheRepository::new(self.db.clone()).clear_expired()
+    }
+
+    fn save_provider_config(
+        &self,
+        provider_type: &str,
+        config: &serde_json::Value,
+        is_default: bool,
+    ) -> Result<()> {
+        ProviderConfigRepository::new(self.db.clone()).save(provider_type, config, is_default)
+    }
+
+    fn get_provider_config(&self, provider_type: &str) -> Result<Option<ProviderConfig>> {
+        ProviderConfigRepository::new(self.db.clone()).get(provider_type)
+    }
+
+    fn list_provider_configs(&self) -> Result<Vec<ProviderConfig>> {
+        ProviderConfigRepository::new(self.db.clone()).list()
+    }
+
+    fn set_default_provider(&self, provider_type: &str) -> Result<()> {
+        ProviderConfigRepository::new(self.db.clone()).set_default(provider_type)
+    }
+
+    fn delete_provider_config(&self, provider_type: &str) -> Result<bool> {
+        ProviderConfigRepository::new(self.db.clone()).delete(provider_type)
+    }
+
+    fn record_tool_call(
+        &self,
+        tool: &str,
+        arguments: &str,
+        transport: &str,
+        duration_ms: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        McpAuditRepository::new(self.db.clone()).record(
+            tool,
+            arguments,
+            transport,
+            duration_ms,
+            status,
+            error,
+        )
+    }
+
+    fn tool_calls_since(&self, since: DateTime<Utc>) -> Result<Vec<ToolCall>> {
+        McpAuditRepository::new(self.db.clone()).since(since)
+    }
+
+    fn try_acquire_lock(
+        &self,
+        resource: &str,
+        owner: &str,
+        purpose: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        GenerationLockRepository::new(self.db.clone())
+            .try_acquire(resource, owner, purpose, expires_at)
+    }
+
+    fn lock_holder(&self, resource: &str) -> Result<Option<GenerationLock>> {
+        GenerationLockRepository::new(self.db.clone()).holder(resource)
+    }
+
+    fn release_lock(&self, resource: &str, owner: &str) -> Result<bool> {
+        GenerationLockRepository::new(self.db.clone()).release(resource, owner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exercise services, mappings, features and history against any backend.
+    fn assert_roundtrip(storage: &dyn Storage, service_name: &str) {
+        let service = storage
+            .create_service(service_name, Some("/srv/billing"), None)
+            .unwrap();
+        storage
+            .add_mapping(service.id, "markdown", "docs/billing.md", None, None, true)
+            .unwrap();
+        let feature_id = uuid::Uuid::new_v4().to_string();
+        storage
+            .create_feature(
+                &feature_id,
+                service.id,
+                "Invoices",
+                None,
+                FeatureType::Api,
+                vec!["billing".to_string()],
+                serde_json::json!({}),
+            )
+            .unwrap();
+        let history_id = storage
+            .record_generation(
+                Some(service.id),
+                "markdown",
+                None,
+                None,
+                "create",
+                Some("commit"),
+                Some("HEAD"),
+                None,
+                "success",
+                None,
+                Some("gpt-4o-mini"),
+                None,
+            )
+            .unwrap();
+
+        assert!(storage.service_settings(service.id).unwrap().is_empty());
+        let mut settings = ServiceSettings::default();
+        settings.assign("model=gpt-4o-mini").unwrap();
+        settings.assign("include=src/**").unwrap();
+        storage.set_service_settings(service.id, &settings).unwrap();
+        assert_eq!(storage.service_settings(service.id).unwrap(), settings);
+
+        let mappings = storage.mappings_for_service(service.id).unwrap();
+        assert_eq!(mappings.len(), 1);
+        assert_eq!(mappings[0].location, "docs/billing.md");
+
+        let features = storage.features_for_service(service.id).unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].feature_type, FeatureType::Api);
+        assert_eq!(features[0].tags, vec!["billing"]);
+
+        storage
+            .index_feature_content(
+                &feature_id,
+                SearchContentType::Documentation,
+                "Invoices are emailed monthly",
+            )
+            .unwrap();
+        storage
+            .index_feature_content(
+                &feature_id,
+                SearchContentType::Documentation,
+                "Invoices are emailed weekly",
+            )
+            .unwrap();
+        let found = storage
+            .search_features(&SearchQuery {
+                query: "emailed weekly".to_string(),
+                service_ids: Some(vec![service.id]),
+                feature_types: None,
+                content_types: None,
+                limit: None,
+                offset: None,
+                min_score: None,
+                similarity_threshold: None,
+                include_related: false,
+                depth: None,
+            })
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].content, "Invoices are emailed weekly");
+
+        let history = storage.recent_generations(50).unwrap();
+        let record = history.iter().find(|r| r.id == history_id).unwrap();
+        assert_eq!(record.model.as_deref(), Some("gpt-4o-mini"));
+
+        let changelog = format!("docs/{}/CHANGELOG.md", service_name);
+        let commit = uuid::Uuid::new_v4().to_string();
+        for status in ["failed", "success"] {
+            storage
+                .record_generation(
+                    Some(service.id),
+                    "markdown",
+                    None,
+                    Some(&changelog),
+                    "update",
+                    Some("commit"),
+                    Some(&commit),
+                    Some("hash-of-entries"),
+                    status,
+                    None,
+                    None,
+                    Some("key-of-entries"),
+                )
+                .unwrap();
+        }
+        let documented = storage
+            .find_documented(&changelog, Some(&commit), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(documented.status, "success");
+        assert!(storage
+            .find_documented(&changelog, None, Some("hash-of-entries"))
+            .unwrap()
+            .is_some());
+        assert!(storage
+            .find_documented(&changelog, Some("another-commit"), None)
+            .unwrap()
+            .is_none());
+        assert!(storage
+            .find_documented("docs/billing.md", Some(&commit), None)
+            .unwrap()
+            .is_none());
+        let keyed = storage
+            .find_by_key(&changelog, "key-of-entries")
+            .unwrap()
+            .unwrap();
+        assert_eq!(keyed.status, "success");
+        assert_eq!(keyed.idempotency_key.as_deref(), Some("key-of-entries"));
+        assert!(storage
+            .find_by_key(&changelog, "another-key")
+            .unwrap()
+            .is_none());
+
+        let queued = storage
+            .queue_doc(service_name, "api-doc", Some("HEAD"), None, "# Billing")
+            .unwrap();
+        assert_eq!(storage.list_pending_docs(Some("pending")).unwrap().len(), 1);
+        assert!(storage
+            .review_pending_doc(queued, "approved", Some("writer"), None)
+            .unwrap());
+        assert!(!storage
+            .review_pending_doc(queued, "rejected", Some("writer"), None)
+            .unwrap());
+        let reviewed = storage.get_pending_doc(queued).unwrap().unwrap();
+        assert_eq!(reviewed.status, "approved");
+        assert_eq!(reviewed.content, "# Billing");
+        assert!(reviewed.reviewed_at.is_some());
+        assert!(storage
+            .list_pending_docs(Some("pending"))
+            .unwrap()
+            .is_empty());
+
+        let steps = vec!["a1b2c3d".to_string(), "e4f5a6b".to_string()];
+        let job = storage
+            .create_job("backfill", "Backfill billing", "{}", &steps)
+            .unwrap();
+        storage.update_job_step(job, 0, "done", None).unwrap();
+        storage
+            .update_job_step(job, 1, "failed", Some("rate limited"))
+            .unwrap();
+        storage
+            .update_job_status(job, "failed", Some("rate limited"))
+            .unwrap();
+        let failed = storage.get_job(job).unwrap().unwrap();
+        assert_eq!(failed.status, "failed");
+        let remaining: Vec<&str> = failed
+            .remaining_steps()
+            .map(|step| step.name.as_str())
+            .collect();
+        assert_eq!(remaining, ["e4f5a6b"]);
+        assert_eq!(failed.steps[1].error.as_deref(), Some("rate limited"));
+        assert!(storage
+            .list_jobs(Some("failed"))
+            .unwrap()
+            .iter()
+            .any(|listed| listed.id == job));
+
+        let hour_ago = chrono::Utc::now() - chrono::Duration::hours(1);
+        storage
+            .record_tool_call(
+                "ktme_generate_documentation",
+                r#"{"service":"billing"}"#,
+                "stdio",
+                42,
+                "error",
+                Some("No changes"),
+            )
+            .unwrap();
+        let calls = storage.tool_calls_since(hour_ago).unwrap();
+        let call = calls
+            .iter()
+            .find(|call| call.tool == "ktme_generate_documentation")
+            .unwrap();
+        assert_eq!(call.duration_ms, 42);
+        assert_eq!(call.error.as_deref(), Some("No changes"));
+        assert!(storage
+            .tool_calls_since(chrono::Utc::now() + chrono::Duration::hours(1))
+            .unwrap()
+            .is_empty());
+
+        // Service detection
+        let directory = format!("/src/{}", service_name);
+        storage
+            .save_service_detection(&format!("{}/api", directory), "api", false)
+            .unwrap();
+        storage
+            .save_service_detection(&directory, service_name, true)
+            .unwrap();
+        let pin = storage.get_service_detection(&directory, true).unwrap();
+        assert_eq!(pin.unwrap().service_name, service_name);
+        assert_eq!(storage.clear_service_detections(&directory).unwrap(), 1);
+        assert!(storage
+            .get_service_detection(&format!("{}/api", directory), false)
+            .unwrap()
+            .is_none());
+        assert!(storage.remove_service_detection(&directory, true).unwrap());
+
+        // Translations
+        storage
+            .add_translation(
+                service_name,
+                "docs/api.md",
+                "markdown",
+                "docs/ja/api.md",
+                "ja",
+            )
+            .unwrap();
+        let translation = storage
+            .list_translations(service_name, Some("docs/api.md"))
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(translation.language, "ja");
+        assert!(translation.synced_at.is_none());
+        let sections = BTreeMap::from([("hash".to_string(), "翻訳".to_string())]);
+        storage
+            .save_translation_sections(translation.id, &sections)
+            .unwrap();
+        let synced = storage.list_translations(service_name, None).unwrap();
+        assert_eq!(synced[0].sections, sections);
+        assert!(synced[0].synced_at.is_some());
+        assert_eq!(storage.remove_translations(service_name).unwrap(), 1);
+
+        // Diff cache
+        let later = chrono::Utc::now() + chrono::Duration::hours(1);
+        let earlier = chrono::Utc::now() - chrono::Duration::seconds(1);
+        storage
+            .cache_diff("commit", "abc123", Some(service_name), "{}", Some(later))
+            .unwrap();
+        storage
+            .cache_diff("commit", "def456", Some(service_name), "{}", Some(earlier))
+            .unwrap();
+        let cached = storage
+            .get_cached_diff("commit", "abc123", Some(service_name))
+            .unwrap();
+        assert_eq!(cached.unwrap().diff_json, "{}");
+        assert!(storage
+            .get_cached_diff("commit", "def456", Some(service_name))
+            .unwrap()
+            .is_none());
+        assert!(storage.clear_expired_diffs().unwrap() >= 1);
+
+        // Document providers
+        let provider = format!("provider-{}", service_name);
+        storage
+            .save_provider_config(&provider, &serde_json::json!({"base_path": "docs"}), false)
+            .unwrap();
+        let saved = storage.get_provider_config(&provider).unwrap().unwrap();
+        assert_eq!(saved.config["base_path"], "docs");
+        storage.set_default_provider(&provider).unwrap();
+        let providers = storage.list_provider_configs().unwrap();
+        assert_eq!(providers[0].provider_type, provider);
+        assert!(providers[0].is_default);
+        assert!(storage.delete_provider_config(&provider).unwrap());
+        assert!(storage.get_provider_config(&provider).unwrap().is_none());
+
+        // Generation locks
+        let resource = format!("service:{}", service_name);
+        let later = chrono::Utc::now() + chrono::Duration::minutes(5);
+        assert!(storage
+            .try_acquire_lock(&resource, "a", "generate", later)
+            .unwrap());
+        assert!(!storage
+            .try_acquire_lock(&resource, "b", "update", later)
+            .unwrap());
+        assert_eq!(storage.lock_holder(&resource).unwrap().unwrap().owner, "a");
+        assert!(!storage.release_lock(&resource, "b").unwrap());
+        assert!(storage.release_lock(&resource, "a").unwrap());
+        assert!(storage.lock_holder(&resource).unwrap().is_none());
+
+        assert!(storage.delete_service(service_name).unwrap());
+        assert!(storage.get_service_by_name(service_name).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sqlite_storage_roundtrip() {
+        let storage = SqliteStorage::new(Database::in_memory().unwrap());
+        assert_eq!(storage.backend_name(), "sqlite");
+        assert_roundtrip(&storage, "billing");
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    #[ignore = "requires a Postgres database in KTME_TEST_DATABASE_URL"]
+    fn test_postgres_storage_roundtrip() {
+        let url = std::env::var("KTME_TEST_DATABASE_URL")
+            .expect("KTME_TEST_DATABASE_URL must point at a disposable Postgres database");
+
+        let storage = crate::storage::postgres::PostgresStorage::connect(&url, "default")
+            .expect("Failed to connect to Postgres");
+        assert_eq!(storage.backend_name(), "postgres");
+        assert_roundtrip(&storage, &format!("billing-{}", uuid::Uuid::new_v4()));
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    #[test]
+    fn test_open_storage_postgres_requires_feature() {
+        let config = StorageConfig {
+            backend: "postgres".to_string(),
+            database_url: Some("postgres://localhost/ktme".to_string()),
+            ..Default::default()
+        };
+        let err = open_storage(&config)
+            .err()
+            .expect("postgres should be rejected");
+        assert!(err.to_string().contains("built without Postgres support"));
+    }
+
+    #[test]
+    fn test_open_storage_postgres_requires_url() {
+        std::env::remove_var("KTME_DATABASE_URL");
+        let config = StorageConfig {
+            backend: "postgres".to_string(),
+            ..Default::default()
+        };
+        let err = open_storage(&config)
+            .err()
+            .expect("postgres without a URL should be rejected");
+        assert!(matches!(err, KtmeError::Config(_)));
+        assert!(err.to_string().contains("requires storage.database_url"));
+    }
+
+    #[test]
+    fn test_open_sqlite_rejects_other_backends() {
+        let config = StorageConfig {
+            backend: "postgres".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            open_sqlite(&config),
+            Err(KtmeError::UnsupportedOperation(_))
+        ));
+    }
+
+    #[test]
+    fn test_open_storage_rejects_unknown_backend() {
+        let config = StorageConfig {
+            backend: "oracle".to_string(),
+            ..Default::default()
+        };
+        assert!(open_storage(&config).is_err());
+    }
+}
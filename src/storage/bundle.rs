@@ -0,0 +1,671 @@
+//! State bundles for moving a ktme setup between machines
+//!
+//! `ktme export state <file>` writes a zstd-compressed tar holding:
+//!
+//! ```text
+//! manifest.json   format, ktme version and schema version of the database
+//! config.toml     the configuration without secrets or machine-specific paths
+//! ktme.db         a snapshot of the SQLite database of the active workspace
+//! mappings.toml   when mappings are kept in a file
+//! templates/      the files of [documentation] template_directory
+//! prompts/        prompt overrides and prompt packs
+//! ```
+//!
+//! Secrets are left out: config keys such as `api_key` or `jira_api_token`
+//! and the values of `headers` tables, at any depth including the entries of
+//! `[[ai.fallback]]` (unless they only reference an environment variable),
+//! and the same keys of the provider configurations in the database.
+//! `ktme import state` keeps the secrets and storage paths of the
+//! configuration it replaces, refuses bundles of a newer schema and migrates
+//! older ones.
+
+use crate::ai::prompt_pack;
+use crate::config::{Config, StorageConfig};
+use crate::error::{KtmeError, Result};
+use crate::storage::database::Database;
+use crate::storage::mapping::StorageManager;
+use crate::storage::migrations;
+use chrono::{DateTime, Utc};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Version of the bundle layout
+pub const FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const CONFIG: &str = "config.toml";
+const DATABASE: &str = "ktme.db";
+const MAPPINGS: &str = "mappings.toml";
+const TEMPLATES: &str = "templates";
+const PROMPTS: &str = "prompts";
+
+/// Configuration keys that only make sense on the machine they were set on
+const LOCAL_KEYS: &[&str] = &[
+    "storage.database_file",
+    "storage.mappings_file",
+    "storage.workspaces",
+    "documentation.template_directory",
+];
+
+/// Description of a bundle, stored in it as manifest.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub ktme_version: String,
+    /// Latest migration applied to the database
+    pub schema_version: i64,
+    pub created_at: DateTime<Utc>,
+    pub workspace: String,
+    /// Files of the bundle
+    pub files: Vec<String>,
+    /// Secrets left out, e.g. `ai.api_key` or `provider jira: api_token`
+    pub redacted: Vec<String>,
+}
+
+/// Outcome of importing a bundle
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub manifest: Manifest,
+    /// Files written
+    pub written: Vec<PathBuf>,
+    /// Copies of the files that were replaced
+    pub backups: Vec<PathBuf>,
+    /// Secrets left out of the bundle and not found in the replaced config
+    pub missing_secrets: Vec<String>,
+}
+
+/// Write the state selected by `config` to the bundle `file`
+pub fn export(config: &Config, file: &Path) -> Result<Manifest> {
+    if !config.storage.backend.eq_ignore_ascii_case("sqlite") {
+        return Err(KtmeError::UnsupportedOperation(format!(
+            "State bundles hold a SQLite database; back up the {} database with its own tools",
+            config.storage.backend
+        )));
+    }
+
+    let config_path = Config::config_file_path()?;
+    let mut settings = if config_path.exists() {
+        toml::from_str(&fs::read_to_string(&config_path)?)?
+    } else {
+        toml::Value::try_from(config)?
+    };
+    let mut redacted = strip_secrets(&mut settings, "");
+    for key in LOCAL_KEYS {
+        remove_key(&mut settings, key);
+    }
+
+    let staging = staging_dir("export")?;
+    let result = (|| {
+        let snapshot = staging.join(DATABASE);
+        let schema_version = snapshot_database(&config.storage, &snapshot, &mut redacted)?;
+
+        let mut files = vec![CONFIG.to_string(), DATABASE.to_string()];
+        let mappings = StorageManager::mappings_file_for(config)?;
+        let mappings = (!config.storage.uses_database() && mappings.is_file()).then_some(mappings);
+        if mappings.is_some() {
+            files.push(MAPPINGS.to_string());
+        }
+        let templates = config
+            .documentation
+            .template_directory
+            .clone()
+            .filter(|dir| dir.is_dir());
+        let prompts = Some(prompt_pack::overrides_dir()?).filter(|dir| dir.is_dir());
+        for (name, dir) in [(TEMPLATES, &templates), (PROMPTS, &prompts)] {
+            if let Some(dir) = dir {
+                files.extend(
+                    relative_files(dir)?
+                        .iter()
+                        .map(|f| format!("{}/{}", name, f.display())),
+                );
+            }
+        }
+
+        let manifest = Manifest {
+            format: FORMAT,
+            ktme_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version,
+            created_at: Utc::now(),
+            workspace: config.storage.workspace.clone(),
+            files,
+            redacted,
+        };
+
+        let encoder = zstd::Encoder::new(File::create(file)?, 0)?.auto_finish();
+        let mut archive = tar::Builder::new(encoder);
+        append_bytes(
+            &mut archive,
+            MANIFEST,
+            &serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        append_bytes(
+            &mut archive,
+            CONFIG,
+            toml::to_string_pretty(&settings)?.as_bytes(),
+        )?;
+        archive.append_path_with_name(&snapshot, DATABASE)?;
+        if let Some(mappings) = &mappings {
+            archive.append_path_with_name(mappings, MAPPINGS)?;
+        }
+        for (name, dir) in [(TEMPLATES, &templates), (PROMPTS, &prompts)] {
+            if let Some(dir) = dir {
+                archive.append_dir_all(name, dir)?;
+            }
+        }
+        archive.into_inner()?;
+        Ok(manifest)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    if result.is_err() {
+        let _ = fs::remove_file(file);
+    }
+    result
+}
+
+/// Restore the bundle `file`. Existing state is only replaced with `force`,
+/// and kept next to it with a `.bak` suffix.
+pub fn import(file: &Path, force: bool) -> Result<ImportReport> {
+    let staging = staging_dir("import")?;
+    let result = (|| {
+        let decoder = zstd::Decoder::new(File::open(file).map_err(|e| {
+            KtmeError::NotFound(format!("State bundle {}: {}", file.display(), e))
+        })?)?;
+        tar::Archive::new(decoder).unpack(&staging).map_err(|e| {
+            KtmeError::InvalidInput(format!(
+                "{} is not a ktme state bundle: {}",
+                file.display(),
+                e
+            ))
+        })?;
+        restore(&staging, force)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+fn restore(staging: &Path, force: bool) -> Result<ImportReport> {
+    let manifest: Manifest =
+        serde_json::from_slice(&fs::read(staging.join(MANIFEST)).map_err(|_| {
+            KtmeError::InvalidInput("The bundle has no manifest.json".to_string())
+        })?)?;
+    check_versions(&manifest)?;
+
+    // The secrets and paths of this machine are kept
+    let config_path = Config::config_file_path()?;
+    let local: Option<toml::Value> = if config_path.exists() {
+        Some(toml::from_str(&fs::read_to_string(&config_path)?)?)
+    } else {
+        None
+    };
+    let mut settings: toml::Value = toml::from_str(&fs::read_to_string(staging.join(CONFIG))?)?;
+    // Secrets of list entries such as `ai.fallback[0].api_key` and of the
+    // provider configurations stay unset: the local entry at the same place
+    // may be another provider
+    let mut missing_secrets = Vec::new();
+    for key in &manifest.redacted {
+        let local_value = local
+            .as_ref()
+            .filter(|_| !key.contains(' ') && !key.contains('['))
+            .and_then(|local| get_key(local, key));
+        match local_value {
+            Some(value) => set_key(&mut settings, key, value.clone()),
+            None => missing_secrets.push(key.clone()),
+        }
+    }
+    for key in LOCAL_KEYS {
+        if let Some(value) = local.as_ref().and_then(|local| get_key(local, key)) {
+            set_key(&mut settings, key, value.clone());
+        }
+    }
+    let bundled_templates = staging.join(TEMPLATES);
+    let templates_dir = if bundled_templates.is_dir() {
+        let dir = match get_key(&settings, "documentation.template_directory") {
+            Some(toml::Value::String(dir)) => PathBuf::from(dir),
+            _ => {
+                let dir = Config::config_dir()?.join(TEMPLATES);
+                set_key(
+                    &mut settings,
+                    "documentation.template_directory",
+                    toml::Value::String(dir.to_string_lossy().into_owned()),
+                );
+                dir
+            }
+        };
+        Some(dir)
+    } else {
+        None
+    };
+    let config: Config = settings.clone().try_into()?;
+    let database = config
+        .storage
+        .workspace_database_file()
+        .unwrap_or_else(Database::default_path);
+    let mappings = StorageManager::mappings_file_for(&config)?;
+
+    // Every file the bundle would replace
+    let mut targets = vec![
+        (staging.join(DATABASE), database.clone()),
+        (staging.join(MAPPINGS), mappings),
+    ];
+    for (name, dir) in [
+        (TEMPLATES, templates_dir),
+        (PROMPTS, Some(prompt_pack::overrides_dir()?)),
+    ] {
+        let (Some(dir), bundled) = (dir, staging.join(name)) else {
+            continue;
+        };
+        if bundled.is_dir() {
+            for file in relative_files(&bundled)? {
+                targets.push((bundled.join(&file), dir.join(&file)));
+            }
+        }
+    }
+    targets.retain(|(source, _)| source.is_file());
+    let existing: Vec<&PathBuf> = std::iter::once(&config_path)
+        .chain(targets.iter().map(|(_, target)| target))
+        .filter(|target| target.exists())
+        .collect();
+    if !force && !existing.is_empty() {
+        return Err(KtmeError::InvalidInput(format!(
+            "Importing would replace {} existing file(s), e.g. {}; pass --force to replace them (they are kept with a .bak suffix)",
+            existing.len(),
+            existing[0].display()
+        )));
+    }
+
+    let mut report = ImportReport {
+        manifest,
+        written: Vec::new(),
+        backups: Vec::new(),
+        missing_secrets,
+    };
+    let config_text = toml::to_string_pretty(&settings)?;
+    replace(
+        &config_path,
+        |path| Ok(fs::write(path, &config_text)?),
+        &mut report,
+    )?;
+    for (source, target) in &targets {
+        if target == &database {
+            // A stale write-ahead log would be replayed into the new database
+            for suffix in ["-wal", "-shm"] {
+                let _ = fs::remove_file(format!("{}{}", database.display(), suffix));
+            }
+        }
+        replace(
+            target,
+            |path| Ok(fs::copy(source, path).map(|_| ())?),
+            &mut report,
+        )?;
+    }
+
+    // Brings a database of an older schema up to date
+    Database::open(&config.storage)?;
+    Ok(report)
+}
+
+/// Fail on bundles this build cannot read
+fn check_versions(manifest: &Manifest) -> Result<()> {
+    if manifest.format > FORMAT {
+        return Err(KtmeError::InvalidInput(format!(
+            "The bundle has format {}, this ktme reads up to {}; update ktme (ktme self-update) and retry",
+            manifest.format, FORMAT
+        )));
+    }
+    if manifest.schema_version > migrations::latest_version() {
+        return Err(KtmeError::InvalidInput(format!(
+            "The bundle was exported by ktme {} with database schema {}, newer than this ktme knows ({}); update ktme (ktme self-update) and retry",
+            manifest.ktme_version,
+            manifest.schema_version,
+            migrations::latest_version()
+        )));
+    }
+    Ok(())
+}
+
+/// Copy the database of `storage` to `snapshot` without the secrets of the
+/// provider configurations; returns its schema version
+fn snapshot_database(
+    storage: &StorageConfig,
+    snapshot: &Path,
+    redacted: &mut Vec<String>,
+) -> Result<i64> {
+    let db = Database::open(storage)?;
+    db.connection()?
+        .execute(
+            "VACUUM INTO ?1",
+            params![snapshot.to_string_lossy().to_string()],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to snapshot the database: {}", e)))?;
+    drop(db);
+
+    let copy = Database::open(&StorageConfig {
+        database_file: Some(snapshot.to_path_buf()),
+        workspaces: Default::default(),
+        ..storage.clone()
+    })?;
+    let conn = copy.connection()?;
+    let storage_error = |e: rusqlite::Error| KtmeError::Storage(e.to_string());
+    let providers: Vec<(i64, String, String)> = conn
+        .prepare("SELECT id, provider_type, config_json FROM provider_configs ORDER BY id")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect()
+        })
+        .map_err(storage_error)?;
+    for (id, provider, json) in providers {
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&json) else {
+            continue;
+        };
+        let removed = strip_json_secrets(&mut value);
+        if removed.is_empty() {
+            continue;
+        }
+        conn.execute(
+            "UPDATE provider_configs SET config_json = ?1 WHERE id = ?2",
+            params![value.to_string(), id],
+        )
+        .map_err(storage_error)?;
+        redacted.extend(
+            removed
+                .into_iter()
+                .map(|key| format!("provider {}: {}", provider, key)),
+        );
+    }
+    let schema_version: i64 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_versions",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(storage_error)?;
+    // One self-contained file, without a write-ahead log next to it
+    conn.execute_batch("VACUUM; PRAGMA journal_mode = DELETE;")
+        .map_err(storage_error)?;
+    Ok(schema_version)
+}
+
+/// Whether the value of `key` is a secret
+fn is_secret(key: &str) -> bool {
+    let key = key.to_lowercase();
+    key == "token"
+        || key.ends_with("_token")
+        || key.ends_with("api_key")
+        || key.ends_with("secret")
+        || key.contains("password")
+        || key == "database_url"
+}
+
+/// Whether `value` only references an environment variable, e.g.
+/// `"${OPENAI_API_KEY}"`
+fn is_reference(value: &toml::Value) -> bool {
+    value
+        .as_str()
+        .is_some_and(|v| v.starts_with("${") && v.ends_with('}') && v.matches("${").count() == 1)
+}
+
+/// Remove the secrets of `value`, returning their paths such as
+/// `ai.api_key` or `ai.fallback[0].api_key`. Every value of a `headers`
+/// table is a secret (`Authorization`, `x-api-key`...). Values that only
+/// reference an environment variable are not secrets and are kept.
+fn strip_secrets(value: &mut toml::Value, prefix: &str) -> Vec<String> {
+    let mut removed = Vec::new();
+    if let Some(items) = value.as_array_mut() {
+        for (index, item) in items.iter_mut().enumerate() {
+            removed.extend(strip_secrets(item, &format!("{}[{}]", prefix, index)));
+        }
+        return removed;
+    }
+    let Some(table) = value.as_table_mut() else {
+        return removed;
+    };
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        let nested = table[&key].is_table() || table[&key].is_array();
+        if key == "headers" {
+            if let Some(headers) = table.get_mut(&key).and_then(toml::Value::as_table_mut) {
+                let names: Vec<String> = headers
+                    .iter()
+                    .filter(|(_, value)| !is_reference(value))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                for name in names {
+                    headers.remove(&name);
+                    removed.push(format!("{}.{}", path, name));
+                }
+            }
+        } else if is_secret(&key) && !nested && !is_reference(&table[&key]) {
+            table.remove(&key);
+            removed.push(path);
+        } else if let Some(child) = table.get_mut(&key) {
+            removed.extend(strip_secrets(child, &path));
+        }
+    }
+    removed
+}
+
+fn strip_json_secrets(value: &mut serde_json::Value) -> Vec<String> {
+    let Some(object) = value.as_object_mut() else {
+        return Vec::new();
+    };
+    let secrets: Vec<String> = object.keys().filter(|k| is_secret(k)).cloned().collect();
+    for key in &secrets {
+        object.remove(key);
+    }
+    secrets
+}
+
+fn get_key<'a>(value: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+fn set_key(value: &mut toml::Value, key: &str, new: toml::Value) {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let Some(last) = parts.pop() else {
+        return;
+    };
+    let mut table = value;
+    for part in parts {
+        let Some(current) = table.as_table_mut() else {
+            return;
+        };
+        table = current
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+    }
+    if let Some(table) = table.as_table_mut() {
+        table.insert(last.to_string(), new);
+    }
+}
+
+fn remove_key(value: &mut toml::Value, key: &str) {
+    let (parent, last) = match key.rsplit_once('.') {
+        Some((parent, last)) => (parent, last),
+        None => ("", key),
+    };
+    let table = if parent.is_empty() {
+        value.as_table_mut()
+    } else {
+        parent
+            .split('.')
+            .try_fold(value, |value, part| value.get_mut(part))
+            .and_then(toml::Value::as_table_mut)
+    };
+    if let Some(table) = table {
+        table.remove(last);
+    }
+}
+
+/// Files under `dir`, relative to it
+fn relative_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().strip_prefix(dir).ok().map(Path::to_path_buf))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Write `path` with `write`, keeping the file it replaces as `<path>.bak`
+fn replace(
+    path: &Path,
+    write: impl FnOnce(&Path) -> Result<()>,
+    report: &mut ImportReport,
+) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    if path.exists() {
+        let backup = PathBuf::from(format!("{}.bak", path.display()));
+        fs::rename(path, &backup)?;
+        report.backups.push(backup);
+    }
+    write(path)?;
+    report.written.push(path.to_path_buf());
+    Ok(())
+}
+
+fn append_bytes<W: std::io::Write>(
+    archive: &mut tar::Builder<W>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    archive.append_data(&mut header, name, bytes)?;
+    Ok(())
+}
+
+fn staging_dir(purpose: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!(
+        "ktme-{}-{}-{}",
+        purpose,
+        std::process::id(),
+        uuid::Uuid::new_v4()
+    ));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_secrets() {
+        let mut settings: toml::Value = toml::from_str(
+            r#"
+            [ai]
+            provider = "openai"
+            api_key = "sk-live"
+            max_tokens = 4000
+
+            [confluence]
+            api_token = "${CONFLUENCE_TOKEN}"
+            space_key = "DOCS"
+
+            [ai.headers]
+            Authorization = "Bearer gateway-token"
+            x-tenant = "${TENANT}"
+
+            [ai.summary]
+            provider = "openai"
+            api_key = "sk-summary"
+
+            [[ai.fallback]]
+            provider = "anthropic"
+            api_key = "sk-ant-fallback"
+            headers = { x-api-key = "gateway-key" }
+
+            [issues]
+            jira_api_token = "secret"
+            "#,
+        )
+        .unwrap();
+        let removed = strip_secrets(&mut settings, "");
+        assert_eq!(
+            removed,
+            vec![
+                "ai.api_key",
+                "ai.fallback[0].api_key",
+                "ai.fallback[0].headers.x-api-key",
+                "ai.headers.Authorization",
+                "ai.summary.api_key",
+                "issues.jira_api_token"
+            ]
+        );
+        let stripped = toml::to_string(&settings).unwrap();
+        for secret in [
+            "sk-live",
+            "gateway-token",
+            "sk-summary",
+            "sk-ant-fallback",
+            "gateway-key",
+        ] {
+            assert!(!stripped.contains(secret), "{} was kept", secret);
+        }
+        assert!(stripped.contains("${TENANT}"));
+        assert!(get_key(&settings, "ai.api_key").is_none());
+        assert_eq!(
+            get_key(&settings, "ai.max_tokens").unwrap().as_integer(),
+            Some(4000)
+        );
+        // References to the environment are not secrets
+        assert_eq!(
+            get_key(&settings, "confluence.api_token").unwrap().as_str(),
+            Some("${CONFLUENCE_TOKEN}")
+        );
+
+        set_key(
+            &mut settings,
+            "ai.api_key",
+            toml::Value::String("sk-mine".into()),
+        );
+        set_key(
+            &mut settings,
+            "storage.database_file",
+            toml::Value::String("/db".into()),
+        );
+        assert_eq!(
+            get_key(&settings, "ai.api_key").unwrap().as_str(),
+            Some("sk-mine")
+        );
+        remove_key(&mut settings, "storage.database_file");
+        assert!(get_key(&settings, "storage.database_file").is_none());
+    }
+
+    #[test]
+    fn test_newer_bundles_are_refused() {
+        let manifest = Manifest {
+            format: FORMAT,
+            ktme_version: "9.0.0".to_string(),
+            schema_version: migrations::latest_version() + 1,
+            created_at: Utc::now(),
+            workspace: "default".to_string(),
+            files: Vec::new(),
+            redacted: Vec::new(),
+        };
+        assert!(check_versions(&manifest)
+            .unwrap_err()
+            .to_string()
+            .contains("newer than this ktme knows"));
+        assert!(check_versions(&Manifest {
+            schema_version: 1,
+            ..manifest
+        })
+        .is_ok());
+    }
+}
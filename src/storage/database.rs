@@ -1,5 +1,10 @@
+use crate::config::interpolate::env_lookup;
+use crate::config::StorageConfig;
 use crate::error::{KtmeError, Result};
+use crate::storage::migrations::{self, MigrationStatus};
+use crate::storage::rows::{impl_from_row, query_all, FromRow};
 use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
@@ -7,6 +12,7 @@ use std::sync::{Arc, Mutex};
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
     path: PathBuf,
+    workspace: String,
 }
 
 impl Database {
@@ -14,14 +20,38 @@ impl Database {
     ///
     /// If path is None, uses default location: ~/.config/ktme/ktme.db
     pub fn new(path: Option<PathBuf>) -> Result<Self> {
+        Self::open_with_key(path, None)
+    }
+
+    /// Open the database described by the storage configuration, unlocking it
+    /// with the configured passphrase when encryption is enabled
+    pub fn open(config: &StorageConfig) -> Result<Self> {
+        let passphrase = if config.encrypt {
+            Some(resolve_passphrase(config, &env_lookup)?)
+        } else {
+            None
+        };
+
+        let db = Self::open_with_key(config.workspace_database_file(), passphrase.as_deref())?;
+        Ok(db.with_workspace(&config.workspace))
+    }
+
+    /// Scope repositories created from this handle to the given workspace
+    pub fn with_workspace(mut self, workspace: &str) -> Self {
+        self.workspace = workspace.to_string();
+        self
+    }
+
+    /// Open a database connection, keying it with SQLCipher when a passphrase is given
+    pub fn open_with_key(path: Option<PathBuf>, passphrase: Option<&str>) -> Result<Self> {
         let db_path = path.unwrap_or_else(|| {
-            // Use ~/.config/ktme/ktme.db explicitly
-            let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-            let config_dir = home_dir.join(".config").join("ktme");
-            if let Err(e) = std::fs::create_dir_all(&config_dir) {
-                tracing::warn!("Failed to create config directory: {}", e);
+            let db_path = Self::default_path();
+            if let Some(config_dir) = db_path.parent() {
+                if let Err(e) = std::fs::create_dir_all(config_dir) {
+                    tracing::warn!("Failed to create config directory: {}", e);
+                }
             }
-            config_dir.join("ktme.db")
+            db_path
         });
 
         tracing::info!("Opening database at: {}", db_path.display());
@@ -34,6 +64,11 @@ impl Database {
         )
         .map_err(|e| KtmeError::Storage(format!("Failed to open database: {}", e)))?;
 
+        // The key must be set before any other statement touches the file
+        if let Some(passphrase) = passphrase {
+            apply_key(&conn, passphrase)?;
+        }
+
         // Enable foreign keys and WAL mode for better concurrency
         conn.execute_batch(
             "PRAGMA foreign_keys = ON;
@@ -45,73 +80,35 @@ impl Database {
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
             path: db_path,
+            workspace: DEFAULT_WORKSPACE.to_string(),
         };
 
         db.migrate()?;
         Ok(db)
     }
 
+    /// Database file used when none is configured: ~/.config/ktme/ktme.db
+    pub fn default_path() -> PathBuf {
+        let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        home_dir.join(".config").join("ktme").join("ktme.db")
+    }
+
     /// Create an in-memory database (for testing)
     pub fn in_memory() -> Result<Self> {
         // First create the connection and run migrations
-        let conn = Connection::open_in_memory()
+        let mut conn = Connection::open_in_memory()
             .map_err(|e| KtmeError::Storage(format!("Failed to open in-memory database: {}", e)))?;
 
         // Only set foreign keys for in-memory (WAL mode not supported)
         conn.execute_batch("PRAGMA foreign_keys = ON;")
             .map_err(|e| KtmeError::Storage(format!("Failed to set pragmas: {}", e)))?;
 
-        // Create schema_versions table if it doesn't exist
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS schema_versions (
-                version INTEGER PRIMARY KEY,
-                applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );",
-        )
-        .map_err(|e| {
-            KtmeError::Storage(format!("Failed to create schema_versions table: {}", e))
-        })?;
-
-        // Get current version (should be 0 for new in-memory DB)
-        let current_version: i64 = conn
-            .query_row("SELECT MAX(version) FROM schema_versions", [], |row| {
-                row.get(0)
-            })
-            .unwrap_or(0);
-
-        // Run migrations in order directly (no mutex needed)
-        let migrations = vec![
-            (1, include_str!("../../migrations/001_initial.sql")),
-            (
-                2,
-                include_str!("../../migrations/002_features_and_search.sql"),
-            ),
-            (
-                3,
-                include_str!("../../migrations/003_search_index_unique.sql"),
-            ),
-        ];
-
-        for (version, sql) in &migrations {
-            if *version > current_version {
-                conn.execute_batch(sql).map_err(|e| {
-                    KtmeError::Storage(format!("Migration {} failed: {}", version, e))
-                })?;
-
-                // Record the migration
-                conn.execute(
-                    "INSERT OR IGNORE INTO schema_versions (version) VALUES (?1)",
-                    rusqlite::params![version],
-                )
-                .map_err(|e| {
-                    KtmeError::Storage(format!("Failed to record migration {}: {}", version, e))
-                })?;
-            }
-        }
+        migrations::migrate(&mut conn)?;
 
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
             path: PathBuf::from(":memory:"),
+            workspace: DEFAULT_WORKSPACE.to_string(),
         };
 
         Ok(db)
@@ -119,70 +116,45 @@ impl Database {
 
     /// Run database migrations
     pub fn migrate(&self) -> Result<()> {
-        let conn = self.connection()?;
-
-        // Create schema_versions table if it doesn't exist
-        conn.execute_batch(
-            "CREATE TABLE IF NOT EXISTS schema_versions (
-                version INTEGER PRIMARY KEY,
-                applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            );",
-        )
-        .map_err(|e| {
-            KtmeError::Storage(format!("Failed to create schema_versions table: {}", e))
-        })?;
-
-        // Get current version
-        let current_version: i64 = conn
-            .query_row("SELECT MAX(version) FROM schema_versions", [], |row| {
-                row.get(0)
-            })
-            .unwrap_or(0);
-
-        tracing::info!("Current database schema version: {}", current_version);
-
-        // Run migrations in order
-        let migrations = vec![
-            (1, include_str!("../../migrations/001_initial.sql")),
-            (
-                2,
-                include_str!("../../migrations/002_features_and_search.sql"),
-            ),
-            (
-                3,
-                include_str!("../../migrations/003_search_index_unique.sql"),
-            ),
-        ];
-
-        let latest_version = migrations.last().map(|(v, _)| *v).unwrap_or(0);
-
-        for (version, sql) in &migrations {
-            if *version > current_version {
-                tracing::info!("Running migration version: {}", version);
-                conn.execute_batch(sql).map_err(|e| {
-                    KtmeError::Storage(format!("Migration {} failed: {}", version, e))
-                })?;
-
-                // Record the migration
-                conn.execute(
-                    "INSERT OR IGNORE INTO schema_versions (version) VALUES (?1)",
-                    params![version],
-                )
-                .map_err(|e| {
-                    KtmeError::Storage(format!("Failed to record migration {}: {}", version, e))
-                })?;
-
-                tracing::debug!("Migration {} completed successfully", version);
-            }
-        }
-
+        let mut conn = self.connection()?;
+        migrations::migrate(&mut conn)?;
         tracing::info!(
             "Database migrations completed. Latest version: {}",
-            latest_version
+            migrations::latest_version()
         );
         Ok(())
     }
 
+    /// State of every known or applied migration
+    pub fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        let conn = self.connection()?;
+        migrations::status(&conn)
+    }
+
+    /// Revert the migrations above `target` (development only)
+    pub fn rollback(&self, target: i64) -> Result<Vec<i64>> {
+        let mut conn = self.connection()?;
+        migrations::rollback(&mut conn, target)
+    }
+
+    /// Tables, indexes, views and triggers of the current schema, each
+    /// with the statement that creates it; only those of `table` when set
+    pub fn schema(&self, table: Option<&str>) -> Result<Vec<SchemaObject>> {
+        let conn = self.connection()?;
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM sqlite_master
+                 WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'
+                   AND (?1 IS NULL OR tbl_name = ?1)
+                 ORDER BY tbl_name, type = 'table' DESC, name",
+                SchemaObject::columns()
+            ),
+            params![table],
+            "schema",
+        )
+    }
+
     /// Get a connection guard for executing queries
     pub fn connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
         self.conn
@@ -195,6 +167,11 @@ impl Database {
         &self.path
     }
 
+    /// Workspace that services and history are scoped to
+    pub fn workspace(&self) -> &str {
+        &self.workspace
+    }
+
     /// Check if the database is healthy
     pub fn health_check(&self) -> Result<bool> {
         let conn = self.connection()?;
@@ -208,29 +185,45 @@ impl Database {
         let conn = self.connection()?;
 
         let service_count: i64 = conn
-            .query_row("SELECT COUNT(*) FROM services", [], |row| row.get(0))
+            .query_row(
+                "SELECT COUNT(*) FROM services WHERE workspace = ?1",
+                params![self.workspace],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
 
         let mapping_count: i64 = conn
-            .query_row("SELECT COUNT(*) FROM document_mappings", [], |row| {
-                row.get(0)
-            })
+            .query_row(
+                "SELECT COUNT(*) FROM document_mappings dm
+                 JOIN services s ON dm.service_id = s.id WHERE s.workspace = ?1",
+                params![self.workspace],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
 
         let history_count: i64 = conn
-            .query_row("SELECT COUNT(*) FROM generation_history", [], |row| {
-                row.get(0)
-            })
+            .query_row(
+                "SELECT COUNT(*) FROM generation_history WHERE workspace = ?1",
+                params![self.workspace],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
 
         let provider_count: i64 = conn
-            .query_row("SELECT COUNT(*) FROM provider_configs", [], |row| {
-                row.get(0)
-            })
+            .query_row(
+                "SELECT COUNT(*) FROM provider_configs WHERE workspace = ?1",
+                params![self.workspace],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
 
         let feature_count: i64 = conn
-            .query_row("SELECT COUNT(*) FROM features", [], |row| row.get(0))
+            .query_row(
+                "SELECT COUNT(*) FROM features f
+                 JOIN services s ON f.service_id = s.id WHERE s.workspace = ?1",
+                params![self.workspace],
+                |row| row.get(0),
+            )
             .unwrap_or(0);
 
         Ok(DatabaseStats {
@@ -249,10 +242,86 @@ impl Clone for Database {
         Self {
             conn: Arc::clone(&self.conn),
             path: self.path.clone(),
+            workspace: self.workspace.clone(),
         }
     }
 }
 
+/// Workspace used when none is configured
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Environment variable holding the SQLCipher passphrase
+pub const PASSPHRASE_ENV: &str = "KTME_DB_PASSPHRASE";
+
+/// Resolve the database passphrase from KTME_DB_PASSPHRASE, read with
+/// `lookup`, or `passphrase_command`
+pub fn resolve_passphrase(
+    config: &StorageConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String> {
+    if let Some(passphrase) = lookup(PASSPHRASE_ENV) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase);
+        }
+    }
+
+    let command = config.passphrase_command.as_deref().ok_or_else(|| {
+        KtmeError::Config(format!(
+            "Database encryption is enabled but no passphrase is available; set {} or storage.passphrase_command",
+            PASSPHRASE_ENV
+        ))
+    })?;
+
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| KtmeError::Config(format!("Failed to run passphrase command: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(KtmeError::Config(format!(
+            "Passphrase command exited with {}",
+            output.status
+        )));
+    }
+
+    let passphrase = String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\r', '\n'])
+        .to_string();
+    if passphrase.is_empty() {
+        return Err(KtmeError::Config(
+            "Passphrase command produced no output".to_string(),
+        ));
+    }
+
+    Ok(passphrase)
+}
+
+#[cfg(feature = "sqlcipher")]
+fn apply_key(conn: &Connection, passphrase: &str) -> Result<()> {
+    conn.pragma_update(None, "key", passphrase)
+        .map_err(|e| KtmeError::Storage(format!("Failed to set database key: {}", e)))?;
+
+    // SQLCipher only reports a wrong key on first read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|_| {
+        KtmeError::Storage(
+            "Failed to unlock database: wrong passphrase or database is not encrypted".to_string(),
+        )
+    })?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+fn apply_key(_conn: &Connection, _passphrase: &str) -> Result<()> {
+    Err(KtmeError::Config(
+        "ktme was built without SQLCipher support; rebuild with `--features sqlcipher`".to_string(),
+    ))
+}
+
 /// Database statistics
 #[derive(Debug, Clone)]
 pub struct DatabaseStats {
@@ -264,6 +333,24 @@ pub struct DatabaseStats {
     pub feature_count: u64,
 }
 
+/// Object of the database schema, as recorded in `sqlite_master`
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaObject {
+    /// table, index, view or trigger
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    pub table: String,
+    pub sql: String,
+}
+
+impl_from_row!(SchemaObject {
+    kind = "type",
+    name,
+    table = "tbl_name",
+    sql,
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,6 +370,24 @@ mod tests {
         assert_eq!(stats.feature_count, 0);
     }
 
+    #[test]
+    fn test_schema_lists_tables_with_their_indexes() {
+        let db = Database::in_memory().expect("Failed to create test database");
+
+        let schema = db.schema(None).expect("Failed to read schema");
+        assert!(schema
+            .iter()
+            .any(|object| object.kind == "table" && object.name == "services"));
+        assert!(schema
+            .iter()
+            .all(|object| !object.name.starts_with("sqlite_")));
+
+        let services = db.schema(Some("services")).expect("Failed to read schema");
+        assert_eq!(services[0].kind, "table");
+        assert!(services[0].sql.starts_with("CREATE TABLE"));
+        assert!(services.iter().all(|object| object.table == "services"));
+    }
+
     #[test]
     fn test_migration_system() {
         let db = Database::in_memory().expect("Failed to create test database");
@@ -325,6 +430,94 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_workspace_migration_keeps_data() {
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("ktme.db");
+
+        // Build a pre-workspace database with one mapped service
+        {
+            let conn = Connection::open(&path).expect("Failed to open database");
+            conn.execute_batch(
+                "CREATE TABLE schema_versions (
+                    version INTEGER PRIMARY KEY,
+                    applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                );",
+            )
+            .unwrap();
+            conn.execute_batch(include_str!("../../migrations/001_initial.sql"))
+                .unwrap();
+            conn.execute_batch(include_str!("../../migrations/002_features_and_search.sql"))
+                .unwrap();
+            conn.execute_batch(include_str!("../../migrations/003_search_index_unique.sql"))
+                .unwrap();
+            conn.execute_batch(
+                "INSERT INTO services (name) VALUES ('api');
+                 INSERT INTO document_mappings (service_id, provider, location)
+                 VALUES (1, 'markdown', 'docs/api.md');",
+            )
+            .unwrap();
+        }
+
+        let db = Database::new(Some(path)).expect("Failed to migrate database");
+        let stats = db.stats().expect("Failed to get stats");
+        assert_eq!(stats.service_count, 1);
+        assert_eq!(stats.mapping_count, 1);
+
+        let other = db.clone().with_workspace("client-a");
+        assert_eq!(other.stats().expect("Failed to get stats").service_count, 0);
+    }
+
+    #[test]
+    fn test_passphrase_from_command() {
+        let config = StorageConfig {
+            encrypt: true,
+            passphrase_command: Some("printf 'hunter2\\n'".to_string()),
+            ..Default::default()
+        };
+        let unset = |_: &str| None;
+        assert_eq!(resolve_passphrase(&config, &unset).unwrap(), "hunter2");
+
+        // The environment variable takes precedence over the command, unless
+        // it is empty
+        let set = |name: &str| (name == PASSPHRASE_ENV).then(|| "from-env".to_string());
+        assert_eq!(resolve_passphrase(&config, &set).unwrap(), "from-env");
+        let empty = |_: &str| Some(String::new());
+        assert_eq!(resolve_passphrase(&config, &empty).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_passphrase_command_failure() {
+        let config = StorageConfig {
+            encrypt: true,
+            passphrase_command: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        assert!(resolve_passphrase(&config, &|_| None).is_err());
+    }
+
+    #[cfg(not(feature = "sqlcipher"))]
+    #[test]
+    fn test_encryption_requires_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Database::open_with_key(Some(dir.path().join("ktme.db")), Some("secret"))
+            .err()
+            .expect("encryption should be rejected");
+        assert!(err.to_string().contains("built without SQLCipher support"));
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn test_encrypted_database_requires_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ktme.db");
+        Database::open_with_key(Some(path.clone()), Some("secret")).unwrap();
+
+        assert!(Database::open_with_key(Some(path.clone()), Some("wrong")).is_err());
+        assert!(Database::new(Some(path.clone())).is_err());
+        assert!(Database::open_with_key(Some(path), Some("secret")).is_ok());
+    }
+
     #[test]
     fn test_feature_stats_field() {
         let db = Database::in_memory().expect("Failed to create test database");
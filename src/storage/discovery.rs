@@ -40,7 +40,7 @@ impl ServiceDiscovery {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct DiscoveredService {
     pub name: String,
     pub path: String,
@@ -0,0 +1,177 @@
+//! Advisory lock of a service held from generation to publish
+//!
+//! The CLI and the MCP server can both publish the documentation of a
+//! service; run at the same time they would race on the same files and
+//! Confluence page versions. A run takes the lock of its service in the
+//! configured storage backend before generating and keeps it until it has
+//! published, so runs sharing a Postgres database exclude each other too.
+//! Locks expire after the operation timeout, so one left behind by a run
+//! that crashed does not block the service for good.
+
+use super::backend::{open_storage, Storage};
+use super::models::GenerationLock;
+use crate::config::Config;
+use crate::error::{KtmeError, Result};
+use crate::shutdown;
+use chrono::Utc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Time between two attempts to take a busy lock with `--wait`
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lock of one service, released when dropped
+pub struct ServiceLock {
+    storage: Box<dyn Storage>,
+    resource: String,
+    owner: String,
+    purpose: String,
+    ttl: Duration,
+}
+
+impl ServiceLock {
+    /// Lock `service` for `purpose` (e.g. `generate`), waiting for the run
+    /// holding it to finish when `wait`, failing with
+    /// [`KtmeError::GenerationInProgress`] otherwise.
+    pub async fn acquire(
+        config: &Config,
+        service: &str,
+        purpose: &str,
+        wait: bool,
+    ) -> Result<Self> {
+        Self::from_config(config, service, purpose)?
+            .take(wait)
+            .await
+    }
+
+    /// [`ServiceLock::acquire`] without waiting, for synchronous callers
+    pub fn try_acquire(config: &Config, service: &str, purpose: &str) -> Result<Self> {
+        let lock = Self::from_config(config, service, purpose)?;
+        match lock.attempt()? {
+            None => Ok(lock),
+            Some(holder) => Err(in_progress(&lock.resource, &holder)),
+        }
+    }
+
+    fn from_config(config: &Config, service: &str, purpose: &str) -> Result<Self> {
+        let storage = open_storage(&config.storage)?;
+        let ttl = config.timeouts.operation_timeout(None)?;
+        Ok(Self::new(storage, service, purpose, ttl))
+    }
+
+    fn new(storage: Box<dyn Storage>, service: &str, purpose: &str, ttl: Duration) -> Self {
+        Self {
+            storage,
+            resource: format!("service:{}", service),
+            owner: Uuid::new_v4().to_string(),
+            purpose: purpose.to_string(),
+            ttl,
+        }
+    }
+
+    async fn take(self, wait: bool) -> Result<Self> {
+        let mut announced = false;
+        loop {
+            let Some(holder) = self.attempt()? else {
+                return Ok(self);
+            };
+            if !wait {
+                return Err(in_progress(&self.resource, &holder));
+            }
+            if !announced {
+                tracing::info!(
+                    "Waiting for {} to finish...",
+                    describe(&self.resource, &holder)
+                );
+                announced = true;
+            }
+            shutdown::check("Waiting for the generation lock")?;
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Take the lock; the current holder when another run has it
+    fn attempt(&self) -> Result<Option<GenerationLock>> {
+        let ttl = chrono::Duration::from_std(self.ttl).unwrap_or(chrono::Duration::MAX);
+        let expires_at = Utc::now()
+            .checked_add_signed(ttl)
+            .unwrap_or(chrono::DateTime::<Utc>::MAX_UTC);
+        loop {
+            if self.storage.try_acquire_lock(
+                &self.resource,
+                &self.owner,
+                &self.purpose,
+                expires_at,
+            )? {
+                tracing::debug!("Acquired lock {} for {}", self.resource, self.purpose);
+                return Ok(None);
+            }
+            // Released between the two queries: take it again
+            if let Some(holder) = self.storage.lock_holder(&self.resource)? {
+                return Ok(Some(holder));
+            }
+        }
+    }
+}
+
+impl Drop for ServiceLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.storage.release_lock(&self.resource, &self.owner) {
+            tracing::warn!("Failed to release lock {}: {}", self.resource, e);
+        }
+    }
+}
+
+fn in_progress(resource: &str, holder: &GenerationLock) -> KtmeError {
+    KtmeError::GenerationInProgress(describe(resource, holder))
+}
+
+/// The run holding the lock of `resource`
+fn describe(resource: &str, holder: &GenerationLock) -> String {
+    format!(
+        "{} of {} (pid {}, started {})",
+        holder.purpose,
+        resource.strip_prefix("service:").unwrap_or(resource),
+        holder.pid,
+        holder.acquired_at.format("%Y-%m-%d %H:%M:%S UTC")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
+
+    fn lock(db: &Database, purpose: &str) -> ServiceLock {
+        let storage = Box::new(SqliteStorage::new(db.clone()));
+        ServiceLock::new(storage, "payments", purpose, Duration::from_secs(60))
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive_and_released_on_drop() {
+        let db = Database::in_memory().unwrap();
+
+        let held = lock(&db, "generate").take(false).await.unwrap();
+        let error = lock(&db, "update").take(false).await.err().unwrap();
+        assert!(matches!(error, KtmeError::GenerationInProgress(_)));
+        assert!(error.to_string().contains("generate of payments"));
+
+        drop(held);
+        lock(&db, "update").take(false).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_takes_the_lock_once_released() {
+        let db = Database::in_memory().unwrap();
+
+        let held = lock(&db, "generate").take(false).await.unwrap();
+        let waiting = tokio::spawn(lock(&db, "update").take(true));
+        tokio::time::sleep(POLL_INTERVAL * 2).await;
+        assert!(!waiting.is_finished());
+
+        drop(held);
+        let taken = waiting.await.unwrap().unwrap();
+        assert_eq!(taken.purpose, "update");
+    }
+}
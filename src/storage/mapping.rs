@@ -1,8 +1,7 @@
 use crate::config::Config;
 use crate::error::{KtmeError, Result};
-use crate::storage::database::Database;
-use crate::storage::models::{FeatureType, SearchQuery, SearchResult};
-use crate::storage::repository::{DocumentMappingRepository, FeatureRepository, ServiceRepository};
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::models::{FeatureType, SearchQuery, SearchResult, ServiceSettings};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -31,12 +30,39 @@ pub struct ServiceMapping {
     pub name: String,
     pub path: Option<String>,
     pub docs: Vec<DocumentLocation>,
+    #[serde(default, skip_serializing_if = "ServiceSettings::is_empty")]
+    pub settings: ServiceSettings,
+}
+
+/// Generation settings stored for `service` in the mappings selected by
+/// `config`; the defaults when the service is not mapped. `storage` is the
+/// already opened database backend.
+pub fn service_settings(
+    config: &Config,
+    storage: &dyn Storage,
+    service: &str,
+) -> Result<ServiceSettings> {
+    if config.storage.uses_database() {
+        return match storage.get_service_by_name(service)? {
+            Some(entity) => storage.service_settings(entity.id),
+            None => Ok(ServiceSettings::default()),
+        };
+    }
+
+    match StorageManager::from_config(config)?.get_mapping(service) {
+        Ok(mapping) => Ok(mapping.settings),
+        Err(KtmeError::MappingNotFound(_)) => Ok(ServiceSettings::default()),
+        Err(e) => Err(e),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentLocation {
     pub r#type: String,
     pub location: String,
+    /// Section of the document updates go to unless `--section` is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,34 +76,57 @@ pub struct ServiceSearchResult {
 
 pub struct StorageManager {
     mappings_file: PathBuf,
-    database: Option<Database>,
-    pub use_sqlite: bool,
+    storage: Option<Box<dyn Storage>>,
+    /// Mappings and features are kept in the configured database backend
+    pub use_database: bool,
 }
 
 impl StorageManager {
     pub fn new() -> Result<Self> {
-        let config = Config::load()?;
-        let mappings_file = if let Some(path) = config.storage.mappings_file {
-            path
-        } else {
-            let config_dir = Config::config_dir()?;
-            config_dir.join("mappings.toml")
-        };
+        Self::from_config(&Config::load()?)
+    }
 
-        let use_sqlite = config.storage.use_sqlite;
-        let database = if use_sqlite {
-            Some(Database::new(config.storage.database_file)?)
+    /// Create a manager for the workspace and backend selected by `config`
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mappings_file = Self::mappings_file_for(config)?;
+
+        let use_database = config.storage.uses_database();
+        let storage = if use_database {
+            if mappings_file.exists() {
+                tracing::warn!(
+                    "Mappings are read from the {} backend; entries in {} are ignored. Re-add them with 'ktme mapping add'.",
+                    config.storage.backend,
+                    mappings_file.display()
+                );
+            }
+            Some(open_storage(&config.storage)?)
         } else {
             None
         };
 
         Ok(Self {
             mappings_file,
-            database,
-            use_sqlite,
+            storage,
+            use_database,
         })
     }
 
+    /// Create a manager over an already opened database backend
+    pub fn with_storage(mappings_file: PathBuf, storage: Box<dyn Storage>) -> Self {
+        Self {
+            mappings_file,
+            storage: Some(storage),
+            use_database: true,
+        }
+    }
+
+    /// The database backend, when mappings are stored in a database
+    pub fn storage(&self) -> Result<&dyn Storage> {
+        self.storage
+            .as_deref()
+            .ok_or_else(|| KtmeError::Storage("Database storage not initialized".to_string()))
+    }
+
     pub fn load_mappings(&self) -> Result<Mappings> {
         if !self.mappings_file.exists() {
             return Ok(Mappings::default());
@@ -102,54 +151,50 @@ impl StorageManager {
         Ok(())
     }
 
-    pub fn add_mapping(&self, service: String, doc_type: String, location: String) -> Result<()> {
-        if self.use_sqlite {
-            // Use SQLite when enabled
-            if let Some(ref db) = self.database {
-                let service_repo = ServiceRepository::new(db.clone());
-                let mapping_repo = DocumentMappingRepository::new(db.clone());
-
-                // Create or get service
-                let service_entity = match service_repo.get_by_name(&service)? {
-                    Some(s) => s,
-                    None => service_repo.create(
-                        &service,
-                        Some(&location),
-                        Some(&format!("Service for {}", service)),
-                    )?,
-                };
-
-                // Add document mapping
-                mapping_repo.add(
-                    service_entity.id,
-                    &doc_type,
-                    &location,
-                    Some(&format!("Documentation for {}", service)),
-                    None,
-                    true, // Set as primary mapping
-                )?;
-
-                tracing::info!("Added mapping to SQLite: {} -> {}", service, location);
-            } else {
-                return Err(KtmeError::Storage("SQLite not initialized".to_string()));
-            }
+    /// Map `service` to a documentation location, including the section
+    /// updates of it go to
+    pub fn add_mapping(&self, service: String, doc: DocumentLocation) -> Result<()> {
+        if self.use_database {
+            let storage = self.storage()?;
+
+            // Create or get service
+            let service_entity = match storage.get_service_by_name(&service)? {
+                Some(s) => s,
+                None => storage.create_service(
+                    &service,
+                    Some(&doc.location),
+                    Some(&format!("Service for {}", service)),
+                )?,
+            };
+
+            // Add document mapping
+            storage.add_mapping(
+                service_entity.id,
+                &doc.r#type,
+                &doc.location,
+                Some(&format!("Documentation for {}", service)),
+                doc.section.as_deref(),
+                true, // Set as primary mapping
+            )?;
+
+            tracing::info!(
+                "Added mapping to {}: {} -> {}",
+                storage.backend_name(),
+                service,
+                doc.location
+            );
         } else {
             // Use TOML file storage
             let mut mappings = self.load_mappings()?;
 
             if let Some(existing) = mappings.services.iter_mut().find(|s| s.name == service) {
-                existing.docs.push(DocumentLocation {
-                    r#type: doc_type,
-                    location,
-                });
+                existing.docs.push(doc);
             } else {
                 mappings.services.push(ServiceMapping {
                     name: service,
                     path: None,
-                    docs: vec![DocumentLocation {
-                        r#type: doc_type,
-                        location,
-                    }],
+                    docs: vec![doc],
+                    settings: ServiceSettings::default(),
                 });
             }
 
@@ -161,33 +206,29 @@ impl StorageManager {
     }
 
     pub fn get_mapping(&self, service: &str) -> Result<ServiceMapping> {
-        if self.use_sqlite {
-            // Use SQLite when enabled
-            if let Some(ref db) = self.database {
-                let service_repo = ServiceRepository::new(db.clone());
-                let mapping_repo = DocumentMappingRepository::new(db.clone());
-
-                let service_entity = service_repo
-                    .get_by_name(service)?
-                    .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
-
-                let mappings = mapping_repo.get_for_service(service_entity.id)?;
-                let docs = mappings
-                    .into_iter()
-                    .map(|m| DocumentLocation {
-                        r#type: m.provider,
-                        location: m.location,
-                    })
-                    .collect();
-
-                Ok(ServiceMapping {
-                    name: service_entity.name,
-                    path: service_entity.path,
-                    docs,
+        if self.use_database {
+            let storage = self.storage()?;
+
+            let service_entity = storage
+                .get_service_by_name(service)?
+                .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+
+            let mappings = storage.mappings_for_service(service_entity.id)?;
+            let docs = mappings
+                .into_iter()
+                .map(|m| DocumentLocation {
+                    r#type: m.provider,
+                    location: m.location,
+                    section: m.section,
                 })
-            } else {
-                Err(KtmeError::Storage("SQLite not initialized".to_string()))
-            }
+                .collect();
+
+            Ok(ServiceMapping {
+                settings: storage.service_settings(service_entity.id)?,
+                name: service_entity.name,
+                path: service_entity.path,
+                docs,
+            })
         } else {
             // Use TOML file storage
             let mappings = self.load_mappings()?;
@@ -200,9 +241,85 @@ impl StorageManager {
         }
     }
 
+    /// Apply `key=value` assignments to the generation settings of a mapped
+    /// service and return the resulting settings
+    pub fn set_settings(&self, service: &str, assignments: &[String]) -> Result<ServiceSettings> {
+        if self.use_database {
+            let storage = self.storage()?;
+
+            let service_entity = storage
+                .get_service_by_name(service)?
+                .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+
+            let mut settings = storage.service_settings(service_entity.id)?;
+            for assignment in assignments {
+                settings.assign(assignment)?;
+            }
+            storage.set_service_settings(service_entity.id, &settings)?;
+
+            return Ok(settings);
+        }
+
+        let mut mappings = self.load_mappings()?;
+
+        let existing = mappings
+            .services
+            .iter_mut()
+            .find(|s| s.name == service)
+            .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+        for assignment in assignments {
+            existing.settings.assign(assignment)?;
+        }
+        let settings = existing.settings.clone();
+
+        mappings.last_updated = Utc::now();
+        self.save_mappings(&mappings)?;
+
+        Ok(settings)
+    }
+
+    /// Record the directory of a service mapped in the mappings file, which
+    /// scopes its diffs. Services in a database keep the path they were
+    /// registered with.
+    pub fn set_path(&self, service: &str, path: &str) -> Result<()> {
+        if self.use_database {
+            return Ok(());
+        }
+
+        let mut mappings = self.load_mappings()?;
+        let entry = mappings
+            .services
+            .iter_mut()
+            .find(|s| s.name == service)
+            .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+        entry.path = Some(path.to_string());
+        mappings.last_updated = Utc::now();
+        self.save_mappings(&mappings)
+    }
+
     pub fn remove_mapping(&self, service: &str) -> Result<()> {
+        if self.use_database {
+            let storage = self.storage()?;
+
+            // Only drop the document mappings; the service row owns features,
+            // relations and history that must survive a mapping removal
+            let service_entity = storage
+                .get_service_by_name(service)?
+                .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+
+            for mapping in storage.mappings_for_service(service_entity.id)? {
+                storage.delete_mapping(mapping.id)?;
+            }
+
+            return Ok(());
+        }
+
         let mut mappings = self.load_mappings()?;
 
+        if !mappings.services.iter().any(|s| s.name == service) {
+            return Err(KtmeError::MappingNotFound(service.to_string()));
+        }
+
         mappings.services.retain(|s| s.name != service);
         mappings.last_updated = Utc::now();
 
@@ -211,6 +328,85 @@ impl StorageManager {
         Ok(())
     }
 
+    /// Rename a mapped service, keeping its locations, settings and, in a
+    /// database, its features and history
+    pub fn rename_service(&self, old: &str, new: &str) -> Result<()> {
+        if self.use_database {
+            let storage = self.storage()?;
+
+            if storage.get_service_by_name(new)?.is_some() {
+                return Err(KtmeError::InvalidInput(format!(
+                    "Service {} already exists",
+                    new
+                )));
+            }
+            if !storage.rename_service(old, new)? {
+                return Err(KtmeError::MappingNotFound(old.to_string()));
+            }
+
+            return Ok(());
+        }
+
+        let mut mappings = self.load_mappings()?;
+
+        if mappings.services.iter().any(|s| s.name == new) {
+            return Err(KtmeError::InvalidInput(format!(
+                "Service {} already exists",
+                new
+            )));
+        }
+        let existing = mappings
+            .services
+            .iter_mut()
+            .find(|s| s.name == old)
+            .ok_or_else(|| KtmeError::MappingNotFound(old.to_string()))?;
+        existing.name = new.to_string();
+
+        mappings.last_updated = Utc::now();
+        self.save_mappings(&mappings)?;
+
+        Ok(())
+    }
+
+    /// Remove one documentation location of a mapped service; false when
+    /// the service has no such location
+    pub fn remove_location(&self, service: &str, location: &str) -> Result<bool> {
+        if self.use_database {
+            let storage = self.storage()?;
+
+            let service_entity = storage
+                .get_service_by_name(service)?
+                .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+
+            let mut removed = false;
+            for mapping in storage.mappings_for_service(service_entity.id)? {
+                if mapping.location == location {
+                    removed |= storage.delete_mapping(mapping.id)?;
+                }
+            }
+
+            return Ok(removed);
+        }
+
+        let mut mappings = self.load_mappings()?;
+
+        let existing = mappings
+            .services
+            .iter_mut()
+            .find(|s| s.name == service)
+            .ok_or_else(|| KtmeError::MappingNotFound(service.to_string()))?;
+        let before = existing.docs.len();
+        existing.docs.retain(|doc| doc.location != location);
+        if existing.docs.len() == before {
+            return Ok(false);
+        }
+
+        mappings.last_updated = Utc::now();
+        self.save_mappings(&mappings)?;
+
+        Ok(true)
+    }
+
     pub fn discover_services(
         &self,
         directory: &str,
@@ -224,14 +420,26 @@ impl StorageManager {
         self.mappings_file.clone()
     }
 
-    pub fn list_services(&self) -> Result<Vec<String>> {
-        if self.use_sqlite {
-            if let Some(ref db) = self.database {
-                let service_repo = ServiceRepository::new(db.clone());
-                service_repo.list_all_names()
+    /// mappings.toml of the workspace selected by `config`
+    pub fn mappings_file_for(config: &Config) -> Result<PathBuf> {
+        Ok(
+            if let Some(path) = config.storage.workspace_mappings_file() {
+                path
+            } else if config.storage.is_default_workspace() {
+                Config::config_dir()?.join("mappings.toml")
             } else {
-                Ok(vec![])
-            }
+                Config::config_dir()?
+                    .join("workspaces")
+                    .join(&config.storage.workspace)
+                    .join("mappings.toml")
+            },
+        )
+    }
+
+    pub fn list_services(&self) -> Result<Vec<String>> {
+        if self.use_database {
+            let services = self.storage()?.list_services()?;
+            Ok(services.into_iter().map(|s| s.name).collect())
         } else {
             let mappings = self.load_mappings()?;
             Ok(mappings.services.iter().map(|s| s.name.clone()).collect())
@@ -240,39 +448,34 @@ impl StorageManager {
 
     /// Search services by name, feature, or keyword
     pub fn search_services(&self, query: &str) -> Result<Vec<ServiceSearchResult>> {
-        if self.use_sqlite {
-            if let Some(ref db) = self.database {
-                let service_repo = ServiceRepository::new(db.clone());
-                let mapping_repo = DocumentMappingRepository::new(db.clone());
-
-                let all_services = service_repo.list()?;
-                let mut results = Vec::new();
-
-                for service in all_services {
-                    let relevance_score = self.calculate_relevance(&service, query);
-                    if relevance_score > 0.0 {
-                        let mappings = mapping_repo.get_for_service(service.id)?;
-                        let docs: Vec<String> = mappings
-                            .into_iter()
-                            .map(|m| format!("{}: {}", m.provider, m.location))
-                            .collect();
-
-                        results.push(ServiceSearchResult {
-                            name: service.name,
-                            path: service.path,
-                            description: service.description,
-                            docs,
-                            relevance_score,
-                        });
-                    }
-                }
+        if self.use_database {
+            let storage = self.storage()?;
 
-                // Sort by relevance score
-                results.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap());
-                Ok(results)
-            } else {
-                Err(KtmeError::Storage("SQLite not initialized".to_string()))
+            let all_services = storage.list_services()?;
+            let mut results = Vec::new();
+
+            for service in all_services {
+                let relevance_score = self.calculate_relevance(&service, query);
+                if relevance_score > 0.0 {
+                    let mappings = storage.mappings_for_service(service.id)?;
+                    let docs: Vec<String> = mappings
+                        .into_iter()
+                        .map(|m| format!("{}: {}", m.provider, m.location))
+                        .collect();
+
+                    results.push(ServiceSearchResult {
+                        name: service.name,
+                        path: service.path,
+                        description: service.description,
+                        docs,
+                        relevance_score,
+                    });
+                }
             }
+
+            // Sort by relevance score
+            results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
+            Ok(results)
         } else {
             // Simple text search in TOML mappings
             let mappings = self.load_mappings()?;
@@ -297,7 +500,7 @@ impl StorageManager {
                 }
             }
 
-            results.sort_by(|a, b| b.relevance_score.partial_cmp(&a.relevance_score).unwrap());
+            results.sort_by(|a, b| b.relevance_score.total_cmp(&a.relevance_score));
             Ok(results)
         }
     }
@@ -369,34 +572,30 @@ impl StorageManager {
         score
     }
 
-    /// Initialize the database with SQLite enabled
+    /// Initialize the database storage
     pub fn initialize_database(&self) -> Result<()> {
-        if self.use_sqlite {
-            if self.database.is_some() {
-                // Database is already initialized via migrations in Database::new()
-                tracing::info!("SQLite database initialized successfully");
-                Ok(())
-            } else {
-                Err(KtmeError::Storage("SQLite not initialized".to_string()))
-            }
+        if self.use_database {
+            // Migrations already ran when the backend was opened
+            let storage = self.storage()?;
+            tracing::info!(
+                "{} database initialized successfully",
+                storage.backend_name()
+            );
+            Ok(())
         } else {
             Err(KtmeError::Storage(
-                "SQLite not enabled in configuration".to_string(),
+                "Database storage not enabled in configuration".to_string(),
             ))
         }
     }
 
-    /// Get database statistics (only available when SQLite is enabled)
+    /// Get database statistics (only available with database storage)
     pub fn get_database_stats(&self) -> Result<crate::storage::database::DatabaseStats> {
-        if let Some(ref db) = self.database {
-            db.stats()
-        } else {
-            Err(KtmeError::Storage("Database not initialized".to_string()))
-        }
+        self.storage()?.stats()
     }
 
     // ============================================================================
-    // Feature Management Methods (SQLite only)
+    // Feature Management Methods (database storage only)
     // ============================================================================
 
     /// Create a new feature
@@ -409,23 +608,18 @@ impl StorageManager {
         tags: Vec<String>,
         metadata: serde_json::Value,
     ) -> Result<crate::storage::models::Feature> {
-        if !self.use_sqlite {
+        if !self.use_database {
             return Err(KtmeError::Storage(
-                "Features require SQLite storage".to_string(),
+                "Features require database storage".to_string(),
             ));
         }
 
-        let db = self
-            .database
-            .as_ref()
-            .ok_or_else(|| KtmeError::Storage("Database not initialized".to_string()))?;
-        let service_repo = ServiceRepository::new(db.clone());
-        let feature_repo = FeatureRepository::new(db.clone());
+        let storage = self.storage()?;
 
         // Find or create service
-        let service = match service_repo.get_by_name(service_name)? {
+        let service = match storage.get_service_by_name(service_name)? {
             Some(service) => service,
-            None => service_repo.create(
+            None => storage.create_service(
                 service_name,
                 None,
                 Some(&format!("Auto-created service for {}", service_name)),
@@ -434,7 +628,7 @@ impl StorageManager {
 
         // Create feature with UUID
         let feature_id = Uuid::new_v4().to_string();
-        feature_repo.create(
+        storage.create_feature(
             &feature_id,
             service.id,
             feature_name,
@@ -450,41 +644,30 @@ impl StorageManager {
         &self,
         service_name: &str,
     ) -> Result<Vec<crate::storage::models::Feature>> {
-        if !self.use_sqlite {
+        if !self.use_database {
             return Err(KtmeError::Storage(
-                "Features require SQLite storage".to_string(),
+                "Features require database storage".to_string(),
             ));
         }
 
-        let db = self
-            .database
-            .as_ref()
-            .ok_or_else(|| KtmeError::Storage("Database not initialized".to_string()))?;
-        let service_repo = ServiceRepository::new(db.clone());
-        let feature_repo = FeatureRepository::new(db.clone());
+        let storage = self.storage()?;
 
-        let service = service_repo
-            .get_by_name(service_name)?
+        let service = storage
+            .get_service_by_name(service_name)?
             .ok_or_else(|| KtmeError::Storage(format!("Service '{}' not found", service_name)))?;
 
-        feature_repo.list_by_service(service.id)
+        storage.features_for_service(service.id)
     }
 
     /// Search features across all services
     pub fn search_features(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
-        if !self.use_sqlite {
+        if !self.use_database {
             return Err(KtmeError::Storage(
-                "Feature search requires SQLite storage".to_string(),
+                "Feature search requires database storage".to_string(),
             ));
         }
 
-        let db = self
-            .database
-            .as_ref()
-            .ok_or_else(|| KtmeError::Storage("Database not initialized".to_string()))?;
-        let feature_repo = FeatureRepository::new(db.clone());
-
-        feature_repo.search(query)
+        self.storage()?.search_features(query)
     }
 
     /// Simple feature search by text
@@ -499,6 +682,8 @@ impl StorageManager {
             feature_types: None,
             content_types: None,
             limit,
+            offset: None,
+            min_score: None,
             similarity_threshold: None,
             include_related: false,
             depth: None,
@@ -509,19 +694,13 @@ impl StorageManager {
 
     /// Update feature relevance score
     pub fn update_feature_relevance(&self, feature_id: &str, score: f64) -> Result<()> {
-        if !self.use_sqlite {
+        if !self.use_database {
             return Err(KtmeError::Storage(
-                "Feature management requires SQLite storage".to_string(),
+                "Feature management requires database storage".to_string(),
             ));
         }
 
-        let db = self
-            .database
-            .as_ref()
-            .ok_or_else(|| KtmeError::Storage("Database not initialized".to_string()))?;
-        let feature_repo = FeatureRepository::new(db.clone());
-
-        feature_repo.update_relevance_score(feature_id, score)
+        self.storage()?.update_feature_relevance(feature_id, score)
     }
 }
 
@@ -534,14 +713,169 @@ impl Default for StorageManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::backend::SqliteStorage;
+    use crate::storage::database::Database;
     use crate::storage::models::FeatureType;
 
+    #[test]
+    fn test_remove_mapping_keeps_service_data() {
+        let storage = StorageManager::with_storage(
+            PathBuf::from("mappings.toml"),
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+        );
+
+        storage
+            .add_mapping(
+                "billing".to_string(),
+                DocumentLocation {
+                    r#type: "markdown".to_string(),
+                    location: "docs/billing.md".to_string(),
+                    section: None,
+                },
+            )
+            .unwrap();
+        storage
+            .create_feature(
+                "billing",
+                "Invoices",
+                None,
+                FeatureType::Api,
+                vec![],
+                serde_json::json!({}),
+            )
+            .unwrap();
+
+        storage.remove_mapping("billing").unwrap();
+
+        assert!(storage.get_mapping("billing").unwrap().docs.is_empty());
+        assert_eq!(storage.get_service_features("billing").unwrap().len(), 1);
+        assert!(matches!(
+            storage.remove_mapping("unknown"),
+            Err(KtmeError::MappingNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_rename_service_keeps_features() {
+        let storage = StorageManager::with_storage(
+            PathBuf::from("mappings.toml"),
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+        );
+        for service in ["billing", "payments"] {
+            storage
+                .add_mapping(
+                    service.to_string(),
+                    DocumentLocation {
+                        r#type: "markdown".to_string(),
+                        location: format!("docs/{}.md", service),
+                        section: None,
+                    },
+                )
+                .unwrap();
+        }
+        storage
+            .create_feature(
+                "billing",
+                "Invoices",
+                None,
+                FeatureType::Api,
+                vec![],
+                serde_json::json!({}),
+            )
+            .unwrap();
+
+        storage.rename_service("billing", "invoicing").unwrap();
+        assert_eq!(
+            storage.get_mapping("invoicing").unwrap().docs[0].location,
+            "docs/billing.md"
+        );
+        assert_eq!(storage.get_service_features("invoicing").unwrap().len(), 1);
+        assert!(matches!(
+            storage.get_mapping("billing"),
+            Err(KtmeError::MappingNotFound(_))
+        ));
+        assert!(matches!(
+            storage.rename_service("invoicing", "payments"),
+            Err(KtmeError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_remove_location() {
+        let storage = StorageManager::with_storage(
+            PathBuf::from("mappings.toml"),
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+        );
+        for location in ["docs/billing.md", "docs/invoices.md"] {
+            storage
+                .add_mapping(
+                    "billing".to_string(),
+                    DocumentLocation {
+                        r#type: "markdown".to_string(),
+                        location: location.to_string(),
+                        section: None,
+                    },
+                )
+                .unwrap();
+        }
+
+        assert!(storage
+            .remove_location("billing", "docs/invoices.md")
+            .unwrap());
+        assert!(!storage
+            .remove_location("billing", "docs/invoices.md")
+            .unwrap());
+        let docs = storage.get_mapping("billing").unwrap().docs;
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].location, "docs/billing.md");
+    }
+
+    #[test]
+    fn test_service_settings_roundtrip() {
+        let storage = StorageManager::with_storage(
+            PathBuf::from("mappings.toml"),
+            Box::new(SqliteStorage::new(Database::in_memory().unwrap())),
+        );
+        storage
+            .add_mapping(
+                "billing".to_string(),
+                DocumentLocation {
+                    r#type: "markdown".to_string(),
+                    location: "docs/billing.md".to_string(),
+                    section: None,
+                },
+            )
+            .unwrap();
+
+        storage
+            .set_settings(
+                "billing",
+                &["model=gpt-4o".to_string(), "exclude=*.lock".to_string()],
+            )
+            .unwrap();
+        let settings = storage
+            .set_settings("billing", &["language=German".to_string()])
+            .unwrap();
+        assert_eq!(settings.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(settings.language.as_deref(), Some("German"));
+        assert_eq!(storage.get_mapping("billing").unwrap().settings, settings);
+
+        assert!(matches!(
+            storage.set_settings("billing", &["color=blue".to_string()]),
+            Err(KtmeError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            storage.set_settings("unknown", &["model=gpt-4o".to_string()]),
+            Err(KtmeError::MappingNotFound(_))
+        ));
+    }
+
     #[test]
     fn test_storage_manager_feature_creation() {
         let storage = StorageManager::new().expect("Failed to create StorageManager");
 
-        if !storage.use_sqlite {
-            // Skip test if SQLite is not enabled
+        if !storage.use_database {
+            // Skip test if database storage is not enabled
             return;
         }
 
@@ -566,8 +900,8 @@ mod tests {
     fn test_storage_manager_feature_search() {
         let storage = StorageManager::new().expect("Failed to create StorageManager");
 
-        if !storage.use_sqlite {
-            // Skip test if SQLite is not enabled
+        if !storage.use_database {
+            // Skip test if database storage is not enabled
             return;
         }
 
@@ -597,8 +931,8 @@ mod tests {
     fn test_storage_manager_feature_list() {
         let storage = StorageManager::new().expect("Failed to create StorageManager");
 
-        if !storage.use_sqlite {
-            // Skip test if SQLite is not enabled
+        if !storage.use_database {
+            // Skip test if database storage is not enabled
             return;
         }
 
@@ -0,0 +1,447 @@
+//! Schema migrations of the SQLite database
+//!
+//! Each migration runs in one transaction with its record in
+//! `schema_versions`, so a migration that fails leaves the schema as it was.
+//! The SHA-256 checksum of every applied migration is recorded, and checked
+//! when the database is opened to catch migrations edited after they were
+//! applied. Down migrations revert migrations during development.
+
+use crate::error::{KtmeError, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// An embedded migration and the script reverting it
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+impl Migration {
+    /// SHA-256 of the migration script
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+macro_rules! migration {
+    ($version:expr, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../../migrations/", $name, ".sql")),
+            down: include_str!(concat!("../../migrations/down/", $name, ".sql")),
+        }
+    };
+}
+
+/// Migrations of the SQLite schema, in order
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "001_initial"),
+    migration!(2, "002_features_and_search"),
+    migration!(3, "003_search_index_unique"),
+    // 004 (cloud sync) is not run on SQLite
+    migration!(5, "005_workspaces"),
+    migration!(6, "006_service_settings"),
+    migration!(7, "007_pending_docs"),
+    migration!(8, "008_generation_model"),
+    migration!(9, "009_jobs"),
+    migration!(10, "010_mcp_audit"),
+    migration!(11, "011_idempotency_keys"),
+    migration!(12, "012_feature_usage"),
+    migration!(13, "013_service_detection"),
+    migration!(14, "014_generation_locks"),
+    migration!(15, "015_translations"),
+    migration!(16, "016_workspace_providers_and_cache"),
+];
+
+/// Latest schema version known to this build
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the embedded script has changed since
+    Modified,
+    /// Applied by another build of ktme; this one does not know it
+    Unknown,
+}
+
+impl std::fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            Self::Applied => "applied",
+            Self::Pending => "pending",
+            Self::Modified => "modified",
+            Self::Unknown => "unknown",
+        };
+        f.write_str(state)
+    }
+}
+
+/// One migration as seen by the database
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: Option<String>,
+    pub state: MigrationState,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// Row of `schema_versions`
+struct AppliedMigration {
+    version: i64,
+    checksum: Option<String>,
+    applied_at: Option<DateTime<Utc>>,
+}
+
+/// Apply the pending migrations, returning their versions. Migrations whose
+/// script changed since they were applied are reported as warnings.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<i64>> {
+    prepare(conn)?;
+    let applied = applied(conn)?;
+
+    let mut ran = Vec::new();
+    for migration in MIGRATIONS {
+        if applied.iter().any(|a| a.version == migration.version) {
+            continue;
+        }
+        tracing::info!("Running migration version: {}", migration.version);
+        run_script(conn, migration.version, migration.up, |tx| {
+            tx.execute(
+                "INSERT INTO schema_versions (version, checksum) VALUES (?1, ?2)
+                 ON CONFLICT(version) DO UPDATE SET checksum = excluded.checksum",
+                params![migration.version, migration.checksum()],
+            )
+            .map(|_| ())
+        })?;
+        tracing::debug!("Migration {} completed successfully", migration.version);
+        ran.push(migration.version);
+    }
+
+    for status in status(conn)? {
+        if status.state == MigrationState::Modified {
+            tracing::warn!(
+                "Migration {} changed since it was applied; run `ktme db migrate --status`",
+                status.version
+            );
+        }
+    }
+    Ok(ran)
+}
+
+/// Every known or applied migration, oldest first
+pub fn status(conn: &Connection) -> Result<Vec<MigrationStatus>> {
+    prepare(conn)?;
+    let applied = applied(conn)?;
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| {
+            let record = applied.iter().find(|a| a.version == migration.version);
+            let state = match record {
+                None => MigrationState::Pending,
+                Some(a) if a.checksum.as_deref() == Some(migration.checksum().as_str()) => {
+                    MigrationState::Applied
+                }
+                Some(_) => MigrationState::Modified,
+            };
+            MigrationStatus {
+                version: migration.version,
+                name: Some(migration.name.to_string()),
+                state,
+                applied_at: record.and_then(|a| a.applied_at),
+            }
+        })
+        .collect();
+    statuses.extend(
+        applied
+            .iter()
+            .filter(|a| !MIGRATIONS.iter().any(|m| m.version == a.version))
+            .map(|a| MigrationStatus {
+                version: a.version,
+                name: None,
+                state: MigrationState::Unknown,
+                applied_at: a.applied_at,
+            }),
+    );
+    statuses.sort_by_key(|status| status.version);
+    Ok(statuses)
+}
+
+/// Revert the applied migrations above `target`, newest first, returning
+/// their versions. Meant for development: reverting drops the tables and
+/// columns the migrations added, with their data.
+pub fn rollback(conn: &mut Connection, target: i64) -> Result<Vec<i64>> {
+    if target < 0 {
+        return Err(KtmeError::InvalidInput(format!(
+            "Cannot migrate down to version {}",
+            target
+        )));
+    }
+    prepare(conn)?;
+
+    let mut reverted = Vec::new();
+    for applied in applied(conn)?.iter().rev() {
+        if applied.version <= target {
+            break;
+        }
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.version == applied.version)
+            .ok_or_else(|| {
+                KtmeError::Storage(format!(
+                    "Migration {} was applied by another version of ktme and cannot be reverted",
+                    applied.version
+                ))
+            })?;
+        tracing::info!("Reverting migration version: {}", migration.version);
+        run_script(conn, migration.version, migration.down, |tx| {
+            tx.execute(
+                "DELETE FROM schema_versions WHERE version = ?1",
+                params![migration.version],
+            )
+            .map(|_| ())
+        })?;
+        reverted.push(migration.version);
+    }
+    Ok(reverted)
+}
+
+/// Create `schema_versions`, add the checksum column to databases created
+/// before checksums were recorded, and record the checksums of migrations
+/// applied back then as they are now
+fn prepare(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_versions (
+            version INTEGER PRIMARY KEY,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            checksum TEXT
+        );",
+    )
+    .map_err(|e| KtmeError::Storage(format!("Failed to create schema_versions table: {}", e)))?;
+
+    let has_checksum = conn
+        .query_row(
+            "SELECT 1 FROM pragma_table_info('schema_versions') WHERE name = 'checksum'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| KtmeError::Storage(format!("Failed to read schema_versions: {}", e)))?
+        .is_some();
+    if !has_checksum {
+        conn.execute_batch("ALTER TABLE schema_versions ADD COLUMN checksum TEXT;")
+            .map_err(|e| KtmeError::Storage(format!("Failed to upgrade schema_versions: {}", e)))?;
+    }
+
+    for migration in MIGRATIONS {
+        conn.execute(
+            "UPDATE schema_versions SET checksum = ?2 WHERE version = ?1 AND checksum IS NULL",
+            params![migration.version, migration.checksum()],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to record checksums: {}", e)))?;
+    }
+    Ok(())
+}
+
+fn applied(conn: &Connection) -> Result<Vec<AppliedMigration>> {
+    let read =
+        |e: rusqlite::Error| KtmeError::Storage(format!("Failed to read schema versions: {}", e));
+    let mut stmt = conn
+        .prepare("SELECT version, checksum, applied_at FROM schema_versions ORDER BY version")
+        .map_err(read)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                checksum: row.get(1)?,
+                applied_at: row.get(2).ok(),
+            })
+        })
+        .map_err(read)?;
+    rows.collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(read)
+}
+
+/// Run `sql` and `record` in one transaction. Foreign keys are switched off
+/// meanwhile (SQLite ignores the switch inside a transaction) so rebuilding
+/// a table does not cascade into the tables referencing it; the migration
+/// is rolled back when it leaves more dangling references than it found.
+fn run_script(
+    conn: &mut Connection,
+    version: i64,
+    sql: &str,
+    record: impl FnOnce(&Connection) -> rusqlite::Result<()>,
+) -> Result<()> {
+    let failed =
+        |e: rusqlite::Error| KtmeError::Storage(format!("Migration {} failed: {}", version, e));
+
+    let foreign_keys: bool = conn
+        .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+        .map_err(failed)?;
+    if foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")
+            .map_err(failed)?;
+    }
+
+    let result = (|| {
+        let before = foreign_key_violations(conn).map_err(failed)?;
+        let tx = conn.transaction().map_err(failed)?;
+        tx.execute_batch(sql).map_err(failed)?;
+        record(&tx).map_err(failed)?;
+        let after = foreign_key_violations(&tx).map_err(failed)?;
+        if after > before {
+            return Err(KtmeError::Storage(format!(
+                "Migration {} failed: it leaves {} broken foreign key reference(s)",
+                version,
+                after - before
+            )));
+        }
+        tx.commit().map_err(failed)
+    })();
+
+    if foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(failed)?;
+    }
+    result
+}
+
+fn foreign_key_violations(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| {
+        row.get(0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrate_records_checksums_and_reports_changes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let ran = migrate(&mut conn).unwrap();
+        assert_eq!(ran.len(), MIGRATIONS.len());
+        assert!(migrate(&mut conn).unwrap().is_empty());
+
+        let statuses = status(&conn).unwrap();
+        assert!(statuses.iter().all(|s| s.state == MigrationState::Applied));
+
+        conn.execute(
+            "UPDATE schema_versions SET checksum = 'edited' WHERE version = 6",
+            [],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO schema_versions (version) VALUES (99)", [])
+            .unwrap();
+        let statuses = status(&conn).unwrap();
+        let state = |version| {
+            statuses
+                .iter()
+                .find(|s| s.version == version)
+                .unwrap()
+                .state
+        };
+        assert_eq!(state(6), MigrationState::Modified);
+        assert_eq!(state(99), MigrationState::Unknown);
+        assert_eq!(state(5), MigrationState::Applied);
+    }
+
+    #[test]
+    fn test_failed_migration_leaves_schema_unchanged() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        prepare(&conn).unwrap();
+        let result = run_script(
+            &mut conn,
+            100,
+            "CREATE TABLE half_done (id INTEGER); SELECT * FROM missing_table;",
+            |_| Ok(()),
+        );
+        assert!(result.is_err());
+        assert!(!tables(&conn).contains(&"half_done".to_string()));
+        let foreign_keys: bool = conn
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        assert!(foreign_keys);
+    }
+
+    #[test]
+    fn test_down_migrations_revert_to_each_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO services (name) VALUES ('api');
+             INSERT INTO document_mappings (service_id, provider, location)
+             VALUES (1, 'markdown', 'docs/api.md');",
+        )
+        .unwrap();
+        let full_schema = tables(&conn);
+
+        assert_eq!(
+            rollback(&mut conn, 8).unwrap(),
+            vec![16, 15, 14, 13, 12, 11, 10, 9]
+        );
+        assert!(!tables(&conn).contains(&"jobs".to_string()));
+        assert_eq!(rollback(&mut conn, 0).unwrap(), vec![8, 7, 6, 5, 3, 2, 1]);
+        assert_eq!(tables(&conn), vec!["schema_versions".to_string()]);
+
+        // Every migration applies again on top of its reverted state
+        migrate(&mut conn).unwrap();
+        assert_eq!(tables(&conn), full_schema);
+        let foreign_keys: bool = conn
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        assert!(foreign_keys);
+    }
+
+    #[test]
+    fn test_rollback_keeps_data_of_remaining_tables() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO services (name) VALUES ('api');
+             INSERT INTO document_mappings (service_id, provider, location)
+             VALUES (1, 'markdown', 'docs/api.md');",
+        )
+        .unwrap();
+
+        rollback(&mut conn, 3).unwrap();
+        let mappings: i64 = conn
+            .query_row("SELECT COUNT(*) FROM document_mappings", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(mappings, 1);
+        migrate(&mut conn).unwrap();
+        let workspace: String = conn
+            .query_row(
+                "SELECT workspace FROM services WHERE name = 'api'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(workspace, "default");
+    }
+}
@@ -1,5 +1,12 @@
+pub mod backend;
+pub mod bundle;
 pub mod database;
 pub mod discovery;
+pub mod lock;
 pub mod mapping;
+pub mod migrations;
 pub mod models;
+#[cfg(feature = "postgres")]
+pub mod postgres;
 pub mod repository;
+pub mod rows;
@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Service entity representing a codebase/project
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +13,216 @@ pub struct Service {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Generation defaults stored with a service mapping. Command-line options
+/// override them, and they override the global config.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// AI model, e.g. "gpt-4o"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Language the documentation is written in, e.g. "German"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Prompt pack used instead of the built-in prompts, e.g. "sre"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_pack: Option<String>,
+    /// Mapped locations `update` publishes to, by provider type
+    /// ("markdown", "confluence") or location; all of them when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub publish: Vec<String>,
+    /// Only changes to files matching one of these globs are documented
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Changes to files matching these globs are ignored
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Paths of the git repositories the service spans, e.g. its API and
+    /// infrastructure checkouts; their changes are documented together
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<String>,
+}
+
+impl ServiceSettings {
+    /// Keys accepted by [`ServiceSettings::set`]
+    pub const KEYS: &'static [&'static str] = &[
+        "doc_type",
+        "template",
+        "model",
+        "language",
+        "prompt_pack",
+        "publish",
+        "include",
+        "exclude",
+        "repositories",
+    ];
+
+    /// The global defaults from `[documentation]` and `[ai]`
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        let docs = &config.documentation;
+        Self {
+            doc_type: docs.doc_type.clone(),
+            template: docs.template.clone(),
+            model: config.ai.model.clone(),
+            language: docs.language.clone(),
+            prompt_pack: docs.prompt_pack.clone(),
+            publish: docs.publish.clone(),
+            include: docs.include.clone(),
+            exclude: docs.exclude.clone(),
+            repositories: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Apply a `key=value` assignment; lists are comma-separated and an
+    /// empty value clears the setting
+    pub fn assign(&mut self, assignment: &str) -> crate::error::Result<()> {
+        let (key, value) = assignment.split_once('=').ok_or_else(|| {
+            crate::error::KtmeError::InvalidInput(format!(
+                "Expected key=value, got '{}'",
+                assignment
+            ))
+        })?;
+        self.set(key.trim(), value.trim())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> crate::error::Result<()> {
+        let single = || (!value.is_empty()).then(|| value.to_string());
+        let list = || {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        match key.replace('-', "_").as_str() {
+            "doc_type" | "type" => self.doc_type = single(),
+            "template" => self.template = single(),
+            "model" => self.model = single(),
+            "language" => self.language = single(),
+            "prompt_pack" | "pack" => self.prompt_pack = single(),
+            "publish" => self.publish = list(),
+            "repositories" | "repos" => self.repositories = list(),
+            "include" | "exclude" => {
+                let patterns = list();
+                if let Some(invalid) = patterns.iter().find(|p| glob::Pattern::new(p).is_err()) {
+                    return Err(crate::error::KtmeError::InvalidInput(format!(
+                        "Invalid glob pattern '{}'",
+                        invalid
+                    )));
+                }
+                if key == "include" {
+                    self.include = patterns;
+                } else {
+                    self.exclude = patterns;
+                }
+            }
+            _ => {
+                return Err(crate::error::KtmeError::InvalidInput(format!(
+                    "Unknown service setting '{}'; expected one of: {}",
+                    key,
+                    Self::KEYS.join(", ")
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// These settings with every value set in `other` taking precedence
+    pub fn overlay(mut self, other: &ServiceSettings) -> Self {
+        fn pick<T: Clone>(own: &mut T, other: &T, is_set: bool) {
+            if is_set {
+                *own = other.clone();
+            }
+        }
+        pick(
+            &mut self.doc_type,
+            &other.doc_type,
+            other.doc_type.is_some(),
+        );
+        pick(
+            &mut self.template,
+            &other.template,
+            other.template.is_some(),
+        );
+        pick(&mut self.model, &other.model, other.model.is_some());
+        pick(
+            &mut self.language,
+            &other.language,
+            other.language.is_some(),
+        );
+        pick(
+            &mut self.prompt_pack,
+            &other.prompt_pack,
+            other.prompt_pack.is_some(),
+        );
+        pick(&mut self.publish, &other.publish, !other.publish.is_empty());
+        pick(&mut self.include, &other.include, !other.include.is_empty());
+        pick(&mut self.exclude, &other.exclude, !other.exclude.is_empty());
+        pick(
+            &mut self.repositories,
+            &other.repositories,
+            !other.repositories.is_empty(),
+        );
+        self
+    }
+
+    /// Whether changes to `path` are documented under the include and
+    /// exclude globs
+    pub fn covers(&self, path: &str) -> bool {
+        let matches = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|p| glob::Pattern::new(p).ok())
+                .any(|p| p.matches(path))
+        };
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+
+    /// Whether `update` publishes to a location of `provider` at `location`
+    pub fn publishes_to(&self, provider: &str, location: &str) -> bool {
+        self.publish.is_empty()
+            || self
+                .publish
+                .iter()
+                .any(|target| target == provider || target == location)
+    }
+
+    /// `key = value` lines for display
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (key, value) in [
+            ("doc_type", &self.doc_type),
+            ("template", &self.template),
+            ("model", &self.model),
+            ("language", &self.language),
+            ("prompt_pack", &self.prompt_pack),
+        ] {
+            if let Some(value) = value {
+                lines.push(format!("{} = {}", key, value));
+            }
+        }
+        for (key, values) in [
+            ("publish", &self.publish),
+            ("include", &self.include),
+            ("exclude", &self.exclude),
+            ("repositories", &self.repositories),
+        ] {
+            if !values.is_empty() {
+                lines.push(format!("{} = {}", key, values.join(", ")));
+            }
+        }
+        lines
+    }
+}
+
 /// Document mapping linking a service to a documentation location
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMapping {
@@ -55,7 +266,9 @@ pub struct PromptTemplate {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptVariable {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub required: bool,
     #[serde(default)]
     pub default: Option<String>,
@@ -89,6 +302,79 @@ pub struct GenerationRecord {
     pub status: String,
     pub error_message: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// AI model that generated the content
+    pub model: Option<String>,
+    /// Canonical key of the generation; see [`GenerationKey`](crate::doc::idempotency::GenerationKey)
+    pub idempotency_key: Option<String>,
+}
+
+/// Generated documentation waiting in the review queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDoc {
+    pub id: i64,
+    pub service: String,
+    pub doc_type: String,
+    pub source_identifier: Option<String>,
+    /// Output file; `None` publishes to the service's mapped locations
+    pub target: Option<String>,
+    pub content: String,
+    /// "pending", "approved" or "rejected"
+    pub status: String,
+    pub reviewer: Option<String>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+/// An MCP tool call recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: i64,
+    pub tool: String,
+    /// Arguments as JSON, long values cut
+    pub arguments: String,
+    /// "stdio", "sse" or "http"
+    pub transport: String,
+    pub duration_ms: i64,
+    /// "success", "error" or "denied"
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A multi-step operation that can be resumed after a failure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    /// Operation: "backfill" or "generate"
+    pub kind: String,
+    pub description: String,
+    /// Arguments of the operation as JSON
+    pub params: String,
+    /// "running", "failed" or "completed"
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub steps: Vec<JobStep>,
+}
+
+impl Job {
+    /// Steps still to run, in order
+    pub fn remaining_steps(&self) -> impl Iterator<Item = &JobStep> {
+        self.steps.iter().filter(|step| step.status != "done")
+    }
+}
+
+/// One step of a job, e.g. one commit of a backfill
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStep {
+    pub position: i64,
+    pub name: String,
+    /// "pending", "done" or "failed"
+    pub status: String,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
 }
 
 /// Cached diff data
@@ -103,6 +389,48 @@ pub struct DiffCache {
     pub created_at: DateTime<Utc>,
 }
 
+/// Service detected for a directory, or pinned to a repository with
+/// `ktme service pin`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceDetection {
+    pub id: i64,
+    pub directory: String,
+    pub service_name: String,
+    pub pinned: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Advisory lock of a service held by a running generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationLock {
+    pub id: i64,
+    pub resource: String,
+    pub owner: String,
+    pub pid: i64,
+    pub purpose: String,
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Translated counterpart of a mapped document, added with
+/// `ktme mapping add --translation-of`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Translation {
+    pub id: i64,
+    pub service_name: String,
+    /// Mapped location of the primary document
+    pub source: String,
+    pub target_type: String,
+    pub target: String,
+    pub language: String,
+    /// Translated sections by the hash of their source text
+    #[serde(skip)]
+    pub sections: BTreeMap<String, String>,
+    pub synced_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Confluence provider configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfluenceConfig {
@@ -255,6 +583,23 @@ impl std::fmt::Display for FeatureType {
     }
 }
 
+impl From<&str> for FeatureType {
+    fn from(s: &str) -> Self {
+        match s {
+            "api" => Self::Api,
+            "ui" => Self::Ui,
+            "business_logic" => Self::BusinessLogic,
+            "config" => Self::Config,
+            "database" => Self::Database,
+            "security" => Self::Security,
+            "performance" => Self::Performance,
+            "testing" => Self::Testing,
+            "deployment" => Self::Deployment,
+            _ => Self::Other,
+        }
+    }
+}
+
 /// Relationship between features
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FeatureRelation {
@@ -267,6 +612,18 @@ pub struct FeatureRelation {
     pub created_at: DateTime<Utc>,
 }
 
+/// Retrievals of a feature since its relevance score was last ranked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureUsage {
+    pub feature_id: String,
+    pub relevance_score: f64,
+    /// Retrievals by searches and MCP tools since `ranked_since`
+    pub usage_count: i64,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When the score was last ranked, or the feature created
+    pub ranked_since: DateTime<Utc>,
+}
+
 /// Type of relationship between features
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -296,6 +653,38 @@ impl std::fmt::Display for RelationType {
     }
 }
 
+impl RelationType {
+    pub const ALL: [Self; 8] = [
+        Self::DependsOn,
+        Self::Implements,
+        Self::Extends,
+        Self::Uses,
+        Self::Configures,
+        Self::Tests,
+        Self::Deploys,
+        Self::Other,
+    ];
+}
+
+impl std::str::FromStr for RelationType {
+    type Err = crate::error::KtmeError;
+
+    /// Relation type from its name, with `-` or `_` between words
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase().replace('-', "_");
+        Self::ALL
+            .into_iter()
+            .find(|relation| relation.to_string() == name)
+            .ok_or_else(|| {
+                crate::error::KtmeError::InvalidInput(format!(
+                    "Unknown relation type '{}' (expected one of: {})",
+                    s,
+                    Self::ALL.map(|relation| relation.to_string()).join(", ")
+                ))
+            })
+    }
+}
+
 /// Search index entry for semantic search
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchIndex {
@@ -334,6 +723,20 @@ impl std::fmt::Display for SearchContentType {
     }
 }
 
+impl From<&str> for SearchContentType {
+    fn from(s: &str) -> Self {
+        match s {
+            "feature_name" => Self::FeatureName,
+            "feature_description" => Self::FeatureDescription,
+            "documentation" => Self::Documentation,
+            "code_example" => Self::CodeExample,
+            "api_reference" => Self::ApiReference,
+            "user_guide" => Self::UserGuide,
+            _ => Self::Other,
+        }
+    }
+}
+
 /// Search result with relevance scoring
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
@@ -347,6 +750,11 @@ pub struct SearchResult {
     pub content_type: SearchContentType,
     pub path: Option<String>,
     pub tags: Vec<String>,
+    /// How a feature that does not match the query itself is connected to
+    /// one that does, e.g. `Checkout depends_on Payments`, when the query
+    /// includes related features
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_via: Option<String>,
 }
 
 /// Knowledge graph node
@@ -392,6 +800,12 @@ pub struct SearchQuery {
     pub feature_types: Option<Vec<FeatureType>>,
     pub content_types: Option<Vec<SearchContentType>>,
     pub limit: Option<u32>,
+    /// Matches to skip before the first result, for paging
+    #[serde(default)]
+    pub offset: Option<u32>,
+    /// Lowest relevance score of a match
+    #[serde(default)]
+    pub min_score: Option<f64>,
     pub similarity_threshold: Option<f64>,
     pub include_related: bool,
     pub depth: Option<u32>,
@@ -575,3 +989,35 @@ pub struct SyncSummary {
     pub earliest_sync: Option<DateTime<Utc>>,
     pub latest_sync: Option<DateTime<Utc>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_settings_precedence_and_globs() {
+        let mut global = ServiceSettings::default();
+        global.assign("model=gpt-4o").unwrap();
+        global.assign("language=English").unwrap();
+        let mut service = ServiceSettings::default();
+        service.assign("language=German").unwrap();
+        service.assign("include=src/**, api/**").unwrap();
+        service.assign("exclude=**/*_test.rs").unwrap();
+        let mut cli = ServiceSettings::default();
+        cli.assign("doc-type=api-doc").unwrap();
+
+        let effective = global.overlay(&service).overlay(&cli);
+        assert_eq!(effective.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(effective.language.as_deref(), Some("German"));
+        assert_eq!(effective.doc_type.as_deref(), Some("api-doc"));
+
+        assert!(effective.covers("src/billing/invoice.rs"));
+        assert!(!effective.covers("src/billing/invoice_test.rs"));
+        assert!(!effective.covers("README.md"));
+
+        service.assign("include=").unwrap();
+        assert!(service.include.is_empty());
+        assert!(service.assign("exclude=[").is_err());
+        assert!(service.assign("model").is_err());
+    }
+}
@@ -0,0 +1,1900 @@
+use crate::error::{KtmeError, Result};
+use crate::storage::backend::{with_related, Storage};
+use crate::storage::database::DatabaseStats;
+use crate::storage::models::{
+    DiffCache, DocumentMapping, Feature, FeatureRelation, FeatureType, FeatureUsage,
+    GenerationLock, GenerationRecord, Job, JobStep, PendingDoc, ProviderConfig, RelationType,
+    SearchContentType, SearchQuery, SearchResult, Service, ServiceDetection, ServiceSettings,
+    ToolCall, Translation,
+};
+use chrono::{DateTime, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
+use sqlx::Row;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
+
+/// Postgres migrations, numbered to match the SQLite versions in `migrations/`
+const MIGRATIONS: &[(i64, &str)] = &[
+    (1, include_str!("../../migrations/postgres/001_initial.sql")),
+    (
+        2,
+        include_str!("../../migrations/postgres/002_features_and_search.sql"),
+    ),
+    (
+        3,
+        include_str!("../../migrations/postgres/003_search_index_unique.sql"),
+    ),
+    (
+        4,
+        include_str!("../../migrations/postgres/004_cloud_sync_status.sql"),
+    ),
+    (
+        5,
+        include_str!("../../migrations/postgres/005_workspaces.sql"),
+    ),
+    (
+        6,
+        include_str!("../../migrations/postgres/006_service_settings.sql"),
+    ),
+    (
+        7,
+        include_str!("../../migrations/postgres/007_pending_docs.sql"),
+    ),
+    (
+        8,
+        include_str!("../../migrations/postgres/008_generation_model.sql"),
+    ),
+    (9, include_str!("../../migrations/postgres/009_jobs.sql")),
+    (
+        10,
+        include_str!("../../migrations/postgres/010_mcp_audit.sql"),
+    ),
+    (
+        11,
+        include_str!("../../migrations/postgres/011_idempotency_keys.sql"),
+    ),
+    (
+        12,
+        include_str!("../../migrations/postgres/012_feature_usage.sql"),
+    ),
+    (
+        13,
+        include_str!("../../migrations/postgres/013_service_detection.sql"),
+    ),
+    (
+        14,
+        include_str!("../../migrations/postgres/014_generation_locks.sql"),
+    ),
+    (
+        15,
+        include_str!("../../migrations/postgres/015_translations.sql"),
+    ),
+    (
+        16,
+        include_str!("../../migrations/postgres/016_workspace_providers_and_cache.sql"),
+    ),
+];
+
+/// Advisory lock key serializing migrations across concurrent ktme processes
+const MIGRATION_LOCK_KEY: i64 = 0x6b74_6d65;
+
+const SERVICE_COLUMNS: &str = "id, name, path, description, created_at, updated_at";
+const MAPPING_COLUMNS: &str =
+    "id, service_id, provider, location, title, section, is_primary, created_at, updated_at";
+const FEATURE_COLUMNS: &str = "id, service_id, name, description, feature_type, tags, metadata, relevance_score, created_at, updated_at";
+const RELATION_COLUMNS: &str =
+    "id, parent_feature_id, child_feature_id, relation_type, strength, metadata, created_at";
+const HISTORY_COLUMNS: &str = "id, service_id, provider, document_id, document_url, action, source_type, source_identifier, content_hash, status, error_message, created_at, model, idempotency_key";
+const PENDING_DOC_COLUMNS: &str = "id, service, doc_type, source_identifier, target, content, status, reviewer, note, created_at, reviewed_at";
+const JOB_COLUMNS: &str = "id, kind, description, params, status, error, created_at, updated_at";
+const JOB_STEP_COLUMNS: &str = "position, name, status, error, updated_at";
+const TOOL_CALL_COLUMNS: &str =
+    "id, tool, arguments, transport, duration_ms, status, error, created_at";
+const DETECTION_COLUMNS: &str = "id, directory, service_name, pinned, created_at, updated_at";
+const TRANSLATION_COLUMNS: &str =
+    "id, service_name, source, target_type, target, language, sections, synced_at, created_at";
+const DIFF_CACHE_COLUMNS: &str =
+    "id, source_type, source_identifier, repository_path, diff_json, expires_at, created_at";
+const PROVIDER_COLUMNS: &str = "id, provider_type, config_json, is_default, created_at, updated_at";
+const LOCK_COLUMNS: &str = "id, resource, owner, pid, purpose, acquired_at, expires_at";
+
+/// Postgres storage backend for sharing one knowledge base across a team.
+///
+/// The `Storage` trait is synchronous like the SQLite repositories, so queries
+/// run on a runtime owned by this backend.
+pub struct PostgresStorage {
+    pool: PgPool,
+    runtime: Option<Runtime>,
+    url: String,
+    workspace: String,
+}
+
+impl PostgresStorage {
+    /// Connect to the database and apply pending migrations. Services and
+    /// history are scoped to `workspace`.
+    pub fn connect(url: &str, workspace: &str) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| KtmeError::Storage(format!("Failed to create runtime: {}", e)))?;
+
+        // The pool is created lazily so that connecting runs on the backend
+        // runtime through `run`, like every other query
+        let pool = {
+            let _guard = runtime.enter();
+            PgPoolOptions::new()
+                .max_connections(5)
+                .connect_lazy(url)
+                .map_err(|e| KtmeError::Storage(format!("Invalid Postgres URL: {}", e)))?
+        };
+
+        let storage = Self {
+            pool,
+            runtime: Some(runtime),
+            url: url.to_string(),
+            workspace: workspace.to_string(),
+        };
+        storage
+            .block_on(storage.pool.acquire())?
+            .map_err(|e| KtmeError::Storage(format!("Failed to connect to Postgres: {}", e)))?;
+        storage.migrate()?;
+        Ok(storage)
+    }
+
+    /// Run a future to completion on the backend runtime.
+    fn block_on<F>(&self, fut: F) -> Result<F::Output>
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        self.run(move || fut)
+    }
+
+    /// Build a future and run it to completion on the backend runtime.
+    ///
+    /// Inside a multi-threaded tokio runtime (the CLI and MCP handlers) the
+    /// query runs through `block_in_place`, so the calling worker hands its
+    /// other tasks off instead of being starved. A current-thread runtime
+    /// cannot do that, so there the query falls back to a scoped thread.
+    /// Building the future where it is polled means it does not need to be
+    /// `Send`, which sqlx transaction futures are not always provably.
+    fn run<M, F>(&self, make: M) -> Result<F::Output>
+    where
+        M: FnOnce() -> F + Send,
+        F: Future,
+        F::Output: Send,
+    {
+        let runtime = self
+            .runtime
+            .as_ref()
+            .ok_or_else(|| KtmeError::Storage("Postgres runtime is shut down".to_string()))?;
+
+        match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+            Ok(RuntimeFlavor::MultiThread) => {
+                Ok(tokio::task::block_in_place(|| runtime.block_on(make())))
+            }
+            Ok(_) => std::thread::scope(|scope| scope.spawn(|| runtime.block_on(make())).join())
+                .map_err(|_| KtmeError::Storage("Postgres query thread panicked".to_string())),
+            Err(_) => Ok(runtime.block_on(make())),
+        }
+    }
+
+    /// Run database migrations.
+    ///
+    /// Each migration runs in its own transaction behind an advisory lock, so a
+    /// failed migration leaves no partial schema and concurrent processes apply
+    /// it only once.
+    pub fn migrate(&self) -> Result<()> {
+        self.run(|| apply_migrations(&self.pool))?
+    }
+
+    /// Connection URL with the password removed, for display
+    fn redacted_url(&self) -> String {
+        match (self.url.find("://"), self.url.rfind('@')) {
+            (Some(scheme), Some(at)) if at > scheme => {
+                format!("{}{}", &self.url[..scheme + 3], &self.url[at + 1..])
+            }
+            _ => self.url.clone(),
+        }
+    }
+}
+
+impl Drop for PostgresStorage {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which is not allowed inside another runtime
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+async fn apply_migrations(pool: &PgPool) -> Result<()> {
+    for (version, sql) in MIGRATIONS {
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| storage_err("begin migration transaction", e))?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(MIGRATION_LOCK_KEY)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| storage_err("acquire migration lock", e))?;
+
+        // schema_versions itself is created by migration 1
+        let has_versions: bool =
+            sqlx::query_scalar("SELECT to_regclass('schema_versions') IS NOT NULL")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| storage_err("read schema version", e))?;
+        let current_version: i64 = if has_versions {
+            sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_versions")
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| storage_err("read schema version", e))?
+        } else {
+            0
+        };
+
+        if *version <= current_version {
+            continue;
+        }
+
+        tracing::info!("Running Postgres migration version: {}", version);
+        sqlx::raw_sql(sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| KtmeError::Storage(format!("Migration {} failed: {}", version, e)))?;
+
+        tx.commit().await.map_err(|e| {
+            KtmeError::Storage(format!("Failed to commit migration {}: {}", version, e))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Escape LIKE wildcards so user input matches literally (used with `ESCAPE '\'`)
+fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn storage_err(action: &str, e: sqlx::Error) -> KtmeError {
+    KtmeError::Storage(format!("Failed to {}: {}", action, e))
+}
+
+fn row_to_service(row: &PgRow) -> std::result::Result<Service, sqlx::Error> {
+    Ok(Service {
+        id: row.try_get(0)?,
+        name: row.try_get(1)?,
+        path: row.try_get(2)?,
+        description: row.try_get(3)?,
+        created_at: row.try_get(4)?,
+        updated_at: row.try_get(5)?,
+    })
+}
+
+fn row_to_mapping(row: &PgRow) -> std::result::Result<DocumentMapping, sqlx::Error> {
+    Ok(DocumentMapping {
+        id: row.try_get(0)?,
+        service_id: row.try_get(1)?,
+        provider: row.try_get(2)?,
+        location: row.try_get(3)?,
+        title: row.try_get(4)?,
+        section: row.try_get(5)?,
+        is_primary: row.try_get(6)?,
+        created_at: row.try_get(7)?,
+        updated_at: row.try_get(8)?,
+    })
+}
+
+fn row_to_feature(row: &PgRow) -> std::result::Result<Feature, sqlx::Error> {
+    let tags_json: Option<String> = row.try_get(5)?;
+    let metadata_json: Option<String> = row.try_get(6)?;
+    let feature_type: String = row.try_get(4)?;
+
+    Ok(Feature {
+        id: row.try_get(0)?,
+        service_id: row.try_get(1)?,
+        name: row.try_get(2)?,
+        description: row.try_get(3)?,
+        feature_type: FeatureType::from(feature_type.as_str()),
+        tags: tags_json
+            .and_then(|t| serde_json::from_str(&t).ok())
+            .unwrap_or_default(),
+        metadata: metadata_json
+            .and_then(|m| serde_json::from_str(&m).ok())
+            .unwrap_or_default(),
+        relevance_score: row.try_get(7)?,
+        embedding: None,
+        created_at: row.try_get(8)?,
+        updated_at: row.try_get(9)?,
+    })
+}
+
+fn row_to_record(row: &PgRow) -> std::result::Result<GenerationRecord, sqlx::Error> {
+    Ok(GenerationRecord {
+        id: row.try_get(0)?,
+        service_id: row.try_get(1)?,
+        provider: row.try_get(2)?,
+        document_id: row.try_get(3)?,
+        document_url: row.try_get(4)?,
+        action: row.try_get(5)?,
+        source_type: row.try_get(6)?,
+        source_identifier: row.try_get(7)?,
+        content_hash: row.try_get(8)?,
+        status: row.try_get(9)?,
+        error_message: row.try_get(10)?,
+        created_at: row.try_get(11)?,
+        model: row.try_get(12)?,
+        idempotency_key: row.try_get(13)?,
+    })
+}
+
+fn row_to_pending_doc(row: &PgRow) -> std::result::Result<PendingDoc, sqlx::Error> {
+    Ok(PendingDoc {
+        id: row.try_get(0)?,
+        service: row.try_get(1)?,
+        doc_type: row.try_get(2)?,
+        source_identifier: row.try_get(3)?,
+        target: row.try_get(4)?,
+        content: row.try_get(5)?,
+        status: row.try_get(6)?,
+        reviewer: row.try_get(7)?,
+        note: row.try_get(8)?,
+        created_at: row.try_get(9)?,
+        reviewed_at: row.try_get(10)?,
+    })
+}
+
+fn row_to_job(row: &PgRow) -> std::result::Result<Job, sqlx::Error> {
+    Ok(Job {
+        id: row.try_get(0)?,
+        kind: row.try_get(1)?,
+        description: row.try_get(2)?,
+        params: row.try_get(3)?,
+        status: row.try_get(4)?,
+        error: row.try_get(5)?,
+        created_at: row.try_get(6)?,
+        updated_at: row.try_get(7)?,
+        steps: Vec::new(),
+    })
+}
+
+fn row_to_job_step(row: &PgRow) -> std::result::Result<JobStep, sqlx::Error> {
+    Ok(JobStep {
+        position: row.try_get(0)?,
+        name: row.try_get(1)?,
+        status: row.try_get(2)?,
+        error: row.try_get(3)?,
+        updated_at: row.try_get(4)?,
+    })
+}
+
+fn row_to_tool_call(row: &PgRow) -> std::result::Result<ToolCall, sqlx::Error> {
+    Ok(ToolCall {
+        id: row.try_get(0)?,
+        tool: row.try_get(1)?,
+        arguments: row.try_get(2)?,
+        transport: row.try_get(3)?,
+        duration_ms: row.try_get(4)?,
+        status: row.try_get(5)?,
+        error: row.try_get(6)?,
+        created_at: row.try_get(7)?,
+    })
+}
+
+fn row_to_detection(row: &PgRow) -> std::result::Result<ServiceDetection, sqlx::Error> {
+    Ok(ServiceDetection {
+        id: row.try_get(0)?,
+        directory: row.try_get(1)?,
+        service_name: row.try_get(2)?,
+        pinned: row.try_get(3)?,
+        created_at: row.try_get(4)?,
+        updated_at: row.try_get(5)?,
+    })
+}
+
+fn row_to_translation(row: &PgRow) -> std::result::Result<Translation, sqlx::Error> {
+    let sections: String = row.try_get(6)?;
+    Ok(Translation {
+        id: row.try_get(0)?,
+        service_name: row.try_get(1)?,
+        source: row.try_get(2)?,
+        target_type: row.try_get(3)?,
+        target: row.try_get(4)?,
+        language: row.try_get(5)?,
+        sections: serde_json::from_str(&sections).unwrap_or_default(),
+        synced_at: row.try_get(7)?,
+        created_at: row.try_get(8)?,
+    })
+}
+
+fn row_to_cached_diff(row: &PgRow) -> std::result::Result<DiffCache, sqlx::Error> {
+    Ok(DiffCache {
+        id: row.try_get(0)?,
+        source_type: row.try_get(1)?,
+        source_identifier: row.try_get(2)?,
+        repository_path: row.try_get(3)?,
+        diff_json: row.try_get(4)?,
+        expires_at: row.try_get(5)?,
+        created_at: row.try_get(6)?,
+    })
+}
+
+fn row_to_provider(row: &PgRow) -> std::result::Result<ProviderConfig, sqlx::Error> {
+    let config_json: String = row.try_get(2)?;
+    Ok(ProviderConfig {
+        id: row.try_get(0)?,
+        provider_type: row.try_get(1)?,
+        config: serde_json::from_str(&config_json).unwrap_or_default(),
+        is_default: row.try_get(3)?,
+        created_at: row.try_get(4)?,
+        updated_at: row.try_get(5)?,
+    })
+}
+
+fn row_to_lock(row: &PgRow) -> std::result::Result<GenerationLock, sqlx::Error> {
+    Ok(GenerationLock {
+        id: row.try_get(0)?,
+        resource: row.try_get(1)?,
+        owner: row.try_get(2)?,
+        pid: row.try_get(3)?,
+        purpose: row.try_get(4)?,
+        acquired_at: row.try_get(5)?,
+        expires_at: row.try_get(6)?,
+    })
+}
+
+fn row_to_search_result(row: &PgRow) -> std::result::Result<SearchResult, sqlx::Error> {
+    let tags_json: Option<String> = row.try_get(9)?;
+    let feature_type: String = row.try_get(3)?;
+    let content_type: String = row.try_get(7)?;
+
+    Ok(SearchResult {
+        feature_id: row.try_get(0)?,
+        service_name: row.try_get(1)?,
+        feature_name: row.try_get(2)?,
+        feature_type: FeatureType::from(feature_type.as_str()),
+        description: row.try_get(4)?,
+        content: row.try_get(5)?,
+        relevance_score: row.try_get(6)?,
+        content_type: SearchContentType::from(content_type.as_str()),
+        path: row.try_get(8)?,
+        tags: tags_json
+            .and_then(|t| serde_json::from_str(&t).ok())
+            .unwrap_or_default(),
+        related_via: None,
+    })
+}
+
+fn row_to_relation(row: &PgRow) -> std::result::Result<FeatureRelation, sqlx::Error> {
+    let relation_type: String = row.try_get(3)?;
+    let metadata_json: Option<String> = row.try_get(5)?;
+
+    Ok(FeatureRelation {
+        id: row.try_get(0)?,
+        parent_feature_id: row.try_get(1)?,
+        child_feature_id: row.try_get(2)?,
+        relation_type: relation_type.parse().unwrap_or(RelationType::Other),
+        strength: row.try_get(4)?,
+        metadata: metadata_json
+            .and_then(|m| serde_json::from_str(&m).ok())
+            .unwrap_or_default(),
+        created_at: row.try_get(6)?,
+    })
+}
+
+impl Storage for PostgresStorage {
+    fn backend_name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn health_check(&self) -> Result<bool> {
+        self.block_on(async {
+            sqlx::query("SELECT 1")
+                .execute(&self.pool)
+                .await
+                .map_err(|e| storage_err("run health check", e))?;
+            Ok(true)
+        })?
+    }
+
+    fn stats(&self) -> Result<DatabaseStats> {
+        let (services, mappings, history, providers, features) = self.block_on(async {
+            sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(
+                "SELECT
+                    (SELECT COUNT(*) FROM services WHERE workspace = $1),
+                    (SELECT COUNT(*) FROM document_mappings dm
+                     JOIN services s ON dm.service_id = s.id WHERE s.workspace = $1),
+                    (SELECT COUNT(*) FROM generation_history WHERE workspace = $1),
+                    (SELECT COUNT(*) FROM provider_configs WHERE workspace = $1),
+                    (SELECT COUNT(*) FROM features f
+                     JOIN services s ON f.service_id = s.id WHERE s.workspace = $1)",
+            )
+            .bind(&self.workspace)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| storage_err("read stats", e))
+        })??;
+
+        Ok(DatabaseStats {
+            path: PathBuf::from(self.redacted_url()),
+            service_count: services as u64,
+            mapping_count: mappings as u64,
+            history_count: history as u64,
+            provider_count: providers as u64,
+            feature_count: features as u64,
+        })
+    }
+
+    fn create_service(
+        &self,
+        name: &str,
+        path: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<Service> {
+        let sql = format!(
+            "INSERT INTO services (workspace, name, path, description) VALUES ($1, $2, $3, $4) RETURNING {}",
+            SERVICE_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(name)
+                    .bind(path)
+                    .bind(description)
+                    .fetch_one(&self.pool),
+            )?
+            .map_err(|e| storage_err("create service", e))?;
+
+        row_to_service(&row).map_err(|e| storage_err("read created service", e))
+    }
+
+    fn get_service_by_name(&self, name: &str) -> Result<Option<Service>> {
+        let sql = format!(
+            "SELECT {} FROM services WHERE workspace = $1 AND name = $2",
+            SERVICE_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(name)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get service", e))?;
+
+        row.as_ref()
+            .map(row_to_service)
+            .transpose()
+            .map_err(|e| storage_err("get service", e))
+    }
+
+    fn list_services(&self) -> Result<Vec<Service>> {
+        let sql = format!(
+            "SELECT {} FROM services WHERE workspace = $1 ORDER BY name",
+            SERVICE_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query services", e))?;
+
+        rows.iter()
+            .map(row_to_service)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect services", e))
+    }
+
+    fn delete_service(&self, name: &str) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query("DELETE FROM services WHERE workspace = $1 AND name = $2")
+                    .bind(&self.workspace)
+                    .bind(name)
+                    .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("delete service", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn rename_service(&self, old: &str, new: &str) -> Result<bool> {
+        let pool = self.pool.clone();
+        let workspace = self.workspace.clone();
+        let (old, new) = (old.to_string(), new.to_string());
+        self.block_on(async move {
+            let mut tx = pool.begin().await?;
+            let renamed = sqlx::query(
+                "UPDATE services SET name = $1, updated_at = NOW()
+                 WHERE workspace = $2 AND name = $3",
+            )
+            .bind(&new)
+            .bind(&workspace)
+            .bind(&old)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            sqlx::query(
+                "UPDATE pending_docs SET service = $1 WHERE workspace = $2 AND service = $3",
+            )
+            .bind(&new)
+            .bind(&workspace)
+            .bind(&old)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(renamed > 0)
+        })?
+        .map_err(|e| storage_err("rename service", e))
+    }
+
+    fn service_settings(&self, service_id: i64) -> Result<ServiceSettings> {
+        let settings: Option<Option<String>> = self
+            .block_on(
+                sqlx::query_scalar("SELECT settings FROM services WHERE id = $1")
+                    .bind(service_id)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get service settings", e))?;
+
+        match settings.flatten() {
+            Some(json) => serde_json::from_str(&json).map_err(|e| {
+                KtmeError::Storage(format!(
+                    "Invalid settings for service {}: {}",
+                    service_id, e
+                ))
+            }),
+            None => Ok(ServiceSettings::default()),
+        }
+    }
+
+    fn set_service_settings(&self, service_id: i64, settings: &ServiceSettings) -> Result<()> {
+        let json = (!settings.is_empty())
+            .then(|| serde_json::to_string(settings))
+            .transpose()?;
+
+        self.block_on(
+            sqlx::query("UPDATE services SET settings = $1, updated_at = NOW() WHERE id = $2")
+                .bind(json)
+                .bind(service_id)
+                .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("update service settings", e))?;
+
+        Ok(())
+    }
+
+    fn add_mapping(
+        &self,
+        service_id: i64,
+        provider: &str,
+        location: &str,
+        title: Option<&str>,
+        section: Option<&str>,
+        is_primary: bool,
+    ) -> Result<DocumentMapping> {
+        let sql = format!(
+            "INSERT INTO document_mappings (service_id, provider, location, title, section, is_primary)
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+            MAPPING_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(service_id)
+                    .bind(provider)
+                    .bind(location)
+                    .bind(title)
+                    .bind(section)
+                    .bind(is_primary)
+                    .fetch_one(&self.pool),
+            )?
+            .map_err(|e| storage_err("add mapping", e))?;
+
+        row_to_mapping(&row).map_err(|e| storage_err("read created mapping", e))
+    }
+
+    fn mappings_for_service(&self, service_id: i64) -> Result<Vec<DocumentMapping>> {
+        let sql = format!(
+            "SELECT {} FROM document_mappings WHERE service_id = $1 ORDER BY is_primary DESC, provider",
+            MAPPING_COLUMNS
+        );
+        let rows = self
+            .block_on(sqlx::query(&sql).bind(service_id).fetch_all(&self.pool))?
+            .map_err(|e| storage_err("query mappings", e))?;
+
+        rows.iter()
+            .map(row_to_mapping)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect mappings", e))
+    }
+
+    fn delete_mapping(&self, id: i64) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query("DELETE FROM document_mappings WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("delete mapping", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn create_feature(
+        &self,
+        id: &str,
+        service_id: i64,
+        name: &str,
+        description: Option<&str>,
+        feature_type: FeatureType,
+        tags: Vec<String>,
+        metadata: serde_json::Value,
+    ) -> Result<Feature> {
+        let tags_json = serde_json::to_string(&tags).map_err(KtmeError::Serialization)?;
+        let metadata_json = serde_json::to_string(&metadata).map_err(KtmeError::Serialization)?;
+
+        let sql = format!(
+            "INSERT INTO features (id, service_id, name, description, feature_type, tags, metadata)
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {}",
+            FEATURE_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(id)
+                    .bind(service_id)
+                    .bind(name)
+                    .bind(description)
+                    .bind(feature_type.to_string())
+                    .bind(tags_json)
+                    .bind(metadata_json)
+                    .fetch_one(&self.pool),
+            )?
+            .map_err(|e| storage_err("create feature", e))?;
+
+        row_to_feature(&row).map_err(|e| storage_err("read created feature", e))
+    }
+
+    fn get_feature(&self, id: &str) -> Result<Option<Feature>> {
+        let sql = format!("SELECT {} FROM features WHERE id = $1", FEATURE_COLUMNS);
+        let row = self
+            .block_on(sqlx::query(&sql).bind(id).fetch_optional(&self.pool))?
+            .map_err(|e| storage_err("get feature", e))?;
+
+        row.as_ref()
+            .map(row_to_feature)
+            .transpose()
+            .map_err(|e| storage_err("get feature", e))
+    }
+
+    fn features_for_service(&self, service_id: i64) -> Result<Vec<Feature>> {
+        let sql = format!(
+            "SELECT {} FROM features WHERE service_id = $1 ORDER BY name",
+            FEATURE_COLUMNS
+        );
+        let rows = self
+            .block_on(sqlx::query(&sql).bind(service_id).fetch_all(&self.pool))?
+            .map_err(|e| storage_err("query features", e))?;
+
+        rows.iter()
+            .map(row_to_feature)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect features", e))
+    }
+
+    fn search_features(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        let sql = "
+            SELECT DISTINCT
+                f.id,
+                s.name,
+                f.name,
+                f.feature_type,
+                f.description,
+                COALESCE(si.content, f.description, f.name),
+                f.relevance_score,
+                COALESCE(si.content_type, 'feature_name'),
+                dm.location,
+                f.tags
+            FROM features f
+            JOIN services s ON f.service_id = s.id
+            LEFT JOIN search_index si ON f.id = si.feature_id
+            LEFT JOIN document_mappings dm ON f.id = dm.feature_id
+            WHERE s.workspace = $5
+              AND ($1::BIGINT[] IS NULL OR f.service_id = ANY($1))
+              AND ($2::TEXT[] IS NULL OR f.feature_type = ANY($2))
+              AND ($3::TEXT IS NULL
+                   OR f.name ILIKE $3 ESCAPE '\\'
+                   OR f.description ILIKE $3 ESCAPE '\\'
+                   OR si.content ILIKE $3 ESCAPE '\\')
+              AND ($6::DOUBLE PRECISION IS NULL OR f.relevance_score >= $6)
+            ORDER BY f.relevance_score DESC, f.name
+            LIMIT $4 OFFSET $7";
+
+        let like_query = if query.query.is_empty() {
+            None
+        } else {
+            Some(format!("%{}%", escape_like(&query.query)))
+        };
+
+        let service_ids = query.service_ids.clone().filter(|ids| !ids.is_empty());
+        let feature_types: Option<Vec<String>> = query
+            .feature_types
+            .as_ref()
+            .filter(|types| !types.is_empty())
+            .map(|types| types.iter().map(|t| t.to_string()).collect());
+        let limit = query.limit.unwrap_or(20) as i64;
+
+        let rows = self
+            .block_on(
+                sqlx::query(sql)
+                    .bind(service_ids)
+                    .bind(feature_types)
+                    .bind(like_query)
+                    .bind(limit)
+                    .bind(&self.workspace)
+                    .bind(query.min_score)
+                    .bind(query.offset.unwrap_or(0) as i64)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("execute search query", e))?;
+
+        let results = rows
+            .iter()
+            .map(row_to_search_result)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect search results", e))?;
+        with_related(self, query, results)
+    }
+
+    fn update_feature_relevance(&self, feature_id: &str, score: f64) -> Result<()> {
+        self.block_on(
+            sqlx::query("UPDATE features SET relevance_score = $1 WHERE id = $2")
+                .bind(score)
+                .bind(feature_id)
+                .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("update relevance score", e))?;
+
+        Ok(())
+    }
+
+    fn delete_feature(&self, id: &str) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query("DELETE FROM features WHERE id = $1")
+                    .bind(id)
+                    .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("delete feature", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn index_feature_content(
+        &self,
+        feature_id: &str,
+        content_type: SearchContentType,
+        content: &str,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "INSERT INTO search_index (id, feature_id, content_type, content)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (feature_id, content_type) DO UPDATE SET
+                   content = EXCLUDED.content,
+                   indexed_at = CURRENT_TIMESTAMP",
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(feature_id)
+            .bind(content_type.to_string())
+            .bind(content)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("index feature content", e))?;
+
+        Ok(())
+    }
+
+    fn record_feature_usage(&self, feature_ids: &[&str]) -> Result<()> {
+        if feature_ids.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<String> = feature_ids.iter().map(|id| id.to_string()).collect();
+        self.block_on(
+            sqlx::query(
+                "UPDATE features SET usage_count = usage_count + 1, last_used_at = NOW()
+                 WHERE id = ANY($1)",
+            )
+            .bind(ids)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("record feature usage", e))?;
+
+        Ok(())
+    }
+
+    fn feature_usage(&self) -> Result<Vec<FeatureUsage>> {
+        let rows = self
+            .block_on(
+                sqlx::query(
+                    "SELECT f.id, f.relevance_score, f.usage_count, f.last_used_at,
+                            COALESCE(f.ranked_at, f.created_at)
+                     FROM features f
+                     JOIN services s ON f.service_id = s.id
+                     WHERE s.workspace = $1
+                     ORDER BY f.id",
+                )
+                .bind(&self.workspace)
+                .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query feature usage", e))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(FeatureUsage {
+                    feature_id: row.try_get(0)?,
+                    relevance_score: row.try_get(1)?,
+                    usage_count: row.try_get(2)?,
+                    last_used_at: row.try_get(3)?,
+                    ranked_since: row.try_get(4)?,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, sqlx::Error>>()
+            .map_err(|e| storage_err("collect feature usage", e))
+    }
+
+    fn apply_feature_ranking(
+        &self,
+        feature_id: &str,
+        score: f64,
+        counted_uses: i64,
+        ranked_at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "UPDATE features
+                 SET relevance_score = $1, usage_count = GREATEST(usage_count - $2, 0), ranked_at = $3
+                 WHERE id = $4",
+            )
+            .bind(score)
+            .bind(counted_uses)
+            .bind(ranked_at)
+            .bind(feature_id)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("store relevance score", e))?;
+
+        Ok(())
+    }
+
+    fn create_relation(
+        &self,
+        id: &str,
+        parent_id: &str,
+        child_id: &str,
+        relation_type: RelationType,
+        strength: f64,
+        metadata: serde_json::Value,
+    ) -> Result<FeatureRelation> {
+        let metadata_json = serde_json::to_string(&metadata).map_err(KtmeError::Serialization)?;
+        let row = self
+            .block_on(
+                sqlx::query(&format!(
+                    "INSERT INTO feature_relations
+                       (id, parent_feature_id, child_feature_id, relation_type, strength, metadata)
+                     VALUES ($1, $2, $3, $4, $5, $6)
+                     RETURNING {}",
+                    RELATION_COLUMNS
+                ))
+                .bind(id)
+                .bind(parent_id)
+                .bind(child_id)
+                .bind(relation_type.to_string())
+                .bind(strength)
+                .bind(metadata_json)
+                .fetch_one(&self.pool),
+            )?
+            .map_err(|e| storage_err("create feature relation", e))?;
+
+        row_to_relation(&row).map_err(|e| storage_err("create feature relation", e))
+    }
+
+    fn relations_of(&self, feature_id: &str) -> Result<Vec<FeatureRelation>> {
+        let sql = format!(
+            "SELECT {} FROM feature_relations
+             WHERE parent_feature_id = $1 OR child_feature_id = $1
+             ORDER BY strength DESC",
+            RELATION_COLUMNS
+        );
+        let rows = self
+            .block_on(sqlx::query(&sql).bind(feature_id).fetch_all(&self.pool))?
+            .map_err(|e| storage_err("query feature relations", e))?;
+
+        rows.iter()
+            .map(row_to_relation)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect feature relations", e))
+    }
+
+    fn delete_relation(&self, parent_id: &str, child_id: &str) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "DELETE FROM feature_relations
+                     WHERE parent_feature_id = $1 AND child_feature_id = $2",
+                )
+                .bind(parent_id)
+                .bind(child_id)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("delete feature relation", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn record_generation(
+        &self,
+        service_id: Option<i64>,
+        provider: &str,
+        document_id: Option<&str>,
+        document_url: Option<&str>,
+        action: &str,
+        source_type: Option<&str>,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+        status: &str,
+        error_message: Option<&str>,
+        model: Option<&str>,
+        idempotency_key: Option<&str>,
+    ) -> Result<i64> {
+        self.block_on(
+            sqlx::query_scalar(
+                "INSERT INTO generation_history
+                 (service_id, provider, document_id, document_url, action, source_type,
+                  source_identifier, content_hash, status, error_message, workspace, model,
+                  idempotency_key)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id",
+            )
+            .bind(service_id)
+            .bind(provider)
+            .bind(document_id)
+            .bind(document_url)
+            .bind(action)
+            .bind(source_type)
+            .bind(source_identifier)
+            .bind(content_hash)
+            .bind(status)
+            .bind(error_message)
+            .bind(&self.workspace)
+            .bind(model)
+            .bind(idempotency_key)
+            .fetch_one(&self.pool),
+        )?
+        .map_err(|e| storage_err("record history", e))
+    }
+
+    fn recent_generations(&self, limit: usize) -> Result<Vec<GenerationRecord>> {
+        let sql = format!(
+            "SELECT {} FROM generation_history WHERE workspace = $1 ORDER BY created_at DESC LIMIT $2",
+            HISTORY_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(limit as i64)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query history", e))?;
+
+        rows.iter()
+            .map(row_to_record)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect history", e))
+    }
+
+    fn find_documented(
+        &self,
+        location: &str,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+    ) -> Result<Option<GenerationRecord>> {
+        let sql = format!(
+            "SELECT {} FROM generation_history
+             WHERE workspace = $1 AND document_url = $2 AND status = 'success'
+               AND (source_identifier = $3 OR content_hash = $4)
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+            HISTORY_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(location)
+                    .bind(source_identifier)
+                    .bind(content_hash)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("query history", e))?;
+
+        row.as_ref()
+            .map(row_to_record)
+            .transpose()
+            .map_err(|e| storage_err("read history", e))
+    }
+
+    fn find_by_key(
+        &self,
+        location: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<GenerationRecord>> {
+        let sql = format!(
+            "SELECT {} FROM generation_history
+             WHERE workspace = $1 AND document_url = $2 AND status = 'success'
+               AND idempotency_key = $3
+             ORDER BY created_at DESC, id DESC LIMIT 1",
+            HISTORY_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(location)
+                    .bind(idempotency_key)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("query history", e))?;
+
+        row.as_ref()
+            .map(row_to_record)
+            .transpose()
+            .map_err(|e| storage_err("read history", e))
+    }
+
+    fn queue_doc(
+        &self,
+        service: &str,
+        doc_type: &str,
+        source_identifier: Option<&str>,
+        target: Option<&str>,
+        content: &str,
+    ) -> Result<i64> {
+        self.block_on(
+            sqlx::query_scalar(
+                "INSERT INTO pending_docs (workspace, service, doc_type, source_identifier, target, content)
+                 VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            )
+            .bind(&self.workspace)
+            .bind(service)
+            .bind(doc_type)
+            .bind(source_identifier)
+            .bind(target)
+            .bind(content)
+            .fetch_one(&self.pool),
+        )?
+        .map_err(|e| storage_err("queue documentation", e))
+    }
+
+    fn get_pending_doc(&self, id: i64) -> Result<Option<PendingDoc>> {
+        let sql = format!(
+            "SELECT {} FROM pending_docs WHERE id = $1 AND workspace = $2",
+            PENDING_DOC_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(id)
+                    .bind(&self.workspace)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get queued documentation", e))?;
+
+        row.as_ref()
+            .map(row_to_pending_doc)
+            .transpose()
+            .map_err(|e| storage_err("read queued documentation", e))
+    }
+
+    fn list_pending_docs(&self, status: Option<&str>) -> Result<Vec<PendingDoc>> {
+        let sql = format!(
+            "SELECT {} FROM pending_docs
+             WHERE workspace = $1 AND ($2::TEXT IS NULL OR status = $2)
+             ORDER BY id",
+            PENDING_DOC_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(status)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query review queue", e))?;
+
+        rows.iter()
+            .map(row_to_pending_doc)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect review queue", e))
+    }
+
+    fn review_pending_doc(
+        &self,
+        id: i64,
+        status: &str,
+        reviewer: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "UPDATE pending_docs
+                     SET status = $1, reviewer = $2, note = $3, reviewed_at = NOW()
+                     WHERE id = $4 AND workspace = $5 AND status = 'pending'",
+                )
+                .bind(status)
+                .bind(reviewer)
+                .bind(note)
+                .bind(id)
+                .bind(&self.workspace)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("review queued documentation", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn create_job(
+        &self,
+        kind: &str,
+        description: &str,
+        params: &str,
+        steps: &[String],
+    ) -> Result<i64> {
+        let pool = self.pool.clone();
+        let workspace = self.workspace.clone();
+        let (kind, description, params) = (
+            kind.to_string(),
+            description.to_string(),
+            params.to_string(),
+        );
+        let steps = steps.to_vec();
+        self.block_on(async move {
+            let mut tx = pool.begin().await?;
+            let id: i64 = sqlx::query_scalar(
+                "INSERT INTO jobs (workspace, kind, description, params)
+                 VALUES ($1, $2, $3, $4) RETURNING id",
+            )
+            .bind(&workspace)
+            .bind(&kind)
+            .bind(&description)
+            .bind(&params)
+            .fetch_one(&mut *tx)
+            .await?;
+            for (position, name) in steps.iter().enumerate() {
+                sqlx::query("INSERT INTO job_steps (job_id, position, name) VALUES ($1, $2, $3)")
+                    .bind(id)
+                    .bind(position as i64)
+                    .bind(name)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(id)
+        })?
+        .map_err(|e| storage_err("create job", e))
+    }
+
+    fn get_job(&self, id: i64) -> Result<Option<Job>> {
+        let sql = format!(
+            "SELECT {} FROM jobs WHERE id = $1 AND workspace = $2",
+            JOB_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(id)
+                    .bind(&self.workspace)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get job", e))?;
+
+        row.as_ref()
+            .map(row_to_job)
+            .transpose()
+            .map_err(|e| storage_err("read job", e))?
+            .map(|job| self.with_steps(job))
+            .transpose()
+    }
+
+    fn list_jobs(&self, status: Option<&str>) -> Result<Vec<Job>> {
+        let sql = format!(
+            "SELECT {} FROM jobs
+             WHERE workspace = $1 AND ($2::TEXT IS NULL OR status = $2)
+             ORDER BY id DESC",
+            JOB_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(status)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query jobs", e))?;
+
+        rows.iter()
+            .map(row_to_job)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect jobs", e))?
+            .into_iter()
+            .map(|job| self.with_steps(job))
+            .collect()
+    }
+
+    fn update_job_step(
+        &self,
+        id: i64,
+        position: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "UPDATE job_steps SET status = $1, error = $2, updated_at = NOW()
+                 WHERE job_id = $3 AND position = $4",
+            )
+            .bind(status)
+            .bind(error)
+            .bind(id)
+            .bind(position)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("update job step", e))?;
+        self.block_on(
+            sqlx::query("UPDATE jobs SET updated_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("update job", e))?;
+
+        Ok(())
+    }
+
+    fn update_job_status(&self, id: i64, status: &str, error: Option<&str>) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "UPDATE jobs SET status = $1, error = $2, updated_at = NOW()
+                 WHERE id = $3 AND workspace = $4",
+            )
+            .bind(status)
+            .bind(error)
+            .bind(id)
+            .bind(&self.workspace)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("update job", e))?;
+
+        Ok(())
+    }
+
+    fn save_service_detection(
+        &self,
+        directory: &str,
+        service_name: &str,
+        pinned: bool,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "INSERT INTO service_detections (workspace, directory, service_name, pinned)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (workspace, directory, pinned) DO UPDATE SET
+                    service_name = EXCLUDED.service_name,
+                    updated_at = NOW()",
+            )
+            .bind(&self.workspace)
+            .bind(directory)
+            .bind(service_name)
+            .bind(pinned)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("save service detection", e))?;
+
+        Ok(())
+    }
+
+    fn get_service_detection(
+        &self,
+        directory: &str,
+        pinned: bool,
+    ) -> Result<Option<ServiceDetection>> {
+        let sql = format!(
+            "SELECT {} FROM service_detections
+             WHERE workspace = $1 AND directory = $2 AND pinned = $3",
+            DETECTION_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(directory)
+                    .bind(pinned)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get service detection", e))?;
+
+        row.as_ref()
+            .map(row_to_detection)
+            .transpose()
+            .map_err(|e| storage_err("read service detection", e))
+    }
+
+    fn remove_service_detection(&self, directory: &str, pinned: bool) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "DELETE FROM service_detections
+                     WHERE workspace = $1 AND directory = $2 AND pinned = $3",
+                )
+                .bind(&self.workspace)
+                .bind(directory)
+                .bind(pinned)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("remove service detection", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn clear_service_detections(&self, directory: &str) -> Result<u64> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "DELETE FROM service_detections
+                     WHERE workspace = $1 AND pinned = FALSE
+                       AND (directory = $2 OR LEFT(directory, LENGTH($3)) = $3)",
+                )
+                .bind(&self.workspace)
+                .bind(directory)
+                .bind(format!("{}/", directory.trim_end_matches('/')))
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("clear service detections", e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn add_translation(
+        &self,
+        service: &str,
+        source: &str,
+        target_type: &str,
+        target: &str,
+        language: &str,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "INSERT INTO translations
+                    (workspace, service_name, source, target_type, target, language)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (workspace, target_type, target) DO UPDATE SET
+                    service_name = EXCLUDED.service_name,
+                    source = EXCLUDED.source,
+                    language = EXCLUDED.language,
+                    sections = '{}',
+                    synced_at = NULL",
+            )
+            .bind(&self.workspace)
+            .bind(service)
+            .bind(source)
+            .bind(target_type)
+            .bind(target)
+            .bind(language)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("add translation", e))?;
+
+        Ok(())
+    }
+
+    fn list_translations(&self, service: &str, source: Option<&str>) -> Result<Vec<Translation>> {
+        let sql = format!(
+            "SELECT {} FROM translations
+             WHERE workspace = $1 AND service_name = $2 AND ($3::TEXT IS NULL OR source = $3)
+             ORDER BY source, target",
+            TRANSLATION_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(service)
+                    .bind(source)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query translations", e))?;
+
+        rows.iter()
+            .map(row_to_translation)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect translations", e))
+    }
+
+    fn save_translation_sections(
+        &self,
+        id: i64,
+        sections: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let sections = serde_json::to_string(sections)?;
+        self.block_on(
+            sqlx::query(
+                "UPDATE translations SET sections = $1, synced_at = NOW()
+                 WHERE id = $2 AND workspace = $3",
+            )
+            .bind(sections)
+            .bind(id)
+            .bind(&self.workspace)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("save translation", e))?;
+
+        Ok(())
+    }
+
+    fn remove_translations(&self, service: &str) -> Result<u64> {
+        let result = self
+            .block_on(
+                sqlx::query("DELETE FROM translations WHERE workspace = $1 AND service_name = $2")
+                    .bind(&self.workspace)
+                    .bind(service)
+                    .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("remove translations", e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn cache_diff(
+        &self,
+        source_type: &str,
+        source_identifier: &str,
+        repository_path: Option<&str>,
+        diff_json: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "INSERT INTO diff_cache
+                    (workspace, source_type, source_identifier, repository_path, diff_json, expires_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (workspace, source_type, source_identifier, repository_path) DO UPDATE SET
+                    diff_json = EXCLUDED.diff_json,
+                    expires_at = EXCLUDED.expires_at,
+                    created_at = NOW()",
+            )
+            .bind(&self.workspace)
+            .bind(source_type)
+            .bind(source_identifier)
+            .bind(repository_path)
+            .bind(diff_json)
+            .bind(expires_at)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("cache diff", e))?;
+
+        Ok(())
+    }
+
+    fn get_cached_diff(
+        &self,
+        source_type: &str,
+        source_identifier: &str,
+        repository_path: Option<&str>,
+    ) -> Result<Option<DiffCache>> {
+        let sql = format!(
+            "SELECT {} FROM diff_cache
+             WHERE workspace = $1 AND source_type = $2 AND source_identifier = $3
+               AND repository_path IS NOT DISTINCT FROM $4
+               AND (expires_at IS NULL OR expires_at > NOW())",
+            DIFF_CACHE_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(source_type)
+                    .bind(source_identifier)
+                    .bind(repository_path)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get cached diff", e))?;
+
+        row.as_ref()
+            .map(row_to_cached_diff)
+            .transpose()
+            .map_err(|e| storage_err("read cached diff", e))
+    }
+
+    fn clear_expired_diffs(&self) -> Result<u64> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "DELETE FROM diff_cache
+                     WHERE workspace = $1 AND expires_at IS NOT NULL AND expires_at <= NOW()",
+                )
+                .bind(&self.workspace)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("clear diff cache", e))?;
+
+        Ok(result.rows_affected())
+    }
+
+    fn save_provider_config(
+        &self,
+        provider_type: &str,
+        config: &serde_json::Value,
+        is_default: bool,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "INSERT INTO provider_configs (workspace, provider_type, config_json, is_default)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (workspace, provider_type) DO UPDATE SET
+                    config_json = EXCLUDED.config_json,
+                    is_default = EXCLUDED.is_default,
+                    updated_at = NOW()",
+            )
+            .bind(&self.workspace)
+            .bind(provider_type)
+            .bind(config.to_string())
+            .bind(is_default)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("save provider config", e))?;
+
+        Ok(())
+    }
+
+    fn get_provider_config(&self, provider_type: &str) -> Result<Option<ProviderConfig>> {
+        let sql = format!(
+            "SELECT {} FROM provider_configs WHERE workspace = $1 AND provider_type = $2",
+            PROVIDER_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(provider_type)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get provider config", e))?;
+
+        row.as_ref()
+            .map(row_to_provider)
+            .transpose()
+            .map_err(|e| storage_err("read provider config", e))
+    }
+
+    fn list_provider_configs(&self) -> Result<Vec<ProviderConfig>> {
+        let sql = format!(
+            "SELECT {} FROM provider_configs WHERE workspace = $1
+             ORDER BY is_default DESC, provider_type",
+            PROVIDER_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query provider configs", e))?;
+
+        rows.iter()
+            .map(row_to_provider)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect provider configs", e))
+    }
+
+    fn set_default_provider(&self, provider_type: &str) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "UPDATE provider_configs
+                 SET is_default = (provider_type = $2),
+                     updated_at = CASE WHEN provider_type = $2 THEN NOW() ELSE updated_at END
+                 WHERE workspace = $1",
+            )
+            .bind(&self.workspace)
+            .bind(provider_type)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("set default provider", e))?;
+
+        Ok(())
+    }
+
+    fn delete_provider_config(&self, provider_type: &str) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "DELETE FROM provider_configs WHERE workspace = $1 AND provider_type = $2",
+                )
+                .bind(&self.workspace)
+                .bind(provider_type)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("delete provider config", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn record_tool_call(
+        &self,
+        tool: &str,
+        arguments: &str,
+        transport: &str,
+        duration_ms: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        self.block_on(
+            sqlx::query(
+                "INSERT INTO mcp_audit (workspace, tool, arguments, transport, duration_ms, status, error)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            )
+            .bind(&self.workspace)
+            .bind(tool)
+            .bind(arguments)
+            .bind(transport)
+            .bind(duration_ms)
+            .bind(status)
+            .bind(error)
+            .execute(&self.pool),
+        )?
+        .map_err(|e| storage_err("record tool call", e))?;
+
+        Ok(())
+    }
+
+    fn tool_calls_since(&self, since: DateTime<Utc>) -> Result<Vec<ToolCall>> {
+        let sql = format!(
+            "SELECT {} FROM mcp_audit
+             WHERE workspace = $1 AND created_at >= $2
+             ORDER BY created_at DESC, id DESC",
+            TOOL_CALL_COLUMNS
+        );
+        let rows = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(since)
+                    .fetch_all(&self.pool),
+            )?
+            .map_err(|e| storage_err("query tool calls", e))?;
+
+        rows.iter()
+            .map(row_to_tool_call)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect tool calls", e))
+    }
+
+    fn try_acquire_lock(
+        &self,
+        resource: &str,
+        owner: &str,
+        purpose: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "INSERT INTO generation_locks
+                        (workspace, resource, owner, pid, purpose, acquired_at, expires_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7)
+                     ON CONFLICT (workspace, resource) DO UPDATE SET
+                        owner = EXCLUDED.owner,
+                        pid = EXCLUDED.pid,
+                        purpose = EXCLUDED.purpose,
+                        acquired_at = EXCLUDED.acquired_at,
+                        expires_at = EXCLUDED.expires_at
+                     WHERE generation_locks.owner = EXCLUDED.owner
+                        OR generation_locks.expires_at <= EXCLUDED.acquired_at",
+                )
+                .bind(&self.workspace)
+                .bind(resource)
+                .bind(owner)
+                .bind(i64::from(std::process::id()))
+                .bind(purpose)
+                .bind(Utc::now())
+                .bind(expires_at)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("acquire lock", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn lock_holder(&self, resource: &str) -> Result<Option<GenerationLock>> {
+        let sql = format!(
+            "SELECT {} FROM generation_locks WHERE workspace = $1 AND resource = $2",
+            LOCK_COLUMNS
+        );
+        let row = self
+            .block_on(
+                sqlx::query(&sql)
+                    .bind(&self.workspace)
+                    .bind(resource)
+                    .fetch_optional(&self.pool),
+            )?
+            .map_err(|e| storage_err("get lock", e))?;
+
+        row.as_ref()
+            .map(row_to_lock)
+            .transpose()
+            .map_err(|e| storage_err("read lock", e))
+    }
+
+    fn release_lock(&self, resource: &str, owner: &str) -> Result<bool> {
+        let result = self
+            .block_on(
+                sqlx::query(
+                    "DELETE FROM generation_locks
+                     WHERE workspace = $1 AND resource = $2 AND owner = $3",
+                )
+                .bind(&self.workspace)
+                .bind(resource)
+                .bind(owner)
+                .execute(&self.pool),
+            )?
+            .map_err(|e| storage_err("release lock", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+impl PostgresStorage {
+    fn with_steps(&self, mut job: Job) -> Result<Job> {
+        let sql = format!(
+            "SELECT {} FROM job_steps WHERE job_id = $1 ORDER BY position",
+            JOB_STEP_COLUMNS
+        );
+        let rows = self
+            .block_on(sqlx::query(&sql).bind(job.id).fetch_all(&self.pool))?
+            .map_err(|e| storage_err("query job steps", e))?;
+
+        job.steps = rows
+            .iter()
+            .map(row_to_job_step)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| storage_err("collect job steps", e))?;
+        Ok(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("c:\\dir"), "c:\\\\dir");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+}
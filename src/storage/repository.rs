@@ -1,13 +1,24 @@
 use super::database::Database;
 use super::models::*;
+use super::rows::{impl_from_row, json_or_default, query_all, query_optional, FromRow};
 use crate::error::{KtmeError, Result};
 use chrono::{DateTime, Utc};
 use rusqlite::params;
+use std::collections::BTreeMap;
 
 // ============================================================================
 // Service Repository
 // ============================================================================
 
+impl_from_row!(Service {
+    id,
+    name,
+    path,
+    description,
+    created_at,
+    updated_at,
+});
+
 pub struct ServiceRepository {
     db: Database,
 }
@@ -26,115 +37,60 @@ impl ServiceRepository {
         let conn = self.db.connection()?;
 
         conn.execute(
-            "INSERT INTO services (name, path, description) VALUES (?1, ?2, ?3)",
-            params![name, path, description],
+            "INSERT INTO services (workspace, name, path, description) VALUES (?1, ?2, ?3, ?4)",
+            params![self.db.workspace(), name, path, description],
         )
         .map_err(|e| KtmeError::Storage(format!("Failed to create service: {}", e)))?;
 
         // Query the created service in the same transaction
-        let result = conn.query_row(
-            "SELECT id, name, path, description, created_at, updated_at
-             FROM services WHERE name = ?1",
-            params![name],
-            |row| {
-                Ok(Service {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    path: row.get(2)?,
-                    description: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(service) => Ok(service),
-            Err(e) => Err(KtmeError::Storage(format!(
-                "Failed to retrieve created service: {}",
-                e
-            ))),
-        }
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM services WHERE workspace = ?1 AND name = ?2",
+                Service::columns()
+            ),
+            params![self.db.workspace(), name],
+            Service::from_row,
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to retrieve created service: {}", e)))
     }
 
     pub fn get_by_id(&self, id: i64) -> Result<Option<Service>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, name, path, description, created_at, updated_at
-             FROM services WHERE id = ?1",
+        query_optional(
+            &conn,
+            &format!("SELECT {} FROM services WHERE id = ?1", Service::columns()),
             params![id],
-            |row| {
-                Ok(Service {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    path: row.get(2)?,
-                    description: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(service) => Ok(Some(service)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get service: {}", e))),
-        }
+            "service",
+        )
     }
 
     pub fn get_by_name(&self, name: &str) -> Result<Option<Service>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, name, path, description, created_at, updated_at
-             FROM services WHERE name = ?1",
-            params![name],
-            |row| {
-                Ok(Service {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    path: row.get(2)?,
-                    description: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(service) => Ok(Some(service)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get service: {}", e))),
-        }
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM services WHERE workspace = ?1 AND name = ?2",
+                Service::columns()
+            ),
+            params![self.db.workspace(), name],
+            "service",
+        )
     }
 
     pub fn list(&self) -> Result<Vec<Service>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, name, path, description, created_at, updated_at
-                 FROM services ORDER BY name",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let services = stmt
-            .query_map([], |row| {
-                Ok(Service {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    path: row.get(2)?,
-                    description: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query services: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect services: {}", e)))?;
-
-        Ok(services)
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM services WHERE workspace = ?1 ORDER BY name",
+                Service::columns()
+            ),
+            params![self.db.workspace()],
+            "services",
+        )
     }
 
     pub fn update(&self, id: i64, path: Option<&str>, description: Option<&str>) -> Result<()> {
@@ -150,25 +106,88 @@ impl ServiceRepository {
         Ok(())
     }
 
+    /// Rename a service; its mappings, features and history follow by id,
+    /// queued documentation by name. False when there is no `old` service.
+    pub fn rename(&self, old: &str, new: &str) -> Result<bool> {
+        let conn = self.db.connection()?;
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| KtmeError::Storage(format!("Failed to begin transaction: {}", e)))?;
+
+        let rows = tx
+            .execute(
+                "UPDATE services SET name = ?1, updated_at = CURRENT_TIMESTAMP
+                 WHERE workspace = ?2 AND name = ?3",
+                params![new, self.db.workspace(), old],
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to rename service: {}", e)))?;
+        tx.execute(
+            "UPDATE pending_docs SET service = ?1 WHERE workspace = ?2 AND service = ?3",
+            params![new, self.db.workspace(), old],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to rename queued docs: {}", e)))?;
+        tx.commit()
+            .map_err(|e| KtmeError::Storage(format!("Failed to rename service: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+
     pub fn delete(&self, name: &str) -> Result<bool> {
         let conn = self.db.connection()?;
 
         let rows = conn
-            .execute("DELETE FROM services WHERE name = ?1", params![name])
+            .execute(
+                "DELETE FROM services WHERE workspace = ?1 AND name = ?2",
+                params![self.db.workspace(), name],
+            )
             .map_err(|e| KtmeError::Storage(format!("Failed to delete service: {}", e)))?;
 
         Ok(rows > 0)
     }
 
+    pub fn get_settings(&self, id: i64) -> Result<ServiceSettings> {
+        let conn = self.db.connection()?;
+
+        let settings: Option<String> = conn
+            .query_row(
+                "SELECT settings FROM services WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to get service settings: {}", e)))?;
+
+        match settings {
+            Some(json) => serde_json::from_str(&json).map_err(|e| {
+                KtmeError::Storage(format!("Invalid settings for service {}: {}", id, e))
+            }),
+            None => Ok(ServiceSettings::default()),
+        }
+    }
+
+    pub fn set_settings(&self, id: i64, settings: &ServiceSettings) -> Result<()> {
+        let conn = self.db.connection()?;
+        let json = (!settings.is_empty())
+            .then(|| serde_json::to_string(settings))
+            .transpose()?;
+
+        conn.execute(
+            "UPDATE services SET settings = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![json, id],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to update service settings: {}", e)))?;
+
+        Ok(())
+    }
+
     pub fn list_all_names(&self) -> Result<Vec<String>> {
         let conn = self.db.connection()?;
 
         let mut stmt = conn
-            .prepare("SELECT name FROM services ORDER BY name")
+            .prepare("SELECT name FROM services WHERE workspace = ?1 ORDER BY name")
             .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
 
         let names: std::result::Result<Vec<String>, rusqlite::Error> = stmt
-            .query_map([], |row| row.get(0))
+            .query_map(params![self.db.workspace()], |row| row.get(0))
             .map_err(|e| KtmeError::Storage(format!("Failed to execute query: {}", e)))?
             .collect();
 
@@ -180,6 +199,18 @@ impl ServiceRepository {
 // Document Mapping Repository
 // ============================================================================
 
+impl_from_row!(DocumentMapping {
+    id,
+    service_id,
+    provider,
+    location,
+    title,
+    section,
+    is_primary,
+    created_at,
+    updated_at,
+});
+
 pub struct DocumentMappingRepository {
     db: Database,
 }
@@ -208,6 +239,8 @@ impl DocumentMappingRepository {
         .map_err(|e| KtmeError::Storage(format!("Failed to add mapping: {}", e)))?;
 
         let id = conn.last_insert_rowid();
+        // Release the lock before re-reading; get_by_id acquires it again
+        drop(conn);
         self.get_by_id(id)?
             .ok_or_else(|| KtmeError::Storage("Failed to retrieve created mapping".into()))
     }
@@ -215,61 +248,29 @@ impl DocumentMappingRepository {
     pub fn get_by_id(&self, id: i64) -> Result<Option<DocumentMapping>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, service_id, provider, location, title, section, is_primary, created_at, updated_at
-             FROM document_mappings WHERE id = ?1",
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM document_mappings WHERE id = ?1",
+                DocumentMapping::columns()
+            ),
             params![id],
-            |row| {
-                Ok(DocumentMapping {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    provider: row.get(2)?,
-                    location: row.get(3)?,
-                    title: row.get(4)?,
-                    section: row.get(5)?,
-                    is_primary: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(mapping) => Ok(Some(mapping)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get mapping: {}", e))),
-        }
+            "mapping",
+        )
     }
 
     pub fn get_for_service(&self, service_id: i64) -> Result<Vec<DocumentMapping>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, service_id, provider, location, title, section, is_primary, created_at, updated_at
-                 FROM document_mappings WHERE service_id = ?1 ORDER BY is_primary DESC, provider",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let mappings = stmt
-            .query_map(params![service_id], |row| {
-                Ok(DocumentMapping {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    provider: row.get(2)?,
-                    location: row.get(3)?,
-                    title: row.get(4)?,
-                    section: row.get(5)?,
-                    is_primary: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query mappings: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect mappings: {}", e)))?;
-
-        Ok(mappings)
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM document_mappings WHERE service_id = ?1 ORDER BY is_primary DESC, provider",
+                DocumentMapping::columns()
+            ),
+            params![service_id],
+            "mappings",
+        )
     }
 
     pub fn get_by_provider(
@@ -279,59 +280,29 @@ impl DocumentMappingRepository {
     ) -> Result<Option<DocumentMapping>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, service_id, provider, location, title, section, is_primary, created_at, updated_at
-             FROM document_mappings WHERE service_id = ?1 AND provider = ?2",
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM document_mappings WHERE service_id = ?1 AND provider = ?2",
+                DocumentMapping::columns()
+            ),
             params![service_id, provider],
-            |row| {
-                Ok(DocumentMapping {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    provider: row.get(2)?,
-                    location: row.get(3)?,
-                    title: row.get(4)?,
-                    section: row.get(5)?,
-                    is_primary: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(mapping) => Ok(Some(mapping)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get mapping: {}", e))),
-        }
+            "mapping",
+        )
     }
 
     pub fn get_primary(&self, service_id: i64) -> Result<Option<DocumentMapping>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, service_id, provider, location, title, section, is_primary, created_at, updated_at
-             FROM document_mappings WHERE service_id = ?1 AND is_primary = TRUE LIMIT 1",
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM document_mappings WHERE service_id = ?1 AND is_primary = TRUE LIMIT 1",
+                DocumentMapping::columns()
+            ),
             params![service_id],
-            |row| {
-                Ok(DocumentMapping {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    provider: row.get(2)?,
-                    location: row.get(3)?,
-                    title: row.get(4)?,
-                    section: row.get(5)?,
-                    is_primary: row.get(6)?,
-                    created_at: row.get(7)?,
-                    updated_at: row.get(8)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(mapping) => Ok(Some(mapping)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get mapping: {}", e))),
-        }
+            "mapping",
+        )
     }
 
     pub fn delete(&self, id: i64) -> Result<bool> {
@@ -369,6 +340,15 @@ impl DocumentMappingRepository {
 // Provider Config Repository
 // ============================================================================
 
+impl_from_row!(ProviderConfig {
+    id,
+    provider_type,
+    config = "config_json" => json_or_default,
+    is_default,
+    created_at,
+    updated_at,
+});
+
 pub struct ProviderConfigRepository {
     db: Database,
 }
@@ -387,13 +367,18 @@ impl ProviderConfigRepository {
         let conn = self.db.connection()?;
 
         conn.execute(
-            "INSERT INTO provider_configs (provider_type, config_json, is_default)
-             VALUES (?1, ?2, ?3)
-             ON CONFLICT(provider_type) DO UPDATE SET
+            "INSERT INTO provider_configs (workspace, provider_type, config_json, is_default)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(workspace, provider_type) DO UPDATE SET
                 config_json = excluded.config_json,
                 is_default = excluded.is_default,
                 updated_at = CURRENT_TIMESTAMP",
-            params![provider_type, config.to_string(), is_default],
+            params![
+                self.db.workspace(),
+                provider_type,
+                config.to_string(),
+                is_default
+            ],
         )
         .map_err(|e| KtmeError::Storage(format!("Failed to save provider config: {}", e)))?;
 
@@ -403,104 +388,61 @@ impl ProviderConfigRepository {
     pub fn get(&self, provider_type: &str) -> Result<Option<ProviderConfig>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, provider_type, config_json, is_default, created_at, updated_at
-             FROM provider_configs WHERE provider_type = ?1",
-            params![provider_type],
-            |row| {
-                let config_str: String = row.get(2)?;
-                Ok(ProviderConfig {
-                    id: row.get(0)?,
-                    provider_type: row.get(1)?,
-                    config: serde_json::from_str(&config_str).unwrap_or(serde_json::Value::Null),
-                    is_default: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(config) => Ok(Some(config)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!(
-                "Failed to get provider config: {}",
-                e
-            ))),
-        }
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM provider_configs WHERE workspace = ?1 AND provider_type = ?2",
+                ProviderConfig::columns()
+            ),
+            params![self.db.workspace(), provider_type],
+            "provider config",
+        )
     }
 
     pub fn get_default(&self) -> Result<Option<ProviderConfig>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, provider_type, config_json, is_default, created_at, updated_at
-             FROM provider_configs WHERE is_default = TRUE LIMIT 1",
-            [],
-            |row| {
-                let config_str: String = row.get(2)?;
-                Ok(ProviderConfig {
-                    id: row.get(0)?,
-                    provider_type: row.get(1)?,
-                    config: serde_json::from_str(&config_str).unwrap_or(serde_json::Value::Null),
-                    is_default: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(config) => Ok(Some(config)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!(
-                "Failed to get default provider: {}",
-                e
-            ))),
-        }
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM provider_configs WHERE workspace = ?1 AND is_default = TRUE LIMIT 1",
+                ProviderConfig::columns()
+            ),
+            params![self.db.workspace()],
+            "default provider",
+        )
     }
 
     pub fn list(&self) -> Result<Vec<ProviderConfig>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, provider_type, config_json, is_default, created_at, updated_at
-                 FROM provider_configs ORDER BY is_default DESC, provider_type",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let configs = stmt
-            .query_map([], |row| {
-                let config_str: String = row.get(2)?;
-                Ok(ProviderConfig {
-                    id: row.get(0)?,
-                    provider_type: row.get(1)?,
-                    config: serde_json::from_str(&config_str).unwrap_or(serde_json::Value::Null),
-                    is_default: row.get(3)?,
-                    created_at: row.get(4)?,
-                    updated_at: row.get(5)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query configs: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect configs: {}", e)))?;
-
-        Ok(configs)
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM provider_configs WHERE workspace = ?1
+                 ORDER BY is_default DESC, provider_type",
+                ProviderConfig::columns()
+            ),
+            params![self.db.workspace()],
+            "configs",
+        )
     }
 
     pub fn set_default(&self, provider_type: &str) -> Result<()> {
         let conn = self.db.connection()?;
 
         // Clear existing default
-        conn.execute("UPDATE provider_configs SET is_default = FALSE", [])
-            .map_err(|e| KtmeError::Storage(format!("Failed to clear default: {}", e)))?;
+        conn.execute(
+            "UPDATE provider_configs SET is_default = FALSE WHERE workspace = ?1",
+            params![self.db.workspace()],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to clear default: {}", e)))?;
 
         // Set new default
         conn.execute(
             "UPDATE provider_configs SET is_default = TRUE, updated_at = CURRENT_TIMESTAMP
-             WHERE provider_type = ?1",
-            params![provider_type],
+             WHERE workspace = ?1 AND provider_type = ?2",
+            params![self.db.workspace(), provider_type],
         )
         .map_err(|e| KtmeError::Storage(format!("Failed to set default: {}", e)))?;
 
@@ -512,8 +454,8 @@ impl ProviderConfigRepository {
 
         let rows = conn
             .execute(
-                "DELETE FROM provider_configs WHERE provider_type = ?1",
-                params![provider_type],
+                "DELETE FROM provider_configs WHERE workspace = ?1 AND provider_type = ?2",
+                params![self.db.workspace(), provider_type],
             )
             .map_err(|e| KtmeError::Storage(format!("Failed to delete provider: {}", e)))?;
 
@@ -522,129 +464,803 @@ impl ProviderConfigRepository {
 }
 
 // ============================================================================
-// Generation History Repository
+// Generation History Repository
+// ============================================================================
+
+impl_from_row!(GenerationRecord {
+    id,
+    service_id,
+    provider,
+    document_id,
+    document_url,
+    action,
+    source_type,
+    source_identifier,
+    content_hash,
+    status,
+    error_message,
+    created_at,
+    model,
+    idempotency_key,
+});
+
+pub struct GenerationHistoryRepository {
+    db: Database,
+}
+
+impl GenerationHistoryRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn record(
+        &self,
+        service_id: Option<i64>,
+        provider: &str,
+        document_id: Option<&str>,
+        document_url: Option<&str>,
+        action: &str,
+        source_type: Option<&str>,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+        status: &str,
+        error_message: Option<&str>,
+        model: Option<&str>,
+        idempotency_key: Option<&str>,
+    ) -> Result<i64> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "INSERT INTO generation_history
+             (service_id, provider, document_id, document_url, action, source_type,
+              source_identifier, content_hash, status, error_message, workspace, model,
+              idempotency_key)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                service_id,
+                provider,
+                document_id,
+                document_url,
+                action,
+                source_type,
+                source_identifier,
+                content_hash,
+                status,
+                error_message,
+                self.db.workspace(),
+                model,
+                idempotency_key
+            ],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to record history: {}", e)))?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_recent(&self, limit: usize) -> Result<Vec<GenerationRecord>> {
+        let conn = self.db.connection()?;
+
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM generation_history
+                 WHERE workspace = ?1
+                 ORDER BY created_at DESC
+                 LIMIT ?2",
+                GenerationRecord::columns()
+            ),
+            params![self.db.workspace(), limit as i64],
+            "history",
+        )
+    }
+
+    /// Latest successful generation that wrote `source_identifier`, or
+    /// content hashing to `content_hash`, to the document at `location`
+    pub fn find_documented(
+        &self,
+        location: &str,
+        source_identifier: Option<&str>,
+        content_hash: Option<&str>,
+    ) -> Result<Option<GenerationRecord>> {
+        self.find_latest(
+            "(source_identifier = ?3 OR content_hash = ?4)",
+            params![
+                self.db.workspace(),
+                location,
+                source_identifier,
+                content_hash
+            ],
+        )
+    }
+
+    /// Latest successful generation with `idempotency_key` that wrote to the
+    /// document at `location`
+    pub fn find_by_key(
+        &self,
+        location: &str,
+        idempotency_key: &str,
+    ) -> Result<Option<GenerationRecord>> {
+        self.find_latest(
+            "idempotency_key = ?3",
+            params![self.db.workspace(), location, idempotency_key],
+        )
+    }
+
+    /// Latest successful generation of the workspace (`?1`) for the document
+    /// at `?2` matching `condition`
+    fn find_latest(
+        &self,
+        condition: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Option<GenerationRecord>> {
+        let conn = self.db.connection()?;
+
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM generation_history
+                 WHERE workspace = ?1 AND document_url = ?2 AND status = 'success' AND {}
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT 1",
+                GenerationRecord::columns(),
+                condition
+            ),
+            params,
+            "history",
+        )
+    }
+
+    pub fn get_for_service(&self, service_id: i64, limit: usize) -> Result<Vec<GenerationRecord>> {
+        let conn = self.db.connection()?;
+
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM generation_history
+                 WHERE service_id = ?1
+                 ORDER BY created_at DESC
+                 LIMIT ?2",
+                GenerationRecord::columns()
+            ),
+            params![service_id, limit as i64],
+            "history",
+        )
+    }
+}
+
+// ============================================================================
+// Pending Documentation Repository
+// ============================================================================
+
+impl_from_row!(PendingDoc {
+    id,
+    service,
+    doc_type,
+    source_identifier,
+    target,
+    content,
+    status,
+    reviewer,
+    note,
+    created_at,
+    reviewed_at,
+});
+
+pub struct PendingDocRepository {
+    db: Database,
+}
+
+impl PendingDocRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn create(
+        &self,
+        service: &str,
+        doc_type: &str,
+        source_identifier: Option<&str>,
+        target: Option<&str>,
+        content: &str,
+    ) -> Result<i64> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "INSERT INTO pending_docs (workspace, service, doc_type, source_identifier, target, content)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                self.db.workspace(),
+                service,
+                doc_type,
+                source_identifier,
+                target,
+                content
+            ],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to queue documentation: {}", e)))?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<PendingDoc>> {
+        let conn = self.db.connection()?;
+
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM pending_docs WHERE id = ?1 AND workspace = ?2",
+                PendingDoc::columns()
+            ),
+            params![id, self.db.workspace()],
+            "queued documentation",
+        )
+    }
+
+    /// Queued documentation, oldest first; every status when `status` is `None`
+    pub fn list(&self, status: Option<&str>) -> Result<Vec<PendingDoc>> {
+        let conn = self.db.connection()?;
+
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM pending_docs
+                 WHERE workspace = ?1 AND (?2 IS NULL OR status = ?2)
+                 ORDER BY id",
+                PendingDoc::columns()
+            ),
+            params![self.db.workspace(), status],
+            "review queue",
+        )
+    }
+
+    /// Move a pending item to `status`; false when it is not pending
+    pub fn review(
+        &self,
+        id: i64,
+        status: &str,
+        reviewer: Option<&str>,
+        note: Option<&str>,
+    ) -> Result<bool> {
+        let conn = self.db.connection()?;
+
+        let rows = conn
+            .execute(
+                "UPDATE pending_docs
+                 SET status = ?1, reviewer = ?2, note = ?3, reviewed_at = CURRENT_TIMESTAMP
+                 WHERE id = ?4 AND workspace = ?5 AND status = 'pending'",
+                params![status, reviewer, note, id, self.db.workspace()],
+            )
+            .map_err(|e| {
+                KtmeError::Storage(format!("Failed to review queued documentation: {}", e))
+            })?;
+
+        Ok(rows > 0)
+    }
+}
+
+// ============================================================================
+// Job Repository
+// ============================================================================
+
+impl_from_row!(Job {
+    id,
+    kind,
+    description,
+    params,
+    status,
+    error,
+    created_at,
+    updated_at,
+} with {
+    steps: Vec::new(),
+});
+
+impl_from_row!(JobStep {
+    position,
+    name,
+    status,
+    error,
+    updated_at,
+});
+
+pub struct JobRepository {
+    db: Database,
+}
+
+impl JobRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Record a running job with `steps`, all pending
+    pub fn create(
+        &self,
+        kind: &str,
+        description: &str,
+        params: &str,
+        steps: &[String],
+    ) -> Result<i64> {
+        let conn = self.db.connection()?;
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| KtmeError::Storage(format!("Failed to begin transaction: {}", e)))?;
+
+        tx.execute(
+            "INSERT INTO jobs (workspace, kind, description, params) VALUES (?1, ?2, ?3, ?4)",
+            params![self.db.workspace(), kind, description, params],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to create job: {}", e)))?;
+        let id = tx.last_insert_rowid();
+        for (position, name) in steps.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO job_steps (job_id, position, name) VALUES (?1, ?2, ?3)",
+                params![id, position as i64, name],
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to create job step: {}", e)))?;
+        }
+        tx.commit()
+            .map_err(|e| KtmeError::Storage(format!("Failed to create job: {}", e)))?;
+
+        Ok(id)
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<Job>> {
+        let conn = self.db.connection()?;
+
+        let job = query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM jobs WHERE id = ?1 AND workspace = ?2",
+                Job::columns()
+            ),
+            params![id, self.db.workspace()],
+            "job",
+        )?;
+
+        job.map(|job| Self::with_steps(&conn, job)).transpose()
+    }
+
+    /// Jobs, newest first; every status when `status` is `None`
+    pub fn list(&self, status: Option<&str>) -> Result<Vec<Job>> {
+        let conn = self.db.connection()?;
+
+        let jobs: Vec<Job> = query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM jobs
+                 WHERE workspace = ?1 AND (?2 IS NULL OR status = ?2)
+                 ORDER BY id DESC",
+                Job::columns()
+            ),
+            params![self.db.workspace(), status],
+            "jobs",
+        )?;
+
+        jobs.into_iter()
+            .map(|job| Self::with_steps(&conn, job))
+            .collect()
+    }
+
+    /// Record the outcome of step `position` of job `id`
+    pub fn update_step(
+        &self,
+        id: i64,
+        position: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "UPDATE job_steps SET status = ?1, error = ?2, updated_at = CURRENT_TIMESTAMP
+             WHERE job_id = ?3 AND position = ?4",
+            params![status, error, id, position],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to update job step: {}", e)))?;
+        conn.execute(
+            "UPDATE jobs SET updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to update job: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Move job `id` to `status`
+    pub fn update_status(&self, id: i64, status: &str, error: Option<&str>) -> Result<()> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "UPDATE jobs SET status = ?1, error = ?2, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?3 AND workspace = ?4",
+            params![status, error, id, self.db.workspace()],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to update job: {}", e)))?;
+
+        Ok(())
+    }
+
+    fn with_steps(conn: &rusqlite::Connection, mut job: Job) -> Result<Job> {
+        job.steps = query_all(
+            conn,
+            &format!(
+                "SELECT {} FROM job_steps WHERE job_id = ?1 ORDER BY position",
+                JobStep::columns()
+            ),
+            params![job.id],
+            "job steps",
+        )?;
+
+        Ok(job)
+    }
+}
+
+// ============================================================================
+// MCP Audit Repository
+// ============================================================================
+
+impl_from_row!(ToolCall {
+    id,
+    tool,
+    arguments,
+    transport,
+    duration_ms,
+    status,
+    error,
+    created_at,
+});
+
+pub struct McpAuditRepository {
+    db: Database,
+}
+
+impl McpAuditRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    pub fn record(
+        &self,
+        tool: &str,
+        arguments: &str,
+        transport: &str,
+        duration_ms: i64,
+        status: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "INSERT INTO mcp_audit (workspace, tool, arguments, transport, duration_ms, status, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.db.workspace(),
+                tool,
+                arguments,
+                transport,
+                duration_ms,
+                status,
+                error
+            ],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to record tool call: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Tool calls made since `since`, newest first
+    pub fn since(&self, since: DateTime<Utc>) -> Result<Vec<ToolCall>> {
+        let conn = self.db.connection()?;
+
+        let since = since.format("%Y-%m-%d %H:%M:%S").to_string();
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM mcp_audit
+                 WHERE workspace = ?1 AND created_at >= ?2
+                 ORDER BY created_at DESC, id DESC",
+                ToolCall::columns()
+            ),
+            params![self.db.workspace(), since],
+            "tool calls",
+        )
+    }
+}
+
+// ============================================================================
+// Service Detection Repository
+// ============================================================================
+
+impl_from_row!(ServiceDetection {
+    id,
+    directory,
+    service_name,
+    pinned,
+    created_at,
+    updated_at,
+});
+
+pub struct ServiceDetectionRepository {
+    db: Database,
+}
+
+impl ServiceDetectionRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Record `service_name` for `directory`, replacing the previous
+    /// detection or pin
+    pub fn save(&self, directory: &str, service_name: &str, pinned: bool) -> Result<()> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "INSERT INTO service_detections (workspace, directory, service_name, pinned)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(workspace, directory, pinned) DO UPDATE SET
+                service_name = excluded.service_name,
+                updated_at = CURRENT_TIMESTAMP",
+            params![self.db.workspace(), directory, service_name, pinned],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to save service detection: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, directory: &str, pinned: bool) -> Result<Option<ServiceDetection>> {
+        let conn = self.db.connection()?;
+
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM service_detections
+                 WHERE workspace = ?1 AND directory = ?2 AND pinned = ?3",
+                ServiceDetection::columns()
+            ),
+            params![self.db.workspace(), directory, pinned],
+            "service detection",
+        )
+    }
+
+    pub fn remove(&self, directory: &str, pinned: bool) -> Result<bool> {
+        let conn = self.db.connection()?;
+
+        let rows = conn
+            .execute(
+                "DELETE FROM service_detections
+                 WHERE workspace = ?1 AND directory = ?2 AND pinned = ?3",
+                params![self.db.workspace(), directory, pinned],
+            )
+            .map_err(|e| {
+                KtmeError::Storage(format!("Failed to remove service detection: {}", e))
+            })?;
+
+        Ok(rows > 0)
+    }
+
+    /// Forget the detections (not the pins) of the directories in
+    /// `directory`, e.g. after its manifests changed
+    pub fn clear_detected(&self, directory: &str) -> Result<u64> {
+        let conn = self.db.connection()?;
+
+        let rows = conn
+            .execute(
+                "DELETE FROM service_detections
+                 WHERE workspace = ?1 AND pinned = FALSE
+                   AND (directory = ?2 OR substr(directory, 1, length(?3)) = ?3)",
+                params![
+                    self.db.workspace(),
+                    directory,
+                    format!("{}/", directory.trim_end_matches('/'))
+                ],
+            )
+            .map_err(|e| {
+                KtmeError::Storage(format!("Failed to clear service detections: {}", e))
+            })?;
+
+        Ok(rows as u64)
+    }
+}
+
+// ============================================================================
+// Generation Lock Repository
+// ============================================================================
+
+impl_from_row!(GenerationLock {
+    id,
+    resource,
+    owner,
+    pid,
+    purpose,
+    acquired_at,
+    expires_at,
+});
+
+pub struct GenerationLockRepository {
+    db: Database,
+}
+
+impl GenerationLockRepository {
+    pub fn new(db: Database) -> Self {
+        Self { db }
+    }
+
+    /// Take the lock of `resource` for `owner` until `expires_at`, unless
+    /// another owner holds it and it has not expired. Returns whether the
+    /// lock was taken; taking it again as its owner extends it.
+    pub fn try_acquire(
+        &self,
+        resource: &str,
+        owner: &str,
+        purpose: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let conn = self.db.connection()?;
+
+        let rows = conn
+            .execute(
+                "INSERT INTO generation_locks
+                    (workspace, resource, owner, pid, purpose, acquired_at, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(workspace, resource) DO UPDATE SET
+                    owner = excluded.owner,
+                    pid = excluded.pid,
+                    purpose = excluded.purpose,
+                    acquired_at = excluded.acquired_at,
+                    expires_at = excluded.expires_at
+                 WHERE generation_locks.owner = excluded.owner
+                    OR generation_locks.expires_at <= excluded.acquired_at",
+                params![
+                    self.db.workspace(),
+                    resource,
+                    owner,
+                    std::process::id(),
+                    purpose,
+                    Utc::now(),
+                    expires_at
+                ],
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to acquire lock: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+
+    /// Current holder of the lock of `resource`, expired or not
+    pub fn holder(&self, resource: &str) -> Result<Option<GenerationLock>> {
+        let conn = self.db.connection()?;
+
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM generation_locks WHERE workspace = ?1 AND resource = ?2",
+                GenerationLock::columns()
+            ),
+            params![self.db.workspace(), resource],
+            "generation lock",
+        )
+    }
+
+    /// Release the lock of `resource` if `owner` still holds it
+    pub fn release(&self, resource: &str, owner: &str) -> Result<bool> {
+        let conn = self.db.connection()?;
+
+        let rows = conn
+            .execute(
+                "DELETE FROM generation_locks
+                 WHERE workspace = ?1 AND resource = ?2 AND owner = ?3",
+                params![self.db.workspace(), resource, owner],
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to release lock: {}", e)))?;
+
+        Ok(rows > 0)
+    }
+}
+
+// ============================================================================
+// Translation Repository
 // ============================================================================
 
-pub struct GenerationHistoryRepository {
+impl_from_row!(Translation {
+    id,
+    service_name,
+    source,
+    target_type,
+    target,
+    language,
+    sections => json_or_default,
+    synced_at,
+    created_at,
+});
+
+pub struct TranslationRepository {
     db: Database,
 }
 
-impl GenerationHistoryRepository {
+impl TranslationRepository {
     pub fn new(db: Database) -> Self {
         Self { db }
     }
 
-    pub fn record(
+    /// Pair the document at `target` with the `source` document of
+    /// `service`; pairing a target again replaces its source and language
+    /// and forgets its translated sections
+    pub fn add(
         &self,
-        service_id: Option<i64>,
-        provider: &str,
-        document_id: Option<&str>,
-        document_url: Option<&str>,
-        action: &str,
-        source_type: Option<&str>,
-        source_identifier: Option<&str>,
-        content_hash: Option<&str>,
-        status: &str,
-        error_message: Option<&str>,
-    ) -> Result<i64> {
+        service: &str,
+        source: &str,
+        target_type: &str,
+        target: &str,
+        language: &str,
+    ) -> Result<()> {
         let conn = self.db.connection()?;
 
         conn.execute(
-            "INSERT INTO generation_history
-             (service_id, provider, document_id, document_url, action, source_type,
-              source_identifier, content_hash, status, error_message)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            "INSERT INTO translations
+                (workspace, service_name, source, target_type, target, language)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(workspace, target_type, target) DO UPDATE SET
+                service_name = excluded.service_name,
+                source = excluded.source,
+                language = excluded.language,
+                sections = '{}',
+                synced_at = NULL",
             params![
-                service_id,
-                provider,
-                document_id,
-                document_url,
-                action,
-                source_type,
-                source_identifier,
-                content_hash,
-                status,
-                error_message
+                self.db.workspace(),
+                service,
+                source,
+                target_type,
+                target,
+                language
             ],
         )
-        .map_err(|e| KtmeError::Storage(format!("Failed to record history: {}", e)))?;
+        .map_err(|e| KtmeError::Storage(format!("Failed to add translation: {}", e)))?;
 
-        Ok(conn.last_insert_rowid())
+        Ok(())
     }
 
-    pub fn get_recent(&self, limit: usize) -> Result<Vec<GenerationRecord>> {
+    /// Translations of `service`, only those of `source` when given
+    pub fn list(&self, service: &str, source: Option<&str>) -> Result<Vec<Translation>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, service_id, provider, document_id, document_url, action,
-                        source_type, source_identifier, content_hash, status, error_message, created_at
-                 FROM generation_history
-                 ORDER BY created_at DESC
-                 LIMIT ?1",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM translations
+                 WHERE workspace = ?1 AND service_name = ?2 AND (?3 IS NULL OR source = ?3)
+                 ORDER BY source, target",
+                Translation::columns()
+            ),
+            params![self.db.workspace(), service, source],
+            "translations",
+        )
+    }
 
-        let records = stmt
-            .query_map(params![limit as i64], |row| {
-                Ok(GenerationRecord {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    provider: row.get(2)?,
-                    document_id: row.get(3)?,
-                    document_url: row.get(4)?,
-                    action: row.get(5)?,
-                    source_type: row.get(6)?,
-                    source_identifier: row.get(7)?,
-                    content_hash: row.get(8)?,
-                    status: row.get(9)?,
-                    error_message: row.get(10)?,
-                    created_at: row.get(11)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query history: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect history: {}", e)))?;
+    /// Record the translated sections of translation `id` as synced now
+    pub fn save_sections(&self, id: i64, sections: &BTreeMap<String, String>) -> Result<()> {
+        let conn = self.db.connection()?;
+        let sections = serde_json::to_string(sections)?;
+
+        conn.execute(
+            "UPDATE translations SET sections = ?1, synced_at = ?2 WHERE id = ?3",
+            params![sections, Utc::now(), id],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to save translation: {}", e)))?;
 
-        Ok(records)
+        Ok(())
     }
 
-    pub fn get_for_service(&self, service_id: i64, limit: usize) -> Result<Vec<GenerationRecord>> {
+    /// Remove the translations of `service`
+    pub fn remove_service(&self, service: &str) -> Result<u64> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, service_id, provider, document_id, document_url, action,
-                        source_type, source_identifier, content_hash, status, error_message, created_at
-                 FROM generation_history
-                 WHERE service_id = ?1
-                 ORDER BY created_at DESC
-                 LIMIT ?2",
+        let rows = conn
+            .execute(
+                "DELETE FROM translations WHERE workspace = ?1 AND service_name = ?2",
+                params![self.db.workspace(), service],
             )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let records = stmt
-            .query_map(params![service_id, limit as i64], |row| {
-                Ok(GenerationRecord {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    provider: row.get(2)?,
-                    document_id: row.get(3)?,
-                    document_url: row.get(4)?,
-                    action: row.get(5)?,
-                    source_type: row.get(6)?,
-                    source_identifier: row.get(7)?,
-                    content_hash: row.get(8)?,
-                    status: row.get(9)?,
-                    error_message: row.get(10)?,
-                    created_at: row.get(11)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query history: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, _>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect history: {}", e)))?;
+            .map_err(|e| KtmeError::Storage(format!("Failed to remove translations: {}", e)))?;
 
-        Ok(records)
+        Ok(rows as u64)
     }
 }
 
@@ -652,6 +1268,16 @@ impl GenerationHistoryRepository {
 // Diff Cache Repository
 // ============================================================================
 
+impl_from_row!(DiffCache {
+    id,
+    source_type,
+    source_identifier,
+    repository_path,
+    diff_json,
+    expires_at,
+    created_at,
+});
+
 pub struct DiffCacheRepository {
     db: Database,
 }
@@ -672,13 +1298,21 @@ impl DiffCacheRepository {
         let conn = self.db.connection()?;
 
         conn.execute(
-            "INSERT INTO diff_cache (source_type, source_identifier, repository_path, diff_json, expires_at)
-             VALUES (?1, ?2, ?3, ?4, ?5)
-             ON CONFLICT(source_type, source_identifier, repository_path) DO UPDATE SET
+            "INSERT INTO diff_cache
+                (workspace, source_type, source_identifier, repository_path, diff_json, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(workspace, source_type, source_identifier, repository_path) DO UPDATE SET
                 diff_json = excluded.diff_json,
                 expires_at = excluded.expires_at,
                 created_at = CURRENT_TIMESTAMP",
-            params![source_type, source_identifier, repository_path, diff_json, expires_at],
+            params![
+                self.db.workspace(),
+                source_type,
+                source_identifier,
+                repository_path,
+                diff_json,
+                expires_at
+            ],
         )
         .map_err(|e| KtmeError::Storage(format!("Failed to cache diff: {}", e)))?;
 
@@ -693,31 +1327,23 @@ impl DiffCacheRepository {
     ) -> Result<Option<DiffCache>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, source_type, source_identifier, repository_path, diff_json, expires_at, created_at
-             FROM diff_cache
-             WHERE source_type = ?1 AND source_identifier = ?2
-               AND (repository_path = ?3 OR (repository_path IS NULL AND ?3 IS NULL))
-               AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
-            params![source_type, source_identifier, repository_path],
-            |row| {
-                Ok(DiffCache {
-                    id: row.get(0)?,
-                    source_type: row.get(1)?,
-                    source_identifier: row.get(2)?,
-                    repository_path: row.get(3)?,
-                    diff_json: row.get(4)?,
-                    expires_at: row.get(5)?,
-                    created_at: row.get(6)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(cache) => Ok(Some(cache)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get cache: {}", e))),
-        }
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM diff_cache
+                 WHERE workspace = ?1 AND source_type = ?2 AND source_identifier = ?3
+                   AND (repository_path = ?4 OR (repository_path IS NULL AND ?4 IS NULL))
+                   AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)",
+                DiffCache::columns()
+            ),
+            params![
+                self.db.workspace(),
+                source_type,
+                source_identifier,
+                repository_path
+            ],
+            "cache",
+        )
     }
 
     pub fn clear_expired(&self) -> Result<u64> {
@@ -725,8 +1351,9 @@ impl DiffCacheRepository {
 
         let rows = conn
             .execute(
-                "DELETE FROM diff_cache WHERE expires_at IS NOT NULL AND expires_at <= CURRENT_TIMESTAMP",
-                [],
+                "DELETE FROM diff_cache
+                 WHERE workspace = ?1 AND expires_at IS NOT NULL AND expires_at <= CURRENT_TIMESTAMP",
+                params![self.db.workspace()],
             )
             .map_err(|e| KtmeError::Storage(format!("Failed to clear cache: {}", e)))?;
 
@@ -737,7 +1364,10 @@ impl DiffCacheRepository {
         let conn = self.db.connection()?;
 
         let rows = conn
-            .execute("DELETE FROM diff_cache", [])
+            .execute(
+                "DELETE FROM diff_cache WHERE workspace = ?1",
+                params![self.db.workspace()],
+            )
             .map_err(|e| KtmeError::Storage(format!("Failed to clear cache: {}", e)))?;
 
         Ok(rows as u64)
@@ -748,6 +1378,26 @@ impl DiffCacheRepository {
 // Feature Repository
 // ============================================================================
 
+fn feature_type(raw: String) -> FeatureType {
+    FeatureType::from(raw.as_str())
+}
+
+impl_from_row!(Feature {
+    id,
+    service_id,
+    name,
+    description,
+    feature_type => feature_type,
+    tags => json_or_default,
+    metadata => json_or_default,
+    relevance_score,
+    created_at,
+    updated_at,
+} with {
+    // Embeddings are loaded separately, by the vector search
+    embedding: None,
+});
+
 pub struct FeatureRepository {
     db: Database,
 }
@@ -788,153 +1438,37 @@ impl FeatureRepository {
         .map_err(|e| KtmeError::Storage(format!("Failed to create feature: {}", e)))?;
 
         // Query the created feature in the same transaction
-        let result = conn.query_row(
-            "SELECT id, service_id, name, description, feature_type, tags, metadata, relevance_score, created_at, updated_at
-             FROM features WHERE id = ?1",
+        conn.query_row(
+            &format!("SELECT {} FROM features WHERE id = ?1", Feature::columns()),
             params![id],
-            |row| {
-                let tags_json: String = row.get(5)?;
-                let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-                let metadata_json: String = row.get(6)?;
-                let metadata: serde_json::Value = serde_json::from_str(&metadata_json).unwrap_or_default();
-                let feature_type_str: String = row.get(4)?;
-                let feature_type = match feature_type_str.as_str() {
-                    "api" => FeatureType::Api,
-                    "ui" => FeatureType::Ui,
-                    "business_logic" => FeatureType::BusinessLogic,
-                    "config" => FeatureType::Config,
-                    "database" => FeatureType::Database,
-                    "security" => FeatureType::Security,
-                    "performance" => FeatureType::Performance,
-                    "testing" => FeatureType::Testing,
-                    "deployment" => FeatureType::Deployment,
-                    _ => FeatureType::Other,
-                };
-
-                Ok(Feature {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    name: row.get(2)?,
-                    description: row.get(3)?,
-                    feature_type,
-                    tags,
-                    metadata,
-                    relevance_score: row.get(7)?,
-                    embedding: None,  // Not loading BLOB for now
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(feature) => Ok(feature),
-            Err(e) => Err(KtmeError::Storage(format!(
-                "Failed to retrieve created feature: {}",
-                e
-            ))),
-        }
+            Feature::from_row,
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to retrieve created feature: {}", e)))
     }
 
     pub fn get_by_id(&self, id: &str) -> Result<Option<Feature>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, service_id, name, description, feature_type, tags, metadata, relevance_score, created_at, updated_at
-             FROM features WHERE id = ?1",
+        query_optional(
+            &conn,
+            &format!("SELECT {} FROM features WHERE id = ?1", Feature::columns()),
             params![id],
-            |row| {
-                let tags_json: String = row.get(5)?;
-                let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-                let metadata_json: String = row.get(6)?;
-                let metadata: serde_json::Value = serde_json::from_str(&metadata_json).unwrap_or_default();
-                let feature_type_str: String = row.get(4)?;
-                let feature_type = match feature_type_str.as_str() {
-                    "api" => FeatureType::Api,
-                    "ui" => FeatureType::Ui,
-                    "business_logic" => FeatureType::BusinessLogic,
-                    "config" => FeatureType::Config,
-                    "database" => FeatureType::Database,
-                    "security" => FeatureType::Security,
-                    "performance" => FeatureType::Performance,
-                    "testing" => FeatureType::Testing,
-                    "deployment" => FeatureType::Deployment,
-                    _ => FeatureType::Other,
-                };
-
-                Ok(Feature {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    name: row.get(2)?,
-                    description: row.get(3)?,
-                    feature_type,
-                    tags,
-                    metadata,
-                    relevance_score: row.get(7)?,
-                    embedding: None,  // Not loading BLOB for now
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                })
-            },
-        );
-
-        match result {
-            Ok(feature) => Ok(Some(feature)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!("Failed to get feature: {}", e))),
-        }
+            "feature",
+        )
     }
 
     pub fn list_by_service(&self, service_id: i64) -> Result<Vec<Feature>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, service_id, name, description, feature_type, tags, metadata, relevance_score, created_at, updated_at
-                 FROM features WHERE service_id = ?1 ORDER BY name",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let features = stmt
-            .query_map(params![service_id], |row| {
-                let tags_json: String = row.get(5)?;
-                let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-                let metadata_json: String = row.get(6)?;
-                let metadata: serde_json::Value =
-                    serde_json::from_str(&metadata_json).unwrap_or_default();
-                let feature_type_str: String = row.get(4)?;
-                let feature_type = match feature_type_str.as_str() {
-                    "api" => FeatureType::Api,
-                    "ui" => FeatureType::Ui,
-                    "business_logic" => FeatureType::BusinessLogic,
-                    "config" => FeatureType::Config,
-                    "database" => FeatureType::Database,
-                    "security" => FeatureType::Security,
-                    "performance" => FeatureType::Performance,
-                    "testing" => FeatureType::Testing,
-                    "deployment" => FeatureType::Deployment,
-                    _ => FeatureType::Other,
-                };
-
-                Ok(Feature {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    name: row.get(2)?,
-                    description: row.get(3)?,
-                    feature_type,
-                    tags,
-                    metadata,
-                    relevance_score: row.get(7)?,
-                    embedding: None,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query features: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect features: {}", e)))?;
-
-        Ok(features)
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM features WHERE service_id = ?1 ORDER BY name",
+                Feature::columns()
+            ),
+            params![service_id],
+            "features",
+        )
     }
 
     pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
@@ -950,20 +1484,20 @@ impl FeatureRepository {
                 f.name as feature_name,
                 f.feature_type,
                 f.description,
-                si.content,
+                COALESCE(si.content, f.description, f.name),
                 f.relevance_score,
-                si.content_type,
+                COALESCE(si.content_type, 'feature_name'),
                 dm.location as path,
                 f.tags
             FROM features f
             JOIN services s ON f.service_id = s.id
             LEFT JOIN search_index si ON f.id = si.feature_id
             LEFT JOIN document_mappings dm ON f.id = dm.feature_id
-            WHERE 1=1
+            WHERE s.workspace = ?
         "
         .to_string();
 
-        let mut params = Vec::new();
+        let mut params = vec![self.db.workspace().to_string()];
 
         // Add service filter
         if let Some(service_ids) = &query.service_ids {
@@ -1006,15 +1540,21 @@ impl FeatureRepository {
             params.extend(vec![like_query.clone(), like_query.clone(), like_query]);
         }
 
-        // Add ordering and limit
-        sql.push_str(" ORDER BY f.relevance_score DESC, f.name LIMIT ?");
+        if let Some(min_score) = query.min_score {
+            sql.push_str(" AND f.relevance_score >= ?");
+            params.push(min_score.to_string());
+        }
+
+        // Add ordering and paging
+        sql.push_str(" ORDER BY f.relevance_score DESC, f.name LIMIT ? OFFSET ?");
 
         let mut stmt = conn
             .prepare(&sql)
             .map_err(|e| KtmeError::Storage(format!("Failed to prepare search query: {}", e)))?;
 
-        // Add limit parameter
+        // Add paging parameters
         params.push(limit.to_string());
+        params.push(query.offset.unwrap_or(0).to_string());
 
         let param_refs: Vec<&dyn rusqlite::ToSql> =
             params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
@@ -1024,28 +1564,9 @@ impl FeatureRepository {
                 let tags_json: String = row.get(9)?;
                 let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
                 let feature_type_str: String = row.get(3)?;
-                let feature_type = match feature_type_str.as_str() {
-                    "api" => FeatureType::Api,
-                    "ui" => FeatureType::Ui,
-                    "business_logic" => FeatureType::BusinessLogic,
-                    "config" => FeatureType::Config,
-                    "database" => FeatureType::Database,
-                    "security" => FeatureType::Security,
-                    "performance" => FeatureType::Performance,
-                    "testing" => FeatureType::Testing,
-                    "deployment" => FeatureType::Deployment,
-                    _ => FeatureType::Other,
-                };
+                let feature_type = FeatureType::from(feature_type_str.as_str());
                 let content_type_str: String = row.get(7)?;
-                let content_type = match content_type_str.as_str() {
-                    "feature_name" => SearchContentType::FeatureName,
-                    "feature_description" => SearchContentType::FeatureDescription,
-                    "documentation" => SearchContentType::Documentation,
-                    "code_example" => SearchContentType::CodeExample,
-                    "api_reference" => SearchContentType::ApiReference,
-                    "user_guide" => SearchContentType::UserGuide,
-                    _ => SearchContentType::Other,
-                };
+                let content_type = SearchContentType::from(content_type_str.as_str());
 
                 Ok(SearchResult {
                     feature_id: row.get(0)?,
@@ -1058,6 +1579,7 @@ impl FeatureRepository {
                     content_type,
                     path: row.get(8)?,
                     tags,
+                    related_via: None,
                 })
             })
             .map_err(|e| KtmeError::Storage(format!("Failed to execute search query: {}", e)))?
@@ -1079,6 +1601,82 @@ impl FeatureRepository {
         Ok(())
     }
 
+    /// Count a retrieval of each feature by a search or an MCP tool
+    pub fn record_usage(&self, feature_ids: &[&str]) -> Result<()> {
+        let conn = self.db.connection()?;
+
+        for id in feature_ids {
+            conn.execute(
+                "UPDATE features SET usage_count = usage_count + 1, last_used_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1",
+                params![id],
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to record feature usage: {}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Usage of every feature of the workspace since it was last ranked
+    pub fn usage(&self) -> Result<Vec<FeatureUsage>> {
+        let conn = self.db.connection()?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT f.id, f.relevance_score, f.usage_count, f.last_used_at,
+                        COALESCE(f.ranked_at, f.created_at)
+                 FROM features f
+                 JOIN services s ON f.service_id = s.id
+                 WHERE s.workspace = ?1
+                 ORDER BY f.id",
+            )
+            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
+
+        let usage = stmt
+            .query_map(params![self.db.workspace()], |row| {
+                Ok(FeatureUsage {
+                    feature_id: row.get(0)?,
+                    relevance_score: row.get(1)?,
+                    usage_count: row.get(2)?,
+                    last_used_at: row.get(3)?,
+                    ranked_since: row.get(4)?,
+                })
+            })
+            .map_err(|e| KtmeError::Storage(format!("Failed to query feature usage: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()
+            .map_err(|e| KtmeError::Storage(format!("Failed to collect feature usage: {}", e)))?;
+
+        Ok(usage)
+    }
+
+    /// Store a ranked relevance score. Only the `counted_uses` retrievals the
+    /// score accounts for are cleared, so ones recorded meanwhile still count
+    /// at the next ranking.
+    pub fn apply_ranking(
+        &self,
+        feature_id: &str,
+        score: f64,
+        counted_uses: i64,
+        ranked_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let conn = self.db.connection()?;
+
+        conn.execute(
+            "UPDATE features
+             SET relevance_score = ?1, usage_count = MAX(usage_count - ?2, 0), ranked_at = ?3
+             WHERE id = ?4",
+            params![
+                score,
+                counted_uses,
+                ranked_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                feature_id
+            ],
+        )
+        .map_err(|e| KtmeError::Storage(format!("Failed to store relevance score: {}", e)))?;
+
+        Ok(())
+    }
+
     pub fn update(
         &self,
         id: &str,
@@ -1125,56 +1723,19 @@ impl FeatureRepository {
     pub fn get_children(&self, feature_id: &str) -> Result<Vec<Feature>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT f.id, f.service_id, f.name, f.description, f.feature_type, f.tags,
-                        f.metadata, f.relevance_score, f.created_at, f.updated_at
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {}
                  FROM features f
                  JOIN feature_relations fr ON f.id = fr.child_feature_id
                  WHERE fr.parent_feature_id = ?1
                  ORDER BY fr.strength DESC, f.name",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare children query: {}", e)))?;
-
-        let features = stmt
-            .query_map(params![feature_id], |row| {
-                let tags_json: String = row.get(5)?;
-                let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-                let metadata_json: String = row.get(6)?;
-                let metadata: serde_json::Value =
-                    serde_json::from_str(&metadata_json).unwrap_or_default();
-                let feature_type_str: String = row.get(4)?;
-                let feature_type = match feature_type_str.as_str() {
-                    "api" => FeatureType::Api,
-                    "ui" => FeatureType::Ui,
-                    "business_logic" => FeatureType::BusinessLogic,
-                    "config" => FeatureType::Config,
-                    "database" => FeatureType::Database,
-                    "security" => FeatureType::Security,
-                    "performance" => FeatureType::Performance,
-                    "testing" => FeatureType::Testing,
-                    "deployment" => FeatureType::Deployment,
-                    _ => FeatureType::Other,
-                };
-                Ok(Feature {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    name: row.get(2)?,
-                    description: row.get(3)?,
-                    feature_type,
-                    tags,
-                    metadata,
-                    relevance_score: row.get(7)?,
-                    embedding: None,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query children: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect children: {}", e)))?;
-
-        Ok(features)
+                Feature::columns_of("f")
+            ),
+            params![feature_id],
+            "children",
+        )
     }
 
     /// Returns direct parents of the feature as defined by feature_relations rows where
@@ -1182,56 +1743,19 @@ impl FeatureRepository {
     pub fn get_parents(&self, feature_id: &str) -> Result<Vec<Feature>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT f.id, f.service_id, f.name, f.description, f.feature_type, f.tags,
-                        f.metadata, f.relevance_score, f.created_at, f.updated_at
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {}
                  FROM features f
                  JOIN feature_relations fr ON f.id = fr.parent_feature_id
                  WHERE fr.child_feature_id = ?1
                  ORDER BY fr.strength DESC, f.name",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare parents query: {}", e)))?;
-
-        let features = stmt
-            .query_map(params![feature_id], |row| {
-                let tags_json: String = row.get(5)?;
-                let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-                let metadata_json: String = row.get(6)?;
-                let metadata: serde_json::Value =
-                    serde_json::from_str(&metadata_json).unwrap_or_default();
-                let feature_type_str: String = row.get(4)?;
-                let feature_type = match feature_type_str.as_str() {
-                    "api" => FeatureType::Api,
-                    "ui" => FeatureType::Ui,
-                    "business_logic" => FeatureType::BusinessLogic,
-                    "config" => FeatureType::Config,
-                    "database" => FeatureType::Database,
-                    "security" => FeatureType::Security,
-                    "performance" => FeatureType::Performance,
-                    "testing" => FeatureType::Testing,
-                    "deployment" => FeatureType::Deployment,
-                    _ => FeatureType::Other,
-                };
-                Ok(Feature {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    name: row.get(2)?,
-                    description: row.get(3)?,
-                    feature_type,
-                    tags,
-                    metadata,
-                    relevance_score: row.get(7)?,
-                    embedding: None,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                })
-            })
-            .map_err(|e| KtmeError::Storage(format!("Failed to query parents: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect parents: {}", e)))?;
-
-        Ok(features)
+                Feature::columns_of("f")
+            ),
+            params![feature_id],
+            "parents",
+        )
     }
 
     /// Insert or replace a search index entry for this feature.
@@ -1262,6 +1786,20 @@ impl FeatureRepository {
 // Feature Relation Repository
 // ============================================================================
 
+fn relation_type(raw: String) -> RelationType {
+    raw.parse().unwrap_or(RelationType::Other)
+}
+
+impl_from_row!(FeatureRelation {
+    id,
+    parent_feature_id,
+    child_feature_id,
+    relation_type => relation_type,
+    strength,
+    metadata => json_or_default,
+    created_at,
+});
+
 pub struct FeatureRelationRepository {
     db: Database,
 }
@@ -1297,6 +1835,7 @@ impl FeatureRelationRepository {
         )
         .map_err(|e| KtmeError::Storage(format!("Failed to create feature relation: {}", e)))?;
 
+        drop(conn);
         self.get_by_id(id)?
             .ok_or_else(|| KtmeError::Storage("Failed to retrieve created feature relation".into()))
     }
@@ -1304,61 +1843,45 @@ impl FeatureRelationRepository {
     pub fn get_by_id(&self, id: &str) -> Result<Option<FeatureRelation>> {
         let conn = self.db.connection()?;
 
-        let result = conn.query_row(
-            "SELECT id, parent_feature_id, child_feature_id, relation_type, strength, metadata, created_at
-             FROM feature_relations WHERE id = ?1",
+        query_optional(
+            &conn,
+            &format!(
+                "SELECT {} FROM feature_relations WHERE id = ?1",
+                FeatureRelation::columns()
+            ),
             params![id],
-            Self::map_row,
-        );
-
-        match result {
-            Ok(rel) => Ok(Some(rel)),
-            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-            Err(e) => Err(KtmeError::Storage(format!(
-                "Failed to get feature relation: {}",
-                e
-            ))),
-        }
+            "feature relation",
+        )
     }
 
     pub fn list_for_parent(&self, parent_feature_id: &str) -> Result<Vec<FeatureRelation>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, parent_feature_id, child_feature_id, relation_type, strength, metadata, created_at
-                 FROM feature_relations WHERE parent_feature_id = ?1
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM feature_relations WHERE parent_feature_id = ?1
                  ORDER BY strength DESC",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let relations = stmt
-            .query_map(params![parent_feature_id], Self::map_row)
-            .map_err(|e| KtmeError::Storage(format!("Failed to query relations: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect relations: {}", e)))?;
-
-        Ok(relations)
+                FeatureRelation::columns()
+            ),
+            params![parent_feature_id],
+            "relations",
+        )
     }
 
     pub fn list_for_child(&self, child_feature_id: &str) -> Result<Vec<FeatureRelation>> {
         let conn = self.db.connection()?;
 
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, parent_feature_id, child_feature_id, relation_type, strength, metadata, created_at
-                 FROM feature_relations WHERE child_feature_id = ?1
+        query_all(
+            &conn,
+            &format!(
+                "SELECT {} FROM feature_relations WHERE child_feature_id = ?1
                  ORDER BY strength DESC",
-            )
-            .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
-
-        let relations = stmt
-            .query_map(params![child_feature_id], Self::map_row)
-            .map_err(|e| KtmeError::Storage(format!("Failed to query relations: {}", e)))?
-            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()
-            .map_err(|e| KtmeError::Storage(format!("Failed to collect relations: {}", e)))?;
-
-        Ok(relations)
+                FeatureRelation::columns()
+            ),
+            params![child_feature_id],
+            "relations",
+        )
     }
 
     pub fn delete(&self, id: &str) -> Result<bool> {
@@ -1385,32 +1908,6 @@ impl FeatureRelationRepository {
 
         Ok(rows > 0)
     }
-
-    fn map_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<FeatureRelation> {
-        let relation_type_str: String = row.get(3)?;
-        let relation_type = match relation_type_str.as_str() {
-            "depends_on" => RelationType::DependsOn,
-            "implements" => RelationType::Implements,
-            "extends" => RelationType::Extends,
-            "uses" => RelationType::Uses,
-            "configures" => RelationType::Configures,
-            "tests" => RelationType::Tests,
-            "deploys" => RelationType::Deploys,
-            _ => RelationType::Other,
-        };
-        let metadata_json: String = row.get(5)?;
-        let metadata: serde_json::Value = serde_json::from_str(&metadata_json).unwrap_or_default();
-
-        Ok(FeatureRelation {
-            id: row.get(0)?,
-            parent_feature_id: row.get(1)?,
-            child_feature_id: row.get(2)?,
-            relation_type,
-            strength: row.get(4)?,
-            metadata,
-            created_at: row.get(6)?,
-        })
-    }
 }
 
 #[cfg(test)]
@@ -1458,6 +1955,137 @@ mod tests {
         assert!(not_found.is_none());
     }
 
+    #[test]
+    fn test_workspace_isolation() {
+        let db = setup_db();
+        let default_repo = ServiceRepository::new(db.clone());
+        let client_repo = ServiceRepository::new(db.clone().with_workspace("client-a"));
+        let client_history = GenerationHistoryRepository::new(db.with_workspace("client-a"));
+
+        // The same service name can exist in both workspaces
+        let default_service = default_repo
+            .create("api", None, None)
+            .expect("Failed to create service");
+        let client_service = client_repo
+            .create("api", Some("/clients/a/api"), None)
+            .expect("Failed to create service in workspace");
+        assert_ne!(default_service.id, client_service.id);
+
+        assert_eq!(default_repo.list().expect("list failed").len(), 1);
+        let found = client_repo
+            .get_by_name("api")
+            .expect("Query failed")
+            .expect("Service not found");
+        assert_eq!(found.id, client_service.id);
+
+        client_history
+            .record(
+                Some(client_service.id),
+                "markdown",
+                None,
+                None,
+                "create",
+                None,
+                None,
+                None,
+                "success",
+                None,
+                None,
+                None,
+            )
+            .expect("Failed to record history");
+        assert_eq!(
+            client_history.get_recent(10).expect("query failed").len(),
+            1
+        );
+
+        // Deleting in one workspace leaves the other untouched
+        assert!(client_repo.delete("api").expect("delete failed"));
+        assert!(default_repo
+            .get_by_name("api")
+            .expect("Query failed")
+            .is_some());
+    }
+
+    #[test]
+    fn test_provider_configs_and_diff_cache_workspace_isolation() {
+        let db = setup_db();
+        let default_providers = ProviderConfigRepository::new(db.clone());
+        let client_providers = ProviderConfigRepository::new(db.clone().with_workspace("client-a"));
+        let default_cache = DiffCacheRepository::new(db.clone());
+        let client_cache = DiffCacheRepository::new(db.with_workspace("client-a"));
+
+        // The same provider type can be saved in both workspaces
+        default_providers
+            .save(
+                "confluence",
+                &serde_json::json!({"space_key": "DEFAULT"}),
+                true,
+            )
+            .expect("Failed to save config");
+        client_providers
+            .save(
+                "confluence",
+                &serde_json::json!({"space_key": "CLIENT"}),
+                true,
+            )
+            .expect("Failed to save config in workspace");
+        let found = default_providers
+            .get("confluence")
+            .expect("Failed to get config")
+            .expect("Config not found");
+        assert_eq!(found.config["space_key"], "DEFAULT");
+        assert_eq!(client_providers.list().expect("list failed").len(), 1);
+
+        // Defaults and deletions stay in their workspace
+        client_providers
+            .save("markdown", &serde_json::json!({}), false)
+            .expect("Failed to save config in workspace");
+        client_providers
+            .set_default("markdown")
+            .expect("Failed to set default");
+        let default = default_providers
+            .get_default()
+            .expect("Failed to get default")
+            .expect("Default not found");
+        assert_eq!(default.provider_type, "confluence");
+        assert!(client_providers
+            .delete("confluence")
+            .expect("delete failed"));
+        assert!(default_providers
+            .get("confluence")
+            .expect("Failed to get config")
+            .is_some());
+
+        // Cached diffs are neither read nor replaced across workspaces
+        default_cache
+            .set(
+                "commit",
+                "abc123",
+                Some("/repo"),
+                "{\"default\":true}",
+                None,
+            )
+            .expect("Failed to cache diff");
+        assert!(client_cache
+            .get("commit", "abc123", Some("/repo"))
+            .expect("Failed to get cached diff")
+            .is_none());
+        client_cache
+            .set("commit", "abc123", Some("/repo"), "{\"client\":true}", None)
+            .expect("Failed to cache diff in workspace");
+        let cached = default_cache
+            .get("commit", "abc123", Some("/repo"))
+            .expect("Failed to get cached diff")
+            .expect("Cached diff not found");
+        assert_eq!(cached.diff_json, "{\"default\":true}");
+        assert_eq!(client_cache.clear_all().expect("clear failed"), 1);
+        assert!(default_cache
+            .get("commit", "abc123", Some("/repo"))
+            .expect("Failed to get cached diff")
+            .is_some());
+    }
+
     #[test]
     fn test_document_mapping() {
         let db = setup_db();
@@ -1530,6 +2158,117 @@ mod tests {
     // Feature Repository Tests
     // ============================================================================
 
+    #[test]
+    fn test_service_detections_keep_pins_apart() {
+        let repo = ServiceDetectionRepository::new(setup_db());
+
+        repo.save("/src/shop", "shop", false).unwrap();
+        repo.save("/src/shop/api", "shop-api", false).unwrap();
+        repo.save("/src/shop", "storefront", true).unwrap();
+        repo.save("/src/shop", "shop-web", false).unwrap();
+
+        let detected = repo.get("/src/shop", false).unwrap().unwrap();
+        assert_eq!(detected.service_name, "shop-web");
+        let pinned = repo.get("/src/shop", true).unwrap().unwrap();
+        assert_eq!(pinned.service_name, "storefront");
+        assert!(pinned.pinned);
+
+        assert_eq!(repo.clear_detected("/src/shop").unwrap(), 2);
+        assert!(repo.get("/src/shop/api", false).unwrap().is_none());
+        assert!(repo.get("/src/shop", true).unwrap().is_some());
+
+        assert!(repo.remove("/src/shop", true).unwrap());
+        assert!(!repo.remove("/src/shop", true).unwrap());
+    }
+
+    #[test]
+    fn test_generation_lock_is_exclusive_until_expiry() {
+        let repo = GenerationLockRepository::new(setup_db());
+        let later = Utc::now() + chrono::Duration::minutes(5);
+
+        assert!(repo
+            .try_acquire("service:shop", "a", "generate", later)
+            .unwrap());
+        assert!(!repo
+            .try_acquire("service:shop", "b", "update", later)
+            .unwrap());
+        assert!(repo
+            .try_acquire("service:cart", "b", "update", later)
+            .unwrap());
+        assert!(repo
+            .try_acquire("service:shop", "a", "generate", later)
+            .unwrap());
+        assert_eq!(repo.holder("service:shop").unwrap().unwrap().owner, "a");
+
+        assert!(!repo.release("service:shop", "b").unwrap());
+        assert!(repo.release("service:shop", "a").unwrap());
+        assert!(repo.holder("service:shop").unwrap().is_none());
+
+        // An expired lock is taken over
+        let past = Utc::now() - chrono::Duration::seconds(1);
+        assert!(repo
+            .try_acquire("service:shop", "a", "generate", past)
+            .unwrap());
+        assert!(repo
+            .try_acquire("service:shop", "b", "update", later)
+            .unwrap());
+        assert_eq!(
+            repo.holder("service:shop").unwrap().unwrap().purpose,
+            "update"
+        );
+    }
+
+    #[test]
+    fn test_translation_pairs_and_sections() {
+        let repo = TranslationRepository::new(setup_db());
+        repo.add(
+            "shop",
+            "docs/api.md",
+            "markdown",
+            "docs/ja/api.md",
+            "Japanese",
+        )
+        .unwrap();
+        repo.add("shop", "docs/api.md", "confluence", "42", "German")
+            .unwrap();
+        repo.add(
+            "cart",
+            "docs/cart.md",
+            "markdown",
+            "docs/ja/cart.md",
+            "Japanese",
+        )
+        .unwrap();
+
+        let translations = repo.list("shop", Some("docs/api.md")).unwrap();
+        assert_eq!(translations.len(), 2);
+        assert!(repo.list("shop", Some("docs/other.md")).unwrap().is_empty());
+        let japanese = &translations[1];
+        assert_eq!(japanese.target, "docs/ja/api.md");
+        assert!(japanese.synced_at.is_none());
+
+        let sections = BTreeMap::from([("abc".to_string(), "# API".to_string())]);
+        repo.save_sections(japanese.id, &sections).unwrap();
+        let saved = repo.list("shop", None).unwrap();
+        assert_eq!(saved[1].sections, sections);
+        assert!(saved[1].synced_at.is_some());
+
+        // Pairing the target again starts over
+        repo.add(
+            "shop",
+            "docs/guide.md",
+            "markdown",
+            "docs/ja/api.md",
+            "Japanese",
+        )
+        .unwrap();
+        let repaired = repo.list("shop", Some("docs/guide.md")).unwrap();
+        assert!(repaired[0].sections.is_empty());
+
+        assert_eq!(repo.remove_service("shop").unwrap(), 2);
+        assert_eq!(repo.list("cart", None).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_feature_crud() {
         let db = setup_db();
@@ -1629,6 +2368,8 @@ mod tests {
             feature_types: None,
             content_types: None,
             limit: Some(10),
+            offset: None,
+            min_score: None,
             similarity_threshold: None,
             include_related: false,
             depth: None,
@@ -1648,6 +2389,8 @@ mod tests {
             feature_types: Some(vec![FeatureType::Database]),
             content_types: None,
             limit: Some(10),
+            offset: None,
+            min_score: None,
             similarity_threshold: None,
             include_related: false,
             depth: None,
@@ -1659,6 +2402,34 @@ mod tests {
 
         assert_eq!(filtered_results.len(), 1);
         assert_eq!(filtered_results[0].feature_type, FeatureType::Database);
+
+        // Paging and minimum score
+        feature_repo
+            .update_relevance_score("feature-001", 0.9)
+            .expect("Failed to update relevance");
+        let page = |offset: u32, min_score: Option<f64>| {
+            feature_repo
+                .search(&SearchQuery {
+                    query: "".to_string(),
+                    service_ids: Some(vec![service.id]),
+                    feature_types: None,
+                    content_types: None,
+                    limit: Some(1),
+                    offset: Some(offset),
+                    min_score,
+                    similarity_threshold: None,
+                    include_related: false,
+                    depth: None,
+                })
+                .expect("Failed to page search results")
+                .into_iter()
+                .map(|r| r.feature_name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(page(0, None), vec!["Authentication API"]);
+        assert_eq!(page(1, None), vec!["Database Layer"]);
+        assert!(page(2, None).is_empty());
+        assert!(page(1, Some(0.5)).is_empty());
     }
 
     #[test]
@@ -1713,6 +2484,8 @@ mod tests {
             feature_types: Some(vec![FeatureType::Api]),
             content_types: None,
             limit: Some(10),
+            offset: None,
+            min_score: None,
             similarity_threshold: None,
             include_related: false,
             depth: None,
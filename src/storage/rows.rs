@@ -0,0 +1,163 @@
+//! Typed row mapping for the SQLite repositories
+//!
+//! [`impl_from_row!`] declares the columns of a model once: the `SELECT`
+//! list and the column each field is read from both come from the same
+//! field list, so the two cannot drift apart as columns are added.
+
+use crate::error::{KtmeError, Result};
+use rusqlite::{Connection, OptionalExtension, Params, Row};
+use serde::de::DeserializeOwned;
+
+/// A model read from the columns listed in [`FromRow::COLUMNS`], in order
+pub trait FromRow: Sized {
+    /// Columns read by [`FromRow::from_row`], in order
+    const COLUMNS: &'static [&'static str];
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self>;
+
+    /// `SELECT` list of the columns
+    fn columns() -> String {
+        Self::COLUMNS.join(", ")
+    }
+
+    /// `SELECT` list of the columns of the table aliased `alias`, for joins
+    fn columns_of(alias: &str) -> String {
+        Self::COLUMNS
+            .iter()
+            .map(|column| format!("{}.{}", alias, column))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Implement [`FromRow`] for a model from its fields, in column order.
+/// A field is read from the column of the same name unless one is given
+/// (`field = "column"`), and its value can go through a conversion from
+/// the stored type (`field => convert`). Fields that are not stored are
+/// set in a trailing `with { field: value }`.
+macro_rules! impl_from_row {
+    (
+        $model:ty {
+            $($field:ident $(= $column:literal)? $(=> $convert:path)?),+ $(,)?
+        }
+        $(with { $($extra:ident: $value:expr),+ $(,)? })?
+    ) => {
+        impl $crate::storage::rows::FromRow for $model {
+            const COLUMNS: &'static [&'static str] =
+                &[$($crate::storage::rows::impl_from_row!(@column $field $(= $column)?)),+];
+
+            #[allow(unused_assignments)]
+            fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+                let mut index = 0;
+                Ok(Self {
+                    $($field: {
+                        let value = row.get(index)?;
+                        index += 1;
+                        $crate::storage::rows::impl_from_row!(@convert value $(, $convert)?)
+                    },)+
+                    $($($extra: $value,)+)?
+                })
+            }
+        }
+    };
+    (@column $field:ident) => { stringify!($field) };
+    (@column $field:ident = $column:literal) => { $column };
+    (@convert $value:ident) => { $value };
+    (@convert $value:ident, $convert:path) => { $convert($value) };
+}
+
+pub(crate) use impl_from_row;
+
+/// JSON column, or the default value when it does not parse
+pub(crate) fn json_or_default<T: DeserializeOwned + Default>(raw: String) -> T {
+    serde_json::from_str(&raw).unwrap_or_default()
+}
+
+/// The row of `sql` as `T`, if there is one; `what` names it in errors
+pub(crate) fn query_optional<T: FromRow>(
+    conn: &Connection,
+    sql: &str,
+    params: impl Params,
+    what: &str,
+) -> Result<Option<T>> {
+    conn.query_row(sql, params, T::from_row)
+        .optional()
+        .map_err(|e| KtmeError::Storage(format!("Failed to get {}: {}", what, e)))
+}
+
+/// The rows of `sql` as `T`; `what` names them in errors
+pub(crate) fn query_all<T: FromRow>(
+    conn: &Connection,
+    sql: &str,
+    params: impl Params,
+    what: &str,
+) -> Result<Vec<T>> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| KtmeError::Storage(format!("Failed to prepare query: {}", e)))?;
+    let rows = stmt
+        .query_map(params, T::from_row)
+        .map_err(|e| KtmeError::Storage(format!("Failed to query {}: {}", what, e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| KtmeError::Storage(format!("Failed to collect {}: {}", what, e)));
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Entry {
+        id: i64,
+        label: String,
+        tags: Vec<String>,
+        note: Option<String>,
+        loaded: bool,
+    }
+
+    fn upper(raw: String) -> String {
+        raw.to_uppercase()
+    }
+
+    impl_from_row!(Entry {
+        id,
+        label = "name" => upper,
+        tags => json_or_default,
+        note,
+    } with {
+        loaded: true,
+    });
+
+    #[test]
+    fn test_columns_and_mapping_follow_the_field_list() {
+        assert_eq!(Entry::columns(), "id, name, tags, note");
+        assert_eq!(Entry::columns_of("e"), "e.id, e.name, e.tags, e.note");
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE entries (id INTEGER, name TEXT, tags TEXT, note TEXT);
+             INSERT INTO entries VALUES (1, 'api', '[\"rest\"]', NULL), (2, 'ui', 'not json', 'x');",
+        )
+        .unwrap();
+
+        let sql = format!("SELECT {} FROM entries ORDER BY id", Entry::columns());
+        let entries: Vec<Entry> = query_all(&conn, &sql, [], "entries").unwrap();
+        assert_eq!(
+            entries[0],
+            Entry {
+                id: 1,
+                label: "API".to_string(),
+                tags: vec!["rest".to_string()],
+                note: None,
+                loaded: true,
+            }
+        );
+        assert!(entries[1].tags.is_empty());
+
+        let sql = format!("SELECT {} FROM entries WHERE id = ?1", Entry::columns());
+        assert!(query_optional::<Entry>(&conn, &sql, [3], "entry")
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,291 @@
+//! Translated counterparts of mapped documents
+//!
+//! `ktme mapping add --translation-of` pairs a document, e.g. a Japanese
+//! Confluence page, with the mapped document it translates. Once the primary
+//! is published, [`after_publish`] reads it back as Markdown and translates
+//! it with the AI provider section by section. The translation of each
+//! section is stored by the hash of its source text, so a section that did
+//! not change is not translated again.
+
+use crate::ai::prompts::PromptTemplates;
+use crate::ai::AIClient;
+use crate::cli::commands::mapping::read_location;
+use crate::cli::commands::update::publish_to_location;
+use crate::config::Config;
+use crate::doc::notices::{NoticeContext, Notices};
+use crate::doc::providers::markdown::MarkdownProvider;
+use crate::doc::sections::{DocKind, SectionFormat, SectionUpdate, SectionedDocument, UpdateMode};
+use crate::error::{KtmeError, Result};
+use crate::shutdown;
+use crate::storage::backend::{open_storage, Storage};
+use crate::storage::mapping::DocumentLocation;
+use crate::storage::models::Translation;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Result of bringing one translation up to date
+#[derive(Debug, Clone, Serialize)]
+pub struct Synced {
+    pub target_type: String,
+    pub target: String,
+    pub language: String,
+    /// Sections sent to the AI provider
+    pub translated: usize,
+    /// Sections whose stored translation was used
+    pub reused: usize,
+    /// Whether the translated document was written
+    pub written: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Synced {
+    fn failed(translation: &Translation, error: &KtmeError) -> Self {
+        Self {
+            target_type: translation.target_type.clone(),
+            target: translation.target.clone(),
+            language: translation.language.clone(),
+            translated: 0,
+            reused: 0,
+            written: false,
+            error: Some(error.to_string()),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let outcome = match &self.error {
+            Some(error) => format!("failed: {}", error),
+            None if !self.written => "up to date".to_string(),
+            None => format!(
+                "{} section(s) translated, {} reused",
+                self.translated, self.reused
+            ),
+        };
+        format!("{} ({}): {}", self.target, self.language, outcome)
+    }
+}
+
+/// One section of the primary document
+#[derive(Debug)]
+struct Part {
+    hash: String,
+    source: String,
+    /// Stored translation; `None` until translated
+    translation: Option<String>,
+}
+
+/// Translations of `service`, only those of the document at `source` when
+/// given
+pub fn translations(
+    config: &Config,
+    service: &str,
+    source: Option<&str>,
+) -> Result<Vec<Translation>> {
+    open_storage(&config.storage)?.list_translations(service, source)
+}
+
+/// Bring the translations of `location` up to date after it was published;
+/// nothing when it has none. Failures are reported in the results, the
+/// primary stays published.
+pub async fn after_publish(
+    config: &Config,
+    service: &str,
+    location: &DocumentLocation,
+) -> Vec<Synced> {
+    let pairs = match translations(config, service, Some(&location.location)) {
+        Ok(pairs) => pairs,
+        Err(e) => {
+            tracing::debug!("Translations unavailable: {}", e);
+            return Vec::new();
+        }
+    };
+    if pairs.is_empty() {
+        return Vec::new();
+    }
+    match AIClient::from_config(&config.ai) {
+        Ok(ai) => sync(config, &ai, location, &pairs, false).await,
+        Err(e) => pairs.iter().map(|pair| Synced::failed(pair, &e)).collect(),
+    }
+}
+
+/// Translate the current content of the `source` document into each of
+/// `pairs` and write it to their locations. Sections translated before are
+/// reused unless `force`.
+pub async fn sync(
+    config: &Config,
+    ai: &AIClient,
+    source: &DocumentLocation,
+    pairs: &[Translation],
+    force: bool,
+) -> Vec<Synced> {
+    // The notices of the primary are not translated, the translations get
+    // their own when written
+    let notices = Notices::for_location(&config.notices, source, &NoticeContext::default());
+    let content = match read_location(config, source).await {
+        Ok(Some(document)) => Ok(notices.strip(&document.content, SectionFormat::Markdown)),
+        Ok(None) => Err(KtmeError::DocumentNotFound(source.location.clone())),
+        Err(e) => Err(e),
+    };
+    let repo = open_storage(&config.storage);
+    let (content, repo) = match (content, repo) {
+        (Ok(content), Ok(repo)) => (content, repo),
+        (Err(e), _) | (_, Err(e)) => {
+            return pairs.iter().map(|pair| Synced::failed(pair, &e)).collect();
+        }
+    };
+
+    let mut results = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        tracing::info!("Translating {} into {}", source.location, pair.language);
+        let result = sync_one(config, ai, repo.as_ref(), &content, pair, force).await;
+        results.push(result.unwrap_or_else(|e| {
+            tracing::warn!("Failed to translate {}: {}", pair.target, e);
+            Synced::failed(pair, &e)
+        }));
+    }
+    results
+}
+
+async fn sync_one(
+    config: &Config,
+    ai: &AIClient,
+    repo: &dyn Storage,
+    content: &str,
+    translation: &Translation,
+    force: bool,
+) -> Result<Synced> {
+    let mut parts = plan(content, &translation.sections, force);
+    let mut translated = 0;
+    for part in parts.iter_mut().filter(|part| part.translation.is_none()) {
+        shutdown::check("Translating documentation")?;
+        let prompt = PromptTemplates::translation_prompt(&part.source, &translation.language);
+        part.translation = Some(ai.generate_documentation(&prompt).await?);
+        translated += 1;
+    }
+    let sections: BTreeMap<String, String> = parts
+        .iter()
+        .map(|part| {
+            let text = part.translation.clone().unwrap_or_default();
+            (part.hash.clone(), text)
+        })
+        .collect();
+
+    let unchanged = sections == translation.sections && translation.synced_at.is_some();
+    if !unchanged || force {
+        write(config, translation, &assemble(&parts)).await?;
+        repo.save_translation_sections(translation.id, &sections)?;
+    }
+    Ok(Synced {
+        target_type: translation.target_type.clone(),
+        target: translation.target.clone(),
+        language: translation.language.clone(),
+        translated,
+        reused: parts.len() - translated,
+        written: !unchanged || force,
+        error: None,
+    })
+}
+
+/// Sections of the Markdown `content`, with the translations in `stored`
+/// of those whose text did not change unless `force`
+fn plan(content: &str, stored: &BTreeMap<String, String>, force: bool) -> Vec<Part> {
+    SectionedDocument::parse(content, SectionFormat::Markdown)
+        .parts()
+        .into_iter()
+        .map(|source| {
+            let hash = hash(&source);
+            let translation = stored.get(&hash).filter(|_| !force).cloned();
+            Part {
+                hash,
+                source,
+                translation,
+            }
+        })
+        .collect()
+}
+
+/// The translated document
+fn assemble(parts: &[Part]) -> String {
+    let text = parts
+        .iter()
+        .filter_map(|part| part.translation.as_deref())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!("{}\n", text)
+}
+
+fn hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.trim().as_bytes()))
+}
+
+/// Replace the document at the translation's location with `content`;
+/// a Markdown file is created when missing
+async fn write(config: &Config, translation: &Translation, content: &str) -> Result<()> {
+    let location = DocumentLocation {
+        r#type: translation.target_type.clone(),
+        location: translation.target.clone(),
+        section: None,
+    };
+    let notice = NoticeContext {
+        service: Some(&translation.service_name),
+        ..NoticeContext::default()
+    };
+    if translation.target_type == "markdown" {
+        let notices = Notices::for_location(&config.notices, &location, &notice);
+        MarkdownProvider::for_file(&translation.target)
+            .write_document(
+                &translation.target,
+                &notices.apply(content, SectionFormat::Markdown),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let update = SectionUpdate {
+        kind: DocKind::General,
+        section: None,
+        mode: Some(UpdateMode::Replace),
+    };
+    publish_to_location(config, &location, content, &update, &notice)
+        .await
+        .unwrap_or_else(|| Err(KtmeError::UnsupportedProvider(location.r#type.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_changed_sections_are_translated_again() {
+        let original = "# Payments\n\nIntro.\n\n## Usage\n\nCall it.\n\n## Limits\n\nNone.\n";
+        let mut parts = plan(original, &BTreeMap::new(), false);
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.translation.is_none()));
+        for part in &mut parts {
+            part.translation = Some(format!("[ja] {}", part.source.trim()));
+        }
+        let stored: BTreeMap<_, _> = parts
+            .iter()
+            .map(|part| (part.hash.clone(), part.translation.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            assemble(&parts),
+            "[ja] # Payments\n\nIntro.\n\n[ja] ## Usage\n\nCall it.\n\n[ja] ## Limits\n\nNone.\n"
+        );
+
+        let edited = "# Payments\n\nIntro.\n\n## Usage\n\nCall it twice.\n\n## Limits\n\nNone.\n";
+        let pending: Vec<_> = plan(edited, &stored, false)
+            .into_iter()
+            .filter(|part| part.translation.is_none())
+            .map(|part| part.source)
+            .collect();
+        assert_eq!(pending, vec!["## Usage\n\nCall it twice.\n\n"]);
+
+        assert!(plan(edited, &stored, true)
+            .iter()
+            .all(|part| part.translation.is_none()));
+    }
+}
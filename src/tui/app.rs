@@ -0,0 +1,298 @@
+use crate::config::Config;
+use crate::error::Result;
+use crate::git::staleness;
+use crate::storage::backend::open_storage;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::GenerationRecord;
+use ratatui::crossterm::event::KeyCode;
+use std::collections::{HashMap, VecDeque};
+
+/// Lines kept in the log pane
+const MAX_LOG_LINES: usize = 1000;
+
+/// History entries loaded from storage on refresh
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Services,
+    History,
+    Logs,
+}
+
+/// Commands the dashboard can run for the selected service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Generate,
+    Publish,
+}
+
+impl Action {
+    /// `ktme` subcommand that performs the action
+    pub fn subcommand(&self) -> &'static str {
+        match self {
+            Action::Generate => "generate",
+            Action::Publish => "update",
+        }
+    }
+}
+
+/// What the event loop should do after a key press
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Quit,
+    Refresh,
+    Run(Action, String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ServiceRow {
+    pub name: String,
+    pub path: Option<String>,
+    pub docs: Vec<DocumentLocation>,
+    /// Most recent generation or update, successful or not
+    pub last: Option<GenerationRecord>,
+    pub stale: bool,
+}
+
+pub struct App {
+    pub services: Vec<ServiceRow>,
+    pub selected: usize,
+    pub view: View,
+    /// Recent history across all services, newest first
+    pub history: Vec<(String, GenerationRecord)>,
+    pub logs: VecDeque<String>,
+    /// Service whose action is currently running
+    pub running: Option<(Action, String)>,
+    pub status: String,
+}
+
+impl App {
+    pub fn new(services: Vec<ServiceRow>, history: Vec<(String, GenerationRecord)>) -> Self {
+        Self {
+            services,
+            selected: 0,
+            view: View::Services,
+            history,
+            logs: VecDeque::new(),
+            running: None,
+            status: String::new(),
+        }
+    }
+
+    /// Load services, mappings and generation history from storage
+    pub fn load() -> Result<Self> {
+        let (services, history) = load_state()?;
+        Ok(Self::new(services, history))
+    }
+
+    /// Reload from storage, keeping the selection on the same service
+    pub fn refresh(&mut self) -> Result<()> {
+        let current = self.selected_service().map(|s| s.name.clone());
+        let (services, history) = load_state()?;
+        self.services = services;
+        self.history = history;
+        self.selected = current
+            .and_then(|name| self.services.iter().position(|s| s.name == name))
+            .unwrap_or(0);
+        Ok(())
+    }
+
+    pub fn selected_service(&self) -> Option<&ServiceRow> {
+        self.services.get(self.selected)
+    }
+
+    /// History entries of the selected service
+    pub fn selected_history(&self) -> Vec<&GenerationRecord> {
+        let Some(service) = self.selected_service() else {
+            return Vec::new();
+        };
+        self.history
+            .iter()
+            .filter(|(name, _)| *name == service.name)
+            .map(|(_, record)| record)
+            .collect()
+    }
+
+    pub fn next(&mut self) {
+        if !self.services.is_empty() {
+            self.selected = (self.selected + 1) % self.services.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.services.is_empty() {
+            self.selected = (self.selected + self.services.len() - 1) % self.services.len();
+        }
+    }
+
+    pub fn push_log(&mut self, line: impl Into<String>) {
+        if self.logs.len() == MAX_LOG_LINES {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(line.into());
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> Option<Command> {
+        match key {
+            KeyCode::Char('q') => return Some(Command::Quit),
+            KeyCode::Esc if self.view == View::Services => return Some(Command::Quit),
+            KeyCode::Esc | KeyCode::Char('s') => self.view = View::Services,
+            KeyCode::Char('h') => self.view = View::History,
+            KeyCode::Char('l') => self.view = View::Logs,
+            KeyCode::Down | KeyCode::Char('j') => self.next(),
+            KeyCode::Up | KeyCode::Char('k') => self.previous(),
+            KeyCode::Char('r') => return Some(Command::Refresh),
+            KeyCode::Char('g') => return self.start(Action::Generate),
+            KeyCode::Char('p') => return self.start(Action::Publish),
+            _ => {}
+        }
+        None
+    }
+
+    /// Mark `action` as running for the selected service; only one action
+    /// runs at a time
+    fn start(&mut self, action: Action) -> Option<Command> {
+        if let Some((running, service)) = &self.running {
+            self.status = format!("{} already running for {}", running.subcommand(), service);
+            return None;
+        }
+        let service = self.selected_service()?.name.clone();
+        self.running = Some((action, service.clone()));
+        self.view = View::Logs;
+        self.status = format!("Running {} for {}...", action.subcommand(), service);
+        Some(Command::Run(action, service))
+    }
+
+    /// Record the end of the running action
+    pub fn finish(&mut self, success: bool) {
+        if let Some((action, service)) = self.running.take() {
+            self.status = format!(
+                "{} for {} {}",
+                action.subcommand(),
+                service,
+                if success { "succeeded" } else { "failed" }
+            );
+        }
+    }
+}
+
+/// Rows of the services table and the recent generations with the name of
+/// their service
+type LoadedState = (Vec<ServiceRow>, Vec<(String, GenerationRecord)>);
+
+fn load_state() -> Result<LoadedState> {
+    let manager = StorageManager::new()?;
+    let storage = open_storage(&Config::load()?.storage)?;
+
+    let names_by_id: HashMap<i64, String> = storage
+        .list_services()?
+        .into_iter()
+        .map(|s| (s.id, s.name))
+        .collect();
+
+    let history: Vec<(String, GenerationRecord)> = storage
+        .recent_generations(HISTORY_LIMIT)?
+        .into_iter()
+        .filter_map(|record| {
+            let name = names_by_id.get(&record.service_id?)?.clone();
+            Some((name, record))
+        })
+        .collect();
+
+    let mut services = Vec::new();
+    for name in manager.list_services()? {
+        let (path, docs) = match manager.get_mapping(&name) {
+            Ok(mapping) => (mapping.path, mapping.docs),
+            Err(_) => (None, Vec::new()),
+        };
+
+        let records: Vec<&GenerationRecord> = history
+            .iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, r)| r)
+            .collect();
+        let last_success = records
+            .iter()
+            .find(|r| r.status == "success")
+            .map(|r| r.created_at);
+        let head_time = path
+            .as_deref()
+            .and_then(staleness::head)
+            .map(|head| head.time);
+
+        services.push(ServiceRow {
+            stale: staleness::is_stale(last_success, head_time),
+            last: records.first().map(|r| (*r).clone()),
+            name,
+            path,
+            docs,
+        });
+    }
+
+    Ok((services, history))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::staleness::is_stale;
+    use chrono::{TimeZone, Utc};
+
+    fn row(name: &str) -> ServiceRow {
+        ServiceRow {
+            name: name.to_string(),
+            path: None,
+            docs: Vec::new(),
+            last: None,
+            stale: true,
+        }
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let earlier = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let later = Utc.timestamp_opt(1_700_000_600, 0).unwrap();
+
+        assert!(is_stale(None, None));
+        assert!(is_stale(None, Some(later)));
+        assert!(is_stale(Some(earlier), Some(later)));
+        assert!(!is_stale(Some(later), Some(earlier)));
+        assert!(!is_stale(Some(earlier), None));
+    }
+
+    #[test]
+    fn test_navigation_wraps() {
+        let mut app = App::new(vec![row("a"), row("b"), row("c")], Vec::new());
+        app.previous();
+        assert_eq!(app.selected, 2);
+        app.next();
+        assert_eq!(app.selected, 0);
+        app.handle_key(KeyCode::Char('j'));
+        assert_eq!(app.selected_service().unwrap().name, "b");
+    }
+
+    #[test]
+    fn test_only_one_action_runs_at_a_time() {
+        let mut app = App::new(vec![row("payments")], Vec::new());
+        assert_eq!(
+            app.handle_key(KeyCode::Char('g')),
+            Some(Command::Run(Action::Generate, "payments".to_string()))
+        );
+        assert_eq!(app.view, View::Logs);
+        assert_eq!(app.handle_key(KeyCode::Char('p')), None);
+
+        app.finish(true);
+        assert!(app.running.is_none());
+        assert_eq!(app.status, "generate for payments succeeded");
+    }
+
+    #[test]
+    fn test_escape_returns_to_services_before_quitting() {
+        let mut app = App::new(Vec::new(), Vec::new());
+        assert_eq!(app.handle_key(KeyCode::Char('h')), None);
+        assert_eq!(app.handle_key(KeyCode::Esc), None);
+        assert_eq!(app.view, View::Services);
+        assert_eq!(app.handle_key(KeyCode::Esc), Some(Command::Quit));
+    }
+}
@@ -0,0 +1,155 @@
+//! Interactive terminal dashboard (`ktme tui`)
+//!
+//! Lists mapped services with their last generation status and a stale
+//! indicator, shows generation history, and runs `generate`/`update` for the
+//! selected service in a child process whose output is tailed in the log
+//! pane.
+
+pub mod app;
+mod ui;
+
+use crate::error::Result;
+use app::{Action, App, Command};
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use std::io::{BufRead, BufReader, Read};
+use std::process::Stdio;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for input before redrawing
+const TICK: Duration = Duration::from_millis(200);
+
+/// Messages from a running action to the event loop
+enum ActionEvent {
+    Line(String),
+    Finished(bool),
+}
+
+/// Run the dashboard until the user quits
+pub fn run() -> Result<()> {
+    let mut app = App::load()?;
+    let (tx, rx) = mpsc::channel();
+
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut app, &tx, &rx);
+    ratatui::restore();
+
+    result
+}
+
+fn event_loop(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+    tx: &Sender<ActionEvent>,
+    rx: &Receiver<ActionEvent>,
+) -> Result<()> {
+    loop {
+        while let Ok(message) = rx.try_recv() {
+            match message {
+                ActionEvent::Line(line) => app.push_log(line),
+                ActionEvent::Finished(success) => {
+                    app.finish(success);
+                    refresh(app);
+                }
+            }
+        }
+
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match app.handle_key(key.code) {
+            Some(Command::Quit) => return Ok(()),
+            Some(Command::Refresh) => refresh(app),
+            Some(Command::Run(action, service)) => {
+                let path = app.selected_service().and_then(|s| s.path.clone());
+                app.push_log(format!(
+                    "$ ktme {} --commit HEAD --service {}",
+                    action.subcommand(),
+                    service
+                ));
+                if let Err(e) = spawn_action(action, &service, path.as_deref(), tx.clone()) {
+                    app.push_log(format!("Failed to start: {}", e));
+                    app.finish(false);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+fn refresh(app: &mut App) {
+    if let Err(e) = app.refresh() {
+        app.status = format!("Refresh failed: {}", e);
+    }
+}
+
+/// Run `ktme <action>` for `service` against its latest commit, streaming
+/// stdout and stderr to the log pane
+fn spawn_action(
+    action: Action,
+    service: &str,
+    path: Option<&str>,
+    tx: Sender<ActionEvent>,
+) -> Result<()> {
+    let mut command = std::process::Command::new(std::env::current_exe()?);
+    command
+        .args([
+            action.subcommand(),
+            "--commit",
+            "HEAD",
+            "--service",
+            service,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(path) = path.filter(|p| std::path::Path::new(p).is_dir()) {
+        command.current_dir(path);
+    }
+
+    let mut child = command.spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| forward_lines(out, tx.clone()));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| forward_lines(err, tx.clone()));
+
+    thread::spawn(move || {
+        for reader in [stdout, stderr].into_iter().flatten() {
+            let _ = reader.join();
+        }
+        let success = child.wait().map(|status| status.success()).unwrap_or(false);
+        let _ = tx.send(ActionEvent::Finished(success));
+    });
+
+    Ok(())
+}
+
+fn forward_lines<R: Read + Send + 'static>(
+    stream: R,
+    tx: Sender<ActionEvent>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream)
+            .lines()
+            .map_while(std::result::Result::ok)
+        {
+            if tx.send(ActionEvent::Line(line)).is_err() {
+                break;
+            }
+        }
+    })
+}
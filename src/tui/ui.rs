@@ -0,0 +1,182 @@
+use super::app::{App, View};
+use crate::storage::models::GenerationRecord;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::Frame;
+
+const KEY_HELP: &str =
+    " j/k move  g generate  p publish  h history  l logs  s services  r refresh  q quit ";
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+    match app.view {
+        View::Services => draw_services(frame, app, main),
+        View::History => draw_history(frame, app, main),
+        View::Logs => draw_logs(frame, app, main),
+    }
+
+    let status_text = if app.status.is_empty() {
+        KEY_HELP.to_string()
+    } else {
+        format!(" {} |{}", app.status, KEY_HELP)
+    };
+    frame.render_widget(
+        Paragraph::new(status_text).style(Style::default().add_modifier(Modifier::REVERSED)),
+        status,
+    );
+}
+
+fn draw_services(frame: &mut Frame, app: &App, area: Rect) {
+    let [list, details] = Layout::vertical([Constraint::Min(5), Constraint::Length(8)]).areas(area);
+
+    let rows = app.services.iter().map(|service| {
+        let (status, when) = match &service.last {
+            Some(record) => (
+                format!("{} ({})", record.status, record.action),
+                record.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            ),
+            None => ("never".to_string(), "-".to_string()),
+        };
+        let stale = if service.stale { "stale" } else { "" };
+
+        Row::new(vec![
+            service.name.clone(),
+            service.docs.len().to_string(),
+            status,
+            when,
+            stale.to_string(),
+        ])
+        .style(if service.stale {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        })
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(30),
+            Constraint::Length(6),
+            Constraint::Percentage(30),
+            Constraint::Length(17),
+            Constraint::Length(6),
+        ],
+    )
+    .header(
+        Row::new(vec!["Service", "Docs", "Last run", "When", ""])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title(" Services "))
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default();
+    if !app.services.is_empty() {
+        state.select(Some(app.selected));
+    }
+    frame.render_stateful_widget(table, list, &mut state);
+
+    let mut lines = Vec::new();
+    match app.selected_service() {
+        Some(service) => {
+            if let Some(path) = &service.path {
+                lines.push(Line::from(format!("Path: {}", path)));
+            }
+            if service.docs.is_empty() {
+                lines.push(Line::from("No documentation mapped"));
+            }
+            for doc in &service.docs {
+                lines.push(Line::from(format!("  {} ({})", doc.location, doc.r#type)));
+            }
+            if let Some(error) = service.last.as_ref().and_then(|r| r.error_message.as_ref()) {
+                lines.push(Line::from(format!("Last error: {}", error)));
+            }
+        }
+        None => lines.push(Line::from(
+            "No services mapped yet. Add one with `ktme mapping add`.",
+        )),
+    }
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title(" Mappings ")),
+        details,
+    );
+}
+
+fn draw_history(frame: &mut Frame, app: &App, area: Rect) {
+    let title = match app.selected_service() {
+        Some(service) => format!(" History: {} ", service.name),
+        None => " History ".to_string(),
+    };
+
+    let rows = app.selected_history().into_iter().map(history_row);
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(17),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(16),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "When", "Action", "Status", "Source", "Model", "Location",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title(title));
+
+    frame.render_widget(table, area);
+}
+
+fn history_row(record: &GenerationRecord) -> Row<'static> {
+    let source = record
+        .source_identifier
+        .as_deref()
+        .map(|id| id.chars().take(10).collect())
+        .unwrap_or_default();
+    let location = match (&record.document_url, &record.error_message) {
+        (_, Some(error)) => error.clone(),
+        (Some(url), None) => url.clone(),
+        (None, None) => record.provider.clone(),
+    };
+
+    Row::new(vec![
+        record.created_at.format("%Y-%m-%d %H:%M").to_string(),
+        record.action.clone(),
+        record.status.clone(),
+        source,
+        record.model.clone().unwrap_or_default(),
+        location,
+    ])
+    .style(if record.status == "success" {
+        Style::default()
+    } else {
+        Style::default().fg(Color::Red)
+    })
+}
+
+fn draw_logs(frame: &mut Frame, app: &App, area: Rect) {
+    // Show the tail that fits inside the borders
+    let visible = area.height.saturating_sub(2) as usize;
+    let skip = app.logs.len().saturating_sub(visible);
+    let lines: Vec<Line> = app
+        .logs
+        .iter()
+        .skip(skip)
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Logs ")),
+        area,
+    );
+}
@@ -0,0 +1,423 @@
+//! Policy-driven run of one service: extract → generate → lint → publish →
+//! translate → notify
+//!
+//! `ktme run` and the MCP `automated_documentation_workflow` tool both go
+//! through [`run`], so a service's `[policies.<service>]` entry decides the
+//! same document types, targets, review and notifications in either
+//! interface:
+//!
+//! ```toml
+//! [policies.payments]
+//! doc_types = ["changelog", "api-doc"]
+//! targets = ["confluence"]
+//! review = false
+//! notify = ["https://hooks.slack.com/services/T000/B000/XXXX"]
+//! ```
+
+use crate::cli::commands::generate::record_history;
+use crate::config::PolicyConfig;
+use crate::doc::idempotency::GenerationKey;
+use crate::doc::lint::{self, Finding};
+use crate::doc::providers::PublishStatus;
+use crate::doc::sections::DocKind;
+use crate::engine::{GenerateOptions, KtmeEngine, PublishOptions, PublishedLocation};
+use crate::error::{KtmeError, Result};
+use crate::git::diff::ExtractedDiff;
+use crate::storage::backend::open_storage;
+use crate::storage::lock::ServiceLock;
+use crate::storage::mapping::{DocumentLocation, StorageManager};
+use crate::storage::models::ServiceSettings;
+use crate::translation::{self, Synced};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// What happened to the documentation of one document type
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    Published {
+        locations: Vec<PublishedLocation>,
+    },
+    /// Held in the review queue, see `ktme queue`
+    Queued {
+        queue_id: i64,
+    },
+    /// Not published because of lint errors
+    Blocked,
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentReport {
+    pub doc_type: String,
+    pub lint: Vec<Finding>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// Delivery of the run summary to one webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of [`run`]
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub service: String,
+    pub source: String,
+    pub documents: Vec<DocumentReport>,
+    /// Translated counterparts of the published documents
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub translations: Vec<Synced>,
+    pub notifications: Vec<Notification>,
+}
+
+impl DocumentReport {
+    /// Whether generating failed, lint errors blocked the publish, or any
+    /// location failed to publish
+    pub fn failed(&self) -> bool {
+        match &self.outcome {
+            Outcome::Published { locations } => locations
+                .iter()
+                .any(|location| matches!(location.status, PublishStatus::Failed(_))),
+            Outcome::Queued { .. } => false,
+            Outcome::Blocked | Outcome::Failed { .. } => true,
+        }
+    }
+}
+
+impl RunReport {
+    pub fn failed(&self) -> bool {
+        self.documents.iter().any(DocumentReport::failed)
+    }
+
+    /// One line per document type
+    pub fn describe(&self) -> Vec<String> {
+        self.documents
+            .iter()
+            .map(|doc| {
+                let outcome = match &doc.outcome {
+                    Outcome::Published { locations } => format!(
+                        "published to {}",
+                        locations
+                            .iter()
+                            .map(|l| match &l.status {
+                                PublishStatus::Failed(error) => {
+                                    format!("{} (failed: {})", l.location, error)
+                                }
+                                PublishStatus::NoChanges => format!("{} (unchanged)", l.location),
+                                _ => l.location.clone(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    Outcome::Queued { queue_id } => {
+                        format!("queued for review (id {})", queue_id)
+                    }
+                    Outcome::Blocked => "not published: lint errors".to_string(),
+                    Outcome::Failed { error } => format!("failed: {}", error),
+                };
+                format!("{}: {}", doc.doc_type, outcome)
+            })
+            .collect()
+    }
+}
+
+/// Document `diff` for `service` as its policy says: generate each of its
+/// document types, lint them, then publish them to its targets or queue
+/// them for review, and notify its webhooks. The service is locked for the
+/// whole run, waiting for another run holding it when `wait`.
+pub async fn run(
+    engine: &KtmeEngine,
+    service: &str,
+    diff: &ExtractedDiff,
+    wait: bool,
+) -> Result<RunReport> {
+    let config = engine.config();
+    let policy = config.policy(service);
+    let mapping = StorageManager::from_config(config)?.get_mapping(service)?;
+    let settings = ServiceSettings::from_config(config).overlay(&mapping.settings);
+
+    let locations = targets(&policy, &settings, &mapping.docs);
+    if locations.is_empty() && !policy.review {
+        return Err(KtmeError::DocumentNotFound(format!(
+            "No documentation locations of service {} match its policy targets",
+            service
+        )));
+    }
+
+    let mut report = RunReport {
+        service: service.to_string(),
+        source: diff.identifier.clone(),
+        documents: Vec::new(),
+        translations: Vec::new(),
+        notifications: Vec::new(),
+    };
+    let diff = diff.filtered(|f| settings.covers(&f.path));
+    if diff.files.is_empty() {
+        tracing::info!(
+            "No changes match the include/exclude settings of {}",
+            service
+        );
+        return Ok(report);
+    }
+
+    let _lock = ServiceLock::acquire(config, service, "run", wait).await?;
+    let storage = open_storage(&config.storage)?;
+    for doc_type in doc_types(&policy, &settings) {
+        tracing::info!("Running the {} workflow for {}", doc_type, service);
+        let options = GenerateOptions {
+            doc_type: doc_type.clone(),
+            ..Default::default()
+        };
+        // Locations that already hold this documentation are skipped, as
+        // by `ktme update` and `generate --publish`
+        let key = GenerationKey::new(&diff, "policy", engine.model())
+            .option("doc_type", Some(&doc_type))
+            .hash();
+        let (published, pending): (Vec<_>, Vec<_>) = locations
+            .iter()
+            .cloned()
+            .partition(|doc| matches!(storage.find_by_key(&doc.location, &key), Ok(Some(_))));
+        let unchanged: Vec<_> = published
+            .into_iter()
+            .map(|doc| PublishedLocation {
+                r#type: doc.r#type,
+                location: doc.location,
+                status: PublishStatus::NoChanges,
+            })
+            .collect();
+        if pending.is_empty() && !policy.review {
+            report.documents.push(DocumentReport {
+                doc_type,
+                lint: Vec::new(),
+                outcome: Outcome::Published {
+                    locations: unchanged,
+                },
+            });
+            continue;
+        }
+
+        let doc = match engine.generate(service, &diff, &options).await {
+            Ok(doc) => doc,
+            Err(e) => {
+                report.documents.push(DocumentReport {
+                    doc_type,
+                    lint: Vec::new(),
+                    outcome: Outcome::Failed {
+                        error: e.to_string(),
+                    },
+                });
+                continue;
+            }
+        };
+
+        let findings = lint::check(&doc.documentation);
+        let outcome = if policy.lint && lint::has_errors(&findings) {
+            Outcome::Blocked
+        } else if policy.review {
+            let queue_id = storage.queue_doc(
+                service,
+                &doc_type,
+                Some(&diff.identifier),
+                None,
+                &doc.documentation,
+            )?;
+            record_history(
+                storage.as_ref(),
+                service,
+                "queue",
+                "queue",
+                None,
+                &diff,
+                Some(&doc.documentation),
+                None,
+                engine.model(),
+                Some(&key),
+            );
+            Outcome::Queued { queue_id }
+        } else {
+            let options = PublishOptions {
+                doc_type: DocKind::from_str(&doc_type, true).ok(),
+                service: Some(service.to_string()),
+                source: Some(diff.identifier.clone()),
+                ..Default::default()
+            };
+            let published = engine
+                .publish_to(&pending, &doc.documentation, &options)
+                .await;
+            for location in &published {
+                let error = match &location.status {
+                    PublishStatus::Failed(error) => Some(error.as_str()),
+                    _ => None,
+                };
+                record_history(
+                    storage.as_ref(),
+                    service,
+                    "publish",
+                    &location.r#type,
+                    Some(&location.location),
+                    &diff,
+                    Some(&doc.documentation),
+                    error,
+                    engine.model(),
+                    Some(&key),
+                );
+            }
+            for location in &pending {
+                let written = published.iter().any(|p| {
+                    p.location == location.location
+                        && matches!(p.status, PublishStatus::Created | PublishStatus::Updated)
+                });
+                if written {
+                    report
+                        .translations
+                        .extend(translation::after_publish(config, service, location).await);
+                }
+            }
+            Outcome::Published {
+                locations: published.into_iter().chain(unchanged).collect(),
+            }
+        };
+        report.documents.push(DocumentReport {
+            doc_type,
+            lint: findings,
+            outcome,
+        });
+    }
+
+    for url in &policy.notify {
+        let error = notify(url, &report).await.err().map(|e| {
+            tracing::warn!("Failed to notify {}: {}", url, e);
+            e.to_string()
+        });
+        report.notifications.push(Notification {
+            url: url.clone(),
+            error,
+        });
+    }
+    Ok(report)
+}
+
+/// Document types of a run: the policy's, else the service's
+fn doc_types(policy: &PolicyConfig, settings: &ServiceSettings) -> Vec<String> {
+    if policy.doc_types.is_empty() {
+        vec![settings
+            .doc_type
+            .clone()
+            .unwrap_or_else(|| "general".to_string())]
+    } else {
+        policy.doc_types.clone()
+    }
+}
+
+/// Mapped locations a run publishes to: those matching the policy's
+/// targets, else the service's publish setting
+fn targets(
+    policy: &PolicyConfig,
+    settings: &ServiceSettings,
+    docs: &[DocumentLocation],
+) -> Vec<DocumentLocation> {
+    docs.iter()
+        .filter(|doc| {
+            if policy.targets.is_empty() {
+                settings.publishes_to(&doc.r#type, &doc.location)
+            } else {
+                policy
+                    .targets
+                    .iter()
+                    .any(|target| target == &doc.r#type || target == &doc.location)
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Post the summary of `report` to the webhook at `url`. The `text` field
+/// is what Slack and compatible chat webhooks display.
+async fn notify(url: &str, report: &RunReport) -> Result<()> {
+    crate::http::ensure_online("Sending notifications")?;
+    let text = format!(
+        "ktme documented {} from {}:\n{}",
+        report.service,
+        report.source,
+        report
+            .describe()
+            .iter()
+            .map(|line| format!("• {}", line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    let mut body = serde_json::to_value(report)?;
+    body["text"] = serde_json::Value::String(text);
+
+    let client = crate::http::client("notify")
+        .build()
+        .map_err(|e| KtmeError::NetworkError(e.to_string()))?;
+    let response = client
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| crate::http::request_error("Notification", e))?;
+    if !response.status().is_success() {
+        return Err(KtmeError::ApiError(format!(
+            "Webhook responded with {}",
+            response.status()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(r#type: &str, location: &str) -> DocumentLocation {
+        DocumentLocation {
+            r#type: r#type.to_string(),
+            location: location.to_string(),
+            section: None,
+        }
+    }
+
+    fn names(docs: Vec<DocumentLocation>) -> Vec<String> {
+        docs.into_iter().map(|doc| doc.location).collect()
+    }
+
+    #[test]
+    fn test_policy_overrides_service_settings() {
+        let docs = vec![
+            location("markdown", "docs/api.md"),
+            location("confluence", "https://wiki.example.com/pages/1"),
+        ];
+        let settings = ServiceSettings {
+            doc_type: Some("changelog".to_string()),
+            publish: vec!["markdown".to_string()],
+            ..Default::default()
+        };
+
+        let policy = PolicyConfig::default();
+        assert_eq!(doc_types(&policy, &settings), vec!["changelog"]);
+        assert_eq!(
+            names(targets(&policy, &settings, &docs)),
+            vec!["docs/api.md"]
+        );
+
+        let policy = PolicyConfig {
+            doc_types: vec!["api-doc".to_string(), "general".to_string()],
+            targets: vec!["confluence".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(doc_types(&policy, &settings), vec!["api-doc", "general"]);
+        assert_eq!(
+            names(targets(&policy, &settings, &docs)),
+            vec!["https://wiki.example.com/pages/1"]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use assert_cmd::Command;
 use std::fs;
+use std::path::Path;
 use tempfile::TempDir;
 
 #[test]
@@ -50,6 +51,99 @@ fn test_init_command() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_init_template_detects_components() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let root = temp_dir.path();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )?;
+    fs::create_dir_all(root.join("crates/core"))?;
+    fs::write(
+        root.join("crates/core/Cargo.toml"),
+        "[package]\nname = \"core\"\n",
+    )?;
+    fs::write(
+        root.join("docker-compose.yml"),
+        "services:\n  db:\n    image: postgres:16\n",
+    )?;
+
+    #[allow(deprecated)]
+    let mut cmd = Command::cargo_bin("ktme").unwrap();
+    cmd.args(&[
+        "init",
+        "--path",
+        root.to_str().unwrap(),
+        "--service",
+        "test-template-service",
+        "--template",
+        "library",
+    ]);
+    cmd.assert().success();
+
+    let docs_dir = root.join("docs");
+    assert!(docs_dir.join("guides").exists());
+    assert!(!docs_dir.join("api").exists());
+    let architecture = fs::read_to_string(docs_dir.join("architecture.md"))?;
+    assert!(architecture.contains("## Modules"));
+    assert!(architecture.contains("### Workspace members\n\n- `crates/core`"));
+    assert!(architecture.contains("### Docker services\n\n- `db`: postgres:16"));
+    assert!(architecture.contains("- Rust"));
+    let api = fs::read_to_string(docs_dir.join("api.md"))?;
+    assert!(api.contains("Public API"));
+
+    Ok(())
+}
+
+#[test]
+fn test_init_with_mapping_and_hooks() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let home = temp_dir.path().join("home");
+    let root = temp_dir.path().join("repo");
+    fs::create_dir_all(&home)?;
+    git2::Repository::init(&root)?;
+
+    let init = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("HOME", &home)
+            .env_remove("XDG_CONFIG_HOME")
+            .env_remove("XDG_DATA_HOME")
+            .args(&[
+                "init",
+                "--path",
+                root.to_str().unwrap(),
+                "--service",
+                "wired-service",
+                "--with-mapping",
+                "--with-hooks",
+            ]);
+        cmd
+    };
+    init().assert().success();
+    // Running it again neither maps nor hooks twice
+    init().assert().success();
+
+    let hook = fs::read_to_string(root.join(".git/hooks/post-commit"))?;
+    assert!(hook.starts_with("#!/bin/sh"));
+    assert_eq!(hook.matches("update --service 'wired-service'").count(), 1);
+
+    #[allow(deprecated)]
+    let mut cmd = Command::cargo_bin("ktme").unwrap();
+    let output = cmd
+        .env("HOME", &home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_DATA_HOME")
+        .args(&["mapping", "get", "wired-service"])
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("docs/changelog.md").count(), 1, "{}", stdout);
+    assert!(stdout.contains("docs/api.md"));
+
+    Ok(())
+}
+
 #[test]
 fn test_init_command_idempotent() -> Result<(), Box<dyn std::error::Error>> {
     let temp_dir = TempDir::new()?;
@@ -153,6 +247,190 @@ fn test_extract_output_to_file() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn test_extract_stdout_formats() -> Result<(), Box<dyn std::error::Error>> {
+    // JSON on stdout is the default and must parse cleanly (logs go to stderr)
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["extract", "--commit", "HEAD"])
+        .output()?;
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(json["files"].is_array());
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["extract", "--commit", "HEAD", "--format", "yaml"])
+        .output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.starts_with("source: "));
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["extract", "--commit", "HEAD", "--format", "patch"])
+        .output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.starts_with("diff --git a/"));
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_truncates_large_file_diffs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let content: String = (0..500).map(|i| format!("vendored line {}\n", i)).collect();
+    fs::write(temp_dir.path().join("vendor.txt"), &content)?;
+    fs::write(temp_dir.path().join("small.txt"), "hello\n")?;
+    let mut index = repo.index()?;
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    repo.commit(Some("HEAD"), &signature, &signature, "Vendor", &tree, &[])?;
+
+    let spill = temp_dir.path().join("spill.diff");
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(&[
+            "extract",
+            "--commit",
+            "HEAD",
+            "--max-file-diff",
+            "1024",
+            "--spill",
+            spill.to_str().unwrap(),
+        ])
+        .output()?;
+    assert!(output.status.success());
+
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let files = diff["files"].as_array().unwrap();
+    let vendor = files.iter().find(|f| f["path"] == "vendor.txt").unwrap();
+    let small = files.iter().find(|f| f["path"] == "small.txt").unwrap();
+    assert!(vendor["diff"].as_str().unwrap().len() < 1200);
+    assert_eq!(vendor["truncated"]["original_lines"], 500);
+    assert!(small.get("truncated").is_none());
+    assert_eq!(small["diff"], "+hello\n");
+
+    let spilled = fs::read_to_string(&spill)?;
+    assert!(spilled.starts_with("diff --git a/vendor.txt b/vendor.txt"));
+    assert!(spilled.contains("+vendored line 499"));
+    assert!(!spilled.contains("small.txt"));
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_detects_renames() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    let content: String = (0..50).map(|i| format!("fn item_{}() {{}}\n", i)).collect();
+
+    fs::create_dir(temp_dir.path().join("old"))?;
+    fs::write(temp_dir.path().join("old/module.rs"), &content)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new("old/module.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = repo.commit(Some("HEAD"), &signature, &signature, "Add", &tree, &[])?;
+
+    fs::create_dir(temp_dir.path().join("new"))?;
+    fs::rename(
+        temp_dir.path().join("old/module.rs"),
+        temp_dir.path().join("new/module.rs"),
+    )?;
+    index.remove_path(Path::new("old/module.rs"))?;
+    index.add_path(Path::new("new/module.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = repo.find_commit(parent)?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "Move",
+        &tree,
+        &[&parent],
+    )?;
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(&["extract", "--commit", "HEAD"])
+        .output()?;
+    assert!(output.status.success());
+
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let files = diff["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["status"], "renamed");
+    assert_eq!(files[0]["path"], "new/module.rs");
+    assert_eq!(files[0]["old_path"], "old/module.rs");
+    assert_eq!(diff["summary"]["total_deletions"], 0);
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(&["extract", "--commit", "HEAD", "--format", "summary"])
+        .output()?;
+    assert!(String::from_utf8(output.stdout)?.contains("old/module.rs → new/module.rs"));
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_from_remote_repository() -> Result<(), Box<dyn std::error::Error>> {
+    let remote_dir = TempDir::new()?;
+    let repo = git2::Repository::init(remote_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    let mut index = repo.index()?;
+
+    fs::write(remote_dir.path().join("lib.rs"), "fn one() {}\n")?;
+    index.add_path(Path::new("lib.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let first = repo.commit(Some("HEAD"), &signature, &signature, "One", &tree, &[])?;
+
+    fs::write(
+        remote_dir.path().join("lib.rs"),
+        "fn one() {}\nfn two() {}\n",
+    )?;
+    index.add_path(Path::new("lib.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parent = repo.find_commit(first)?;
+    let second = repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "Two",
+        &tree,
+        &[&parent],
+    )?;
+
+    // Run outside any working copy
+    let work_dir = TempDir::new()?;
+    let url = format!("file://{}", remote_dir.path().display());
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .current_dir(work_dir.path())
+        .args(&["extract", "--repo", &url, "--commit", &second.to_string()])
+        .output()?;
+    assert!(output.status.success());
+
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(diff["identifier"], second.to_string());
+    assert_eq!(diff["message"], "Two");
+    assert_eq!(diff["files"][0]["diff"], " fn one() {}\n+fn two() {}\n");
+
+    Ok(())
+}
+
 #[test]
 fn test_generate_command_without_ai_key() {
     #[allow(deprecated)]
@@ -237,3 +515,2564 @@ fn test_extract_and_generate_pipeline() -> Result<(), Box<dyn std::error::Error>
 
     Ok(())
 }
+
+#[test]
+fn test_global_json_output() -> Result<(), Box<dyn std::error::Error>> {
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["--json", "config", "show"])
+        .output()?;
+    assert!(output.status.success());
+    let config: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(config["storage"].is_object());
+
+    // Errors are reported as JSON on stdout with a failing exit code
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["mapping", "get", "no-such-service-for-json-test", "--json"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(5));
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(error["error"].is_string());
+    assert_eq!(error["code"], "not_found");
+    assert_eq!(error["exit_code"], 5);
+
+    Ok(())
+}
+
+#[test]
+fn test_config_show_masks_secrets() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        r#"
+[git]
+github_token = "${KTME_TEST_SHOW_TOKEN}"
+gitlab_token = "gitlab-in-clear"
+
+[[git.github_hosts]]
+host = "github.example.com"
+token = "${KTME_TEST_SHOW_TOKEN}"
+
+[confluence]
+api_token = "${KTME_TEST_SHOW_TOKEN}"
+
+[issues]
+jira_api_token = "${KTME_TEST_SHOW_TOKEN}"
+
+[ai]
+provider = "mock"
+api_key = "${KTME_TEST_SHOW_TOKEN}"
+
+[ai.headers]
+Authorization = "Bearer ${KTME_TEST_SHOW_TOKEN}"
+"#,
+    )?;
+
+    for json in [false, true] {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env("KTME_TEST_SHOW_TOKEN", "token-from-env-1234");
+        if json {
+            cmd.arg("--json");
+        }
+        let output = cmd.args(&["config", "show"]).output()?;
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout)?;
+        assert!(!stdout.contains("token-from-env-1234"), "{}", stdout);
+        assert!(!stdout.contains("gitlab-in-clear"), "{}", stdout);
+        assert!(stdout.contains("****"));
+        if json {
+            let config: serde_json::Value = serde_json::from_str(&stdout)?;
+            assert_eq!(config["git"]["github_hosts"][0]["token"], "****");
+            assert_eq!(config["ai"]["headers"]["Authorization"], "****");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_errors_show_class_hint_and_exit_code() {
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["mapping", "get", "no-such-service-for-hint-test"])
+        .assert()
+        .code(5)
+        .stderr(predicates::str::contains(
+            "Error [not_found]: Service mapping not found: no-such-service-for-hint-test",
+        ))
+        .stderr(predicates::str::contains(
+            "Hint: Map the service with `ktme mapping add no-such-service-for-hint-test",
+        ));
+
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["update", "--service", "any"])
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("Error [usage]"));
+
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("4   authentication rejected"));
+}
+
+#[test]
+fn test_completions_and_man_page() -> Result<(), Box<dyn std::error::Error>> {
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["completions", "bash"])
+        .output()?;
+    assert!(output.status.success());
+    // The dynamic script calls back into ktme for candidates
+    assert!(String::from_utf8(output.stdout)?.contains("COMPLETE=\"bash\""));
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["completions", "zsh", "--static"])
+        .output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.starts_with("#compdef ktme"));
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme").unwrap().arg("man").output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains(".TH ktme 1"));
+
+    Ok(())
+}
+
+#[test]
+fn test_logs_command_reads_json_log_file() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .env("KTME_CONFIG", &config_path)
+        .args(&["extract", "--commit", "HEAD"])
+        .assert()
+        .success();
+    assert!(temp_dir.path().join("logs").is_dir());
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .env("KTME_CONFIG", &config_path)
+        .args(&["--json", "logs", "--since", "1h"])
+        .output()?;
+    assert!(output.status.success());
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|e| e["message"] == "Extracting code changes..."));
+
+    Ok(())
+}
+
+#[test]
+fn test_timings_summary_on_stderr() -> Result<(), Box<dyn std::error::Error>> {
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&[
+            "--timings",
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "test-service",
+            "--force",
+        ])
+        .output()?;
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Timings (total"));
+    assert!(stderr.contains("AI Mock: 1 request(s)"));
+    assert!(!String::from_utf8(output.stdout)?.contains("Timings (total"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_skips_unchanged_input() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let output = temp_dir.path().join("incremental.md");
+    let generate = |force: bool| {
+        let mut args = vec![
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "incremental-service",
+            "--output",
+            output.to_str().unwrap(),
+        ];
+        if force {
+            args.push("--force");
+        }
+        #[allow(deprecated)]
+        Command::cargo_bin("ktme")
+            .unwrap()
+            .args(&args)
+            .assert()
+            .success()
+    };
+
+    generate(false).stdout(predicates::str::contains("Documentation saved to"));
+    generate(false).stdout(predicates::str::contains(
+        "No changes for incremental-service since the last generation",
+    ));
+    generate(true).stdout(predicates::str::contains("Documentation saved to"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_lists_primary_contacts() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let mut index = repo.index()?;
+
+    fs::write(temp_dir.path().join("CODEOWNERS"), "*.rs @org/core\n")?;
+    fs::write(temp_dir.path().join("lib.rs"), "fn one() {}\nfn two() {}\n")?;
+    index.add_path(Path::new("CODEOWNERS"))?;
+    index.add_path(Path::new("lib.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let alice = git2::Signature::now("Alice", "alice@example.com")?;
+    let first = repo.commit(Some("HEAD"), &alice, &alice, "Add lib", &tree, &[])?;
+
+    fs::write(
+        temp_dir.path().join("lib.rs"),
+        "fn one() {}\nfn three() {}\n",
+    )?;
+    index.add_path(Path::new("lib.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let bob = git2::Signature::now("Bob", "bob@example.com")?;
+    let parent = repo.find_commit(first)?;
+    repo.commit(Some("HEAD"), &bob, &bob, "Rename", &tree, &[&parent])?;
+
+    let output = temp_dir.path().join("contacts.md");
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "contacts-service",
+            "--output",
+            output.to_str().unwrap(),
+            "--force",
+        ])
+        .assert()
+        .success();
+
+    let content = fs::read_to_string(&output)?;
+    assert!(content.contains("## Primary contacts"));
+    assert!(content.contains("- Alice <alice@example.com> (2 lines)"));
+    assert!(content.contains("- Code owners: @org/core"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_resolves_services_automatically() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+
+    fs::write(
+        temp_dir.path().join("CODEOWNERS"),
+        "/billing/ @org/auto-billing\n",
+    )?;
+    fs::create_dir(temp_dir.path().join("billing"))?;
+    fs::write(
+        temp_dir.path().join("billing/invoice.rs"),
+        "fn total() {}\n",
+    )?;
+    fs::write(temp_dir.path().join("notes.txt"), "unowned\n")?;
+    let mut index = repo.index()?;
+    index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    repo.commit(Some("HEAD"), &signature, &signature, "Billing", &tree, &[])?;
+
+    let docs = config_dir.path().join("billing.md");
+    fs::write(&docs, "# Billing\n")?;
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .env("KTME_CONFIG", config_dir.path().join("config.toml"))
+        .args(&[
+            "mapping",
+            "add",
+            "auto-billing",
+            "--file",
+            docs.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let output = temp_dir.path().join("{service}.md");
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .env("KTME_CONFIG", config_dir.path().join("config.toml"))
+        .current_dir(temp_dir.path())
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "auto",
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Resolved services: auto-billing (1 files)",
+        ));
+
+    let content = fs::read_to_string(temp_dir.path().join("auto-billing.md"))?;
+    assert!(content.contains("# Documentation for auto-billing"));
+
+    Ok(())
+}
+
+#[test]
+fn test_update_routes_changelog_entries() -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = TempDir::new()?;
+    let changelog = config_dir.path().join("CHANGELOG.md");
+    fs::write(
+        &changelog,
+        "# Changelog\n\n## [Unreleased]\n\n- Pending fix\n\n## [1.0.0]\n\n- First release\n",
+    )?;
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .env("KTME_CONFIG", config_dir.path().join("config.toml"))
+        .args(&[
+            "mapping",
+            "add",
+            "routed-changelog",
+            "--file",
+            changelog.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let update = |extra: &[&str]| {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"))
+            .args(&[
+                "update",
+                "--commit",
+                "HEAD",
+                "--service",
+                "routed-changelog",
+            ])
+            .args(extra);
+        cmd
+    };
+
+    update(&["--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("-> [Unreleased] (append)"));
+
+    update(&[]).assert().success();
+    let content = fs::read_to_string(&changelog)?;
+    let unreleased = content.find("## [Unreleased]").unwrap();
+    let pending = content.find("- Pending fix").unwrap();
+    let released = content.find("## [1.0.0]").unwrap();
+    assert!(unreleased < pending && pending < released);
+    assert!(content[pending..released].len() > "- Pending fix\n\n".len());
+    assert!(content.ends_with("- First release\n"));
+
+    update(&["--section", "unreleased", "--mode", "replace"])
+        .assert()
+        .success();
+    let content = fs::read_to_string(&changelog)?;
+    assert!(!content.contains("- Pending fix"));
+    assert!(content.ends_with("## [1.0.0]\n\n- First release\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_mapping_service_settings() -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = TempDir::new()?;
+    let docs = config_dir.path().join("settings.md");
+    fs::write(&docs, "# Settings\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"));
+        cmd
+    };
+
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "settings-service",
+            "--file",
+            docs.to_str().unwrap(),
+            "--set",
+            "language=German",
+            "--set",
+            "exclude=*.lock, docs/**",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("language = German"));
+
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "settings-service",
+            "--set",
+            "model=gpt-4o",
+        ])
+        .assert()
+        .success();
+    ktme()
+        .args(&["mapping", "add", "settings-service", "--set", "color=blue"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Unknown service setting 'color'"));
+
+    let output = ktme()
+        .args(&["mapping", "get", "settings-service", "--json"])
+        .output()?;
+    let mapping: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(mapping["settings"]["language"], "German");
+    assert_eq!(mapping["settings"]["model"], "gpt-4o");
+    assert_eq!(
+        mapping["settings"]["exclude"],
+        serde_json::json!(["*.lock", "docs/**"])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_mapping_get_details_shows_document_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = TempDir::new()?;
+    let docs = config_dir.path().join("details.md");
+    fs::write(&docs, "# Details\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"));
+        cmd
+    };
+
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "details-service",
+            "--file",
+            docs.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    ktme()
+        .args(&["mapping", "get", "details-service", "--details"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("      Updated: "));
+
+    let output = ktme()
+        .args(&["--json", "mapping", "get", "details-service", "--details"])
+        .output()?;
+    assert!(output.status.success());
+    let mapping: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(mapping["details"][0]["location"], docs.to_str().unwrap());
+    assert!(mapping["details"][0]["metadata"]["updated_at"].is_string());
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_commits_docs() -> Result<(), Box<dyn std::error::Error>> {
+    let docs_repo = TempDir::new()?;
+    let repo = git2::Repository::init(docs_repo.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])?;
+    let output = docs_repo.path().join("docs/commit-docs.md");
+    fs::create_dir_all(output.parent().unwrap())?;
+
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "commit-docs-service",
+            "--output",
+            output.to_str().unwrap(),
+            "--force",
+            "--commit-docs",
+            "--branch",
+            "docs/update-commit-docs-service",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Committed 1 documentation file(s)",
+        ));
+
+    let head = repo.head()?;
+    assert_eq!(head.shorthand(), Some("docs/update-commit-docs-service"));
+    let commit = head.peel_to_commit()?;
+    assert!(commit
+        .message()
+        .unwrap()
+        .starts_with("docs(commit-docs-service): update documentation for "));
+    assert!(commit
+        .tree()?
+        .get_path(Path::new("docs/commit-docs.md"))
+        .is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_snapshot_at_tag() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    let commit = |message: &str| -> Result<git2::Oid, git2::Error> {
+        let mut index = repo.index()?;
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => Vec::new(),
+        };
+        let parents: Vec<_> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+    };
+    fs::write(temp_dir.path().join("lib.rs"), "pub fn v1() {}\n")?;
+    let v1 = commit("Release 1.0")?;
+    repo.tag_lightweight("v1.0.0", &repo.find_object(v1, None)?, false)?;
+    fs::write(temp_dir.path().join("lib.rs"), "pub fn v2() {}\n")?;
+    commit("Start 2.0")?;
+
+    let generate = |at: &str| {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.current_dir(temp_dir.path()).args(&[
+            "--json",
+            "generate",
+            "--at",
+            at,
+            "--service",
+            "snapshot-service",
+            "--force",
+        ]);
+        cmd
+    };
+    for (at, version) in [
+        ("v1.0.0", "v1.0.0".to_string()),
+        ("HEAD", "v1.0.0-1-g".to_string()),
+    ] {
+        let output = generate(at).output()?;
+        assert!(output.status.success());
+        let doc: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+        assert!(doc["source"].as_str().unwrap().starts_with(&version));
+    }
+    generate("2000-01-01")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "No commit on or before 2000-01-01",
+        ));
+
+    // The snapshot is read from git, not checked out
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("lib.rs"))?,
+        "pub fn v2() {}\n"
+    );
+    assert!(repo.statuses(None)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_multi_repository_service() -> Result<(), Box<dyn std::error::Error>> {
+    let workspace = TempDir::new()?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    for (name, file, message) in [
+        ("api", "handler.rs", "Add handler"),
+        ("infra", "main.tf", "Add route"),
+    ] {
+        let dir = workspace.path().join(name);
+        fs::create_dir_all(&dir)?;
+        let repo = git2::Repository::init(&dir)?;
+        fs::write(dir.join(file), "change\n")?;
+        let mut index = repo.index()?;
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[])?;
+    }
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.current_dir(workspace.path())
+            .env("KTME_CONFIG", workspace.path().join("config.toml"));
+        cmd
+    };
+
+    let output = ktme()
+        .args(&["extract", "--commit", "HEAD", "--repos", "api,infra"])
+        .output()?;
+    assert!(output.status.success());
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let paths: Vec<_> = diff["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, ["api/handler.rs", "infra/main.tf"]);
+    assert_eq!(diff["repositories"][1]["name"], "infra");
+    assert_eq!(diff["message"], "[api] Add handler\n\n[infra] Add route");
+
+    // Without --repos the registered repositories of the service are read
+    let api = workspace.path().join("api");
+    let infra = workspace.path().join("infra");
+    let docs = workspace.path().join("payments.md");
+    fs::write(&docs, "# Payments\n")?;
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "payments",
+            "--file",
+            docs.to_str().unwrap(),
+            "--set",
+            &format!("repositories={},{}", api.display(), infra.display()),
+        ])
+        .assert()
+        .success();
+    let output = ktme()
+        .args(&[
+            "--json",
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "payments",
+            "--force",
+        ])
+        .output()?;
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(doc["source"].as_str().unwrap().starts_with("api@"));
+    assert!(doc["source"].as_str().unwrap().contains(", infra@"));
+
+    Ok(())
+}
+
+#[test]
+fn test_pr_provider_detected_from_remote() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .env("KTME_CONFIG", temp_dir.path().join("config.toml"));
+        cmd
+    };
+
+    repo.remote("origin", "git@bitbucket.org:acme/api.git")?;
+    ktme()
+        .args(&["generate", "--pr", "42", "--service", "api"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Bitbucket pull requests are not supported yet (acme/api)",
+        ));
+
+    repo.remote_set_url("origin", "https://code.example.org/acme/api.git")?;
+    ktme()
+        .args(&["extract", "--pr", "42"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "pass --provider github or gitlab",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_provider_config_crud() -> Result<(), Box<dyn std::error::Error>> {
+    use predicates::boolean::PredicateBooleanExt;
+
+    let config_dir = TempDir::new()?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"));
+        cmd
+    };
+    let docs = config_dir.path().join("docs");
+    let markdown = serde_json::json!({ "base_path": docs }).to_string();
+
+    ktme()
+        .args(&[
+            "provider",
+            "add",
+            "confluence",
+            "--config",
+            r#"{"base_url": "x"}"#,
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Invalid confluence configuration",
+        ));
+    ktme()
+        .args(&["provider", "add", "markdown", "--config", &markdown])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Saved markdown provider (default)",
+        ));
+
+    let confluence = config_dir.path().join("confluence.json");
+    fs::write(
+        &confluence,
+        r#"{"base_url": "https://example.atlassian.net", "username": "me", "api_token": "secret", "space_key": "DEV"}"#,
+    )?;
+    ktme()
+        .args(&[
+            "provider",
+            "add",
+            "confluence",
+            "--file",
+            confluence.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    ktme()
+        .args(&["provider", "list"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("secret").not());
+
+    ktme()
+        .args(&["provider", "set-default", "confluence"])
+        .assert()
+        .success();
+    let output = ktme().args(&["--json", "provider", "list"]).output()?;
+    let list: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(list["providers"][0]["provider"], "confluence");
+    assert_eq!(list["providers"][0]["default"], true);
+    assert_eq!(list["providers"][0]["config"]["api_token"], "***");
+
+    ktme()
+        .args(&["provider", "remove", "confluence"])
+        .assert()
+        .success();
+    ktme()
+        .args(&["provider", "remove", "confluence"])
+        .assert()
+        .failure();
+    ktme().args(&["providers", "status"]).assert().success();
+
+    Ok(())
+}
+
+#[test]
+fn test_mapping_validate_fix() -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = TempDir::new()?;
+    let kept = config_dir.path().join("kept.md");
+    let deleted = config_dir.path().join("deleted.md");
+    fs::write(&kept, "# Kept\n")?;
+    fs::write(&deleted, "# Deleted\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"));
+        cmd
+    };
+    for file in [&kept, &deleted] {
+        ktme()
+            .args(&["mapping", "add", "validate-service", "--file"])
+            .arg(file)
+            .assert()
+            .success();
+    }
+    fs::remove_file(&deleted)?;
+
+    ktme()
+        .args(&["mapping", "validate"])
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains("file does not exist"))
+        .stdout(predicates::str::contains("1 missing"));
+
+    let output = ktme()
+        .args(&["--json", "mapping", "validate", "--fix"])
+        .output()?;
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(report["removed"], 1);
+
+    ktme()
+        .args(&["mapping", "validate", "--service", "validate-service"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("0 missing"));
+
+    Ok(())
+}
+
+#[test]
+fn test_mapping_rename_updates_titles() -> Result<(), Box<dyn std::error::Error>> {
+    let config_dir = TempDir::new()?;
+    let docs = config_dir.path().join("billing.md");
+    fs::write(&docs, "# Billing API\n\nBilling handles invoices.\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"));
+        cmd
+    };
+    ktme()
+        .args(&["mapping", "add", "Billing", "--file"])
+        .arg(&docs)
+        .assert()
+        .success();
+
+    ktme()
+        .args(&[
+            "mapping",
+            "rename",
+            "Billing",
+            "Invoicing",
+            "--update-titles",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Renamed service Billing to Invoicing",
+        ));
+
+    ktme()
+        .args(&["mapping", "get", "Invoicing"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(docs.to_str().unwrap()));
+    ktme()
+        .args(&["mapping", "get", "Billing"])
+        .assert()
+        .failure();
+    assert_eq!(
+        fs::read_to_string(&docs)?,
+        "# Invoicing API\n\nBilling handles invoices.\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_review_queue() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "queued-commit", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Queue", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let output = temp_dir.path().join("queued.md");
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path);
+        cmd
+    };
+    let queue = || {
+        ktme()
+            .args(&[
+                "generate",
+                "--input",
+                diff_path.to_str().unwrap(),
+                "--service",
+                "queued-service",
+                "--output",
+                output.to_str().unwrap(),
+                "--queue",
+            ])
+            .assert()
+            .success()
+    };
+
+    queue().stdout(predicates::str::contains(
+        "Queued documentation for queued-service for review (id 1)",
+    ));
+    assert!(!output.exists());
+    queue();
+
+    let listed = ktme().args(&["--json", "queue", "list"]).output()?;
+    let listed: serde_json::Value = serde_json::from_slice(&listed.stdout)?;
+    assert_eq!(listed["documents"].as_array().unwrap().len(), 2);
+    assert_eq!(listed["documents"][0]["source"], "queued-commit");
+
+    ktme()
+        .args(&["queue", "show", "1"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "# Documentation for queued-service",
+        ));
+
+    ktme().args(&["queue", "approve", "1"]).assert().success();
+    assert!(fs::read_to_string(&output)?.contains("# Documentation for queued-service"));
+    ktme()
+        .args(&["queue", "reject", "1"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Queued documentation 1 was already approved",
+        ));
+
+    ktme()
+        .args(&["queue", "reject", "2", "--reason", "Too vague"])
+        .assert()
+        .success();
+    ktme()
+        .args(&["queue", "list"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No pending documentation"));
+
+    Ok(())
+}
+
+#[test]
+fn test_run_follows_service_policy() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    let docs = temp_dir.path().join("docs.md");
+    fs::write(&docs, "# Docs\n")?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "run-commit", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Add endpoint",
+            "files": [{"path": "src/api.rs", "status": "modified", "diff": "+fn api() {}", "additions": 1, "deletions": 0}],
+            "summary": {"total_files": 1, "total_additions": 1, "total_deletions": 0}}"#,
+    )?;
+    let write_config = |review: bool| {
+        fs::write(
+            &config_path,
+            format!(
+                "[storage]\ndatabase_file = {:?}\n\n[ai]\nprovider = \"mock\"\n\n\
+                 [policies.run-service]\ndoc_types = [\"general\"]\ntargets = [\"markdown\"]\nreview = {}\n",
+                temp_dir.path().join("ktme.db"),
+                review
+            ),
+        )
+    };
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("ANTHROPIC_API_KEY")
+            .env_remove("OPENAI_API_KEY");
+        cmd
+    };
+
+    write_config(false)?;
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "run-service",
+            "--file",
+            docs.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let output = ktme()
+        .args(&[
+            "--json",
+            "run",
+            "--service",
+            "run-service",
+            "--source",
+            diff_path.to_str().unwrap(),
+        ])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let reports: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let document = &reports[0]["documents"][0];
+    assert_eq!(document["doc_type"], "general");
+    assert_eq!(document["status"], "published");
+    assert_eq!(document["locations"][0]["location"], docs.to_str().unwrap());
+    assert!(fs::read_to_string(&docs)?.len() > "# Docs\n".len());
+
+    // The same changes are not published twice
+    ktme()
+        .args(&[
+            "run",
+            "--service",
+            "run-service",
+            "--source",
+            diff_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("(unchanged)"));
+
+    write_config(true)?;
+    ktme()
+        .args(&[
+            "run",
+            "--service",
+            "run-service",
+            "--source",
+            diff_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "general: queued for review (id 1)",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_translate_only_changed_sections() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n\n[ai]\nprovider = \"mock\"\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let docs = temp_dir.path().join("api.md");
+    fs::write(&docs, "# API\n\nIntro.\n\n## Usage\n\nCall it.\n")?;
+    let translated = temp_dir.path().join("ja").join("api.md");
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("ANTHROPIC_API_KEY")
+            .env_remove("OPENAI_API_KEY");
+        cmd
+    };
+    let translate = || {
+        let output = ktme()
+            .args(&["--json", "translate", "--service", "i18n-service"])
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    ktme()
+        .args(&["mapping", "add", "i18n-service", "--file"])
+        .arg(&docs)
+        .assert()
+        .success();
+    // The source must be a mapped location of the service
+    ktme()
+        .args(&["mapping", "add", "i18n-service", "--file"])
+        .arg(&translated)
+        .args(&[
+            "--translation-of",
+            "docs/other.md",
+            "--language",
+            "Japanese",
+        ])
+        .assert()
+        .failure();
+    ktme()
+        .args(&["mapping", "add", "i18n-service", "--file"])
+        .arg(&translated)
+        .args(&["--translation-of", docs.to_str().unwrap()])
+        .args(&["--language", "Japanese"])
+        .assert()
+        .success();
+
+    let results = translate();
+    assert_eq!(results[0]["translated"], 2);
+    assert_eq!(results[0]["written"], true);
+    assert!(translated.exists());
+
+    let results = translate();
+    assert_eq!(results[0]["translated"], 0);
+    assert_eq!(results[0]["written"], false);
+
+    fs::write(&docs, "# API\n\nIntro.\n\n## Usage\n\nCall it twice.\n")?;
+    let results = translate();
+    assert_eq!(results[0]["translated"], 1);
+    assert_eq!(results[0]["reused"], 1);
+
+    ktme()
+        .args(&["mapping", "get", "i18n-service"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("(Japanese) of"));
+
+    Ok(())
+}
+
+#[test]
+fn test_update_keeps_one_copy_of_the_notices() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n\n[ai]\nprovider = \"mock\"\n\n\
+             [notices]\nheader = \"**Internal** - do not share\"\n\
+             footer = \"*Generated by ktme for {{service}} from {{commit}}*\"\n\n\
+             [[notices.rules]]\nlocation = \"public\"\nenabled = false\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let internal = temp_dir.path().join("internal.md");
+    let public = temp_dir.path().join("public.md");
+    for doc in [&internal, &public] {
+        fs::write(doc, "# Notes\n\nIntro.\n\n---\n\n*Generated by ktme*\n")?;
+    }
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("ANTHROPIC_API_KEY")
+            .env_remove("OPENAI_API_KEY");
+        cmd
+    };
+    for doc in [&internal, &public] {
+        ktme()
+            .args(&["mapping", "add", "notice-service", "--file"])
+            .arg(doc)
+            .assert()
+            .success();
+    }
+
+    let update = || {
+        ktme()
+            .args(&[
+                "update",
+                "--commit",
+                "HEAD",
+                "--service",
+                "notice-service",
+                "--force",
+            ])
+            .assert()
+            .success();
+    };
+    update();
+    update();
+
+    let content = fs::read_to_string(&internal)?;
+    assert!(content.starts_with("**Internal** - do not share\n\n---\n\n# Notes"));
+    assert_eq!(content.matches("**Internal**").count(), 1);
+    assert!(!content.contains("*Generated by ktme*"));
+    assert_eq!(content.matches("*Generated by ktme for ").count(), 1);
+    let footer = content.rfind("\n---\n\n*Generated by ktme for notice-service from ");
+    assert!(footer.is_some_and(|at| !content[at + 5..].contains("\n---\n")));
+
+    let content = fs::read_to_string(&public)?;
+    assert!(content.starts_with("# Notes"));
+    assert!(!content.contains("*Generated by ktme*"));
+    assert!(!content.contains("Generated by ktme for"));
+
+    Ok(())
+}
+
+#[test]
+fn test_republish_adds_configured_notices() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n\n[notices]\nfooter = \"*Maintained by the platform team*\"\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let api = temp_dir.path().join("api.md");
+    let guide = temp_dir.path().join("guide.md");
+    fs::write(&api, "# API\n\nEndpoints.\n")?;
+    fs::write(&guide, "# Guide\n\nSteps.\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path);
+        cmd
+    };
+    for (service, doc) in [("api-service", &api), ("guide-service", &guide)] {
+        ktme()
+            .args(&["mapping", "add", service, "--file"])
+            .arg(doc)
+            .assert()
+            .success();
+    }
+
+    ktme()
+        .args(&["republish", "--all", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("would republish 2 document(s)"));
+    assert_eq!(fs::read_to_string(&api)?, "# API\n\nEndpoints.\n");
+
+    for _ in 0..2 {
+        ktme()
+            .args(&["republish", "--all", "--rate", "100/s"])
+            .assert()
+            .success()
+            .stdout(predicates::str::contains("Republished 2 of 2 document(s)"));
+    }
+    assert_eq!(
+        fs::read_to_string(&api)?,
+        "# API\n\nEndpoints.\n\n---\n\n*Maintained by the platform team*\n"
+    );
+    assert!(fs::read_to_string(&guide)?.ends_with("---\n\n*Maintained by the platform team*\n"));
+
+    ktme()
+        .args(&["republish", "--all", "--rate", "fast"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_for_several_services() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let output = temp_dir.path().join("{service}.md");
+
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "parallel-a,parallel-b",
+            "--jobs",
+            "2",
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    for service in ["parallel-a", "parallel-b"] {
+        let content = fs::read_to_string(temp_dir.path().join(format!("{}.md", service)))?;
+        assert!(content.contains(&format!("# Documentation for {}", service)));
+    }
+
+    // A single output file cannot hold several services
+    #[allow(deprecated)]
+    Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--service",
+            "parallel-a",
+            "--service",
+            "parallel-b",
+            "--output",
+            temp_dir.path().join("all.md").to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_plugins_list() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+    let bin_dir = temp_dir.path().join("bin");
+    let wasm_dir = temp_dir.path().join("wasm");
+    fs::create_dir_all(&bin_dir)?;
+    fs::create_dir_all(&wasm_dir)?;
+
+    let plugin = bin_dir.join("ktme-provider-wiki");
+    fs::write(&plugin, "#!/bin/sh\necho '{\"result\": true}'\n")?;
+    fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755))?;
+    fs::write(wasm_dir.join("not-a-module.wasm"), "")?;
+
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[plugins]\ndirectory = {:?}\n",
+            wasm_dir.display().to_string()
+        ),
+    )?;
+
+    #[allow(deprecated)]
+    let output = Command::cargo_bin("ktme")
+        .unwrap()
+        .args(&["--json", "plugins", "list"])
+        .env("KTME_CONFIG", &config_path)
+        .env("PATH", &bin_dir)
+        .output()?;
+    assert!(output.status.success());
+
+    let listing: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(listing["providers"][0]["name"], "wiki");
+    assert_eq!(listing["ai"].as_array().map(Vec::len), Some(0));
+    let extension = &listing["wasm"]["extensions"][0];
+    assert_eq!(extension["name"], "not-a-module");
+    // Empty files are not valid modules, and builds without `wasm` load none
+    assert!(extension["error"].is_string());
+
+    Ok(())
+}
+
+#[test]
+fn test_auth_status_confluence() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("CONFLUENCE_OAUTH_CLIENT_SECRET");
+        cmd
+    };
+
+    fs::write(
+        &config_path,
+        "[confluence]\nbase_url = \"https://docs.atlassian.net/wiki\"\nauth_type = \"oauth\"\n",
+    )?;
+    ktme()
+        .args(&["auth", "status", "confluence"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("oauth_client_id"));
+
+    fs::write(
+        &config_path,
+        "[confluence]\nbase_url = \"https://docs.atlassian.net/wiki\"\nauth_type = \"oauth\"\n\
+         oauth_client_id = \"app\"\noauth_client_secret = \"secret\"\n",
+    )?;
+    ktme()
+        .args(&["auth", "status", "confluence"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Not signed in to https://docs.atlassian.net",
+        ));
+    ktme()
+        .args(&["auth", "logout", "confluence"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Not signed in"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_timeout_options() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(&config_path, "")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path);
+        cmd
+    };
+
+    ktme()
+        .args(&[
+            "generate",
+            "--staged",
+            "--service",
+            "svc",
+            "--timeout",
+            "soon",
+        ])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("Invalid duration 'soon'"));
+
+    fs::write(&config_path, "[timeouts]\nconnect = \"0s\"\n")?;
+    ktme()
+        .args(&["config", "validate"])
+        .assert()
+        .failure()
+        .code(3)
+        .stdout(predicates::str::contains(
+            "timeouts.connect: Invalid timeout '0s'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_offline_mode() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"openai\"\napi_key = \"sk-test\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "offline-commit", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Offline", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let output = temp_dir.path().join("offline.md");
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("KTME_OFFLINE");
+        cmd
+    };
+
+    // The hosted AI provider is replaced by the mock
+    ktme()
+        .args(&[
+            "--offline",
+            "--json",
+            "generate",
+            "--input",
+            diff_path.to_str().unwrap(),
+            "--service",
+            "offline-service",
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(r#""provider": "Mock""#));
+    assert!(output.exists());
+
+    ktme()
+        .args(&["--offline", "extract", "--pr", "1"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("offline mode"));
+
+    // Only remote locations are mapped, so there is nothing to update
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "offline-service",
+            "--url",
+            "https://docs.atlassian.net/wiki/pages/viewpage.action?pageId=1",
+        ])
+        .assert()
+        .success();
+    ktme()
+        .args(&[
+            "--offline",
+            "update",
+            "--staged",
+            "--service",
+            "offline-service",
+        ])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("offline mode"));
+
+    Ok(())
+}
+
+#[test]
+fn test_self_update_check_only() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+
+    // Release metadata served once from a local port
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/releases/latest", listener.local_addr()?);
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let _ = stream.read(&mut request).unwrap();
+        let body = r#"{"tag_name": "v99.0.0", "html_url": "https://github.com/FreePeak/ktme/releases/tag/v99.0.0", "assets": []}"#;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+
+    #[allow(deprecated)]
+    let mut cmd = Command::cargo_bin("ktme").unwrap();
+    cmd.env("KTME_RELEASES_URL", &url)
+        .env_remove("KTME_OFFLINE")
+        .args(&["--json", "self-update", "--check-only"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(r#""latest": "99.0.0""#))
+        .stdout(predicates::str::contains(r#""update_available": true"#));
+    server.join().unwrap();
+
+    #[allow(deprecated)]
+    let mut cmd = Command::cargo_bin("ktme").unwrap();
+    cmd.env("KTME_RELEASES_URL", &url)
+        .args(&["--offline", "self-update", "--check-only"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicates::str::contains("offline mode"));
+
+    Ok(())
+}
+
+#[test]
+fn test_export_and_import_state() -> Result<(), Box<dyn std::error::Error>> {
+    let source = TempDir::new()?;
+    let source_config = source.path().join("config.toml");
+    fs::write(
+        &source_config,
+        format!(
+            "[ai]\nprovider = \"openai\"\napi_key = \"sk-exported-secret\"\n\n[storage]\ndatabase_file = {:?}\n",
+            source.path().join("ktme.db")
+        ),
+    )?;
+    fs::create_dir_all(source.path().join("prompts"))?;
+    fs::write(
+        source.path().join("prompts/changelog.md"),
+        "Team changelog prompt\n",
+    )?;
+    let ktme = |config: &Path| {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config);
+        cmd
+    };
+    ktme(&source_config)
+        .args(&[
+            "mapping",
+            "add",
+            "state-service",
+            "--url",
+            "https://docs.atlassian.net/wiki/pages/viewpage.action?pageId=7",
+        ])
+        .assert()
+        .success();
+
+    let bundle = source.path().join("state.tar.zst");
+    ktme(&source_config)
+        .args(&["export", "state", bundle.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("ai.api_key"));
+    let unpacked = zstd::decode_all(fs::File::open(&bundle)?)?;
+    assert!(!String::from_utf8_lossy(&unpacked).contains("sk-exported-secret"));
+
+    // A teammate's setup: its own key and database location are kept
+    let target = TempDir::new()?;
+    let target_config = target.path().join("config.toml");
+    let target_db = target.path().join("data/ktme.db");
+    fs::write(
+        &target_config,
+        format!(
+            "[ai]\napi_key = \"sk-teammate\"\n\n[storage]\ndatabase_file = {:?}\n",
+            target_db
+        ),
+    )?;
+    ktme(&target_config)
+        .args(&["import", "state", bundle.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--force"));
+    ktme(&target_config)
+        .args(&["import", "state", bundle.to_str().unwrap(), "--force"])
+        .assert()
+        .success();
+
+    let imported = fs::read_to_string(&target_config)?;
+    assert!(imported.contains("provider = \"openai\""));
+    assert!(imported.contains("sk-teammate"));
+    assert!(imported.contains(&target_db.display().to_string()));
+    assert!(target.path().join("config.toml.bak").exists());
+    assert!(target_db.exists());
+    assert!(fs::read_to_string(target.path().join("mappings.toml"))?.contains("state-service"));
+    assert_eq!(
+        fs::read_to_string(target.path().join("prompts/changelog.md"))?,
+        "Team changelog prompt\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_state_bundles_leave_out_nested_secrets() -> Result<(), Box<dyn std::error::Error>> {
+    let source = TempDir::new()?;
+    let source_config = source.path().join("config.toml");
+    fs::write(
+        &source_config,
+        format!(
+            r#"[ai]
+provider = "openai"
+headers = {{ Authorization = "Bearer gateway-token" }}
+
+[[ai.fallback]]
+provider = "anthropic"
+api_key = "sk-ant-fallback"
+headers = {{ x-api-key = "fallback-gateway-key" }}
+
+[storage]
+database_file = {:?}
+"#,
+            source.path().join("ktme.db")
+        ),
+    )?;
+    let ktme = |config: &Path| {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config);
+        cmd
+    };
+
+    let bundle = source.path().join("state.tar.zst");
+    ktme(&source_config)
+        .args(&["export", "state", bundle.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("ai.fallback[0].api_key"))
+        .stdout(predicates::str::contains("ai.headers.Authorization"));
+    let archive =
+        String::from_utf8_lossy(&zstd::decode_all(fs::File::open(&bundle)?)?).into_owned();
+    for secret in ["gateway-token", "sk-ant-fallback", "fallback-gateway-key"] {
+        assert!(!archive.contains(secret), "{} was exported", secret);
+    }
+
+    // The fallback entry of the bundle does not get the local fallback key
+    let target = TempDir::new()?;
+    let target_config = target.path().join("config.toml");
+    fs::write(
+        &target_config,
+        format!(
+            "[ai]\nprovider = \"openai\"\n\n[[ai.fallback]]\nprovider = \"openai\"\napi_key = \"sk-teammate-fallback\"\n\n[storage]\ndatabase_file = {:?}\n",
+            target.path().join("ktme.db")
+        ),
+    )?;
+    ktme(&target_config)
+        .args(&["import", "state", bundle.to_str().unwrap(), "--force"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("ai.fallback[0].api_key"));
+
+    let imported: toml::Value = toml::from_str(&fs::read_to_string(&target_config)?)?;
+    let fallback = &imported["ai"]["fallback"][0];
+    assert_eq!(fallback["provider"].as_str(), Some("anthropic"));
+    assert!(fallback.get("api_key").is_none());
+    assert!(fallback
+        .get("headers")
+        .and_then(|headers| headers.get("x-api-key"))
+        .is_none());
+    assert!(imported["ai"]
+        .get("headers")
+        .and_then(|headers| headers.get("Authorization"))
+        .is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_record_and_replay_ai_responses() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    let database = temp_dir.path().join("ktme.db");
+    let fixtures = temp_dir.path().join("fixtures");
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "recorded-commit", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Record", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("ANTHROPIC_API_KEY")
+            .env_remove("OPENAI_API_KEY");
+        cmd
+    };
+    let generate = |mode: &str, output: &std::path::Path| {
+        ktme()
+            .args(&[
+                mode,
+                fixtures.to_str().unwrap(),
+                "generate",
+                "--input",
+                diff_path.to_str().unwrap(),
+                "--service",
+                "recorded-service",
+                "--output",
+                output.to_str().unwrap(),
+                "--force",
+            ])
+            .assert()
+    };
+
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            database
+        ),
+    )?;
+    let recorded = temp_dir.path().join("recorded.md");
+    generate("--record", &recorded).success();
+    assert_eq!(fs::read_dir(&fixtures)?.count(), 1);
+
+    // Replaying needs neither the provider nor its API key
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"anthropic\"\n\n[storage]\ndatabase_file = {:?}\n",
+            database
+        ),
+    )?;
+    let replayed = temp_dir.path().join("replayed.md");
+    generate("--replay", &replayed).success();
+    assert_eq!(
+        fs::read_to_string(&recorded)?,
+        fs::read_to_string(&replayed)?
+    );
+
+    fs::remove_dir_all(&fixtures)?;
+    generate("--replay", &temp_dir.path().join("missing.md"))
+        .failure()
+        .stderr(predicates::str::contains("No recorded AI response"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_redacts_code() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    let fixtures = temp_dir.path().join("fixtures");
+    let diff_path = temp_dir.path().join("diff.json");
+    let output = temp_dir.path().join("demo.md");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "demo-commit", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Demo", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let generate = |mode: &str| {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .env_remove("ANTHROPIC_API_KEY")
+            .env_remove("OPENAI_API_KEY")
+            .args(&[mode, fixtures.to_str().unwrap(), "generate", "--input"])
+            .arg(&diff_path)
+            .args(&["--service", "demo-service", "--output"])
+            .arg(&output)
+            .args(&["--redact-code", "--force"])
+            .assert()
+            .success();
+    };
+    generate("--record");
+
+    // The model was asked not to quote code, and whatever it quotes anyway
+    // is replaced
+    let recording = fs::read_dir(&fixtures)?.next().unwrap()?.path();
+    let mut fixture: serde_json::Value = serde_json::from_str(&fs::read_to_string(&recording)?)?;
+    assert!(fixture["prompt"]
+        .as_str()
+        .unwrap()
+        .contains("do not quote source code"));
+    fixture["response"] = serde_json::json!(
+        "# Charges\n\n```python\ndef charge(card):\n    return gateway.post(card)\n```\n"
+    );
+    fs::write(&recording, serde_json::to_string(&fixture)?)?;
+    generate("--replay");
+
+    let content = fs::read_to_string(&output)?;
+    assert!(content.contains("> *Code omitted: 2 lines of python declaring function `charge`.*"));
+    assert!(!content.contains("gateway.post"));
+
+    Ok(())
+}
+
+#[test]
+fn test_template_preview_validates_variables() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Add exports", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let template_path = temp_dir.path().join("release-notes.md");
+    fs::write(
+        &template_path,
+        "---\nvariables:\n  - name: AUDIENCE\n    required: true\n  - name: TONE\n    default: friendly\n---\nNotes for {{AUDIENCE}} in a {{TONE}} tone: {{MESSAGE}}\n",
+    )?;
+    let preview = |extra: &[&str]| {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .args(&[
+                "template",
+                "preview",
+                template_path.to_str().unwrap(),
+                "--input",
+                diff_path.to_str().unwrap(),
+            ])
+            .args(extra)
+            .assert()
+    };
+
+    preview(&[]).failure().stderr(predicates::str::contains(
+        "missing required variables: AUDIENCE",
+    ));
+    preview(&["--var", "AUDIENCE=customers"])
+        .success()
+        .stdout(predicates::str::contains(
+            "Notes for customers in a friendly tone: Add exports",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_prompt_export_and_packs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Add exports", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path);
+        cmd
+    };
+    let preview = |doc_type: &str| {
+        ktme()
+            .args(&[
+                "template",
+                "preview",
+                doc_type,
+                "--input",
+                diff_path.to_str().unwrap(),
+            ])
+            .assert()
+    };
+
+    ktme().args(&["prompts", "export"]).assert().success();
+    let changelog = temp_dir.path().join("prompts").join("changelog.md");
+    assert!(fs::read_to_string(&changelog)?.contains("changelog entries"));
+
+    // Edited prompts replace the built-in ones
+    fs::write(&changelog, "Team changelog prompt")?;
+    preview("changelog")
+        .success()
+        .stdout(predicates::str::starts_with("Team changelog prompt"));
+
+    // A pack adds its own document types and wins over the overrides
+    let pack = temp_dir.path().join("prompts").join("packs").join("sre");
+    fs::create_dir_all(&pack)?;
+    fs::write(pack.join("runbook.md"), "SRE runbook prompt")?;
+    preview("runbook")
+        .failure()
+        .stderr(predicates::str::contains("Unknown template 'runbook'"));
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n\n[documentation]\nprompt_pack = \"sre\"\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    preview("runbook")
+        .success()
+        .stdout(predicates::str::starts_with("SRE runbook prompt"));
+    ktme()
+        .args(&["prompts", "list"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Prompt pack: sre"))
+        .stdout(predicates::str::contains(changelog.to_str().unwrap()));
+
+    Ok(())
+}
+
+#[test]
+fn test_index_lists_services_and_docs() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let docs_dir = temp_dir.path().join("docs");
+    fs::create_dir_all(&docs_dir)?;
+    let billing = docs_dir.join("billing.md");
+    fs::write(&billing, "# Billing\n")?;
+    fs::write(
+        temp_dir.path().join("config.toml"),
+        format!(
+            "[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", temp_dir.path().join("config.toml"));
+        cmd
+    };
+
+    ktme()
+        .args(&["index"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No services are mapped yet"));
+
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "billing",
+            "--file",
+            billing.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let index = docs_dir.join("INDEX.md");
+    ktme()
+        .args(&["index", "--output", index.to_str().unwrap()])
+        .assert()
+        .success();
+    let content = fs::read_to_string(&index)?;
+    assert!(content.starts_with("# Documentation Index"));
+    assert!(content.contains("- [billing](#billing)"));
+    assert!(content.contains("](billing.md) (markdown)"));
+    assert!(content.contains("- **Last updated:** never"));
+
+    let output = ktme().args(&["index", "--json"]).output()?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json["services"][0]["name"], "billing");
+
+    Ok(())
+}
+
+#[test]
+fn test_onboarding_prompt_includes_repository_context() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Add invoices", "files": [],
+            "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}}"#,
+    )?;
+    let service_dir = temp_dir.path().join("billing");
+    fs::create_dir_all(service_dir.join("src"))?;
+    fs::write(
+        service_dir.join("README.md"),
+        "# Billing\n\n## Installation\n\n```bash\ncargo build --release\n```\n",
+    )?;
+    fs::write(
+        service_dir.join("Makefile"),
+        "test: ## Run the tests\n\tcargo test\n",
+    )?;
+    fs::write(
+        service_dir.join("src/invoice.rs"),
+        "pub struct Invoice {}\n",
+    )?;
+    let docs_path = temp_dir.path().join("billing.md");
+    fs::write(&docs_path, "# Billing\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .current_dir(&service_dir);
+        cmd
+    };
+
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "billing",
+            "--file",
+            docs_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    ktme()
+        .args(&[
+            "template",
+            "preview",
+            "onboarding",
+            "--input",
+            diff_path.to_str().unwrap(),
+            "--service",
+            "billing",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("# Onboarding Guide"))
+        .stdout(predicates::str::contains("- `cargo build --release`"))
+        .stdout(predicates::str::contains("- `make test  # Run the tests`"))
+        .stdout(predicates::str::contains("- src/invoice.rs: Invoice"))
+        .stdout(predicates::str::contains("billing.md (markdown)"));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_postmortem_from_range() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    let mut index = repo.index()?;
+    let mut parents = Vec::new();
+    for (content, message) in [
+        ("timeout = 30\n", "Add config"),
+        ("timeout = 1\n", "Lower timeout"),
+        ("timeout = 30\nretries = 3\n", "Restore timeout and retry"),
+    ] {
+        fs::write(temp_dir.path().join("service.toml"), content)?;
+        index.add_path(Path::new("service.toml"))?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let parent_commits: Vec<git2::Commit> = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<Result<_, _>>()?;
+        let parent_refs: Vec<&git2::Commit> = parent_commits.iter().collect();
+        let oid = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parent_refs,
+        )?;
+        parents = vec![oid];
+    }
+    let start = repo.revparse_single("HEAD~2")?.id().to_string();
+
+    let config_path = config_dir.path().join("config.toml");
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .current_dir(temp_dir.path());
+        cmd
+    };
+    let range = format!("{}..HEAD", start);
+    let output = temp_dir.path().join("postmortem.md");
+
+    ktme()
+        .args(&[
+            "generate",
+            "--range",
+            &range,
+            "--type",
+            "postmortem",
+            "--service",
+            "incident-service",
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let content = fs::read_to_string(&output)?;
+    assert!(content.contains("**Type**: postmortem"));
+
+    fs::write(
+        &config_path,
+        format!(
+            "[git]\nmax_commit_range = 1\n\n[storage]\ndatabase_file = {:?}\n",
+            config_dir.path().join("ktme.db")
+        ),
+    )?;
+    ktme()
+        .args(&[
+            "generate",
+            "--range",
+            &range,
+            "--type",
+            "postmortem",
+            "--service",
+            "incident-service",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "has 2 commits, more than git.max_commit_range (1)",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_release_notes_publishes_to_releases_page() -> Result<(), Box<dyn std::error::Error>>
+{
+    let temp_dir = TempDir::new()?;
+    let config_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    fs::write(temp_dir.path().join("lib.rs"), "fn export() {}\n")?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new("lib.rs"))?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        "feat: Add invoice export",
+        &tree,
+        &[],
+    )?;
+
+    fs::write(
+        config_dir.path().join("config.toml"),
+        format!(
+            "[storage]\ndatabase_file = {:?}\n",
+            config_dir.path().join("ktme.db")
+        ),
+    )?;
+    let releases = config_dir.path().join("Releases.md");
+    fs::write(&releases, "# Releases\n\nOlder notes\n")?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", config_dir.path().join("config.toml"))
+            .current_dir(temp_dir.path());
+        cmd
+    };
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "release-notes-service",
+            "--file",
+            releases.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    ktme()
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--type",
+            "release-notes",
+            "--audience",
+            "internal",
+            "--service",
+            "release-notes-service",
+            "--publish",
+            "Releases",
+        ])
+        .assert()
+        .success();
+    let content = fs::read_to_string(&releases)?;
+    assert!(content.starts_with("# Releases\n"));
+    assert!(content.len() > "# Releases\n\nOlder notes\n".len());
+    assert!(content.contains("Older notes"));
+
+    ktme()
+        .args(&[
+            "generate",
+            "--commit",
+            "HEAD",
+            "--type",
+            "release-notes",
+            "--service",
+            "release-notes-service",
+            "--publish",
+            "Changelog",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "No documentation location of release-notes-service matches 'Changelog'",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_test_plan_prompt_lists_test_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Add refunds",
+            "files": [
+                {"path": "billing/refund.py", "status": "modified", "additions": 20, "deletions": 0,
+                 "diff": "+def refund(amount):\n+    return -amount\n"},
+                {"path": "billing/test_refund.py", "status": "added", "additions": 2, "deletions": 0,
+                 "diff": "+def test_refund_negates_amount():\n+    assert refund(3) == -3\n"},
+                {"path": "billing/export.py", "status": "modified", "additions": 5, "deletions": 0,
+                 "diff": "+def export():\n+    pass\n"}
+            ],
+            "summary": {"total_files": 3, "total_additions": 27, "total_deletions": 0}}"#,
+    )?;
+
+    #[allow(deprecated)]
+    let mut cmd = Command::cargo_bin("ktme")?;
+    cmd.env("KTME_CONFIG", &config_path)
+        .args(&[
+            "template",
+            "preview",
+            "test-plan",
+            "--input",
+            diff_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("## Test Summary"))
+        .stdout(predicates::str::contains(
+            "- test_refund_negates_amount (added, billing/test_refund.py)",
+        ))
+        .stdout(predicates::str::contains(
+            "Source files with test changes:\n- billing/refund.py",
+        ))
+        .stdout(predicates::str::contains(
+            "Source files without test changes:\n- billing/export.py",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_security_review_prompt_lists_sensitive_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )?;
+    let diff_path = temp_dir.path().join("diff.json");
+    fs::write(
+        &diff_path,
+        r#"{"source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+            "author": "test@example.com", "message": "Add admin export",
+            "files": [
+                {"path": "app/routes.py", "status": "modified", "additions": 3, "deletions": 0,
+                 "diff": "+@app.route(\"/admin/export\")\n+def export():\n+    check_password(request.args[\"password\"])\n"},
+                {"path": "requirements.txt", "status": "modified", "additions": 1, "deletions": 0,
+                 "diff": "+pyyaml==5.3\n"}
+            ],
+            "summary": {"total_files": 2, "total_additions": 4, "total_deletions": 0}}"#,
+    )?;
+
+    #[allow(deprecated)]
+    let mut cmd = Command::cargo_bin("ktme")?;
+    cmd.env("KTME_CONFIG", &config_path)
+        .args(&[
+            "template",
+            "preview",
+            "security-review",
+            "--input",
+            diff_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("# Security Review"))
+        .stdout(predicates::str::contains(
+            "New endpoints:\n- app/routes.py (added): `@app.route(\"/admin/export\")`",
+        ))
+        .stdout(predicates::str::contains(
+            "Authentication and authorization:\n- app/routes.py (added): `check_password(request.args[\"password\"])`",
+        ))
+        .stdout(predicates::str::contains(
+            "Dependency changes:\n- requirements.txt (added): `pyyaml==5.3`",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn test_backfill_generates_one_document_per_commit() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = TempDir::new()?;
+    let config_dir = TempDir::new()?;
+    let repo = git2::Repository::init(temp_dir.path())?;
+    let signature = git2::Signature::now("Test", "test@example.com")?;
+    let mut index = repo.index()?;
+    let mut parents = Vec::new();
+    for (content, message) in [
+        ("pub fn total() {}\n", "Add totals"),
+        ("pub fn total() {}\npub fn refund() {}\n", "Add refunds"),
+        (
+            "pub fn total() {}\npub fn refund() {}\npub fn export() {}\n",
+            "Add exports",
+        ),
+    ] {
+        fs::write(temp_dir.path().join("billing.rs"), content)?;
+        index.add_path(Path::new("billing.rs"))?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let parent_commits: Vec<git2::Commit> = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<Result<_, _>>()?;
+        let parent_refs: Vec<&git2::Commit> = parent_commits.iter().collect();
+        let oid = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parent_refs,
+        )?;
+        parents = vec![oid];
+    }
+    let start = repo.revparse_single("HEAD~2")?.id().to_string();
+    let range = format!("{}..HEAD", start);
+
+    let config_path = config_dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            config_dir.path().join("ktme.db")
+        ),
+    )?;
+    let ktme = || {
+        #[allow(deprecated)]
+        let mut cmd = Command::cargo_bin("ktme").unwrap();
+        cmd.env("KTME_CONFIG", &config_path)
+            .current_dir(temp_dir.path());
+        cmd
+    };
+
+    let history = temp_dir.path().join("history");
+    ktme()
+        .args(&[
+            "backfill",
+            "--service",
+            "billing",
+            "--range",
+            &range,
+            "--output",
+            history.join("single.md").to_str().unwrap(),
+        ])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--output must contain {bucket} when the range has several commits",
+        ));
+
+    ktme()
+        .args(&[
+            "backfill",
+            "--service",
+            "billing",
+            "--range",
+            &range,
+            "--per",
+            "commit",
+            "--output",
+            history.join("{bucket}.md").to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "Backfilled 2 document(s) for billing",
+        ));
+
+    let mut documents: Vec<String> = fs::read_dir(&history)?
+        .map(|entry| entry.map(|e| e.file_name().to_string_lossy().to_string()))
+        .collect::<Result<_, _>>()?;
+    documents.sort();
+    let mut expected: Vec<String> = ["HEAD~1", "HEAD"]
+        .iter()
+        .map(|rev| {
+            let id = repo.revparse_single(rev).unwrap().id().to_string();
+            format!("{}.md", &id[..7])
+        })
+        .collect();
+    expected.sort();
+    assert_eq!(documents, expected);
+
+    // A backfill that cannot publish stops as a failed job, and resumes
+    // once the publish target exists
+    let releases = temp_dir.path().join("releases.md");
+    ktme()
+        .args(&[
+            "backfill",
+            "--service",
+            "billing",
+            "--range",
+            &range,
+            "--publish",
+            "releases",
+        ])
+        .assert()
+        .failure()
+        .stdout(predicates::str::contains(
+            "Backfill stopped; continue it with 'ktme jobs resume 2'",
+        ));
+    ktme()
+        .args(&["jobs", "show", "2"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Status: failed"))
+        .stdout(predicates::str::contains("Steps (0/2 done):"));
+
+    fs::write(&releases, "# Releases\n")?;
+    ktme()
+        .args(&[
+            "mapping",
+            "add",
+            "billing",
+            "--file",
+            releases.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    ktme()
+        .args(&["jobs", "resume", "2"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Resuming job 2 at step 1 of 2"))
+        .stdout(predicates::str::contains(
+            "Backfilled 2 document(s) for billing",
+        ));
+    ktme()
+        .args(&["jobs", "list", "--status", "completed"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("backfill"))
+        .stdout(predicates::str::contains("2/2"));
+    ktme()
+        .args(&["jobs", "resume", "2"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("Job 2 is already completed"));
+
+    Ok(())
+}
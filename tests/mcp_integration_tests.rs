@@ -133,6 +133,385 @@ fn test_mcp_stdio_tools_list() {
     child.kill().expect("Failed to kill child process");
 }
 
+/// Test MCP server in STDIO mode refusing requests over max_payload_bytes
+#[test]
+fn test_mcp_stdio_refuses_large_requests() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n\n[mcp]\nmax_payload_bytes = 1000\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )
+    .unwrap();
+    let mut child = Command::new("cargo")
+        .args(&[
+            "run",
+            "--release",
+            "--bin",
+            "ktme",
+            "--",
+            "mcp",
+            "start",
+            "--stdio",
+        ])
+        .env("KTME_CONFIG", &config_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start MCP server");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+    let mut reader = BufReader::new(stdout);
+    let mut call = |request: Value| {
+        writeln!(stdin, "{}", request).expect("Failed to write to stdin");
+        stdin.flush().expect("Failed to flush stdin");
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .expect("Failed to read response");
+        serde_json::from_str::<Value>(&line).expect("Invalid JSON response")
+    };
+
+    let response = call(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {
+            "name": "generate_documentation",
+            "arguments": {"service": "api", "changes": "x".repeat(2000)}
+        }
+    }));
+    // Refused before parsing, so without the id of the request
+    assert!(response["id"].is_null());
+    assert_eq!(response["error"]["code"], -32600);
+    assert_eq!(response["error"]["message"], "Request too large");
+
+    // Smaller requests are still served
+    let response = call(json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "initialize",
+        "params": {"protocolVersion": "2024-11-05", "capabilities": {"sampling": {}}}
+    }));
+    assert_eq!(response["id"], 2);
+    assert!(response["result"]["capabilities"]["resources"].is_object());
+
+    // An oversized answer to a sampling request fails that request rather
+    // than leaving the call waiting; the tool falls back to basic docs
+    let changes = json!({
+        "source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+        "author": "test@example.com", "message": "Add refunds", "files": [],
+        "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}
+    });
+    let request = call(json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "generate_documentation",
+            "arguments": {"service": "payments", "changes": changes.to_string()}
+        }
+    }));
+    assert_eq!(request["method"], "sampling/createMessage");
+    let response = call(json!({
+        "jsonrpc": "2.0",
+        "result": {
+            "role": "assistant",
+            "content": {"type": "text", "text": "x".repeat(2000)},
+            "model": "client-model"
+        },
+        "id": request["id"]
+    }));
+    assert_eq!(response["id"], 3);
+    let text = response["result"]["content"][0]["text"].as_str().unwrap();
+    assert!(text.contains("basic documentation"));
+    assert!(!text.contains("xxxx"));
+
+    child.kill().expect("Failed to kill child process");
+}
+
+/// Test that the stdio server lists and runs the tools of the other transports
+#[test]
+fn test_mcp_stdio_serves_every_tool() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[ai]\nprovider = \"mock\"\n\n[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )
+    .unwrap();
+    let mut child = Command::new("cargo")
+        .args(&[
+            "run",
+            "--release",
+            "--bin",
+            "ktme",
+            "--",
+            "mcp",
+            "start",
+            "--stdio",
+        ])
+        .env("KTME_CONFIG", &config_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start MCP server");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+    let mut reader = BufReader::new(stdout);
+    let mut call = |request: Value| {
+        writeln!(stdin, "{}", request).expect("Failed to write to stdin");
+        stdin.flush().expect("Failed to flush stdin");
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .expect("Failed to read response");
+        serde_json::from_str::<Value>(&line).expect("Invalid JSON response")
+    };
+
+    let list = call(json!({"jsonrpc": "2.0", "id": 1, "method": "tools/list"}));
+    let listed: Vec<&str> = list["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|tool| tool["name"].as_str())
+        .collect();
+
+    let calls = [
+        ("search_documentation", json!({"query": "payments"})),
+        ("stale_docs_report", json!({})),
+        (
+            "summarize_commit_history",
+            json!({"range": "HEAD~1..HEAD", "max_commits": 1}),
+        ),
+        ("read_documentation", json!({"service": "payments"})),
+        ("search_services", json!({"query": "payments", "limit": 5})),
+        (
+            "search_by_keyword",
+            json!({"keyword": "payments", "limit": 5}),
+        ),
+    ];
+    for (id, (tool, arguments)) in calls.into_iter().enumerate() {
+        assert!(listed.contains(&tool), "{} is not listed", tool);
+        let response = call(json!({
+            "jsonrpc": "2.0",
+            "id": id + 2,
+            "method": "tools/call",
+            "params": {"name": tool, "arguments": arguments}
+        }));
+        assert_eq!(response["id"], id + 2);
+        assert!(
+            !response.to_string().contains("Unknown tool"),
+            "{} is not dispatched: {}",
+            tool,
+            response
+        );
+        // Only the unmapped service has nothing to read
+        if tool != "read_documentation" {
+            assert!(
+                response["result"]["content"][0]["text"].is_string(),
+                "{} failed: {}",
+                tool,
+                response
+            );
+        }
+    }
+
+    child.kill().expect("Failed to kill child process");
+}
+
+/// Test that --stdio writes documentation with the client's model when it
+/// offers sampling
+#[test]
+fn test_mcp_stdio_sampling() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )
+    .unwrap();
+    let mut child = Command::new("cargo")
+        .args(&[
+            "run",
+            "--release",
+            "--bin",
+            "ktme",
+            "--",
+            "mcp",
+            "start",
+            "--stdio",
+        ])
+        .env("KTME_CONFIG", &config_path)
+        .env_remove("OPENAI_API_KEY")
+        .env_remove("ANTHROPIC_API_KEY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start MCP server");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+    let mut reader = BufReader::new(stdout);
+    let mut send = |message: Value| {
+        writeln!(stdin, "{}", message).expect("Failed to write to stdin");
+        stdin.flush().expect("Failed to flush stdin");
+    };
+    let mut receive = || {
+        let mut line = String::new();
+        reader
+            .read_line(&mut line)
+            .expect("Failed to read response");
+        serde_json::from_str::<Value>(&line).expect("Invalid JSON response")
+    };
+
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {"protocolVersion": "2024-11-05", "capabilities": {"sampling": {}}}
+    }));
+    assert_eq!(receive()["id"], 1);
+
+    let changes = json!({
+        "source": "test", "identifier": "abc123", "timestamp": "2025-12-06T00:00:00Z",
+        "author": "test@example.com", "message": "Add refunds", "files": [],
+        "summary": {"total_files": 0, "total_additions": 0, "total_deletions": 0}
+    });
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "generate_documentation",
+            "arguments": {"service": "payments", "changes": changes.to_string()}
+        }
+    }));
+
+    // The server asks the client's model while the call waits
+    let request = receive();
+    assert_eq!(request["method"], "sampling/createMessage");
+    send(json!({
+        "jsonrpc": "2.0",
+        "id": request["id"],
+        "result": {
+            "role": "assistant",
+            "content": {"type": "text", "text": "Refunds are documented by the client model"},
+            "model": "client-model"
+        }
+    }));
+
+    let response = receive();
+    assert_eq!(response["id"], 2);
+    assert!(response["result"]["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("Refunds are documented by the client model"));
+
+    child.kill().expect("Failed to kill child process");
+}
+
+/// Test capturing the stdio traffic with --trace and printing it
+#[test]
+fn test_mcp_stdio_trace() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[storage]\ndatabase_file = {:?}\n",
+            temp_dir.path().join("ktme.db")
+        ),
+    )
+    .unwrap();
+    let trace_path = temp_dir.path().join("trace.jsonl");
+    let mut child = Command::new("cargo")
+        .args(&[
+            "run",
+            "--release",
+            "--bin",
+            "ktme",
+            "--",
+            "mcp",
+            "start",
+            "--stdio",
+            "--trace",
+        ])
+        .arg(&trace_path)
+        .env("KTME_CONFIG", &config_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to start MCP server");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+    let mut reader = BufReader::new(stdout);
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {
+            "name": "generate_documentation",
+            "arguments": {
+                "service": "api",
+                "changes": "diff --git a/src/lib.rs b/src/lib.rs\n+SECRET_LINE",
+                "api_token": "hunter2"
+            }
+        }
+    });
+    writeln!(stdin, "{}", request).expect("Failed to write to stdin");
+    stdin.flush().expect("Failed to flush stdin");
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .expect("Failed to read response");
+    let response: Value = serde_json::from_str(&line).expect("Invalid JSON response");
+    assert_eq!(response["id"], 1);
+    child.kill().expect("Failed to kill child process");
+
+    // Only JSON-RPC went to stdout; the trace holds both messages, redacted
+    let trace = std::fs::read_to_string(&trace_path).unwrap();
+    assert_eq!(trace.lines().count(), 2);
+    assert!(!trace.contains("hunter2"));
+    assert!(!trace.contains("SECRET_LINE"));
+
+    let output = Command::new("cargo")
+        .args(&[
+            "run",
+            "--release",
+            "--bin",
+            "ktme",
+            "--",
+            "mcp",
+            "trace",
+            "--summary",
+        ])
+        .arg(&trace_path)
+        .env("KTME_CONFIG", &config_path)
+        .output()
+        .expect("Failed to run ktme mcp trace");
+    assert!(output.status.success());
+    let printed = String::from_utf8_lossy(&output.stdout);
+    assert!(printed.contains("→ tools/call generate_documentation #1"));
+    assert!(printed.contains("← response #1 ("));
+}
+
 /// Test MCP server HTTP mode with status endpoint
 #[test]
 #[ignore] // Ignored by default to avoid port conflicts in CI